@@ -0,0 +1,227 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::HashMap;
+
+use log::info;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{entity::Entity, quickner::Quickner};
+
+/// Longest short form, in characters
+const MAX_SHORT_FORM: usize = 10;
+
+/// An abbreviation defined in a text, e.g. "World Health Organization (WHO)"
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Abbreviation {
+    /// The abbreviation, e.g. "WHO"
+    pub short: String,
+    /// Its long form, e.g. "World Health Organization"
+    pub long: String,
+}
+
+/// Find the abbreviations defined in a text with the algorithm of Schwartz and Hearst
+/// (2003), as "long form (SF)" or "SF (long form)". The short form is a single word of
+/// 2 to 10 characters with two uppercase letters (or one and a digit), and its letters
+/// must be found in order in the long form, the first one at the start of a word.
+/// # Example
+/// ```
+/// use quickner::find_abbreviations;
+/// let abbreviations = find_abbreviations("The World Health Organization (WHO) said");
+/// assert_eq!(abbreviations[0].short, "WHO");
+/// assert_eq!(abbreviations[0].long, "World Health Organization");
+/// ```
+pub fn find_abbreviations(text: &str) -> Vec<Abbreviation> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut abbreviations = Vec::new();
+    let mut open = 0;
+    while let Some(offset) = chars[open..].iter().position(|c| *c == '(') {
+        open += offset;
+        let close = match chars[open + 1..]
+            .iter()
+            .position(|c| *c == ')' || *c == '(')
+        {
+            Some(offset) if chars[open + 1 + offset] == ')' => open + 1 + offset,
+            _ => {
+                open += 1;
+                continue;
+            }
+        };
+        let inside: String = chars[open + 1..close].iter().collect();
+        // "(WHO; see below)" defines WHO
+        let inside = inside.split([';', ',']).next().unwrap_or_default().trim();
+        let before = sentence_before(&chars, open);
+        let abbreviation = if is_short_form(inside) {
+            let length = inside.chars().count();
+            let max_words = (length + 5).min(length * 2);
+            let start = last_words_start(&before, max_words);
+            best_long_form(inside, &before[start..], max_words).map(|long| (inside, long))
+        } else {
+            // "WHO (World Health Organization)"
+            let short = before
+                .split_whitespace()
+                .last()
+                .unwrap_or_default()
+                .trim_matches(|c: char| !c.is_alphanumeric());
+            let length = short.chars().count();
+            let max_words = (length + 5).min(length * 2);
+            match is_short_form(short) {
+                true => best_long_form(short, inside, max_words).map(|long| (short, long)),
+                false => None,
+            }
+        };
+        if let Some((short, long)) = abbreviation {
+            abbreviations.push(Abbreviation {
+                short: short.to_string(),
+                long: long.to_string(),
+            });
+        }
+        open = close + 1;
+    }
+    abbreviations
+}
+
+/// Text of the sentence before the character at `end`
+fn sentence_before(chars: &[char], end: usize) -> String {
+    let start = (0..end)
+        .rev()
+        .find(|&index| match chars[index] {
+            '!' | '?' | ';' | '\n' | '(' | ')' => true,
+            '.' => chars.get(index + 1).is_some_and(|c| c.is_whitespace()),
+            _ => false,
+        })
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    chars[start..end].iter().collect()
+}
+
+/// Byte offset of the last `count` words of a text
+fn last_words_start(text: &str, count: usize) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for (index, c) in text.char_indices().rev() {
+        match (c.is_whitespace(), in_word) {
+            (false, _) => in_word = true,
+            (true, true) => {
+                words += 1;
+                in_word = false;
+                if words == count {
+                    return index + c.len_utf8();
+                }
+            }
+            (true, false) => {}
+        }
+    }
+    0
+}
+
+/// A single word of 2 to 10 characters with two uppercase letters, or an uppercase
+/// letter and a digit, so capitalized words like "(Figure)" are not short forms
+fn is_short_form(text: &str) -> bool {
+    let length = text.chars().count();
+    let uppercase = text.chars().filter(|c| c.is_uppercase()).count();
+    (2..=MAX_SHORT_FORM).contains(&length)
+        && !text.contains(char::is_whitespace)
+        && text.starts_with(char::is_alphanumeric)
+        && (uppercase >= 2 || (uppercase == 1 && text.contains(|c: char| c.is_ascii_digit())))
+}
+
+/// Shortest end of `long` holding the characters of `short` in order, the first
+/// one at the start of a word, `None` if there is none or it has too many words
+fn best_long_form<'a>(short: &str, long: &'a str, max_words: usize) -> Option<&'a str> {
+    let short: Vec<char> = short.chars().flat_map(char::to_lowercase).collect();
+    let long_chars: Vec<(usize, char)> = long.char_indices().collect();
+    let lower = |index: usize| long_chars[index].1.to_lowercase().next().unwrap_or(' ');
+    let mut l = long_chars.len() as isize - 1;
+    for (s, c) in short.iter().enumerate().rev() {
+        if !c.is_alphanumeric() {
+            continue;
+        }
+        while l >= 0
+            && (lower(l as usize) != *c
+                || (s == 0 && l > 0 && long_chars[l as usize - 1].1.is_alphanumeric()))
+        {
+            l -= 1;
+        }
+        if l < 0 {
+            return None;
+        }
+        l -= 1;
+    }
+    // The long form starts at the word of the first character
+    let first = long_chars[(l + 1) as usize].0;
+    let start = long[..first]
+        .rfind(char::is_whitespace)
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let form = long[start..].trim();
+    let words = form.split_whitespace().count();
+    (words <= max_words && form.chars().count() > short.len() && !form.contains('('))
+        .then_some(form)
+}
+
+impl Quickner {
+    /// Find the abbreviations defined in the documents, see [`find_abbreviations`],
+    /// and add the ones whose long form or short form is an entity to the entities,
+    /// with the same label and metadata. The canonical form of a new entity is the
+    /// one of the entity it comes from, or the long form, so the spans of the
+    /// abbreviation and of its long form link to the same entity.
+    /// The first definition of an abbreviation in the corpus is kept.
+    /// Returns the entities added.
+    pub fn learn_abbreviations(&mut self) -> Vec<Entity> {
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        let normalize = |name: &str| match case_sensitive {
+            true => name.to_string(),
+            false => name.to_lowercase(),
+        };
+        let found: Vec<Vec<Abbreviation>> = self.install(|| {
+            self.documents
+                .par_iter()
+                .map(|document| find_abbreviations(&document.text))
+                .collect()
+        });
+        let mut known: HashMap<String, Vec<Entity>> = HashMap::new();
+        for entity in &self.entities {
+            known
+                .entry(normalize(&entity.name))
+                .or_default()
+                .push(entity.clone());
+        }
+        let mut learned = Vec::new();
+        for abbreviation in found.into_iter().flatten() {
+            let short = normalize(&abbreviation.short);
+            let long = normalize(&abbreviation.long);
+            let (name, sources) = match (known.get(&short), known.get(&long)) {
+                (None, Some(entities)) => (short, entities),
+                (Some(entities), None) => (long, entities),
+                _ => continue,
+            };
+            let entities: Vec<Entity> = sources
+                .iter()
+                .map(|entity| Entity {
+                    name: name.clone(),
+                    canonical: Some(
+                        entity
+                            .canonical
+                            .clone()
+                            .unwrap_or_else(|| abbreviation.long.clone()),
+                    ),
+                    ..entity.clone()
+                })
+                .collect();
+            learned.extend(entities.iter().cloned());
+            known.insert(name, entities);
+        }
+        if !learned.is_empty() {
+            info!("{} entities added from the abbreviations", learned.len());
+        }
+        self.entities.extend(learned.iter().cloned());
+        learned
+    }
+}
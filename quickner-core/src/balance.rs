@@ -0,0 +1,94 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::{HashMap, HashSet};
+
+use log::info;
+
+use crate::{quickner::Quickner, utils::sample_score};
+
+impl Quickner {
+    /// Downsample the documents of the over-represented labels, so that no label
+    /// is annotated in more than `max_per_label` documents unless those documents
+    /// also hold a label below the limit.
+    ///
+    /// The documents are visited from the ones whose rarest label is the least
+    /// frequent, then in a random order given by the seed: a document is kept if one
+    /// of its labels is still in fewer than `max_per_label` kept documents, so the
+    /// documents of rare labels are all kept. The documents without spans are kept.
+    /// The order of the documents is kept and the indexes are rebuilt.
+    /// Returns the number of documents removed.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let removed = quickner.balance(1000, 42);
+    /// println!("{} documents removed", removed);
+    /// ```
+    pub fn balance(&mut self, max_per_label: usize, seed: u64) -> usize {
+        let snapshot = self.history_snapshot();
+        let labels: Vec<HashSet<&str>> = self
+            .documents
+            .iter()
+            .map(|document| {
+                document
+                    .label
+                    .iter()
+                    .map(|(_, _, label)| label.as_str())
+                    .collect()
+            })
+            .collect();
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for label in labels.iter().flatten() {
+            *totals.entry(label).or_default() += 1;
+        }
+        let mut order: Vec<(usize, f64, usize)> = labels
+            .iter()
+            .enumerate()
+            .map(|(index, labels)| {
+                let rarest = labels.iter().map(|label| totals[label]).min();
+                (
+                    rarest.unwrap_or(0),
+                    sample_score(seed, &self.documents[index].id),
+                    index,
+                )
+            })
+            .collect();
+        order.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        let mut kept_per_label: HashMap<&str, usize> = HashMap::new();
+        let mut keep = vec![false; labels.len()];
+        for (_, _, index) in order {
+            let document_labels = &labels[index];
+            let under_limit = document_labels.is_empty()
+                || document_labels
+                    .iter()
+                    .any(|label| kept_per_label.get(label).copied().unwrap_or(0) < max_per_label);
+            if !under_limit {
+                continue;
+            }
+            keep[index] = true;
+            for label in document_labels {
+                *kept_per_label.entry(label).or_default() += 1;
+            }
+        }
+        let total = self.documents.len();
+        let mut keep = keep.into_iter();
+        self.documents.retain(|_| keep.next().unwrap_or(true));
+        let removed = total - self.documents.len();
+        info!(
+            "{} of {} documents removed to keep at most {} documents per label",
+            removed, total, max_per_label
+        );
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("balance", snapshot);
+        removed
+    }
+}
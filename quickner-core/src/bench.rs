@@ -0,0 +1,237 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::time::Instant;
+
+use rayon::{ThreadPoolBuildError, ThreadPoolBuilder};
+use serde::Serialize;
+
+use crate::{
+    config::Matching, document::Document, entity::Entity, matcher::Matcher, performance::install,
+    utils::splitmix64,
+};
+
+/// Syllables of the words of the synthetic corpus
+const SYLLABLES: [&str; 16] = [
+    "ka", "lo", "mi", "ra", "te", "su", "no", "vi", "de", "po", "ga", "ri", "zu", "be", "fa", "hu",
+];
+
+/// Number of different words around the entities
+const VOCABULARY: usize = 2000;
+
+/// Labels of the synthetic entities
+const LABELS: [&str; 4] = ["PERSON", "ORG", "LOC", "PRODUCT"];
+
+/// Options of the benchmark: size of the synthetic corpus, share of the
+/// words mentioning an entity, matching options and thread counts compared
+#[derive(Clone, Debug)]
+pub struct BenchOptions {
+    /// Number of documents of the corpus
+    pub documents: usize,
+    /// Number of words of each document
+    pub words: usize,
+    /// Number of entities of the gazetteer
+    pub entities: usize,
+    /// Share of the words starting an entity mention, between 0 and 1
+    pub density: f64,
+    /// Share of the mentions with a typo, to exercise the fuzzy matching
+    pub typos: f64,
+    /// Matching options of the matcher
+    pub matching: Matching,
+    /// Thread counts to compare, the global pool of rayon if empty
+    pub threads: Vec<usize>,
+    /// Number of runs of each thread count, the fastest one is kept
+    pub iterations: usize,
+    /// Seed of the synthetic corpus, the same seed gives the same corpus
+    pub seed: u64,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        BenchOptions {
+            documents: 10000,
+            words: 200,
+            entities: 1000,
+            density: 0.05,
+            typos: 0.0,
+            matching: Matching::default(),
+            threads: Vec::new(),
+            iterations: 3,
+            seed: 0,
+        }
+    }
+}
+
+/// Throughput of the annotation of the corpus with a number of threads
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BenchRun {
+    pub threads: usize,
+    /// Wall time of the fastest run
+    pub seconds: f64,
+    /// Number of spans found
+    pub spans: usize,
+    pub documents_per_second: f64,
+    pub megabytes_per_second: f64,
+}
+
+/// Result of a benchmark, the synthetic corpus and a run per thread count
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    pub documents: usize,
+    /// Size of the texts in bytes
+    pub bytes: usize,
+    pub entities: usize,
+    /// Number of entity mentions written in the texts
+    pub mentions: usize,
+    /// Wall time of the compilation of the matcher
+    pub compile_seconds: f64,
+    pub runs: Vec<BenchRun>,
+}
+
+/// Pseudo-random numbers of the corpus, a splitmix64 sequence
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        splitmix64(self.0)
+    }
+
+    /// Number between 0 and `n`, excluded
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    /// Number between 0 and 1
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Word of 2 to 4 syllables
+    fn word(&mut self) -> String {
+        (0..2 + self.below(3))
+            .map(|_| SYLLABLES[self.below(SYLLABLES.len())])
+            .collect()
+    }
+}
+
+impl BenchOptions {
+    /// Synthetic gazetteer and documents of the options. The entities have
+    /// 1 to 3 words ending with an "x", which the other words never do, so
+    /// the mentions are only found where they were written. Returns the entities,
+    /// the documents and the number of mentions written in the documents.
+    pub fn corpus(&self) -> (Vec<Entity>, Vec<Document>, usize) {
+        let mut random = Random(self.seed);
+        let vocabulary: Vec<String> = (0..VOCABULARY).map(|_| random.word()).collect();
+        let entities: Vec<Entity> = (0..self.entities)
+            .map(|index| Entity {
+                name: (0..1 + random.below(3))
+                    .map(|_| random.word() + "x")
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                label: LABELS[index % LABELS.len()].to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let mut mentions = 0;
+        let documents = (0..self.documents)
+            .map(|_| {
+                let mut words: Vec<String> = Vec::with_capacity(self.words);
+                while words.len() < self.words {
+                    if !entities.is_empty() && random.unit() < self.density {
+                        let mut name = entities[random.below(entities.len())].name.clone();
+                        if random.unit() < self.typos {
+                            // A letter missing from the syllables replaces a letter
+                            let index = random.below(name.len());
+                            if name.as_bytes()[index] != b' ' {
+                                name.replace_range(index..index + 1, "q");
+                            }
+                        }
+                        words.push(name);
+                        mentions += 1;
+                    } else {
+                        words.push(vocabulary[random.below(VOCABULARY)].clone());
+                    }
+                    if random.below(15) == 0 {
+                        words.push(".".to_string());
+                    }
+                }
+                Document::from_string(words.join(" "))
+            })
+            .collect();
+        (entities, documents, mentions)
+    }
+
+    /// Annotate the synthetic corpus with each thread count, `iterations` times,
+    /// and report the fastest run of each, e.g. to compare the matching options
+    /// and the thread counts on a machine.
+    /// # Example
+    /// ```
+    /// use quickner::BenchOptions;
+    /// let options = BenchOptions { documents: 100, entities: 50, threads: vec![1, 2], ..Default::default() };
+    /// let report = options.run().unwrap();
+    /// assert_eq!(report.runs.len(), 2);
+    /// assert_eq!(report.runs[0].spans, report.runs[1].spans);
+    /// ```
+    /// # Errors
+    /// Returns an error if a thread pool cannot be built
+    pub fn run(&self) -> Result<BenchReport, ThreadPoolBuildError> {
+        let (entities, documents, mentions) = self.corpus();
+        let started = Instant::now();
+        let matcher = Matcher::with_matching(&entities, false, self.matching.clone());
+        let compile_seconds = started.elapsed().as_secs_f64();
+        let bytes: usize = documents.iter().map(|document| document.text.len()).sum();
+        let pools = match self.threads.is_empty() {
+            true => vec![None],
+            false => self
+                .threads
+                .iter()
+                .map(|threads| {
+                    ThreadPoolBuilder::new()
+                        .num_threads(*threads)
+                        .build()
+                        .map(Some)
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        let runs = pools
+            .iter()
+            .map(|pool| {
+                let mut fastest = f64::MAX;
+                let mut spans = 0;
+                for _ in 0..self.iterations.max(1) {
+                    let mut annotated = documents.clone();
+                    let started = Instant::now();
+                    install(pool.as_ref(), || matcher.annotate_many(&mut annotated));
+                    fastest = fastest.min(started.elapsed().as_secs_f64());
+                    spans = annotated.iter().map(|document| document.label.len()).sum();
+                }
+                let seconds = fastest.max(f64::EPSILON);
+                BenchRun {
+                    threads: pool
+                        .as_ref()
+                        .map_or_else(rayon::current_num_threads, |pool| {
+                            pool.current_num_threads()
+                        }),
+                    seconds,
+                    spans,
+                    documents_per_second: documents.len() as f64 / seconds,
+                    megabytes_per_second: bytes as f64 / 1_000_000.0 / seconds,
+                }
+            })
+            .collect();
+        Ok(BenchReport {
+            documents: documents.len(),
+            bytes,
+            entities: entities.len(),
+            mentions,
+            compile_seconds,
+            runs,
+        })
+    }
+}
@@ -0,0 +1,53 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use log::warn;
+
+/// Set when the cancellation of the run is requested
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+/// Number of Ctrl-C received by the handler
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Install a Ctrl-C handler requesting the cancellation of the run.
+/// The current batch of documents is finished, the documents annotated so far
+/// are saved to a partial file (e.g. annotations_partial.jsonl) and `process`
+/// returns an error of kind `Interrupted`.
+/// A second Ctrl-C exits immediately.
+/// # Errors
+/// Returns an error if a handler is already installed
+pub fn install_cancel_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTS.fetch_add(1, Ordering::SeqCst) > 0 {
+            std::process::exit(130);
+        }
+        warn!("Cancelling, finishing the current batch (press Ctrl-C again to exit now)");
+        cancel();
+    })
+}
+
+/// Request the cancellation of the run, as Ctrl-C does
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// Whether the cancellation of the run was requested
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Clear a cancellation request, e.g. before starting a new run
+pub fn reset_cancel() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+/// Error returned by a cancelled run
+pub(crate) fn interrupted(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Interrupted, message)
+}
@@ -0,0 +1,208 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Checkpoint,
+    document::Document,
+    models::Stamp,
+    utils::{format_rfc3339, long_path},
+};
+
+/// Manifest of a checkpoint directory, listing the files of annotated
+/// documents written so far. A checkpoint is only resumed with the
+/// gazetteer it was written with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CheckpointManifest {
+    /// Version of quickner
+    pub version: String,
+    /// Hash of the gazetteer of the run
+    pub gazetteer_hash: Option<String>,
+    /// Hash of the configuration file of the run
+    pub config_hash: Option<String>,
+    /// Number of documents of the run
+    pub documents: usize,
+    /// Number of annotated documents in the checkpoint
+    pub annotated: usize,
+    /// Files of annotated documents, in the order they were written
+    pub chunks: Vec<String>,
+    /// Time of the last checkpoint, RFC 3339 in UTC
+    pub updated: String,
+}
+
+impl CheckpointManifest {
+    const FILE_NAME: &'static str = "manifest.json";
+
+    /// Read the manifest of a checkpoint directory, `None` if there is no checkpoint
+    pub fn read<P: AsRef<Path>>(directory: P) -> Result<Option<Self>, Error> {
+        let path = directory.as_ref().join(CheckpointManifest::FILE_NAME);
+        if !long_path(&path).exists() {
+            return Ok(None);
+        }
+        let manifest = fs::read_to_string(long_path(&path))?;
+        serde_json::from_str(&manifest)
+            .map(Some)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// Writes the annotated documents of a run to a checkpoint directory,
+/// one JSONL file per checkpoint, and the manifest listing them
+pub(crate) struct CheckpointWriter {
+    directory: PathBuf,
+    every: usize,
+    manifest: CheckpointManifest,
+    /// Documents of the checkpoint resumed, by id
+    restored: HashMap<String, Document>,
+}
+
+impl CheckpointWriter {
+    /// Open the checkpoint of the run saved to `output`. With `resume`, the documents
+    /// of the last checkpoint are restored, otherwise a previous checkpoint is removed.
+    /// Fails if the checkpoint resumed was written with another gazetteer.
+    pub(crate) fn open(
+        options: &Checkpoint,
+        output: &str,
+        stamp: &Stamp,
+        documents: usize,
+    ) -> Result<Self, Error> {
+        let directory = PathBuf::from(options.directory(output));
+        let previous = CheckpointManifest::read(&directory)?;
+        let mut restored = HashMap::new();
+        let manifest = match previous {
+            Some(manifest) if options.resume => {
+                if manifest.gazetteer_hash != stamp.gazetteer_hash {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "The checkpoint {} was written with another gazetteer, remove it or run without resume",
+                            directory.display()
+                        ),
+                    ));
+                }
+                // e.g. resume set in the configuration file
+                if manifest.config_hash != stamp.config_hash {
+                    warn!(
+                        "The configuration file changed since the checkpoint {} was written",
+                        directory.display()
+                    );
+                }
+                for chunk in &manifest.chunks {
+                    let file = File::open(long_path(directory.join(chunk)))?;
+                    for line in BufReader::new(file).lines() {
+                        let document: Document = serde_json::from_str(&line?)
+                            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                        restored.insert(document.id.clone(), document);
+                    }
+                }
+                info!(
+                    "Resuming from the checkpoint {}, {} documents already annotated",
+                    directory.display(),
+                    restored.len()
+                );
+                CheckpointManifest {
+                    documents,
+                    ..manifest
+                }
+            }
+            previous => {
+                if options.resume {
+                    info!(
+                        "No checkpoint found in {}, starting from the beginning",
+                        directory.display()
+                    );
+                } else if previous.is_some() {
+                    warn!(
+                        "Removing the previous checkpoint {}, use resume to continue from it",
+                        directory.display()
+                    );
+                    fs::remove_dir_all(long_path(&directory))?;
+                }
+                CheckpointManifest {
+                    version: stamp.version.clone(),
+                    gazetteer_hash: stamp.gazetteer_hash.clone(),
+                    config_hash: stamp.config_hash.clone(),
+                    documents,
+                    annotated: 0,
+                    chunks: Vec::new(),
+                    updated: format_rfc3339(SystemTime::now()),
+                }
+            }
+        };
+        fs::create_dir_all(long_path(&directory))?;
+        let writer = CheckpointWriter {
+            directory,
+            every: options.every.max(1),
+            manifest,
+            restored,
+        };
+        writer.write_manifest()?;
+        Ok(writer)
+    }
+
+    /// Number of documents annotated between two checkpoints
+    pub(crate) fn every(&self) -> usize {
+        self.every
+    }
+
+    /// Take the annotated version of a document from the checkpoint resumed
+    pub(crate) fn restore(&mut self, id: &str) -> Option<Document> {
+        self.restored.remove(id)
+    }
+
+    /// Write the annotated documents to a new file of the checkpoint,
+    /// then add it to the manifest
+    pub(crate) fn write(&mut self, documents: &[Document]) -> Result<(), Error> {
+        if documents.is_empty() {
+            return Ok(());
+        }
+        let chunk = format!("chunk_{:05}.jsonl", self.manifest.chunks.len() + 1);
+        let mut file = BufWriter::new(File::create(long_path(self.directory.join(&chunk)))?);
+        for document in documents {
+            serde_json::to_writer(&mut file, document)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        self.manifest.chunks.push(chunk);
+        self.manifest.annotated += documents.len();
+        self.manifest.updated = format_rfc3339(SystemTime::now());
+        self.write_manifest()?;
+        info!(
+            "Checkpoint: {} of {} documents annotated",
+            self.manifest.annotated, self.manifest.documents
+        );
+        Ok(())
+    }
+
+    /// Remove the checkpoint, once the annotations are saved
+    pub(crate) fn finish(self) -> Result<(), Error> {
+        fs::remove_dir_all(long_path(&self.directory))
+    }
+
+    /// Write the manifest to a temporary file renamed over the previous one,
+    /// so a crash never leaves a truncated manifest
+    fn write_manifest(&self) -> Result<(), Error> {
+        let path = self.directory.join(CheckpointManifest::FILE_NAME);
+        let temporary = self.directory.join("manifest.json.tmp");
+        fs::write(
+            long_path(&temporary),
+            serde_json::to_string_pretty(&self.manifest)?,
+        )?;
+        fs::rename(long_path(&temporary), long_path(&path))
+    }
+}
@@ -6,13 +6,22 @@
 // Licensed under Mozilla Public License 2.0
 //
 
-use log::{debug, error};
-use serde::Deserialize;
-use std::{collections::HashSet, fs};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 use std::{fmt::Display, fmt::Formatter, iter::FromIterator};
+use toml::Value;
 
+use crate::span::SpanSource;
 use crate::utils::{
-    contains_numbers, contains_punctuation, contains_special_characters, is_alphanumeric,
+    contains_numbers, contains_punctuation, contains_special_characters, hash_string,
+    is_alphanumeric, long_path, preview, tokenize, TermColor,
 };
 /// A struct representing the configuration file.
 /// # Examples
@@ -24,12 +33,27 @@ use crate::utils::{
 /// Panics if the configuration file cannot be read or parsed.
 /// # Errors
 /// Returns an error if the configuration file cannot be read or parsed.
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     pub texts: Texts,
     pub annotations: Annotations,
     pub entities: Entities,
     pub logging: Option<Logging>,
+    #[serde(default)]
+    pub labels: Labels,
+    #[serde(default)]
+    pub matching: Matching,
+    pub checkpoint: Option<Checkpoint>,
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub relations: Vec<RelationPattern>,
+    #[serde(default)]
+    pub performance: Performance,
+    /// Tags of the documents, from a second dictionary, none if not set
+    pub tags: Option<Tags>,
 }
 
 impl Default for Config {
@@ -39,38 +63,99 @@ impl Default for Config {
             annotations: Annotations::default(),
             entities: Entities::default(),
             logging: Some(Logging::default()),
+            labels: Labels::default(),
+            matching: Matching::default(),
+            checkpoint: None,
+            routes: Vec::new(),
+            rules: Vec::new(),
+            relations: Vec::new(),
+            performance: Performance::default(),
+            tags: None,
         }
     }
 }
 
 /// A struct used to deserialize logging from the configuration file.
-#[derive(Deserialize, Clone)]
+/// The library installs no logger, see [`init_logging`](crate::init_logging):
+/// these settings are the ones of the CLI.
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Logging {
     pub level: String,
+    pub format: LogFormat,
 }
 
 impl Default for Logging {
     fn default() -> Self {
         Logging {
             level: "info".to_string(),
+            format: LogFormat::default(),
+        }
+    }
+}
+
+/// Format of the log lines
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the stages of a run
+    Json,
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format: {format}")),
         }
     }
 }
 
 /// A struct used to deserialize annotations from the configuration file.
 
-#[derive(Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Texts {
     pub input: Input,
     pub filters: Filters,
 }
 
 /// A struct used to deserialize input from the configuration file.
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Input {
     pub path: String,
     pub filter: Option<bool>,
+    /// Format of the entities file, detected from the extension if not set
+    pub format: Option<EntitiesFormat>,
+    /// How to split the texts into documents
+    pub split: Option<Split>,
+    /// Maximum number of documents annotated, the first ones (after sampling)
+    pub limit: Option<usize>,
+    /// Fraction of the documents annotated, between 0 and 1, drawn with `seed`
+    pub sample: Option<f64>,
+    /// Seed of the sample, the same seed gives the same documents
+    #[serde(default)]
+    pub seed: u64,
+    /// Similarity from which documents are near-duplicates, between 0 and 1,
+    /// only the first of the duplicates is annotated
+    pub dedupe: Option<f64>,
+    /// How the ids of the documents are made, the hash of the text for a texts
+    /// file and the file names for a directory if not set
+    pub id: Option<IdStrategy>,
 }
 
 impl Default for Input {
@@ -78,12 +163,211 @@ impl Default for Input {
         Input {
             path: "".to_string(),
             filter: Some(true),
+            format: None,
+            split: None,
+            limit: None,
+            sample: None,
+            seed: 0,
+            dedupe: None,
+            id: None,
+        }
+    }
+}
+
+/// A struct used to deserialize how texts are split into documents from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum Split {
+    /// One document per paragraph, paragraphs are separated by blank lines
+    #[serde(rename = "paragraph")]
+    Paragraph,
+    /// One document per sentence, with the id of the whole text in the
+    /// "parent" metadata and the position of the sentence in "sentence"
+    #[serde(rename = "sentence")]
+    Sentence,
+}
+
+impl Display for Split {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Split::Paragraph => write!(f, "paragraph"),
+            Split::Sentence => write!(f, "sentence"),
+        }
+    }
+}
+
+impl FromStr for Split {
+    type Err = String;
+
+    fn from_str(split: &str) -> Result<Self, Self::Err> {
+        match split.to_lowercase().as_str() {
+            "paragraph" => Ok(Split::Paragraph),
+            "sentence" => Ok(Split::Sentence),
+            _ => Err(format!("Unknown split: {split}")),
+        }
+    }
+}
+
+/// A struct used to deserialize how the ids of the documents are made from the configuration file.
+/// The ids only depend on the texts (or their position), so the same input gives the
+/// same ids from one run to the next, which checkpoints, samples and the watch mode rely on.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdStrategy {
+    /// FNV-1a hash of the text, 16 hexadecimal digits
+    #[serde(rename = "hash")]
+    #[default]
+    Hash,
+    /// Position of the document in the input, from 0, after the duplicated
+    /// texts are dropped and before the sample and limit
+    #[serde(rename = "sequential")]
+    Sequential,
+    /// UUID made of the SHA-256 of the text (version 8, RFC 9562)
+    #[serde(rename = "uuid")]
+    Uuid,
+    /// SHA-256 of the text, 64 hexadecimal digits
+    #[serde(rename = "sha256")]
+    Sha256,
+    /// The `id` column of the texts file, the rows with an id already read are duplicates
+    #[serde(rename = "column")]
+    Column,
+}
+
+impl IdStrategy {
+    /// Id of the document at `position` in the input with the text `text`,
+    /// None for the ids taken from the texts file
+    /// # Examples
+    /// ```
+    /// use quickner::IdStrategy;
+    /// assert_eq!(IdStrategy::Sequential.id(2, "Rust"), Some("2".to_string()));
+    /// assert_eq!(IdStrategy::Uuid.id(0, "Rust").unwrap().len(), 36);
+    /// assert_eq!(IdStrategy::Sha256.id(0, "Rust"), IdStrategy::Sha256.id(5, "Rust"));
+    /// assert_eq!(IdStrategy::Column.id(0, "Rust"), None);
+    /// ```
+    pub fn id(&self, position: usize, text: &str) -> Option<String> {
+        match self {
+            IdStrategy::Hash => Some(hash_string(text)),
+            IdStrategy::Sequential => Some(position.to_string()),
+            IdStrategy::Uuid => {
+                let mut bytes = [0; 16];
+                bytes.copy_from_slice(&Sha256::digest(text.as_bytes())[..16]);
+                bytes[6] = (bytes[6] & 0x0f) | 0x80;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+                Some(format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                ))
+            }
+            IdStrategy::Sha256 => Some(
+                Sha256::digest(text.as_bytes())
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect(),
+            ),
+            IdStrategy::Column => None,
+        }
+    }
+}
+
+impl Display for IdStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdStrategy::Hash => write!(f, "hash"),
+            IdStrategy::Sequential => write!(f, "sequential"),
+            IdStrategy::Uuid => write!(f, "uuid"),
+            IdStrategy::Sha256 => write!(f, "sha256"),
+            IdStrategy::Column => write!(f, "column"),
+        }
+    }
+}
+
+impl FromStr for IdStrategy {
+    type Err = String;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy.to_lowercase().as_str() {
+            "hash" => Ok(IdStrategy::Hash),
+            "sequential" => Ok(IdStrategy::Sequential),
+            "uuid" => Ok(IdStrategy::Uuid),
+            "sha256" => Ok(IdStrategy::Sha256),
+            "column" => Ok(IdStrategy::Column),
+            _ => Err(format!("Unknown id strategy: {strategy}")),
+        }
+    }
+}
+
+/// A struct used to deserialize the format of the entities file from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum EntitiesFormat {
+    #[serde(rename = "csv")]
+    #[default]
+    Csv,
+    #[serde(rename = "tsv")]
+    Tsv,
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "toml")]
+    Toml,
+    #[serde(rename = "txt")]
+    Txt,
+}
+
+impl EntitiesFormat {
+    /// Detect the format of the entities file from its extension,
+    /// a directory is read as a folder of txt files (one label per file).
+    /// Defaults to csv.
+    pub fn from_path(path: &str) -> Self {
+        let path = Path::new(path);
+        if path.is_dir() {
+            return EntitiesFormat::Txt;
+        }
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase())
+            .as_deref()
+        {
+            Some("tsv") => EntitiesFormat::Tsv,
+            Some("json") => EntitiesFormat::Json,
+            Some("toml") => EntitiesFormat::Toml,
+            Some("txt") => EntitiesFormat::Txt,
+            _ => EntitiesFormat::Csv,
+        }
+    }
+}
+
+impl Display for EntitiesFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntitiesFormat::Csv => write!(f, "csv"),
+            EntitiesFormat::Tsv => write!(f, "tsv"),
+            EntitiesFormat::Json => write!(f, "json"),
+            EntitiesFormat::Toml => write!(f, "toml"),
+            EntitiesFormat::Txt => write!(f, "txt"),
+        }
+    }
+}
+
+impl FromStr for EntitiesFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(EntitiesFormat::Csv),
+            "tsv" => Ok(EntitiesFormat::Tsv),
+            "json" => Ok(EntitiesFormat::Json),
+            "toml" => Ok(EntitiesFormat::Toml),
+            "txt" => Ok(EntitiesFormat::Txt),
+            _ => Err(format!("Unknown entities format: {format}")),
         }
     }
 }
 
 /// A struct used to deserialize filters from the configuration file.
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Filters {
     pub alphanumeric: bool,
     pub case_sensitive: bool,
@@ -94,6 +378,10 @@ pub struct Filters {
     pub special_characters: bool,
     pub accept_special_characters: Option<String>,
     pub list_of_special_characters: Option<HashSet<char>>,
+    /// Minimum number of tokens, runs of alphanumeric characters, no minimum if not set
+    pub min_tokens: Option<usize>,
+    /// Maximum number of tokens, no maximum if not set
+    pub max_tokens: Option<usize>,
 }
 
 impl Default for Filters {
@@ -108,6 +396,8 @@ impl Default for Filters {
             special_characters: false,
             accept_special_characters: None,
             list_of_special_characters: Some(HashSet::new()),
+            min_tokens: None,
+            max_tokens: None,
         }
     }
 }
@@ -116,8 +406,8 @@ impl Display for Filters {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "alphanumeric: {}, case_sensitive: {}, min_length: {}, max_length: {}, punctuation: {}, numbers: {}, special_characters: {}, accept_special_characters: {:?}",
-            self.alphanumeric, self.case_sensitive, self.min_length, self.max_length, self.punctuation, self.numbers, self.special_characters, self.accept_special_characters
+            "alphanumeric: {}, case_sensitive: {}, min_length: {}, max_length: {}, punctuation: {}, numbers: {}, special_characters: {}, accept_special_characters: {:?}, min_tokens: {:?}, max_tokens: {:?}",
+            self.alphanumeric, self.case_sensitive, self.min_length, self.max_length, self.punctuation, self.numbers, self.special_characters, self.accept_special_characters, self.min_tokens, self.max_tokens
         )
     }
 }
@@ -147,7 +437,8 @@ impl Filters {
     }
 
     /// Checks if a string is a valid entity.
-    /// Using the configuration file, it checks if the string is alphanumeric, contains punctuation, numbers, or special characters.
+    /// Using the configuration file, it checks if the string is alphanumeric, contains punctuation, numbers, or special characters,
+    /// and if its length in characters and in tokens is within the limits.
     /// # Examples
     /// ```
     /// use utils::is_valid;
@@ -160,43 +451,60 @@ impl Filters {
         }
         // False
         if self.alphanumeric && is_alphanumeric(text) {
-            debug!("{} is not alphanumeric", text);
+            debug!("{} is not alphanumeric", preview(text));
             return false;
         }
         if self.punctuation && contains_punctuation(text) {
-            debug!("'{}' contains punctuation", text);
+            debug!("'{}' contains punctuation", preview(text));
             return false;
         }
         if self.numbers && contains_numbers(text) {
-            debug!("{} does not contain numbers", text);
+            debug!("{} does not contain numbers", preview(text));
             return false;
         }
         if self.special_characters
             && contains_special_characters(text, self.get_special_characters())
         {
-            debug!("{} contains special characters", text);
+            debug!("{} contains special characters", preview(text));
             return false;
         }
         if self.min_length >= 0 && text.len() < self.min_length as usize {
-            debug!("{} is too short", text);
+            debug!("{} is too short", preview(text));
             return false;
         }
         if self.max_length >= 0 && text.len() > self.max_length as usize {
             return false;
         }
+        if self.min_tokens.is_some() || self.max_tokens.is_some() {
+            let tokens = tokenize(text).len();
+            if self
+                .min_tokens
+                .is_some_and(|min_tokens| tokens < min_tokens)
+            {
+                debug!("{} has too few tokens", preview(text));
+                return false;
+            }
+            if self
+                .max_tokens
+                .is_some_and(|max_tokens| tokens > max_tokens)
+            {
+                debug!("{} has too many tokens", preview(text));
+                return false;
+            }
+        }
         true
     }
 }
 
 /// A struct used to deserialize annotations from the configuration file.
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Annotations {
     pub output: Output,
     pub format: Format,
 }
 
 /// A struct used to deserialize output format from the configuration file.
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub enum Format {
     #[serde(rename = "csv")]
     Csv,
@@ -209,16 +517,755 @@ pub enum Format {
     Brat,
     #[serde(rename = "conll")]
     Conll,
+    /// Standalone HTML pages with the spans highlighted, for reviews
+    #[serde(rename = "html")]
+    Html,
+    /// NIF 2.0 (Turtle) with the knowledge-base ids of the spans, for entity linking
+    #[serde(rename = "nif")]
+    Nif,
+    /// JSONL with the tokens of each document and their BIO tag, for token classification
+    #[serde(rename = "tokens")]
+    Tokens,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Csv => write!(f, "csv"),
+            Format::Jsonl => write!(f, "jsonl"),
+            Format::Spacy => write!(f, "spacy"),
+            Format::Brat => write!(f, "brat"),
+            Format::Conll => write!(f, "conll"),
+            Format::Html => write!(f, "html"),
+            Format::Nif => write!(f, "nif"),
+            Format::Tokens => write!(f, "tokens"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "jsonl" => Ok(Format::Jsonl),
+            "spacy" => Ok(Format::Spacy),
+            "brat" => Ok(Format::Brat),
+            "conll" => Ok(Format::Conll),
+            "html" => Ok(Format::Html),
+            "nif" => Ok(Format::Nif),
+            "tokens" => Ok(Format::Tokens),
+            _ => Err(format!("Unknown format: {format}")),
+        }
+    }
 }
 
 /// A struct used to deserialize output from the configuration file.
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Output {
     pub path: String,
+    #[serde(default)]
+    pub fields: Fields,
+    #[serde(default)]
+    pub json: JsonOptions,
+    #[serde(default)]
+    pub html: HtmlOptions,
+    #[serde(default)]
+    pub nif: NifOptions,
+    /// Which documents are exported
+    #[serde(default)]
+    pub documents: DocumentSelection,
+    /// Labels with fewer spans than this are dropped from the export
+    pub min_label_count: Option<usize>,
+    /// If set, labels below `min_label_count` are remapped to this label instead of dropped
+    pub rare_label: Option<String>,
+    /// Spans with a lower confidence are dropped from the export,
+    /// the spans without a confidence are kept
+    pub min_confidence: Option<f64>,
+    /// JSON list of the labels expected in the export, the run fails before saving
+    /// if labels are added or missing. Created with the labels of the first run
+    pub frozen_labels: Option<String>,
+    /// Create the missing directories of the output path
+    #[serde(default = "Output::default_create_dirs")]
+    pub create_dirs: bool,
+    /// Write the JSONL output from a writer thread fed by a bounded queue
+    pub streaming: Option<Streaming>,
+    /// Compression of the JSONL output
+    #[serde(default)]
+    pub compression: Compression,
+    /// Write one file per label (or per group of labels) instead of a combined file
+    pub per_label: Option<PerLabel>,
+    /// Write one file per label, like a `per_label` section without groups
+    #[serde(default)]
+    pub split_by_label: bool,
+    /// Write a provenance stamp (quickner version, gazetteer and config hashes,
+    /// export time) in the formats supporting it: a JSONL header record and a brat note
+    #[serde(default)]
+    pub stamp: bool,
+    /// Write the JSON report of the run next to the annotations, e.g. annotations_report.json
+    #[serde(default = "Output::default_report")]
+    pub report: bool,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            path: "".to_string(),
+            fields: Fields::default(),
+            json: JsonOptions::default(),
+            html: HtmlOptions::default(),
+            nif: NifOptions::default(),
+            documents: DocumentSelection::default(),
+            min_label_count: None,
+            rare_label: None,
+            min_confidence: None,
+            frozen_labels: None,
+            create_dirs: Output::default_create_dirs(),
+            streaming: None,
+            compression: Compression::default(),
+            per_label: None,
+            split_by_label: false,
+            stamp: false,
+            report: true,
+        }
+    }
+}
+
+/// A struct used to deserialize which documents are exported from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum DocumentSelection {
+    /// Every document
+    #[serde(rename = "all")]
+    #[default]
+    All,
+    /// Only the documents with at least one span
+    #[serde(rename = "annotated")]
+    Annotated,
+    /// Only the documents without any span
+    #[serde(rename = "unannotated")]
+    Unannotated,
+}
+
+impl Display for DocumentSelection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentSelection::All => write!(f, "all"),
+            DocumentSelection::Annotated => write!(f, "annotated"),
+            DocumentSelection::Unannotated => write!(f, "unannotated"),
+        }
+    }
+}
+
+impl FromStr for DocumentSelection {
+    type Err = String;
+
+    fn from_str(selection: &str) -> Result<Self, Self::Err> {
+        match selection.to_lowercase().as_str() {
+            "all" => Ok(DocumentSelection::All),
+            "annotated" => Ok(DocumentSelection::Annotated),
+            "unannotated" => Ok(DocumentSelection::Unannotated),
+            _ => Err(format!("Unknown documents selection: {selection}")),
+        }
+    }
+}
+
+/// A struct used to deserialize the compression of the output from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Plain text output
+    #[serde(rename = "none")]
+    #[default]
+    None,
+    /// Gzip-compressed output, written to a .jsonl.gz file
+    #[serde(rename = "gzip")]
+    Gzip,
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(compression: &str) -> Result<Self, Self::Err> {
+        match compression.to_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            _ => Err(format!("Unknown compression: {compression}")),
+        }
+    }
+}
+
+/// A struct used to deserialize JSON output options from the configuration file.
+/// `pretty` and `indent` only apply to JSON files (spaCy format), JSONL files
+/// always hold one compact record per line.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct JsonOptions {
+    /// Pretty-print the JSON output (for human diffing)
+    pub pretty: bool,
+    /// Number of spaces used for indentation when pretty-printing
+    pub indent: usize,
+    /// Escape non-ASCII characters as \uXXXX sequences
+    pub ensure_ascii: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions {
+            pretty: false,
+            indent: 2,
+            ensure_ascii: false,
+        }
+    }
+}
+
+/// A struct used to deserialize HTML output options from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HtmlOptions {
+    /// Title of the pages
+    pub title: String,
+    /// Number of documents per page, the documents are written to numbered pages
+    /// (annotations_1.html, annotations_2.html, ...) if set, to a single page if not
+    pub documents_per_page: Option<usize>,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        HtmlOptions {
+            title: "Annotations".to_string(),
+            documents_per_page: None,
+        }
+    }
+}
+
+/// A struct used to deserialize NIF output options from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NifOptions {
+    /// Base URI of the resources, the documents are {base_uri}documents/{id}
+    /// and the labels {base_uri}labels/{label}
+    pub base_uri: String,
+    /// Prefix of the knowledge-base ids which are not URIs, e.g.
+    /// http://www.wikidata.org/entity/ for Q312, {base_uri}entities/ if not set
+    pub kb_uri: Option<String>,
+}
+
+impl Default for NifOptions {
+    fn default() -> Self {
+        NifOptions {
+            base_uri: "http://example.org/quickner/".to_string(),
+            kb_uri: None,
+        }
+    }
+}
+
+/// A struct used to deserialize streaming export options from the configuration file.
+/// The documents are serialized in parallel and handed to a writer thread
+/// through a bounded queue, the workers wait when the queue is full so the
+/// memory used stays bounded when the disk is slower than the annotation.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Streaming {
+    /// Number of serialized documents buffered before the workers wait for the writer
+    pub queue_size: usize,
+}
+
+impl Default for Streaming {
+    fn default() -> Self {
+        Streaming { queue_size: 1024 }
+    }
+}
+
+/// A struct used to deserialize the performance options from the configuration file.
+/// The parallel stages run in a pool of `num_threads` threads, or in the global
+/// pool of rayon (one thread per core) if it is not set.
+/// ```toml
+/// [performance]
+/// num_threads = 4
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Performance {
+    pub num_threads: Option<usize>,
+}
+
+/// A struct used to deserialize the checkpoints of the annotation from the configuration file.
+/// The annotated documents are written to the checkpoint directory every `every`
+/// documents, a run started with `resume` skips the documents found there.
+/// The directory is removed at the end of the run, once the annotations are saved.
+/// ```toml
+/// [checkpoint]
+/// every = 10000
+/// path = "annotations.checkpoint"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Checkpoint {
+    /// Number of documents annotated between two checkpoints
+    pub every: usize,
+    /// Directory of the checkpoint, the output path followed by `.checkpoint` if not set
+    pub path: Option<String>,
+    /// Resume from the last checkpoint, the run starts from the beginning if there is none
+    pub resume: bool,
+}
+
+impl Default for Checkpoint {
+    fn default() -> Self {
+        Checkpoint {
+            every: 10_000,
+            path: None,
+            resume: false,
+        }
+    }
+}
+
+impl Checkpoint {
+    /// Directory of the checkpoint of the annotations saved to `output`
+    pub fn directory(&self, output: &str) -> String {
+        match &self.path {
+            Some(path) => path.to_string(),
+            None => format!("{output}.checkpoint"),
+        }
+    }
+}
+
+/// A struct used to deserialize the per-label export from the configuration file.
+/// Each file holds the documents restricted to the spans of its labels,
+/// e.g. annotations_ORG.jsonl, the labels of a group are written to the same file.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PerLabel {
+    /// Name of the file of a group mapped to the labels of the group,
+    /// the labels without a group get a file of their own
+    pub groups: HashMap<String, Vec<String>>,
+}
+
+/// A struct used to deserialize JSONL field names from the configuration file.
+/// Allows matching third-party schemas, e.g. "content" instead of "text"
+/// or "labels" instead of "label".
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Fields {
+    pub id: String,
+    pub text: String,
+    pub label: String,
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Fields {
+            id: "id".to_string(),
+            text: "text".to_string(),
+            label: "label".to_string(),
+        }
+    }
+}
+
+/// A struct used to deserialize a routing rule from the configuration file.
+/// Documents whose metadata match every key of `when` are annotated
+/// with the entities of the route instead of the default ones.
+/// ```toml
+/// [[routes]]
+/// when = { lang = "fr" }
+/// [routes.entities.input]
+/// path = "entities_fr.csv"
+/// ```
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Route {
+    pub when: HashMap<String, String>,
+    pub entities: RouteEntities,
+}
+
+/// A struct used to deserialize the entities of a route from the configuration file.
+/// The default entities filters are used if none are given.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RouteEntities {
+    pub input: Input,
+    pub filters: Option<Filters>,
+}
+
+/// A struct used to deserialize the document tags from the configuration file.
+/// After the annotation, each document is tagged with the label of the keywords
+/// of the dictionary found at least `min_matches` times in its text, e.g. to
+/// classify the documents along with the NER pre-annotation. The keywords are
+/// matched like the entities, the default entities filters are used if none are given.
+/// ```toml
+/// [tags]
+/// min_matches = 2
+/// [tags.input]
+/// path = "keywords.csv" # name,label columns, the label is the tag
+/// ```
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Tags {
+    pub input: Input,
+    pub filters: Option<Filters>,
+    /// Number of matches of the keywords of a tag needed to tag a document
+    #[serde(default = "Tags::default_min_matches")]
+    pub min_matches: usize,
+}
+
+impl Tags {
+    fn default_min_matches() -> usize {
+        1
+    }
+}
+
+/// A struct used to deserialize a context rule from the configuration file.
+/// Rules are evaluated after the dictionary matching, on the spans of `pattern`
+/// annotated with `label`. The context of a span matches when one of the `require`
+/// terms (if any) and none of the `forbid` terms appear within `window` tokens
+/// before or after the span.
+/// ```toml
+/// [[rules]]
+/// pattern = "apple"
+/// label = "ORG"
+/// require = ["iphone", "ceo"]
+/// window = 10
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub pattern: String,
+    pub label: String,
+    #[serde(default)]
+    pub require: Vec<String>,
+    #[serde(default)]
+    pub forbid: Vec<String>,
+    /// Number of tokens on each side of the span
+    #[serde(default = "Rule::default_window")]
+    pub window: usize,
+    #[serde(default)]
+    pub action: RuleAction,
+    /// New label of the spans, with the redirect action
+    #[serde(default)]
+    pub to: Option<String>,
+}
+
+/// A struct used to deserialize a relation pattern from the configuration file.
+/// Relations are proposed after the annotation between the spans of the `head`
+/// and `tail` labels (any label if not set). With a `pattern`, the words around
+/// the spans must be the words of the pattern, ignoring the case and the
+/// punctuation. Without one, the spans within `window` tokens of each other are related.
+/// ```toml
+/// [[relations]]
+/// label = "CEO_OF"
+/// head = "PERSON"
+/// tail = "ORG"
+/// pattern = "{head}, CEO of {tail}"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RelationPattern {
+    pub label: String,
+    pub head: Option<String>,
+    pub tail: Option<String>,
+    /// Words around the spans, with the `{head}` and `{tail}` placeholders
+    pub pattern: Option<String>,
+    /// Most tokens between the spans, without a pattern
+    #[serde(default = "RelationPattern::default_window")]
+    pub window: usize,
+}
+
+/// A struct used to deserialize what a context rule does from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub enum RuleAction {
+    /// Keep the span only when its context matches
+    #[serde(rename = "accept")]
+    #[default]
+    Accept,
+    /// Drop the span when its context matches
+    #[serde(rename = "reject")]
+    Reject,
+    /// Change the label of the span to `to` when its context matches
+    #[serde(rename = "redirect")]
+    Redirect,
+}
+
+impl Display for RuleAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleAction::Accept => write!(f, "accept"),
+            RuleAction::Reject => write!(f, "reject"),
+            RuleAction::Redirect => write!(f, "redirect"),
+        }
+    }
+}
+
+impl FromStr for RuleAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action.to_lowercase().as_str() {
+            "accept" => Ok(RuleAction::Accept),
+            "reject" => Ok(RuleAction::Reject),
+            "redirect" => Ok(RuleAction::Redirect),
+            _ => Err(format!("Unknown rule action: {action}")),
+        }
+    }
+}
+
+/// A struct used to deserialize labels from the configuration file.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Labels {
+    #[serde(default)]
+    pub priority: LabelPriority,
+    /// Color and name of the labels in the pretty prints and the HTML pages
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub display: BTreeMap<String, LabelDisplay>,
+    /// Conditions of the spans of the high precision labels, by label
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub precision: BTreeMap<String, LabelPrecision>,
+    #[serde(default)]
+    pub aggregation: LabelAggregation,
+}
+
+/// How the labels are aggregated when the annotators (the gazetteer, fuzzy
+/// matching, rules, mentions, imported spans) give the exact same span different
+/// labels. The span kept records the decision and its sources in its provenance.
+/// ```toml
+/// [labels.aggregation]
+/// strategy = "vote"
+/// weights = { import = 3.0, gazetteer = 1.0, fuzzy = 0.5 }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct LabelAggregation {
+    pub strategy: AggregationStrategy,
+    /// Weight of the vote of each source, 1 for the sources not listed
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub weights: BTreeMap<SpanSource, f64>,
+}
+
+/// Label kept for a span given different labels
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AggregationStrategy {
+    /// The first label of the label priority, every label is kept without one
+    #[default]
+    Priority,
+    /// The label with the most votes of the sources, by weight, ties are
+    /// broken by the label priority
+    Vote,
+    /// No label, the span is dropped
+    SkipConflicts,
+}
+
+impl Display for AggregationStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregationStrategy::Priority => write!(f, "priority"),
+            AggregationStrategy::Vote => write!(f, "vote"),
+            AggregationStrategy::SkipConflicts => write!(f, "skip-conflicts"),
+        }
+    }
+}
+
+impl FromStr for AggregationStrategy {
+    type Err = String;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy.to_lowercase().as_str() {
+            "priority" => Ok(AggregationStrategy::Priority),
+            "vote" => Ok(AggregationStrategy::Vote),
+            "skip-conflicts" => Ok(AggregationStrategy::SkipConflicts),
+            _ => Err(format!("Unknown aggregation strategy: {strategy}")),
+        }
+    }
+}
+
+/// Guardrails of a high precision label: a span of the label is only kept if
+/// it meets one of the conditions set, e.g. for the names that are also common
+/// words ("Apple", "Bill"). The casing is the one of the original text.
+/// ```toml
+/// [labels.precision]
+/// ORG = { title_case = true }                  # "Apple" but not "apple"
+/// PERSON = { title_case = true, min_mentions = 2 } # or matched twice in the document
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct LabelPrecision {
+    /// Keep the spans whose first word and words of more than 3 letters start
+    /// with a capital letter, "Bank of America" but not "Apple pie"
+    pub title_case: bool,
+    /// Keep the spans whose text is matched with the label at least this many
+    /// times in the document, 0 to disable
+    pub min_mentions: usize,
+}
+
+/// How a label is rendered, the same from one run to the next.
+/// ```toml
+/// [labels.display]
+/// ORG = { color = "blue", name = "Organization" }
+/// PER = { color = "#f28e2b" }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct LabelDisplay {
+    /// Color of the spans of the label, one of the colors given in turn if not set
+    pub color: Option<TermColor>,
+    /// Name shown after the spans and in the legends, the label if not set
+    pub name: Option<String>,
+}
+
+/// A struct used to deserialize the matching options from the configuration file.
+/// Both the entities and the texts are normalized at match time only,
+/// the spans keep the offsets of the original text.
+/// ```toml
+/// [matching]
+/// fold_diacritics = true # "Montreal" matches "Montréal"
+/// match_plurals = true # "apple" matches "apples"
+/// language = "french" # "bureau" also matches "bureaux"
+/// abbreviations = true # "WHO" matches with "World Health Organization (WHO)" in the corpus
+/// propagate_mentions = true # "Obama" is annotated after "Barack Obama"
+/// fuzzy = { max_edits = 1, min_len = 6 } # "Mozila" matches "Mozilla"
+/// verified = "append" # the corrected and gold documents only get new spans
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Matching {
+    /// Match letters with and without diacritics alike
+    pub fold_diacritics: bool,
+    /// Also match the plural forms of the entities
+    pub match_plurals: bool,
+    /// Language of the plural forms matched
+    pub language: Language,
+    /// Add the abbreviations defined in the corpus, e.g. "World Health Organization (WHO)",
+    /// whose long form or short form is an entity, before the annotation
+    pub abbreviations: bool,
+    /// Annotate the repeated mentions of the surfaces annotated in a document,
+    /// the whole surface or its first or last word, after the matching
+    pub propagate_mentions: bool,
+    /// Also match the entities with typos or OCR errors, disabled if not set
+    pub fuzzy: Option<Fuzzy>,
+    /// How the documents checked by a human, corrected or gold, are annotated
+    pub verified: VerifiedPolicy,
+}
+
+/// How the matcher annotates a verified document, see [`DocumentStatus`](crate::DocumentStatus)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifiedPolicy {
+    /// The document is left as it is
+    #[default]
+    Skip,
+    /// The spans found are added if they do not overlap a span of the document,
+    /// whose spans are all kept
+    Append,
+    /// The document is annotated like the others, its spans may be dropped
+    /// by the label priority
+    Overwrite,
+}
+
+impl Display for VerifiedPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifiedPolicy::Skip => write!(f, "skip"),
+            VerifiedPolicy::Append => write!(f, "append"),
+            VerifiedPolicy::Overwrite => write!(f, "overwrite"),
+        }
+    }
+}
+
+impl FromStr for VerifiedPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.to_lowercase().as_str() {
+            "skip" => Ok(VerifiedPolicy::Skip),
+            "append" => Ok(VerifiedPolicy::Append),
+            "overwrite" => Ok(VerifiedPolicy::Overwrite),
+            _ => Err(format!(
+                "Unknown policy for the verified documents: {policy}"
+            )),
+        }
+    }
+}
+
+/// Language of the plural forms matched with `match_plurals`. The regular
+/// English plurals ("apples", "boxes", "companies") are matched in every
+/// language, French adds "-x" and "-aux" ("bureaux", "journaux") and Spanish
+/// adds "-es" after a consonant ("ciudades")
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    English,
+    French,
+    Spanish,
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Language::English => write!(f, "english"),
+            Language::French => write!(f, "french"),
+            Language::Spanish => write!(f, "spanish"),
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(language: &str) -> Result<Self, Self::Err> {
+        match language.to_lowercase().as_str() {
+            "english" => Ok(Language::English),
+            "french" => Ok(Language::French),
+            "spanish" => Ok(Language::Spanish),
+            _ => Err(format!("Unknown language of the plurals: {language}")),
+        }
+    }
+}
+
+/// A struct used to deserialize the fuzzy matching options from the configuration file.
+/// The entities of at least `min_len` characters also match the words of the texts
+/// at most `max_edits` insertions, deletions or substitutions away, found in a
+/// second pass after the exact matching. A fuzzy match overlapping an exact match
+/// is dropped, and the closest of overlapping fuzzy matches is kept.
+/// ```toml
+/// [matching.fuzzy]
+/// max_edits = 1
+/// min_len = 6
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Fuzzy {
+    /// Largest edit distance between an entity and a match
+    pub max_edits: usize,
+    /// Shortest entity matched with edits, in characters
+    pub min_len: usize,
+}
+
+impl Default for Fuzzy {
+    fn default() -> Self {
+        Fuzzy {
+            max_edits: 1,
+            min_len: 6,
+        }
+    }
+}
+
+/// A struct used to deserialize the label priority from the configuration file.
+/// When entities of different labels match the exact same span,
+/// only the label coming first in `order` is kept.
+/// ```toml
+/// [labels.priority]
+/// order = ["PRODUCT", "ORG"]
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LabelPriority {
+    #[serde(default)]
+    pub order: Vec<String>,
 }
 
 /// A struct used to deserialize entities from the configuration file.
-#[derive(Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Entities {
     pub input: Input,
     pub filters: Filters,
@@ -226,33 +1273,731 @@ pub struct Entities {
 }
 
 /// A struct used to deserialize excludes from the configuration file.
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Excludes {
     pub path: Option<String>,
 }
 
+/// An error found while loading or validating the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The configuration file cannot be read
+    Read { path: String, message: String },
+    /// The configuration file is not valid TOML or has unknown values
+    Parse { path: String, message: String },
+    /// The configuration cannot be serialized or written to a file
+    Write { path: String, message: String },
+    /// A path given in the configuration does not exist
+    MissingPath { field: String, path: String },
+    /// A filter has a minimum length greater than its maximum length
+    InvalidLength { field: String, min: i32, max: i32 },
+    /// Two settings cannot be used together
+    Conflict { fields: String, message: String },
+    /// The selected profile is not defined in the configuration file
+    UnknownProfile {
+        path: String,
+        profile: String,
+        available: Vec<String>,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read { path, message } => {
+                write!(f, "Unable to read the configuration file {path}: {message}")
+            }
+            ConfigError::Parse { path, message } => {
+                write!(
+                    f,
+                    "Unable to parse the configuration file {path}: {message}"
+                )
+            }
+            ConfigError::Write { path, message } => {
+                write!(
+                    f,
+                    "Unable to write the configuration file {path}: {message}"
+                )
+            }
+            ConfigError::MissingPath { field, path } => {
+                write!(f, "{field}: the path \"{path}\" does not exist")
+            }
+            ConfigError::InvalidLength { field, min, max } => write!(
+                f,
+                "{field}: min_length ({min}) is greater than max_length ({max})"
+            ),
+            ConfigError::Conflict { fields, message } => write!(f, "{fields}: {message}"),
+            ConfigError::UnknownProfile {
+                path,
+                profile,
+                available,
+            } => match available.is_empty() {
+                true => write!(f, "Unknown profile {profile}, {path} has no profiles"),
+                false => write!(
+                    f,
+                    "Unknown profile {profile} in {path}, available profiles: {}",
+                    available.join(", ")
+                ),
+            },
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Commented configuration file with the default settings, see `Config::template`
+const TEMPLATE: &str = include_str!("default_config.toml");
+
 impl Config {
     pub fn from_file(path: &str) -> Self {
-        let config = fs::read_to_string(path).expect("Unable to read the configuration file");
-        let config = toml::from_str(&config);
-        match config {
+        match Config::try_from_file(path) {
             Ok(config) => config,
             Err(e) => {
-                error!("Unable to parse the configuration file: {}", e);
+                error!("{}", e);
                 std::process::exit(1);
             }
         }
     }
 
+    /// Read and parse the configuration file without exiting on error.
+    /// The profile named by the `QUICKNER_PROFILE` environment variable, if set,
+    /// and the environment variable overrides are applied,
+    /// see `try_from_file_with_profile`.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Config;
+    /// let config = Config::try_from_file("./config.toml").unwrap();
+    /// ```
+    pub fn try_from_file(path: &str) -> Result<Self, ConfigError> {
+        Config::try_from_file_with_profile(path, None)
+    }
+
+    /// Read and parse the configuration file with a profile, a `[profiles.<name>]`
+    /// section whose settings replace the ones of the file, e.g. to switch the
+    /// paths between environments. Without `profile`, the `QUICKNER_PROFILE`
+    /// environment variable selects it, no profile is applied if it is not set.
+    ///
+    /// The files listed by `include` are read first, with their own includes,
+    /// and the settings of the file replace theirs, e.g. to share the labels
+    /// or the filters between configurations. The includes and the paths they
+    /// set are relative to the file including them.
+    ///
+    /// The environment variables named after a setting then override it, with
+    /// the `QUICKNER_` prefix and `__` between the sections and the key:
+    /// `QUICKNER_TEXTS__INPUT__PATH=texts.csv` sets `path` in `[texts.input]`.
+    /// Their values are read as TOML values (`true`, `10`, `["a", "b"]`),
+    /// as strings for string settings and when they are not valid TOML.
+    /// # Example
+    /// ```toml
+    /// include = ["shared/labels.toml"]
+    ///
+    /// [annotations.output]
+    /// path = "annotations.jsonl"
+    ///
+    /// [profiles.prod.annotations.output]
+    /// path = "/data/annotations.jsonl"
+    /// ```
+    /// ```no_run
+    /// use quickner::Config;
+    /// let config = Config::try_from_file_with_profile("./config.toml", Some("prod")).unwrap();
+    /// ```
+    pub fn try_from_file_with_profile(
+        path: &str,
+        profile: Option<&str>,
+    ) -> Result<Self, ConfigError> {
+        let parse_error = |e: toml::de::Error| ConfigError::Parse {
+            path: path.to_string(),
+            message: e.to_string(),
+        };
+        let mut value = Config::read_value(Path::new(path), Path::new(""), &mut Vec::new())?;
+        let profile = match profile {
+            Some(profile) => Some(profile.to_string()),
+            None => env::var("QUICKNER_PROFILE")
+                .ok()
+                .filter(|profile| !profile.is_empty()),
+        };
+        Config::apply_profile(&mut value, path, profile.as_deref())?;
+        Config::apply_env_overrides(&mut value, env::vars());
+        let mut config: Config = value.try_into().map_err(parse_error)?;
+        if let Some(directory) = Path::new(path).parent() {
+            if !directory.as_os_str().is_empty() {
+                info!(
+                    "Paths in {} are resolved relative to {}",
+                    path,
+                    directory.display()
+                );
+                config.resolve_paths(directory);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Read a configuration file merged over the files of its `include` list,
+    /// in their order. The paths set by the file are rebased on `directory`,
+    /// its directory relative to the configuration file read. `chain` holds
+    /// the files including it, to stop on a file including itself.
+    fn read_value(
+        path: &Path,
+        directory: &Path,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<Value, ConfigError> {
+        let name = path.to_string_lossy().to_string();
+        let parse_error = |message: String| ConfigError::Parse {
+            path: name.clone(),
+            message,
+        };
+        let config = fs::read_to_string(long_path(path)).map_err(|e| ConfigError::Read {
+            path: name.clone(),
+            message: e.to_string(),
+        })?;
+        let mut value: Value =
+            toml::from_str(&config).map_err(|e: toml::de::Error| parse_error(e.to_string()))?;
+        if !directory.as_os_str().is_empty() {
+            rebase_paths(&mut value, directory);
+        }
+        let includes = match value
+            .as_table_mut()
+            .and_then(|table| table.remove("include"))
+        {
+            None => return Ok(value),
+            Some(Value::String(include)) => vec![include],
+            Some(Value::Array(includes)) => includes
+                .into_iter()
+                .map(|include| match include {
+                    Value::String(include) => Ok(include),
+                    other => Err(parse_error(format!("include must list paths, got {other}"))),
+                })
+                .collect::<Result<Vec<String>, ConfigError>>()?,
+            Some(other) => {
+                return Err(parse_error(format!(
+                    "include must be a list of paths, got {other}"
+                )))
+            }
+        };
+        let file = fs::canonicalize(long_path(path)).unwrap_or_else(|_| path.to_path_buf());
+        if chain.contains(&file) {
+            return Err(parse_error("the file includes itself".to_string()));
+        }
+        chain.push(file);
+        let parent = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut merged = Value::Table(Default::default());
+        for include in includes {
+            let include = Path::new(&include);
+            debug!("{} includes {}", name, include.display());
+            let included = Config::read_value(
+                &parent.join(include),
+                &directory.join(include.parent().unwrap_or_else(|| Path::new(""))),
+                chain,
+            )?;
+            merge_toml(&mut merged, included);
+        }
+        chain.pop();
+        merge_toml(&mut merged, value);
+        Ok(merged)
+    }
+
+    /// Merge the `[profiles.<profile>]` section into the settings of the file,
+    /// the `profiles` section itself is removed
+    fn apply_profile(
+        value: &mut Value,
+        path: &str,
+        profile: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        let profiles = match value.as_table_mut() {
+            Some(table) => table.remove("profiles"),
+            None => None,
+        };
+        let profile = match profile {
+            Some(profile) => profile,
+            None => return Ok(()),
+        };
+        let mut profiles = match profiles {
+            Some(Value::Table(profiles)) => profiles,
+            _ => Default::default(),
+        };
+        match profiles.remove(profile) {
+            Some(settings) => {
+                info!("Using the profile {} of {}", profile, path);
+                merge_toml(value, settings);
+                Ok(())
+            }
+            None => Err(ConfigError::UnknownProfile {
+                path: path.to_string(),
+                profile: profile.to_string(),
+                available: profiles.keys().cloned().collect(),
+            }),
+        }
+    }
+
+    /// Set the settings named by the `QUICKNER_SECTION__KEY` variables,
+    /// variables without `__` are not settings and are ignored
+    fn apply_env_overrides(value: &mut Value, vars: impl Iterator<Item = (String, String)>) {
+        for (name, raw) in vars {
+            let keys: Vec<String> = match name.strip_prefix("QUICKNER_") {
+                Some(keys) if keys.contains("__") => {
+                    keys.split("__").map(|key| key.to_lowercase()).collect()
+                }
+                _ => continue,
+            };
+            let mut table = match value.as_table_mut() {
+                Some(table) => table,
+                None => return,
+            };
+            for key in &keys[..keys.len() - 1] {
+                let entry = table
+                    .entry(key.as_str())
+                    .or_insert_with(|| Value::Table(Default::default()));
+                if !entry.is_table() {
+                    *entry = Value::Table(Default::default());
+                }
+                table = entry.as_table_mut().unwrap();
+            }
+            let key = keys[keys.len() - 1].as_str();
+            let parsed = toml::from_str::<toml::Table>(&format!("value = {raw}"))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("value"));
+            let setting = match (table.get(key), parsed) {
+                (Some(Value::String(_)), _) | (_, None) => Value::String(raw),
+                (_, Some(parsed)) => parsed,
+            };
+            info!("{} overrides {}", name, keys.join("."));
+            table.insert(key.to_string(), setting);
+        }
+    }
+
+    /// Resolve the relative input and output paths against a directory,
+    /// usually the one containing the configuration file.
+    /// Absolute and empty paths are left unchanged.
+    /// # Example
+    /// ```
+    /// use quickner::Config;
+    /// let mut config = Config::default();
+    /// config.texts.input.path = "texts.csv".to_string();
+    /// config.resolve_paths(std::path::Path::new("/data/project"));
+    /// assert_eq!(config.texts.input.path, "/data/project/texts.csv");
+    /// ```
+    pub fn resolve_paths(&mut self, directory: &Path) {
+        let resolve = |path: &mut String| {
+            if path.is_empty() || Path::new(path.as_str()).is_absolute() {
+                return;
+            }
+            *path = directory.join(path.as_str()).to_string_lossy().to_string();
+        };
+        resolve(&mut self.texts.input.path);
+        resolve(&mut self.entities.input.path);
+        resolve(&mut self.annotations.output.path);
+        if let Some(path) = self.entities.excludes.path.as_mut() {
+            resolve(path);
+        }
+        for route in self.routes.iter_mut() {
+            resolve(&mut route.entities.input.path);
+        }
+        if let Some(tags) = self.tags.as_mut() {
+            resolve(&mut tags.input.path);
+        }
+        if let Some(path) = self
+            .checkpoint
+            .as_mut()
+            .and_then(|checkpoint| checkpoint.path.as_mut())
+        {
+            resolve(path);
+        }
+    }
+
+    /// Serialize the configuration to TOML, as read by `try_from_file`.
+    /// The paths are written as they are, resolved from the directory of the
+    /// configuration file read if it was read from a file.
+    /// # Example
+    /// ```
+    /// use quickner::Config;
+    /// let config = Config::default();
+    /// let toml = config.to_toml().unwrap();
+    /// assert!(toml.contains("[texts.input]"));
+    /// ```
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(|e| ConfigError::Write {
+            path: String::new(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Save the configuration to a TOML file, see `to_toml`
+    /// # Example
+    /// ```no_run
+    /// use quickner::Config;
+    /// let mut config = Config::default();
+    /// config.texts.input.path = "texts.csv".to_string();
+    /// config.to_file("./config.toml").unwrap();
+    /// ```
+    pub fn to_file(&self, path: &str) -> Result<(), ConfigError> {
+        let write_error = |message: String| ConfigError::Write {
+            path: path.to_string(),
+            message,
+        };
+        let toml = self.to_toml().map_err(|e| match e {
+            ConfigError::Write { message, .. } => write_error(message),
+            e => e,
+        })?;
+        fs::write(long_path(path), toml).map_err(|e| write_error(e.to_string()))
+    }
+
+    /// Commented configuration file with the default settings and the optional
+    /// ones commented out, written by `quickner init`
+    pub fn template() -> &'static str {
+        TEMPLATE
+    }
+
+    /// Check that the paths exist, that the filters make sense
+    /// and that no settings conflict with each other.
+    /// Every problem found is returned, not only the first one.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Config;
+    /// let config = Config::try_from_file("./config.toml").unwrap();
+    /// if let Err(errors) = config.validate() {
+    ///     for error in errors {
+    ///         eprintln!("{error}");
+    ///     }
+    /// }
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let mut check_path = |field: &str, path: &str| {
+            if !Path::new(path).exists() {
+                errors.push(ConfigError::MissingPath {
+                    field: field.to_string(),
+                    path: path.to_string(),
+                });
+            }
+        };
+        check_path("texts.input.path", &self.texts.input.path);
+        check_path("entities.input.path", &self.entities.input.path);
+        if let Some(path) = &self.entities.excludes.path {
+            check_path("entities.excludes.path", path);
+        }
+        for (index, route) in self.routes.iter().enumerate() {
+            check_path(
+                &format!("routes[{index}].entities.input.path"),
+                &route.entities.input.path,
+            );
+        }
+        if let Some(tags) = &self.tags {
+            check_path("tags.input.path", &tags.input.path);
+        }
+        for (field, filters) in [
+            ("texts.filters", &self.texts.filters),
+            ("entities.filters", &self.entities.filters),
+        ] {
+            if filters.min_length >= 0
+                && filters.max_length >= 0
+                && filters.min_length > filters.max_length
+            {
+                errors.push(ConfigError::InvalidLength {
+                    field: field.to_string(),
+                    min: filters.min_length,
+                    max: filters.max_length,
+                });
+            }
+        }
+        if self.texts.input.split == Some(Split::Paragraph)
+            && !Path::new(&self.texts.input.path).is_dir()
+        {
+            errors.push(ConfigError::Conflict {
+                fields: "texts.input.split".to_string(),
+                message:
+                    "the paragraph split is only used when texts.input.path is a directory of texts"
+                        .to_string(),
+            });
+        }
+        if self.texts.input.format.is_some() {
+            errors.push(ConfigError::Conflict {
+                fields: "texts.input.format".to_string(),
+                message: "the format is only read for the entities files, not for the texts"
+                    .to_string(),
+            });
+        }
+        if let Some(sample) = self.texts.input.sample {
+            if !(sample > 0.0 && sample <= 1.0) {
+                errors.push(ConfigError::Conflict {
+                    fields: "texts.input.sample".to_string(),
+                    message: format!("the sample is a fraction between 0 and 1, got {sample}"),
+                });
+            }
+        }
+        if let Some(dedupe) = self.texts.input.dedupe {
+            if !(dedupe > 0.0 && dedupe <= 1.0) {
+                errors.push(ConfigError::Conflict {
+                    fields: "texts.input.dedupe".to_string(),
+                    message: format!("the dedupe similarity is between 0 and 1, got {dedupe}"),
+                });
+            }
+        }
+        if self.entities.input.limit.is_some()
+            || self.entities.input.sample.is_some()
+            || self.entities.input.dedupe.is_some()
+        {
+            errors.push(ConfigError::Conflict {
+                fields: "entities.input.limit, entities.input.sample, entities.input.dedupe"
+                    .to_string(),
+                message: "only the texts can be limited, sampled or deduplicated".to_string(),
+            });
+        }
+        if self.texts.input.id == Some(IdStrategy::Column)
+            && Path::new(&self.texts.input.path).is_dir()
+        {
+            errors.push(ConfigError::Conflict {
+                fields: "texts.input.id".to_string(),
+                message: "the ids of a directory of texts are the file names, the id column needs a texts file".to_string(),
+            });
+        }
+        if let Some(fuzzy) = &self.matching.fuzzy {
+            if fuzzy.max_edits == 0 || fuzzy.min_len <= fuzzy.max_edits {
+                errors.push(ConfigError::Conflict {
+                    fields: "matching.fuzzy".to_string(),
+                    message: format!(
+                        "max_edits must be at least 1 and below min_len, got {} and {}",
+                        fuzzy.max_edits, fuzzy.min_len
+                    ),
+                });
+            }
+        }
+        for (index, route) in self.routes.iter().enumerate() {
+            if route.when.is_empty() {
+                errors.push(ConfigError::Conflict {
+                    fields: format!("routes[{index}].when"),
+                    message: "a route needs at least one metadata key to match".to_string(),
+                });
+            }
+        }
+        for (label, precision) in &self.labels.precision {
+            if !precision.title_case && precision.min_mentions == 0 {
+                errors.push(ConfigError::Conflict {
+                    fields: format!("labels.precision.{label}"),
+                    message: "a high precision label needs title_case or min_mentions".to_string(),
+                });
+            }
+        }
+        for (source, weight) in &self.labels.aggregation.weights {
+            if !weight.is_finite() || *weight < 0.0 {
+                errors.push(ConfigError::Conflict {
+                    fields: format!("labels.aggregation.weights.{source}"),
+                    message: format!("a weight must be a positive number, got {weight}"),
+                });
+            }
+        }
+        if self
+            .checkpoint
+            .as_ref()
+            .is_some_and(|checkpoint| checkpoint.every == 0)
+        {
+            errors.push(ConfigError::Conflict {
+                fields: "checkpoint.every".to_string(),
+                message: "the number of documents between two checkpoints must be at least 1"
+                    .to_string(),
+            });
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.require.is_empty() && rule.forbid.is_empty() {
+                errors.push(ConfigError::Conflict {
+                    fields: format!("rules[{index}]"),
+                    message: "a rule needs require or forbid terms".to_string(),
+                });
+            }
+            if (rule.action == RuleAction::Redirect) != rule.to.is_some() {
+                errors.push(ConfigError::Conflict {
+                    fields: format!("rules[{index}].to"),
+                    message: "the label to redirect to is set with the redirect action only"
+                        .to_string(),
+                });
+            }
+        }
+        if self.performance.num_threads == Some(0) {
+            errors.push(ConfigError::Conflict {
+                fields: "performance.num_threads".to_string(),
+                message: "at least one thread is needed, leave it unset to use every core"
+                    .to_string(),
+            });
+        }
+        for (index, relation) in self.relations.iter().enumerate() {
+            let pattern = relation.pattern.as_deref();
+            if pattern.is_some_and(|pattern| RelationPattern::parts(pattern).is_none()) {
+                errors.push(ConfigError::Conflict {
+                    fields: format!("relations[{index}].pattern"),
+                    message: "a pattern needs the {head} and {tail} placeholders once each"
+                        .to_string(),
+                });
+            }
+        }
+        let output = &self.annotations.output;
+        if !output.create_dirs {
+            let directory = Path::new(&output.path)
+                .parent()
+                .filter(|directory| !directory.as_os_str().is_empty());
+            if let Some(directory) = directory {
+                if !directory.is_dir() {
+                    errors.push(ConfigError::MissingPath {
+                        field: "annotations.output.path".to_string(),
+                        path: directory.display().to_string(),
+                    });
+                }
+            }
+        }
+        if output.streaming.is_some() && !matches!(self.annotations.format, Format::Jsonl) {
+            errors.push(ConfigError::Conflict {
+                fields: "annotations.output.streaming".to_string(),
+                message: format!(
+                    "streaming is only supported by the jsonl format, not {:?}",
+                    self.annotations.format
+                ),
+            });
+        }
+        if output.compression != Compression::None
+            && !matches!(self.annotations.format, Format::Jsonl)
+        {
+            errors.push(ConfigError::Conflict {
+                fields: "annotations.output.compression".to_string(),
+                message: format!(
+                    "compression is only supported by the jsonl format, not {:?}",
+                    self.annotations.format
+                ),
+            });
+        }
+        if let Some(per_label) = &output.per_label {
+            let mut groups: HashMap<&str, &str> = HashMap::new();
+            let mut names: Vec<&String> = per_label.groups.keys().collect();
+            names.sort();
+            for name in names {
+                for label in &per_label.groups[name] {
+                    if let Some(other) = groups.insert(label, name) {
+                        errors.push(ConfigError::Conflict {
+                            fields: "annotations.output.per_label.groups".to_string(),
+                            message: format!(
+                                "the label {label} is in the groups {other} and {name}"
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        if output.html.documents_per_page == Some(0) {
+            errors.push(ConfigError::Conflict {
+                fields: "annotations.output.html.documents_per_page".to_string(),
+                message: "must be greater than 0".to_string(),
+            });
+        }
+        if output
+            .min_confidence
+            .is_some_and(|confidence| !(0.0..=1.0).contains(&confidence))
+        {
+            errors.push(ConfigError::Conflict {
+                fields: "annotations.output.min_confidence".to_string(),
+                message: "must be between 0 and 1".to_string(),
+            });
+        }
+        if output.rare_label.is_some() && output.min_label_count.is_none() {
+            errors.push(ConfigError::Conflict {
+                fields: "annotations.output.rare_label".to_string(),
+                message: "only used when min_label_count is set".to_string(),
+            });
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
     pub fn summary(&self) {
         debug!("------------------------------");
         debug!("Configuration file summary    |");
         debug!("------------------------------");
         debug!("Texts input path: {}", self.texts.input.path);
+        if let Some(sample) = self.texts.input.sample {
+            debug!("Texts sample: {} (seed {})", sample, self.texts.input.seed);
+        }
+        if let Some(limit) = self.texts.input.limit {
+            debug!("Texts limit: {}", limit);
+        }
+        if let Some(dedupe) = self.texts.input.dedupe {
+            debug!("Texts dedupe: {}", dedupe);
+        }
+        if let Some(id) = self.texts.input.id {
+            debug!("Texts ids: {}", id);
+        }
         debug!("Texts filters: {}", self.texts.filters);
         debug!("Annotations output path: {}", self.annotations.output.path);
+        debug!(
+            "Annotations output documents: {}",
+            self.annotations.output.documents
+        );
+        debug!(
+            "Annotations output compression: {}",
+            self.annotations.output.compression
+        );
+        debug!(
+            "Annotations output stamp: {}",
+            self.annotations.output.stamp
+        );
+        debug!(
+            "Annotations output split by label: {}",
+            self.annotations.output.split_by_label
+        );
+        if let Some(per_label) = &self.annotations.output.per_label {
+            debug!(
+                "Annotations output per label, groups: {:?}",
+                per_label.groups
+            );
+        }
+        if let Some(streaming) = &self.annotations.output.streaming {
+            debug!(
+                "Annotations output streaming queue size: {}",
+                streaming.queue_size
+            );
+        }
         debug!("Entities input path: {}", self.entities.input.path);
+        debug!(
+            "Entities input format: {}",
+            self.entities
+                .input
+                .format
+                .clone()
+                .unwrap_or_else(|| EntitiesFormat::from_path(&self.entities.input.path))
+        );
         debug!("Entities filters: {}", self.entities.filters);
+        debug!("Labels priority: {:?}", self.labels.priority.order);
+        debug!(
+            "Labels aggregation: {} {:?}",
+            self.labels.aggregation.strategy, self.labels.aggregation.weights
+        );
+        for (label, precision) in &self.labels.precision {
+            debug!(
+                "High precision label: {} (title case {}, min mentions {})",
+                label, precision.title_case, precision.min_mentions
+            );
+        }
+        if let Some(num_threads) = self.performance.num_threads {
+            debug!("Threads: {}", num_threads);
+        }
+        for route in &self.routes {
+            debug!("Route: {:?} -> {}", route.when, route.entities.input.path);
+        }
+        if let Some(tags) = &self.tags {
+            debug!(
+                "Tags input path: {} (min matches {})",
+                tags.input.path, tags.min_matches
+            );
+        }
+        for rule in &self.rules {
+            debug!(
+                "Rule: {} ({}) {} require {:?} forbid {:?} within {} tokens",
+                rule.pattern, rule.label, rule.action, rule.require, rule.forbid, rule.window
+            );
+        }
+        for relation in &self.relations {
+            debug!(
+                "Relation: {} from {:?} to {:?} with {:?} within {} tokens",
+                relation.label, relation.head, relation.tail, relation.pattern, relation.window
+            );
+        }
         debug!(
             "Entities excludes path: {}",
             self.entities
@@ -263,3 +2008,60 @@ impl Config {
         );
     }
 }
+
+/// Merge the tables of `overlay` into `base`, the other values of `overlay`
+/// replace the ones of `base`
+/// Rebase the relative paths set in a configuration file, the ones resolved
+/// by `Config::resolve_paths`, and in its profiles, on a directory
+fn rebase_paths(value: &mut Value, directory: &Path) {
+    const PATHS: [&[&str]; 6] = [
+        &["texts", "input", "path"],
+        &["entities", "input", "path"],
+        &["annotations", "output", "path"],
+        &["entities", "excludes", "path"],
+        &["tags", "input", "path"],
+        &["checkpoint", "path"],
+    ];
+    let rebase = |settings: &mut Value, keys: &[&str]| {
+        let setting = keys
+            .iter()
+            .try_fold(settings, |value, key| value.get_mut(*key));
+        if let Some(Value::String(path)) = setting {
+            if !path.is_empty() && !Path::new(path.as_str()).is_absolute() {
+                *path = directory.join(path.as_str()).to_string_lossy().to_string();
+            }
+        }
+    };
+    let rebase_settings = |settings: &mut Value| {
+        for keys in PATHS {
+            rebase(settings, keys);
+        }
+        if let Some(Value::Array(routes)) = settings.get_mut("routes") {
+            for route in routes {
+                rebase(route, &["entities", "input", "path"]);
+            }
+        }
+    };
+    if let Some(Value::Table(profiles)) = value.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            rebase_settings(profile);
+        }
+    }
+    rebase_settings(value);
+}
+
+fn merge_toml(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
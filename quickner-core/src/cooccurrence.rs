@@ -0,0 +1,208 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{document::Document, quickner::Quickner, utils::tokenize};
+
+/// Two entities annotated in the same documents
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntityPair {
+    /// Surface form of the first entity, lowercased
+    pub source: String,
+    pub source_label: String,
+    /// Surface form of the second entity, lowercased
+    pub target: String,
+    pub target_label: String,
+    /// Number of documents in which both entities appear
+    pub count: usize,
+}
+
+/// Entities and labels annotated together, counted once per document
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Cooccurrences {
+    /// Maximum number of tokens between two spans counted together,
+    /// anywhere in the same document if not set
+    pub window: Option<usize>,
+    /// Pairs of different entities, most frequent first
+    pub entities: Vec<EntityPair>,
+    /// Number of documents in which two labels appear together, in both orders.
+    /// A label is paired with itself when two different entities have it.
+    pub labels: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+/// An entity, its surface form lowercased and its label
+type Key = (String, String);
+
+/// Pairs of entities and pairs of labels of a document
+type Pairs = (HashSet<(Key, Key)>, HashSet<(String, String)>);
+
+impl Cooccurrences {
+    /// The labels and the symmetric matrix of their counts, in the order of the labels
+    pub fn label_matrix(&self) -> (Vec<String>, Vec<Vec<usize>>) {
+        let labels: Vec<String> = self.labels.keys().cloned().collect();
+        let matrix = labels
+            .iter()
+            .map(|row| {
+                labels
+                    .iter()
+                    .map(|column| self.labels[row].get(column).copied().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        (labels, matrix)
+    }
+
+    /// The entities (surface form and label) and the symmetric matrix of their
+    /// counts, in the order of the entities sorted by label then surface form
+    pub fn entity_matrix(&self) -> (Vec<(String, String)>, Vec<Vec<usize>>) {
+        let entities: BTreeSet<(&str, &str)> = self
+            .entities
+            .iter()
+            .flat_map(|pair| {
+                [
+                    (pair.source_label.as_str(), pair.source.as_str()),
+                    (pair.target_label.as_str(), pair.target.as_str()),
+                ]
+            })
+            .collect();
+        let index: HashMap<(&str, &str), usize> = entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect();
+        let mut matrix = vec![vec![0; entities.len()]; entities.len()];
+        for pair in &self.entities {
+            let source = index[&(pair.source_label.as_str(), pair.source.as_str())];
+            let target = index[&(pair.target_label.as_str(), pair.target.as_str())];
+            matrix[source][target] = pair.count;
+            matrix[target][source] = pair.count;
+        }
+        let entities = entities
+            .into_iter()
+            .map(|(label, name)| (name.to_string(), label.to_string()))
+            .collect();
+        (entities, matrix)
+    }
+}
+
+/// Pairs of entities and of labels of a document, each pair once in a
+/// canonical order, with the spans within `window` tokens of each other
+fn document_pairs(document: &Document, window: Option<usize>) -> Pairs {
+    let tokens = tokenize(&document.text);
+    let chars: Vec<char> = document.text.chars().collect();
+    // Entity of each span with the indices of its first and last tokens
+    let spans: Vec<(Key, usize, usize)> = document
+        .label
+        .iter()
+        .filter(|(start, end, _)| start < end && *end <= chars.len())
+        .map(|(start, end, label)| {
+            let first = tokens.partition_point(|(_, token_end)| token_end <= start);
+            let last = tokens
+                .partition_point(|(token_start, _)| token_start < end)
+                .max(first + 1);
+            let surface: String = chars[*start..*end].iter().collect();
+            ((surface.to_lowercase(), label.clone()), first, last - 1)
+        })
+        .collect();
+    let mut entities = HashSet::new();
+    let mut labels = HashSet::new();
+    for (index, (a, a_first, a_last)) in spans.iter().enumerate() {
+        for (b, b_first, b_last) in &spans[index + 1..] {
+            if a == b {
+                continue;
+            }
+            // Tokens between the two spans, none when they overlap
+            let gap = match a_first <= b_first {
+                true => b_first.saturating_sub(a_last + 1),
+                false => a_first.saturating_sub(b_last + 1),
+            };
+            if window.is_some_and(|window| gap > window) {
+                continue;
+            }
+            let (first, second) = match a <= b {
+                true => (a, b),
+                false => (b, a),
+            };
+            entities.insert((first.clone(), second.clone()));
+            let (first, second) = match a.1 <= b.1 {
+                true => (&a.1, &b.1),
+                false => (&b.1, &a.1),
+            };
+            labels.insert((first.clone(), second.clone()));
+        }
+    }
+    (entities, labels)
+}
+
+impl Quickner {
+    /// Count the entities and labels annotated together, e.g. to build a graph of
+    /// related entities. Two spans are counted together when they are in the same
+    /// document, with at most `window` tokens between them if it is set. Each pair
+    /// is counted once per document, the entities are compared by their surface
+    /// form lowercased and their label.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let cooccurrences = quickner.cooccurrences(Some(10));
+    /// for pair in cooccurrences.entities.iter().take(10) {
+    ///     println!("{} - {}: {}", pair.source, pair.target, pair.count);
+    /// }
+    /// ```
+    pub fn cooccurrences(&self, window: Option<usize>) -> Cooccurrences {
+        let pairs: Vec<Pairs> = self.install(|| {
+            self.documents
+                .par_iter()
+                .map(|document| document_pairs(document, window))
+                .collect()
+        });
+        let mut entities: HashMap<(Key, Key), usize> = HashMap::new();
+        let mut labels: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for (document_entities, document_labels) in pairs {
+            for pair in document_entities {
+                *entities.entry(pair).or_default() += 1;
+            }
+            for (first, second) in document_labels {
+                *labels
+                    .entry(first.clone())
+                    .or_default()
+                    .entry(second.clone())
+                    .or_default() += 1;
+                if first != second {
+                    *labels.entry(second).or_default().entry(first).or_default() += 1;
+                }
+            }
+        }
+        let mut entities: Vec<EntityPair> = entities
+            .into_iter()
+            .map(|((source, target), count)| EntityPair {
+                source: source.0,
+                source_label: source.1,
+                target: target.0,
+                target_label: target.1,
+                count,
+            })
+            .collect();
+        entities.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| (&a.source, &a.source_label).cmp(&(&b.source, &b.source_label)))
+                .then_with(|| (&a.target, &a.target_label).cmp(&(&b.target, &b.target_label)))
+        });
+        Cooccurrences {
+            window,
+            entities,
+            labels,
+        }
+    }
+}
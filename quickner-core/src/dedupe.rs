@@ -0,0 +1,240 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::{HashMap, HashSet};
+
+use log::info;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    quickner::Quickner,
+    utils::{fnv1a, fold_diacritics, splitmix64},
+};
+
+/// Number of words of a shingle
+const SHINGLE_SIZE: usize = 3;
+/// Number of hash functions of a MinHash signature
+const PERMUTATIONS: usize = 128;
+
+/// Documents found to be duplicates of the same document
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DuplicateCluster {
+    /// Id of the first document of the cluster, kept by `dedupe`
+    pub kept: String,
+    /// Ids of the duplicates with their similarity to the kept document,
+    /// 1 for the same text once normalized
+    pub duplicates: Vec<(String, f64)>,
+}
+
+/// Normalized form of a text: the hash of its lowercased words without
+/// diacritics, and the sorted hashes of its shingles of consecutive words
+struct Shingles {
+    key: u64,
+    hashes: Vec<u64>,
+}
+
+impl Shingles {
+    fn new(text: &str) -> Self {
+        let lowercase = text.to_lowercase();
+        let folded = fold_diacritics(&lowercase);
+        let words: Vec<&str> = folded
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let size = SHINGLE_SIZE.min(words.len()).max(1);
+        let mut hashes: Vec<u64> = words
+            .windows(size)
+            .map(|shingle| fnv1a(&shingle.join(" ")))
+            .collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        Shingles {
+            key: fnv1a(&words.join(" ")),
+            hashes,
+        }
+    }
+
+    /// Jaccard similarity of the shingles of two texts
+    fn similarity(&self, other: &Shingles) -> f64 {
+        if self.hashes.is_empty() || other.hashes.is_empty() {
+            return 0.0;
+        }
+        let (mut i, mut j, mut shared) = (0, 0, 0);
+        while i < self.hashes.len() && j < other.hashes.len() {
+            match self.hashes[i].cmp(&other.hashes[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        shared as f64 / (self.hashes.len() + other.hashes.len() - shared) as f64
+    }
+
+    /// Keys of the bands of the MinHash signature, texts sharing a band are
+    /// compared. With `rows` hashes per band, a pair is likely to share a band
+    /// when its similarity is above about (rows / PERMUTATIONS)^(1 / rows).
+    fn bands(&self, rows: usize) -> Vec<(usize, u64)> {
+        let signature: Vec<u64> = (0..PERMUTATIONS as u64)
+            .map(|permutation| {
+                let seed = splitmix64(permutation + 1);
+                self.hashes
+                    .iter()
+                    .map(|hash| splitmix64(hash ^ seed))
+                    .min()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+        signature
+            .chunks(rows)
+            .enumerate()
+            .map(|(band, rows)| {
+                (
+                    band,
+                    rows.iter().fold(0, |key, hash| splitmix64(key ^ hash)),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Most hashes per band that still make the pairs at the threshold candidates
+fn rows_per_band(threshold: f64) -> usize {
+    [16, 8, 4, 2]
+        .into_iter()
+        .find(|&rows| {
+            let bands = (PERMUTATIONS / rows) as f64;
+            (1.0 / bands).powf(1.0 / rows as f64) <= threshold - 0.15
+        })
+        .unwrap_or(1)
+}
+
+impl Quickner {
+    /// Find the exact and near-duplicate documents. Texts are compared once
+    /// normalized (lowercased, without diacritics, punctuation and extra spaces):
+    /// the same normalized text is an exact duplicate, and below a `threshold` of 1
+    /// the Jaccard similarity of their shingles of 3 words is compared to the
+    /// threshold. Candidate pairs are found with MinHash signatures, so the corpus
+    /// is not compared pair by pair.
+    ///
+    /// A document is a duplicate of the first document similar to it,
+    /// the clusters are in the order of the documents.
+    pub fn duplicates(&self, threshold: f64) -> Vec<DuplicateCluster> {
+        self.duplicate_clusters(&self.duplicate_indices(threshold))
+    }
+
+    /// Remove the duplicates found by [`Quickner::duplicates`], the first document
+    /// of each cluster is kept. The order of the documents is kept and the indexes
+    /// are rebuilt. Returns the clusters of the documents removed.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let clusters = quickner.dedupe(0.9);
+    /// println!("{} documents kept with duplicates", clusters.len());
+    /// ```
+    pub fn dedupe(&mut self, threshold: f64) -> Vec<DuplicateCluster> {
+        let indices = self.duplicate_indices(threshold);
+        let clusters = self.duplicate_clusters(&indices);
+        let duplicates: HashSet<usize> = indices
+            .into_iter()
+            .flat_map(|(_, duplicates)| duplicates.into_iter().map(|(index, _)| index))
+            .collect();
+        if duplicates.is_empty() {
+            return clusters;
+        }
+        let snapshot = self.history_snapshot();
+        let total = self.documents.len();
+        let mut index = 0;
+        self.documents.retain(|_| {
+            index += 1;
+            !duplicates.contains(&(index - 1))
+        });
+        info!(
+            "{} of {} documents removed as duplicates (similarity of at least {})",
+            total - self.documents.len(),
+            total,
+            threshold
+        );
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("dedupe", snapshot);
+        clusters
+    }
+
+    fn duplicate_clusters(&self, indices: &[(usize, Vec<(usize, f64)>)]) -> Vec<DuplicateCluster> {
+        indices
+            .iter()
+            .map(|(kept, duplicates)| DuplicateCluster {
+                kept: self.documents[*kept].id.clone(),
+                duplicates: duplicates
+                    .iter()
+                    .map(|(index, similarity)| (self.documents[*index].id.clone(), *similarity))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Clusters of duplicates as the index of the kept document and the
+    /// indices of its duplicates with their similarity
+    fn duplicate_indices(&self, threshold: f64) -> Vec<(usize, Vec<(usize, f64)>)> {
+        let shingles: Vec<Shingles> = self.install(|| {
+            self.documents
+                .par_iter()
+                .map(|document| Shingles::new(&document.text))
+                .collect()
+        });
+        let near = threshold < 1.0;
+        let rows = rows_per_band(threshold);
+        let bands: Vec<Vec<(usize, u64)>> = match near {
+            true => self.install(|| shingles.par_iter().map(|text| text.bands(rows)).collect()),
+            false => Vec::new(),
+        };
+        let mut clusters: Vec<(usize, Vec<(usize, f64)>)> = Vec::new();
+        // Normalized texts and bands of the kept documents, to their cluster
+        let mut exact: HashMap<u64, usize> = HashMap::new();
+        let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+        for (index, text) in shingles.iter().enumerate() {
+            if let Some(&cluster) = exact.get(&text.key) {
+                clusters[cluster].1.push((index, 1.0));
+                continue;
+            }
+            if near {
+                let similar = bands[index]
+                    .iter()
+                    .filter_map(|band| buckets.get(band))
+                    .flatten()
+                    .copied()
+                    .collect::<HashSet<usize>>()
+                    .into_iter()
+                    .filter_map(|cluster| {
+                        let similarity = text.similarity(&shingles[clusters[cluster].0]);
+                        (similarity >= threshold).then_some((cluster, similarity))
+                    })
+                    .min_by_key(|(cluster, _)| *cluster);
+                if let Some((cluster, similarity)) = similar {
+                    clusters[cluster].1.push((index, similarity));
+                    continue;
+                }
+                for band in &bands[index] {
+                    buckets.entry(*band).or_default().push(clusters.len());
+                }
+            }
+            exact.insert(text.key, clusters.len());
+            clusters.push((index, Vec::new()));
+        }
+        clusters.retain(|(_, duplicates)| !duplicates.is_empty());
+        clusters
+    }
+}
@@ -0,0 +1,361 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use log::info;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    quickner::Quickner,
+    utils::{long_path, tokenize},
+};
+
+/// Lowercase words kept inside a title-case sequence, "Bank of America"
+const CONNECTORS: [&str; 14] = [
+    "of", "and", "the", "de", "du", "des", "del", "la", "le", "da", "di", "van", "von", "der",
+];
+
+/// Number of context words given with a candidate
+const CONTEXT_WORDS: usize = 5;
+
+/// Options of the entity discovery
+#[derive(Clone, Debug)]
+pub struct DiscoverOptions {
+    /// Minimum number of occurrences of a candidate in the documents
+    pub min_frequency: usize,
+    /// Maximum number of words of a candidate, longer sequences are ignored
+    pub max_words: usize,
+    /// Number of words on each side of an occurrence making its context
+    pub window: usize,
+    /// Maximum number of candidates returned, the best ranked ones
+    pub max_candidates: usize,
+}
+
+impl Default for DiscoverOptions {
+    fn default() -> Self {
+        DiscoverOptions {
+            min_frequency: 3,
+            max_words: 4,
+            window: 5,
+            max_candidates: 100,
+        }
+    }
+}
+
+/// A title-case sequence of the documents missing from the gazetteer,
+/// to be reviewed before adding it to the entities
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct EntityCandidate {
+    /// The most frequent surface form of the candidate
+    pub name: String,
+    /// Label whose annotated spans have the contexts most similar to the
+    /// contexts of the candidate, None if the documents have no spans
+    pub label: Option<String>,
+    /// Number of occurrences of the candidate outside the annotated spans
+    pub frequency: usize,
+    /// Cosine similarity between the contexts of the candidate and of the label
+    pub score: f32,
+    /// The most specific words around the occurrences of the candidate
+    pub context: Vec<String>,
+}
+
+/// Words of a text with their character offsets
+struct Words {
+    words: Vec<(String, usize, usize)>,
+    /// Whether a word starts a sentence
+    starts: Vec<bool>,
+    /// Whether only spaces, a hyphen or an apostrophe separate a word from the next one
+    joined: Vec<bool>,
+}
+
+impl Words {
+    fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let tokens = tokenize(text);
+        let gap = |from: usize, to: usize| chars[from..to].iter().collect::<String>();
+        let mut words = Vec::with_capacity(tokens.len());
+        let mut starts = Vec::with_capacity(tokens.len());
+        let mut joined = Vec::with_capacity(tokens.len());
+        for (index, (start, end)) in tokens.iter().enumerate() {
+            words.push((chars[*start..*end].iter().collect(), *start, *end));
+            let previous = index.checked_sub(1).map(|index| tokens[index].1);
+            starts.push(
+                previous
+                    .is_none_or(|previous| gap(previous, *start).contains(['.', '!', '?', '\n'])),
+            );
+            joined.push(
+                tokens.get(index + 1).is_some_and(|(next, _)| {
+                    matches!(gap(*end, *next).trim(), "" | "-" | "'" | "’")
+                }),
+            );
+        }
+        Words {
+            words,
+            starts,
+            joined,
+        }
+    }
+
+    fn capitalized(&self, index: usize) -> bool {
+        self.words[index].0.starts_with(char::is_uppercase)
+    }
+
+    /// Lowercased words within `window` words of the words from `first` to `last`
+    fn context(&self, first: usize, last: usize, window: usize) -> Vec<String> {
+        let before = first.saturating_sub(window)..first;
+        let after = last + 1..(last + 1 + window).min(self.words.len());
+        before
+            .chain(after)
+            .map(|index| self.words[index].0.to_lowercase())
+            .collect()
+    }
+
+    /// Title-case sequences as the indices of their first and last words: capitalized
+    /// words separated by spaces, hyphens or apostrophes, with connectors inside.
+    /// A capitalized word starting a sentence is dropped if it is also written in
+    /// lowercase in the corpus, e.g. "The" or "Yesterday".
+    fn sequences(&self, lowercase: &HashSet<String>) -> Vec<(usize, usize)> {
+        let mut sequences = Vec::new();
+        let mut index = 0;
+        while index < self.words.len() {
+            if !self.capitalized(index) {
+                index += 1;
+                continue;
+            }
+            let mut first = index;
+            let mut last = index;
+            let mut next = index;
+            while self.joined[next] && next + 1 < self.words.len() {
+                next += 1;
+                if self.capitalized(next) {
+                    last = next;
+                } else if !CONNECTORS.contains(&self.words[next].0.as_str()) {
+                    break;
+                }
+            }
+            index = last + 1;
+            if self.starts[first] && lowercase.contains(&self.words[first].0.to_lowercase()) {
+                first += 1;
+                while first <= last && !self.capitalized(first) {
+                    first += 1;
+                }
+            }
+            if first <= last {
+                sequences.push((first, last));
+            }
+        }
+        sequences
+    }
+}
+
+/// Sparse vector of context words weighted by their inverse document frequency
+type Profile = HashMap<String, f32>;
+
+fn add_context(profile: &mut Profile, context: &[String], idf: &HashMap<String, f32>) {
+    for word in context {
+        *profile.entry(word.clone()).or_default() += idf.get(word).copied().unwrap_or_default();
+    }
+}
+
+fn cosine(a: &Profile, b: &Profile) -> f32 {
+    let norm = |profile: &Profile| profile.values().map(|x| x * x).sum::<f32>().sqrt();
+    let (norm_a, norm_b) = (norm(a), norm(b));
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    let dot: f32 = a
+        .iter()
+        .filter_map(|(word, x)| b.get(word).map(|y| x * y))
+        .sum();
+    dot / (norm_a * norm_b)
+}
+
+/// Occurrences of a candidate, by surface form, and their contexts
+#[derive(Default)]
+struct Occurrences {
+    forms: HashMap<String, usize>,
+    frequency: usize,
+    profile: Profile,
+}
+
+/// Write the candidates as CSV with the columns name, label, frequency, score and
+/// context (words separated by `|`). The name and label columns can be copied to
+/// the entities file once reviewed.
+pub fn write_candidates<W: Write>(
+    candidates: &[EntityCandidate],
+    writer: W,
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["name", "label", "frequency", "score", "context"])?;
+    for candidate in candidates {
+        writer.write_record([
+            candidate.name.as_str(),
+            candidate.label.as_deref().unwrap_or_default(),
+            &candidate.frequency.to_string(),
+            &format!("{:.3}", candidate.score),
+            &candidate.context.join("|"),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Save the candidates to a CSV file, see `write_candidates`
+pub fn candidates_to_csv(candidates: &[EntityCandidate], path: &str) -> Result<(), csv::Error> {
+    write_candidates(candidates, std::fs::File::create(long_path(path))?)
+}
+
+impl Quickner {
+    /// Propose new gazetteer entries from the documents: the frequent title-case
+    /// sequences ("Bank of America", "OpenAI") that are not entities and not
+    /// annotated. The words around the occurrences of each candidate are compared
+    /// to the words around the annotated spans of each label (weighted by their
+    /// inverse document frequency), so a candidate gets the label used in the most
+    /// similar contexts. The candidates are ranked by this similarity and their
+    /// frequency, best first, and are meant to be reviewed, e.g. saved with
+    /// [`candidates_to_csv`], before adding them to the entities file.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{DiscoverOptions, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// for candidate in quickner.discover(&DiscoverOptions::default()) {
+    ///     println!("{} {:?} ({})", candidate.name, candidate.label, candidate.frequency);
+    /// }
+    /// ```
+    pub fn discover(&self, options: &DiscoverOptions) -> Vec<EntityCandidate> {
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        let normalize = |name: &str| match case_sensitive {
+            true => name.to_string(),
+            false => name.to_lowercase(),
+        };
+        let known: HashSet<String> = self
+            .entities
+            .iter()
+            .map(|entity| normalize(&entity.name))
+            .collect();
+        let words: Vec<Words> = self.install(|| {
+            self.documents
+                .par_iter()
+                .map(|document| Words::new(&document.text))
+                .collect()
+        });
+        // Words written in lowercase, and the number of documents of each word
+        let mut lowercase: HashSet<String> = HashSet::new();
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for document in &words {
+            let mut seen: HashSet<String> = HashSet::new();
+            for (word, _, _) in &document.words {
+                if word.starts_with(char::is_lowercase) {
+                    lowercase.insert(word.clone());
+                }
+                seen.insert(word.to_lowercase());
+            }
+            for word in seen {
+                *frequencies.entry(word).or_default() += 1;
+            }
+        }
+        let total = self.documents.len() as f32;
+        let idf: HashMap<String, f32> = frequencies
+            .into_iter()
+            .map(|(word, count)| (word, (total / count as f32).ln()))
+            .collect();
+        let mut labels: HashMap<&str, Profile> = HashMap::new();
+        let mut candidates: HashMap<String, Occurrences> = HashMap::new();
+        for (document, words) in self.documents.iter().zip(&words) {
+            for (start, end, label) in &document.label {
+                let covered: Vec<usize> = (0..words.words.len())
+                    .filter(|&index| words.words[index].1 < *end && *start < words.words[index].2)
+                    .collect();
+                if let (Some(first), Some(last)) = (covered.first(), covered.last()) {
+                    let context = words.context(*first, *last, options.window);
+                    add_context(labels.entry(label).or_default(), &context, &idf);
+                }
+            }
+            for (first, last) in words.sequences(&lowercase) {
+                if last - first + 1 > options.max_words {
+                    continue;
+                }
+                let (start, end) = (words.words[first].1, words.words[last].2);
+                let annotated = document
+                    .label
+                    .iter()
+                    .any(|(span_start, span_end, _)| start < *span_end && *span_start < end);
+                let name: String = document
+                    .text
+                    .chars()
+                    .skip(start)
+                    .take(end - start)
+                    .collect();
+                if annotated || name.chars().count() < 2 || known.contains(&normalize(&name)) {
+                    continue;
+                }
+                let occurrences = candidates.entry(normalize(&name)).or_default();
+                occurrences.frequency += 1;
+                add_context(
+                    &mut occurrences.profile,
+                    &words.context(first, last, options.window),
+                    &idf,
+                );
+                *occurrences.forms.entry(name).or_default() += 1;
+            }
+        }
+        let mut found: Vec<EntityCandidate> = self.install(|| {
+            candidates
+                .into_par_iter()
+                .filter(|(_, occurrences)| occurrences.frequency >= options.min_frequency)
+                .map(|(_, occurrences)| {
+                    let name = occurrences
+                        .forms
+                        .iter()
+                        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                        .map(|(name, _)| name.clone())
+                        .unwrap_or_default();
+                    let best = labels
+                        .iter()
+                        .map(|(label, profile)| (*label, cosine(&occurrences.profile, profile)))
+                        .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)));
+                    let mut context: Vec<(&String, &f32)> = occurrences.profile.iter().collect();
+                    context.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+                    EntityCandidate {
+                        name,
+                        label: best.map(|(label, _)| label.to_string()),
+                        frequency: occurrences.frequency,
+                        score: best.map(|(_, score)| score).unwrap_or_default(),
+                        context: context
+                            .into_iter()
+                            .take(CONTEXT_WORDS)
+                            .map(|(word, _)| word.clone())
+                            .collect(),
+                    }
+                })
+                .collect()
+        });
+        // The similarity counts more than the frequency, which grows without bound
+        let rank =
+            |candidate: &EntityCandidate| candidate.score * (1.0 + candidate.frequency as f32).ln();
+        found.sort_by(|a, b| {
+            rank(b)
+                .total_cmp(&rank(a))
+                .then_with(|| b.frequency.cmp(&a.frequency))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        found.truncate(options.max_candidates);
+        info!(
+            "{} entity candidates found in {} documents",
+            found.len(),
+            self.documents.len()
+        );
+        found
+    }
+}
@@ -1,12 +1,22 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{Display, Formatter},
+    str::FromStr,
+    sync::Arc,
+};
 
-use aho_corasick::AhoCorasick;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use utils::hash_string;
 
-use crate::entity::Entity;
-use crate::quickner::Quickner;
-use crate::utils;
+use crate::config::LabelDisplay;
+use crate::entity::{Entity, EntityMeta};
+use crate::matcher::Matcher;
+use crate::relation::Relation;
+use crate::span::{Span, SpanSource};
+use crate::utils::{
+    self, colorize, escape_html, label_display, preview_end, preview_length, TermColor,
+};
 /// An annotation is a text with a set of entities
 ///
 /// This object is used to hold the text and the
@@ -14,8 +24,83 @@ use crate::utils;
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Document {
     pub id: String,
-    pub text: String,
+    /// Text of the document, shared with the clones of the document
+    pub text: Arc<str>,
+    /// Spans (start, end, label), the offsets are in characters (as the indices
+    /// of a Python string) and the end is exclusive. Formats with byte offsets
+    /// are converted when they are read and written, see [`utils::char_to_byte`]
     pub label: Vec<(usize, usize, String)>,
+    /// Metadata of the document, e.g. the extra columns of the texts file
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, String>,
+    /// Metadata of the entities matched by the spans, see [`Entity`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entity_meta: Vec<EntityMeta>,
+    /// Provenance of the spans: the annotator, the entity matched and the
+    /// confidence, see [`Span`]. The spans without one have an unknown source
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub span_meta: Vec<Span>,
+    /// Relations between the spans, see [`Relation`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations: Vec<Relation>,
+    /// Tags of the document as a whole, e.g. its topics, sorted, see `tag_documents`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether the spans were checked by a human, see [`DocumentStatus`]
+    #[serde(default, skip_serializing_if = "DocumentStatus::is_machine")]
+    pub status: DocumentStatus,
+    /// Fields of an imported JSONL record that are not fields of a document,
+    /// e.g. the fields added by another tool, written back unchanged
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Who annotated a document. The spans of a verified document, corrected or
+/// gold, are kept when it is annotated again, see `verified` under `[matching]`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentStatus {
+    /// Annotated by quickner only
+    #[default]
+    Machine,
+    /// Annotated by quickner then corrected by a human
+    Corrected,
+    /// Annotated or reviewed by a human, the reference of the evaluations
+    Gold,
+}
+
+impl DocumentStatus {
+    fn is_machine(&self) -> bool {
+        *self == DocumentStatus::Machine
+    }
+
+    /// Whether a human checked the spans
+    pub fn is_verified(&self) -> bool {
+        !self.is_machine()
+    }
+}
+
+impl Display for DocumentStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentStatus::Machine => write!(f, "machine"),
+            DocumentStatus::Corrected => write!(f, "corrected"),
+            DocumentStatus::Gold => write!(f, "gold"),
+        }
+    }
+}
+
+impl FromStr for DocumentStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.to_lowercase().as_str() {
+            "machine" => Ok(DocumentStatus::Machine),
+            "corrected" => Ok(DocumentStatus::Corrected),
+            "gold" => Ok(DocumentStatus::Gold),
+            _ => Err(format!("Unknown document status: {status}")),
+        }
+    }
 }
 
 impl PartialEq for Document {
@@ -37,14 +122,248 @@ impl Document {
         let id = hash_string(text.as_str());
         Document {
             id,
-            text,
+            text: text.into(),
             label: Vec::new(),
+            meta: HashMap::new(),
+            entity_meta: Vec::new(),
+            span_meta: Vec::new(),
+            relations: Vec::new(),
+            tags: Vec::new(),
+            status: DocumentStatus::Machine,
+            extra: Map::new(),
         }
     }
 
     pub fn new(text: String, label: Vec<(usize, usize, String)>) -> Self {
         let id = hash_string(text.as_str());
-        Self { id, text, label }
+        Self {
+            id,
+            text: text.into(),
+            label,
+            meta: HashMap::new(),
+            entity_meta: Vec::new(),
+            span_meta: Vec::new(),
+            relations: Vec::new(),
+            tags: Vec::new(),
+            status: DocumentStatus::Machine,
+            extra: Map::new(),
+        }
+    }
+
+    /// Create a document from spans with byte offsets, converted to characters
+    /// # Examples
+    /// ```
+    /// use quickner::Document;
+    /// let document = Document::from_byte_spans("Café in Paris".to_string(), vec![(9, 14, "LOC".to_string())]);
+    /// assert_eq!(document.label, vec![(8, 13, "LOC".to_string())]);
+    /// ```
+    pub fn from_byte_spans(text: String, spans: Vec<(usize, usize, String)>) -> Self {
+        let label = spans
+            .into_iter()
+            .map(|(start, end, label)| {
+                let (start, end) = utils::byte_to_char(&text, start, end);
+                (start, end, label)
+            })
+            .collect();
+        Document::new(text, label)
+    }
+
+    /// Spans of the document with byte offsets, to slice the text in Rust
+    pub fn byte_spans(&self) -> Vec<(usize, usize, String)> {
+        self.label
+            .iter()
+            .map(|(start, end, label)| {
+                let (start, end) = utils::char_to_byte(&self.text, *start, *end);
+                (start, end, label.clone())
+            })
+            .collect()
+    }
+
+    /// Tokens of the text with their character offsets and BIO tag, to train
+    /// token classification models, e.g. ("Rust", 0, 4, "B-PL"). The tokens are
+    /// the runs of alphanumeric characters and each other non-space character,
+    /// split at the bounds of the spans so every span covers whole tokens.
+    /// Of overlapping spans, the first one is kept, the longest if they start together.
+    /// # Examples
+    /// ```
+    /// use quickner::Document;
+    /// let document = Document::new("New York, USA".to_string(), vec![(0, 8, "LOC".to_string())]);
+    /// let tags: Vec<String> = document.tokens().into_iter().map(|token| token.3).collect();
+    /// assert_eq!(tags, vec!["B-LOC", "I-LOC", "O", "O"]);
+    /// ```
+    pub fn tokens(&self) -> Vec<(String, usize, usize, String)> {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut spans: Vec<&(usize, usize, String)> = self
+            .label
+            .iter()
+            .filter(|span| span.0 < span.1 && span.1 <= chars.len())
+            .collect();
+        spans.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        let mut kept: Vec<&(usize, usize, String)> = Vec::new();
+        for span in spans {
+            if kept.last().is_none_or(|last| last.1 <= span.0) {
+                kept.push(span);
+            }
+        }
+        let bounds: HashSet<usize> = kept.iter().flat_map(|span| [span.0, span.1]).collect();
+        let mut offsets = Vec::new();
+        let mut start = None;
+        for (index, c) in chars.iter().enumerate() {
+            if let Some(begin) = start {
+                if !c.is_alphanumeric() || bounds.contains(&index) {
+                    offsets.push((begin, index));
+                    start = None;
+                }
+            }
+            if c.is_alphanumeric() {
+                start.get_or_insert(index);
+            } else if !c.is_whitespace() {
+                offsets.push((index, index + 1));
+            }
+        }
+        if let Some(begin) = start {
+            offsets.push((begin, chars.len()));
+        }
+        let mut spans = kept.into_iter().peekable();
+        offsets
+            .into_iter()
+            .map(|(start, end)| {
+                while spans.next_if(|span| span.1 <= start).is_some() {}
+                let tag = match spans.peek() {
+                    Some(span) if span.0 == start => format!("B-{}", span.2),
+                    Some(span) if span.0 < start => format!("I-{}", span.2),
+                    _ => "O".to_string(),
+                };
+                (chars[start..end].iter().collect(), start, end, tag)
+            })
+            .collect()
+    }
+
+    /// Spans of the document with their provenance, in the order of the spans
+    /// # Examples
+    /// ```
+    /// use quickner::{Document, Entity, SpanSource};
+    /// let mut document = Document::from_string("I love rust".to_string());
+    /// let rust = Entity { name: "rust".to_string(), label: "PL".to_string(), ..Default::default() };
+    /// document.annotate(vec![rust], false);
+    /// let spans = document.spans();
+    /// assert_eq!(spans[0].source, SpanSource::Gazetteer);
+    /// assert_eq!(spans[0].entity_name.as_deref(), Some("rust"));
+    /// ```
+    pub fn spans(&self) -> Vec<Span> {
+        self.label
+            .iter()
+            .map(|span| {
+                self.span_meta
+                    .iter()
+                    .find(|meta| meta.is(span))
+                    .cloned()
+                    .unwrap_or_else(|| Span::from(span.clone()))
+            })
+            .collect()
+    }
+
+    /// Add a span with its provenance, e.g. found by another annotator.
+    /// The provenance of a span already in the document is replaced
+    pub fn add_span(&mut self, span: Span) {
+        let tuple = span.to_tuple();
+        if !self.label.contains(&tuple) {
+            self.label.push(tuple);
+        }
+        self.span_meta.retain(|meta| !meta.is(&span.to_tuple()));
+        self.span_meta.push(span);
+    }
+
+    /// Keep the spans for which `keep` returns true, with their provenance and
+    /// the metadata of their entity, returns the number of spans removed
+    /// # Examples
+    /// ```
+    /// use quickner::{Document, Span, SpanSource};
+    /// let mut document = Document::from_string("I love rust".to_string());
+    /// document.add_span(Span {
+    ///     confidence: Some(0.2),
+    ///     ..Span::new(&(7, 11, "PL".to_string()), SpanSource::Regex)
+    /// });
+    /// assert_eq!(document.retain_spans(|span| span.confidence.unwrap_or(1.0) >= 0.5), 1);
+    /// assert!(document.label.is_empty());
+    /// ```
+    pub fn retain_spans<F: FnMut(&Span) -> bool>(&mut self, mut keep: F) -> usize {
+        let kept: Vec<bool> = self.spans().iter().map(&mut keep).collect();
+        let before = self.label.len();
+        let mut kept = kept.into_iter();
+        self.label.retain(|_| kept.next().unwrap_or(true));
+        if self.label.len() == before {
+            return 0;
+        }
+        self.retain_span_meta();
+        self.retain_relations();
+        let labels = &self.label;
+        self.entity_meta.retain(|meta| {
+            labels
+                .iter()
+                .any(|span| span.0 == meta.start && span.1 == meta.end && span.2 == meta.label)
+        });
+        before - self.label.len()
+    }
+
+    /// Record the spans without provenance as read from an annotation file
+    pub(crate) fn record_imported(&mut self) {
+        for span in &self.label {
+            if !self.span_meta.iter().any(|meta| meta.is(span)) {
+                self.span_meta.push(Span::new(span, SpanSource::Import));
+            }
+        }
+    }
+
+    /// Rename the label `from` of the spans to `to`, with their provenance, the
+    /// metadata of their entity and their relations, returns the number of spans renamed
+    /// # Examples
+    /// ```
+    /// use quickner::Document;
+    /// let mut document = Document::from_string("I love rust".to_string());
+    /// document.label.push((7, 11, "PL".to_string()));
+    /// assert_eq!(document.rename_label("PL", "LANGUAGE"), 1);
+    /// assert_eq!(document.label, vec![(7, 11, "LANGUAGE".to_string())]);
+    /// ```
+    pub fn rename_label(&mut self, from: &str, to: &str) -> usize {
+        let rename = |label: &mut String| {
+            if label == from {
+                *label = to.to_string();
+            }
+        };
+        let renamed = self.label.iter().filter(|span| span.2 == from).count();
+        if renamed == 0 || from == to {
+            return 0;
+        }
+        self.label.iter_mut().for_each(|span| rename(&mut span.2));
+        self.span_meta
+            .iter_mut()
+            .for_each(|span| rename(&mut span.label));
+        self.entity_meta
+            .iter_mut()
+            .for_each(|meta| rename(&mut meta.label));
+        for relation in self.relations.iter_mut() {
+            rename(&mut relation.head_span.2);
+            rename(&mut relation.tail_span.2);
+        }
+        // A span may already have the new label
+        self.set_unique_labels();
+        renamed
+    }
+
+    /// Drop the provenance of the spans no longer in the document
+    pub(crate) fn retain_span_meta(&mut self) {
+        let labels = &self.label;
+        self.span_meta
+            .retain(|meta| labels.iter().any(|span| meta.is(span)));
+    }
+
+    /// Drop the relations of the spans no longer in the document
+    pub(crate) fn retain_relations(&mut self) {
+        let labels = &self.label;
+        self.relations.retain(|relation| {
+            labels.contains(&relation.head_span) && labels.contains(&relation.tail_span)
+        });
     }
 
     /// Annotate text given a set of entities
@@ -62,29 +381,153 @@ impl Document {
     /// annotation.annotate(entities);
     /// assert_eq!(annotation.label, vec![(0, 4, "Language".to_string()), (23, 30, "Organization".to_string())]);
     /// ```
-    pub fn annotate(&mut self, mut entities: Vec<Entity>, case_sensitive: bool) {
-        if !case_sensitive {
-            self.text = self.text.to_lowercase();
-            entities
-                .iter_mut()
-                .for_each(|e| e.name = e.name.to_lowercase());
-        }
-        let patterns = entities
-            .iter()
-            .map(|entity| entity.name.as_str())
-            .collect::<Vec<&str>>();
-        let aho_corasick = Arc::new(AhoCorasick::new(patterns));
-        let label = Quickner::find_index_using_aho_corasick(&self.text, &aho_corasick, &entities);
-        match label {
-            Some(label) => self.label.extend(label),
-            None => self.label.extend(Vec::new()),
-        }
+    pub fn annotate(&mut self, entities: Vec<Entity>, case_sensitive: bool) {
+        Matcher::new(&entities, case_sensitive).annotate(self);
         // Remove duplicate labels based on start and end index and label
         self.label
             .sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
         self.set_unique_labels();
     }
 
+    /// Text of the document with the spans colorized, one color per label,
+    /// and followed by their label, e.g. "\x1b[31mRust\x1b[0m[PL] is fast".
+    /// The colors and names of the labels are those set by `set_label_display`.
+    /// Overlapping spans are all rendered: the text covered by several spans has
+    /// the color of the innermost one and each label is printed where its span ends,
+    /// e.g. "New York[LOC] City[ORG]". Spans whose offsets are out of the text are skipped.
+    /// Long texts are cut after `length` characters (the preview length if None,
+    /// 0 for the full text), a span crossing the cut is kept whole.
+    pub fn render_ansi(&self, length: Option<usize>) -> String {
+        let (segments, left) = self.segments(length, &label_colors(&[self]));
+        let display = label_display();
+        let mut rendered = String::new();
+        for segment in segments {
+            match segment.spans.last() {
+                Some(span) => rendered.push_str(&colorize(segment.text, span.color)),
+                None => rendered.push_str(segment.text),
+            }
+            for label in &segment.closing {
+                rendered.push_str(&format!("[{}]", display_name(&display, label)));
+            }
+        }
+        if let Some(left) = left {
+            rendered.push_str(&format!("… (+{left} chars)"));
+        }
+        rendered
+    }
+
+    /// Text of the document as an HTML fragment, the spans in `<mark>` elements
+    /// with a background color per label and followed by their label, e.g.
+    /// `<mark class="entity" data-labels="PL" style="background: #ffb3b3">Rust<span class="label">PL</span></mark> is fast`.
+    /// The text is escaped. Like `render_ansi`, overlapping spans are all rendered:
+    /// the text covered by several spans is in its own `<mark>` listing their labels,
+    /// with the color of the innermost one. Long texts are cut the same way.
+    pub fn render_html(&self, length: Option<usize>) -> String {
+        self.render_html_with_colors(length, &label_colors(&[self]))
+    }
+
+    /// Same as `render_html` with the colors of the labels given,
+    /// e.g. to keep the same colors across the documents of a page
+    pub(crate) fn render_html_with_colors(
+        &self,
+        length: Option<usize>,
+        colors: &HashMap<&str, TermColor>,
+    ) -> String {
+        let (segments, left) = self.segments(length, colors);
+        let display = label_display();
+        let mut rendered = String::new();
+        for segment in segments {
+            let span = match segment.spans.last() {
+                Some(span) => span,
+                None => {
+                    rendered.push_str(&escape_html(segment.text));
+                    continue;
+                }
+            };
+            let labels: Vec<&str> = segment.spans.iter().map(|span| span.label).collect();
+            rendered.push_str(&format!(
+                "<mark class=\"entity\" data-labels=\"{}\" style=\"background: {}\">{}",
+                escape_html(&labels.join(" ")),
+                span.color.html(),
+                escape_html(segment.text)
+            ));
+            for label in &segment.closing {
+                rendered.push_str(&format!(
+                    "<span class=\"label\">{}</span>",
+                    escape_html(display_name(&display, label))
+                ));
+            }
+            rendered.push_str("</mark>");
+        }
+        if let Some(left) = left {
+            rendered.push_str(&format!("… (+{left} chars)"));
+        }
+        rendered
+    }
+
+    /// Cut the text at the bounds of the spans, see `render_ansi`. Returns the
+    /// segments of the text kept and the number of characters cut after them
+    fn segments<'a>(
+        &'a self,
+        length: Option<usize>,
+        colors: &HashMap<&str, TermColor>,
+    ) -> (Vec<Segment<'a>>, Option<usize>) {
+        let text = self.text.as_ref();
+        // Byte index of every character, and of the end of the text
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let cut = preview_end(text, length.unwrap_or_else(preview_length));
+        let mut spans: Vec<(usize, usize, &str)> = self
+            .label
+            .iter()
+            .filter_map(|(start, end, label)| {
+                Some((*offsets.get(*start)?, *offsets.get(*end)?, label.as_str()))
+            })
+            .filter(|(start, end, _)| start < end)
+            .filter(|(start, _, _)| cut.is_none_or(|cut| *start < cut))
+            .collect();
+        // Outer spans first, so the last span covering a segment is the innermost
+        spans.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)));
+        let end = match cut {
+            Some(cut) => spans.iter().map(|span| span.1).fold(cut, usize::max),
+            None => text.len(),
+        };
+        let mut bounds: Vec<usize> = spans
+            .iter()
+            .flat_map(|span| [span.0, span.1])
+            .chain([0, end])
+            .collect();
+        bounds.sort();
+        bounds.dedup();
+        let mut segments = Vec::new();
+        for bound in bounds.windows(2) {
+            let (start, end) = (bound[0], bound[1]);
+            segments.push(Segment {
+                text: &text[start..end],
+                spans: spans
+                    .iter()
+                    .filter(|span| span.0 <= start && end <= span.1)
+                    .map(|span| SegmentSpan {
+                        label: span.2,
+                        color: colors.get(span.2).copied().unwrap_or(TermColor::ALL[0]),
+                    })
+                    .collect(),
+                // Inner spans end first
+                closing: spans
+                    .iter()
+                    .rev()
+                    .filter(|span| span.1 == end)
+                    .map(|span| span.2)
+                    .collect(),
+            });
+        }
+        let left = cut.map(|_| text[end..].chars().count());
+        (segments, left)
+    }
+
     fn set_unique_labels(&mut self) {
         let mut labels: Vec<(usize, usize, String)> = Vec::new();
         for (start, end, label) in &self.label {
@@ -95,3 +538,50 @@ impl Document {
         self.label = labels;
     }
 }
+
+/// Color of each label of the documents, the color set by `set_label_display`,
+/// or the colors in turn in the order of their first span
+pub(crate) fn label_colors<'a>(documents: &[&'a Document]) -> HashMap<&'a str, TermColor> {
+    let display = label_display();
+    let mut colors: HashMap<&str, TermColor> = HashMap::new();
+    let mut next = 0;
+    for (_, _, label) in documents.iter().flat_map(|document| &document.label) {
+        if colors.contains_key(label.as_str()) {
+            continue;
+        }
+        let color = match display.get(label).and_then(|display| display.color) {
+            Some(color) => color,
+            None => {
+                next += 1;
+                TermColor::ALL[(next - 1) % TermColor::ALL.len()]
+            }
+        };
+        colors.insert(label.as_str(), color);
+    }
+    colors
+}
+
+/// Name of a label in the renderings, the name set by `set_label_display` or the label
+pub(crate) fn display_name<'a>(
+    display: &'a BTreeMap<String, LabelDisplay>,
+    label: &'a str,
+) -> &'a str {
+    display
+        .get(label)
+        .and_then(|display| display.name.as_deref())
+        .unwrap_or(label)
+}
+
+/// A part of the text of a document between two bounds of its spans
+struct Segment<'a> {
+    text: &'a str,
+    /// The spans covering the segment, the innermost one last
+    spans: Vec<SegmentSpan<'a>>,
+    /// Labels of the spans ending with the segment, the innermost one first
+    closing: Vec<&'a str>,
+}
+
+struct SegmentSpan<'a> {
+    label: &'a str,
+    color: TermColor,
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::hash::Hash;
 
 use serde::{Deserialize, Serialize};
@@ -6,10 +7,26 @@ use serde::{Deserialize, Serialize};
 ///
 /// This object is used to hold the label used to
 /// annotate the text.
-#[derive(Eq, Serialize, Deserialize, Clone, Debug)]
+/// The optional `id`, `canonical` and `attributes` fields are carried
+/// to the annotated spans, e.g. to reference a knowledge base.
+#[derive(Eq, Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Entity {
     pub name: String,
     pub label: String,
+    /// Id of the entity, e.g. in a knowledge base, read from `id` or `kb_id`
+    #[serde(default, alias = "kb_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, String>,
+}
+
+impl Entity {
+    /// Whether the entity carries an id, a canonical form or attributes
+    pub fn has_meta(&self) -> bool {
+        self.id.is_some() || self.canonical.is_some() || !self.attributes.is_empty()
+    }
 }
 
 impl PartialEq for Entity {
@@ -24,3 +41,51 @@ impl Hash for Entity {
         self.label.hash(state);
     }
 }
+
+/// An entry of the excludes file, a text that is never annotated,
+/// with any label or only with the label of the entry.
+///
+/// The spans found inside an occurrence of the text are dropped, e.g.
+/// "apple pie" excluded as ORG keeps "apple" from being an ORG there.
+#[derive(Eq, Hash, PartialEq, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Exclude {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Exclude {
+    /// Whether the exclude applies to the spans of the label
+    pub fn applies_to(&self, label: &str) -> bool {
+        self.label.as_deref().is_none_or(|scope| scope == label)
+    }
+}
+
+/// Metadata of the entity matched by an annotated span
+///
+/// It is written under the `entity_meta` key of the JSONL output.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntityMeta {
+    pub start: usize,
+    pub end: usize,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub attributes: HashMap<String, String>,
+}
+
+impl EntityMeta {
+    pub fn new(span: &(usize, usize, String), entity: &Entity) -> Self {
+        EntityMeta {
+            start: span.0,
+            end: span.1,
+            label: span.2.to_string(),
+            id: entity.id.clone(),
+            canonical: entity.canonical.clone(),
+            attributes: entity.attributes.clone(),
+        }
+    }
+}
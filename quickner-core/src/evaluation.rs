@@ -0,0 +1,115 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::quickner::Quickner;
+
+/// Precision, recall and F1 score computed from exact span matches
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl Metrics {
+    fn from_counts(true_positives: usize, false_positives: usize, false_negatives: usize) -> Self {
+        let ratio = |numerator: usize, denominator: usize| match denominator {
+            0 => 0.0,
+            _ => numerator as f64 / denominator as f64,
+        };
+        let precision = ratio(true_positives, true_positives + false_positives);
+        let recall = ratio(true_positives, true_positives + false_negatives);
+        let f1 = if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        };
+        Metrics {
+            precision,
+            recall,
+            f1,
+            true_positives,
+            false_positives,
+            false_negatives,
+        }
+    }
+}
+
+/// Evaluation of the annotations against a reference corpus
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Evaluation {
+    /// Micro-averaged metrics over every label
+    pub overall: Metrics,
+    /// Metrics per label
+    pub labels: BTreeMap<String, Metrics>,
+    /// Number of documents found in both corpora
+    pub documents: usize,
+}
+
+impl Quickner {
+    /// Evaluate the annotations against a reference (gold) corpus.
+    /// Documents are matched by id and spans must match exactly
+    /// (same start, end and label).
+    /// Evaluating two annotators against each other gives their agreement.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let predictions = Quickner::from_jsonl("./predictions.jsonl");
+    /// let gold = Quickner::from_jsonl("./gold.jsonl");
+    /// let evaluation = predictions.evaluate(&gold);
+    /// println!("F1: {:.2}", evaluation.overall.f1);
+    /// ```
+    pub fn evaluate(&self, reference: &Quickner) -> Evaluation {
+        let references: HashMap<&str, HashSet<&(usize, usize, String)>> = reference
+            .documents
+            .iter()
+            .map(|document| (document.id.as_str(), document.label.iter().collect()))
+            .collect();
+        // label -> (true positives, false positives, false negatives)
+        let mut counts: BTreeMap<String, (usize, usize, usize)> = BTreeMap::new();
+        let mut documents = 0;
+        for document in &self.documents {
+            let expected = match references.get(document.id.as_str()) {
+                Some(expected) => expected,
+                None => continue,
+            };
+            documents += 1;
+            let predicted: HashSet<&(usize, usize, String)> = document.label.iter().collect();
+            for span in &predicted {
+                let count = counts.entry(span.2.to_string()).or_default();
+                if expected.contains(span) {
+                    count.0 += 1;
+                } else {
+                    count.1 += 1;
+                }
+            }
+            for span in expected.difference(&predicted) {
+                counts.entry(span.2.to_string()).or_default().2 += 1;
+            }
+        }
+        let (true_positives, false_positives, false_negatives) =
+            counts.values().fold((0, 0, 0), |acc, count| {
+                (acc.0 + count.0, acc.1 + count.1, acc.2 + count.2)
+            });
+        Evaluation {
+            overall: Metrics::from_counts(true_positives, false_positives, false_negatives),
+            labels: counts
+                .into_iter()
+                .map(|(label, count)| (label, Metrics::from_counts(count.0, count.1, count.2)))
+                .collect(),
+            documents,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "pdf")]
+use std::{io::ErrorKind, process::Command};
+
+use crate::{quickner::Quickner, utils::long_path};
+
+/// Reads the text of the files of a directory of texts, by extension.
+/// The extractors are called from the thread reading the texts, before the
+/// documents enter the pipeline.
+pub trait TextExtractor: Send + Sync {
+    /// Extensions of the files read, lowercase and without the dot, e.g. ["pdf"]
+    fn extensions(&self) -> Vec<String>;
+    /// Text of the file
+    fn extract(&self, path: &Path) -> Result<String, Error>;
+}
+
+/// The .txt files, read as UTF-8, the default extractor
+pub struct PlainTextExtractor;
+
+impl TextExtractor for PlainTextExtractor {
+    fn extensions(&self) -> Vec<String> {
+        vec!["txt".to_string()]
+    }
+
+    fn extract(&self, path: &Path) -> Result<String, Error> {
+        fs::read_to_string(long_path(path))
+    }
+}
+
+/// The .pdf files, read with `pdftotext` from poppler, which must be in the PATH
+/// unless another command is given. The text layer is read, a scanned PDF
+/// without one gives an empty text.
+#[cfg(feature = "pdf")]
+pub struct PdfExtractor {
+    /// Command converting a PDF to text, called as `command file.pdf -`
+    pub command: String,
+}
+
+#[cfg(feature = "pdf")]
+impl Default for PdfExtractor {
+    fn default() -> Self {
+        PdfExtractor {
+            command: "pdftotext".to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "pdf")]
+impl TextExtractor for PdfExtractor {
+    fn extensions(&self) -> Vec<String> {
+        vec!["pdf".to_string()]
+    }
+
+    fn extract(&self, path: &Path) -> Result<String, Error> {
+        let output = Command::new(&self.command)
+            .args(["-enc", "UTF-8"])
+            .arg(long_path(path))
+            .arg("-")
+            .output()
+            .map_err(|e| Error::new(e.kind(), format!("Unable to run {}: {}", self.command, e)))?;
+        if !output.status.success() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} failed on {}: {}",
+                    self.command,
+                    path.display(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        // pdftotext ends each page with a form feed
+        Ok(String::from_utf8_lossy(&output.stdout).replace('\x0c', "\n"))
+    }
+}
+
+/// Extractors of the texts of a directory, the last one registered for an
+/// extension is used. The .txt files are read by default, and the .pdf files
+/// with the `pdf` feature.
+#[derive(Clone)]
+pub struct Extractors {
+    extractors: Vec<Arc<dyn TextExtractor>>,
+}
+
+impl Default for Extractors {
+    fn default() -> Self {
+        let mut extractors = Extractors {
+            extractors: Vec::new(),
+        };
+        extractors.register(Arc::new(PlainTextExtractor));
+        #[cfg(feature = "pdf")]
+        extractors.register(Arc::new(PdfExtractor::default()));
+        extractors
+    }
+}
+
+impl Extractors {
+    /// Read the files of its extensions with the extractor, instead of the
+    /// extractor registered before for one of them
+    pub fn register(&mut self, extractor: Arc<dyn TextExtractor>) {
+        self.extractors.push(extractor);
+    }
+
+    /// The extractor of a file, by its extension in any case, None if no
+    /// extractor reads it
+    pub fn get(&self, path: &Path) -> Option<&dyn TextExtractor> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.extractors
+            .iter()
+            .rev()
+            .find(|extractor| extractor.extensions().contains(&extension))
+            .map(|extractor| extractor.as_ref())
+    }
+
+    /// The files of a directory read by an extractor, sorted by name
+    pub fn files(&self, directory: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut files: Vec<PathBuf> = fs::read_dir(long_path(directory))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && self.get(path).is_some())
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl Quickner {
+    /// Read the files of the extensions of `extractor` in a directory of texts
+    /// with it, e.g. to read .docx or .html files, or the .pdf files with
+    /// another tool. It replaces the extractor registered before for one of them.
+    /// # Example
+    /// ```
+    /// use std::{io::Error, path::Path};
+    /// use quickner::{Quickner, TextExtractor};
+    ///
+    /// struct Markdown;
+    ///
+    /// impl TextExtractor for Markdown {
+    ///     fn extensions(&self) -> Vec<String> {
+    ///         vec!["md".to_string()]
+    ///     }
+    ///
+    ///     fn extract(&self, path: &Path) -> Result<String, Error> {
+    ///         let text = std::fs::read_to_string(path)?;
+    ///         Ok(text.lines().map(|line| line.trim_start_matches('#').trim()).collect::<Vec<_>>().join("\n"))
+    ///     }
+    /// }
+    ///
+    /// let mut quickner = Quickner::default();
+    /// quickner.register_extractor(Markdown);
+    /// assert!(quickner.extractors.get(Path::new("notes.MD")).is_some());
+    /// ```
+    pub fn register_extractor(&mut self, extractor: impl TextExtractor + 'static) {
+        self.extractors.register(Arc::new(extractor));
+    }
+}
@@ -0,0 +1,135 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    fmt::{Display, Formatter},
+    fs,
+};
+
+use log::info;
+
+use crate::{config::Output, document::Document, quickner::Quickner, utils::long_path};
+
+/// Error of the check of the labels of a run against a frozen label set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelSetError {
+    /// The frozen label set cannot be read or written
+    Io { path: String, message: String },
+    /// The labels of the run differ from the frozen label set
+    Changed {
+        path: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+impl Display for LabelSetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelSetError::Io { path, message } => {
+                write!(f, "Unable to use the frozen labels {path}: {message}")
+            }
+            LabelSetError::Changed {
+                path,
+                added,
+                removed,
+            } => {
+                write!(f, "The labels differ from the frozen labels {path}:")?;
+                if !added.is_empty() {
+                    write!(f, " new labels {}", added.join(", "))?;
+                }
+                if !added.is_empty() && !removed.is_empty() {
+                    write!(f, ",")?;
+                }
+                if !removed.is_empty() {
+                    write!(f, " missing labels {}", removed.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LabelSetError {}
+
+impl Quickner {
+    /// Labels of the annotations as they are exported,
+    /// after the labels below `min_label_count` are dropped or remapped
+    pub fn exported_labels(&self) -> BTreeSet<String> {
+        exported_labels(&self.documents, &self.config.annotations.output)
+    }
+
+    /// Check the labels of the annotations against a frozen label set, a JSON
+    /// list of labels, to keep the label inventory of the downstream models.
+    /// The file is created with the current labels if it does not exist.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// quickner.check_frozen_labels("./labels.json").unwrap();
+    /// ```
+    /// # Errors
+    /// Returns an error if labels are added or missing,
+    /// or if the file cannot be read or written
+    pub fn check_frozen_labels(&self, path: &str) -> Result<(), LabelSetError> {
+        check_frozen_labels(&self.exported_labels(), path)
+    }
+}
+
+pub(crate) fn exported_labels(documents: &[Document], output: &Output) -> BTreeSet<String> {
+    let labels = |documents: &[Document]| -> BTreeSet<String> {
+        documents
+            .iter()
+            .flat_map(|document| document.label.iter().map(|(_, _, label)| label.clone()))
+            .collect()
+    };
+    let documents = match output.min_confidence {
+        Some(min_confidence) => Cow::Owned(Output::apply_min_confidence(documents, min_confidence)),
+        None => Cow::Borrowed(documents),
+    };
+    match output.min_label_count {
+        Some(min_label_count) => labels(&output.apply_label_floor(&documents, min_label_count)),
+        None => labels(&documents),
+    }
+}
+
+pub(crate) fn check_frozen_labels(
+    labels: &BTreeSet<String>,
+    path: &str,
+) -> Result<(), LabelSetError> {
+    let io = |e: &dyn std::error::Error| LabelSetError::Io {
+        path: path.to_string(),
+        message: e.to_string(),
+    };
+    if !long_path(path).exists() {
+        let content = serde_json::to_string_pretty(labels).map_err(|e| io(&e))?;
+        fs::write(long_path(path), content).map_err(|e| io(&e))?;
+        info!("{} labels frozen to {}", labels.len(), path);
+        return Ok(());
+    }
+    let content = fs::read_to_string(long_path(path)).map_err(|e| io(&e))?;
+    let frozen: BTreeSet<String> = serde_json::from_str(&content).map_err(|e| io(&e))?;
+    let added: Vec<String> = labels.difference(&frozen).cloned().collect();
+    let removed: Vec<String> = frozen.difference(labels).cloned().collect();
+    if added.is_empty() && removed.is_empty() {
+        info!(
+            "The {} labels match the frozen labels {}",
+            labels.len(),
+            path
+        );
+        return Ok(());
+    }
+    Err(LabelSetError::Changed {
+        path: path.to_string(),
+        added,
+        removed,
+    })
+}
@@ -0,0 +1,155 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::HashMap;
+
+use crate::{
+    config::Fuzzy,
+    entity::Entity,
+    utils::{levenshtein, tokenize},
+};
+
+/// A match of a pattern with edits, in character offsets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FuzzyMatch {
+    pub start: usize,
+    pub end: usize,
+    /// Index of the pattern matched
+    pub pattern: usize,
+    pub distance: usize,
+}
+
+/// Patterns long enough to be matched with edits, indexed by their trigrams.
+/// Each edit changes at most 3 trigrams, so a text `max_edits` away from a
+/// pattern shares most of its trigrams, only those are compared to the text.
+pub(crate) struct FuzzyIndex {
+    max_edits: usize,
+    /// Index of the pattern, its characters and its number of distinct trigrams
+    patterns: Vec<(usize, Vec<char>, usize)>,
+    trigrams: HashMap<[char; 3], Vec<usize>>,
+    /// Most words of a pattern, a match can have one more
+    max_words: usize,
+    /// Length of the shortest and longest patterns
+    lengths: (usize, usize),
+}
+
+fn trigrams(chars: &[char]) -> Vec<[char; 3]> {
+    let mut trigrams: Vec<[char; 3]> = chars
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams
+}
+
+impl FuzzyIndex {
+    /// Index of the patterns of at least `min_len` characters,
+    /// `None` if there is none
+    pub(crate) fn new(patterns: &[Entity], fuzzy: &Fuzzy) -> Option<Self> {
+        let mut index = FuzzyIndex {
+            max_edits: fuzzy.max_edits,
+            patterns: Vec::new(),
+            trigrams: HashMap::new(),
+            max_words: 0,
+            lengths: (usize::MAX, 0),
+        };
+        for (pattern, entity) in patterns.iter().enumerate() {
+            let chars: Vec<char> = entity.name.chars().collect();
+            if chars.len() < fuzzy.min_len.max(3) {
+                continue;
+            }
+            let trigrams = trigrams(&chars);
+            for trigram in &trigrams {
+                index
+                    .trigrams
+                    .entry(*trigram)
+                    .or_default()
+                    .push(index.patterns.len());
+            }
+            index.max_words = index.max_words.max(tokenize(&entity.name).len());
+            index.lengths = (
+                index.lengths.0.min(chars.len()),
+                index.lengths.1.max(chars.len()),
+            );
+            index.patterns.push((pattern, chars, trigrams.len()));
+        }
+        (!index.patterns.is_empty()).then_some(index)
+    }
+
+    /// Matches of the patterns with 1 to `max_edits` edits in the text, on whole
+    /// words. The closest match is kept when matches overlap, then the longest,
+    /// and every pattern matching the same words at the same distance is kept.
+    pub(crate) fn find(&self, text: &str) -> Vec<FuzzyMatch> {
+        let chars: Vec<char> = text.chars().collect();
+        let tokens = tokenize(text);
+        let mut found = Vec::new();
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for (first, (start, _)) in tokens.iter().enumerate() {
+            for (_, end) in tokens[first..].iter().take(self.max_words + 1) {
+                let window = &chars[*start..*end];
+                if window.len() > self.lengths.1 + self.max_edits {
+                    break;
+                }
+                if window.len() + self.max_edits < self.lengths.0 {
+                    continue;
+                }
+                shared.clear();
+                for trigram in trigrams(window) {
+                    for pattern in self.trigrams.get(&trigram).into_iter().flatten() {
+                        *shared.entry(*pattern).or_default() += 1;
+                    }
+                }
+                let mut best: Vec<FuzzyMatch> = Vec::new();
+                for (pattern, count) in &shared {
+                    let (index, name, total) = &self.patterns[*pattern];
+                    if *count + 3 * self.max_edits < *total {
+                        continue;
+                    }
+                    let distance = match levenshtein(window, name, self.max_edits) {
+                        Some(0) | None => continue,
+                        Some(distance) => distance,
+                    };
+                    if best.first().is_some_and(|other| other.distance < distance) {
+                        continue;
+                    }
+                    if best.first().is_some_and(|other| other.distance > distance) {
+                        best.clear();
+                    }
+                    best.push(FuzzyMatch {
+                        start: *start,
+                        end: *end,
+                        pattern: *index,
+                        distance,
+                    });
+                }
+                found.extend(best);
+            }
+        }
+        found.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| (b.end - b.start).cmp(&(a.end - a.start)))
+                .then_with(|| a.start.cmp(&b.start))
+                .then_with(|| a.pattern.cmp(&b.pattern))
+        });
+        let mut kept: Vec<FuzzyMatch> = Vec::new();
+        for candidate in found {
+            let overlaps = kept.iter().any(|other| {
+                other.start < candidate.end
+                    && candidate.start < other.end
+                    && (other.start, other.end) != (candidate.start, candidate.end)
+            });
+            if !overlaps {
+                kept.push(candidate);
+            }
+        }
+        kept.sort_by_key(|found| (found.start, found.end, found.pattern));
+        kept
+    }
+}
@@ -0,0 +1,105 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{collections::HashSet, error::Error};
+
+use log::info;
+
+use crate::{config::EntitiesFormat, entity::Entity, quickner::Quickner};
+
+/// Names and labels of entities, the key of the set operations
+fn keys(entities: &[Entity]) -> HashSet<(&str, &str)> {
+    entities
+        .iter()
+        .map(|entity| (entity.name.as_str(), entity.label.as_str()))
+        .collect()
+}
+
+impl Quickner {
+    /// The entities of the instance, then the entities of `other` with a name
+    /// and label not among them, e.g. to combine the gazetteers extracted from
+    /// two corpora. The entities are compared on their name and label, with
+    /// their case, the first one is kept with its metadata.
+    /// # Example
+    /// ```
+    /// use quickner::{Entity, Quickner};
+    /// let entity = |name: &str, label: &str| Entity {
+    ///     name: name.to_string(),
+    ///     label: label.to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let mut quickner = Quickner::default();
+    /// quickner.add_entity(entity("rust", "PL"));
+    /// quickner.add_entity(entity("mozilla", "ORG"));
+    /// let other = vec![entity("mozilla", "ORG"), entity("python", "PL")];
+    /// assert_eq!(quickner.entities_union(&other).len(), 3);
+    /// assert_eq!(quickner.entities_intersect(&other), vec![entity("mozilla", "ORG")]);
+    /// assert_eq!(quickner.entities_diff(&other), vec![entity("rust", "PL")]);
+    /// ```
+    pub fn entities_union(&self, other: &[Entity]) -> Vec<Entity> {
+        let mut seen = HashSet::new();
+        self.entities
+            .iter()
+            .chain(other)
+            .filter(|entity| seen.insert((entity.name.as_str(), entity.label.as_str())))
+            .cloned()
+            .collect()
+    }
+
+    /// The entities of the instance with a name and label among the entities
+    /// of `other`, in their order, see `entities_union`
+    pub fn entities_intersect(&self, other: &[Entity]) -> Vec<Entity> {
+        let other = keys(other);
+        self.entities
+            .iter()
+            .filter(|entity| other.contains(&(entity.name.as_str(), entity.label.as_str())))
+            .cloned()
+            .collect()
+    }
+
+    /// The entities of the instance with a name and label not among the entities
+    /// of `other`, in their order, e.g. to audit what a new extraction lost,
+    /// see `entities_union`
+    pub fn entities_diff(&self, other: &[Entity]) -> Vec<Entity> {
+        let other = keys(other);
+        self.entities
+            .iter()
+            .filter(|entity| !other.contains(&(entity.name.as_str(), entity.label.as_str())))
+            .cloned()
+            .collect()
+    }
+
+    /// Save the entities to a file, in the format given or detected from the
+    /// extension of the path (csv by default), see `EntitiesFormat::write`.
+    /// The file can be used as the entities input of a configuration.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{EntitiesFormat, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// quickner.export_entities("./gazetteer.json", None).unwrap();
+    /// quickner.export_entities("./gazetteer", Some(EntitiesFormat::Txt)).unwrap();
+    /// ```
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn export_entities(
+        &self,
+        path: &str,
+        format: Option<EntitiesFormat>,
+    ) -> Result<(), Box<dyn Error>> {
+        let format = format.unwrap_or_else(|| EntitiesFormat::from_path(path));
+        format.write(path, &self.entities)?;
+        info!(
+            "{} entities saved to {} ({})",
+            self.entities.len(),
+            path,
+            format
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    document::Document,
+    quickner::Quickner,
+    utils::{format_rfc3339, long_path},
+};
+
+/// A document changed by an operation, as it was before and after it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DocumentChange {
+    /// Position of the document before the operation, after it for an added document
+    pub index: usize,
+    /// Position of a changed document after the operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_index: Option<usize>,
+    /// The document before the operation, None if it was added
+    pub before: Option<Document>,
+    /// The document after the operation, None if it was removed
+    pub after: Option<Document>,
+}
+
+/// An operation of the history, a line of the history file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// Number of the operation, from 1
+    pub seq: u64,
+    /// Time of the operation, RFC 3339 in UTC
+    pub time: String,
+    /// Name of the operation, e.g. "merge", "rename_label" or "undo"
+    pub operation: String,
+    pub documents_added: usize,
+    pub documents_removed: usize,
+    pub spans_added: usize,
+    pub spans_removed: usize,
+    /// Operations reverted by an undo
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undone: Vec<u64>,
+    /// The documents changed by the operation
+    pub changes: Vec<DocumentChange>,
+}
+
+/// Append-only history of the changes of the documents, a JSONL file with
+/// an entry per operation, see `Quickner::enable_history`
+#[derive(Clone, Debug)]
+pub struct History {
+    path: PathBuf,
+    /// Number of the next operation
+    next: u64,
+}
+
+impl History {
+    /// Open the history file, created if missing, the operations are numbered
+    /// after the last one already in the file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let next = History::read(&path)?
+            .last()
+            .map_or(1, |entry| entry.seq + 1);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(long_path(&path))?;
+        Ok(History { path, next })
+    }
+
+    /// Path of the history file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The operations of the history file, oldest first
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<HistoryEntry>, Error> {
+        let file = match fs::File::open(long_path(path.as_ref())) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(
+                serde_json::from_str(&line).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Append an operation to the history file, nothing is written without changes
+    fn append(
+        &mut self,
+        operation: &str,
+        changes: Vec<DocumentChange>,
+        undone: Vec<u64>,
+    ) -> Result<(), Error> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut entry = HistoryEntry {
+            seq: self.next,
+            time: format_rfc3339(SystemTime::now()),
+            operation: operation.to_string(),
+            documents_added: 0,
+            documents_removed: 0,
+            spans_added: 0,
+            spans_removed: 0,
+            undone,
+            changes: Vec::new(),
+        };
+        for change in &changes {
+            let before = change
+                .before
+                .as_ref()
+                .map_or(&[][..], |document| &document.label);
+            let after = change
+                .after
+                .as_ref()
+                .map_or(&[][..], |document| &document.label);
+            entry.documents_added += usize::from(change.before.is_none());
+            entry.documents_removed += usize::from(change.after.is_none());
+            entry.spans_added += after.iter().filter(|span| !before.contains(span)).count();
+            entry.spans_removed += before.iter().filter(|span| !after.contains(span)).count();
+        }
+        entry.changes = changes;
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(long_path(&self.path))?
+            .write_all(line.as_bytes())?;
+        self.next += 1;
+        Ok(())
+    }
+}
+
+/// Whether an operation changed a document
+fn changed(before: &Document, after: &Document) -> bool {
+    before.text != after.text
+        || before.label != after.label
+        || before.meta != after.meta
+        || before.span_meta != after.span_meta
+        || before.entity_meta != after.entity_meta
+        || before.relations != after.relations
+        || before.tags != after.tags
+        || before.status != after.status
+}
+
+/// The documents removed, changed and added between two versions of the documents.
+/// Documents sharing an id, e.g. duplicated texts, are matched in order:
+/// the k-th occurrence of an id before with the k-th one after.
+fn diff(before: &[Document], after: &[Document]) -> Vec<DocumentChange> {
+    let mut positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, document) in after.iter().enumerate() {
+        positions
+            .entry(document.id.as_str())
+            .or_default()
+            .push(index);
+    }
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    let mut matched = vec![false; after.len()];
+    let mut changes = Vec::new();
+    for (index, document) in before.iter().enumerate() {
+        let occurrence = occurrences.entry(document.id.as_str()).or_default();
+        let position = positions
+            .get(document.id.as_str())
+            .and_then(|positions| positions.get(*occurrence))
+            .copied();
+        *occurrence += 1;
+        match position {
+            Some(position) => {
+                matched[position] = true;
+                if changed(document, &after[position]) {
+                    changes.push(DocumentChange {
+                        index,
+                        after_index: Some(position),
+                        before: Some(document.clone()),
+                        after: Some(after[position].clone()),
+                    });
+                }
+            }
+            None => changes.push(DocumentChange {
+                index,
+                after_index: None,
+                before: Some(document.clone()),
+                after: None,
+            }),
+        }
+    }
+    for (index, document) in after.iter().enumerate() {
+        if !matched[index] {
+            changes.push(DocumentChange {
+                index,
+                after_index: None,
+                before: None,
+                after: Some(document.clone()),
+            });
+        }
+    }
+    changes
+}
+
+impl Quickner {
+    /// Keep an append-only history of the changes of the documents in a JSONL
+    /// file, e.g. annotations_history.jsonl, so that mistaken operations can be
+    /// reverted with `undo`. Each operation (adding documents, `annotate_incremental`,
+    /// `balance`, `dedupe`, `merge`, `filter_spans`, `rename_label`, `set_documents`,
+    /// `tag_documents`)
+    /// appends a line with its time, the number of documents and spans added and
+    /// removed, and the documents changed, before and after the operation.
+    /// An existing history is continued.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::from_jsonl("./annotations.jsonl");
+    /// quickner.enable_history("./annotations_history.jsonl").unwrap();
+    /// quickner.balance(100, 42);
+    /// quickner.undo(1).unwrap();
+    /// ```
+    /// # Errors
+    /// Returns an error if the history file cannot be read or created
+    pub fn enable_history(&mut self, path: &str) -> Result<(), Error> {
+        let history = History::open(path)?;
+        info!("History of the changes kept in {}", path);
+        self.history = Some(history);
+        Ok(())
+    }
+
+    /// Stop recording the changes, the history file is kept
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// The operations of the history, oldest first, empty if it is not enabled
+    /// # Errors
+    /// Returns an error if the history file cannot be read
+    pub fn read_history(&self) -> Result<Vec<HistoryEntry>, Error> {
+        match &self.history {
+            Some(history) => History::read(history.path()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Revert the last `n` operations of the history not reverted yet, latest
+    /// first. The undo is an operation of the history too, listing the
+    /// operations it reverted, and is not reverted by the next undos.
+    /// Returns the number of operations reverted, fewer than `n` if the
+    /// history has fewer.
+    /// # Errors
+    /// Returns an error if the history is not enabled or cannot be read or written
+    pub fn undo(&mut self, n: usize) -> Result<usize, Error> {
+        let entries = match &self.history {
+            Some(history) => History::read(history.path())?,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "The history is not enabled, see enable_history",
+                ))
+            }
+        };
+        let undone: HashSet<u64> = entries
+            .iter()
+            .flat_map(|entry| entry.undone.iter().copied())
+            .collect();
+        let reverted: Vec<&HistoryEntry> = entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.operation != "undo" && !undone.contains(&entry.seq))
+            .take(n)
+            .collect();
+        if reverted.is_empty() {
+            return Ok(0);
+        }
+        let before = self.documents.clone();
+        for entry in &reverted {
+            Quickner::revert(&mut self.documents, &entry.changes);
+        }
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        let seqs: Vec<u64> = reverted.iter().map(|entry| entry.seq).collect();
+        info!("Operations {:?} reverted", seqs);
+        if let Some(history) = &mut self.history {
+            history.append("undo", diff(&before, &self.documents), seqs)?;
+        }
+        Ok(reverted.len())
+    }
+
+    /// Put the documents back as they were before the changes of an operation.
+    /// The documents are found at their position after the operation, by id for
+    /// the changes recorded without it
+    fn revert(documents: &mut Vec<Document>, changes: &[DocumentChange]) {
+        let find = |documents: &[Document], index: Option<usize>, after: &Document| {
+            index
+                .filter(|&index| documents.get(index).is_some_and(|d| d.id == after.id))
+                .or_else(|| documents.iter().position(|d| d.id == after.id))
+        };
+        for change in changes {
+            if let (Some(before), Some(after)) = (&change.before, &change.after) {
+                if let Some(position) = find(documents, change.after_index, after) {
+                    documents[position] = before.clone();
+                }
+            }
+        }
+        let mut added: Vec<(usize, &Document)> = changes
+            .iter()
+            .filter(|change| change.before.is_none())
+            .filter_map(|change| Some((change.index, change.after.as_ref()?)))
+            .collect();
+        added.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+        for (index, after) in added {
+            if let Some(position) = find(documents, Some(index), after) {
+                documents.remove(position);
+            }
+        }
+        let mut removed: Vec<(usize, &Document)> = changes
+            .iter()
+            .filter(|change| change.after.is_none())
+            .filter_map(|change| Some((change.index, change.before.as_ref()?)))
+            .collect();
+        removed.sort_by_key(|(index, _)| *index);
+        for (index, document) in removed {
+            documents.insert(index.min(documents.len()), document.clone());
+        }
+    }
+
+    /// The documents before an operation, to record its changes with
+    /// `record_history`, None if the history is not enabled
+    pub(crate) fn history_snapshot(&self) -> Option<Vec<Document>> {
+        self.history.as_ref().map(|_| self.documents.clone())
+    }
+
+    /// Append the changes of the documents since `snapshot` to the history.
+    /// The operation is already done, a history that cannot be written is logged
+    pub(crate) fn record_history(&mut self, operation: &str, snapshot: Option<Vec<Document>>) {
+        if let Some(before) = snapshot {
+            let changes = diff(&before, &self.documents);
+            self.record_changes(operation, changes);
+        }
+    }
+
+    /// Append a document added at the end of the documents to the history
+    pub(crate) fn record_added(&mut self, operation: &str, document: Document) {
+        if self.history.is_some() {
+            let change = DocumentChange {
+                index: self.documents.len() - 1,
+                after_index: None,
+                before: None,
+                after: Some(document),
+            };
+            self.record_changes(operation, vec![change]);
+        }
+    }
+
+    /// Append the changes of an operation to the history, if it is enabled
+    fn record_changes(&mut self, operation: &str, changes: Vec<DocumentChange>) {
+        if let Some(history) = &mut self.history {
+            if let Err(e) = history.append(operation, changes, Vec::new()) {
+                error!(
+                    "Unable to write the history to {}: {}",
+                    history.path().display(),
+                    e
+                );
+            }
+        }
+    }
+}
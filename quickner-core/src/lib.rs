@@ -6,27 +6,38 @@
 //! You can use quickner to annotate a batch of texts.
 //!
 //! Provide a configuration file and a folder containing your texts:
-//! - a csv file containing the **texts** you want to annotate.
-//! - a csv file containing the **entities** you want to annotate.
+//! - a csv file (or a directory of .txt files) containing the **texts** you want to annotate.
+//! - a csv file containing the **entities** you want to annotate (tsv, json, toml and txt are also supported).
 //! - a csv file containing the **excludes** you want to exclude from the annotation.
 //!
 //! ## Configuration
 //!
-//! The configuration file is a toml file that contains the following fields:
+//! The configuration file is a toml file that contains the following fields,
+//! relative paths are resolved from the directory containing the configuration file:
 //! ```toml
+//! # include = ["shared/labels.toml"] # files of shared settings, read first, the settings of this file replace theirs
+//!
 //! [logging]
 //! level = "info" # level of logging (debug, info, warning, error, fatal)
+//! # format = "json"  # log lines as JSON objects, with the timings and counts of the stages of a run, "text" by default (--json-logs)
 //!
 //! [texts]
 //!
 //! [texts.input]
 //! filter = false     # if true, only texts in the filter list will be used
-//! path = "texts.csv" # path to the texts file
+//! path = "texts.csv" # path to the texts file, or a directory of .txt files, and .pdf files with the pdf feature (one document per file)
+//! # split = "paragraph" # if set, the .txt files are split into one document per paragraph (blank line separated)
+//! # split = "sentence"  # or the texts (and .txt files) into one document per sentence, see Split::Sentence
+//! # limit = 1000        # annotate only the first 1000 documents
+//! # sample = 0.1        # or a random 10% of the documents, the same for the same seed
+//! # dedupe = 0.9        # drop the near-duplicate documents (similarity from 0 to 1) before annotation
+//! # id = "sha256"       # ids of the documents: hash (default), sequential, uuid, sha256 or column (the id column)
+//! # seed = 42
 //!
 //! [texts.filters]
 //! accept_special_characters = ".,-" # list of special characters to accept in the text (if special_characters is true)
 //! alphanumeric = false              # if true, only strictly alphanumeric texts will be used
-//! case_sensitive = false            # if true, case sensitive search will be used
+//! case_sensitive = false            # unused, the search follows case_sensitive of [entities.filters]
 //! max_length = 1024                 # maximum length of the text
 //! min_length = 0                    # minimum length of the text
 //! numbers = false                   # if true, texts with numbers will not be used
@@ -34,16 +45,56 @@
 //! special_characters = false        # if true, texts with special characters will not be used
 //!
 //! [annotations]
-//! format = "spacy" # format of the output file (jsonl, spaCy, brat, conll)
+//! format = "spacy" # format of the output file (jsonl, spaCy, brat, conll, html, nif, tokens)
 //!
 //! [annotations.output]
 //! path = "annotations.jsonl" # path to the output file
+//! documents = "all"          # documents to export (all, annotated, unannotated)
+//! # min_label_count = 5      # labels with fewer spans are dropped from the export
+//! # rare_label = "MISC"      # if set, labels below min_label_count are remapped to this label instead
+//! # min_confidence = 0.5     # spans with a lower confidence are dropped from the export, fuzzy matches lose confidence per edit
+//! # frozen_labels = "labels.json" # the run fails if its labels differ from this JSON list, created by the first run
+//! # create_dirs = true       # create the missing directories of the output path (default: true)
+//! # compression = "gzip"     # compress the jsonl output, written to annotations.jsonl.gz (default: "none")
+//! # stamp = true             # write a provenance stamp: jsonl header record, brat note (default: false)
+//! # report = true            # write the run report next to the annotations (default: true)
+//! # split_by_label = true    # write one file per label, e.g. annotations_ORG.jsonl, see [annotations.output.per_label] (default: false)
+//!
+//! [annotations.output.fields]
+//! id = "id"       # name of the id field in the JSONL file
+//! text = "text"   # name of the text field in the JSONL file
+//! label = "label" # name of the label field in the JSONL file
+//!
+//! [annotations.output.json]
+//! pretty = false       # if true, JSON files (spaCy format) are pretty-printed
+//! indent = 2           # number of spaces used for indentation when pretty-printing
+//! ensure_ascii = false # if true, non-ASCII characters are escaped as \uXXXX
+//!
+//! # [annotations.output.html]
+//! # title = "Annotations"     # title of the HTML pages
+//! # documents_per_page = 500  # write numbered pages, annotations_1.html, annotations_2.html, ...
+//!
+//! # [annotations.output.nif]
+//! # base_uri = "http://example.org/quickner/"  # base URI of the documents and labels
+//! # kb_uri = "http://www.wikidata.org/entity/" # prefix of the entity ids which are not URIs
+//!
+//! # [annotations.output.per_label]       # write one file per label, e.g. annotations_ORG.jsonl
+//! # groups = { PEOPLE = ["PER", "PERSON"] } # labels written to the same file, annotations_PEOPLE.jsonl
+//!
+//! # [annotations.output.streaming]
+//! # queue_size = 1024   # serialized documents buffered before the workers wait for the writer (jsonl only)
+//!
+//! # [checkpoint]
+//! # every = 10000                   # annotated documents written to the checkpoint every 10000 documents
+//! # path = "annotations.checkpoint" # directory of the checkpoint, the output path followed by .checkpoint if not set
+//! # resume = false                  # skip the documents of the last checkpoint, also set by the --resume flag
 //!
 //! [entities]
 //!
 //! [entities.input]
 //! filter = true         # if true, only entities in the filter list will be used
 //! path = "entities.csv" # path to the entities file
+//! # format = "csv"      # format of the entities file (csv, tsv, json, toml, txt), detected from the extension if not set
 //! save = true           # if true, the entities found will be saved in the output file
 //!
 //! [entities.filters]
@@ -55,9 +106,67 @@
 //! numbers = false                  # if true, entities with numbers will not be used
 //! punctuation = false              # if true, entities with punctuation will not be used
 //! special_characters = true        # if true, entities with special characters will not be used
+//! # min_tokens = 1                 # minimum number of tokens (runs of letters and digits) of the entity
+//! # max_tokens = 5                 # maximum number of tokens of the entity
 //!
 //! [entities.excludes]
-//! # path = "excludes.csv" # path to entities to exclude from the search
+//! # path = "excludes.csv" # path to texts to exclude from the annotation, an optional `label` column scopes them to a label
+//!
+//! [labels.priority]
+//! # order = ["PRODUCT", "ORG"] # when several labels match the exact same span, only the first listed label is kept
+//!
+//! [labels.precision]
+//! # ORG = { title_case = true, min_mentions = 2 } # high precision label, a span is kept if its text is title-cased in the original text ("Apple", not "apple") or matched 2+ times in the document
+//!
+//! [labels.aggregation]
+//! # strategy = "vote" # label of a span given different labels by the annotators: "priority" (the label priority, default), "vote" (the most weighted votes of the sources) or "skip-conflicts" (the span is dropped)
+//! # weights = { import = 3.0, gazetteer = 1.0, fuzzy = 0.5 } # weight of the votes of each source (gazetteer, fuzzy, regex, rule, mention, import, unknown), 1 if not set
+//!
+//! [matching]
+//! fold_diacritics = false # if true, "Montreal" matches "Montréal", the spans keep the offsets of the original text
+//! match_plurals = false   # if true, the plural forms of the entities are matched too, "apple" matches "apples"
+//! language = "english"    # language of the plurals, "french" also matches "bureaux" and "journaux", "spanish" also matches "ciudades"
+//! abbreviations = false   # if true, the abbreviations defined in the texts, "World Health Organization (WHO)", become entities
+//! propagate_mentions = false # if true, the repeated mentions of an annotated span are annotated too, "Obama" after "Barack Obama"
+//! # fuzzy = { max_edits = 1, min_len = 6 } # optional, the entities of 6+ characters also match with 1 typo, "Mozila" matches "Mozilla"
+//! verified = "skip"       # the documents with a "corrected" or "gold" status are skipped, "append" adds the spans not overlapping theirs, "overwrite" annotates them as the others
+//!
+//! [performance]
+//! # num_threads = 4 # optional, threads of the parallel stages, one per core (the global pool of rayon) if not set
+//!
+//! # Documents whose metadata (extra columns of the texts file) match `when`
+//! # are annotated with the entities of the route, the first matching route wins
+//! # [[routes]]
+//! # when = { lang = "fr" }
+//! # [routes.entities.input]
+//! # path = "entities_fr.csv"
+//! # [routes.entities.filters] # optional, the [entities.filters] are used if not set
+//!
+//! # Documents tagged with the label of the keywords of a second dictionary found
+//! # in their text, written as "tags" in the JSONL output
+//! # [tags]
+//! # min_matches = 1 # matches of the keywords of a tag needed to tag a document
+//! # [tags.input]
+//! # path = "keywords.csv" # name,label columns, the label is the tag
+//! # [tags.filters] # optional, the [entities.filters] are used if not set
+//!
+//! # Context rules, evaluated after the dictionary matching on the spans of `pattern` with `label`
+//! # [[rules]]
+//! # pattern = "apple"
+//! # label = "ORG"
+//! # require = ["iphone", "ceo"] # one of the terms must appear within `window` tokens of the span
+//! # forbid = ["pie"]            # none of the terms may appear within `window` tokens of the span
+//! # window = 10
+//! # action = "accept"           # accept: keep the span only if the context matches, reject: drop it if it does
+//! #                             # redirect: change its label to `to` if it does
+//!
+//! # Relations proposed between the spans of `head` and `tail` (any label if not set)
+//! # [[relations]]
+//! # label = "CEO_OF"
+//! # head = "PERSON"
+//! # tail = "ORG"
+//! # pattern = "{head}, CEO of {tail}" # words around the spans, without it the spans within `window` tokens
+//! # window = 10
 //!
 //! ```
 //!
@@ -84,18 +193,99 @@
 //! entities.insert("Mozilla", "Organization");
 //! annotation.annotate(entities);
 //! ```
+mod abbreviations;
+mod balance;
+mod bench;
+mod cancel;
+mod checkpoint;
 mod config;
+mod cooccurrence;
+mod dedupe;
+mod discover;
 mod document;
 mod entity;
+mod evaluation;
+mod extract;
+mod frozen;
+mod fuzzy;
+mod gazetteer;
+mod history;
+mod linking;
+mod logging;
+mod matcher;
+mod mention;
+mod merge;
 mod models;
+mod performance;
+mod progress;
+mod project;
+mod prune;
+mod query;
 mod quickner;
+mod relation;
+mod report;
+mod roundtrip;
+#[cfg(feature = "server")]
+mod server;
+mod span;
+mod stats;
+#[cfg(feature = "sqlite")]
+mod storage;
+#[cfg(feature = "suggestions")]
+mod suggest;
+mod tags;
 mod utils;
+mod validate;
+mod watch;
+mod workspace;
 
+pub use crate::abbreviations::{find_abbreviations, Abbreviation};
+pub use crate::bench::{BenchOptions, BenchReport, BenchRun};
+pub use crate::cancel::{cancel, install_cancel_handler, is_cancelled, reset_cancel};
+pub use crate::checkpoint::CheckpointManifest;
 pub use crate::config::{
-    Annotations, Config, Entities, Excludes, Filters, Format, Input, Logging, Output, Texts,
+    AggregationStrategy, Annotations, Checkpoint, Compression, Config, ConfigError,
+    DocumentSelection, Entities, EntitiesFormat, Excludes, Fields, Filters, Format, Fuzzy,
+    HtmlOptions, IdStrategy, Input, JsonOptions, LabelAggregation, LabelDisplay, LabelPrecision,
+    LabelPriority, Labels, Language, LogFormat, Logging, Matching, NifOptions, Output, PerLabel,
+    Performance, RelationPattern, Route, RouteEntities, Rule, RuleAction, Split, Streaming, Tags,
+    Texts, VerifiedPolicy,
 };
-pub use crate::document::Document;
-pub use crate::entity::Entity;
-pub use crate::models::SpacyEntity;
+pub use crate::cooccurrence::{Cooccurrences, EntityPair};
+pub use crate::dedupe::DuplicateCluster;
+pub use crate::discover::{candidates_to_csv, write_candidates, DiscoverOptions, EntityCandidate};
+pub use crate::document::{Document, DocumentStatus};
+pub use crate::entity::{Entity, EntityMeta, Exclude};
+pub use crate::evaluation::{Evaluation, Metrics};
+#[cfg(feature = "pdf")]
+pub use crate::extract::PdfExtractor;
+pub use crate::extract::{Extractors, PlainTextExtractor, TextExtractor};
+pub use crate::frozen::LabelSetError;
+pub use crate::history::{DocumentChange, History, HistoryEntry};
+pub use crate::linking::{DocumentLinks, EntityLink, LinkCandidate};
+pub use crate::logging::init_logging;
+pub use crate::matcher::Matcher;
+pub use crate::merge::{MergeReport, MergeStrategy};
+pub use crate::models::{EntityRulerPattern, RulerPattern, RulerToken, SpacyEntity, Stamp};
+pub use crate::progress::{IndicatifProgress, NoopProgress, ProgressReporter};
+pub use crate::prune::{PruneReport, PrunedEntity};
+pub use crate::query::{Query, TokenIndex};
 pub use crate::quickner::Quickner;
-pub use crate::utils::hash_string;
+pub use crate::relation::Relation;
+pub use crate::report::{EntityCount, RunReport, SkippedTexts, StageTiming};
+pub use crate::roundtrip::{RoundTripChange, RoundTripChangeKind, RoundTripReport};
+#[cfg(feature = "server")]
+pub use crate::server::Server;
+pub use crate::span::{Aggregation, Span, SpanSource};
+pub use crate::stats::{
+    EntityFrequency, GazetteerStats, LabelStats, Period, PeriodStats, Stats, TimeStats,
+};
+#[cfg(feature = "suggestions")]
+pub use crate::suggest::{LabelSuggestion, SuggestOptions, Vectors};
+pub use crate::utils::{
+    byte_to_char, char_to_byte, colorize, escape_html, hash_string, label_display, preview,
+    preview_end, preview_length, set_label_display, set_preview_length, TermColor,
+};
+pub use crate::validate::{SpanError, SpanErrorKind, SpanValidation, ValidationReport};
+pub use crate::watch::{WatchOptions, WatchState};
+pub use crate::workspace::{ProjectStatus, Workspace};
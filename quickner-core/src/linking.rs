@@ -0,0 +1,150 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entity::Entity,
+    quickner::Quickner,
+    utils::{char_to_byte, long_path},
+};
+
+/// The spans of a document linked to the entities of the gazetteer,
+/// one line of the entity linking export
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DocumentLinks {
+    pub id: String,
+    pub text: String,
+    pub spans: Vec<EntityLink>,
+}
+
+/// A span linked to the entities of the gazetteer with its surface form and label.
+/// An unambiguous span has the id and canonical form of its entity, an ambiguous
+/// one has no id and lists its candidates instead.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntityLink {
+    pub start: usize,
+    pub end: usize,
+    pub label: String,
+    /// Text of the span
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    /// The entities the span can refer to, when there are several
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<LinkCandidate>,
+}
+
+/// An entity a span can refer to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LinkCandidate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+}
+
+impl Quickner {
+    /// Link the spans of the documents to the entities of the gazetteer.
+    /// The entities with the surface form and label of a span and an id or a
+    /// canonical form are its candidates: the span is linked to the candidate
+    /// when there is only one, the candidates are listed otherwise.
+    pub fn entity_links(&self) -> Vec<DocumentLinks> {
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        let key = |name: &str, label: &str| match case_sensitive {
+            true => (name.to_string(), label.to_string()),
+            false => (name.to_lowercase(), label.to_string()),
+        };
+        let mut candidates: HashMap<(String, String), Vec<LinkCandidate>> = HashMap::new();
+        for entity in self.entities.iter().filter(|entity| entity.has_meta()) {
+            let candidate = LinkCandidate::from(entity);
+            let entry = candidates
+                .entry(key(&entity.name, &entity.label))
+                .or_default();
+            if !entry.contains(&candidate) {
+                entry.push(candidate);
+            }
+        }
+        self.documents
+            .iter()
+            .map(|document| DocumentLinks {
+                id: document.id.to_string(),
+                text: document.text.to_string(),
+                spans: document
+                    .label
+                    .iter()
+                    .map(|(start, end, label)| {
+                        let (byte_start, byte_end) = char_to_byte(&document.text, *start, *end);
+                        let text = document
+                            .text
+                            .get(byte_start..byte_end)
+                            .unwrap_or_default()
+                            .to_string();
+                        let mut link = EntityLink {
+                            start: *start,
+                            end: *end,
+                            label: label.to_string(),
+                            id: None,
+                            canonical: None,
+                            candidates: Vec::new(),
+                            text,
+                        };
+                        match candidates.get(&key(&link.text, label)).map(Vec::as_slice) {
+                            Some([candidate]) => {
+                                link.id = candidate.id.clone();
+                                link.canonical = candidate.canonical.clone();
+                            }
+                            Some(several) => link.candidates = several.to_vec(),
+                            None => {}
+                        }
+                        link
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Save the spans linked to the entities of the gazetteer as JSONL, one
+    /// document per line, to train or evaluate an entity linking model:
+    /// {"id": "...", "text": "...", "spans": [{"start": 0, "end": 5, "label": "ORG",
+    /// "text": "Apple", "id": "Q312"}]}
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// quickner.to_entity_links("./links.jsonl").unwrap();
+    /// ```
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn to_entity_links(&self, path: &str) -> Result<(), std::io::Error> {
+        let mut file = BufWriter::new(File::create(long_path(path))?);
+        for links in self.entity_links() {
+            serde_json::to_writer(&mut file, &links)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()
+    }
+}
+
+impl From<&Entity> for LinkCandidate {
+    fn from(entity: &Entity) -> Self {
+        LinkCandidate {
+            id: entity.id.clone(),
+            canonical: entity.canonical.clone(),
+        }
+    }
+}
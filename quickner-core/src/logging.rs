@@ -0,0 +1,120 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    io::Write,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
+};
+
+use env_logger::{Builder, Env};
+use log::{
+    debug, info,
+    kv::{self, Key, VisitSource},
+    LevelFilter, SetLoggerError,
+};
+use serde_json::{Map, Number, Value};
+
+use crate::{config::LogFormat, utils::format_rfc3339};
+
+/// Install a logger writing to stderr, at the level of the `[logging]`
+/// settings unless the `RUST_LOG` environment variable is set.
+/// quickner only logs through the `log` crate and installs no logger itself,
+/// an application embedding it installs its own logger or calls this once.
+/// With the JSON format, each line is an object with the time, level, target
+/// and message, and the fields of the record: the stages of a run are logged
+/// with their `stage`, `seconds`, `count` and `unit`.
+/// # Example
+/// ```no_run
+/// use quickner::{init_logging, LogFormat, Quickner};
+/// init_logging("info", LogFormat::Json).unwrap();
+/// let mut quickner = Quickner::new(Some("./config.toml"));
+/// quickner.process(true).unwrap();
+/// ```
+/// # Errors
+/// Returns an error if a logger is already installed
+pub fn init_logging(level: &str, format: LogFormat) -> Result<(), SetLoggerError> {
+    let mut builder = Builder::new();
+    builder.filter_level(level_filter(level));
+    builder.parse_env(Env::default());
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let mut line = Map::new();
+            line.insert("time".to_string(), format_rfc3339(SystemTime::now()).into());
+            line.insert("level".to_string(), record.level().as_str().into());
+            line.insert("target".to_string(), record.target().into());
+            line.insert("message".to_string(), record.args().to_string().into());
+            // A field is only missing if it cannot be visited
+            let _ = record.key_values().visit(&mut JsonFields(&mut line));
+            writeln!(buf, "{}", Value::Object(line))
+        });
+    }
+    builder.try_init()
+}
+
+/// Level of the `[logging]` settings, "warning" and "fatal" are read as
+/// warn and error, info if unknown
+fn level_filter(level: &str) -> LevelFilter {
+    match level.to_lowercase().as_str() {
+        "warning" => LevelFilter::Warn,
+        "fatal" => LevelFilter::Error,
+        level => LevelFilter::from_str(level).unwrap_or(LevelFilter::Info),
+    }
+}
+
+/// Fields of a record added to its JSON line
+struct JsonFields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(number) = value.to_u64() {
+            number.into()
+        } else if let Some(number) = value.to_i64() {
+            number.into()
+        } else if let Some(number) = value.to_f64() {
+            Number::from_f64(number).map_or(Value::Null, Value::Number)
+        } else if let Some(boolean) = value.to_bool() {
+            boolean.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// A stage of a run, timed from its start and logged when it ends with the
+/// number of items it went through
+pub(crate) struct Stage {
+    name: &'static str,
+    started: Instant,
+}
+
+impl Stage {
+    pub(crate) fn start(name: &'static str) -> Self {
+        debug!(target: "quickner::stage", stage = name; "Stage {} started", name);
+        Stage {
+            name,
+            started: Instant::now(),
+        }
+    }
+
+    /// Log the end of the stage, returns its name and duration
+    pub(crate) fn finish(self, count: usize, unit: &str) -> (&'static str, Duration) {
+        let elapsed = self.started.elapsed();
+        info!(
+            target: "quickner::stage",
+            stage = self.name,
+            seconds = elapsed.as_secs_f64(),
+            count = count,
+            unit = unit;
+            "Stage {}: {} {} in {:.2?}", self.name, count, unit, elapsed
+        );
+        (self.name, elapsed)
+    }
+}
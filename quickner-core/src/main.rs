@@ -0,0 +1,844 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
+
+use clap::{Parser, Subcommand};
+use quickner::{
+    candidates_to_csv, init_logging, install_cancel_handler, write_candidates, BenchOptions,
+    Compression, Config, DiscoverOptions, DocumentSelection, Fields, Format, Fuzzy, LogFormat,
+    Matching, MergeStrategy, NoopProgress, Output, Period, Quickner, RoundTripReport, RunReport,
+    SpanValidation, WatchOptions, Workspace,
+};
+use serde_json::json;
+
+/// A fast and simple NER tool
+///
+/// Annotate the texts with the entities described in the configuration file
+/// and save the annotations.
+#[derive(Parser)]
+#[command(author, version, about)]
+struct Cli {
+    /// Path to the configuration file
+    #[arg(short, long, default_value = "./config.toml")]
+    config: String,
+
+    /// Profile of the configuration file to apply, a [profiles.<name>] section,
+    /// the QUICKNER_PROFILE environment variable is used if not given
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Export only the documents with at least one annotation
+    #[arg(long, conflicts_with = "unannotated_only")]
+    annotated_only: bool,
+
+    /// Export only the documents without any annotation
+    #[arg(long)]
+    unannotated_only: bool,
+
+    /// Fail before saving if the labels of the annotations differ from the
+    /// JSON list of labels of this file, which is created by the first run
+    #[arg(long, value_name = "LABELS_JSON")]
+    frozen_labels: Option<String>,
+
+    /// Do not print the progress bar, e.g. when the output goes to log files
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Log only the errors and do not print the progress bar, e.g. from cron
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Print a one-line JSON summary of the run to stdout when it ends, with
+    /// the counts, output and wall time of the stages, implies --quiet
+    #[arg(long)]
+    output_json: bool,
+
+    /// Write the logs as JSON lines, with the timings and counts of the stages
+    /// of the run as fields, whatever the format of [logging]
+    #[arg(long)]
+    json_logs: bool,
+
+    /// Resume the annotation from the last checkpoint, see [checkpoint]
+    /// in the configuration file, the documents found there are not annotated again
+    #[arg(long)]
+    resume: bool,
+
+    /// Annotate only the first documents and print them with their spans
+    /// colorized, nothing is saved
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Number of documents annotated by a dry run
+    #[arg(long, default_value_t = 10, requires = "dry_run")]
+    preview: usize,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write a commented configuration file with the default settings,
+    /// to the path of --config
+    Init {
+        /// Overwrite the configuration file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Manage a directory of quickner projects
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommand,
+    },
+    /// Validate the spans of any JSONL file (offsets, overlaps and labels)
+    /// without a configuration file, and print a JSON report.
+    /// Exits with status 1 if errors are found
+    ValidateSpans {
+        /// Path to the JSONL file, .jsonl.gz files are decompressed
+        path: String,
+        /// Name of the id field
+        #[arg(long, default_value = "id")]
+        id_field: String,
+        /// Name of the text field
+        #[arg(long, default_value = "text")]
+        text_field: String,
+        /// Name of the spans field, a list of [start, end, label]
+        /// or {"start": ..., "end": ..., "label": ...}
+        #[arg(long, default_value = "label")]
+        label_field: String,
+        /// Labels allowed in the spans, comma separated, any label if not given
+        #[arg(long, value_delimiter = ',')]
+        labels: Vec<String>,
+        /// Accept overlapping spans
+        #[arg(long)]
+        allow_overlaps: bool,
+    },
+    /// Export a JSONL (or spaCy .json) dataset to a format, import it back and print
+    /// a JSON report of the documents and spans lost or altered on the way,
+    /// without a configuration file. Exits with status 1 if anything changed
+    Roundtrip {
+        /// Path to the dataset, .jsonl, .jsonl.gz or spaCy .json
+        path: String,
+        /// Format to go through: jsonl, spacy, csv, brat, conll or tokens
+        #[arg(long)]
+        format: Format,
+    },
+    /// Merge JSONL annotation files, e.g. annotated in parallel batches, into a
+    /// single file without a configuration file, and print a JSON report of the
+    /// merge of each file. The documents are matched by id, in the order of the files
+    Merge {
+        /// Paths to the JSONL files, .jsonl.gz files are decompressed
+        #[arg(required = true, num_args = 2..)]
+        paths: Vec<String>,
+        /// Path to the merged JSONL file, compressed with gzip if it ends with .gz
+        #[arg(long)]
+        output: String,
+        /// How the documents annotated differently are resolved: keep-ours (the
+        /// first file wins), keep-theirs (the last file wins) or union-spans
+        #[arg(long, default_value_t = MergeStrategy::UnionSpans)]
+        strategy: MergeStrategy,
+    },
+    /// Annotate a synthetic corpus without a configuration file and print a JSON
+    /// report of the throughput (documents and megabytes per second) of each
+    /// thread count, e.g. to compare the matching options on a machine
+    Bench {
+        /// Number of documents of the corpus
+        #[arg(long, default_value_t = 10000)]
+        documents: usize,
+        /// Number of words of each document
+        #[arg(long, default_value_t = 200)]
+        words: usize,
+        /// Number of entities of the gazetteer
+        #[arg(long, default_value_t = 1000)]
+        entities: usize,
+        /// Share of the words starting an entity mention, between 0 and 1
+        #[arg(long, default_value_t = 0.05)]
+        density: f64,
+        /// Share of the mentions with a typo, between 0 and 1
+        #[arg(long, default_value_t = 0.0)]
+        typos: f64,
+        /// Enable the fuzzy matching with this largest edit distance
+        #[arg(long)]
+        max_edits: Option<usize>,
+        /// Match the entities regardless of their diacritics
+        #[arg(long)]
+        fold_diacritics: bool,
+        /// Match the plurals of the entities
+        #[arg(long)]
+        match_plurals: bool,
+        /// Thread counts to compare, e.g. 1,2,4, the global pool if not given
+        #[arg(long, value_delimiter = ',')]
+        threads: Vec<usize>,
+        /// Number of runs of each thread count, the fastest one is reported
+        #[arg(long, default_value_t = 3)]
+        iterations: usize,
+        /// Seed of the synthetic corpus
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Annotate the texts, then print as CSV the frequent title-case sequences
+    /// missing from the gazetteer, with the label of the spans found in the most
+    /// similar contexts, for review before adding them to the entities file
+    Discover {
+        /// Minimum number of occurrences of a candidate
+        #[arg(long, default_value_t = 3)]
+        min_frequency: usize,
+        /// Maximum number of words of a candidate
+        #[arg(long, default_value_t = 4)]
+        max_words: usize,
+        /// Number of words on each side of an occurrence making its context
+        #[arg(long, default_value_t = 5)]
+        window: usize,
+        /// Maximum number of candidates, the best ranked ones
+        #[arg(long, default_value_t = 100)]
+        max_candidates: usize,
+        /// Path to the CSV file, printed to the standard output if not given
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print the entity frequencies per time period as CSV, from a date in the
+    /// metadata of the documents, flagging the entities annotated for the first time
+    Drift {
+        /// Metadata field holding the date of the documents (e.g. 2023-05-12)
+        #[arg(long, default_value = "date")]
+        field: String,
+        /// Length of the periods: day, month or year
+        #[arg(long, default_value_t = Period::Month)]
+        period: Period,
+        /// Path to the CSV file, printed to the standard output if not given
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Watch the texts input and annotate the new documents as they appear,
+    /// appending them to the JSONL annotations. The ids of the documents annotated
+    /// are kept in a state file, e.g. annotations_watch.json. Stops with Ctrl-C
+    Watch {
+        /// Seconds between two scans of the input
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        /// Path to the state file, next to the annotations if not given
+        #[arg(long)]
+        state: Option<String>,
+        /// Annotate the new documents once and exit, e.g. from a scheduled job
+        #[arg(long)]
+        once: bool,
+    },
+    /// Serve the annotation over HTTP with the gazetteer of the configuration:
+    /// POST /annotate with {"texts": [...]} returns the annotated documents, and
+    /// /entities lists (GET), adds (POST), replaces (PUT) or removes (DELETE) entities
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Annotate the texts, then downsample the documents of the over-represented
+    /// labels and save the balanced annotations, see `Quickner::balance`
+    Balance {
+        /// Maximum number of documents annotated with a label, the documents
+        /// also holding a label below the limit are kept
+        #[arg(long)]
+        max_per_label: usize,
+        /// Seed of the random order in which the documents are kept
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Path to the output, the output path of the configuration file if not given
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Suggest labels for the frequent terms missing from the gazetteer,
+    /// from their nearest entities in a word vectors file (word2vec or GloVe text format).
+    /// The suggestions are printed as CSV, approved rows can be added to the entities file.
+    #[cfg(feature = "suggestions")]
+    Suggest {
+        /// Path to the word vectors file
+        #[arg(long)]
+        vectors: String,
+        /// Minimum number of occurrences of a term
+        #[arg(long, default_value_t = 3)]
+        min_frequency: usize,
+        /// Maximum number of terms compared to the gazetteer, the most frequent ones
+        #[arg(long, default_value_t = 1000)]
+        max_terms: usize,
+        /// Number of nearest entities voting for the label of a term
+        #[arg(long, default_value_t = 5)]
+        neighbors: usize,
+        /// Minimum cosine similarity between a term and the entities of its label
+        #[arg(long, default_value_t = 0.5)]
+        min_similarity: f32,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommand {
+    /// Print the dataset sizes, last run dates and label counts
+    /// of the projects found in a directory
+    Status {
+        /// Directory containing the configuration files of the projects
+        #[arg(default_value = ".")]
+        directory: String,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Some(Command::Init { force }) = cli.command {
+        init(&cli.config, force);
+        return;
+    }
+    if let Some(Command::Workspace {
+        command: WorkspaceCommand::Status { directory },
+    }) = cli.command
+    {
+        workspace_status(&directory);
+        return;
+    }
+    if let Some(Command::ValidateSpans {
+        path,
+        id_field,
+        text_field,
+        label_field,
+        labels,
+        allow_overlaps,
+    }) = cli.command
+    {
+        let validation = SpanValidation {
+            fields: Fields {
+                id: id_field,
+                text: text_field,
+                label: label_field,
+            },
+            labels: labels.into_iter().collect(),
+            allow_overlaps,
+        };
+        validate_spans(&path, &validation);
+        return;
+    }
+    if let Some(Command::Roundtrip { path, format }) = cli.command {
+        roundtrip(&path, &format);
+        return;
+    }
+    if let Some(Command::Merge {
+        paths,
+        output,
+        strategy,
+    }) = cli.command
+    {
+        merge(&paths, &output, strategy);
+        return;
+    }
+    if let Some(Command::Bench {
+        documents,
+        words,
+        entities,
+        density,
+        typos,
+        max_edits,
+        fold_diacritics,
+        match_plurals,
+        threads,
+        iterations,
+        seed,
+    }) = cli.command
+    {
+        let options = BenchOptions {
+            documents,
+            words,
+            entities,
+            density,
+            typos,
+            matching: Matching {
+                fold_diacritics,
+                match_plurals,
+                fuzzy: max_edits.map(|max_edits| Fuzzy {
+                    max_edits,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            threads,
+            iterations,
+            seed,
+        };
+        bench(&options);
+        return;
+    }
+    let config = match Config::try_from_file_with_profile(&cli.config, cli.profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(errors) = config.validate() {
+        eprintln!("Invalid configuration file {}:", cli.config);
+        for error in errors {
+            eprintln!("  - {error}");
+        }
+        std::process::exit(1);
+    }
+    let logging = config.logging.clone().unwrap_or_default();
+    let format = match cli.json_logs {
+        true => LogFormat::Json,
+        false => logging.format,
+    };
+    let quiet = cli.quiet || cli.output_json;
+    let level = match quiet {
+        true => "error",
+        false => logging.level.as_str(),
+    };
+    if let Err(e) = init_logging(level, format) {
+        eprintln!("Unable to install the logger: {e}");
+    }
+    let mut quickner = Quickner {
+        config,
+        config_file: Some(cli.config),
+        ..Default::default()
+    };
+    if cli.annotated_only {
+        quickner.config.annotations.output.documents = DocumentSelection::Annotated;
+    }
+    if cli.unannotated_only {
+        quickner.config.annotations.output.documents = DocumentSelection::Unannotated;
+    }
+    if cli.no_progress || quiet {
+        quickner.progress = Arc::new(NoopProgress);
+    }
+    if cli.frozen_labels.is_some() {
+        quickner.config.annotations.output.frozen_labels = cli.frozen_labels;
+    }
+    if cli.resume {
+        quickner
+            .config
+            .checkpoint
+            .get_or_insert_with(Default::default)
+            .resume = true;
+    }
+    #[cfg(feature = "suggestions")]
+    if let Some(Command::Suggest {
+        vectors,
+        min_frequency,
+        max_terms,
+        neighbors,
+        min_similarity,
+    }) = cli.command
+    {
+        let options = quickner::SuggestOptions {
+            min_frequency,
+            max_terms,
+            neighbors,
+            min_similarity,
+        };
+        suggest(&mut quickner, &vectors, &options);
+        return;
+    }
+    if let Some(Command::Discover {
+        min_frequency,
+        max_words,
+        window,
+        max_candidates,
+        output,
+    }) = cli.command
+    {
+        let options = DiscoverOptions {
+            min_frequency,
+            max_words,
+            window,
+            max_candidates,
+        };
+        discover(&mut quickner, &options, output.as_deref());
+        return;
+    }
+    if let Some(Command::Drift {
+        field,
+        period,
+        output,
+    }) = cli.command
+    {
+        drift(&mut quickner, &field, period, output.as_deref());
+        return;
+    }
+    if let Some(Command::Balance {
+        max_per_label,
+        seed,
+        output,
+    }) = cli.command
+    {
+        balance(&mut quickner, max_per_label, seed, output);
+        return;
+    }
+    #[cfg(feature = "server")]
+    if let Some(Command::Serve { host, port }) = cli.command {
+        let server = match quickner::Server::new(quickner) {
+            Ok(server) => server,
+            Err(e) => {
+                eprintln!("Unable to read the gazetteer: {e}");
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = server.serve(&format!("{host}:{port}")) {
+            eprintln!("Unable to serve on {host}:{port}: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Watch {
+        interval,
+        state,
+        once,
+    }) = cli.command
+    {
+        let options = WatchOptions {
+            interval: Duration::from_secs(interval),
+            state,
+            once,
+        };
+        watch(&mut quickner, &options);
+        return;
+    }
+    if cli.dry_run {
+        dry_run(&quickner, cli.preview);
+        return;
+    }
+    // Ctrl-C finishes the current batch and saves a partial output
+    if let Err(e) = install_cancel_handler() {
+        eprintln!("Unable to install the Ctrl-C handler: {e}");
+    }
+    let report = match quickner.process(true) {
+        Ok(report) => report,
+        Err(e) => {
+            let cancelled = e
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::Interrupted);
+            if cli.output_json {
+                let status = if cancelled { "cancelled" } else { "error" };
+                println!("{}", json!({ "status": status, "error": e.to_string() }));
+            }
+            if cancelled {
+                eprintln!("{e}");
+                std::process::exit(130);
+            }
+            eprintln!("Unable to process the annotations: {e}");
+            std::process::exit(1);
+        }
+    };
+    if cli.output_json {
+        println!("{}", run_summary(&report));
+    }
+}
+
+/// One-line summary of a run printed by --output-json
+fn run_summary(report: &RunReport) -> serde_json::Value {
+    let stages: serde_json::Map<String, serde_json::Value> = report
+        .stages
+        .iter()
+        .map(|stage| (stage.stage.clone(), stage.seconds.into()))
+        .collect();
+    json!({
+        "status": "ok",
+        "documents": report.stats.documents,
+        "annotated_documents": report.stats.annotated_documents,
+        "spans": report.stats.spans,
+        "entities": report.stats.entities,
+        "filtered_texts": report.skipped_texts.filtered,
+        "duplicate_texts": report.skipped_texts.duplicates,
+        "output": report.output,
+        "seconds": report.seconds,
+        "stages": stages,
+    })
+}
+
+fn init(path: &str, force: bool) {
+    if !force && std::path::Path::new(path).exists() {
+        eprintln!("{path} already exists, use --force to overwrite it");
+        std::process::exit(1);
+    }
+    if let Err(e) = std::fs::write(path, Config::template()) {
+        eprintln!("Unable to write {path}: {e}");
+        std::process::exit(1);
+    }
+    println!("Configuration written to {path}");
+}
+
+fn watch(quickner: &mut Quickner, options: &WatchOptions) {
+    // Ctrl-C stops the watch between two scans, or cancels the current batch
+    if let Err(e) = install_cancel_handler() {
+        eprintln!("Unable to install the Ctrl-C handler: {e}");
+    }
+    if let Err(e) = quickner.watch(options) {
+        eprintln!("Unable to watch the texts: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn dry_run(quickner: &Quickner, preview: usize) {
+    let documents = match quickner.preview(preview) {
+        Ok(documents) => documents,
+        Err(e) => {
+            eprintln!("Unable to process the annotations: {e}");
+            std::process::exit(1);
+        }
+    };
+    for document in &documents {
+        println!("{}\t{}", document.id, document.render_ansi(None));
+    }
+    let spans: usize = documents.iter().map(|document| document.label.len()).sum();
+    println!(
+        "{} documents annotated, {} spans (dry run, nothing saved)",
+        documents.len(),
+        spans
+    );
+}
+
+fn roundtrip(path: &str, format: &Format) {
+    let input = match path.ends_with(".json") {
+        true => Format::Spacy,
+        false => Format::Jsonl,
+    };
+    // The file is read as given, .jsonl.gz files are decompressed
+    let documents = match input.read(Path::new(path)) {
+        Ok(documents) => documents,
+        Err(e) => {
+            eprintln!("Unable to read {path}: {e}");
+            std::process::exit(2);
+        }
+    };
+    let report = match RoundTripReport::check(format, &documents) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Unable to export the documents to {format}: {e}");
+            std::process::exit(2);
+        }
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(report) => println!("{report}"),
+        Err(e) => {
+            eprintln!("Unable to write the report: {e}");
+            std::process::exit(2);
+        }
+    }
+    if !report.lossless {
+        std::process::exit(1);
+    }
+}
+
+fn bench(options: &BenchOptions) {
+    let report = match options.run() {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Unable to build the thread pool: {e}");
+            std::process::exit(1);
+        }
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(report) => println!("{report}"),
+        Err(e) => {
+            eprintln!("Unable to write the report: {e}");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn merge(paths: &[String], output: &str, strategy: MergeStrategy) {
+    let mut quickner = Quickner::from_jsonl(&paths[0]);
+    let mut merges = Vec::new();
+    for path in &paths[1..] {
+        let report = quickner.merge(Quickner::from_jsonl(path), strategy);
+        merges.push(json!({ "path": path, "report": report }));
+    }
+    let output = match output.strip_suffix(".gz") {
+        Some(path) => Output {
+            path: path.to_string(),
+            compression: Compression::Gzip,
+            ..Default::default()
+        },
+        None => Output {
+            path: output.to_string(),
+            ..Default::default()
+        },
+    };
+    let path = match Format::Jsonl.save_with_options(&quickner.documents, &output) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Unable to save the merged annotations: {e}");
+            std::process::exit(1);
+        }
+    };
+    let report = json!({
+        "output": format!("{path}.{}", Format::Jsonl.extension(&output.compression)),
+        "strategy": strategy,
+        "documents": quickner.documents.len(),
+        "entities": quickner.entities.len(),
+        "merges": merges,
+    });
+    match serde_json::to_string_pretty(&report) {
+        Ok(report) => println!("{report}"),
+        Err(e) => {
+            eprintln!("Unable to write the report: {e}");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn validate_spans(path: &str, validation: &SpanValidation) {
+    let report = match validation.validate_jsonl(path) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Unable to read {path}: {e}");
+            std::process::exit(2);
+        }
+    };
+    match serde_json::to_string_pretty(&report) {
+        Ok(report) => println!("{report}"),
+        Err(e) => {
+            eprintln!("Unable to write the report: {e}");
+            std::process::exit(2);
+        }
+    }
+    if !report.valid {
+        std::process::exit(1);
+    }
+}
+
+fn balance(quickner: &mut Quickner, max_per_label: usize, seed: u64, output: Option<String>) {
+    if let Err(e) = quickner.process(false) {
+        eprintln!("Unable to process the annotations: {e}");
+        std::process::exit(1);
+    }
+    let total = quickner.documents.len();
+    let removed = quickner.balance(max_per_label, seed);
+    let mut output_config = quickner.config.annotations.output.clone();
+    if let Some(path) = output {
+        output_config.path = path;
+    }
+    let saved = quickner.config.annotations.format.save_stamped(
+        &quickner.documents,
+        &output_config,
+        &quickner.stamp(),
+    );
+    match saved {
+        Ok(path) => println!(
+            "{} of {} documents kept, saved to {}",
+            total - removed,
+            total,
+            path
+        ),
+        Err(e) => {
+            eprintln!("Unable to save the annotations: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn drift(quickner: &mut Quickner, field: &str, period: Period, output: Option<&str>) {
+    if let Err(e) = quickner.process(false) {
+        eprintln!("Unable to process the annotations: {e}");
+        std::process::exit(1);
+    }
+    let stats = quickner.time_stats(field, period);
+    let written = match output {
+        Some(path) => stats.to_csv(path),
+        None => stats.write_csv(std::io::stdout()),
+    };
+    if let Err(e) = written {
+        eprintln!("Unable to write the statistics: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "suggestions")]
+fn suggest(quickner: &mut Quickner, vectors: &str, options: &quickner::SuggestOptions) {
+    if let Err(e) = quickner.process(false) {
+        eprintln!("Unable to process the annotations: {e}");
+        std::process::exit(1);
+    }
+    let suggestions = match quickner.suggest_labels(vectors, options) {
+        Ok(suggestions) => suggestions,
+        Err(e) => {
+            eprintln!("Unable to suggest labels: {e}");
+            std::process::exit(1);
+        }
+    };
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    let written = writer
+        .write_record(["name", "label", "score", "frequency", "neighbors"])
+        .and_then(|_| {
+            suggestions.iter().try_for_each(|suggestion| {
+                writer.write_record([
+                    suggestion.term.as_str(),
+                    suggestion.label.as_str(),
+                    &format!("{:.3}", suggestion.score),
+                    &suggestion.frequency.to_string(),
+                    &suggestion.neighbors.join("|"),
+                ])
+            })
+        })
+        .and_then(|_| writer.flush().map_err(csv::Error::from));
+    if let Err(e) = written {
+        eprintln!("Unable to write the suggestions: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn discover(quickner: &mut Quickner, options: &DiscoverOptions, output: Option<&str>) {
+    // The annotation lowercases the texts unless it is case sensitive,
+    // the candidates are found in the texts as read
+    let texts = match quickner.read_texts() {
+        Ok(()) => quickner.documents.clone(),
+        Err(e) => {
+            eprintln!("Unable to read the texts: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = quickner.process(false) {
+        eprintln!("Unable to process the annotations: {e}");
+        std::process::exit(1);
+    }
+    let texts: HashMap<String, Arc<str>> = texts
+        .into_iter()
+        .map(|document| (document.id, document.text))
+        .collect();
+    for document in quickner.documents.iter_mut() {
+        if let Some(text) = texts.get(&document.id) {
+            document.text = text.clone();
+        }
+    }
+    let candidates = quickner.discover(options);
+    let written = match output {
+        Some(path) => candidates_to_csv(&candidates, path),
+        None => write_candidates(&candidates, std::io::stdout()),
+    };
+    if let Err(e) = written {
+        eprintln!("Unable to write the candidates: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn workspace_status(directory: &str) {
+    let workspace = match Workspace::scan(directory) {
+        Ok(workspace) => workspace,
+        Err(e) => {
+            eprintln!("Unable to read the workspace {directory}: {e}");
+            std::process::exit(1);
+        }
+    };
+    if workspace.projects.is_empty() {
+        println!("No quickner project found in {directory}");
+    } else {
+        println!("{}", workspace.table());
+    }
+    for (path, error) in &workspace.skipped {
+        let error = error.to_string();
+        let error = error.lines().next().unwrap_or_default();
+        eprintln!("Skipped {}: {}", path.display(), error);
+    }
+}
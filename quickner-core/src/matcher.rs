@@ -0,0 +1,597 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use aho_corasick::AhoCorasick;
+use rayon::prelude::*;
+
+use crate::{
+    config::{
+        LabelAggregation, LabelPrecision, LabelPriority, Matching, RelationPattern, Rule,
+        VerifiedPolicy,
+    },
+    document::Document,
+    entity::{Entity, EntityMeta, Exclude},
+    fuzzy::FuzzyIndex,
+    mention::mentions,
+    quickner::Quickner,
+    span::{Span, SpanSource},
+    stats::GazetteerStats,
+    utils::{char_to_byte, fold_diacritics, plural_forms},
+};
+
+/// Checks run on the spans found by the dictionary matching:
+/// the excludes, matched like entities, the context rules, then the
+/// guardrails of the high precision labels
+#[derive(Clone, Default)]
+struct SpanChecks {
+    /// Automaton of the excludes, with the excludes as entities,
+    /// an empty label applies to every label
+    excludes: Option<(Arc<AhoCorasick>, Vec<Entity>)>,
+    rules: Vec<Rule>,
+    precision: BTreeMap<String, LabelPrecision>,
+}
+
+impl SpanChecks {
+    /// The names of the excludes are folded like the text when `fold` is set
+    fn new(
+        excludes: &[Exclude],
+        rules: &[Rule],
+        precision: &BTreeMap<String, LabelPrecision>,
+        fold: bool,
+    ) -> Self {
+        let excludes: Vec<Entity> = excludes
+            .iter()
+            .map(|exclude| Entity {
+                name: match fold {
+                    true => fold_diacritics(&exclude.name).into_owned(),
+                    false => exclude.name.to_string(),
+                },
+                label: exclude.label.clone().unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect();
+        let excludes = match excludes.is_empty() {
+            true => None,
+            false => Some((
+                Arc::new(AhoCorasick::new(
+                    excludes.iter().map(|exclude| exclude.name.as_str()),
+                )),
+                excludes,
+            )),
+        };
+        SpanChecks {
+            excludes,
+            rules: rules.to_vec(),
+            precision: precision.clone(),
+        }
+    }
+
+    /// Drop the spans inside an occurrence of an exclude of their label,
+    /// then apply the context rules and the guardrails. The excludes are
+    /// matched in the text given to the automaton, the rules in the text of
+    /// the document, the casing of the guardrails is read in the original text.
+    fn apply(
+        &self,
+        text: &str,
+        original: &str,
+        matched: &str,
+        spans: &mut Vec<(usize, usize, String)>,
+        case_sensitive: bool,
+    ) {
+        if let Some((automaton, excludes)) = &self.excludes {
+            let excluded = Quickner::find_index_using_aho_corasick(matched, automaton, excludes)
+                .unwrap_or_default();
+            spans.retain(|span| {
+                !excluded.iter().any(|(start, end, label)| {
+                    *start <= span.0 && span.1 <= *end && (label.is_empty() || *label == span.2)
+                })
+            });
+        }
+        Rule::apply(&self.rules, text, spans, case_sensitive);
+        LabelPrecision::apply(&self.precision, original, matched, spans);
+    }
+}
+
+/// Dictionary matcher compiled once from the entities, to annotate many
+/// documents without building the automaton again for each of them.
+/// A matcher compiled from a Quickner instance also applies its excludes,
+/// context rules, label priority and aggregation, and links the metadata of
+/// the entities.
+/// With the [`Matching`] options, the diacritics are folded and the plural
+/// forms of the entities are matched too, the spans keep the original offsets.
+/// With fuzzy matching, the words a few edits away from an entity are matched
+/// in a second pass, where the exact matching found nothing.
+/// # Example
+/// ```
+/// use quickner::{Document, Entity, Matcher};
+/// let entities = vec![Entity {
+///     name: "Rust".to_string(),
+///     label: "PL".to_string(),
+///     ..Default::default()
+/// }];
+/// let matcher = Matcher::new(&entities, false);
+/// let mut document = Document::from_string("I love rust".to_string());
+/// matcher.annotate(&mut document);
+/// assert_eq!(document.label, vec![(7, 11, "PL".to_string())]);
+/// ```
+#[derive(Clone)]
+pub struct Matcher {
+    automaton: Arc<AhoCorasick>,
+    entities: Vec<Entity>,
+    /// Patterns of the automaton, the entities and their plural forms,
+    /// as entities named after the pattern
+    patterns: Vec<Entity>,
+    /// Patterns matched with edits, when fuzzy matching is enabled
+    fuzzy: Option<Arc<FuzzyIndex>>,
+    checks: SpanChecks,
+    case_sensitive: bool,
+    matching: Matching,
+    priority: LabelPriority,
+    aggregation: LabelAggregation,
+    /// Entity with metadata of each pattern and label, the first one
+    /// is linked when a pattern is ambiguous
+    metadata: HashMap<(String, String), usize>,
+    /// Patterns of the relations proposed between the spans
+    relations: Vec<RelationPattern>,
+}
+
+impl Matcher {
+    /// Compile the entities, lowercased unless `case_sensitive` is set,
+    /// without excludes, context rules or label priority
+    pub fn new(entities: &[Entity], case_sensitive: bool) -> Self {
+        Matcher::with_matching(entities, case_sensitive, Matching::default())
+    }
+
+    /// Compile the entities like `new`, with the given matching options
+    /// # Example
+    /// ```
+    /// use quickner::{Document, Entity, Matcher, Matching};
+    /// let entities = vec![Entity {
+    ///     name: "Montreal".to_string(),
+    ///     label: "CITY".to_string(),
+    ///     ..Default::default()
+    /// }];
+    /// let matching = Matching { fold_diacritics: true, ..Default::default() };
+    /// let matcher = Matcher::with_matching(&entities, false, matching);
+    /// let mut document = Document::from_string("Montréal".to_string());
+    /// matcher.annotate(&mut document);
+    /// assert_eq!(document.label, vec![(0, 8, "CITY".to_string())]);
+    /// ```
+    pub fn with_matching(entities: &[Entity], case_sensitive: bool, matching: Matching) -> Self {
+        let entities = entities
+            .iter()
+            .map(|entity| Entity {
+                name: match case_sensitive {
+                    true => entity.name.clone(),
+                    false => entity.name.to_lowercase(),
+                },
+                ..entity.clone()
+            })
+            .collect();
+        Matcher::compile(
+            entities,
+            SpanChecks::default(),
+            case_sensitive,
+            matching,
+            LabelPriority::default(),
+            LabelAggregation::default(),
+            Vec::new(),
+        )
+    }
+
+    fn compile(
+        entities: Vec<Entity>,
+        checks: SpanChecks,
+        case_sensitive: bool,
+        matching: Matching,
+        priority: LabelPriority,
+        aggregation: LabelAggregation,
+        relations: Vec<RelationPattern>,
+    ) -> Self {
+        let mut patterns = Vec::with_capacity(entities.len());
+        let mut metadata = HashMap::new();
+        for (index, entity) in entities.iter().enumerate() {
+            let name = match matching.fold_diacritics {
+                true => fold_diacritics(&entity.name).into_owned(),
+                false => entity.name.clone(),
+            };
+            let mut names = vec![name];
+            if matching.match_plurals {
+                names.extend(plural_forms(&names[0], matching.language));
+            }
+            for name in names {
+                if entity.has_meta() {
+                    metadata
+                        .entry((name.clone(), entity.label.clone()))
+                        .or_insert(index);
+                }
+                patterns.push(Entity {
+                    name,
+                    ..entity.clone()
+                });
+            }
+        }
+        let automaton = Arc::new(AhoCorasick::new(
+            patterns.iter().map(|pattern| pattern.name.as_str()),
+        ));
+        let fuzzy = matching
+            .fuzzy
+            .as_ref()
+            .and_then(|fuzzy| FuzzyIndex::new(&patterns, fuzzy))
+            .map(Arc::new);
+        Matcher {
+            automaton,
+            entities,
+            patterns,
+            fuzzy,
+            checks,
+            case_sensitive,
+            matching,
+            priority,
+            aggregation,
+            metadata,
+            relations,
+        }
+    }
+
+    /// Entities compiled into the matcher
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Number of matches of each label in a text, matched as `annotate` does
+    /// but without the fuzzy matches, excludes and context rules
+    /// # Example
+    /// ```
+    /// use quickner::{Entity, Matcher};
+    /// let entities = vec![
+    ///     Entity { name: "goal".to_string(), label: "SPORT".to_string(), ..Default::default() },
+    ///     Entity { name: "match".to_string(), label: "SPORT".to_string(), ..Default::default() },
+    /// ];
+    /// let matcher = Matcher::new(&entities, false);
+    /// let counts = matcher.count_labels("A late goal won the match");
+    /// assert_eq!(counts.get("SPORT"), Some(&2));
+    /// ```
+    pub fn count_labels(&self, text: &str) -> BTreeMap<String, usize> {
+        let text = match self.case_sensitive {
+            true => Cow::Borrowed(text),
+            false => Cow::Owned(text.to_lowercase()),
+        };
+        let matched = match self.matching.fold_diacritics {
+            true => Cow::Owned(fold_diacritics(&text).into_owned()),
+            false => text,
+        };
+        let mut counts = BTreeMap::new();
+        for (_, _, label) in
+            Quickner::find_index_using_aho_corasick(&matched, &self.automaton, &self.patterns)
+                .unwrap_or_default()
+        {
+            *counts.entry(label).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Annotate a document, the spans already present are not added twice.
+    /// The text of the document is lowercased unless the matcher is case sensitive.
+    /// The spans added are recorded in the `span_meta` of the document
+    /// with the entity they matched, see [`Span`]. The exact matches have a
+    /// confidence of 1, the fuzzy matches lose a share of it per edit.
+    /// With `propagate_mentions`, the repeated mentions of the spans are added,
+    /// with the share of the words of the span they repeat as confidence.
+    /// The relations of the configuration are then proposed between the spans.
+    /// A verified document, corrected or gold, is annotated as set by `verified`
+    /// under `[matching]`: left as it is by default.
+    pub fn annotate(&self, document: &mut Document) {
+        match self.matching.verified {
+            _ if !document.status.is_verified() => self.annotate_spans(document),
+            VerifiedPolicy::Skip => {}
+            VerifiedPolicy::Append => self.append_spans(document),
+            VerifiedPolicy::Overwrite => self.annotate_spans(document),
+        }
+    }
+
+    /// Add the spans found in a verified document that do not overlap one of
+    /// its spans, with their provenance, metadata and relations, its spans are kept
+    fn append_spans(&self, document: &mut Document) {
+        let mut found = Document {
+            label: Vec::new(),
+            entity_meta: Vec::new(),
+            span_meta: Vec::new(),
+            relations: Vec::new(),
+            ..document.clone()
+        };
+        self.annotate_spans(&mut found);
+        document.text = found.text;
+        let added: Vec<(usize, usize, String)> = found
+            .label
+            .into_iter()
+            .filter(|span| {
+                !document
+                    .label
+                    .iter()
+                    .any(|other| other.0 < span.1 && span.0 < other.1)
+            })
+            .collect();
+        if added.is_empty() {
+            return;
+        }
+        let is_added = |start: usize, end: usize, label: &str| {
+            added
+                .iter()
+                .any(|span| span.0 == start && span.1 == end && span.2 == label)
+        };
+        document.span_meta.extend(
+            found
+                .span_meta
+                .into_iter()
+                .filter(|span| is_added(span.start, span.end, &span.label)),
+        );
+        document.entity_meta.extend(
+            found
+                .entity_meta
+                .into_iter()
+                .filter(|meta| is_added(meta.start, meta.end, &meta.label)),
+        );
+        document.label.extend(added);
+        document.label.sort_by_key(|span| (span.0, span.1));
+        for relation in found.relations {
+            if document.label.contains(&relation.head_span)
+                && document.label.contains(&relation.tail_span)
+                && !document.relations.contains(&relation)
+            {
+                document.relations.push(relation);
+            }
+        }
+    }
+
+    fn annotate_spans(&self, document: &mut Document) {
+        // The casing of the text is lost once it is lowercased
+        let original = Arc::clone(&document.text);
+        if !self.case_sensitive {
+            document.text = document.text.to_lowercase().into();
+        };
+        // Folding keeps one character for each character, the offsets found
+        // in the folded text are the offsets in the text of the document
+        let matched = match self.matching.fold_diacritics {
+            true => fold_diacritics(&document.text),
+            false => Cow::Borrowed(&*document.text),
+        };
+        let mut index =
+            Quickner::find_index_using_aho_corasick(&matched, &self.automaton, &self.patterns)
+                .unwrap_or_default();
+        let fuzzy = self.find_fuzzy(&matched, &mut index);
+        // The spans relabeled by the context rules are the ones not found before
+        let found = match self.checks.rules.is_empty() {
+            true => Vec::new(),
+            false => index.clone(),
+        };
+        self.checks.apply(
+            &document.text,
+            &original,
+            &matched,
+            &mut index,
+            self.case_sensitive,
+        );
+        // The spans found again, votes for their label
+        let mut votes = Vec::new();
+        for span in index {
+            let relabeled = !found.is_empty() && !found.contains(&span);
+            // A span relabeled by a rule keeps the match of its offsets
+            let edits = fuzzy.get(&span).or_else(|| {
+                fuzzy
+                    .iter()
+                    .find(|(other, _)| relabeled && other.0 == span.0 && other.1 == span.1)
+                    .map(|(_, edits)| edits)
+            });
+            let (name, confidence) = match edits {
+                Some((pattern, distance)) => {
+                    let name = &self.patterns[*pattern].name;
+                    let length = name.chars().count().max(1);
+                    (name.clone(), 1.0 - *distance as f32 / length as f32)
+                }
+                None => {
+                    let (start, end) = char_to_byte(&matched, span.0, span.1);
+                    (matched[start..end].to_string(), 1.0)
+                }
+            };
+            let source = match (relabeled, edits) {
+                (true, _) => SpanSource::Rule,
+                (false, Some(_)) => SpanSource::Fuzzy,
+                (false, None) => SpanSource::Gazetteer,
+            };
+            if document.label.contains(&span) {
+                votes.push(Span::new(&span, source));
+                continue;
+            }
+            document.span_meta.push(Span {
+                entity_name: Some(name),
+                confidence: Some(confidence),
+                ..Span::new(&span, source)
+            });
+            document.label.push(span);
+        }
+        if self.matching.propagate_mentions {
+            for span in self.propagated_mentions(document, &original, &matched) {
+                document.label.push(span.to_tuple());
+                document.span_meta.push(span);
+            }
+        }
+        self.aggregation.resolve(&self.priority, document, &votes);
+        document.retain_span_meta();
+        document.retain_relations();
+        if !self.metadata.is_empty() || !document.entity_meta.is_empty() {
+            self.link_entity_meta(document, &fuzzy);
+        }
+        RelationPattern::apply(&self.relations, document);
+    }
+
+    /// Repeated mentions of the spans of the document, checked against
+    /// the excludes, the context rules and the guardrails like the matches
+    fn propagated_mentions(&self, document: &Document, original: &str, matched: &str) -> Vec<Span> {
+        let found = mentions(matched, &document.label);
+        let mut spans: Vec<(usize, usize, String)> =
+            found.iter().map(|mention| mention.span.clone()).collect();
+        self.checks.apply(
+            &document.text,
+            original,
+            matched,
+            &mut spans,
+            self.case_sensitive,
+        );
+        found
+            .into_iter()
+            .filter(|mention| spans.contains(&mention.span))
+            .map(|mention| Span {
+                entity_name: Some(mention.antecedent),
+                confidence: Some(mention.confidence),
+                ..Span::new(&mention.span, SpanSource::Mention)
+            })
+            .collect()
+    }
+
+    /// Add the fuzzy matches not overlapping a span of the exact matching to
+    /// the spans, returns the pattern matched by each span added and its distance
+    fn find_fuzzy(
+        &self,
+        matched: &str,
+        spans: &mut Vec<(usize, usize, String)>,
+    ) -> HashMap<(usize, usize, String), (usize, usize)> {
+        let mut found = HashMap::new();
+        let Some(fuzzy) = &self.fuzzy else {
+            return found;
+        };
+        let exact = spans.len();
+        for candidate in fuzzy.find(matched) {
+            if spans[..exact]
+                .iter()
+                .any(|span| span.0 < candidate.end && candidate.start < span.1)
+            {
+                continue;
+            }
+            let span = (
+                candidate.start,
+                candidate.end,
+                self.patterns[candidate.pattern].label.clone(),
+            );
+            if !found.contains_key(&span) {
+                found.insert(span.clone(), (candidate.pattern, candidate.distance));
+                spans.push(span);
+            }
+        }
+        if !found.is_empty() {
+            spans.sort_by_key(|span| (span.0, span.1));
+        }
+        found
+    }
+
+    /// Annotate the documents in parallel, see `annotate`
+    pub fn annotate_many(&self, documents: &mut [Document]) {
+        documents
+            .par_iter_mut()
+            .for_each(|document| self.annotate(document));
+    }
+
+    /// Attach the metadata of the matched entities to the spans of the document,
+    /// the metadata of spans removed by the label priority is dropped.
+    /// The spans of `fuzzy` are linked to the metadata of the pattern they matched.
+    fn link_entity_meta(
+        &self,
+        document: &mut Document,
+        fuzzy: &HashMap<(usize, usize, String), (usize, usize)>,
+    ) {
+        let labels = &document.label;
+        document.entity_meta.retain(|meta| {
+            labels
+                .iter()
+                .any(|span| span.0 == meta.start && span.1 == meta.end && span.2 == meta.label)
+        });
+        for span in labels {
+            if document
+                .entity_meta
+                .iter()
+                .any(|meta| meta.start == span.0 && meta.end == span.1 && meta.label == span.2)
+            {
+                continue;
+            }
+            let (start, end) = char_to_byte(&document.text, span.0, span.1);
+            let surface = &document.text[start..end];
+            let surface = match self.matching.fold_diacritics {
+                true => fold_diacritics(surface),
+                false => Cow::Borrowed(surface),
+            };
+            let key = match fuzzy.get(span) {
+                Some((pattern, _)) => (self.patterns[*pattern].name.clone(), span.2.clone()),
+                None => (surface.into_owned(), span.2.clone()),
+            };
+            if let Some(index) = self.metadata.get(&key) {
+                document
+                    .entity_meta
+                    .push(EntityMeta::new(span, &self.entities[*index]));
+            }
+        }
+    }
+}
+
+impl Quickner {
+    /// Compile the entities into a matcher applying the excludes, the context
+    /// rules, the label priority and aggregation of the instance, as `annotate`
+    /// does, to annotate single documents as they come without compiling it again.
+    /// The statistics of the gazetteer are logged.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Document, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let matcher = quickner.compile_matcher();
+    /// let mut document = Document::from_string("Rust is made by Mozilla".to_string());
+    /// matcher.annotate(&mut document);
+    /// ```
+    pub fn compile_matcher(&self) -> Matcher {
+        // The entities are normalized by `process`, not when given directly
+        let entities: Vec<Entity> = self
+            .entities
+            .iter()
+            .map(|entity| Entity {
+                name: match self.config.entities.filters.case_sensitive {
+                    true => entity.name.clone(),
+                    false => entity.name.to_lowercase(),
+                },
+                ..entity.clone()
+            })
+            .collect();
+        self.matcher_for(&entities)
+    }
+
+    /// Matcher of the given entities with the checks of the instance
+    pub(crate) fn matcher_for(&self, entities: &[Entity]) -> Matcher {
+        let matcher = Matcher::compile(
+            entities.to_vec(),
+            SpanChecks::new(
+                &self.excludes,
+                &self.config.rules,
+                &self.config.labels.precision,
+                self.config.matching.fold_diacritics,
+            ),
+            self.config.entities.filters.case_sensitive,
+            self.config.matching.clone(),
+            self.config.labels.priority.clone(),
+            self.config.labels.aggregation.clone(),
+            self.config.relations.clone(),
+        );
+        if !entities.is_empty() {
+            GazetteerStats::new(&matcher.patterns, &matcher.automaton).report();
+        }
+        matcher
+    }
+}
@@ -0,0 +1,97 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::HashMap;
+
+use crate::utils::tokenize;
+
+/// Shortest word of a span taken as a mention on its own, in characters
+const MIN_WORD_LEN: usize = 3;
+
+/// A repeated mention of an annotated surface, found by [`mentions`]
+pub(crate) struct Mention {
+    pub span: (usize, usize, String),
+    /// Surface of the span it repeats
+    pub antecedent: String,
+    /// Share of the words of the antecedent repeated by the mention
+    pub confidence: f32,
+}
+
+/// Mentions of the surfaces of the spans not annotated elsewhere in the text:
+/// the whole surface, or the first or last word of a surface of several words,
+/// e.g. "Obama" after "Barack Obama". A word too short, without letters or
+/// shared by surfaces of different labels is not a mention. The words are
+/// compared as they are written in the text, which is lowercased unless the
+/// matching is case sensitive. The mentions overlapping a span are dropped.
+pub(crate) fn mentions(text: &str, spans: &[(usize, usize, String)]) -> Vec<Mention> {
+    let tokens = tokenize(text);
+    let chars: Vec<char> = text.chars().collect();
+    let words: Vec<String> = tokens
+        .iter()
+        .map(|(start, end)| chars[*start..*end].iter().collect())
+        .collect();
+    // Forms of the surfaces, with the label, antecedent and confidence of each form
+    let mut forms: HashMap<Vec<&str>, Option<(&str, String, f32)>> = HashMap::new();
+    for (start, end, label) in spans {
+        let first = tokens.partition_point(|(_, token_end)| token_end <= start);
+        let last = tokens.partition_point(|(token_start, _)| token_start < end);
+        if first >= last || *end > chars.len() {
+            continue;
+        }
+        let surface: Vec<&str> = words[first..last].iter().map(String::as_str).collect();
+        let antecedent: String = chars[*start..*end].iter().collect();
+        let mut candidates = vec![(surface.clone(), 1.0)];
+        if surface.len() > 1 {
+            let share = 1.0 / surface.len() as f32;
+            for word in [surface[0], surface[surface.len() - 1]] {
+                if word.chars().count() >= MIN_WORD_LEN && word.chars().any(char::is_alphabetic) {
+                    candidates.push((vec![word], share));
+                }
+            }
+        }
+        for (form, confidence) in candidates {
+            let entry = forms
+                .entry(form)
+                .or_insert_with(|| Some((label, antecedent.clone(), confidence)));
+            match entry {
+                // A form of several labels is ambiguous
+                Some((other, _, _)) if *other != label => *entry = None,
+                Some((_, _, best)) if *best < confidence => {
+                    *entry = Some((label, antecedent.clone(), confidence))
+                }
+                _ => {}
+            }
+        }
+    }
+    // The longer forms first, a mention of a whole surface is not cut in words
+    let mut forms: Vec<_> = forms
+        .into_iter()
+        .filter_map(|(form, entry)| entry.map(|entry| (form, entry)))
+        .collect();
+    forms.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    let mut found: Vec<Mention> = Vec::new();
+    for (form, (label, antecedent, confidence)) in &forms {
+        for index in 0..words.len().saturating_sub(form.len() - 1) {
+            if words[index..index + form.len()] != form[..] {
+                continue;
+            }
+            let (start, end) = (tokens[index].0, tokens[index + form.len() - 1].1);
+            let overlaps = |span: &(usize, usize, String)| span.0 < end && start < span.1;
+            if spans.iter().any(overlaps) || found.iter().any(|mention| overlaps(&mention.span)) {
+                continue;
+            }
+            found.push(Mention {
+                span: (start, end, label.to_string()),
+                antecedent: antecedent.clone(),
+                confidence: *confidence,
+            });
+        }
+    }
+    found.sort_by_key(|mention| (mention.span.0, mention.span.1));
+    found
+}
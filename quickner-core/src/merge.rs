@@ -0,0 +1,188 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{document::Document, quickner::Quickner, utils::preview};
+
+/// How `merge` resolves a document annotated differently in both corpora
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Keep the document of the corpus merged into
+    KeepOurs,
+    /// Replace the document by the one of the corpus merged
+    KeepTheirs,
+    /// Keep the spans of both documents, when their texts are the same
+    #[default]
+    UnionSpans,
+}
+
+impl Display for MergeStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeStrategy::KeepOurs => write!(f, "keep-ours"),
+            MergeStrategy::KeepTheirs => write!(f, "keep-theirs"),
+            MergeStrategy::UnionSpans => write!(f, "union-spans"),
+        }
+    }
+}
+
+impl FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy.to_lowercase().replace('_', "-").as_str() {
+            "keep-ours" => Ok(MergeStrategy::KeepOurs),
+            "keep-theirs" => Ok(MergeStrategy::KeepTheirs),
+            "union-spans" => Ok(MergeStrategy::UnionSpans),
+            _ => Err(format!("Unknown merge strategy: {strategy}")),
+        }
+    }
+}
+
+/// Counts of a merge of two corpora
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Documents of the other corpus added, their id was not found
+    pub documents_added: usize,
+    /// Documents found in both corpora with the same spans
+    pub documents_identical: usize,
+    /// Documents found in both corpora with different spans, resolved by the strategy
+    pub conflicts: usize,
+    /// Conflicts between documents with different texts under the same id,
+    /// their spans cannot be merged and the document of the corpus merged into is kept
+    pub text_conflicts: Vec<String>,
+    /// Entities of the other corpus added, by name and label
+    pub entities_added: usize,
+}
+
+impl Quickner {
+    /// Merge the documents and entities of another corpus, e.g. annotated in
+    /// parallel batches. The documents are matched by id: the new ones are added
+    /// in their order, and the ones annotated differently in both corpora are
+    /// resolved with the `strategy`. With [`MergeStrategy::UnionSpans`], the spans,
+    /// metadata and entity metadata of both documents are kept (ours first), unless
+    /// their texts differ under the same id: ours is kept and the id is reported.
+    /// The entities are matched by name and label, theirs replace ours with
+    /// [`MergeStrategy::KeepTheirs`]. The indexes are rebuilt.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{MergeStrategy, Quickner};
+    /// let mut quickner = Quickner::from_jsonl("./batch_1.jsonl");
+    /// let report = quickner.merge(Quickner::from_jsonl("./batch_2.jsonl"), MergeStrategy::UnionSpans);
+    /// println!("{} documents added, {} conflicts", report.documents_added, report.conflicts);
+    /// ```
+    pub fn merge(&mut self, other: Quickner, strategy: MergeStrategy) -> MergeReport {
+        let mut report = MergeReport::default();
+        let snapshot = self.history_snapshot();
+        let mut ids: HashMap<String, usize> = self
+            .documents
+            .iter()
+            .enumerate()
+            .map(|(index, document)| (document.id.clone(), index))
+            .collect();
+        for theirs in other.documents {
+            let Some(&index) = ids.get(&theirs.id) else {
+                ids.insert(theirs.id.clone(), self.documents.len());
+                self.documents.push(theirs);
+                report.documents_added += 1;
+                continue;
+            };
+            let ours = &mut self.documents[index];
+            let spans = |document: &Document| {
+                let mut spans = document.label.clone();
+                spans.sort();
+                spans
+            };
+            if ours.text == theirs.text && spans(ours) == spans(&theirs) {
+                report.documents_identical += 1;
+                continue;
+            }
+            report.conflicts += 1;
+            match strategy {
+                MergeStrategy::KeepOurs => {}
+                MergeStrategy::KeepTheirs => *ours = theirs,
+                MergeStrategy::UnionSpans if ours.text != theirs.text => {
+                    warn!(
+                        "Document {} has different texts, kept as is: \"{}\"",
+                        ours.id,
+                        preview(&ours.text)
+                    );
+                    report.text_conflicts.push(ours.id.clone());
+                }
+                MergeStrategy::UnionSpans => {
+                    for span in theirs.label {
+                        if !ours.label.contains(&span) {
+                            ours.label.push(span);
+                        }
+                    }
+                    ours.label.sort_by_key(|span| (span.0, span.1));
+                    for (key, value) in theirs.meta {
+                        ours.meta.entry(key).or_insert(value);
+                    }
+                    for meta in theirs.entity_meta {
+                        if !ours.entity_meta.contains(&meta) {
+                            ours.entity_meta.push(meta);
+                        }
+                    }
+                    for meta in theirs.span_meta {
+                        if !ours.span_meta.iter().any(|span| span.is(&meta.to_tuple())) {
+                            ours.span_meta.push(meta);
+                        }
+                    }
+                    for relation in theirs.relations {
+                        if !ours.relations.contains(&relation) {
+                            ours.relations.push(relation);
+                        }
+                    }
+                }
+            }
+        }
+        let mut entities: HashMap<(String, String), usize> = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| ((entity.name.clone(), entity.label.clone()), index))
+            .collect();
+        for entity in other.entities {
+            let key = (entity.name.clone(), entity.label.clone());
+            match entities.get(&key) {
+                Some(&index) if strategy == MergeStrategy::KeepTheirs => {
+                    self.entities[index] = entity
+                }
+                Some(_) => {}
+                None => {
+                    entities.insert(key, self.entities.len());
+                    self.entities.push(entity);
+                    report.entities_added += 1;
+                }
+            }
+        }
+        info!(
+            "Merged with {}: {} documents added, {} identical, {} conflicts, {} entities added",
+            strategy,
+            report.documents_added,
+            report.documents_identical,
+            report.conflicts,
+            report.entities_added
+        );
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("merge", snapshot);
+        report
+    }
+}
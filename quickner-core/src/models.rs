@@ -6,18 +6,286 @@
 // Licensed under Mozilla Public License 2.0
 //
 
-use crate::{config::Format, Document};
+use crate::{
+    cancel::{interrupted, is_cancelled},
+    config::{
+        AggregationStrategy, Compression, DocumentSelection, EntitiesFormat, Fields, Format,
+        HtmlOptions, JsonOptions, LabelAggregation, LabelPrecision, LabelPriority, NifOptions,
+        Output, PerLabel, Route, Rule, RuleAction, Streaming,
+    },
+    document::{display_name, label_colors},
+    span::{Aggregation, Span, SpanSource},
+    utils::{
+        char_to_byte, escape_html, format_rfc3339, hash_string, iri_escape, label_display,
+        long_path, tokenize, turtle_escape, TermColor,
+    },
+    Document, Entity, Relation,
+};
+use flate2::{read::GzDecoder, write::GzEncoder};
+use log::info;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fs,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
 
 #[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
 pub struct Text {
     pub text: String,
 }
 
+/// Layout of a TOML gazetteer
+/// ```toml
+/// [[entities]]
+/// name = "Rust"
+/// label = "PL"
+/// ```
+#[derive(Serialize, Deserialize)]
+struct TomlEntities {
+    entities: Vec<Entity>,
+}
+
+/// A document of the tokens output, borrowed when it is written.
+/// The tokens are (token, start, end, BIO tag), see [`Document::tokens`]
+#[derive(Serialize, Deserialize)]
+struct TokensRecord<'a> {
+    id: Cow<'a, str>,
+    text: Cow<'a, str>,
+    tokens: Vec<(String, usize, usize, String)>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    meta: Cow<'a, HashMap<String, String>>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SpacyEntity {
     pub entity: Vec<(usize, usize, String)>,
+    /// Metadata of the document, kept for provenance
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, String>,
+}
+
+/// Style of the HTML pages, the spans are laid out like displaCy
+const HTML_STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+.summary, nav, .id { color: #777; font-size: 0.85em; }
+.legend { margin: 1em 0; line-height: 2.5; }
+.document { border-top: 1px solid #ddd; padding: 1em 0; }
+.text { line-height: 2.5; white-space: pre-wrap; }
+mark.entity { padding: 0.25em 0.4em; margin: 0 0.1em; border-radius: 0.35em; }
+mark.entity .label { font-size: 0.7em; font-weight: bold; text-transform: uppercase; margin-left: 0.5em; vertical-align: middle; }
+";
+
+/// A pattern of a spaCy EntityRuler, one line of its JSONL patterns file
+/// e.g. {"label": "ORG", "pattern": "Apple"}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntityRulerPattern {
+    pub label: String,
+    pub pattern: RulerPattern,
+    /// Id of the entity, set as the ent_id_ of the matched spans
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// Pattern of an EntityRuler: an exact phrase, or one token pattern
+/// per word matching the lowercased text of the tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RulerPattern {
+    Phrase(String),
+    Tokens(Vec<RulerToken>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RulerToken {
+    #[serde(rename = "LOWER")]
+    pub lower: String,
+}
+
+impl EntityRulerPattern {
+    /// Pattern of an entity, case sensitive entities are matched
+    /// as exact phrases, the other ones token by token on the lowercased text
+    pub fn new(entity: &Entity, case_sensitive: bool) -> EntityRulerPattern {
+        let pattern = match case_sensitive {
+            true => RulerPattern::Phrase(entity.name.to_string()),
+            false => RulerPattern::Tokens(
+                entity
+                    .name
+                    .split_whitespace()
+                    .map(|word| RulerToken {
+                        lower: word.to_lowercase(),
+                    })
+                    .collect(),
+            ),
+        };
+        EntityRulerPattern {
+            label: entity.label.to_string(),
+            pattern,
+            id: entity.id.clone(),
+        }
+    }
+}
+
+/// Provenance of an export, written when `stamp` is enabled in the output
+/// so a dataset file can be traced back to the inputs that produced it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Stamp {
+    /// Version of quickner
+    pub version: String,
+    /// Hash of the gazetteer, independent of the order of the entities
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gazetteer_hash: Option<String>,
+    /// Hash of the configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// Export time, RFC 3339 in UTC
+    pub created: String,
+}
+
+impl Stamp {
+    /// Key of the JSONL header record holding the stamp
+    const RECORD_KEY: &'static str = "quickner";
+
+    /// Stamp with the current version and time
+    pub fn new() -> Stamp {
+        Stamp {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            gazetteer_hash: None,
+            config_hash: None,
+            created: format_rfc3339(SystemTime::now()),
+        }
+    }
+
+    /// Set the hash of the gazetteer from its entities
+    pub fn with_gazetteer(mut self, entities: &[Entity]) -> Stamp {
+        let mut entries: Vec<String> = entities
+            .iter()
+            .map(|entity| format!("{}\t{}", entity.name, entity.label))
+            .collect();
+        entries.sort();
+        self.gazetteer_hash = Some(hash_string(&entries.join("\n")));
+        self
+    }
+
+    /// Set the hash of the configuration
+    pub fn with_config_hash(mut self, config_hash: Option<String>) -> Stamp {
+        self.config_hash = config_hash;
+        self
+    }
+
+    /// JSONL header record, e.g. {"quickner": {"version": "0.1.0", ...}}
+    fn record(&self) -> Result<String, std::io::Error> {
+        let mut record = serde_json::Map::new();
+        record.insert(Stamp::RECORD_KEY.to_string(), serde_json::to_value(self)?);
+        Ok(Value::Object(record).to_string())
+    }
+
+    /// Whether a JSONL line is the header record of a stamp
+    pub fn is_record(value: &Value) -> bool {
+        match value {
+            Value::Object(object) => object.len() == 1 && object.contains_key(Stamp::RECORD_KEY),
+            _ => false,
+        }
+    }
+
+    /// One line summary, used for the brat note
+    fn note(&self) -> String {
+        format!(
+            "quickner {} gazetteer={} config={} created={}",
+            self.version,
+            self.gazetteer_hash.as_deref().unwrap_or("-"),
+            self.config_hash.as_deref().unwrap_or("-"),
+            self.created
+        )
+    }
+}
+
+impl Default for Stamp {
+    fn default() -> Self {
+        Stamp::new()
+    }
+}
+
+/// Number of documents serialized in parallel before being written
+const JSONL_CHUNK_SIZE: usize = 10_000;
+
+/// Buffered writer of a JSONL output, gzip-compressed if required
+enum JsonlWriter {
+    Plain(BufWriter<fs::File>),
+    Gzip(GzEncoder<BufWriter<fs::File>>),
+}
+
+impl JsonlWriter {
+    /// Create the output file, with the jsonl extension or jsonl.gz if compressed
+    fn create(path: &str, compression: &Compression) -> Result<Self, std::io::Error> {
+        let extension = JsonlWriter::extension(compression);
+        Ok(match compression {
+            Compression::None => {
+                JsonlWriter::Plain(BufWriter::new(Format::create_file(path, extension)?))
+            }
+            Compression::Gzip => JsonlWriter::Gzip(GzEncoder::new(
+                BufWriter::new(Format::create_file(path, extension)?),
+                flate2::Compression::default(),
+            )),
+        })
+    }
+
+    fn extension(compression: &Compression) -> &'static str {
+        match compression {
+            Compression::None => "jsonl",
+            Compression::Gzip => "jsonl.gz",
+        }
+    }
+
+    /// Move the finished file of a cancelled export to a partial file,
+    /// e.g. annotations_partial.jsonl, so it is not mistaken for a complete export.
+    /// Returns the cancellation error
+    fn move_to_partial(path: &str, compression: &Compression, written: usize) -> std::io::Error {
+        let extension = JsonlWriter::extension(compression);
+        let partial = Path::new(&Output::partial_path(path)).with_extension(extension);
+        let file = Path::new(path).with_extension(extension);
+        if let Err(e) = fs::rename(long_path(&file), long_path(&partial)) {
+            return e;
+        }
+        interrupted(format!(
+            "Export cancelled, {} documents written to {}",
+            written,
+            partial.display()
+        ))
+    }
+
+    /// Flush the buffer and write the gzip trailer, reporting the errors
+    /// that would be silently ignored when dropping the writer
+    fn finish(self) -> Result<(), std::io::Error> {
+        match self {
+            JsonlWriter::Plain(mut file) => file.flush(),
+            JsonlWriter::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for JsonlWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            JsonlWriter::Plain(file) => file.write(buf),
+            JsonlWriter::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            JsonlWriter::Plain(file) => file.flush(),
+            JsonlWriter::Gzip(encoder) => encoder.flush(),
+        }
+    }
 }
 
 impl Format {
@@ -37,30 +305,184 @@ impl Format {
     /// # Panics
     /// Panics if the format is not supported
     pub fn save(&self, annotations: &Vec<Document>, path: &str) -> Result<String, std::io::Error> {
+        let output = Output {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        self.save_with_options(annotations, &output)
+    }
+
+    /// Save annotations to a file in the specified format
+    /// using the options of the output configuration (path, field names, ...)
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save_with_options(
+        &self,
+        annotations: &Vec<Document>,
+        output: &Output,
+    ) -> Result<String, std::io::Error> {
+        self.save_stamped(annotations, output, &Stamp::new())
+    }
+
+    /// Same as `save_with_options`, the stamp is written
+    /// when `stamp` is enabled in the output
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn save_stamped(
+        &self,
+        annotations: &Vec<Document>,
+        output: &Output,
+        stamp: &Stamp,
+    ) -> Result<String, std::io::Error> {
+        let path = output.path.as_str();
+        let mut documents = Cow::Borrowed(annotations);
+        if let Some(min_confidence) = output.min_confidence {
+            documents = Cow::Owned(Output::apply_min_confidence(&documents, min_confidence));
+        }
+        if let Some(min_label_count) = output.min_label_count {
+            documents = Cow::Owned(output.apply_label_floor(&documents, min_label_count));
+        }
+        let split = PerLabel::default();
+        let per_label = output
+            .per_label
+            .as_ref()
+            .or(output.split_by_label.then_some(&split));
+        if let Some(per_label) = per_label {
+            return self.save_per_label(&documents, output, per_label, stamp);
+        }
+        match output.documents {
+            DocumentSelection::All => {}
+            DocumentSelection::Annotated => documents
+                .to_mut()
+                .retain(|document| !document.label.is_empty()),
+            DocumentSelection::Unannotated => documents
+                .to_mut()
+                .retain(|document| document.label.is_empty()),
+        };
+        let annotations = documents.as_ref();
+        let stamp = Some(stamp).filter(|_| output.stamp);
+        output.create_directory()?;
         match self {
-            Format::Spacy => Format::spacy(annotations, path),
-            Format::Jsonl => Format::jsonl(annotations, path),
+            Format::Spacy => Format::spacy(annotations, path, &output.json),
+            Format::Jsonl => match &output.streaming {
+                Some(streaming) => Format::jsonl_streaming(
+                    annotations,
+                    path,
+                    &output.fields,
+                    &output.json,
+                    &output.compression,
+                    streaming,
+                    stamp,
+                ),
+                None => Format::jsonl(
+                    annotations,
+                    path,
+                    &output.fields,
+                    &output.json,
+                    &output.compression,
+                    stamp,
+                ),
+            },
             Format::Csv => Format::csv(annotations, path),
-            Format::Brat => Format::brat(annotations, path),
+            Format::Brat => Format::brat(annotations, path, stamp),
             Format::Conll => Format::conll(annotations, path),
+            Format::Html => Format::html(annotations, path, &output.html),
+            Format::Nif => Format::nif(annotations, path, &output.nif),
+            Format::Tokens => Format::tokens(annotations, path),
         }
     }
 
-    fn remove_extension_from_path(path: &str) -> String {
-        let mut path = path.to_string();
-        if path.contains('.') {
-            path.truncate(path.rfind('.').unwrap());
+    /// Save one file per label or group of labels, e.g. annotations_ORG.jsonl,
+    /// the documents of each file are restricted to the spans of its labels
+    /// and selected with the `documents` option of the output
+    fn save_per_label(
+        &self,
+        documents: &[Document],
+        output: &Output,
+        per_label: &PerLabel,
+        stamp: &Stamp,
+    ) -> Result<String, std::io::Error> {
+        let mut groups: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for label in documents
+            .iter()
+            .flat_map(|document| document.label.iter().map(|(_, _, label)| label))
+        {
+            let group = per_label
+                .groups
+                .iter()
+                .find(|(_, labels)| labels.contains(label))
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_else(|| label.to_string());
+            groups.entry(group).or_default().insert(label.to_string());
+        }
+        for (group, labels) in groups {
+            let restricted: Vec<Document> = documents
+                .iter()
+                .map(|document| {
+                    let mut document = document.clone();
+                    document
+                        .label
+                        .retain(|(_, _, label)| labels.contains(label));
+                    document
+                        .entity_meta
+                        .retain(|meta| labels.contains(&meta.label));
+                    document
+                        .span_meta
+                        .retain(|meta| labels.contains(&meta.label));
+                    document.retain_relations();
+                    document
+                })
+                .collect();
+            let path = Output::label_path(&output.path, &group);
+            let saved = self.save_stamped(
+                &restricted,
+                &Output {
+                    path: path.to_string_lossy().to_string(),
+                    min_label_count: None,
+                    per_label: None,
+                    split_by_label: false,
+                    ..output.clone()
+                },
+                stamp,
+            )?;
+            info!("Annotations of {} saved to {}", group, saved);
         }
-        path
+        Ok(Format::remove_extension_from_path(&output.path))
+    }
+
+    /// Path of the output without its extension, returned by the writers.
+    /// Only the extension of the file name is removed,
+    /// directories containing dots are kept as is.
+    fn remove_extension_from_path(path: &str) -> String {
+        Path::new(path)
+            .with_extension("")
+            .to_string_lossy()
+            .to_string()
     }
 
-    fn spacy(documents: &Vec<Document>, path: &str) -> Result<String, std::io::Error> {
+    /// Create the output file, replacing the extension of the file name.
+    /// The error includes the absolute path of the file.
+    fn create_file(path: &str, extension: &str) -> Result<fs::File, std::io::Error> {
+        let path = Path::new(path).with_extension(extension);
+        fs::File::create(long_path(&path)).map_err(|e| {
+            let path = std::path::absolute(&path).unwrap_or(path);
+            std::io::Error::new(
+                e.kind(),
+                format!("Unable to create {}: {}", path.display(), e),
+            )
+        })
+    }
+
+    fn spacy(
+        documents: &Vec<Document>,
+        path: &str,
+        options: &JsonOptions,
+    ) -> Result<String, std::io::Error> {
         // Save as such [["text", {"entity": [[0, 4, "ORG"], [5, 10, "ORG"]]}]]
 
         // Transform Vec<(String, HashMap<String, Vec<(usize, usize, String)>>)> into Structure
 
-        let path = Format::remove_extension_from_path(path);
-        let mut file = std::fs::File::create(format!("{path}.json"))?;
+        let mut file = Format::create_file(path, "json")?;
         let annotations_tranformed: Vec<(String, SpacyEntity)> = documents
             .into_iter()
             .map(|annotation| {
@@ -68,87 +490,277 @@ impl Format {
                     (*annotation.text).to_string(),
                     SpacyEntity {
                         entity: (*annotation.label).to_vec(),
+                        meta: annotation.meta.clone(),
                     },
                 )
             })
             .collect();
-        let json = serde_json::to_string(&annotations_tranformed).unwrap();
+        let json = options.to_string(&annotations_tranformed, options.pretty)?;
         file.write_all(json.as_bytes())?;
-        Ok(path)
+        Ok(Format::remove_extension_from_path(path))
     }
 
-    fn jsonl(documents: &Vec<Document>, path: &str) -> Result<String, std::io::Error> {
+    fn jsonl(
+        documents: &[Document],
+        path: &str,
+        fields: &Fields,
+        options: &JsonOptions,
+        compression: &Compression,
+        stamp: Option<&Stamp>,
+    ) -> Result<String, std::io::Error> {
         // Save as such {"text": "text", "label": [[0, 4, "ORG"], [5, 10, "ORG"]]}
-        let path = Format::remove_extension_from_path(path);
-        let mut file = std::fs::File::create(format!("{path}.jsonl"))?;
-        for document in documents {
-            let json = serde_json::to_string(&document).unwrap();
-            file.write_all(json.as_bytes())?;
+        // using the configured field names
+        let mut file = JsonlWriter::create(path, compression)?;
+        if let Some(stamp) = stamp {
+            file.write_all(stamp.record()?.as_bytes())?;
             file.write_all(b"\n")?;
         }
-        Ok(path)
+        let mut written = 0;
+        for chunk in documents.chunks(JSONL_CHUNK_SIZE) {
+            if is_cancelled() {
+                file.finish()?;
+                return Err(JsonlWriter::move_to_partial(path, compression, written));
+            }
+            for line in Format::jsonl_lines(chunk, fields, options)? {
+                file.write_all(line.as_bytes())?;
+                file.write_all(b"\n")?;
+            }
+            written += chunk.len();
+        }
+        file.finish()?;
+        Ok(Format::remove_extension_from_path(path))
     }
 
+    /// Append the documents to the uncompressed JSONL output, created if missing,
+    /// with the fields, JSON options, document selection and minimum confidence
+    /// of the output, e.g. in the watch mode. Returns the path without extension
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub(crate) fn append_jsonl(
+        documents: &[Document],
+        output: &Output,
+    ) -> Result<String, std::io::Error> {
+        let mut documents = Cow::Borrowed(documents);
+        if let Some(min_confidence) = output.min_confidence {
+            documents = Cow::Owned(Output::apply_min_confidence(&documents, min_confidence));
+        }
+        match output.documents {
+            DocumentSelection::All => {}
+            DocumentSelection::Annotated => documents
+                .to_mut()
+                .retain(|document| !document.label.is_empty()),
+            DocumentSelection::Unannotated => documents
+                .to_mut()
+                .retain(|document| document.label.is_empty()),
+        };
+        output.create_directory()?;
+        let path = Path::new(&output.path).with_extension("jsonl");
+        let mut file = BufWriter::new(
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(long_path(&path))?,
+        );
+        for line in Format::jsonl_lines(&documents, &output.fields, &output.json)? {
+            file.write_all(line.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(Format::remove_extension_from_path(&output.path))
+    }
+
+    /// Serialize the documents to JSONL lines in parallel, keeping their order
+    fn jsonl_lines(
+        documents: &[Document],
+        fields: &Fields,
+        options: &JsonOptions,
+    ) -> Result<Vec<String>, std::io::Error> {
+        documents
+            .par_iter()
+            .map(|document| {
+                let document = fields.to_external(serde_json::to_value(document)?);
+                options.to_string(&document, false)
+            })
+            .collect()
+    }
+
+    /// Same output as `jsonl`, written by a writer thread fed by a bounded queue.
+    /// The documents are serialized in parallel batches of `queue_size` documents,
+    /// the order of the documents is kept and the workers wait when the writer
+    /// is `queue_size` lines behind. The time spent waiting on each side is logged.
+    fn jsonl_streaming(
+        documents: &[Document],
+        path: &str,
+        fields: &Fields,
+        options: &JsonOptions,
+        compression: &Compression,
+        streaming: &Streaming,
+        stamp: Option<&Stamp>,
+    ) -> Result<String, std::io::Error> {
+        let mut file = JsonlWriter::create(path, compression)?;
+        if let Some(stamp) = stamp {
+            file.write_all(stamp.record()?.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        let queue_size = streaming.queue_size.max(1);
+        let (sender, receiver) = mpsc::sync_channel::<String>(queue_size);
+        let writer = thread::spawn(move || -> Result<Duration, std::io::Error> {
+            let mut idle = Duration::ZERO;
+            loop {
+                let waiting = Instant::now();
+                let line = match receiver.recv() {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                idle += waiting.elapsed();
+                file.write_all(line.as_bytes())?;
+                file.write_all(b"\n")?;
+            }
+            file.finish()?;
+            Ok(idle)
+        });
+        let mut stall = Duration::ZERO;
+        let mut written = 0;
+        let mut cancelled = false;
+        'batches: for batch in documents.chunks(queue_size) {
+            if is_cancelled() {
+                cancelled = true;
+                break;
+            }
+            for line in Format::jsonl_lines(batch, fields, options)? {
+                let waiting = Instant::now();
+                // The writer stopped on an error, it is returned when joining it
+                if sender.send(line).is_err() {
+                    break 'batches;
+                }
+                stall += waiting.elapsed();
+            }
+            written += batch.len();
+        }
+        drop(sender);
+        let idle = writer
+            .join()
+            .map_err(|_| std::io::Error::other("The writer thread panicked"))??;
+        if cancelled {
+            return Err(JsonlWriter::move_to_partial(path, compression, written));
+        }
+        info!(
+            "{} documents streamed (queue size {}): workers stalled {:.2?} waiting for the writer, writer idle {:.2?}",
+            documents.len(),
+            queue_size,
+            stall,
+            idle
+        );
+        Ok(Format::remove_extension_from_path(path))
+    }
+
+    /// Columns of the CSV output
+    const CSV_HEADER: [&'static str; 6] = ["id", "text", "start", "end", "label", "span_text"];
+
     fn csv(documents: &Vec<Document>, path: &str) -> Result<String, std::io::Error> {
-        // Save as such "text", "label"
-        let path = Format::remove_extension_from_path(path);
-        let mut file = std::fs::File::create(format!("{path}.csv"))?;
+        // Save a row per span, and a row without span for the documents without spans
+        let mut writer = csv::Writer::from_writer(Format::create_file(path, "csv")?);
+        writer.write_record(Format::CSV_HEADER)?;
         for document in documents {
-            let json = serde_json::to_string(&document).unwrap();
-            file.write_all(json.as_bytes())?;
-            file.write_all(b"\n")?;
+            let text = &document.text;
+            if document.label.is_empty() {
+                writer.write_record([document.id.as_str(), text, "", "", "", ""])?;
+            }
+            for (start, end, label) in &document.label {
+                let (first, last) = char_to_byte(text, *start, *end);
+                writer.write_record([
+                    document.id.as_str(),
+                    text,
+                    &start.to_string(),
+                    &end.to_string(),
+                    label,
+                    &text[first..last],
+                ])?;
+            }
         }
-        Ok(path)
+        writer.flush()?;
+        Ok(Format::remove_extension_from_path(path))
     }
 
-    fn brat(documents: &Vec<Document>, path: &str) -> Result<String, std::io::Error> {
+    fn brat(
+        documents: &Vec<Document>,
+        path: &str,
+        stamp: Option<&Stamp>,
+    ) -> Result<String, std::io::Error> {
         // Save .ann and .txt files
-        let path = Format::remove_extension_from_path(path);
-        let mut file_ann = std::fs::File::create(format!("{path}.ann"))?;
-        let mut file_txt = std::fs::File::create(format!("{path}.txt"))?;
+        let mut file_ann = Format::create_file(path, "ann")?;
+        let mut file_txt = Format::create_file(path, "txt")?;
+        // Brat notes are attached to an annotation, the stamp goes on the first one
+        let annotated = documents.iter().any(|document| !document.label.is_empty());
+        if let Some(stamp) = stamp.filter(|_| annotated) {
+            let line = format!("#0\tAnnotatorNotes T0\t{}", stamp.note());
+            file_ann.write_all(line.as_bytes())?;
+            file_ann.write_all(b"\n")?;
+        }
         for document in documents {
             let text = &document.text;
             file_txt.write_all(text.as_bytes())?;
             file_txt.write_all(b"\n")?;
             for (id, (start, end, label)) in (*document.label).to_vec().into_iter().enumerate() {
-                let entity = text[start..end].to_string();
+                let (first, last) = char_to_byte(text, start, end);
+                let entity = text[first..last].to_string();
                 let line = format!("T{id}\t{label}\t{start}\t{end}\t{entity}");
                 file_ann.write_all(line.as_bytes())?;
                 file_ann.write_all(b"\n")?;
             }
+            // The relations refer to the spans by the ids of their T lines
+            let span_id = |span: &(usize, usize, String)| {
+                document.label.iter().position(|other| other == span)
+            };
+            for (id, relation) in document.relations.iter().enumerate() {
+                let (Some(head), Some(tail)) =
+                    (span_id(&relation.head_span), span_id(&relation.tail_span))
+                else {
+                    continue;
+                };
+                let line = format!("R{id}\t{} Arg1:T{head} Arg2:T{tail}", relation.label);
+                file_ann.write_all(line.as_bytes())?;
+                file_ann.write_all(b"\n")?;
+            }
         }
-        Ok(path)
+        Ok(Format::remove_extension_from_path(path))
     }
 
     fn conll(documents: &Vec<Document>, path: &str) -> Result<String, std::io::Error> {
         // for reference: https://simpletransformers.ai/docs/ner-data-formats/
-        let path = Format::remove_extension_from_path(path);
-        let mut file = std::fs::File::create(format!("{path}.txt"))?;
+        let mut file = Format::create_file(path, "txt")?;
         let annotations_tranformed: Vec<Vec<(String, String)>> = documents
-            .into_iter()
+            .iter()
             .map(|annotation| {
-                let text = &annotation.text;
-                // Split text into words
-                let words: Vec<&str> = text.split_whitespace().collect();
-                // If the word is not associated with an entity, then it is an "O"
-                let mut labels: Vec<String> = vec!["O".to_string(); words.len()];
-                // For each entity, find the word that contains it and assign the label to it
-                for (start, end, label) in (*annotation.label).to_vec() {
-                    let entity = text[start..end].to_string();
-                    // Find the index of the word that contains the entity
-                    let index = words.iter().position(|&word| word.contains(&entity));
-                    if index.is_none() {
-                        continue;
+                // Words with their character offsets, the offsets of the spans
+                let mut words: Vec<(String, usize, usize)> = Vec::new();
+                let mut word = String::new();
+                let mut start = 0;
+                for (index, c) in annotation.text.chars().enumerate() {
+                    if !c.is_whitespace() {
+                        if word.is_empty() {
+                            start = index;
+                        }
+                        word.push(c);
+                    } else if !word.is_empty() {
+                        words.push((std::mem::take(&mut word), start, index));
                     }
-                    let index = index.unwrap();
-                    // If the word is the same as the entity, then it is a "B" label
-                    labels[index] = label;
                 }
-                // Combine the words and labels into a single vector
+                if !word.is_empty() {
+                    let end = start + word.chars().count();
+                    words.push((word, start, end));
+                }
+                // A word takes the label of the first span overlapping it, "O" otherwise
                 words
-                    .iter()
-                    .zip(labels.iter())
-                    .map(|(word, label)| (word.to_string(), label.to_string()))
+                    .into_iter()
+                    .map(|(word, start, end)| {
+                        let label = annotation
+                            .label
+                            .iter()
+                            .find(|span| span.0 < end && start < span.1)
+                            .map_or("O", |span| span.2.as_str());
+                        (word, label.to_string())
+                    })
                     .collect()
             })
             .collect();
@@ -161,6 +773,1353 @@ impl Format {
             }
             file.write_all(b"\n")?;
         }
-        Ok(path)
+        Ok(Format::remove_extension_from_path(path))
+    }
+
+    /// Save a JSON record per line with the id, text and metadata of each
+    /// document and its tokens as (token, start, end, BIO tag), the offsets in
+    /// characters, so the training data keeps the tokenization of the annotations
+    fn tokens(documents: &[Document], path: &str) -> Result<String, std::io::Error> {
+        let mut file = BufWriter::new(Format::create_file(path, "tokens.jsonl")?);
+        let lines: Vec<String> = documents
+            .par_iter()
+            .map(|document| {
+                serde_json::to_string(&TokensRecord {
+                    id: Cow::Borrowed(&document.id),
+                    text: Cow::Borrowed(&document.text),
+                    tokens: document.tokens(),
+                    meta: Cow::Borrowed(&document.meta),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        for line in lines {
+            file.write_all(line.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(Format::remove_extension_from_path(path))
+    }
+
+    /// Save standalone HTML pages with the spans highlighted, a color per label,
+    /// and a legend of the labels with their number of spans, to review the
+    /// annotations in a browser. The documents are written to a single page,
+    /// or to numbered pages linked to each other if `documents_per_page` is set.
+    /// The colors of the labels are the same on every page.
+    fn html(
+        documents: &[Document],
+        path: &str,
+        options: &HtmlOptions,
+    ) -> Result<String, std::io::Error> {
+        let all: Vec<&Document> = documents.iter().collect();
+        let colors = label_colors(&all);
+        let per_page = options
+            .documents_per_page
+            .filter(|per_page| *per_page > 0)
+            .unwrap_or(documents.len())
+            .max(1);
+        let pages: Vec<&[Document]> = match documents.is_empty() {
+            true => vec![&[]],
+            false => documents.chunks(per_page).collect(),
+        };
+        let paged = options.documents_per_page.is_some();
+        let page_path = |number: usize| match paged {
+            true => Output::label_path(path, &number.to_string())
+                .to_string_lossy()
+                .to_string(),
+            false => path.to_string(),
+        };
+        let page_name = |number: usize| {
+            Path::new(&page_path(number))
+                .with_extension("html")
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        for (index, page) in pages.iter().enumerate() {
+            let number = index + 1;
+            let mut file = BufWriter::new(Format::create_file(&page_path(number), "html")?);
+            let title = escape_html(&options.title);
+            write!(
+                file,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+            )?;
+            let spans: usize = page.iter().map(|document| document.label.len()).sum();
+            write!(
+                file,
+                "<p class=\"summary\">{} documents, {} spans",
+                page.len(),
+                spans
+            )?;
+            if paged {
+                write!(file, ", page {} of {}", number, pages.len())?;
+            }
+            writeln!(file, "</p>")?;
+            if paged {
+                write!(file, "<nav>")?;
+                if number > 1 {
+                    write!(file, "<a href=\"{}\">previous</a> ", page_name(number - 1))?;
+                }
+                if number < pages.len() {
+                    write!(file, "<a href=\"{}\">next</a>", page_name(number + 1))?;
+                }
+                writeln!(file, "</nav>")?;
+            }
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for (_, _, label) in page.iter().flat_map(|document| &document.label) {
+                *counts.entry(label.as_str()).or_default() += 1;
+            }
+            write!(file, "<div class=\"legend\">")?;
+            let display = label_display();
+            for (label, count) in counts {
+                let color = colors.get(label).copied().unwrap_or(TermColor::ALL[0]);
+                write!(
+                    file,
+                    "<mark class=\"entity\" style=\"background: {}\">{}<span class=\"label\">{}</span></mark>",
+                    color.html(),
+                    escape_html(display_name(&display, label)),
+                    count
+                )?;
+            }
+            writeln!(file, "</div>")?;
+            for document in page.iter() {
+                writeln!(
+                    file,
+                    "<div class=\"document\" id=\"{id}\">\n<div class=\"id\">{id}</div>\n<div class=\"text\">{}</div>\n</div>",
+                    document.render_html_with_colors(Some(0), &colors),
+                    id = escape_html(&document.id),
+                )?;
+            }
+            writeln!(file, "</body>\n</html>")?;
+            file.flush()?;
+        }
+        Ok(Format::remove_extension_from_path(path))
+    }
+
+    /// Save the documents as NIF 2.0 in Turtle, the format of the entity linking
+    /// benchmarks (e.g. GERBIL): a nif:Context per document and a nif:Phrase per
+    /// span, with its label as itsrdf:taClassRef and the knowledge-base id of its
+    /// entity, if it has one, as itsrdf:taIdentRef. NIF offsets are in characters,
+    /// as the offsets of the spans.
+    fn nif(
+        documents: &[Document],
+        path: &str,
+        options: &NifOptions,
+    ) -> Result<String, std::io::Error> {
+        let mut file = BufWriter::new(Format::create_file(path, "ttl")?);
+        let base = &options.base_uri;
+        writeln!(
+            file,
+            "@prefix nif: <http://persistence.uni-leipzig.org/nlp2rdf/ontologies/nif-core#> .\n\
+             @prefix itsrdf: <http://www.w3.org/2005/11/its/rdf#> .\n\
+             @prefix xsd: <http://www.w3.org/2001/XMLSchema#> ."
+        )?;
+        for document in documents {
+            let uri = format!("{base}documents/{}", iri_escape(&document.id));
+            let length = document.text.chars().count();
+            let context = format!("<{uri}#char=0,{length}>");
+            writeln!(
+                file,
+                "\n{context} a nif:Context , nif:String , nif:RFC5147String ;\n    \
+                 nif:isString \"{}\" ;\n    \
+                 nif:beginIndex \"0\"^^xsd:nonNegativeInteger ;\n    \
+                 nif:endIndex \"{length}\"^^xsd:nonNegativeInteger .",
+                turtle_escape(&document.text)
+            )?;
+            for span in &document.label {
+                let (start, end) = char_to_byte(&document.text, span.0, span.1);
+                let id = document
+                    .entity_meta
+                    .iter()
+                    .find(|meta| meta.start == span.0 && meta.end == span.1 && meta.label == span.2)
+                    .and_then(|meta| meta.id.as_deref());
+                write!(
+                    file,
+                    "\n<{uri}#char={},{}> a nif:Phrase , nif:String , nif:RFC5147String ;\n    \
+                     nif:referenceContext {context} ;\n    \
+                     nif:anchorOf \"{}\" ;\n    \
+                     nif:beginIndex \"{}\"^^xsd:nonNegativeInteger ;\n    \
+                     nif:endIndex \"{}\"^^xsd:nonNegativeInteger ;\n    \
+                     itsrdf:taClassRef <{base}labels/{}>",
+                    span.0,
+                    span.1,
+                    turtle_escape(document.text.get(start..end).unwrap_or_default()),
+                    span.0,
+                    span.1,
+                    iri_escape(&span.2)
+                )?;
+                if let Some(id) = id {
+                    let id = match (id.contains("://"), &options.kb_uri) {
+                        (true, _) => iri_escape(id),
+                        (false, Some(kb_uri)) => format!("{kb_uri}{}", iri_escape(id)),
+                        (false, None) => format!("{base}entities/{}", iri_escape(id)),
+                    };
+                    write!(file, " ;\n    itsrdf:taIdentRef <{id}>")?;
+                }
+                writeln!(file, " .")?;
+            }
+        }
+        file.flush()?;
+        Ok(Format::remove_extension_from_path(path))
+    }
+
+    /// Extension of the files written in the format, the JSONL files
+    /// compressed with gzip end with .jsonl.gz
+    pub fn extension(&self, compression: &Compression) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl if *compression == Compression::Gzip => "jsonl.gz",
+            Format::Jsonl => "jsonl",
+            Format::Spacy => "json",
+            Format::Brat => "ann",
+            Format::Conll => "txt",
+            Format::Html => "html",
+            Format::Nif => "ttl",
+            Format::Tokens => "tokens.jsonl",
+        }
+    }
+
+    /// Read back the documents saved with `save_with_options` and the same output.
+    /// The formats do not all keep everything: the ids are derived from the texts
+    /// outside of the JSONL and CSV files, the metadata is lost by brat and CoNLL,
+    /// and CoNLL only keeps the words of the texts and the labeled words.
+    /// Files written per label are not read.
+    /// # Errors
+    /// Returns an error if the files cannot be read or parsed
+    pub fn load(&self, output: &Output) -> Result<Vec<Document>, std::io::Error> {
+        let path = Path::new(&output.path);
+        let file = |extension: &str| path.with_extension(extension).to_string_lossy().to_string();
+        let documents = match self {
+            Format::Jsonl => {
+                Format::read_jsonl(&file(self.extension(&output.compression)), &output.fields)
+            }
+            Format::Spacy => Format::read_spacy(&file("json")),
+            Format::Csv => Format::read_csv(&file("csv")),
+            Format::Brat => Format::read_brat(&file("ann"), &file("txt")),
+            Format::Conll => Format::read_conll(&file("txt")),
+            Format::Tokens => Format::read_tokens(&file("tokens.jsonl")),
+            Format::Html | Format::Nif => Err(Format::unreadable(self)),
+        };
+        documents.map(Format::imported)
+    }
+
+    /// Read the documents of a single file in the format, the .ann file of a brat
+    /// pair is read with the .txt file next to it. JSONL files use the default fields.
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn read(&self, path: &Path) -> Result<Vec<Document>, std::io::Error> {
+        let file = path.to_string_lossy();
+        let documents = match self {
+            Format::Jsonl => Format::read_jsonl(&file, &Fields::default()),
+            Format::Spacy => Format::read_spacy(&file),
+            Format::Csv => Format::read_csv(&file),
+            Format::Brat => Format::read_brat(&file, &path.with_extension("txt").to_string_lossy()),
+            Format::Conll => Format::read_conll(&file),
+            Format::Tokens => Format::read_tokens(&file),
+            Format::Html | Format::Nif => Err(Format::unreadable(self)),
+        };
+        documents.map(Format::imported)
+    }
+
+    /// Documents read, with the spans of unknown provenance recorded as imported
+    pub(crate) fn imported(mut documents: Vec<Document>) -> Vec<Document> {
+        for document in &mut documents {
+            document.record_imported();
+        }
+        documents
+    }
+
+    /// Error of the formats written for reviews only, which cannot be read back
+    fn unreadable(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("The {self} format cannot be read back"),
+        )
+    }
+
+    /// Guess the format of an annotation file from its extension, and from its
+    /// first lines when the extension is ambiguous:
+    /// - .tokens.jsonl files are tokens, the other .jsonl and .jsonl.gz files
+    ///   are JSONL, .json files spaCy
+    /// - .ann files are brat, read with the .txt file of the same name
+    /// - .conll, .iob and .bio files are CoNLL, as are the .txt files that are not
+    ///   the text of a brat pair and hold a word and a label per line
+    /// - .csv files with the columns of the CSV output of quickner are CSV
+    ///
+    /// Returns None for the other files, e.g. plain texts.
+    pub fn detect(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tokens.jsonl") {
+            return Some(Format::Tokens);
+        }
+        if name.ends_with(".jsonl") || name.ends_with(".jsonl.gz") {
+            return Some(Format::Jsonl);
+        }
+        match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+            "json" => Some(Format::Spacy),
+            "ann" if path.with_extension("txt").is_file() => Some(Format::Brat),
+            "conll" | "iob" | "bio" => Some(Format::Conll),
+            "txt" if !path.with_extension("ann").is_file() => {
+                let lines = Format::first_lines(path, 20)?;
+                let conll = lines
+                    .iter()
+                    .all(|line| line.trim().is_empty() || line.split_whitespace().count() == 2);
+                (conll && lines.iter().any(|line| !line.trim().is_empty())).then_some(Format::Conll)
+            }
+            "csv" => {
+                let lines = Format::first_lines(path, 1)?;
+                let header = lines.first()?.trim_start_matches('\u{feff}').trim_end();
+                (header == Format::CSV_HEADER.join(",")).then_some(Format::Csv)
+            }
+            _ => None,
+        }
+    }
+
+    /// First lines of a text file, None if it cannot be read as UTF-8
+    fn first_lines(path: &Path, count: usize) -> Option<Vec<String>> {
+        let reader = BufReader::new(fs::File::open(long_path(path)).ok()?);
+        reader.lines().take(count).collect::<Result<_, _>>().ok()
+    }
+
+    fn invalid(path: &str, number: usize, message: &str) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid record at {}:{}: {}", path, number + 1, message),
+        )
+    }
+
+    /// Read the documents of a JSONL file with the given field names,
+    /// the ids missing from the file are derived from the texts and the extra
+    /// fields are moved to the metadata. Files ending with .gz are decompressed.
+    pub(crate) fn read_jsonl(path: &str, fields: &Fields) -> Result<Vec<Document>, std::io::Error> {
+        let file = fs::File::open(long_path(path))?;
+        let reader: Box<dyn BufRead> = match path.ends_with(".gz") {
+            true => Box::new(BufReader::new(GzDecoder::new(file))),
+            false => Box::new(BufReader::new(file)),
+        };
+        let mut documents = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value = serde_json::from_str::<Value>(&line)
+                .map_err(|e| Format::invalid(path, number, &e.to_string()))?;
+            // The provenance stamp written as a header record is not a document
+            if Stamp::is_record(&value) {
+                continue;
+            }
+            let mut value = fields.to_internal(value);
+            if let Value::Object(ref mut object) = value {
+                if !object.contains_key("id") {
+                    let id = object
+                        .get("text")
+                        .and_then(|text| text.as_str())
+                        .map(hash_string)
+                        .unwrap_or_default();
+                    object.insert("id".to_string(), Value::String(id));
+                }
+            }
+            let document: Document = serde_json::from_value(value)
+                .map_err(|e| Format::invalid(path, number, &e.to_string()))?;
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    fn read_spacy(path: &str) -> Result<Vec<Document>, std::io::Error> {
+        let reader = BufReader::new(fs::File::open(long_path(path))?);
+        let spacy: Vec<(String, SpacyEntity)> = serde_json::from_reader(reader)?;
+        Ok(spacy
+            .into_iter()
+            .map(|(text, entity)| Document {
+                meta: entity.meta,
+                ..Document::new(text, entity.entity)
+            })
+            .collect())
+    }
+
+    /// The CSV output holds a row per span, the consecutive rows of the same id
+    /// are the spans of a document, and a row without start is a document without spans
+    fn read_csv(path: &str) -> Result<Vec<Document>, std::io::Error> {
+        let mut reader = csv::Reader::from_reader(fs::File::open(long_path(path))?);
+        let mut documents: Vec<Document> = Vec::new();
+        for (number, record) in reader.records().enumerate() {
+            let record = record?;
+            // The header is the first line
+            let number = number + 1;
+            let column = |name: &str| {
+                let index = Format::CSV_HEADER.iter().position(|column| *column == name);
+                index
+                    .and_then(|index| record.get(index))
+                    .unwrap_or_default()
+            };
+            let (id, text) = (column("id"), column("text"));
+            if documents.last().is_none_or(|document| document.id != id) {
+                documents.push(Document {
+                    id: id.to_string(),
+                    ..Document::new(text.to_string(), Vec::new())
+                });
+            }
+            if column("start").is_empty() {
+                continue;
+            }
+            let offset = |name: &str| {
+                column(name).parse::<usize>().map_err(|e| {
+                    Format::invalid(path, number, &format!("{name} {:?}: {e}", column(name)))
+                })
+            };
+            let span = (
+                offset("start")?,
+                offset("end")?,
+                column("label").to_string(),
+            );
+            if let Some(document) = documents.last_mut() {
+                document.label.push(span);
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Read the texts of the .txt file, one per line, and their spans from the .ann
+    /// file. The offsets of the spans are relative to their text and the ids of the
+    /// spans start again at T0 with each text: a span goes to the next text
+    /// (starting with the next one after a T0) where its offsets cover its entity.
+    /// The relations (R lines) go to the text of the spans they refer to.
+    fn read_brat(ann: &str, txt: &str) -> Result<Vec<Document>, std::io::Error> {
+        let texts = fs::read_to_string(long_path(txt))?;
+        let mut documents: Vec<Document> = texts
+            .lines()
+            .map(|text| Document::new(text.to_string(), Vec::new()))
+            .collect();
+        let annotations = fs::read_to_string(long_path(ann))?;
+        let mut current: Option<usize> = None;
+        // Spans of the current text by the id of their T line
+        let mut spans: HashMap<&str, (usize, usize, String)> = HashMap::new();
+        for (number, line) in annotations.lines().enumerate() {
+            // Notes (e.g. the provenance stamp) are not spans
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if line.starts_with('R') {
+                let relation = match fields.as_slice() {
+                    [_, relation] => Format::brat_relation(relation, &spans),
+                    _ => None,
+                };
+                match (relation, current) {
+                    (Some(relation), Some(current)) => documents[current].relations.push(relation),
+                    _ => return Err(Format::invalid(ann, number, "invalid relation")),
+                }
+                continue;
+            }
+            // Standard brat lines have "LABEL START END" in the second field
+            let fields: Vec<&str> = match fields.len() {
+                3 => std::iter::once(fields[0])
+                    .chain(fields[1].split(' '))
+                    .chain(std::iter::once(fields[2]))
+                    .collect(),
+                _ => fields,
+            };
+            let (id, label, start, end, entity) = match fields.as_slice() {
+                [id, label, start, end, entity] => (*id, *label, *start, *end, *entity),
+                _ => return Err(Format::invalid(ann, number, "expected 5 fields")),
+            };
+            let offset = |offset: &str| {
+                offset
+                    .parse::<usize>()
+                    .map_err(|e| Format::invalid(ann, number, &e.to_string()))
+            };
+            let (start, end) = (offset(start)?, offset(end)?);
+            let first = match (current, id == "T0") {
+                (None, _) => 0,
+                (Some(current), true) => current + 1,
+                (Some(current), false) => current,
+            };
+            let covers = |document: &Document| {
+                let (start, end) = char_to_byte(&document.text, start, end);
+                document.text.get(start..end) == Some(entity)
+            };
+            let index = (first..documents.len())
+                .find(|index| covers(&documents[*index]))
+                .ok_or_else(|| Format::invalid(ann, number, "no text contains the span"))?;
+            if current != Some(index) {
+                spans.clear();
+            }
+            spans.insert(id, (start, end, label.to_string()));
+            documents[index].label.push((start, end, label.to_string()));
+            current = Some(index);
+        }
+        Ok(documents)
+    }
+
+    /// Relation of a brat R line, "LABEL Arg1:T0 Arg2:T1",
+    /// None if it refers to a span not in the text
+    fn brat_relation(
+        relation: &str,
+        spans: &HashMap<&str, (usize, usize, String)>,
+    ) -> Option<Relation> {
+        let mut fields = relation.split(' ');
+        let label = fields.next()?;
+        let mut span = |arg: &str| {
+            let id = fields.next()?.strip_prefix(arg)?;
+            spans.get(id).cloned()
+        };
+        let (head_span, tail_span) = (span("Arg1:")?, span("Arg2:")?);
+        Some(Relation {
+            head_span,
+            tail_span,
+            label: label.to_string(),
+        })
+    }
+
+    /// Read the words and labels of a CoNLL file, one word per line followed by
+    /// its label, the documents are separated by a blank line. The words are joined
+    /// by spaces and consecutive words with the same label are merged in a span.
+    fn read_conll(path: &str) -> Result<Vec<Document>, std::io::Error> {
+        let reader = BufReader::new(fs::File::open(long_path(path))?);
+        let mut documents = Vec::new();
+        let mut words: Vec<(String, String)> = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                documents.push(Format::conll_document(std::mem::take(&mut words)));
+                continue;
+            }
+            match line.rsplit_once(['\t', ' ']) {
+                Some((word, label)) => words.push((word.to_string(), label.to_string())),
+                None => return Err(Format::invalid(path, number, "expected a word and a label")),
+            }
+        }
+        if !words.is_empty() {
+            documents.push(Format::conll_document(words));
+        }
+        Ok(documents)
+    }
+
+    /// Read the tokens output, the spans are rebuilt from the BIO tags
+    fn read_tokens(path: &str) -> Result<Vec<Document>, std::io::Error> {
+        let reader = BufReader::new(fs::File::open(long_path(path))?);
+        let mut documents = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: TokensRecord = serde_json::from_str(&line)
+                .map_err(|e| Format::invalid(path, number, &e.to_string()))?;
+            let mut spans: Vec<(usize, usize, String)> = Vec::new();
+            // A span goes on while the next tokens are inside it
+            let mut inside = false;
+            for (_, start, end, tag) in record.tokens {
+                match (tag.split_once('-'), spans.last_mut()) {
+                    (Some(("I", label)), Some(span)) if inside && span.2 == label => span.1 = end,
+                    (Some(("B" | "I", label)), _) => spans.push((start, end, label.to_string())),
+                    _ => {
+                        inside = false;
+                        continue;
+                    }
+                }
+                inside = true;
+            }
+            let mut document = Document::new(record.text.into_owned(), spans);
+            document.id = record.id.into_owned();
+            document.meta = record.meta.into_owned();
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    fn conll_document(words: Vec<(String, String)>) -> Document {
+        let mut text = String::new();
+        let mut spans: Vec<(usize, usize, String)> = Vec::new();
+        let mut position = 0;
+        for (index, (word, label)) in words.into_iter().enumerate() {
+            if index > 0 {
+                text.push(' ');
+                position += 1;
+            }
+            let end = position + word.chars().count();
+            // The label of the previous word goes on if it is the same
+            match spans.last_mut() {
+                Some(span) if label != "O" && span.2 == label && span.1 + 1 == position => {
+                    span.1 = end
+                }
+                _ if label != "O" => spans.push((position, end, label)),
+                _ => {}
+            }
+            text.push_str(&word);
+            position = end;
+        }
+        Document::new(text, spans)
+    }
+}
+
+impl Route {
+    /// Whether every key of `when` is in the metadata of the document with the same value
+    pub fn matches(&self, document: &Document) -> bool {
+        self.when
+            .iter()
+            .all(|(key, value)| document.meta.get(key) == Some(value))
+    }
+}
+
+impl Rule {
+    pub(crate) fn default_window() -> usize {
+        10
+    }
+
+    /// Apply the rules, in order, to the spans found in a text.
+    /// Rules compare the span text to their pattern, and the context tokens
+    /// to their terms, ignoring the case unless `case_sensitive` is set.
+    pub fn apply(
+        rules: &[Rule],
+        text: &str,
+        spans: &mut Vec<(usize, usize, String)>,
+        case_sensitive: bool,
+    ) {
+        if rules.is_empty() || spans.is_empty() {
+            return;
+        }
+        let normalize = |text: &str| match case_sensitive {
+            true => text.to_string(),
+            false => text.to_lowercase(),
+        };
+        let chars: Vec<char> = text.chars().collect();
+        let tokens: Vec<(usize, usize, String)> = tokenize(text)
+            .into_iter()
+            .map(|(start, end)| {
+                let token: String = chars[start..end].iter().collect();
+                (start, end, normalize(&token))
+            })
+            .collect();
+        spans.retain_mut(|span| {
+            let surface: String = chars[span.0.min(chars.len())..span.1.min(chars.len())]
+                .iter()
+                .collect();
+            let surface = normalize(&surface);
+            for rule in rules {
+                if rule.label != span.2 || normalize(&rule.pattern) != surface {
+                    continue;
+                }
+                let first = tokens.partition_point(|token| token.1 <= span.0);
+                let last = tokens.partition_point(|token| token.0 < span.1);
+                let before = &tokens[first.saturating_sub(rule.window)..first];
+                let after = &tokens[last..(last + rule.window).min(tokens.len())];
+                let appears = |term: &String| {
+                    let term: Vec<String> = normalize(term)
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    !term.is_empty()
+                        && [before, after].iter().any(|context| {
+                            context.windows(term.len()).any(|window| {
+                                window
+                                    .iter()
+                                    .zip(&term)
+                                    .all(|(token, word)| token.2 == *word)
+                            })
+                        })
+                };
+                let matched = (rule.require.is_empty() || rule.require.iter().any(appears))
+                    && !rule.forbid.iter().any(appears);
+                match (&rule.action, matched) {
+                    (RuleAction::Accept, false) | (RuleAction::Reject, true) => return false,
+                    (RuleAction::Redirect, true) => {
+                        if let Some(to) = &rule.to {
+                            span.2 = to.to_string();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            true
+        });
+    }
+}
+
+impl LabelPriority {
+    /// Keep a single label per span when several labels match the exact same span,
+    /// the first label of `order` wins. Labels missing from `order` come after
+    /// the listed ones, in alphabetical order.
+    /// Nothing is done if no priority is configured.
+    pub fn resolve(&self, spans: &mut Vec<(usize, usize, String)>) {
+        if self.order.is_empty() {
+            return;
+        }
+        spans.sort_by(|a, b| {
+            (a.0, a.1, self.rank(&a.2), &a.2).cmp(&(b.0, b.1, self.rank(&b.2), &b.2))
+        });
+        spans.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+    }
+
+    /// Position of the label in `order`, the labels missing from it come last
+    pub(crate) fn rank(&self, label: &str) -> usize {
+        self.order
+            .iter()
+            .position(|ordered| ordered == label)
+            .unwrap_or(self.order.len())
+    }
+}
+
+impl LabelAggregation {
+    /// Keep a single label per span when the annotators gave the exact same span
+    /// different labels, as set by the strategy, then resolve the label priority.
+    /// The votes for a label are the sources of its span in the `span_meta` of
+    /// the document (unknown for a span without one) and in `votes`, the spans
+    /// found again by another annotator. The span kept records the decision.
+    /// Nothing is done with the `priority` strategy and no label priority.
+    pub(crate) fn resolve(
+        &self,
+        priority: &LabelPriority,
+        document: &mut Document,
+        votes: &[Span],
+    ) {
+        let mut conflicts: BTreeMap<(usize, usize), BTreeSet<String>> = BTreeMap::new();
+        for span in &document.label {
+            conflicts
+                .entry((span.0, span.1))
+                .or_default()
+                .insert(span.2.clone());
+        }
+        conflicts.retain(|_, labels| labels.len() > 1);
+        if conflicts.is_empty()
+            || (self.strategy == AggregationStrategy::Priority && priority.order.is_empty())
+        {
+            priority.resolve(&mut document.label);
+            return;
+        }
+        let mut decisions = BTreeMap::new();
+        for ((start, end), labels) in conflicts {
+            let mut sources: BTreeMap<String, BTreeSet<SpanSource>> = labels
+                .into_iter()
+                .map(|label| (label, BTreeSet::new()))
+                .collect();
+            for span in document.span_meta.iter().chain(votes) {
+                if span.start == start && span.end == end {
+                    if let Some(sources) = sources.get_mut(&span.label) {
+                        sources.insert(span.source);
+                    }
+                }
+            }
+            let mut scores = BTreeMap::new();
+            for (label, sources) in sources.iter_mut() {
+                if sources.is_empty() {
+                    sources.insert(SpanSource::Unknown);
+                }
+                let score: f64 = sources.iter().map(|source| self.weight(*source)).sum();
+                scores.insert(label.clone(), score);
+            }
+            // The labels are sorted, the first one wins a tie
+            let kept = match self.strategy {
+                AggregationStrategy::Priority => {
+                    scores.keys().min_by_key(|label| priority.rank(label))
+                }
+                AggregationStrategy::Vote => scores
+                    .iter()
+                    .min_by(|a, b| {
+                        b.1.total_cmp(a.1)
+                            .then_with(|| priority.rank(a.0).cmp(&priority.rank(b.0)))
+                    })
+                    .map(|(label, _)| label),
+                AggregationStrategy::SkipConflicts => None,
+            }
+            .cloned();
+            let decision = kept.map(|label| {
+                let aggregation = Aggregation {
+                    strategy: self.strategy,
+                    sources: sources[&label].iter().copied().collect(),
+                    score: scores[&label],
+                    rejected: scores
+                        .iter()
+                        .filter(|(other, _)| **other != label)
+                        .map(|(other, score)| (other.clone(), *score))
+                        .collect(),
+                };
+                (label, aggregation)
+            });
+            decisions.insert((start, end), decision);
+        }
+        document
+            .label
+            .retain(|span| match decisions.get(&(span.0, span.1)) {
+                None => true,
+                Some(decision) => decision.as_ref().is_some_and(|(label, _)| *label == span.2),
+            });
+        for ((start, end), decision) in decisions {
+            let Some((label, aggregation)) = decision else {
+                continue;
+            };
+            match document
+                .span_meta
+                .iter_mut()
+                .find(|span| span.start == start && span.end == end && span.label == label)
+            {
+                Some(span) => span.aggregation = Some(aggregation),
+                None => document.span_meta.push(Span {
+                    aggregation: Some(aggregation),
+                    ..Span::from((start, end, label))
+                }),
+            }
+        }
+        priority.resolve(&mut document.label);
+    }
+
+    /// Weight of the votes of a source, 1 if not set
+    fn weight(&self, source: SpanSource) -> f64 {
+        self.weights.get(&source).copied().unwrap_or(1.0)
+    }
+}
+
+impl LabelPrecision {
+    /// Drop the spans of the high precision labels meeting none of their
+    /// conditions. The casing is read in the original text, the mentions are
+    /// the spans of the label with the same text in the text given to the
+    /// automaton, lowercased unless the matching is case sensitive.
+    pub fn apply(
+        precision: &BTreeMap<String, LabelPrecision>,
+        original: &str,
+        matched: &str,
+        spans: &mut Vec<(usize, usize, String)>,
+    ) {
+        if precision.is_empty() || spans.is_empty() {
+            return;
+        }
+        let surface = |text: &str, span: &(usize, usize, String)| {
+            let (start, end) = char_to_byte(text, span.0, span.1);
+            text[start..end].to_string()
+        };
+        let mut mentions: HashMap<(String, String), usize> = HashMap::new();
+        for span in spans.iter() {
+            if precision.contains_key(&span.2) {
+                *mentions
+                    .entry((surface(matched, span), span.2.clone()))
+                    .or_default() += 1;
+            }
+        }
+        spans.retain(|span| {
+            let Some(precision) = precision.get(&span.2) else {
+                return true;
+            };
+            (precision.title_case && is_title_case(&surface(original, span)))
+                || (precision.min_mentions > 0
+                    && mentions[&(surface(matched, span), span.2.clone())]
+                        >= precision.min_mentions)
+        });
+    }
+}
+
+/// Whether the first word and the words of more than 3 letters of a text
+/// start with a capital letter
+fn is_title_case(text: &str) -> bool {
+    let mut words = text.split_whitespace();
+    let starts_upper = |word: &str| word.chars().next().is_some_and(char::is_uppercase);
+    words.next().is_some_and(starts_upper)
+        && words.all(|word| word.chars().count() <= 3 || !word.starts_with(char::is_lowercase))
+}
+
+impl Output {
+    pub(crate) fn default_create_dirs() -> bool {
+        true
+    }
+
+    pub(crate) fn default_report() -> bool {
+        true
+    }
+
+    /// Path of the file of a label, the label is appended to the file stem,
+    /// e.g. annotations.jsonl and ORG give annotations_ORG.jsonl.
+    /// Characters of the label that are not safe in a file name are replaced by "_".
+    fn label_path(path: &str, label: &str) -> PathBuf {
+        let path = Path::new(path);
+        let label: String = label
+            .chars()
+            .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            })
+            .collect();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let file_name = match path.extension() {
+            Some(extension) => format!("{}_{}.{}", stem, label, extension.to_string_lossy()),
+            None => format!("{}_{}", stem, label),
+        };
+        path.with_file_name(file_name)
+    }
+
+    /// Path of the output of a cancelled run, e.g. annotations_partial.jsonl
+    pub(crate) fn partial_path(path: &str) -> String {
+        Output::label_path(path, "partial")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Create the missing directories of the output path if `create_dirs` is set,
+    /// otherwise fail with the absolute path of the missing directory.
+    fn create_directory(&self) -> Result<(), std::io::Error> {
+        let directory = match Path::new(&self.path).parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => return Ok(()),
+        };
+        if directory.is_dir() {
+            return Ok(());
+        }
+        let absolute = std::path::absolute(directory).unwrap_or(directory.to_path_buf());
+        if !self.create_dirs {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "The output directory {} does not exist, set create_dirs = true to create it",
+                    absolute.display()
+                ),
+            ));
+        }
+        fs::create_dir_all(long_path(directory)).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Unable to create the directory {}: {}",
+                    absolute.display(),
+                    e
+                ),
+            )
+        })?;
+        info!("Created the output directory {}", absolute.display());
+        Ok(())
+    }
+
+    /// Drop the spans with a confidence below `min_confidence`,
+    /// the spans without a confidence are kept
+    pub(crate) fn apply_min_confidence(
+        documents: &[Document],
+        min_confidence: f64,
+    ) -> Vec<Document> {
+        let mut documents = documents.to_vec();
+        let removed: usize = documents
+            .iter_mut()
+            .map(|document| {
+                document.retain_spans(|span| {
+                    span.confidence
+                        .is_none_or(|confidence| f64::from(confidence) >= min_confidence)
+                })
+            })
+            .sum();
+        if removed > 0 {
+            info!(
+                "{} spans with a confidence below {} dropped from the export",
+                removed, min_confidence
+            );
+        }
+        documents
+    }
+
+    /// Drop the labels with fewer than `min_label_count` spans,
+    /// or remap them to `rare_label` if it is set.
+    /// The labels dropped or remapped are reported in the logs.
+    pub(crate) fn apply_label_floor(
+        &self,
+        documents: &[Document],
+        min_label_count: usize,
+    ) -> Vec<Document> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for document in documents {
+            for (_, _, label) in &document.label {
+                *counts.entry(label.as_str()).or_default() += 1;
+            }
+        }
+        let rare: BTreeMap<String, usize> = counts
+            .into_iter()
+            .filter(|(_, count)| *count < min_label_count)
+            .map(|(label, count)| (label.to_string(), count))
+            .collect();
+        if rare.is_empty() {
+            return documents.to_vec();
+        }
+        let report = rare
+            .iter()
+            .map(|(label, count)| format!("{label}: {count}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+        match &self.rare_label {
+            Some(rare_label) => info!(
+                "Labels with fewer than {} spans remapped to {}: {}",
+                min_label_count, rare_label, report
+            ),
+            None => info!(
+                "Labels with fewer than {} spans dropped: {}",
+                min_label_count, report
+            ),
+        }
+        documents
+            .iter()
+            .map(|document| {
+                let mut document = document.clone();
+                document.label = document
+                    .label
+                    .into_iter()
+                    .filter_map(|(start, end, label)| {
+                        if !rare.contains_key(&label) {
+                            return Some((start, end, label));
+                        }
+                        self.rare_label
+                            .as_ref()
+                            .map(|rare_label| (start, end, rare_label.to_string()))
+                    })
+                    .collect();
+                document.entity_meta = document
+                    .entity_meta
+                    .into_iter()
+                    .filter_map(|mut meta| {
+                        if !rare.contains_key(&meta.label) {
+                            return Some(meta);
+                        }
+                        self.rare_label.as_ref().map(|rare_label| {
+                            meta.label = rare_label.to_string();
+                            meta
+                        })
+                    })
+                    .collect();
+                document.span_meta = document
+                    .span_meta
+                    .into_iter()
+                    .filter_map(|mut meta| {
+                        if !rare.contains_key(&meta.label) {
+                            return Some(meta);
+                        }
+                        self.rare_label.as_ref().map(|rare_label| {
+                            meta.label = rare_label.to_string();
+                            meta
+                        })
+                    })
+                    .collect();
+                let relabel = |span: &mut (usize, usize, String)| {
+                    if rare.contains_key(&span.2) {
+                        if let Some(rare_label) = &self.rare_label {
+                            span.2 = rare_label.to_string();
+                        }
+                    }
+                };
+                for relation in &mut document.relations {
+                    relabel(&mut relation.head_span);
+                    relabel(&mut relation.tail_span);
+                }
+                document.retain_relations();
+                document
+            })
+            .collect()
+    }
+}
+
+impl JsonOptions {
+    /// Serialize a value to a JSON string, pretty-printed or compact,
+    /// escaping non-ASCII characters if required
+    pub(crate) fn to_string<T: Serialize>(
+        &self,
+        value: &T,
+        pretty: bool,
+    ) -> Result<String, std::io::Error> {
+        let json = if pretty {
+            let indent = " ".repeat(self.indent);
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut buffer = Vec::new();
+            let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+            value.serialize(&mut serializer)?;
+            String::from_utf8(buffer).unwrap()
+        } else {
+            serde_json::to_string(value)?
+        };
+        if !self.ensure_ascii {
+            return Ok(json);
+        }
+        // Non-ASCII characters can only appear inside JSON strings,
+        // escaping them in place keeps the document valid
+        let mut escaped = String::with_capacity(json.len());
+        for c in json.chars() {
+            if c.is_ascii() {
+                escaped.push(c);
+            } else {
+                let mut buffer = [0u16; 2];
+                for unit in c.encode_utf16(&mut buffer) {
+                    escaped.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+        }
+        Ok(escaped)
+    }
+}
+
+impl Fields {
+    fn mapping(&self) -> [(&str, &str); 3] {
+        [
+            ("id", self.id.as_str()),
+            ("text", self.text.as_str()),
+            ("label", self.label.as_str()),
+        ]
+    }
+
+    /// Rename the fields of a serialized document to the configured field names
+    pub(crate) fn to_external(&self, value: Value) -> Value {
+        Fields::rename(value, self.mapping().into_iter())
+    }
+
+    /// Rename the configured field names back to the fields of a document
+    pub(crate) fn to_internal(&self, value: Value) -> Value {
+        Fields::rename(
+            value,
+            self.mapping()
+                .into_iter()
+                .map(|(internal, external)| (external, internal)),
+        )
+    }
+
+    fn rename<'a>(
+        value: Value,
+        mapping: impl Iterator<Item = (&'a str, &'a str)> + Clone,
+    ) -> Value {
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let key = mapping
+                            .clone()
+                            .find(|(from, _)| *from == key)
+                            .map(|(_, to)| to.to_string())
+                            .unwrap_or(key);
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+}
+
+impl EntitiesFormat {
+    /// Read entities from a file in the specified format
+    /// - csv and tsv files expect the columns: name, label, the optional columns
+    ///   id (or kb_id) and canonical are read as is, any other column is kept as an attribute.
+    ///   The optional aliases column lists other names of the entity separated by "|",
+    ///   each alias is an entity with the same label and metadata, whose canonical
+    ///   form is the one of the row (its name if not set)
+    /// - json files expect an array of objects: [{"name": "Rust", "label": "PL"}]
+    /// - toml files expect an array of tables: [[entities]] name = "Rust" label = "PL"
+    /// - txt files contain one entity per line, the label is the file name (e.g. PL.txt),
+    ///   if the path is a directory, every txt file in it is read
+    ///
+    /// The same name can be given several labels, either on several rows
+    /// or separated by "|" (ORG|PRODUCT), every label is annotated.
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub fn read(&self, path: &str) -> Result<Vec<Entity>, Box<dyn Error>> {
+        let entities = match self {
+            EntitiesFormat::Csv => EntitiesFormat::delimited(path, b','),
+            EntitiesFormat::Tsv => EntitiesFormat::delimited(path, b'\t'),
+            EntitiesFormat::Json => {
+                let file = fs::File::open(long_path(path))?;
+                Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+            }
+            EntitiesFormat::Toml => {
+                let content = fs::read_to_string(long_path(path))?;
+                let entities: TomlEntities = toml::from_str(&content)?;
+                Ok(entities.entities)
+            }
+            EntitiesFormat::Txt => {
+                let path = Path::new(path);
+                if !path.is_dir() {
+                    return EntitiesFormat::txt(path);
+                }
+                let mut entities = Vec::new();
+                for entry in fs::read_dir(long_path(path))? {
+                    let entry = entry?.path();
+                    if entry.extension().and_then(|extension| extension.to_str()) == Some("txt") {
+                        entities.extend(EntitiesFormat::txt(&entry)?);
+                    }
+                }
+                Ok(entities)
+            }
+        }?;
+        Ok(EntitiesFormat::split_labels(entities))
+    }
+
+    /// Write entities to a file in the specified format, read back by `read`
+    /// - csv and tsv files have the columns name and label, then id and canonical
+    ///   if an entity has one, then a column per attribute, sorted
+    /// - json files hold an array of objects, toml files an array of tables
+    /// - txt: the path is a directory, created if missing, with one file per
+    ///   label (e.g. PL.txt) listing its entities, the metadata is not written
+    ///
+    /// The entities are written in their order.
+    /// # Errors
+    /// Returns an error if the file cannot be written, or with txt if a label
+    /// cannot be a file name
+    pub fn write(&self, path: &str, entities: &[Entity]) -> Result<(), Box<dyn Error>> {
+        match self {
+            EntitiesFormat::Csv => EntitiesFormat::write_delimited(path, entities, b','),
+            EntitiesFormat::Tsv => EntitiesFormat::write_delimited(path, entities, b'\t'),
+            EntitiesFormat::Json => {
+                let file = BufWriter::new(fs::File::create(long_path(path))?);
+                Ok(serde_json::to_writer_pretty(file, entities)?)
+            }
+            EntitiesFormat::Toml => {
+                let entities = TomlEntities {
+                    entities: entities.to_vec(),
+                };
+                Ok(fs::write(long_path(path), toml::to_string(&entities)?)?)
+            }
+            EntitiesFormat::Txt => {
+                let mut labels: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+                for entity in entities {
+                    labels.entry(&entity.label).or_default().push(&entity.name);
+                }
+                fs::create_dir_all(long_path(path))?;
+                for (label, names) in labels {
+                    if label.is_empty() || label.contains(['/', '\\', '.']) {
+                        return Err(format!("The label \"{label}\" cannot be a file name").into());
+                    }
+                    let mut content = names.join("\n");
+                    content.push('\n');
+                    fs::write(
+                        long_path(Path::new(path).join(format!("{label}.txt"))),
+                        content,
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_delimited(
+        path: &str,
+        entities: &[Entity],
+        delimiter: u8,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_path(long_path(path))?;
+        let has_id = entities.iter().any(|entity| entity.id.is_some());
+        let has_canonical = entities.iter().any(|entity| entity.canonical.is_some());
+        let attributes: BTreeSet<&str> = entities
+            .iter()
+            .flat_map(|entity| entity.attributes.keys().map(String::as_str))
+            .collect();
+        let mut header = vec!["name", "label"];
+        if has_id {
+            header.push("id");
+        }
+        if has_canonical {
+            header.push("canonical");
+        }
+        header.extend(&attributes);
+        writer.write_record(&header)?;
+        for entity in entities {
+            let mut record = vec![entity.name.as_str(), entity.label.as_str()];
+            if has_id {
+                record.push(entity.id.as_deref().unwrap_or_default());
+            }
+            if has_canonical {
+                record.push(entity.canonical.as_deref().unwrap_or_default());
+            }
+            record.extend(
+                attributes
+                    .iter()
+                    .map(|attribute| entity.attributes.get(*attribute).map_or("", String::as_str)),
+            );
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// An entity can have several labels separated by "|", e.g. "Apple,ORG|PRODUCT",
+    /// it is expanded into one entity per label
+    fn split_labels(entities: Vec<Entity>) -> Vec<Entity> {
+        entities
+            .into_iter()
+            .flat_map(|entity| {
+                entity
+                    .label
+                    .split('|')
+                    .map(|label| label.trim())
+                    .filter(|label| !label.is_empty())
+                    .map(|label| Entity {
+                        label: label.to_string(),
+                        ..entity.clone()
+                    })
+                    .collect::<Vec<Entity>>()
+            })
+            .collect()
+    }
+
+    fn delimited(path: &str, delimiter: u8) -> Result<Vec<Entity>, Box<dyn Error>> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_path(long_path(path))?;
+        let headers = rdr.headers()?.clone();
+        for column in ["name", "label"] {
+            if !headers.iter().any(|header| header == column) {
+                return Err(format!("Missing column \"{}\" in {}", column, path).into());
+            }
+        }
+        let mut entities = Vec::new();
+        for record in rdr.records() {
+            let record = record?;
+            let mut entity = Entity::default();
+            let mut aliases = "";
+            for (header, value) in headers.iter().zip(record.iter()) {
+                match header {
+                    "name" => entity.name = value.to_string(),
+                    "label" => entity.label = value.to_string(),
+                    _ if value.is_empty() => {}
+                    "id" | "kb_id" => entity.id = Some(value.to_string()),
+                    "canonical" => entity.canonical = Some(value.to_string()),
+                    "aliases" => aliases = value,
+                    _ => {
+                        entity
+                            .attributes
+                            .insert(header.to_string(), value.to_string());
+                    }
+                }
+            }
+            let aliases: Vec<&str> = aliases
+                .split('|')
+                .map(|alias| alias.trim())
+                .filter(|alias| !alias.is_empty() && *alias != entity.name)
+                .collect();
+            if !aliases.is_empty() && entity.canonical.is_none() {
+                entity.canonical = Some(entity.name.clone());
+            }
+            let aliases: Vec<Entity> = aliases
+                .into_iter()
+                .map(|alias| Entity {
+                    name: alias.to_string(),
+                    ..entity.clone()
+                })
+                .collect();
+            entities.push(entity);
+            entities.extend(aliases);
+        }
+        Ok(entities)
+    }
+
+    fn txt(path: &Path) -> Result<Vec<Entity>, Box<dyn Error>> {
+        let label = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Unable to infer the label from {}", path.display()))?;
+        let content = fs::read_to_string(long_path(path))?;
+        Ok(content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|name| Entity {
+                name: name.to_string(),
+                label: label.to_string(),
+                ..Default::default()
+            })
+            .collect())
     }
 }
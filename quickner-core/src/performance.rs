@@ -0,0 +1,87 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::sync::Arc;
+
+use log::{error, info};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::quickner::Quickner;
+
+/// Run `op` in the thread pool if there is one, in the global pool of rayon otherwise
+pub(crate) fn install<R: Send>(pool: Option<&ThreadPool>, op: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+impl Quickner {
+    /// Run the parallel stages in a pool of `num_threads` threads instead of the
+    /// global pool of rayon, e.g. to leave the other threads to the host of a
+    /// server embedding quickner. Same as `num_threads` under `[performance]`.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml")).with_thread_pool(2);
+    /// quickner.process(false).unwrap();
+    /// assert_eq!(quickner.num_threads(), 2);
+    /// ```
+    pub fn with_thread_pool(mut self, num_threads: usize) -> Self {
+        self.config.performance.num_threads = Some(num_threads);
+        self.sync_thread_pool();
+        self
+    }
+
+    /// Number of threads of the parallel stages
+    pub fn num_threads(&self) -> usize {
+        match &self.thread_pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        }
+    }
+
+    /// Run `op` in the thread pool of the instance, so the parallel iterators
+    /// it calls are limited to the `num_threads` of the configuration
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        install(self.thread_pool.as_deref(), op)
+    }
+
+    /// Build the thread pool of the `num_threads` of the configuration, if it
+    /// changed, or drop it to use the global pool when it is not set
+    pub(crate) fn sync_thread_pool(&mut self) {
+        let num_threads = match self.config.performance.num_threads {
+            Some(num_threads) => num_threads,
+            None => {
+                self.thread_pool = None;
+                return;
+            }
+        };
+        if self
+            .thread_pool
+            .as_ref()
+            .is_some_and(|pool| pool.current_num_threads() == num_threads)
+        {
+            return;
+        }
+        match ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|index| format!("quickner-{index}"))
+            .build()
+        {
+            Ok(pool) => {
+                info!("Thread pool of {} threads built", num_threads);
+                self.thread_pool = Some(Arc::new(pool));
+            }
+            Err(e) => {
+                error!("Unable to build a thread pool of {num_threads} threads, the global pool is used: {e}");
+                self.thread_pool = None;
+            }
+        }
+    }
+}
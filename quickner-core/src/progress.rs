@@ -0,0 +1,79 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use indicatif::ProgressBar;
+
+use crate::utils::get_progress_bar;
+
+/// Receives the progress of the annotation. The documents are annotated
+/// in parallel by chunks of `interval` documents, each method is called
+/// from the thread running the annotation, between two chunks.
+pub trait ProgressReporter: Send + Sync {
+    /// A step starts, with the number of documents to annotate
+    fn start(&self, _message: &str, _total: usize) {}
+    /// Number of documents annotated since the start of the step
+    fn update(&self, done: usize);
+    /// The step is finished or cancelled
+    fn finish(&self) {}
+    /// Number of documents annotated between two updates
+    fn interval(&self) -> usize {
+        1000
+    }
+    /// Whether the run reporting to it is cancelled, checked between two chunks
+    /// as a Ctrl-C is: unlike `cancel`, only the runs of this reporter stop
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Progress bar printed to the terminal, the default reporter
+pub struct IndicatifProgress {
+    bar: ProgressBar,
+}
+
+impl IndicatifProgress {
+    pub fn new() -> Self {
+        IndicatifProgress {
+            bar: get_progress_bar(0),
+        }
+    }
+}
+
+impl Default for IndicatifProgress {
+    fn default() -> Self {
+        IndicatifProgress::new()
+    }
+}
+
+impl ProgressReporter for IndicatifProgress {
+    fn start(&self, message: &str, total: usize) {
+        self.bar.reset();
+        self.bar.set_length(total as u64);
+        self.bar.set_message(message.to_string());
+    }
+
+    fn update(&self, done: usize) {
+        self.bar.set_position(done as u64);
+    }
+
+    fn finish(&self) {
+        self.bar.finish();
+    }
+}
+
+/// Reporter ignoring the progress, e.g. for the logs of a server
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopProgress;
+
+impl ProgressReporter for NoopProgress {
+    fn update(&self, _done: usize) {}
+
+    fn interval(&self) -> usize {
+        usize::MAX
+    }
+}
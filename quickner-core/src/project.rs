@@ -0,0 +1,110 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Write},
+    sync::Arc,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    document::Document,
+    entity::{Entity, Exclude},
+    progress::IndicatifProgress,
+    quickner::Quickner,
+    utils::long_path,
+};
+
+/// State of a Quickner object written by `save_project`,
+/// borrowed when it is saved and owned when it is loaded
+#[derive(Serialize, Deserialize)]
+struct Project<'a> {
+    /// Version of quickner
+    version: Cow<'a, str>,
+    config: Cow<'a, Config>,
+    config_file: Option<Cow<'a, str>>,
+    entities: Cow<'a, [Entity]>,
+    excludes: Cow<'a, [Exclude]>,
+    documents: Cow<'a, [Document]>,
+    documents_label_index: Cow<'a, HashMap<String, Vec<String>>>,
+    documents_entities_index: Cow<'a, HashMap<String, Vec<String>>>,
+}
+
+impl Quickner {
+    /// Save the whole state (configuration, entities, excludes, documents and
+    /// their indexes) to a single gzip-compressed JSON file, to persist an
+    /// annotation session and reload it exactly with [`Quickner::load_project`].
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// quickner.save_project("./session.qnr").unwrap();
+    /// let quickner = Quickner::load_project("./session.qnr").unwrap();
+    /// ```
+    pub fn save_project(&self, path: &str) -> Result<(), Error> {
+        let project = Project {
+            version: Cow::Borrowed(env!("CARGO_PKG_VERSION")),
+            config: Cow::Borrowed(&self.config),
+            config_file: self.config_file.as_deref().map(Cow::Borrowed),
+            entities: Cow::Borrowed(&self.entities),
+            excludes: Cow::Borrowed(&self.excludes),
+            documents: Cow::Borrowed(&self.documents),
+            documents_label_index: Cow::Borrowed(&self.documents_label_index),
+            documents_entities_index: Cow::Borrowed(&self.documents_entities_index),
+        };
+        let file = BufWriter::new(File::create(long_path(path))?);
+        let mut writer = GzEncoder::new(file, flate2::Compression::default());
+        serde_json::to_writer(&mut writer, &project)?;
+        writer.finish()?.flush()?;
+        info!(
+            "Project saved to {}: {} documents, {} entities",
+            path,
+            self.documents.len(),
+            self.entities.len()
+        );
+        Ok(())
+    }
+
+    /// Load a project saved with [`Quickner::save_project`]
+    pub fn load_project(path: &str) -> Result<Quickner, Error> {
+        let file = BufReader::new(File::open(long_path(path))?);
+        let project: Project = serde_json::from_reader(GzDecoder::new(file)).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{path} is not a quickner project: {e}"),
+            )
+        })?;
+        if project.version != env!("CARGO_PKG_VERSION") {
+            info!("Project {} was saved by quickner {}", path, project.version);
+        }
+        let documents = project.documents.into_owned();
+        Ok(Quickner {
+            config: project.config.into_owned(),
+            config_file: project.config_file.map(Cow::into_owned),
+            documents_hash: Quickner::document_hash(&documents),
+            documents,
+            entities: project.entities.into_owned(),
+            excludes: project.excludes.into_owned(),
+            documents_label_index: project.documents_label_index.into_owned(),
+            documents_entities_index: project.documents_entities_index.into_owned(),
+            progress: Arc::new(IndicatifProgress::new()),
+            token_index: Default::default(),
+            thread_pool: None,
+            history: None,
+            extractors: Default::default(),
+        })
+    }
+}
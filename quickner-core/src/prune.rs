@@ -0,0 +1,168 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    quickner::Quickner,
+    span::{Span, SpanSource},
+    utils::{char_to_byte, fold_diacritics, plural_forms},
+};
+
+/// An entity removed by `prune_entities`, with the number of documents
+/// holding one of its spans
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct PrunedEntity {
+    pub name: String,
+    pub label: String,
+    pub documents: usize,
+}
+
+/// Entities removed by `prune_entities`
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Documents inspected
+    pub documents: usize,
+    /// Entities matched in more documents than the maximum, most frequent first
+    pub frequent: Vec<PrunedEntity>,
+    /// Entities matched in fewer documents than the minimum, in their order
+    pub rare: Vec<PrunedEntity>,
+    /// Spans of the frequent entities removed from the documents
+    pub spans_removed: usize,
+}
+
+impl Quickner {
+    /// Remove the noisy entities once the documents are annotated: the ones
+    /// matched in more than `max_doc_frequency` of the documents (a share, e.g.
+    /// 0.5, likely stopwords, 1.0 keeps them all), and the ones matched in fewer
+    /// than `min_doc_frequency` documents (1 removes the entities never matched).
+    /// An entity is matched in a document holding a span with its label whose
+    /// text is its name, ignoring the case unless the matching is case sensitive,
+    /// and read like the matcher does with the `[matching]` options: diacritics
+    /// folded, plural forms, and the name recorded for the spans matched with edits.
+    /// The spans of the frequent entities are removed from the documents, except
+    /// the verified ones, and the indexes are rebuilt.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let report = quickner.prune_entities(0.5, 1);
+    /// for entity in &report.frequent {
+    ///     println!("{} ({}) in {} documents", entity.name, entity.label, entity.documents);
+    /// }
+    /// ```
+    pub fn prune_entities(
+        &mut self,
+        max_doc_frequency: f32,
+        min_doc_frequency: usize,
+    ) -> PruneReport {
+        let snapshot = self.history_snapshot();
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        let matching = &self.config.matching;
+        let normalize = |text: &str| {
+            let text = match case_sensitive {
+                true => text.to_string(),
+                false => text.to_lowercase(),
+            };
+            match matching.fold_diacritics {
+                true => fold_diacritics(&text).into_owned(),
+                false => text,
+            }
+        };
+        // Forms of the names matched in the texts, to the names
+        let mut forms: HashMap<String, String> = HashMap::new();
+        for entity in &self.entities {
+            let name = normalize(&entity.name);
+            if matching.match_plurals {
+                for plural in plural_forms(&name, matching.language) {
+                    forms.entry(plural).or_insert_with(|| name.clone());
+                }
+            }
+            forms.insert(name.clone(), name);
+        }
+        // Name and label of the entity of a span
+        let key = |text: &str, span: &Span| {
+            let name = match (&span.source, &span.entity_name) {
+                (SpanSource::Fuzzy, Some(name)) => normalize(name),
+                _ => {
+                    let (start, end) = char_to_byte(text, span.start, span.end);
+                    normalize(&text[start..end])
+                }
+            };
+            let name = forms.get(&name).cloned().unwrap_or(name);
+            (name, span.label.clone())
+        };
+        let mut frequencies: HashMap<(String, String), usize> = HashMap::new();
+        for document in &self.documents {
+            let found: HashSet<(String, String)> = document
+                .spans()
+                .iter()
+                .map(|span| key(&document.text, span))
+                .collect();
+            for key in found {
+                *frequencies.entry(key).or_default() += 1;
+            }
+        }
+        let mut report = PruneReport {
+            documents: self.documents.len(),
+            ..Default::default()
+        };
+        let limit = max_doc_frequency * self.documents.len() as f32;
+        let mut frequent = HashSet::new();
+        let mut kept = Vec::with_capacity(self.entities.len());
+        for entity in std::mem::take(&mut self.entities) {
+            let key = (normalize(&entity.name), entity.label.clone());
+            let documents = frequencies.get(&key).copied().unwrap_or(0);
+            let pruned = PrunedEntity {
+                name: entity.name.clone(),
+                label: entity.label.clone(),
+                documents,
+            };
+            if documents as f32 > limit {
+                report.frequent.push(pruned);
+                frequent.insert(key);
+            } else if documents < min_doc_frequency {
+                report.rare.push(pruned);
+            } else {
+                kept.push(entity);
+            }
+        }
+        self.entities = kept;
+        report
+            .frequent
+            .sort_by_key(|entity| std::cmp::Reverse(entity.documents));
+        if !frequent.is_empty() {
+            for document in self.documents.iter_mut() {
+                if document.status.is_verified() {
+                    continue;
+                }
+                let text = Arc::clone(&document.text);
+                report.spans_removed +=
+                    document.retain_spans(|span| !frequent.contains(&key(&text, span)));
+            }
+        }
+        info!(
+            "{} frequent and {} rare entities pruned, {} spans removed",
+            report.frequent.len(),
+            report.rare.len(),
+            report.spans_removed
+        );
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("prune_entities", snapshot);
+        report
+    }
+}
@@ -0,0 +1,348 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, PoisonError, Weak},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    document::Document,
+    quickner::Quickner,
+    utils::{char_to_byte, tokenize},
+};
+
+/// A query on the documents of a corpus, a document matches
+/// when it matches every condition of the query
+/// # Example
+/// ```
+/// use quickner::Query;
+/// let query = Query::new()
+///     .with_label("ORG")
+///     .with_entity("apple")
+///     .with_meta("source", "tweets")
+///     .contains_text("merger")
+///     .without_label("PER")
+///     .limit(100);
+/// assert_eq!(query.labels, vec!["ORG".to_string()]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Labels the document has at least one span of
+    pub labels: Vec<String>,
+    /// Labels the document has no span of
+    pub without_labels: Vec<String>,
+    /// Entities annotated in the document, case insensitive
+    pub entities: Vec<String>,
+    /// Metadata of the document, e.g. the extra columns of the texts file
+    pub meta: HashMap<String, String>,
+    /// Substrings of the text of the document, lowercased
+    pub texts: Vec<String>,
+    /// Most documents matched, all of them if not set
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Create a query matching every document
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// Only match the documents with at least one span of the label
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    /// Only match the documents without any span of the label
+    pub fn without_label(mut self, label: &str) -> Self {
+        self.without_labels.push(label.to_string());
+        self
+    }
+
+    /// Only match the documents where the entity is annotated
+    pub fn with_entity(mut self, name: &str) -> Self {
+        self.entities.push(name.to_lowercase());
+        self
+    }
+
+    /// Only match the documents whose metadata has the value for the key
+    pub fn with_meta(mut self, key: &str, value: &str) -> Self {
+        self.meta.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Only match the documents whose text contains the substring, case insensitive
+    pub fn contains_text(mut self, text: &str) -> Self {
+        self.texts.push(text.to_lowercase());
+        self
+    }
+
+    /// Match at most `limit` documents, the first ones of the corpus
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether the document matches every condition of the query, the limit aside
+    pub fn matches(&self, document: &Document) -> bool {
+        let labels: HashSet<&str> = document
+            .label
+            .iter()
+            .map(|(_, _, label)| label.as_str())
+            .collect();
+        let entities: HashSet<String> = document
+            .label
+            .iter()
+            .map(|(start, end, _)| {
+                let (start, end) = char_to_byte(&document.text, *start, *end);
+                document.text[start..end].to_lowercase()
+            })
+            .collect();
+        self.labels
+            .iter()
+            .all(|label| labels.contains(label.as_str()))
+            && self
+                .entities
+                .iter()
+                .all(|name| entities.contains(&name.to_lowercase()))
+            && self.matches_meta(document)
+    }
+
+    /// Whether the document matches the conditions not found in the indexes
+    fn matches_meta(&self, document: &Document) -> bool {
+        self.meta
+            .iter()
+            .all(|(key, value)| document.meta.get(key) == Some(value))
+            && !document
+                .label
+                .iter()
+                .any(|(_, _, label)| self.without_labels.contains(label))
+            && self.matches_text(document)
+    }
+
+    fn matches_text(&self, document: &Document) -> bool {
+        if self.texts.is_empty() {
+            return true;
+        }
+        let text = document.text.to_lowercase();
+        self.texts
+            .iter()
+            .all(|substring| text.contains(&substring.to_lowercase()))
+    }
+}
+
+/// Lowercased words of a text
+fn words(text: &str) -> Vec<String> {
+    let text = text.to_lowercase();
+    let chars: Vec<char> = text.chars().collect();
+    tokenize(&text)
+        .into_iter()
+        .map(|(start, end)| chars[start..end].iter().collect())
+        .collect()
+}
+
+/// Inverted index of the lowercased tokens of the documents of a corpus
+struct InvertedIndex {
+    /// Text of each document indexed, to find out when they change
+    texts: Vec<Weak<str>>,
+    /// Indices of the documents containing each token, in ascending order
+    tokens: HashMap<String, Vec<usize>>,
+}
+
+impl InvertedIndex {
+    fn new(documents: &[Document]) -> Self {
+        let words: Vec<HashSet<String>> = documents
+            .par_iter()
+            .map(|document| words(&document.text).into_iter().collect())
+            .collect();
+        let mut tokens: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, words) in words.into_iter().enumerate() {
+            for word in words {
+                tokens.entry(word).or_default().push(index);
+            }
+        }
+        InvertedIndex {
+            texts: documents
+                .iter()
+                .map(|document| Arc::downgrade(&document.text))
+                .collect(),
+            tokens,
+        }
+    }
+
+    /// Whether the documents are the ones indexed, with the same texts
+    fn is_current(&self, documents: &[Document]) -> bool {
+        self.texts.len() == documents.len()
+            && self.texts.iter().zip(documents).all(|(text, document)| {
+                std::ptr::addr_eq(text.as_ptr(), Arc::as_ptr(&document.text))
+            })
+    }
+
+    /// Indices of the documents which may contain the substring, in ascending
+    /// order, `None` if it has no word to look up
+    fn candidates(&self, substring: &str) -> Option<Vec<usize>> {
+        let words = words(substring);
+        let last = words.len().checked_sub(1)?;
+        let mut candidates: Option<Vec<usize>> = None;
+        for (position, word) in words.iter().enumerate() {
+            // The words inside the substring are tokens of the document,
+            // the first one can end a token and the last one can start a token
+            let matches = |token: &str| match (position == 0, position == last) {
+                (true, true) => token.contains(word.as_str()),
+                (true, false) => token.ends_with(word.as_str()),
+                (false, true) => token.starts_with(word.as_str()),
+                (false, false) => token == word,
+            };
+            let mut documents: Vec<usize> = match 0 < position && position < last {
+                true => self.tokens.get(word).cloned().unwrap_or_default(),
+                false => self
+                    .tokens
+                    .iter()
+                    .filter(|(token, _)| matches(token))
+                    .flat_map(|(_, documents)| documents.iter().copied())
+                    .collect(),
+            };
+            documents.sort_unstable();
+            documents.dedup();
+            candidates = Some(match candidates {
+                Some(mut candidates) => {
+                    candidates.retain(|index| documents.binary_search(index).is_ok());
+                    candidates
+                }
+                None => documents,
+            });
+        }
+        candidates
+    }
+}
+
+/// Inverted index of the tokens of the documents, built on the first search
+/// by text and rebuilt on the next one when the documents have changed
+#[derive(Default)]
+pub struct TokenIndex(Mutex<Option<Arc<InvertedIndex>>>);
+
+impl Clone for TokenIndex {
+    fn clone(&self) -> Self {
+        let index = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        TokenIndex(Mutex::new(index.clone()))
+    }
+}
+
+impl TokenIndex {
+    fn get(&self, documents: &[Document]) -> Arc<InvertedIndex> {
+        let mut index = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        match index.as_ref() {
+            Some(index) if index.is_current(documents) => index.clone(),
+            _ => index
+                .insert(Arc::new(InvertedIndex::new(documents)))
+                .clone(),
+        }
+    }
+}
+
+impl Quickner {
+    /// Find the documents matching the query, in the order of the corpus.
+    /// The labels and entities are looked up in the indexes, the substrings
+    /// of the texts in an inverted index of the tokens built on the first search.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Query, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let query = Query::new()
+    ///     .contains_text("merger")
+    ///     .with_label("ORG")
+    ///     .without_label("PER")
+    ///     .limit(100);
+    /// for document in quickner.search(&query) {
+    ///     println!("{}", document.text);
+    /// }
+    /// ```
+    pub fn search(&self, query: &Query) -> Vec<&Document> {
+        // The label and entity conditions are looked up in the indexes
+        let mut candidates: Option<HashSet<&str>> = None;
+        let lookups = query
+            .labels
+            .iter()
+            .map(|label| self.documents_label_index.get(label))
+            .chain(query.entities.iter().map(|name| {
+                self.documents_entities_index
+                    .get(name.to_lowercase().as_str())
+            }));
+        for ids in lookups {
+            let ids: HashSet<&str> = ids.into_iter().flatten().map(|id| id.as_str()).collect();
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+        let matches = |document: &&Document| {
+            candidates
+                .as_ref()
+                .is_none_or(|candidates| candidates.contains(document.id.as_str()))
+                && query.matches_meta(document)
+        };
+        let limit = query.limit.unwrap_or(usize::MAX);
+        // The substrings are looked up in the token index, then in the texts
+        let mut indices: Option<Vec<usize>> = None;
+        if !query.texts.is_empty() {
+            let index = self.install(|| self.token_index.get(&self.documents));
+            for substring in &query.texts {
+                let Some(documents) = index.candidates(substring) else {
+                    continue;
+                };
+                indices = Some(match indices {
+                    Some(mut indices) => {
+                        indices.retain(|index| documents.binary_search(index).is_ok());
+                        indices
+                    }
+                    None => documents,
+                });
+            }
+        }
+        match indices {
+            Some(indices) => indices
+                .into_iter()
+                .map(|index| &self.documents[index])
+                .filter(matches)
+                .take(limit)
+                .collect(),
+            None => self.documents.iter().filter(matches).take(limit).collect(),
+        }
+    }
+
+    /// Create a new instance restricted to the documents matching the query,
+    /// on which stats, evaluation, export and splitting can be run.
+    /// The configuration and the entities are kept, the order of the documents too.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Query, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let tweets = quickner.subset(&Query::new().with_meta("source", "tweets"));
+    /// println!("{} spans in tweets", tweets.stats().spans);
+    /// ```
+    pub fn subset(&self, query: &Query) -> Quickner {
+        let documents: Vec<Document> = self.search(query).into_iter().cloned().collect();
+        let mut quickner = Quickner {
+            config: self.config.clone(),
+            config_file: self.config_file.clone(),
+            documents_hash: Quickner::document_hash(&documents),
+            documents,
+            entities: self.entities.clone(),
+            excludes: self.excludes.clone(),
+            ..Default::default()
+        };
+        quickner.build_label_index();
+        quickner.build_entity_index();
+        quickner
+    }
+}
@@ -1,22 +1,50 @@
 use crate::{
-    config::{Config, Filters},
-    models::Text,
-    utils::{char_to_byte, get_progress_bar, is_valid_utf8},
+    cancel::{interrupted, is_cancelled, reset_cancel},
+    checkpoint::CheckpointWriter,
+    config::{
+        Config, EntitiesFormat, Fields, Filters, Format, IdStrategy, Input, Output, Route, Split,
+    },
+    extract::Extractors,
+    frozen::{check_frozen_labels, exported_labels},
+    history::History,
+    matcher::Matcher,
+    models::{EntityRulerPattern, Stamp, Text},
+    performance::install,
+    progress::{IndicatifProgress, NoopProgress, ProgressReporter},
+    query::TokenIndex,
+    report::{RunReport, SkippedTexts},
+    utils::{
+        char_to_byte, hash_string, is_valid_utf8, long_path, preview, sample_score,
+        set_label_display, split_paragraphs, split_sentences,
+    },
     SpacyEntity,
 };
 use aho_corasick::AhoCorasick;
 use log::{error, info, warn};
-use rayon::prelude::*;
+use rayon::{prelude::*, ThreadPool};
 use std::{collections::HashMap, path::Path, sync::Arc};
 use std::{
     collections::HashSet,
-    fs::File,
-    io::{BufRead, BufReader},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
 };
-use std::{env, error::Error};
+use std::{
+    error::Error,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::document::{Document, DocumentStatus};
+use crate::entity::{Entity, Exclude};
+use crate::logging::Stage;
+
+/// Number of documents annotated in parallel between two cancellation checks
+const ANNOTATION_BATCH_SIZE: usize = 10_000;
+
+/// Number of rows of the texts file read before they are filtered in parallel
+const READ_CHUNK_SIZE: usize = 100_000;
 
-use crate::document::Document;
-use crate::entity::Entity;
+/// Id, text and metadata of a row of the texts file
+type TextRow = (String, String, HashMap<String, String>);
 
 /// Quickner is the main struct of the application
 /// It holds the configuration file and the path to the configuration file
@@ -28,9 +56,23 @@ pub struct Quickner {
     pub config_file: Option<String>,
     pub documents: Vec<Document>,
     pub entities: Vec<Entity>,
+    /// Texts never annotated, read from the excludes file
+    pub excludes: Vec<Exclude>,
     pub documents_hash: HashMap<String, Document>,
     pub documents_label_index: HashMap<String, Vec<String>>,
     pub documents_entities_index: HashMap<String, Vec<String>>,
+    /// Receives the progress of the annotation, a progress bar by default
+    pub progress: Arc<dyn ProgressReporter>,
+    /// Tokens of the documents, built on the first search by text
+    pub token_index: TokenIndex,
+    /// Pool of the parallel stages, the global pool of rayon if not set,
+    /// see `num_threads` under `[performance]`
+    pub thread_pool: Option<Arc<ThreadPool>>,
+    /// History of the changes of the documents, see `enable_history`
+    pub history: Option<History>,
+    /// Readers of the files of a directory of texts, by extension,
+    /// see `register_extractor`
+    pub extractors: Extractors,
 }
 
 impl Default for Quickner {
@@ -40,9 +82,15 @@ impl Default for Quickner {
             config_file: Some("./config.toml".to_string()),
             documents: Vec::new(),
             entities: Vec::new(),
+            excludes: Vec::new(),
             documents_hash: HashMap::new(),
             documents_label_index: HashMap::new(),
             documents_entities_index: HashMap::new(),
+            progress: Arc::new(IndicatifProgress::new()),
+            token_index: TokenIndex::default(),
+            thread_pool: None,
+            history: None,
+            extractors: Extractors::default(),
         }
     }
 }
@@ -118,10 +166,10 @@ impl Quickner {
     pub(crate) fn find_index_using_aho_corasick(
         text: &str,
         aho_corasick: &Arc<AhoCorasick>,
-        entites: &Vec<Entity>,
+        entites: &[Entity],
     ) -> Option<Vec<(usize, usize, String)>> {
         if !is_valid_utf8(text) {
-            warn!("Skipping invalid utf8 text: \"{}\"", text);
+            warn!("Skipping invalid utf8 text: \"{}\"", preview(text));
             return None;
         }
         let mut annotations = Vec::new();
@@ -221,10 +269,10 @@ impl Quickner {
                 annotations.push((start, end, label));
             }
         }
-        // Unique annotations
-        annotations.sort_by(|a, b| a.0.cmp(&b.0));
+        // Unique annotations, sorted by start index. A surface with several labels
+        // gives one span per label, in a stable order
+        annotations.sort();
         annotations.dedup();
-        // Sort annotations by start index
         if !annotations.is_empty() {
             Some(annotations)
         } else {
@@ -251,41 +299,215 @@ impl Quickner {
     /// # Errors
     /// This function will return an error if the texts are not loaded
     pub fn annotate(&mut self) {
-        let pb = get_progress_bar(self.documents.len() as u64);
-        pb.set_message("Annotating texts");
-        let patterns = self
-            .entities
-            .iter()
-            .map(|entity| entity.name.as_str())
-            .collect::<Vec<&str>>();
-        // Check if apple is in the patterns
-        // if patterns.contains(&"apple") {
-        //     println!("Apple found in patterns");
-        // }
-        let aho_corasick = Arc::new(AhoCorasick::new(patterns));
-        self.documents.par_iter_mut().for_each(|document| {
-            let t: &mut String = &mut document.text;
-            if !self.config.texts.filters.case_sensitive {
-                *t = t.to_lowercase();
+        // Nothing is written without a checkpoint, the annotation cannot fail
+        let _ = self.annotate_routed(&[], None);
+    }
+
+    /// Annotate the documents matching a route with the entities of the route
+    /// (the first matching route wins), the other documents with the default entities.
+    /// The order of the documents is kept. With a checkpoint, the documents it holds
+    /// are not annotated again and the others are written to it as they are annotated.
+    fn annotate_routed(
+        &mut self,
+        routes: &[(Route, Vec<Entity>)],
+        mut checkpoint: Option<&mut CheckpointWriter>,
+    ) -> Result<(), std::io::Error> {
+        self.progress
+            .start("Annotating texts", self.documents.len());
+        let mut groups: Vec<Vec<(usize, Document)>> = vec![Vec::new(); routes.len() + 1];
+        let mut documents: Vec<(usize, Document)> = Vec::new();
+        for (index, document) in std::mem::take(&mut self.documents).into_iter().enumerate() {
+            if let Some(restored) = checkpoint
+                .as_mut()
+                .and_then(|checkpoint| checkpoint.restore(&document.id))
+            {
+                documents.push((index, restored));
+                continue;
+            }
+            let group = routes
+                .iter()
+                .position(|(route, _)| route.matches(&document))
+                .unwrap_or(routes.len());
+            groups[group].push((index, document));
+        }
+        let total: usize = documents.len() + groups.iter().map(Vec::len).sum::<usize>();
+        for (group, members) in groups.into_iter().enumerate() {
+            let entities = match routes.get(group) {
+                Some((route, entities)) => {
+                    info!(
+                        "{} documents routed to {}",
+                        members.len(),
+                        route.entities.input.path
+                    );
+                    entities
+                }
+                None => &self.entities,
             };
-            // ahocorasick implementation
-            let index = Quickner::find_index_using_aho_corasick(&t, &aho_corasick, &self.entities);
-            let mut index = match index {
-                Some(index) => index,
-                None => vec![],
+            let (indexes, mut group_documents): (Vec<usize>, Vec<Document>) =
+                members.into_iter().unzip();
+            let matcher = self.matcher_for(entities);
+            let pool = self.thread_pool.as_deref();
+            let every = match &checkpoint {
+                Some(checkpoint) => checkpoint.every(),
+                None => group_documents.len().max(1),
             };
-            index.sort_by(|a, b| a.0.cmp(&b.0));
-            document.label.extend(index);
-            pb.inc(1);
-        });
-        self.documents_hash = self
-            .documents
-            .iter()
-            .map(|document| (document.id.clone(), document.clone()))
+            let mut annotated = 0;
+            for chunk in group_documents.chunks_mut(every) {
+                let count = Quickner::annotate_documents(
+                    chunk,
+                    &matcher,
+                    pool,
+                    self.progress.as_ref(),
+                    documents.len() + annotated,
+                );
+                if let Some(checkpoint) = checkpoint.as_mut() {
+                    checkpoint.write(&chunk[..count])?;
+                }
+                annotated += count;
+                if count < chunk.len() {
+                    break;
+                }
+            }
+            // Only the documents annotated before a cancellation are kept
+            documents.extend(indexes.into_iter().zip(group_documents).take(annotated));
+        }
+        if documents.len() < total {
+            warn!(
+                "Annotation cancelled, {} of {} documents annotated",
+                documents.len(),
+                total
+            );
+        }
+        documents.sort_by_key(|(index, _)| *index);
+        self.documents = documents
+            .into_iter()
+            .map(|(_, document)| document)
             .collect();
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.progress.finish();
+        Ok(())
+    }
+
+    /// Load the entities of every route, with the filters of the route
+    /// (or the default entities filters)
+    fn route_entities(&self, config: &Config) -> Vec<(Route, Vec<Entity>)> {
+        config
+            .routes
+            .iter()
+            .map(|route| {
+                let mut filters = route
+                    .entities
+                    .filters
+                    .clone()
+                    .unwrap_or_else(|| config.entities.filters.clone());
+                filters.set_special_characters();
+                let entities = self
+                    .entities(
+                        route.entities.input.path.as_str(),
+                        route.entities.input.format.clone(),
+                        filters,
+                        route.entities.input.filter.unwrap_or(false),
+                    )
+                    .into_iter()
+                    .map(|entity| Entity {
+                        name: match config.entities.filters.case_sensitive {
+                            true => entity.name,
+                            false => entity.name.to_lowercase(),
+                        },
+                        ..entity
+                    })
+                    .collect();
+                (route.clone(), Quickner::unique_entities(entities))
+            })
+            .collect()
+    }
+
+    /// Annotate the documents with new entities only, without re-running
+    /// the whole annotation. An automaton is built for the new patterns only,
+    /// the spans found are merged with the existing ones.
+    /// Entities already known are ignored.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Entity, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let entity = Entity {
+    ///     name: "Rust".to_string(),
+    ///     label: "PL".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// quickner.annotate_incremental(&[entity]);
+    /// ```
+    pub fn annotate_incremental(&mut self, new_entities: &[Entity]) {
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        let entities: Vec<Entity> = Quickner::unique_entities(
+            new_entities
+                .iter()
+                .map(|entity| Entity {
+                    name: match case_sensitive {
+                        true => entity.name.to_string(),
+                        false => entity.name.to_lowercase(),
+                    },
+                    ..entity.clone()
+                })
+                .filter(|entity| !self.entities.contains(entity))
+                .collect(),
+        );
+        if entities.is_empty() {
+            info!("No new entities to annotate");
+            return;
+        }
+        info!("{} new entities found", entities.len());
+        let snapshot = self.history_snapshot();
+        self.progress
+            .start("Annotating texts with new entities", self.documents.len());
+        let matcher = self.matcher_for(&entities);
+        Quickner::annotate_documents(
+            &mut self.documents,
+            &matcher,
+            self.thread_pool.as_deref(),
+            self.progress.as_ref(),
+            0,
+        );
+        self.entities.extend(entities);
+        self.documents_hash = Quickner::document_hash(&self.documents);
         self.build_label_index();
         self.build_entity_index();
-        pb.finish();
+        self.record_history("annotate_incremental", snapshot);
+        self.progress.finish();
+    }
+
+    /// Annotate the documents with the given entities, spans already present
+    /// in a document are not added twice and labels matching the same span
+    /// are resolved with the label priority.
+    /// Spans found inside an occurrence of an exclude of their label are dropped,
+    /// then the context rules accept, reject or redirect the spans left.
+    /// This function is parallelized using rayon, in the pool if one is given, the
+    /// documents are annotated in batches and a cancellation stops it between two batches.
+    /// The progress is reported after each batch, counting from the given number
+    /// of documents already annotated in the step.
+    /// Returns the number of documents annotated, from the start of the slice
+    fn annotate_documents(
+        documents: &mut [Document],
+        matcher: &Matcher,
+        pool: Option<&ThreadPool>,
+        progress: &dyn ProgressReporter,
+        done: usize,
+    ) -> usize {
+        let mut annotated = 0;
+        let batch_size = ANNOTATION_BATCH_SIZE.min(progress.interval()).max(1);
+        for batch in documents.chunks_mut(batch_size) {
+            if is_cancelled() || progress.is_cancelled() {
+                break;
+            }
+            // The progress is reported from the calling thread, between the batches
+            install(pool, || matcher.annotate_many(batch));
+            annotated += batch.len();
+            progress.update(done + annotated);
+        }
+        annotated
     }
 
     /// Creates a new instance of Quickner
@@ -320,11 +542,26 @@ impl Quickner {
             return Quickner::default();
         }
         let config = Config::from_file(config_file.as_str());
-        Quickner {
+        let mut quickner = Quickner {
             config,
             config_file: Some(config_file),
             ..Default::default()
-        }
+        };
+        quickner.sync_thread_pool();
+        quickner
+    }
+
+    /// Provenance stamp of the exports: quickner version, hash of the gazetteer,
+    /// hash of the configuration file and current time
+    pub fn stamp(&self) -> Stamp {
+        let config_hash = self
+            .config_file
+            .as_ref()
+            .and_then(|path| fs::read_to_string(long_path(path)).ok())
+            .map(|content| hash_string(&content));
+        Stamp::new()
+            .with_gazetteer(&self.entities)
+            .with_config_hash(config_hash)
     }
 
     pub fn add_document(&mut self, document: Document) {
@@ -340,6 +577,7 @@ impl Quickner {
             .insert(document.id.to_owned(), document.to_owned());
         self.add_to_entity_index(&document);
         self.add_to_label_index(&document);
+        self.record_added("add_document", document);
     }
 
     pub fn add_document_from_string(&mut self, text: &str) {
@@ -349,6 +587,44 @@ impl Quickner {
             .insert(document.id.to_owned(), document.to_owned());
         self.add_to_entity_index(&document);
         self.add_to_label_index(&document);
+        self.record_added("add_document", document);
+    }
+
+    /// Replace the documents, e.g. edited outside of quickner, the indexes are rebuilt
+    pub fn set_documents(&mut self, documents: Vec<Document>) {
+        let snapshot = self.history_snapshot();
+        self.documents = documents;
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("set_documents", snapshot);
+    }
+
+    /// Set the status of the documents with the given ids, e.g. once their spans
+    /// were corrected, returns the number of documents changed
+    /// # Example
+    /// ```
+    /// use quickner::{Document, DocumentStatus, Quickner};
+    /// let mut quickner = Quickner::default();
+    /// let document = Document::from_string("Rust is made by Mozilla".to_string());
+    /// let id = document.id.clone();
+    /// quickner.add_document(document);
+    /// assert_eq!(quickner.set_status(&[id], DocumentStatus::Gold), 1);
+    /// assert!(quickner.documents[0].status.is_verified());
+    /// ```
+    pub fn set_status(&mut self, ids: &[String], status: DocumentStatus) -> usize {
+        let snapshot = self.history_snapshot();
+        let ids: HashSet<&str> = ids.iter().map(String::as_str).collect();
+        let mut changed = 0;
+        for document in self.documents.iter_mut() {
+            if ids.contains(document.id.as_str()) && document.status != status {
+                document.status = status;
+                changed += 1;
+            }
+        }
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.record_history("set_status", snapshot);
+        changed
     }
 
     pub fn add_entity(&mut self, entity: Entity) {
@@ -359,30 +635,13 @@ impl Quickner {
         self.entities.push(entity);
     }
 
-    fn parse_config(&self) -> Config {
+    pub(crate) fn parse_config(&self) -> Config {
         let mut config = self.config.clone();
         config.entities.filters.set_special_characters();
         config.texts.filters.set_special_characters();
-        let log_level_is_set = env::var("QUICKNER_LOG_LEVEL_SET").ok();
-        if log_level_is_set.is_none() {
-            match config.logging {
-                Some(ref mut logging) => {
-                    env_logger::Builder::from_env(
-                        env_logger::Env::default().default_filter_or(logging.level.as_str()),
-                    )
-                    .init();
-                    env::set_var("QUICKNER_LOG_LEVEL_SET", "true");
-                }
-                None => {
-                    env_logger::Builder::from_env(
-                        env_logger::Env::default().default_filter_or("info"),
-                    )
-                    .init();
-                    env::set_var("QUICKNER_LOG_LEVEL_SET", "true");
-                }
-            };
+        if !config.labels.display.is_empty() {
+            set_label_display(config.labels.display.clone());
         }
-
         config
     }
 
@@ -398,244 +657,815 @@ impl Quickner {
     /// quickner.process(true);
     /// ```
     /// # Returns
-    /// * `Result<Annotations, Box<dyn Error>>` - The annotations
+    /// * `Result<RunReport, Box<dyn Error>>` - The report of the run, also written
+    ///   next to the annotations when they are saved, unless `report` is disabled in the output
     /// # Errors
     /// This function will return an error if the configuration file does not exist
     /// This function will return an error if the entities file does not exist
     /// This function will return an error if the texts file does not exist
-    pub fn process(&mut self, save: bool) -> Result<(), Box<dyn Error>> {
+    pub fn process(&mut self, save: bool) -> Result<RunReport, Box<dyn Error>> {
         let config = self.parse_config();
         config.summary();
         info!("----------------------------------------");
+        self.sync_thread_pool();
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let mut skipped = SkippedTexts::default();
+        let mut stages: Vec<(&str, Duration)> = Vec::new();
+        let stage = Stage::start("entities");
+        self.load_entities(&config);
+        stages.push(stage.finish(self.entities.len(), "entities"));
+        let stage = Stage::start("texts");
+        self.load_texts(&config, &mut skipped)?;
+        stages.push(stage.finish(self.documents.len(), "documents"));
+        let stage = Stage::start("preparation");
+        self.prepare_gazetteer(&config)?;
+        if config.matching.abbreviations {
+            self.learn_abbreviations();
+        }
+        stages.push(stage.finish(self.entities.len(), "entities"));
+        let stage = Stage::start("annotation");
+        let mut checkpoint = match &config.checkpoint {
+            Some(options) => Some(CheckpointWriter::open(
+                options,
+                &config.annotations.output.path,
+                &self.stamp(),
+                self.documents.len(),
+            )?),
+            None => None,
+        };
+        let routes = self.route_entities(&config);
+        self.annotate_routed(&routes, checkpoint.as_mut())?;
+        let spans = self.documents.iter().map(|document| document.label.len());
+        stages.push(stage.finish(spans.sum(), "spans"));
+        if config.tags.is_some() {
+            let stage = Stage::start("tagging");
+            self.tag_from_config(&config);
+            let tagged = self
+                .documents
+                .iter()
+                .filter(|document| !document.tags.is_empty());
+            stages.push(stage.finish(tagged.count(), "documents tagged"));
+        }
+        info!("{} annotations found", self.documents.len());
+        let len_entities = self.entities.len();
+        let len_documents = self.documents.len();
+        let number_of_checks = len_entities * len_documents;
+        // Transform number of checks to a human readable string
+        let number_of_checks = match number_of_checks {
+            0..=1000 => format!("{}", number_of_checks),
+            1001..=1000000 => format!("{:.2}K", number_of_checks as f64 / 1000.0),
+            1000001..=1000000000 => format!("{:.2}M", number_of_checks as f64 / 1000000.0),
+            _ => format!("{:.2}B", number_of_checks as f64 / 1000000000.0),
+        };
+        info!("Number of unique checks: {}", number_of_checks);
+        // annotations.save(&config.annotations.output.path);
+        if is_cancelled() || self.progress.is_cancelled() {
+            return self.save_partial(&config, save);
+        }
+        if let Some(path) = &config.annotations.output.frozen_labels {
+            check_frozen_labels(
+                &exported_labels(&self.documents, &config.annotations.output),
+                path,
+            )?;
+        }
+        let mut output = None;
+        if save {
+            let stage = Stage::start("save");
+            let stamp = self.stamp();
+            let save = self.install(|| {
+                config.annotations.format.save_stamped(
+                    &self.documents,
+                    &config.annotations.output,
+                    &stamp,
+                )
+            });
+            match save {
+                Ok(saved) => {
+                    info!(
+                        "Annotations saved with format {:?}",
+                        config.annotations.format
+                    );
+                    output = Some(saved);
+                    if let Some(checkpoint) = checkpoint.take() {
+                        checkpoint.finish()?;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => return Err(e.into()),
+                Err(e) => error!("Unable to save the annotations: {}", e),
+            }
+            stages.push(stage.finish(self.documents.len(), "documents"));
+        } else if let Some(checkpoint) = checkpoint {
+            checkpoint.finish()?;
+        }
+        self.log_run_summary(&stages, started.elapsed());
+        let mut report = self.run_report(&config, started_at, &stages, started.elapsed(), skipped);
+        report.output = output;
+        if report.output.is_some() && config.annotations.output.report {
+            let path = RunReport::path_for(&config.annotations.output.path);
+            match report.save(&path) {
+                Ok(()) => info!("Run report saved to {}", path),
+                Err(e) => error!("Unable to save the run report {}: {}", path, e),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Read the entities and the excludes of the configuration and normalize them
+    /// as `process` does, without reading the texts, e.g. before `compile_matcher`
+    /// to annotate the texts as they come. Unlike `process`, an entities file
+    /// which cannot be read is an error rather than an empty gazetteer.
+    /// # Errors
+    /// Returns an error if the entities or the excludes cannot be read
+    pub fn load_gazetteer(&mut self) -> Result<(), Box<dyn Error>> {
+        let config = self.parse_config();
         if self.entities.is_empty() {
-            let entities: HashSet<Entity> = self.entities(
+            self.entities = self.read_entities(
                 config.entities.input.path.as_str(),
-                config.entities.filters,
+                config.entities.input.format.clone(),
+                config.entities.filters.clone(),
                 config.entities.input.filter.unwrap_or(false),
-            );
-            self.entities = entities.into_iter().collect();
+            )?;
         }
-        if self.documents.is_empty() {
-            let texts: HashSet<Text> = self.texts(
-                config.texts.input.path.as_str(),
-                config.texts.filters,
-                config.texts.input.filter.unwrap_or(false),
+        self.prepare_gazetteer(&config)
+    }
+
+    /// Read the entities of the configuration, unless there are entities already
+    pub(crate) fn load_entities(&mut self, config: &Config) {
+        if self.entities.is_empty() {
+            self.entities = self.entities(
+                config.entities.input.path.as_str(),
+                config.entities.input.format.clone(),
+                config.entities.filters.clone(),
+                config.entities.input.filter.unwrap_or(false),
             );
-            self.documents = texts
-                .par_iter()
-                .map(|text| Document::new((*text.text).to_string(), vec![]))
-                .collect();
         }
-        let excludes: HashSet<String> = match config.entities.excludes.path {
+    }
+
+    /// Read the excludes of the configuration, then lowercase the entities and
+    /// the excludes unless the matching is case sensitive and drop the duplicates
+    /// # Errors
+    /// Returns an error if the excludes file cannot be read
+    pub(crate) fn prepare_gazetteer(&mut self, config: &Config) -> Result<(), Box<dyn Error>> {
+        match config.entities.excludes.path.as_ref() {
             Some(path) => {
                 info!("Reading excludes from {}", path.as_str());
-                self.excludes(path.as_str())
-            }
-            None => {
-                info!("No excludes file provided");
-                HashSet::new()
+                self.excludes = self.excludes(path.as_str())?;
             }
+            None => info!("No excludes file provided"),
         };
-        // Remove excludes from entities
-        let entities: HashSet<Entity> = self
-            .entities
-            .iter()
-            .filter(|entity| !excludes.contains(&entity.name))
-            .cloned()
-            .collect();
-        self.entities = Vec::from_iter(entities);
+        // Excludes are applied while matching, they are compared to the text
+        // of the documents like the entities
+        if !self.config.entities.filters.case_sensitive {
+            self.excludes = self
+                .excludes
+                .iter()
+                .map(|exclude| Exclude {
+                    name: exclude.name.to_lowercase(),
+                    ..exclude.clone()
+                })
+                .collect();
+        }
         if !self.config.entities.filters.case_sensitive {
             self.entities = self
                 .entities
                 .iter()
                 .map(|entity| Entity {
                     name: entity.name.to_lowercase(),
-                    label: entity.label.to_string(),
+                    ..entity.clone()
                 })
                 .collect();
         }
+        self.entities = Quickner::unique_entities(std::mem::take(&mut self.entities));
         info!("{} entities found", self.entities.len());
-        self.annotate();
-        info!("{} annotations found", self.documents.len());
-        let len_entities = self.entities.len();
-        let len_documents = self.documents.len();
-        let number_of_checks = len_entities * len_documents;
-        // Transform number of checks to a human readable string
-        let number_of_checks = match number_of_checks {
-            0..=1000 => format!("{}", number_of_checks),
-            1001..=1000000 => format!("{:.2}K", number_of_checks as f64 / 1000.0),
-            1000001..=1000000000 => format!("{:.2}M", number_of_checks as f64 / 1000000.0),
-            _ => format!("{:.2}B", number_of_checks as f64 / 1000000000.0),
+        Ok(())
+    }
+
+    /// Read the texts of the configuration into the documents, without annotating
+    /// them, e.g. to look at the texts with their case before `process` lowercases
+    /// them. Nothing is read if there are documents already.
+    /// # Errors
+    /// Returns an error if the directory of the texts cannot be read
+    pub fn read_texts(&mut self) -> Result<(), Box<dyn Error>> {
+        let config = self.parse_config();
+        self.load_texts(&config, &mut SkippedTexts::default())
+    }
+
+    /// Read the texts into the documents if there are none, with the filters,
+    /// split and selection of the configuration
+    fn load_texts(
+        &mut self,
+        config: &Config,
+        skipped: &mut SkippedTexts,
+    ) -> Result<(), Box<dyn Error>> {
+        let loaded = self.documents.is_empty();
+        if self.documents.is_empty() && Path::new(&config.texts.input.path).is_dir() {
+            self.documents = self.texts_from_dir(
+                config.texts.input.path.as_str(),
+                config.texts.input.split.as_ref(),
+                config.texts.input.id,
+                &config.texts.filters,
+                config.texts.input.filter.unwrap_or(false),
+                skipped,
+            )?;
+        }
+        if self.documents.is_empty() {
+            let texts: Vec<TextRow> = self.texts(
+                config.texts.input.path.as_str(),
+                config.texts.input.id.unwrap_or_default(),
+                config.texts.filters.clone(),
+                config.texts.input.filter.unwrap_or(false),
+                skipped,
+            )?;
+            self.documents = self.install(|| match config.texts.input.split {
+                Some(Split::Sentence) => texts
+                    .into_par_iter()
+                    .flat_map_iter(|(id, text, meta)| Quickner::sentences(&id, &text, meta))
+                    .collect(),
+                _ => texts
+                    .into_par_iter()
+                    .map(|(id, text, meta)| Document {
+                        id,
+                        meta,
+                        ..Document::new(text, vec![])
+                    })
+                    .collect(),
+            });
+        }
+        if loaded {
+            self.select_documents(&config.texts.input, skipped);
+        }
+        Ok(())
+    }
+
+    /// Log the number of documents, spans and entities of the run
+    /// with the wall time of each stage
+    fn log_run_summary(&self, stages: &[(&str, Duration)], total: Duration) {
+        let spans: usize = self
+            .documents
+            .iter()
+            .map(|document| document.label.len())
+            .sum();
+        info!("----------------------------------------");
+        info!(
+            "Run summary: {} documents, {} spans, {} entities in {:.2?} on {} threads",
+            self.documents.len(),
+            spans,
+            self.entities.len(),
+            total,
+            self.num_threads()
+        );
+        for (stage, elapsed) in stages {
+            let share = match total.as_secs_f64() {
+                0.0 => 0.0,
+                total => elapsed.as_secs_f64() / total * 100.0,
+            };
+            info!("  {:<12}{:>10.2?} ({:.0}%)", stage, elapsed, share);
+        }
+    }
+
+    /// Annotate only the first `n` documents, without saving anything, to check
+    /// the filters and the gazetteer before a full run. The documents are read
+    /// from the texts input if none are loaded, the sample of the input is kept.
+    /// `self` is left unchanged.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let quickner = Quickner::new(Some("./config.toml"));
+    /// for document in quickner.preview(20).unwrap() {
+    ///     println!("{}", document.render_ansi(None));
+    /// }
+    /// ```
+    /// # Errors
+    /// Returns an error if the texts cannot be read
+    pub fn preview(&self, n: usize) -> Result<Vec<Document>, Box<dyn Error>> {
+        let mut config = self.config.clone();
+        config.texts.input.limit = Some(config.texts.input.limit.map_or(n, |limit| limit.min(n)));
+        // A dry run does not create the frozen label set
+        config.annotations.output.frozen_labels = None;
+        let mut preview = Quickner {
+            config,
+            config_file: self.config_file.clone(),
+            documents: self.documents.iter().take(n).cloned().collect(),
+            entities: self.entities.clone(),
+            excludes: self.excludes.clone(),
+            progress: Arc::new(NoopProgress),
+            ..Default::default()
         };
-        info!("Number of unique checks: {}", number_of_checks);
-        // annotations.save(&config.annotations.output.path);
-        if save {
-            let save = config
+        preview.process(false)?;
+        Ok(preview.documents)
+    }
+
+    /// Save the documents annotated before a cancellation to a partial file,
+    /// e.g. annotations_partial.jsonl, and return the cancellation error
+    fn save_partial<T>(&self, config: &Config, save: bool) -> Result<T, Box<dyn Error>> {
+        if !save {
+            return Err(interrupted(format!(
+                "Run cancelled, {} documents annotated",
+                self.documents.len()
+            ))
+            .into());
+        }
+        // Saving the partial output is not cancelled, a second Ctrl-C exits
+        reset_cancel();
+        let output = Output {
+            path: Output::partial_path(&config.annotations.output.path),
+            ..config.annotations.output.clone()
+        };
+        let saved =
+            config
                 .annotations
                 .format
-                .save(&self.documents, &config.annotations.output.path);
-            match save {
-                Ok(_) => info!(
-                    "Annotations saved with format {:?}",
-                    config.annotations.format
-                ),
-                Err(e) => error!("Unable to save the annotations: {}", e),
+                .save_stamped(&self.documents, &output, &self.stamp())?;
+        Err(interrupted(format!(
+            "Run cancelled, the {} documents annotated were saved to {}",
+            self.documents.len(),
+            saved
+        ))
+        .into())
+    }
+
+    /// Read the entities file like `read_entities`, an empty list if it
+    /// cannot be read
+    pub(crate) fn entities(
+        &self,
+        path: &str,
+        format: Option<EntitiesFormat>,
+        filters: Filters,
+        filter: bool,
+    ) -> Vec<Entity> {
+        match self.read_entities(path, format, filters, filter) {
+            Ok(entities) => entities,
+            Err(e) => {
+                warn!("{}, using empty list", e);
+                Vec::new()
             }
         }
-        // Transform annotations to Python objects
-        // List of tuples (text, [[start, end, label], [start, end, label], ...
-        // let annotations_py: Vec<(String, Vec<(usize, usize, String)>)> =
-        //     annotations.transform_annotations();
-        // Ok(annotations_py)
-        Ok(())
     }
 
-    fn entities(&self, path: &str, filters: Filters, filter: bool) -> HashSet<Entity> {
-        // Read CSV file and parse it
+    /// Read the entities file in the format given or detected from its extension,
+    /// keep the entities valid for the filters if `filter` is set and drop the
+    /// duplicates
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    pub(crate) fn read_entities(
+        &self,
+        path: &str,
+        format: Option<EntitiesFormat>,
+        filters: Filters,
+        filter: bool,
+    ) -> Result<Vec<Entity>, Box<dyn Error>> {
+        // Read the entities file and parse it
         // Expect columns: name, label
-        info!("Reading entities from {}", path);
-        let rdr = csv::Reader::from_path(path);
-        match rdr {
-            Ok(mut rdr) => {
-                let mut entities = HashSet::new();
-                for result in rdr.deserialize() {
-                    let record: Result<Entity, csv::Error> = result;
-                    match record {
-                        Ok(mut entity) => {
-                            if filter {
-                                if filters.is_valid(&entity.name) {
-                                    if !filters.case_sensitive {
-                                        entity.name = entity.name.to_lowercase();
-                                    }
-                                    entities.insert(entity);
-                                }
-                            } else {
-                                entities.insert(entity);
-                            }
-                        }
-                        Err(_) => {
-                            warn!("Unable to parse the entities file, using empty list");
-                            return HashSet::new();
+        let format = format.unwrap_or_else(|| EntitiesFormat::from_path(path));
+        info!("Reading entities from {} ({})", path, format);
+        let entities = format
+            .read(path)
+            .map_err(|e| format!("Unable to parse the entities file {path}: {e}"))?;
+        let entities = self.install(|| {
+            entities
+                .into_par_iter()
+                .filter_map(|mut entity| {
+                    if !filter {
+                        return Some(entity);
+                    }
+                    if filters.is_valid(&entity.name) {
+                        if !filters.case_sensitive {
+                            entity.name = entity.name.to_lowercase();
                         }
+                        Some(entity)
+                    } else {
+                        None
                     }
-                }
-                entities
-            }
-            Err(_) => {
-                warn!("Unable to parse the entities file, using empty list");
-                HashSet::new()
-            }
-        }
+                })
+                .collect()
+        });
+        Ok(Quickner::unique_entities(entities))
     }
 
-    fn texts(&self, path: &str, filters: Filters, filter: bool) -> HashSet<Text> {
+    /// Read the ids and texts from a CSV file, in the order of the file, with
+    /// the ids of `strategy`. Duplicated texts (or ids, with the id column) are
+    /// read once, with the metadata of their first row.
+    /// # Errors
+    /// Returns an error if the file cannot be read, misses the `text` column
+    /// (or the `id` column with the id column strategy) or has an empty id
+    fn texts(
+        &self,
+        path: &str,
+        strategy: IdStrategy,
+        filters: Filters,
+        filter: bool,
+        skipped: &mut SkippedTexts,
+    ) -> Result<Vec<TextRow>, Box<dyn Error>> {
         // Read CSV file and parse it
-        // Expect columns: texts
+        // Expect columns: text (and id with the id column), the other columns are kept as metadata
         info!("Reading texts from {}", path);
-        let rdr = csv::Reader::from_path(path);
-        match rdr {
-            Ok(mut rdr) => {
-                let mut texts = HashSet::new();
-                for result in rdr.deserialize() {
-                    let record: Result<Text, csv::Error> = result;
-                    match record {
-                        Ok(text) => {
-                            if filter {
-                                if filters.is_valid(&text.text) {
-                                    texts.insert(text);
-                                }
-                            } else {
-                                texts.insert(text);
-                            }
+        let invalid = |message: String| -> Box<dyn Error> {
+            Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unable to parse the texts file {path}: {message}"),
+            ))
+        };
+        let mut rdr =
+            csv::Reader::from_path(long_path(path)).map_err(|e| invalid(e.to_string()))?;
+        let headers = rdr.headers().map_err(|e| invalid(e.to_string()))?.clone();
+        let column = headers
+            .iter()
+            .position(|header| header == "text")
+            .ok_or_else(|| invalid("missing field `text`".to_string()))?;
+        let id_column = match strategy {
+            IdStrategy::Column => Some(
+                headers
+                    .iter()
+                    .position(|header| header == "id")
+                    .ok_or_else(|| invalid("missing field `id`".to_string()))?,
+            ),
+            _ => None,
+        };
+        let mut seen = HashSet::new();
+        let mut texts = Vec::new();
+        let mut records = rdr.into_records();
+        // Rows are read in chunks, filtered in parallel and deduplicated in order
+        loop {
+            let chunk: Result<Vec<csv::StringRecord>, csv::Error> =
+                records.by_ref().take(READ_CHUNK_SIZE).collect();
+            let chunk = chunk.map_err(|e| invalid(e.to_string()))?;
+            if chunk.is_empty() {
+                break;
+            }
+            // Texts are deduplicated on their hash, or on their id with the id column
+            // The texts rejected by the filters are kept for the run report
+            let rows: Vec<Result<_, String>> = self.install(|| {
+                chunk
+                    .into_par_iter()
+                    .map(|record| {
+                        let text = record.get(column).unwrap_or_default().to_string();
+                        if filter && !filters.is_valid(&text) {
+                            return Err(text);
                         }
-                        Err(e) => {
-                            error!("Unable to parse the texts file: {}", e);
-                            std::process::exit(1);
+                        let meta: HashMap<String, String> = headers
+                            .iter()
+                            .zip(record.iter())
+                            .enumerate()
+                            .filter(|(index, (_, value))| {
+                                *index != column && Some(*index) != id_column && !value.is_empty()
+                            })
+                            .map(|(_, (header, value))| (header.to_string(), value.to_string()))
+                            .collect();
+                        let (key, id) = match id_column {
+                            Some(id_column) => {
+                                let id = record.get(id_column).unwrap_or_default().to_string();
+                                (id.clone(), Some(id))
+                            }
+                            // The position is only known once the duplicates are dropped
+                            None => (hash_string(&text), strategy.id(0, &text)),
+                        };
+                        Ok((key, id, text, meta))
+                    })
+                    .collect()
+            });
+            for row in rows {
+                match row {
+                    Ok((key, id, text, meta)) => match seen.insert(key) {
+                        true => {
+                            let id = match strategy {
+                                IdStrategy::Sequential => texts.len().to_string(),
+                                _ => id.unwrap_or_default(),
+                            };
+                            if id.is_empty() {
+                                return Err(invalid(format!(
+                                    "empty id for the text {}",
+                                    preview(&text)
+                                )));
+                            }
+                            texts.push((id, text, meta));
                         }
+                        false => skipped.duplicates += 1,
+                    },
+                    Err(text) => skipped.filtered(&text),
+                }
+            }
+        }
+        info!("{} texts read from {}", texts.len(), path);
+        Ok(texts)
+    }
+
+    /// Drop the near-duplicates, then keep the sample and the first `limit` documents
+    /// read from the texts input, to iterate quickly on a subset of the corpus
+    fn select_documents(&mut self, input: &Input, skipped: &mut SkippedTexts) {
+        if let Some(threshold) = input.dedupe {
+            skipped.near_duplicates = self
+                .dedupe(threshold)
+                .iter()
+                .map(|cluster| cluster.duplicates.len())
+                .sum();
+        }
+        let total = self.documents.len();
+        if let Some(sample) = input.sample {
+            self.documents
+                .retain(|document| sample_score(input.seed, &document.id) < sample);
+        }
+        if let Some(limit) = input.limit {
+            self.documents.truncate(limit);
+        }
+        if self.documents.len() < total {
+            info!(
+                "{} of {} documents selected by the sample and limit of the texts",
+                self.documents.len(),
+                total
+            );
+        }
+    }
+
+    /// Split a text into one document per sentence, the id of a sentence is the
+    /// id of the text followed by its position, e.g. "report-2" for the third one.
+    /// The id of the text is kept in the "parent" metadata, the position in "sentence".
+    fn sentences(parent: &str, text: &str, meta: HashMap<String, String>) -> Vec<Document> {
+        split_sentences(text)
+            .into_iter()
+            .enumerate()
+            .map(|(index, sentence)| {
+                let mut meta = meta.clone();
+                meta.insert("parent".to_string(), parent.to_string());
+                meta.insert("sentence".to_string(), index.to_string());
+                Document {
+                    id: format!("{parent}-{index}"),
+                    meta,
+                    ..Document::new(sentence, vec![])
+                }
+            })
+            .collect()
+    }
+
+    /// Read the texts from a directory of files, one document per file
+    /// (or per paragraph or sentence if split is set), each file read by the
+    /// extractor of its extension, the .txt files by default, see `register_extractor`.
+    /// The files without an extractor are skipped.
+    /// Document ids are derived from the file names, e.g. "report" or "report-2"
+    /// for the third paragraph of report.txt, or from the whole text of the file
+    /// with an id strategy. The file name is kept in the "file" metadata
+    fn texts_from_dir(
+        &self,
+        path: &str,
+        split: Option<&Split>,
+        strategy: Option<IdStrategy>,
+        filters: &Filters,
+        filter: bool,
+        skipped: &mut SkippedTexts,
+    ) -> Result<Vec<Document>, Box<dyn Error>> {
+        info!("Reading texts from directory {}", path);
+        let files = self.extractors.files(Path::new(path))?;
+        let mut documents = Vec::new();
+        for (position, file) in files.iter().enumerate() {
+            let file_name = file
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let content = match self.extractors.get(file) {
+                Some(extractor) => extractor.extract(file)?,
+                None => continue,
+            };
+            let stem = match strategy.and_then(|strategy| strategy.id(position, &content)) {
+                Some(id) => id,
+                None => file
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            let file_meta = HashMap::from([("file".to_string(), file_name.to_string())]);
+            if split == Some(&Split::Sentence) {
+                for document in Quickner::sentences(&stem, &content, file_meta) {
+                    match !filter || filters.is_valid(&document.text) {
+                        true => documents.push(document),
+                        false => skipped.filtered(&document.text),
                     }
                 }
-                texts
+                continue;
             }
-            Err(e) => {
-                error!("Unable to parse the texts file: {}", e);
-                std::process::exit(1);
+            let texts: Vec<(String, String)> = match split {
+                Some(Split::Paragraph) => split_paragraphs(&content)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, paragraph)| (format!("{stem}-{index}"), paragraph))
+                    .collect(),
+                _ => vec![(stem, content.trim().to_string())],
+            };
+            for (id, text) in texts {
+                if filter && !filters.is_valid(&text) {
+                    skipped.filtered(&text);
+                    continue;
+                }
+                documents.push(Document {
+                    id,
+                    text: text.into(),
+                    label: Vec::new(),
+                    meta: file_meta.clone(),
+                    entity_meta: Vec::new(),
+                    span_meta: Vec::new(),
+                    relations: Vec::new(),
+                    tags: Vec::new(),
+                    status: DocumentStatus::Machine,
+                    extra: serde_json::Map::new(),
+                });
             }
         }
+        info!("{} documents read from {}", documents.len(), path);
+        Ok(documents)
     }
 
-    fn excludes(&self, path: &str) -> HashSet<String> {
+    /// Read the excludes file, the text to exclude in the first column
+    /// and an optional `label` column scoping the exclude to a label,
+    /// an empty label excludes the text with every label
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed
+    fn excludes(&self, path: &str) -> Result<Vec<Exclude>, Box<dyn Error>> {
         // Read CSV file and parse it
-        let rdr = csv::Reader::from_path(path);
-        match rdr {
-            Ok(mut rdr) => {
-                let mut excludes = HashSet::new();
-                for result in rdr.records() {
-                    let record = result.unwrap();
-                    excludes.insert(record[0].to_string());
-                }
-                excludes
-            }
-            Err(e) => {
-                error!("Unable to parse the excludes file: {}", e);
-                std::process::exit(1);
+        let invalid = |e: csv::Error| format!("Unable to parse the excludes file {path}: {e}");
+        let mut rdr = csv::Reader::from_path(long_path(path)).map_err(invalid)?;
+        let label = rdr
+            .headers()
+            .map_err(invalid)?
+            .iter()
+            .position(|header| header == "label");
+        let mut excludes = Vec::new();
+        for result in rdr.records() {
+            let record = result.map_err(invalid)?;
+            let exclude = Exclude {
+                name: record.get(0).unwrap_or_default().to_string(),
+                label: label
+                    .and_then(|label| record.get(label))
+                    .filter(|label| !label.is_empty())
+                    .map(str::to_string),
+            };
+            if !excludes.contains(&exclude) {
+                excludes.push(exclude);
             }
         }
+        Ok(excludes)
     }
 
     pub fn from_jsonl(path: &str) -> Quickner {
-        let file = File::open(path);
-        let file = match file {
-            Ok(file) => file,
+        Quickner::from_jsonl_with_fields(path, &Fields::default())
+    }
+
+    /// Load documents from a JSONL file whose field names differ from
+    /// the default ones ("id", "text" and "label").
+    /// If the file does not provide an id, it is derived from the text.
+    /// Files ending with .gz are read as gzip-compressed files.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Fields, Quickner};
+    /// let fields = Fields {
+    ///     text: "content".to_string(),
+    ///     label: "labels".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let quickner = Quickner::from_jsonl_with_fields("./annotations.jsonl", &fields);
+    /// ```
+    pub fn from_jsonl_with_fields(path: &str, fields: &Fields) -> Quickner {
+        let documents = match Format::read_jsonl(path, fields) {
+            Ok(documents) => documents,
             Err(e) => {
-                error!("Unable to open the file {}: {}", path, e);
+                error!("Unable to read the file {}: {}", path, e);
                 std::process::exit(1);
             }
         };
-        let reader = BufReader::new(file);
-        // Read the JSON objects from the file
-        // Parse each JSON object as Annotation and add it to the annotations
+        Quickner::from_documents(documents)
+    }
+
+    /// Quickner with a default configuration holding already annotated documents,
+    /// the entities are extracted from their spans
+    fn from_documents(documents: Vec<Document>) -> Quickner {
+        // Extract the entity name from the label
         let mut entities = Vec::new();
-        let mut texts: Vec<Text> = Vec::new();
-        let documents: Vec<Document> = reader
-            .lines()
-            .map(|line| {
-                let line = line.unwrap();
-                let annotation: Document = serde_json::from_str(line.as_str()).unwrap();
-                let text = Text {
-                    text: (*annotation.text).to_string(),
+        for annotation in &documents {
+            for label in &annotation.label {
+                let indices = char_to_byte(&annotation.text, label.0, label.1);
+                let name = annotation.text[indices.0..indices.1].to_string();
+                let entity = Entity {
+                    name: name.to_string().to_lowercase(),
+                    label: label.2.to_string(),
+                    ..Default::default()
                 };
-                texts.push(text);
-                // Extract the entity name from the label
-                for label in &annotation.label {
-                    let indices = char_to_byte((*annotation.text).to_string(), label.0, label.1);
-                    let name = annotation.text[indices.0..indices.1].to_string();
-                    let entity = Entity {
-                        name: name.to_string().to_lowercase(),
-                        label: label.2.to_string(),
-                    };
-                    entities.push(entity);
-                }
-                annotation
-            })
-            .collect();
+                entities.push(entity);
+            }
+        }
+        let documents = Format::imported(documents);
         let entities = Quickner::unique_entities(entities);
         let documents_hash = Quickner::document_hash(&documents);
         let mut quick = Quickner {
-            config: Config::default(),
             config_file: None,
             documents,
             entities,
             documents_hash,
-            documents_label_index: HashMap::new(),
-            documents_entities_index: HashMap::new(),
+            ..Default::default()
         };
         quick.build_entity_index();
         quick.build_label_index();
         quick
     }
 
+    /// Import every annotation file of a directory and of its subdirectories,
+    /// whatever their format: JSONL, spaCy, brat pairs, CoNLL or the CSV output
+    /// of quickner, see `Format::detect`. The other files are skipped, as are the
+    /// files that cannot be parsed, with a warning.
+    ///
+    /// The path of the file of each document, relative to the directory, is kept
+    /// in the "source" metadata and its format in "source_format". A document found
+    /// in several files (same id) is imported once: its spans are merged, the
+    /// metadata of the first file is kept and the other files are added to
+    /// "source", comma separated. The files are read in the order of their paths.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let quickner = Quickner::from_dir("./legacy_annotations").unwrap();
+    /// println!("{} documents imported", quickner.documents.len());
+    /// ```
+    /// # Errors
+    /// Returns an error if the directory cannot be read
+    pub fn from_dir(path: &str) -> Result<Quickner, std::io::Error> {
+        let root = Path::new(path);
+        let mut files = Vec::new();
+        Quickner::walk_dir(root, &mut files)?;
+        files.sort();
+        let mut documents: Vec<Document> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for file in files {
+            let format = match Format::detect(&file) {
+                Some(format) => format,
+                None => continue,
+            };
+            let source = file
+                .strip_prefix(root)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let imported = match format.read(&file) {
+                Ok(imported) => imported,
+                Err(e) => {
+                    warn!("Skipping {} ({}): {}", source, format, e);
+                    continue;
+                }
+            };
+            info!(
+                "{} documents read from {} ({})",
+                imported.len(),
+                source,
+                format
+            );
+            for mut document in imported {
+                match positions.get(&document.id) {
+                    Some(position) => {
+                        let merged = &mut documents[*position];
+                        for span in document.label {
+                            if !merged.label.contains(&span) {
+                                merged.label.push(span);
+                            }
+                        }
+                        merged.label.sort();
+                        let sources = merged.meta.entry("source".to_string()).or_default();
+                        if !sources.split(',').any(|known| known == source) {
+                            sources.push(',');
+                            sources.push_str(&source);
+                        }
+                    }
+                    None => {
+                        document.meta.insert("source".to_string(), source.clone());
+                        document
+                            .meta
+                            .insert("source_format".to_string(), format.to_string());
+                        positions.insert(document.id.clone(), documents.len());
+                        documents.push(document);
+                    }
+                }
+            }
+        }
+        info!("{} documents imported from {}", documents.len(), path);
+        Ok(Quickner::from_documents(documents))
+    }
+
+    /// Files of a directory and of its subdirectories, hidden ones excluded
+    fn walk_dir(directory: &Path, files: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+        for entry in fs::read_dir(long_path(directory))? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                Quickner::walk_dir(&path, files)?;
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+
     pub fn from_spacy(path: &str) -> Quickner {
-        let file = File::open(path);
+        let file = File::open(long_path(path));
         let file = match file {
             Ok(file) => file,
             Err(e) => {
@@ -664,27 +1494,32 @@ impl Quickner {
                 };
                 texts.push(text);
                 // Extract the entity name from the label
+                // spaCy offsets are in characters, as the spans of the documents
                 for ent in &doc.1.entity {
-                    let name = doc.0[ent.0..ent.1].to_string();
+                    let (start, end) = char_to_byte(&doc.0, ent.0, ent.1);
+                    let name = doc.0[start..end].to_string();
                     let entity = Entity {
                         name: name.to_lowercase(),
                         label: ent.2.to_string(),
+                        ..Default::default()
                     };
                     entities.push(entity);
                 }
-                Document::new(doc.0, doc.1.entity)
+                Document {
+                    meta: doc.1.meta,
+                    ..Document::new(doc.0, doc.1.entity)
+                }
             })
             .collect();
+        let documents = Format::imported(documents);
         let entities = Quickner::unique_entities(entities);
         let documents_hash = Quickner::document_hash(&documents);
         let mut quick = Quickner {
-            config: Config::default(),
             config_file: None,
             documents,
             entities,
             documents_hash,
-            documents_label_index: HashMap::new(),
-            documents_entities_index: HashMap::new(),
+            ..Default::default()
         };
         quick.build_entity_index();
         quick.build_label_index();
@@ -698,7 +1533,13 @@ impl Quickner {
             for label in &document.label {
                 entity.push((label.0, label.1, (*label.2).to_string()));
             }
-            spacy.push(((*document.text).to_string(), SpacyEntity { entity }));
+            spacy.push((
+                (*document.text).to_string(),
+                SpacyEntity {
+                    entity,
+                    meta: document.meta.clone(),
+                },
+            ));
         }
         let chunks = match chunks {
             Some(chunks) => chunks,
@@ -713,6 +1554,37 @@ impl Quickner {
         }
         spacy_chunks
     }
+
+    /// Patterns of a spaCy EntityRuler matching the entities of the gazetteer,
+    /// see `EntityRulerPattern::new` for the case sensitivity
+    pub fn entity_ruler_patterns(&self) -> Vec<EntityRulerPattern> {
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        self.entities
+            .iter()
+            .filter(|entity| !entity.name.trim().is_empty())
+            .map(|entity| EntityRulerPattern::new(entity, case_sensitive))
+            .collect()
+    }
+
+    /// Save the gazetteer as the JSONL patterns of a spaCy EntityRuler,
+    /// one pattern per line, multi-word entities have one token pattern per word
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// quickner.to_entity_ruler("./patterns.jsonl").unwrap();
+    /// ```
+    /// # Errors
+    /// Returns an error if the file cannot be written
+    pub fn to_entity_ruler(&self, path: &str) -> Result<(), std::io::Error> {
+        let mut file = BufWriter::new(File::create(long_path(path))?);
+        for pattern in self.entity_ruler_patterns() {
+            serde_json::to_writer(&mut file, &pattern)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()
+    }
 }
 
 impl Quickner {
@@ -732,7 +1604,7 @@ impl Quickner {
         for document in &self.documents {
             for label in &document.label {
                 // Translate the indices to byte indices
-                let indices = char_to_byte((*document.text).to_string(), label.0, label.1);
+                let indices = char_to_byte(&document.text, label.0, label.1);
                 let name = document.text[indices.0..indices.1].to_string();
                 let entry = index.entry(name.to_lowercase()).or_insert(Vec::new());
                 entry.push((*document.id).to_string());
@@ -753,7 +1625,7 @@ impl Quickner {
 
     fn add_to_entity_index(&mut self, document: &Document) {
         for label in &document.label {
-            let indices = char_to_byte((*document.text).to_string(), label.0, label.1);
+            let indices = char_to_byte(&document.text, label.0, label.1);
             let name = document.text[indices.0..indices.1].to_string();
             let entry = self
                 .documents_entities_index
@@ -775,7 +1647,7 @@ impl Quickner {
 
     fn _remove_from_entity_index(&mut self, document: &Document) {
         for label in &document.label {
-            let indices = char_to_byte(document.text.clone(), label.0, label.1);
+            let indices = char_to_byte(&document.text, label.0, label.1);
             let name = document.text[indices.0..indices.1].to_string();
             let entry = self
                 .documents_entities_index
@@ -785,12 +1657,18 @@ impl Quickner {
         }
     }
 
-    fn unique_entities(entities: Vec<Entity>) -> Vec<Entity> {
+    /// Remove the duplicated entities, keeping the first occurrence so the order
+    /// of the entities file is preserved. Entities with the same name and label
+    /// but different ids are kept, they are the candidates of an ambiguous
+    /// surface form.
+    pub(crate) fn unique_entities(entities: Vec<Entity>) -> Vec<Entity> {
+        let mut seen = HashSet::new();
         entities
             .into_iter()
-            .collect::<HashSet<Entity>>()
-            .into_iter()
-            .collect::<Vec<Entity>>()
+            .filter(|entity| {
+                seen.insert((entity.name.clone(), entity.label.clone(), entity.id.clone()))
+            })
+            .collect()
     }
 
     pub fn document_hash(documents: &[Document]) -> HashMap<String, Document> {
@@ -0,0 +1,144 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::RelationPattern, document::Document, utils::tokenize};
+
+/// A relation between two spans of a document, e.g. a person and the company
+/// they lead. The spans are in the (start, end, label) form of the spans.
+///
+/// It is written under the `relations` key of the JSONL output
+/// and as the `R` lines of the brat output.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Relation {
+    pub head_span: (usize, usize, String),
+    pub tail_span: (usize, usize, String),
+    pub label: String,
+}
+
+/// Placeholders of the spans in a relation pattern
+const HEAD: &str = "{head}";
+const TAIL: &str = "{tail}";
+
+/// Lowercased words of a text
+fn words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    tokenize(text)
+        .into_iter()
+        .map(|(start, end)| chars[start..end].iter().collect::<String>().to_lowercase())
+        .collect()
+}
+
+impl RelationPattern {
+    pub(crate) fn default_window() -> usize {
+        10
+    }
+
+    /// Words of the pattern before the first span, between the spans and after
+    /// the second one, and whether the head comes first. None if the pattern
+    /// does not have each placeholder once.
+    pub(crate) fn parts(pattern: &str) -> Option<([Vec<String>; 3], bool)> {
+        if pattern.matches(HEAD).count() != 1 || pattern.matches(TAIL).count() != 1 {
+            return None;
+        }
+        let (head, tail) = (pattern.find(HEAD)?, pattern.find(TAIL)?);
+        let (first, second) = (head.min(tail), head.max(tail));
+        let parts = [
+            words(&pattern[..first]),
+            words(&pattern[first + HEAD.len()..second]),
+            words(&pattern[second + TAIL.len()..]),
+        ];
+        Some((parts, head < tail))
+    }
+
+    /// Add the relations of the patterns between the spans of the document,
+    /// the relations already present are not added twice
+    pub fn apply(patterns: &[RelationPattern], document: &mut Document) {
+        if patterns.is_empty() || document.label.len() < 2 {
+            return;
+        }
+        let tokens = tokenize(&document.text);
+        let chars: Vec<char> = document.text.chars().collect();
+        let words: Vec<String> = tokens
+            .iter()
+            .map(|(start, end)| {
+                chars[*start..*end]
+                    .iter()
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .collect();
+        // Indices of the first token of each span and of the token after it
+        let bounds: Vec<(usize, usize)> = document
+            .label
+            .iter()
+            .map(|(start, end, _)| {
+                let first = tokens.partition_point(|(_, token_end)| token_end <= start);
+                let last = tokens
+                    .partition_point(|(token_start, _)| token_start < end)
+                    .max(first);
+                (first, last)
+            })
+            .collect();
+        let mut relations = Vec::new();
+        for pattern in patterns {
+            let parts = pattern.pattern.as_deref().and_then(RelationPattern::parts);
+            // Without a pattern, the spans are in any order unless their labels
+            // cannot tell the head from the tail, each pair is then taken once
+            let either = parts.is_none() && pattern.head != pattern.tail;
+            for (head, head_span) in document.label.iter().enumerate() {
+                for (tail, tail_span) in document.label.iter().enumerate() {
+                    let labels = |span: &(usize, usize, String), label: &Option<String>| {
+                        label.as_ref().is_none_or(|label| *label == span.2)
+                    };
+                    if head == tail
+                        || !labels(head_span, &pattern.head)
+                        || !labels(tail_span, &pattern.tail)
+                    {
+                        continue;
+                    }
+                    let head_first = match &parts {
+                        Some((_, head_first)) => *head_first,
+                        None => !either || bounds[head] <= bounds[tail],
+                    };
+                    let ((first, first_end), (second, second_end)) = match head_first {
+                        true => (bounds[head], bounds[tail]),
+                        false => (bounds[tail], bounds[head]),
+                    };
+                    // The first span must end before the second one starts
+                    if first_end > second || first == first_end || second == second_end {
+                        continue;
+                    }
+                    let matches = match &parts {
+                        Some(([before, between, after], _)) => {
+                            first >= before.len()
+                                && words[first - before.len()..first] == before[..]
+                                && words[first_end..second] == between[..]
+                                && words.len() >= second_end + after.len()
+                                && words[second_end..second_end + after.len()] == after[..]
+                        }
+                        None => second - first_end <= pattern.window,
+                    };
+                    if !matches {
+                        continue;
+                    }
+                    let relation = Relation {
+                        head_span: head_span.clone(),
+                        tail_span: tail_span.clone(),
+                        label: pattern.label.clone(),
+                    };
+                    if !document.relations.contains(&relation) && !relations.contains(&relation) {
+                        relations.push(relation);
+                    }
+                }
+            }
+        }
+        document.relations.extend(relations);
+    }
+}
@@ -0,0 +1,180 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    config::Config,
+    quickner::Quickner,
+    stats::Stats,
+    utils::{char_to_byte, format_rfc3339, long_path, preview},
+};
+
+/// Machine-readable report of a run of `process`, written next to the
+/// annotations when they are saved, for pipeline monitoring and dataset cards
+#[derive(Serialize, Clone, Debug)]
+pub struct RunReport {
+    /// Version of quickner
+    pub version: String,
+    /// Start of the run, RFC 3339 in UTC
+    pub started: String,
+    /// Wall time of the run in seconds
+    pub seconds: f64,
+    /// Number of threads of the parallel stages
+    pub threads: usize,
+    /// Configuration of the run
+    pub config: Value,
+    /// Path of the annotations, if they were saved
+    pub output: Option<String>,
+    /// Counts of the annotated corpus and spans per label
+    pub stats: Stats,
+    /// Number of entities of the gazetteer per label
+    pub gazetteer: BTreeMap<String, usize>,
+    /// Most annotated entities, by number of spans
+    pub top_entities: Vec<EntityCount>,
+    /// Texts read but not annotated
+    pub skipped_texts: SkippedTexts,
+    /// Wall time of each stage of the run, in order
+    pub stages: Vec<StageTiming>,
+}
+
+/// Number of spans of an entity, by surface form and label
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct EntityCount {
+    pub name: String,
+    pub label: String,
+    pub count: usize,
+}
+
+/// Wall time of a stage of a run
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct StageTiming {
+    pub stage: String,
+    pub seconds: f64,
+}
+
+/// Texts of the input left out of the annotation
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SkippedTexts {
+    /// Texts rejected by the filters of the texts
+    pub filtered: usize,
+    /// Texts found more than once, annotated once
+    pub duplicates: usize,
+    /// Documents dropped as near-duplicates of another one, see `dedupe`
+    pub near_duplicates: usize,
+    /// Previews of the first texts rejected by the filters
+    pub examples: Vec<String>,
+}
+
+impl SkippedTexts {
+    /// Number of filtered texts kept as examples
+    const EXAMPLES: usize = 10;
+
+    /// Count a text rejected by the filters
+    pub(crate) fn filtered(&mut self, text: &str) {
+        self.filtered += 1;
+        if self.examples.len() < SkippedTexts::EXAMPLES {
+            self.examples.push(preview(text).to_string());
+        }
+    }
+}
+
+impl RunReport {
+    /// Number of entities listed in `top_entities`
+    const TOP_ENTITIES: usize = 100;
+
+    /// Path of the report of the annotations saved to `output`,
+    /// e.g. annotations_report.json for annotations.jsonl.gz
+    pub fn path_for(output: &str) -> String {
+        let path = Path::new(output);
+        let stem = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = stem.split('.').next().unwrap_or_default();
+        path.with_file_name(format!("{stem}_report.json"))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// The report as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub fn save(&self, path: &str) -> Result<(), std::io::Error> {
+        fs::write(long_path(path), self.to_json())
+    }
+}
+
+impl Quickner {
+    /// Report of the run with the current documents and entities
+    pub(crate) fn run_report(
+        &self,
+        config: &Config,
+        started: SystemTime,
+        stages: &[(&str, Duration)],
+        total: Duration,
+        skipped_texts: SkippedTexts,
+    ) -> RunReport {
+        let mut gazetteer: BTreeMap<String, usize> = BTreeMap::new();
+        for entity in &self.entities {
+            *gazetteer.entry(entity.label.to_string()).or_default() += 1;
+        }
+        let mut counts: HashMap<(String, String), usize> = HashMap::new();
+        for document in &self.documents {
+            for (start, end, label) in &document.label {
+                let (start, end) = char_to_byte(&document.text, *start, *end);
+                if let Some(surface) = document.text.get(start..end) {
+                    *counts
+                        .entry((surface.to_string(), label.to_string()))
+                        .or_default() += 1;
+                }
+            }
+        }
+        let mut top_entities: Vec<EntityCount> = counts
+            .into_iter()
+            .map(|((name, label), count)| EntityCount { name, label, count })
+            .collect();
+        top_entities.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.label.cmp(&b.label))
+        });
+        top_entities.truncate(RunReport::TOP_ENTITIES);
+        RunReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started: format_rfc3339(started),
+            seconds: total.as_secs_f64(),
+            threads: self.num_threads(),
+            config: serde_json::to_value(config).unwrap_or_default(),
+            output: None,
+            stats: self.stats(),
+            gazetteer,
+            top_entities,
+            skipped_texts,
+            stages: stages
+                .iter()
+                .map(|(stage, duration)| StageTiming {
+                    stage: stage.to_string(),
+                    seconds: duration.as_secs_f64(),
+                })
+                .collect(),
+        }
+    }
+}
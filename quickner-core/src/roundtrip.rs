@@ -0,0 +1,245 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{collections::HashSet, fs};
+
+use serde::Serialize;
+
+use crate::{
+    config::{Format, Output},
+    document::Document,
+};
+
+/// Report of the export of documents to a format and of their import back,
+/// serialized as JSON
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RoundTripReport {
+    /// The format the documents went through
+    pub format: String,
+    /// Whether the imported documents are the exported ones
+    pub lossless: bool,
+    /// Number of documents exported
+    pub documents: usize,
+    /// Number of documents imported back
+    pub imported: usize,
+    /// Number of spans exported
+    pub spans: usize,
+    /// Number of spans imported back
+    pub imported_spans: usize,
+    /// The differences, in the order of the documents
+    pub changes: Vec<RoundTripChange>,
+}
+
+/// A difference between an exported document and the document imported back
+/// at the same position
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct RoundTripChange {
+    /// Position of the document, starting at 0
+    pub index: usize,
+    /// Id of the exported document
+    pub id: String,
+    pub kind: RoundTripChangeKind,
+    /// The exported span, for the span changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exported: Option<(usize, usize, String)>,
+    /// The imported span, for the span changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imported: Option<(usize, usize, String)>,
+    pub message: String,
+}
+
+/// Kind of a round trip change
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundTripChangeKind {
+    /// The document is not imported back
+    LostDocument,
+    /// A document is imported back that was not exported
+    AddedDocument,
+    /// The id of the document changed
+    AlteredId,
+    /// The text of the document changed
+    AlteredText,
+    /// Metadata fields of the document are not imported back
+    LostMeta,
+    /// The span is not imported back
+    LostSpan,
+    /// A span is imported back that was not exported
+    AddedSpan,
+    /// The span is imported back with other offsets or another label
+    AlteredSpan,
+}
+
+impl RoundTripReport {
+    /// Export the documents to a format in a temporary directory, import them back
+    /// and compare them, to check which formats keep the data of a pipeline.
+    /// Documents are compared by position, their spans exactly, a lost span
+    /// overlapping an added one is reported as altered.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Format, Quickner, RoundTripReport};
+    /// let quickner = Quickner::from_jsonl("./annotations.jsonl");
+    /// let report = RoundTripReport::check(&Format::Conll, &quickner.documents).unwrap();
+    /// println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    /// ```
+    /// # Errors
+    /// Returns an error if the files cannot be written or read back
+    pub fn check(format: &Format, documents: &[Document]) -> Result<Self, std::io::Error> {
+        let directory =
+            std::env::temp_dir().join(format!("quickner-roundtrip-{}", std::process::id()));
+        fs::create_dir_all(&directory)?;
+        let output = Output {
+            path: directory.join("annotations").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let imported = format
+            .save_with_options(&documents.to_vec(), &output)
+            .and_then(|_| format.load(&output));
+        fs::remove_dir_all(&directory)?;
+        Ok(RoundTripReport::compare(format, documents, &imported?))
+    }
+
+    /// Compare the exported documents to the documents imported back
+    pub fn compare(format: &Format, exported: &[Document], imported: &[Document]) -> Self {
+        let mut changes = Vec::new();
+        for (index, document) in exported.iter().enumerate() {
+            let change = |kind, message: String| RoundTripChange {
+                index,
+                id: document.id.clone(),
+                kind,
+                exported: None,
+                imported: None,
+                message,
+            };
+            let other = match imported.get(index) {
+                Some(other) => other,
+                None => {
+                    changes.push(change(
+                        RoundTripChangeKind::LostDocument,
+                        "the document is not imported back".to_string(),
+                    ));
+                    continue;
+                }
+            };
+            if other.id != document.id {
+                changes.push(change(
+                    RoundTripChangeKind::AlteredId,
+                    format!("imported with the id {}", other.id),
+                ));
+            }
+            if other.text != document.text {
+                changes.push(change(
+                    RoundTripChangeKind::AlteredText,
+                    format!(
+                        "{} characters exported, {} imported",
+                        document.text.chars().count(),
+                        other.text.chars().count()
+                    ),
+                ));
+            }
+            let mut lost: Vec<&String> = document
+                .meta
+                .keys()
+                .filter(|key| other.meta.get(*key) != document.meta.get(*key))
+                .collect();
+            if !lost.is_empty() {
+                lost.sort();
+                changes.push(change(
+                    RoundTripChangeKind::LostMeta,
+                    format!(
+                        "metadata not imported back: {}",
+                        lost.iter()
+                            .map(|key| key.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ),
+                ));
+            }
+            RoundTripReport::compare_spans(index, document, other, &mut changes);
+        }
+        for (index, document) in imported.iter().enumerate().skip(exported.len()) {
+            changes.push(RoundTripChange {
+                index,
+                id: document.id.clone(),
+                kind: RoundTripChangeKind::AddedDocument,
+                exported: None,
+                imported: None,
+                message: "the document was not exported".to_string(),
+            });
+        }
+        let spans =
+            |documents: &[Document]| documents.iter().map(|document| document.label.len()).sum();
+        RoundTripReport {
+            format: format.to_string(),
+            lossless: changes.is_empty(),
+            documents: exported.len(),
+            imported: imported.len(),
+            spans: spans(exported),
+            imported_spans: spans(imported),
+            changes,
+        }
+    }
+
+    fn compare_spans(
+        index: usize,
+        document: &Document,
+        other: &Document,
+        changes: &mut Vec<RoundTripChange>,
+    ) {
+        let exported: HashSet<&(usize, usize, String)> = document.label.iter().collect();
+        let imported: HashSet<&(usize, usize, String)> = other.label.iter().collect();
+        let mut lost: Vec<&(usize, usize, String)> =
+            exported.difference(&imported).copied().collect();
+        let mut added: Vec<&(usize, usize, String)> =
+            imported.difference(&exported).copied().collect();
+        lost.sort();
+        added.sort();
+        let change =
+            |kind, exported: Option<&(usize, usize, String)>, imported, message| RoundTripChange {
+                index,
+                id: document.id.clone(),
+                kind,
+                exported: exported.cloned(),
+                imported,
+                message,
+            };
+        for span in lost {
+            let altered = added
+                .iter()
+                .position(|other| other.0 < span.1 && span.0 < other.1);
+            match altered {
+                Some(position) => {
+                    let other = added.remove(position);
+                    changes.push(change(
+                        RoundTripChangeKind::AlteredSpan,
+                        Some(span),
+                        Some(other.clone()),
+                        format!(
+                            "({}, {}, {}) imported as ({}, {}, {})",
+                            span.0, span.1, span.2, other.0, other.1, other.2
+                        ),
+                    ));
+                }
+                None => changes.push(change(
+                    RoundTripChangeKind::LostSpan,
+                    Some(span),
+                    None,
+                    "the span is not imported back".to_string(),
+                )),
+            }
+        }
+        for span in added {
+            changes.push(change(
+                RoundTripChangeKind::AddedSpan,
+                None,
+                Some(span.clone()),
+                "the span was not exported".to_string(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,455 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex, PoisonError, RwLock, RwLockReadGuard},
+    thread,
+    time::Duration,
+};
+
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{document::Document, entity::Entity, matcher::Matcher, quickner::Quickner};
+
+/// Largest body of a request, in bytes
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// Largest request line and headers of a request, in bytes
+const MAX_HEAD_SIZE: u64 = 64 * 1024;
+
+/// Number of connections handled at once, the others wait in the queue
+const WORKERS: usize = 16;
+
+/// Number of connections accepted and waiting for a worker, the next ones
+/// wait to be accepted
+const QUEUE_SIZE: usize = 64;
+
+/// Longest wait for the next bytes of a request, or for a response to be sent
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Body of `POST /annotate`
+#[derive(Deserialize)]
+struct AnnotateRequest {
+    texts: Vec<String>,
+}
+
+/// Entities of the body of `POST /entities`, `PUT /entities` and `DELETE /entities`:
+/// a single entity or a list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EntitiesRequest {
+    One(Entity),
+    Many(Vec<Entity>),
+}
+
+impl From<EntitiesRequest> for Vec<Entity> {
+    fn from(request: EntitiesRequest) -> Self {
+        match request {
+            EntitiesRequest::One(entity) => vec![entity],
+            EntitiesRequest::Many(entities) => entities,
+        }
+    }
+}
+
+/// A response: status code and JSON body. The locks of the server are used
+/// even if a thread panicked while holding them, the gazetteer is only
+/// replaced once it is compiled
+struct Response(u16, Value);
+
+impl Response {
+    fn error(status: u16, message: impl ToString) -> Self {
+        Response(status, json!({ "error": message.to_string() }))
+    }
+
+    fn write(&self, stream: &mut impl Write) -> Result<(), Error> {
+        let reason = match self.0 {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            _ => "Internal Server Error",
+        };
+        let body = self.1.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.0,
+            reason,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+/// Annotation service over HTTP: the gazetteer is compiled once and the texts
+/// posted to `/annotate` are annotated with it. The entities can be listed,
+/// added, replaced and removed through `/entities`, the matcher is compiled
+/// again after each change.
+pub struct Server {
+    /// The instance holding the configuration, the entities and the excludes
+    quickner: RwLock<Quickner>,
+    matcher: RwLock<Arc<Matcher>>,
+}
+
+impl Server {
+    /// Read the entities and the excludes of the configuration of the instance
+    /// and compile them, as `process` does
+    /// # Errors
+    /// Returns an error if the entities or the excludes cannot be read
+    pub fn new(mut quickner: Quickner) -> Result<Self, Box<dyn std::error::Error>> {
+        quickner.load_gazetteer()?;
+        let matcher = Arc::new(quickner.compile_matcher());
+        Ok(Server {
+            quickner: RwLock::new(quickner),
+            matcher: RwLock::new(matcher),
+        })
+    }
+
+    /// Serve the requests on `address` (e.g. 127.0.0.1:8080) until the process
+    /// is stopped. The connections are handled by a pool of `WORKERS` threads,
+    /// a client silent for `TIMEOUT` is disconnected
+    /// # Errors
+    /// Returns an error if the address cannot be bound
+    pub fn serve(self, address: &str) -> Result<(), Error> {
+        let listener = TcpListener::bind(address)?;
+        info!("Serving on http://{}", listener.local_addr()?);
+        let server = Arc::new(self);
+        // The connections wait in a bounded queue, accepting blocks when it is full
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(QUEUE_SIZE);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..WORKERS {
+            let server = Arc::clone(&server);
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("quickner-server-{index}"))
+                .spawn(move || loop {
+                    let stream = receiver
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .recv();
+                    match stream {
+                        Ok(stream) => server.respond(stream),
+                        Err(_) => break,
+                    }
+                })?;
+        }
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Unable to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = stream
+                .set_read_timeout(Some(TIMEOUT))
+                .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+            {
+                warn!("Unable to set the timeouts of a connection: {}", e);
+                continue;
+            }
+            if sender.send(stream).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a request from the connection and write its response
+    fn respond(&self, mut stream: TcpStream) {
+        let response = match Server::read_request(&mut stream) {
+            Ok((method, path, body)) => self.handle(&method, &path, &body),
+            Err(e) if e.kind() == ErrorKind::InvalidInput => Response::error(413, e),
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                warn!("Connection closed after {:?} without a request", TIMEOUT);
+                return;
+            }
+            Err(e) => Response::error(400, e),
+        };
+        if let Err(e) = response.write(&mut stream) {
+            warn!("Unable to write a response: {}", e);
+        }
+    }
+
+    /// Method, path and body of a request. The request line and headers are
+    /// limited to `MAX_HEAD_SIZE` bytes and the body to `MAX_BODY_SIZE` bytes,
+    /// the body is read as it comes rather than allocated from its length
+    fn read_request(stream: impl Read) -> Result<(String, String, Vec<u8>), Error> {
+        let mut reader = BufReader::new(stream);
+        let mut head = reader.by_ref().take(MAX_HEAD_SIZE);
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (method, path) = match (parts.next(), parts.next()) {
+            (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Malformed request line")),
+        };
+        let mut length = 0;
+        loop {
+            line.clear();
+            if head.read_line(&mut line)? == 0 {
+                if head.limit() == 0 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The headers are larger than {MAX_HEAD_SIZE} bytes"),
+                    ));
+                }
+                break;
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = value
+                        .trim()
+                        .parse()
+                        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                }
+            }
+        }
+        if length > MAX_BODY_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The body is larger than {MAX_BODY_SIZE} bytes"),
+            ));
+        }
+        let mut body = Vec::new();
+        reader.take(length as u64).read_to_end(&mut body)?;
+        if body.len() < length {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("The body is shorter than its length of {length} bytes"),
+            ));
+        }
+        Ok((method, path, body))
+    }
+
+    fn handle(&self, method: &str, path: &str, body: &[u8]) -> Response {
+        let path = path.split('?').next().unwrap_or_default();
+        let response = match (method, path) {
+            ("GET", "/health") => Ok(self.health()),
+            ("POST", "/annotate") => {
+                serde_json::from_slice(body).map(|request| self.annotate(request))
+            }
+            ("GET", "/entities") => Ok(self.entities()),
+            ("POST", "/entities") => serde_json::from_slice(body)
+                .map(|request: EntitiesRequest| self.add_entities(request.into())),
+            ("PUT", "/entities") => serde_json::from_slice(body)
+                .map(|request: EntitiesRequest| self.replace_entities(request.into())),
+            ("DELETE", "/entities") => serde_json::from_slice(body)
+                .map(|request: EntitiesRequest| self.remove_entities(request.into())),
+            (_, "/health" | "/annotate" | "/entities") => {
+                return Response::error(405, format!("{method} is not allowed on {path}"))
+            }
+            _ => return Response::error(404, format!("No route for {path}")),
+        };
+        response.unwrap_or_else(|e| Response::error(400, format!("Invalid body: {e}")))
+    }
+
+    fn health(&self) -> Response {
+        let entities = self.read().entities.len();
+        Response(200, json!({ "status": "ok", "entities": entities }))
+    }
+
+    /// Annotate the texts, the documents keep the case of the texts
+    fn annotate(&self, request: AnnotateRequest) -> Response {
+        let matcher = Arc::clone(&self.matcher.read().unwrap_or_else(PoisonError::into_inner));
+        let mut documents: Vec<Document> = request
+            .texts
+            .iter()
+            .map(|text| Document::from_string(text.to_string()))
+            .collect();
+        self.read()
+            .install(|| matcher.annotate_many(&mut documents));
+        for (document, text) in documents.iter_mut().zip(request.texts) {
+            document.text = text.into();
+        }
+        Response(200, json!({ "documents": documents }))
+    }
+
+    fn entities(&self) -> Response {
+        Response(200, json!({ "entities": self.read().entities }))
+    }
+
+    fn add_entities(&self, entities: Vec<Entity>) -> Response {
+        self.update(entities, |gazetteer, entities| gazetteer.extend(entities))
+    }
+
+    fn replace_entities(&self, entities: Vec<Entity>) -> Response {
+        self.update(entities, |gazetteer, entities| *gazetteer = entities)
+    }
+
+    /// Remove the entities with the name and label of one of the entities
+    fn remove_entities(&self, entities: Vec<Entity>) -> Response {
+        self.update(entities, |gazetteer, entities| {
+            gazetteer.retain(|entity| {
+                !entities
+                    .iter()
+                    .any(|removed| removed.name == entity.name && removed.label == entity.label)
+            })
+        })
+    }
+
+    /// Change the gazetteer with the entities of a request, lowercased like the
+    /// entities of the configuration unless the matching is case sensitive,
+    /// then compile the matcher again. The body holds the number of entities
+    fn update(
+        &self,
+        mut entities: Vec<Entity>,
+        change: impl FnOnce(&mut Vec<Entity>, Vec<Entity>),
+    ) -> Response {
+        let mut quickner = self
+            .quickner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !quickner.config.entities.filters.case_sensitive {
+            for entity in entities.iter_mut() {
+                entity.name = entity.name.to_lowercase();
+            }
+        }
+        change(&mut quickner.entities, entities);
+        quickner.entities = Quickner::unique_entities(std::mem::take(&mut quickner.entities));
+        let matcher = Arc::new(quickner.compile_matcher());
+        *self.matcher.write().unwrap_or_else(PoisonError::into_inner) = matcher;
+        Response(200, json!({ "entities": quickner.entities.len() }))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Quickner> {
+        self.quickner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        let quickner = Quickner {
+            entities: vec![Entity {
+                name: "Rust".to_string(),
+                label: "PL".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        Server::new(quickner).unwrap()
+    }
+
+    /// Spans of the documents annotated by the server
+    fn annotate(server: &Server, texts: Value) -> Value {
+        let body = json!({ "texts": texts }).to_string();
+        let Response(status, body) = server.handle("POST", "/annotate", body.as_bytes());
+        assert_eq!(status, 200);
+        body["documents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|document| document["label"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn health_and_entities() {
+        let server = server();
+        let Response(status, body) = server.handle("GET", "/health?verbose=1", b"");
+        assert_eq!(status, 200);
+        assert_eq!(body, json!({ "status": "ok", "entities": 1 }));
+        let Response(status, body) = server.handle("GET", "/entities", b"");
+        assert_eq!(status, 200);
+        assert_eq!(body["entities"][0]["name"], "rust");
+    }
+
+    #[test]
+    fn annotate_keeps_the_case_of_the_texts() {
+        let server = server();
+        let body = json!({ "texts": ["I love Rust"] }).to_string();
+        let Response(status, body) = server.handle("POST", "/annotate", body.as_bytes());
+        assert_eq!(status, 200);
+        assert_eq!(body["documents"][0]["text"], "I love Rust");
+        assert_eq!(body["documents"][0]["label"], json!([[7, 11, "PL"]]));
+    }
+
+    #[test]
+    fn entity_changes_are_used_by_the_next_annotations() {
+        let server = server();
+        let texts = json!(["Rust and Python"]);
+        let added = json!([{ "name": "Python", "label": "PL" }]).to_string();
+        let Response(status, body) = server.handle("POST", "/entities", added.as_bytes());
+        assert_eq!((status, body), (200, json!({ "entities": 2 })));
+        assert_eq!(
+            annotate(&server, texts.clone()),
+            json!([[[0, 4, "PL"], [9, 15, "PL"]]])
+        );
+
+        let replaced = json!({ "name": "and", "label": "CONJ" }).to_string();
+        let Response(status, body) = server.handle("PUT", "/entities", replaced.as_bytes());
+        assert_eq!((status, body), (200, json!({ "entities": 1 })));
+        assert_eq!(annotate(&server, texts.clone()), json!([[[5, 8, "CONJ"]]]));
+
+        let removed = json!({ "name": "AND", "label": "CONJ" }).to_string();
+        let Response(status, body) = server.handle("DELETE", "/entities", removed.as_bytes());
+        assert_eq!((status, body), (200, json!({ "entities": 0 })));
+        assert_eq!(annotate(&server, texts), json!([[]]));
+    }
+
+    #[test]
+    fn unknown_routes_methods_and_bodies_are_rejected() {
+        let server = server();
+        assert_eq!(server.handle("GET", "/missing", b"").0, 404);
+        assert_eq!(server.handle("GET", "/annotate", b"").0, 405);
+        assert_eq!(server.handle("PATCH", "/entities", b"").0, 405);
+        let Response(status, body) = server.handle("POST", "/annotate", b"{\"text\": \"Rust\"}");
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().starts_with("Invalid body"));
+        assert_eq!(server.handle("POST", "/entities", b"[1, 2]").0, 400);
+    }
+
+    #[test]
+    fn read_request_limits_the_head_and_the_body() {
+        let request = b"POST /annotate HTTP/1.1\r\nContent-Length: 4\r\n\r\nRust";
+        let (method, path, body) = Server::read_request(&request[..]).unwrap();
+        assert_eq!((method.as_str(), path.as_str()), ("POST", "/annotate"));
+        assert_eq!(body, b"Rust");
+
+        let request = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", usize::MAX);
+        let error = Server::read_request(request.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        let request = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nRust";
+        let error = Server::read_request(&request[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        let request = format!(
+            "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(70_000)
+        );
+        let error = Server::read_request(request.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        let error = Server::read_request(&b"\r\n"[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn response_is_written_as_http() {
+        let mut written = Vec::new();
+        Response::error(404, "No route for /")
+            .write(&mut written)
+            .unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(written.ends_with("\r\n\r\n{\"error\":\"No route for /\"}"));
+    }
+}
@@ -0,0 +1,266 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::AggregationStrategy, quickner::Quickner};
+
+/// Where the span of a document comes from
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanSource {
+    /// Added without a record of where it comes from, e.g. by hand
+    #[default]
+    Unknown,
+    /// Matched by an entity of the gazetteer
+    Gazetteer,
+    /// Matched by an entity of the gazetteer with edits, see fuzzy matching
+    Fuzzy,
+    /// Matched by a regular expression
+    Regex,
+    /// Relabeled by a context rule
+    Rule,
+    /// Repeated mention of a span of the document, see `propagate_mentions`
+    Mention,
+    /// Read from an annotation file
+    Import,
+}
+
+impl Display for SpanSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpanSource::Unknown => write!(f, "unknown"),
+            SpanSource::Gazetteer => write!(f, "gazetteer"),
+            SpanSource::Fuzzy => write!(f, "fuzzy"),
+            SpanSource::Regex => write!(f, "regex"),
+            SpanSource::Rule => write!(f, "rule"),
+            SpanSource::Mention => write!(f, "mention"),
+            SpanSource::Import => write!(f, "import"),
+        }
+    }
+}
+
+impl FromStr for SpanSource {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source.to_lowercase().as_str() {
+            "unknown" => Ok(SpanSource::Unknown),
+            "gazetteer" => Ok(SpanSource::Gazetteer),
+            "fuzzy" => Ok(SpanSource::Fuzzy),
+            "regex" => Ok(SpanSource::Regex),
+            "rule" => Ok(SpanSource::Rule),
+            "mention" => Ok(SpanSource::Mention),
+            "import" => Ok(SpanSource::Import),
+            _ => Err(format!("Unknown span source: {source}")),
+        }
+    }
+}
+
+/// A span of a document with its provenance, the offsets are in characters
+/// and the end is exclusive, as the spans (start, end, label) of the document.
+///
+/// It is written under the `span_meta` key of the JSONL output, and read
+/// from objects or from the (start, end, label) form of the spans.
+/// # Example
+/// ```
+/// use quickner::{Span, SpanSource};
+/// let span: Span = serde_json::from_str(r#"[0, 4, "PL"]"#).unwrap();
+/// assert_eq!(span.source, SpanSource::Unknown);
+/// let span: Span = serde_json::from_str(
+///     r#"{"start": 0, "end": 4, "label": "PL", "source": "gazetteer", "entity_name": "rust"}"#,
+/// ).unwrap();
+/// assert_eq!(span.entity_name.as_deref(), Some("rust"));
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(from = "SpanRecord")]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub label: String,
+    pub source: SpanSource,
+    /// Name of the entity of the gazetteer matched, as written in the text
+    /// unless the span was matched with edits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_name: Option<String>,
+    /// Confidence of the annotator in the span, between 0 and 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// Decision taken when the annotators gave the span different labels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<Aggregation>,
+}
+
+/// Aggregation of the labels given to the exact same span by the annotators,
+/// see [`LabelAggregation`](crate::LabelAggregation)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Aggregation {
+    pub strategy: AggregationStrategy,
+    /// Sources which gave the span its label
+    pub sources: Vec<SpanSource>,
+    /// Weighted votes of the sources for the label
+    pub score: f64,
+    /// Weighted votes for the labels dropped, by label
+    pub rejected: BTreeMap<String, f64>,
+}
+
+/// Forms of a span in the files read
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SpanRecord {
+    Tuple(usize, usize, String),
+    Object {
+        start: usize,
+        end: usize,
+        label: String,
+        #[serde(default)]
+        source: SpanSource,
+        #[serde(default)]
+        entity_name: Option<String>,
+        #[serde(default)]
+        confidence: Option<f32>,
+        #[serde(default)]
+        aggregation: Option<Aggregation>,
+    },
+}
+
+impl From<SpanRecord> for Span {
+    fn from(record: SpanRecord) -> Self {
+        match record {
+            SpanRecord::Tuple(start, end, label) => Span::from((start, end, label)),
+            SpanRecord::Object {
+                start,
+                end,
+                label,
+                source,
+                entity_name,
+                confidence,
+                aggregation,
+            } => Span {
+                start,
+                end,
+                label,
+                source,
+                entity_name,
+                confidence,
+                aggregation,
+            },
+        }
+    }
+}
+
+impl From<(usize, usize, String)> for Span {
+    fn from((start, end, label): (usize, usize, String)) -> Self {
+        Span {
+            start,
+            end,
+            label,
+            ..Default::default()
+        }
+    }
+}
+
+impl Eq for Span {}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.start, self.end, &self.label, self.source).hash(state);
+        self.entity_name.hash(state);
+        self.confidence.map(f32::to_bits).hash(state);
+    }
+}
+
+impl Span {
+    /// Span of the given source, e.g. of an annotator outside of quickner
+    pub fn new(span: &(usize, usize, String), source: SpanSource) -> Self {
+        Span {
+            source,
+            ..Span::from(span.clone())
+        }
+    }
+
+    /// The span in the (start, end, label) form of the spans of a document
+    pub fn to_tuple(&self) -> (usize, usize, String) {
+        (self.start, self.end, self.label.clone())
+    }
+
+    /// Whether the span is the (start, end, label) span
+    pub fn is(&self, span: &(usize, usize, String)) -> bool {
+        self.start == span.0 && self.end == span.1 && self.label == span.2
+    }
+}
+
+impl Quickner {
+    /// Keep the spans for which `keep` returns true, e.g. to remove the spans
+    /// of an annotator or below a confidence, with their provenance and the
+    /// metadata of their entity. Returns the number of spans removed, the
+    /// indexes are rebuilt.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Quickner, SpanSource};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let removed = quickner.filter_spans(|span| span.source != SpanSource::Fuzzy);
+    /// println!("{removed} fuzzy matches removed");
+    /// ```
+    pub fn filter_spans<F: FnMut(&Span) -> bool>(&mut self, mut keep: F) -> usize {
+        let snapshot = self.history_snapshot();
+        let removed = self
+            .documents
+            .iter_mut()
+            .map(|document| document.retain_spans(&mut keep))
+            .sum();
+        info!("{} spans removed", removed);
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("filter_spans", snapshot);
+        removed
+    }
+
+    /// Rename the label `from` to `to` in the documents, with the provenance,
+    /// the metadata and the relations of their spans, and in the entities.
+    /// Returns the number of spans renamed, the indexes are rebuilt.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::from_jsonl("./annotations.jsonl");
+    /// let renamed = quickner.rename_label("ORG", "ORGANIZATION");
+    /// println!("{renamed} spans renamed");
+    /// ```
+    pub fn rename_label(&mut self, from: &str, to: &str) -> usize {
+        let snapshot = self.history_snapshot();
+        let renamed = self
+            .documents
+            .iter_mut()
+            .map(|document| document.rename_label(from, to))
+            .sum();
+        for entity in self.entities.iter_mut() {
+            if entity.label == from {
+                entity.label = to.to_string();
+            }
+        }
+        self.entities = Quickner::unique_entities(std::mem::take(&mut self.entities));
+        info!("{} spans renamed from {} to {}", renamed, from, to);
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.build_label_index();
+        self.build_entity_index();
+        self.record_history("rename_label", snapshot);
+        renamed
+    }
+}
@@ -0,0 +1,452 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::{Display, Formatter},
+    io::Write,
+    str::FromStr,
+};
+
+use aho_corasick::AhoCorasick;
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{
+    entity::Entity,
+    quickner::Quickner,
+    utils::{char_to_byte, long_path},
+};
+
+/// Statistics of an annotated corpus
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of documents
+    pub documents: usize,
+    /// Number of documents with at least one span
+    pub annotated_documents: usize,
+    /// Number of spans
+    pub spans: usize,
+    /// Number of entities in the gazetteer
+    pub entities: usize,
+    /// Statistics per label
+    pub labels: BTreeMap<String, LabelStats>,
+}
+
+/// Statistics of a label in an annotated corpus
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct LabelStats {
+    /// Number of spans with the label
+    pub spans: usize,
+    /// Number of documents with at least one span with the label
+    pub documents: usize,
+    /// Number of unique surface forms annotated with the label
+    pub surfaces: usize,
+}
+
+/// Length of the periods of the time-sliced statistics
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    #[default]
+    Month,
+    Year,
+}
+
+/// Statistics of an annotated corpus per time period, from a date
+/// in the metadata of the documents, to monitor the drift of the data
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct TimeStats {
+    /// Metadata field holding the date of the documents
+    pub field: String,
+    /// Length of the periods
+    pub period: Period,
+    /// Statistics of each period, oldest first
+    pub periods: Vec<PeriodStats>,
+    /// Number of documents without a valid date
+    pub undated: usize,
+}
+
+/// Statistics of the documents of a time period
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct PeriodStats {
+    /// The period, e.g. 2023-05 for a month
+    pub period: String,
+    /// Number of documents
+    pub documents: usize,
+    /// Number of documents with at least one span
+    pub annotated_documents: usize,
+    /// Number of spans
+    pub spans: usize,
+    /// Number of entities annotated for the first time in this period
+    pub new_entities: usize,
+    /// Frequency of the entities annotated in the period, most frequent first
+    pub entities: Vec<EntityFrequency>,
+}
+
+/// Number of spans of an entity in a time period
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntityFrequency {
+    /// Surface form of the entity, lowercased
+    pub entity: String,
+    pub label: String,
+    /// Number of spans
+    pub count: usize,
+    /// Whether the entity was not annotated in any earlier period
+    pub new: bool,
+}
+
+impl Period {
+    /// The period of a date starting with YYYY, YYYY-MM or YYYY-MM-DD
+    /// (e.g. 2023-05-12 or 2023-05-12T08:30:00Z), None if it is not a date
+    pub fn of(&self, date: &str) -> Option<String> {
+        let date = date.trim();
+        let parts: Vec<&str> = date
+            .get(..10.min(date.len()))?
+            .splitn(3, ['-', '/'])
+            .collect();
+        let valid = |part: &&str, length: usize| {
+            part.len() == length && part.chars().all(|c| c.is_ascii_digit())
+        };
+        let year = parts.first().filter(|year| valid(year, 4))?;
+        let month = parts.get(1).filter(|month| valid(month, 2));
+        let day = parts.get(2).filter(|day| valid(day, 2));
+        match self {
+            Period::Year => Some(year.to_string()),
+            Period::Month => Some(format!("{}-{}", year, month?)),
+            Period::Day => Some(format!("{}-{}-{}", year, month?, day?)),
+        }
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Period::Day => write!(f, "day"),
+            Period::Month => write!(f, "month"),
+            Period::Year => write!(f, "year"),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(period: &str) -> Result<Self, Self::Err> {
+        match period.to_lowercase().as_str() {
+            "day" => Ok(Period::Day),
+            "month" => Ok(Period::Month),
+            "year" => Ok(Period::Year),
+            _ => Err(format!("Unknown period: {period}")),
+        }
+    }
+}
+
+impl TimeStats {
+    /// Write the entity frequencies as CSV, one row per entity and period
+    /// with the columns period, documents, label, entity, count and new
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(["period", "documents", "label", "entity", "count", "new"])?;
+        for period in &self.periods {
+            for entity in &period.entities {
+                writer.write_record([
+                    period.period.as_str(),
+                    &period.documents.to_string(),
+                    entity.label.as_str(),
+                    entity.entity.as_str(),
+                    &entity.count.to_string(),
+                    &entity.new.to_string(),
+                ])?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Save the entity frequencies to a CSV file, see `write_csv`
+    pub fn to_csv(&self, path: &str) -> Result<(), csv::Error> {
+        self.write_csv(std::fs::File::create(long_path(path))?)
+    }
+}
+
+/// Statistics of the gazetteer compiled into the matcher
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct GazetteerStats {
+    /// Number of patterns in the automaton
+    pub patterns: usize,
+    /// Heap memory used by the automaton, in bytes
+    pub memory: usize,
+    /// Longest pattern, in characters
+    pub longest: Option<String>,
+    /// Shortest pattern, in characters
+    pub shortest: Option<String>,
+    /// Surface forms given several labels, with their labels
+    pub duplicates: BTreeMap<String, Vec<String>>,
+    /// Patterns containing other patterns, with the patterns they contain.
+    /// Both are matched, so the spans of the shorter pattern overlap the longer one.
+    pub substrings: BTreeMap<String, Vec<String>>,
+}
+
+impl GazetteerStats {
+    /// Number of examples given in the warnings
+    const EXAMPLES: usize = 5;
+
+    pub(crate) fn new(entities: &[Entity], automaton: &AhoCorasick) -> Self {
+        let mut labels: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for entity in entities {
+            labels
+                .entry(entity.name.as_str())
+                .or_default()
+                .insert(entity.label.as_str());
+        }
+        let mut substrings = BTreeMap::new();
+        for name in labels.keys() {
+            let contained: BTreeSet<&str> = automaton
+                .find_overlapping_iter(name)
+                .map(|mat| entities[mat.pattern()].name.as_str())
+                .filter(|pattern| pattern != name)
+                .collect();
+            if !contained.is_empty() {
+                substrings.insert(
+                    name.to_string(),
+                    contained.into_iter().map(String::from).collect(),
+                );
+            }
+        }
+        GazetteerStats {
+            patterns: automaton.pattern_count(),
+            memory: automaton.heap_bytes(),
+            longest: labels
+                .keys()
+                .max_by_key(|name| name.chars().count())
+                .map(|name| name.to_string()),
+            shortest: labels
+                .keys()
+                .min_by_key(|name| name.chars().count())
+                .map(|name| name.to_string()),
+            duplicates: labels
+                .iter()
+                .filter(|(_, labels)| labels.len() > 1)
+                .map(|(name, labels)| {
+                    (
+                        name.to_string(),
+                        labels.iter().map(|label| label.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            substrings,
+        }
+    }
+
+    /// Log the statistics, with a warning for the duplicated surface forms
+    /// and the patterns containing other patterns
+    pub(crate) fn report(&self) {
+        let memory = match self.memory {
+            0..=1023 => format!("{} B", self.memory),
+            1024..=1048575 => format!("{:.2} KB", self.memory as f64 / 1024.0),
+            _ => format!("{:.2} MB", self.memory as f64 / 1048576.0),
+        };
+        info!(
+            "Gazetteer compiled: {} patterns, {} of automaton memory, shortest \"{}\", longest \"{}\"",
+            self.patterns,
+            memory,
+            self.shortest.as_deref().unwrap_or_default(),
+            self.longest.as_deref().unwrap_or_default()
+        );
+        if !self.duplicates.is_empty() {
+            let examples = self
+                .duplicates
+                .iter()
+                .take(GazetteerStats::EXAMPLES)
+                .map(|(name, labels)| format!("\"{}\" ({})", name, labels.join(", ")))
+                .collect::<Vec<String>>()
+                .join(", ");
+            warn!(
+                "{} surface forms have several labels, one span is annotated per label: {}",
+                self.duplicates.len(),
+                examples
+            );
+        }
+        if !self.substrings.is_empty() {
+            let examples = self
+                .substrings
+                .iter()
+                .take(GazetteerStats::EXAMPLES)
+                .map(|(name, contained)| {
+                    format!("\"{}\" contains \"{}\"", name, contained.join("\", \""))
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            warn!(
+                "{} patterns contain other patterns, the shorter patterns may also be matched inside the longer ones: {}",
+                self.substrings.len(),
+                examples
+            );
+        }
+    }
+}
+
+impl Quickner {
+    /// Compute the statistics of the gazetteer, as compiled when annotating:
+    /// pattern count, automaton memory, longest and shortest patterns,
+    /// surface forms with several labels and patterns containing other patterns
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let stats = quickner.gazetteer_stats();
+    /// println!("{} patterns", stats.patterns);
+    /// ```
+    pub fn gazetteer_stats(&self) -> GazetteerStats {
+        let automaton = AhoCorasick::new(self.entities.iter().map(|entity| entity.name.as_str()));
+        GazetteerStats::new(&self.entities, &automaton)
+    }
+
+    /// Compute the statistics of the annotated corpus
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let stats = quickner.stats();
+    /// println!("{} spans in {} documents", stats.spans, stats.documents);
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let mut labels: BTreeMap<String, LabelStats> = BTreeMap::new();
+        let mut surfaces: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        let mut spans = 0;
+        let mut annotated_documents = 0;
+        for document in &self.documents {
+            if !document.label.is_empty() {
+                annotated_documents += 1;
+            }
+            let mut document_labels: HashSet<&str> = HashSet::new();
+            for (start, end, label) in &document.label {
+                spans += 1;
+                let stats = labels.entry(label.to_string()).or_default();
+                stats.spans += 1;
+                if document_labels.insert(label.as_str()) {
+                    stats.documents += 1;
+                }
+                let (start, end) = char_to_byte(&document.text, *start, *end);
+                if let Some(surface) = document.text.get(start..end) {
+                    surfaces
+                        .entry(label.to_string())
+                        .or_default()
+                        .insert(surface.to_lowercase());
+                }
+            }
+        }
+        for (label, surfaces) in surfaces {
+            if let Some(stats) = labels.get_mut(&label) {
+                stats.surfaces = surfaces.len();
+            }
+        }
+        Stats {
+            documents: self.documents.len(),
+            annotated_documents,
+            spans,
+            entities: self.entities.len(),
+            labels,
+        }
+    }
+
+    /// Compute the statistics of the annotated corpus per time period,
+    /// from the date in the `field` metadata of the documents (e.g. a `date`
+    /// column of the texts file): entity frequencies and new entities per period
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Period, Quickner};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let stats = quickner.time_stats("date", Period::Month);
+    /// for period in &stats.periods {
+    ///     println!("{}: {} new entities", period.period, period.new_entities);
+    /// }
+    /// stats.to_csv("./drift.csv").unwrap();
+    /// ```
+    pub fn time_stats(&self, field: &str, period: Period) -> TimeStats {
+        let mut periods: BTreeMap<String, PeriodStats> = BTreeMap::new();
+        let mut frequencies: BTreeMap<String, HashMap<(String, String), usize>> = BTreeMap::new();
+        let mut undated = 0;
+        for document in &self.documents {
+            let key = match document.meta.get(field).and_then(|date| period.of(date)) {
+                Some(key) => key,
+                None => {
+                    undated += 1;
+                    continue;
+                }
+            };
+            let stats = periods.entry(key.clone()).or_insert_with(|| PeriodStats {
+                period: key.clone(),
+                ..Default::default()
+            });
+            stats.documents += 1;
+            if !document.label.is_empty() {
+                stats.annotated_documents += 1;
+            }
+            stats.spans += document.label.len();
+            let frequencies = frequencies.entry(key).or_default();
+            for (start, end, label) in &document.label {
+                let (start, end) = char_to_byte(&document.text, *start, *end);
+                if let Some(surface) = document.text.get(start..end) {
+                    *frequencies
+                        .entry((surface.to_lowercase(), label.to_string()))
+                        .or_default() += 1;
+                }
+            }
+        }
+        if undated > 0 {
+            warn!(
+                "{} documents without a valid date in the {} metadata are not counted",
+                undated, field
+            );
+        }
+        // Periods are sorted oldest first, an entity is new in the first one it appears in
+        let mut seen: HashSet<(String, String)> = HashSet::new();
+        let periods = periods
+            .into_values()
+            .map(|mut stats| {
+                let mut entities: Vec<EntityFrequency> = frequencies
+                    .remove(&stats.period)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|((entity, label), count)| EntityFrequency {
+                        new: !seen.contains(&(entity.clone(), label.clone())),
+                        entity,
+                        label,
+                        count,
+                    })
+                    .collect();
+                entities.sort_by(|a, b| {
+                    b.count
+                        .cmp(&a.count)
+                        .then_with(|| a.label.cmp(&b.label))
+                        .then_with(|| a.entity.cmp(&b.entity))
+                });
+                seen.extend(
+                    entities
+                        .iter()
+                        .map(|entity| (entity.entity.clone(), entity.label.clone())),
+                );
+                stats.new_entities = entities.iter().filter(|entity| entity.new).count();
+                stats.entities = entities;
+                stats
+            })
+            .collect();
+        TimeStats {
+            field: field.to_string(),
+            period,
+            periods,
+            undated,
+        }
+    }
+}
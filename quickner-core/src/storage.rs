@@ -0,0 +1,388 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{collections::HashMap, error::Error};
+
+use log::info;
+use rusqlite::{params, types::Type, Connection, OpenFlags, Row};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{document::Document, entity::Entity, quickner::Quickner, utils::long_path};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS documents (
+    id TEXT PRIMARY KEY,
+    text TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'machine',
+    meta TEXT,
+    entity_meta TEXT,
+    span_meta TEXT,
+    relations TEXT,
+    tags TEXT,
+    extra TEXT
+);
+CREATE TABLE IF NOT EXISTS entities (
+    name TEXT NOT NULL,
+    label TEXT NOT NULL,
+    id TEXT NOT NULL DEFAULT '',
+    canonical TEXT,
+    attributes TEXT,
+    PRIMARY KEY (name, label, id)
+);
+CREATE TABLE IF NOT EXISTS annotations (
+    document_id TEXT NOT NULL REFERENCES documents (id) ON DELETE CASCADE,
+    start INTEGER NOT NULL,
+    end INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    PRIMARY KEY (document_id, start, end, label)
+);
+CREATE INDEX IF NOT EXISTS annotations_label ON annotations (label);
+";
+
+/// Columns of the documents added since the first schema, with their definition
+const DOCUMENT_COLUMNS: [(&str, &str); 7] = [
+    ("status", "TEXT NOT NULL DEFAULT 'machine'"),
+    ("meta", "TEXT"),
+    ("entity_meta", "TEXT"),
+    ("span_meta", "TEXT"),
+    ("relations", "TEXT"),
+    ("tags", "TEXT"),
+    ("extra", "TEXT"),
+];
+
+/// Entities of a database created before their metadata, keyed on their name
+/// and label only, copied to the current table
+const MIGRATE_ENTITIES: &str = "
+ALTER TABLE entities RENAME TO entities_previous;
+CREATE TABLE entities (
+    name TEXT NOT NULL,
+    label TEXT NOT NULL,
+    id TEXT NOT NULL DEFAULT '',
+    canonical TEXT,
+    attributes TEXT,
+    PRIMARY KEY (name, label, id)
+);
+INSERT INTO entities (name, label) SELECT name, label FROM entities_previous;
+DROP TABLE entities_previous;
+";
+
+fn has_column(connection: &Connection, table: &str, column: &str) -> Result<bool, rusqlite::Error> {
+    connection
+        .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")?
+        .exists(params![table, column])
+}
+
+/// Create the tables missing from the database and add the columns added since
+/// it was created
+fn create_schema(connection: &Connection) -> Result<(), rusqlite::Error> {
+    connection.execute_batch(SCHEMA)?;
+    for (column, definition) in DOCUMENT_COLUMNS {
+        if !has_column(connection, "documents", column)? {
+            connection.execute_batch(&format!(
+                "ALTER TABLE documents ADD COLUMN {column} {definition}"
+            ))?;
+        }
+    }
+    if !has_column(connection, "entities", "id")? {
+        connection.execute_batch(MIGRATE_ENTITIES)?;
+    }
+    Ok(())
+}
+
+/// Value stored as JSON text
+fn to_json<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(value)
+}
+
+/// Value of a JSON text column, the default if it is NULL
+fn from_json<T: DeserializeOwned + Default>(row: &Row, index: usize) -> Result<T, rusqlite::Error> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e))),
+        None => Ok(T::default()),
+    }
+}
+
+impl Quickner {
+    /// Save the documents, entities and annotations to a SQLite database.
+    /// The database is created if it does not exist, existing documents
+    /// are updated in place (keyed on the document id) and their
+    /// annotations replaced, other documents are left untouched.
+    /// The metadata, provenance, relations, tags and extra fields of the
+    /// documents, and the id, canonical form and attributes of the entities,
+    /// are stored as JSON text columns.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// quickner.to_sqlite("./corpus.db").unwrap();
+    /// ```
+    pub fn to_sqlite(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut connection = Connection::open(long_path(path))?;
+        create_schema(&connection)?;
+        let transaction = connection.transaction()?;
+        {
+            let mut upsert_document = transaction.prepare(
+                "INSERT INTO documents
+                 (id, text, status, meta, entity_meta, span_meta, relations, tags, extra)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT (id) DO UPDATE SET text = excluded.text,
+                 status = excluded.status, meta = excluded.meta,
+                 entity_meta = excluded.entity_meta, span_meta = excluded.span_meta,
+                 relations = excluded.relations, tags = excluded.tags,
+                 extra = excluded.extra",
+            )?;
+            let mut delete_annotations =
+                transaction.prepare("DELETE FROM annotations WHERE document_id = ?1")?;
+            let mut insert_annotation = transaction.prepare(
+                "INSERT OR IGNORE INTO annotations (document_id, start, end, label)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut upsert_entity = transaction.prepare(
+                "INSERT INTO entities (name, label, id, canonical, attributes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (name, label, id) DO UPDATE SET
+                 canonical = excluded.canonical, attributes = excluded.attributes",
+            )?;
+            for document in &self.documents {
+                upsert_document.execute(params![
+                    document.id,
+                    document.text,
+                    document.status.to_string(),
+                    to_json(&document.meta)?,
+                    to_json(&document.entity_meta)?,
+                    to_json(&document.span_meta)?,
+                    to_json(&document.relations)?,
+                    to_json(&document.tags)?,
+                    to_json(&document.extra)?,
+                ])?;
+                delete_annotations.execute(params![document.id])?;
+                for (start, end, label) in &document.label {
+                    insert_annotation.execute(params![document.id, start, end, label])?;
+                }
+            }
+            for entity in &self.entities {
+                upsert_entity.execute(params![
+                    entity.name,
+                    entity.label,
+                    entity.id.as_deref().unwrap_or_default(),
+                    entity.canonical,
+                    to_json(&entity.attributes)?,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        info!(
+            "{} documents and {} entities saved to {}",
+            self.documents.len(),
+            self.entities.len(),
+            path
+        );
+        Ok(())
+    }
+
+    /// Load the documents, entities and annotations from a SQLite database
+    /// created with `to_sqlite`.
+    /// # Errors
+    /// Returns an error if the database does not exist, it is not created
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let quickner = Quickner::from_sqlite("./corpus.db").unwrap();
+    /// ```
+    pub fn from_sqlite(path: &str) -> Result<Quickner, Box<dyn Error>> {
+        if !long_path(path).exists() {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("The database {path} does not exist"),
+            )));
+        }
+        let flags = OpenFlags::default().difference(OpenFlags::SQLITE_OPEN_CREATE);
+        let connection = Connection::open_with_flags(long_path(path), flags)?;
+        create_schema(&connection)?;
+        let mut labels: HashMap<String, Vec<(usize, usize, String)>> = HashMap::new();
+        let mut statement = connection.prepare(
+            "SELECT document_id, start, end, label FROM annotations ORDER BY start, end",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (row.get(1)?, row.get(2)?, row.get(3)?),
+            ))
+        })?;
+        for row in rows {
+            let (id, span) = row?;
+            labels.entry(id).or_default().push(span);
+        }
+        let mut statement = connection.prepare(
+            "SELECT id, text, status, meta, entity_meta, span_meta, relations, tags, extra
+             FROM documents ORDER BY rowid",
+        )?;
+        let documents = statement
+            .query_map([], |row| {
+                let id: String = row.get(0)?;
+                let label = labels.remove(&id).unwrap_or_default();
+                let status: String = row.get(2)?;
+                Ok(Document {
+                    id,
+                    text: row.get(1)?,
+                    label,
+                    meta: from_json(row, 3)?,
+                    entity_meta: from_json(row, 4)?,
+                    span_meta: from_json(row, 5)?,
+                    relations: from_json(row, 6)?,
+                    tags: from_json(row, 7)?,
+                    status: status.parse().unwrap_or_default(),
+                    extra: from_json(row, 8)?,
+                })
+            })?
+            .collect::<Result<Vec<Document>, _>>()?;
+        let mut statement = connection.prepare(
+            "SELECT name, label, id, canonical, attributes FROM entities ORDER BY rowid",
+        )?;
+        let entities = statement
+            .query_map([], |row| {
+                let id: String = row.get(2)?;
+                Ok(Entity {
+                    name: row.get(0)?,
+                    label: row.get(1)?,
+                    id: (!id.is_empty()).then_some(id),
+                    canonical: row.get(3)?,
+                    attributes: from_json(row, 4)?,
+                })
+            })?
+            .collect::<Result<Vec<Entity>, _>>()?;
+        let documents_hash = Quickner::document_hash(&documents);
+        let mut quick = Quickner {
+            config_file: None,
+            documents,
+            entities,
+            documents_hash,
+            ..Default::default()
+        };
+        quick.build_entity_index();
+        quick.build_label_index();
+        Ok(quick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::AggregationStrategy,
+        document::DocumentStatus,
+        entity::EntityMeta,
+        relation::Relation,
+        span::{Aggregation, Span, SpanSource},
+    };
+
+    #[test]
+    fn sqlite_round_trip_keeps_the_documents_and_entities() {
+        let entity = Entity {
+            name: "rust".to_string(),
+            label: "PL".to_string(),
+            id: Some("Q575650".to_string()),
+            canonical: Some("Rust".to_string()),
+            attributes: HashMap::from([("paradigm".to_string(), "systems".to_string())]),
+        };
+        let span = (0, 4, "PL".to_string());
+        let mut document = Document::new(
+            "Rust by Mozilla".to_string(),
+            vec![span.clone(), (8, 15, "ORG".to_string())],
+        );
+        document.meta = HashMap::from([("source".to_string(), "blog".to_string())]);
+        document.entity_meta = vec![EntityMeta::new(&span, &entity)];
+        document.span_meta = vec![Span {
+            start: 0,
+            end: 4,
+            label: "PL".to_string(),
+            source: SpanSource::Gazetteer,
+            entity_name: Some("rust".to_string()),
+            confidence: Some(0.5),
+            aggregation: Some(Aggregation {
+                strategy: AggregationStrategy::Vote,
+                sources: vec![SpanSource::Gazetteer],
+                score: 1.0,
+                rejected: BTreeMap::from([("ORG".to_string(), 0.5)]),
+            }),
+        }];
+        document.relations = vec![Relation {
+            head_span: span,
+            tail_span: (8, 15, "ORG".to_string()),
+            label: "made_by".to_string(),
+        }];
+        document.tags = vec!["tech".to_string()];
+        document.status = DocumentStatus::Gold;
+        document.extra = json!({"origin": {"tool": "other"}})
+            .as_object()
+            .cloned()
+            .unwrap();
+        let mut quickner = Quickner::default();
+        quickner.add_document(document);
+        quickner.add_document(Document::from_string("No entity here".to_string()));
+        quickner.add_entity(entity);
+        quickner.add_entity(Entity {
+            name: "mozilla".to_string(),
+            label: "ORG".to_string(),
+            ..Default::default()
+        });
+
+        let path = std::env::temp_dir().join(format!("quickner_{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        quickner.to_sqlite(path).unwrap();
+        let loaded = Quickner::from_sqlite(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // Documents and entities compare equal on their text and labels only
+        let documents = serde_json::to_value(&quickner.documents).unwrap();
+        assert_eq!(serde_json::to_value(&loaded.documents).unwrap(), documents);
+        let entities = serde_json::to_value(&quickner.entities).unwrap();
+        assert_eq!(serde_json::to_value(&loaded.entities).unwrap(), entities);
+    }
+
+    #[test]
+    fn sqlite_database_of_a_previous_schema_is_migrated() {
+        let path = std::env::temp_dir().join(format!("quickner_v1_{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        let connection = Connection::open(path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE documents (id TEXT PRIMARY KEY, text TEXT NOT NULL);
+                 CREATE TABLE entities (name TEXT NOT NULL, label TEXT NOT NULL,
+                 PRIMARY KEY (name, label));
+                 INSERT INTO documents VALUES ('1', 'Rust');
+                 INSERT INTO entities VALUES ('rust', 'PL');",
+            )
+            .unwrap();
+        drop(connection);
+        let mut quickner = Quickner::from_sqlite(path).unwrap();
+        assert_eq!(quickner.documents[0].text.as_ref(), "Rust");
+        assert_eq!(quickner.entities[0].name, "rust");
+        quickner.entities[0].id = Some("Q575650".to_string());
+        quickner.to_sqlite(path).unwrap();
+        let loaded = Quickner::from_sqlite(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        // The entity with an id is another entry than the one without
+        assert_eq!(loaded.entities.len(), 2);
+        assert_eq!(loaded.entities[1].id.as_deref(), Some("Q575650"));
+    }
+
+    #[test]
+    fn sqlite_database_missing_is_not_created() {
+        let path = std::env::temp_dir().join(format!("quickner_missing_{}.db", std::process::id()));
+        let error = Quickner::from_sqlite(path.to_str().unwrap()).err().unwrap();
+        assert!(error.to_string().contains("does not exist"));
+        assert!(!path.exists());
+    }
+}
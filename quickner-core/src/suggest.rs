@@ -0,0 +1,329 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use log::info;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    quickner::Quickner,
+    utils::{long_path, tokenize},
+};
+
+/// Word vectors read from a text file in the word2vec or GloVe format:
+/// one word per line followed by its components, separated by spaces.
+/// The optional word2vec header line ("<count> <dimension>") is skipped.
+#[derive(Clone, Debug, Default)]
+pub struct Vectors {
+    /// Dimension of the vectors
+    pub dimension: usize,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+/// Options of the label suggestions
+#[derive(Clone, Debug)]
+pub struct SuggestOptions {
+    /// Minimum number of occurrences of a term in the documents
+    pub min_frequency: usize,
+    /// Maximum number of terms compared to the gazetteer, the most frequent ones
+    pub max_terms: usize,
+    /// Number of nearest gazetteer entries voting for the label of a term
+    pub neighbors: usize,
+    /// Minimum cosine similarity between a term and the entries of its label
+    pub min_similarity: f32,
+}
+
+impl Default for SuggestOptions {
+    fn default() -> Self {
+        SuggestOptions {
+            min_frequency: 3,
+            max_terms: 1000,
+            neighbors: 5,
+            min_similarity: 0.5,
+        }
+    }
+}
+
+/// A label suggested for a frequent term missing from the gazetteer,
+/// to be approved before adding it to the entities
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct LabelSuggestion {
+    /// The term, lowercased
+    pub term: String,
+    /// The suggested label
+    pub label: String,
+    /// Mean cosine similarity between the term and the nearest entries of the label
+    pub score: f32,
+    /// Number of occurrences of the term outside the annotated spans
+    pub frequency: usize,
+    /// The nearest gazetteer entries with the label
+    pub neighbors: Vec<String>,
+}
+
+impl Vectors {
+    /// Read the vectors of a file, keeping only the words of the vocabulary
+    /// if one is given, vectors are normalized to unit length
+    /// # Errors
+    /// Returns an error if the file cannot be read or if the vectors
+    /// do not all have the same dimension
+    pub fn from_file(
+        path: &str,
+        vocabulary: Option<&HashSet<String>>,
+    ) -> Result<Vectors, std::io::Error> {
+        let reader = BufReader::new(File::open(long_path(path))?);
+        let mut vectors = Vectors::default();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let word = match fields.next() {
+                Some(word) => word,
+                None => continue,
+            };
+            let components: Vec<&str> = fields.collect();
+            // word2vec header: number of words and dimension
+            if number == 0 && components.len() == 1 {
+                continue;
+            }
+            if vocabulary.is_some_and(|vocabulary| !vocabulary.contains(word)) {
+                continue;
+            }
+            let vector = components
+                .iter()
+                .map(|component| component.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| Vectors::invalid(path, number, &e.to_string()))?;
+            if vectors.dimension == 0 {
+                vectors.dimension = vector.len();
+            } else if vector.len() != vectors.dimension {
+                return Err(Vectors::invalid(
+                    path,
+                    number,
+                    &format!(
+                        "expected {} components, found {}",
+                        vectors.dimension,
+                        vector.len()
+                    ),
+                ));
+            }
+            if let Some(vector) = Vectors::normalize(vector) {
+                vectors.vectors.insert(word.to_string(), vector);
+            }
+        }
+        Ok(vectors)
+    }
+
+    fn invalid(path: &str, number: usize, message: &str) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid vector at {}:{}: {}", path, number + 1, message),
+        )
+    }
+
+    /// Number of words with a vector
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Whether no word has a vector
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Vector of a word
+    pub fn get(&self, word: &str) -> Option<&[f32]> {
+        self.vectors.get(word).map(Vec::as_slice)
+    }
+
+    /// Vector of a phrase, the normalized mean of the vectors of its words.
+    /// Words without a vector are ignored, None if none has one
+    pub fn phrase(&self, phrase: &str) -> Option<Vec<f32>> {
+        let mut sum = vec![0.0; self.dimension];
+        let mut found = false;
+        for word in phrase.split_whitespace() {
+            if let Some(vector) = self.get(word) {
+                sum.iter_mut()
+                    .zip(vector)
+                    .for_each(|(sum, component)| *sum += component);
+                found = true;
+            }
+        }
+        match found {
+            true => Vectors::normalize(sum),
+            false => None,
+        }
+    }
+
+    fn normalize(vector: Vec<f32>) -> Option<Vec<f32>> {
+        let norm = vector
+            .iter()
+            .map(|component| component * component)
+            .sum::<f32>();
+        if norm == 0.0 {
+            return None;
+        }
+        let norm = norm.sqrt();
+        Some(
+            vector
+                .into_iter()
+                .map(|component| component / norm)
+                .collect(),
+        )
+    }
+}
+
+/// Cosine similarity of two unit vectors
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+impl Quickner {
+    /// Suggest labels for the frequent terms of the documents that are not
+    /// in the gazetteer and not annotated: each term gets the label of its
+    /// nearest gazetteer entries in the vector space.
+    /// The suggestions are sorted by score, best first.
+    /// # Example
+    /// ```no_run
+    /// use quickner::{Quickner, SuggestOptions};
+    /// let mut quickner = Quickner::new(Some("./config.toml"));
+    /// quickner.process(false).unwrap();
+    /// let suggestions = quickner
+    ///     .suggest_labels("./glove.6B.100d.txt", &SuggestOptions::default())
+    ///     .unwrap();
+    /// for suggestion in suggestions {
+    ///     println!("{} -> {} ({:.2})", suggestion.term, suggestion.label, suggestion.score);
+    /// }
+    /// ```
+    /// # Errors
+    /// Returns an error if the vectors file cannot be read
+    pub fn suggest_labels(
+        &self,
+        vectors: &str,
+        options: &SuggestOptions,
+    ) -> Result<Vec<LabelSuggestion>, std::io::Error> {
+        let terms = self.unmatched_terms(options);
+        let entities: Vec<(String, &str)> = self
+            .entities
+            .iter()
+            .map(|entity| (entity.name.to_lowercase(), entity.label.as_str()))
+            .collect();
+        // Only the vectors of the terms and of the entity words are kept in memory
+        let mut vocabulary: HashSet<String> =
+            terms.iter().map(|(term, _)| term.to_string()).collect();
+        vocabulary.extend(
+            entities
+                .iter()
+                .flat_map(|(name, _)| name.split_whitespace().map(str::to_string)),
+        );
+        let vectors = Vectors::from_file(vectors, Some(&vocabulary))?;
+        info!(
+            "{} vectors read for {} terms and {} entities",
+            vectors.len(),
+            terms.len(),
+            entities.len()
+        );
+        let entries: Vec<(&str, &str, Vec<f32>)> = entities
+            .iter()
+            .filter_map(|(name, label)| {
+                vectors
+                    .phrase(name)
+                    .map(|vector| (name.as_str(), *label, vector))
+            })
+            .collect();
+        let mut suggestions: Vec<LabelSuggestion> = self.install(|| {
+            terms
+                .par_iter()
+                .filter_map(|(term, frequency)| {
+                    let vector = vectors.get(term)?;
+                    let mut nearest: Vec<(f32, &str, &str)> = entries
+                        .iter()
+                        .map(|(name, label, entry)| (similarity(vector, entry), *name, *label))
+                        .collect();
+                    nearest.sort_by(|a, b| b.0.total_cmp(&a.0));
+                    nearest.truncate(options.neighbors.max(1));
+                    // Each neighbor votes for its label with its similarity
+                    let mut votes: HashMap<&str, f32> = HashMap::new();
+                    for (score, _, label) in &nearest {
+                        *votes.entry(label).or_default() += score;
+                    }
+                    let label = votes
+                        .into_iter()
+                        .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))?
+                        .0;
+                    let neighbors: Vec<(f32, &str, &str)> = nearest
+                        .into_iter()
+                        .filter(|(_, _, neighbor)| *neighbor == label)
+                        .collect();
+                    let score = neighbors.iter().map(|(score, _, _)| score).sum::<f32>()
+                        / neighbors.len() as f32;
+                    if score < options.min_similarity {
+                        return None;
+                    }
+                    Some(LabelSuggestion {
+                        term: term.to_string(),
+                        label: label.to_string(),
+                        score,
+                        frequency: *frequency,
+                        neighbors: neighbors
+                            .into_iter()
+                            .map(|(_, name, _)| name.to_string())
+                            .collect(),
+                    })
+                })
+                .collect()
+        });
+        suggestions.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.frequency.cmp(&a.frequency))
+                .then_with(|| a.term.cmp(&b.term))
+        });
+        Ok(suggestions)
+    }
+
+    /// Frequent words of the documents outside the annotated spans and missing
+    /// from the gazetteer, lowercased, with their number of occurrences.
+    /// Words shorter than 3 characters and numbers are ignored.
+    fn unmatched_terms(&self, options: &SuggestOptions) -> Vec<(String, usize)> {
+        let known: HashSet<String> = self
+            .entities
+            .iter()
+            .map(|entity| entity.name.to_lowercase())
+            .collect();
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for document in &self.documents {
+            let chars: Vec<char> = document.text.chars().collect();
+            for (start, end) in tokenize(&document.text) {
+                let covered = document
+                    .label
+                    .iter()
+                    .any(|(span_start, span_end, _)| start < *span_end && *span_start < end);
+                let word: String = chars[start..end].iter().collect::<String>().to_lowercase();
+                if !covered
+                    && word.chars().count() >= 3
+                    && !word.chars().all(char::is_numeric)
+                    && !known.contains(&word)
+                {
+                    *frequencies.entry(word).or_default() += 1;
+                }
+            }
+        }
+        let mut terms: Vec<(String, usize)> = frequencies
+            .into_iter()
+            .filter(|(_, frequency)| *frequency >= options.min_frequency)
+            .collect();
+        terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        terms.truncate(options.max_terms);
+        terms
+    }
+}
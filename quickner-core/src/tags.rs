@@ -0,0 +1,107 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::collections::BTreeSet;
+
+use log::info;
+use rayon::prelude::*;
+
+use crate::{
+    config::{Config, VerifiedPolicy},
+    entity::Entity,
+    matcher::Matcher,
+    performance::install,
+    quickner::Quickner,
+};
+
+impl Quickner {
+    /// Tag the documents with the label of the keywords found at least
+    /// `min_matches` times in their text, e.g. their topics, alongside the spans.
+    /// The keywords are matched like the entities, with the `[matching]` options
+    /// but without fuzzy matching, excludes or context rules, and the tags are
+    /// sorted. The tags of a verified document, corrected or gold, are handled
+    /// as its spans are, see `verified` under `[matching]`: kept by default,
+    /// completed with `append`, replaced with `overwrite`.
+    /// Returns the number of documents tagged, with at least one tag found.
+    /// # Example
+    /// ```
+    /// use quickner::{Document, Entity, Quickner};
+    /// let mut quickner = Quickner::default();
+    /// quickner.add_document(Document::from_string("The match ended with a late goal".to_string()));
+    /// quickner.add_document(Document::from_string("Rates are up again".to_string()));
+    /// let keywords = vec![
+    ///     Entity { name: "goal".to_string(), label: "sports".to_string(), ..Default::default() },
+    ///     Entity { name: "rates".to_string(), label: "finance".to_string(), ..Default::default() },
+    /// ];
+    /// assert_eq!(quickner.tag_documents(&keywords, 1), 2);
+    /// assert_eq!(quickner.documents[0].tags, vec!["sports".to_string()]);
+    /// ```
+    pub fn tag_documents(&mut self, keywords: &[Entity], min_matches: usize) -> usize {
+        let snapshot = self.history_snapshot();
+        let matcher = Matcher::with_matching(
+            keywords,
+            self.config.entities.filters.case_sensitive,
+            self.config.matching.clone(),
+        );
+        let min_matches = min_matches.max(1);
+        let policy = self.config.matching.verified;
+        let documents = &mut self.documents;
+        let tagged = install(self.thread_pool.as_deref(), || {
+            documents
+                .par_iter_mut()
+                .map(|document| {
+                    let found: BTreeSet<String> = matcher
+                        .count_labels(&document.text)
+                        .into_iter()
+                        .filter(|(_, count)| *count >= min_matches)
+                        .map(|(tag, _)| tag)
+                        .collect();
+                    let tagged = usize::from(!found.is_empty());
+                    match policy {
+                        _ if !document.status.is_verified() => {
+                            document.tags = found.into_iter().collect()
+                        }
+                        VerifiedPolicy::Skip => return 0,
+                        VerifiedPolicy::Append => {
+                            let mut tags: BTreeSet<String> =
+                                std::mem::take(&mut document.tags).into_iter().collect();
+                            tags.extend(found);
+                            document.tags = tags.into_iter().collect();
+                        }
+                        VerifiedPolicy::Overwrite => document.tags = found.into_iter().collect(),
+                    }
+                    tagged
+                })
+                .sum()
+        });
+        self.documents_hash = Quickner::document_hash(&self.documents);
+        self.record_history("tag_documents", snapshot);
+        tagged
+    }
+
+    /// Tag the documents with the keywords of the `[tags]` of the configuration,
+    /// if set, read with its filters (or the default entities filters)
+    pub(crate) fn tag_from_config(&mut self, config: &Config) {
+        let Some(tags) = &config.tags else {
+            return;
+        };
+        let mut filters = tags
+            .filters
+            .clone()
+            .unwrap_or_else(|| config.entities.filters.clone());
+        filters.set_special_characters();
+        let keywords = self.entities(
+            tags.input.path.as_str(),
+            tags.input.format.clone(),
+            filters,
+            tags.input.filter.unwrap_or(false),
+        );
+        let tagged = self.tag_documents(&keywords, tags.min_matches);
+        info!("{} documents tagged from {}", tagged, tags.input.path);
+    }
+}
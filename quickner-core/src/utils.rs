@@ -5,9 +5,23 @@
 //
 // Licensed under Mozilla Public License 2.0
 //
-use std::{collections::HashSet, str};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    str::{self, FromStr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        PoisonError, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{LabelDisplay, Language};
 
 /// Checks if a string is alphanumeric.
 /// # Examples
@@ -92,13 +106,409 @@ pub(crate) fn get_progress_bar(total: u64) -> ProgressBar {
     progress_bar
 }
 
+/// Split a text into paragraphs, paragraphs are separated by blank lines.
+/// Empty paragraphs are skipped.
+/// # Examples
+/// ```ignore
+/// use utils::split_paragraphs;
+/// let paragraphs = split_paragraphs("Hello\n\nWorld");
+/// assert_eq!(paragraphs, vec!["Hello", "World"]);
+/// ```
+pub(crate) fn split_paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !paragraph.is_empty() {
+                paragraphs.push(paragraph.join("\n").trim().to_string());
+                paragraph.clear();
+            }
+        } else {
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        paragraphs.push(paragraph.join("\n").trim().to_string());
+    }
+    paragraphs
+}
+
+/// Abbreviations whose final period does not end a sentence
+const ABBREVIATIONS: [&str; 18] = [
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "e.g", "i.e", "inc", "ltd",
+    "co", "corp", "no", "fig",
+];
+
+/// Split a text into sentences. A sentence ends with ".", "!", "?" or "…"
+/// (and the closing quotes or brackets following it) when the next word starts
+/// with an uppercase letter, a digit or an opening quote, or at a blank line.
+/// Common abbreviations and initials (e.g. "Dr." or "J.") do not end a sentence.
+/// # Examples
+/// ```ignore
+/// use utils::split_sentences;
+/// let sentences = split_sentences("Dr. Smith works at Apple. He likes it!");
+/// assert_eq!(sentences, vec!["Dr. Smith works at Apple.", "He likes it!"]);
+/// ```
+pub(crate) fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for paragraph in split_paragraphs(text) {
+        let chars: Vec<char> = paragraph.chars().collect();
+        let mut start = 0;
+        let mut index = 0;
+        while index < chars.len() {
+            if !matches!(chars[index], '.' | '!' | '?' | '…') {
+                index += 1;
+                continue;
+            }
+            let mut end = index + 1;
+            while end < chars.len() && matches!(chars[end], '.' | '!' | '?' | '…') {
+                end += 1;
+            }
+            while end < chars.len() && matches!(chars[end], '"' | '\'' | ')' | ']' | '”' | '’')
+            {
+                end += 1;
+            }
+            let mut next = end;
+            while next < chars.len() && chars[next].is_whitespace() {
+                next += 1;
+            }
+            let boundary = next == chars.len()
+                || (next > end
+                    && (chars[next].is_uppercase()
+                        || chars[next].is_numeric()
+                        || matches!(chars[next], '"' | '\'' | '(' | '“' | '‘')));
+            // The word before a period, to skip the abbreviations and the initials
+            let word: String = chars[start..index]
+                .iter()
+                .rev()
+                .take_while(|c| !c.is_whitespace())
+                .collect::<Vec<&char>>()
+                .into_iter()
+                .rev()
+                .collect();
+            let abbreviation = chars[index] == '.'
+                && (ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+                    || (word.chars().count() == 1 && word.chars().all(char::is_uppercase)));
+            if boundary && !abbreviation {
+                let sentence: String = chars[start..end].iter().collect();
+                if !sentence.trim().is_empty() {
+                    sentences.push(sentence.trim().to_string());
+                }
+                start = next;
+            }
+            index = end;
+        }
+        let sentence: String = chars[start.min(chars.len())..].iter().collect();
+        if !sentence.trim().is_empty() {
+            sentences.push(sentence.trim().to_string());
+        }
+    }
+    sentences
+}
+
+/// Colors of the terminal, written with ANSI escape codes. In the configuration,
+/// a color is the name of one of the six colors or an RGB color, e.g. "#4e79a7"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TermColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    /// An RGB color, written with a 24-bit escape code in the terminal
+    Rgb(u8, u8, u8),
+}
+
+impl TermColor {
+    /// The colors given to the labels, in order
+    pub const ALL: [TermColor; 6] = [
+        TermColor::Red,
+        TermColor::Green,
+        TermColor::Yellow,
+        TermColor::Blue,
+        TermColor::Magenta,
+        TermColor::Cyan,
+    ];
+
+    /// Light shade of the color used as the background of the HTML spans,
+    /// an RGB color is used as is
+    pub fn html(&self) -> Cow<'static, str> {
+        match self {
+            TermColor::Red => Cow::Borrowed("#ffb3b3"),
+            TermColor::Green => Cow::Borrowed("#b3f0b3"),
+            TermColor::Yellow => Cow::Borrowed("#fff0a0"),
+            TermColor::Blue => Cow::Borrowed("#b3d4ff"),
+            TermColor::Magenta => Cow::Borrowed("#f0b3f0"),
+            TermColor::Cyan => Cow::Borrowed("#b3f0f0"),
+            TermColor::Rgb(red, green, blue) => {
+                Cow::Owned(format!("#{red:02x}{green:02x}{blue:02x}"))
+            }
+        }
+    }
+}
+
+impl Display for TermColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TermColor::Red => write!(f, "red"),
+            TermColor::Green => write!(f, "green"),
+            TermColor::Yellow => write!(f, "yellow"),
+            TermColor::Blue => write!(f, "blue"),
+            TermColor::Magenta => write!(f, "magenta"),
+            TermColor::Cyan => write!(f, "cyan"),
+            TermColor::Rgb(..) => write!(f, "{}", self.html()),
+        }
+    }
+}
+
+impl FromStr for TermColor {
+    type Err = String;
+
+    /// # Examples
+    /// ```
+    /// use quickner::TermColor;
+    /// assert_eq!("Blue".parse(), Ok(TermColor::Blue));
+    /// assert_eq!("#4e79a7".parse(), Ok(TermColor::Rgb(0x4e, 0x79, 0xa7)));
+    /// assert!("#4e79".parse::<TermColor>().is_err());
+    /// ```
+    fn from_str(color: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!("Unknown color: {color}, expected red, green, yellow, blue, magenta, cyan or #rrggbb")
+        };
+        match color.to_lowercase().as_str() {
+            "red" => Ok(TermColor::Red),
+            "green" => Ok(TermColor::Green),
+            "yellow" => Ok(TermColor::Yellow),
+            "blue" => Ok(TermColor::Blue),
+            "magenta" => Ok(TermColor::Magenta),
+            "cyan" => Ok(TermColor::Cyan),
+            hex => {
+                let hex = hex
+                    .strip_prefix('#')
+                    .filter(|hex| hex.len() == 6 && hex.is_ascii());
+                let channel = |index: usize| {
+                    hex.and_then(|hex| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+                };
+                match (channel(0), channel(2), channel(4)) {
+                    (Some(red), Some(green), Some(blue)) => Ok(TermColor::Rgb(red, green, blue)),
+                    _ => Err(error()),
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for TermColor {
+    type Error = String;
+
+    fn try_from(color: String) -> Result<Self, Self::Error> {
+        TermColor::from_str(&color)
+    }
+}
+
+impl From<TermColor> for String {
+    fn from(color: TermColor) -> Self {
+        color.to_string()
+    }
+}
+
+/// Escape the characters of a text that have a meaning in HTML
+/// # Examples
+/// ```
+/// use quickner::escape_html;
+/// assert_eq!(escape_html("AT&T <b>"), "AT&amp;T &lt;b&gt;");
+/// ```
+pub fn escape_html(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Escape a text for a Turtle string literal
+pub(crate) fn turtle_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Percent-encode the characters not allowed in an IRI, e.g. the spaces of a label
+pub(crate) fn iri_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            c if c.is_control() || c.is_whitespace() || "<>\"{}|^`\\".contains(c) => {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    escaped.push_str(&format!("%{byte:02X}"));
+                }
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Convert String to colored String with ANSI escape codes
+/// # Examples
+/// ```
+/// use quickner::{colorize, TermColor};
+/// assert_eq!(colorize("Rust", TermColor::Red), "\x1b[31mRust\x1b[0m");
+/// ```
+pub fn colorize(text: &str, color: TermColor) -> String {
+    let color_code = match color {
+        TermColor::Red => "31".to_string(),
+        TermColor::Green => "32".to_string(),
+        TermColor::Yellow => "33".to_string(),
+        TermColor::Blue => "34".to_string(),
+        TermColor::Magenta => "35".to_string(),
+        TermColor::Cyan => "36".to_string(),
+        TermColor::Rgb(red, green, blue) => format!("38;2;{red};{green};{blue}"),
+    };
+    format!("\x1b[{color_code}m{text}\x1b[0m")
+}
+
+/// Number of characters of the texts shown in the logs and representations
+static PREVIEW_LENGTH: AtomicUsize = AtomicUsize::new(100);
+
+/// Set the number of characters of the texts shown in the logs,
+/// the representations and the pretty prints, 0 shows the full texts
+pub fn set_preview_length(length: usize) {
+    PREVIEW_LENGTH.store(length, Ordering::Relaxed);
+}
+
+/// Number of characters of the texts shown in the logs and representations
+pub fn preview_length() -> usize {
+    PREVIEW_LENGTH.load(Ordering::Relaxed)
+}
+
+/// Colors and names of the labels in the pretty prints and the HTML pages
+static LABEL_DISPLAY: RwLock<BTreeMap<String, LabelDisplay>> = RwLock::new(BTreeMap::new());
+
+/// Set the colors and names of the labels in the pretty prints and the HTML
+/// pages, `[labels.display]` of the configuration of the last run if it has one.
+/// The labels missing from it are given the colors in turn and keep their name.
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use quickner::{set_label_display, Document, LabelDisplay, TermColor};
+/// set_label_display(BTreeMap::from([(
+///     "ORG".to_string(),
+///     LabelDisplay { color: Some(TermColor::Blue), name: Some("Organization".to_string()) },
+/// )]));
+/// let document = Document::new("Mozilla".to_string(), vec![(0, 7, "ORG".to_string())]);
+/// assert_eq!(document.render_ansi(None), "\x1b[34mMozilla\x1b[0m[Organization]");
+/// set_label_display(BTreeMap::new());
+/// ```
+pub fn set_label_display(display: BTreeMap<String, LabelDisplay>) {
+    *LABEL_DISPLAY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = display;
+}
+
+/// Colors and names of the labels set by `set_label_display`
+pub fn label_display() -> BTreeMap<String, LabelDisplay> {
+    LABEL_DISPLAY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Preview of a text for the logs and representations: texts longer than the
+/// preview length are cut, at a word boundary if there is one close to the limit,
+/// followed by an ellipsis and the number of characters left out.
+/// # Examples
+/// ```
+/// use quickner::{preview, set_preview_length};
+/// set_preview_length(10);
+/// assert_eq!(preview("Rust is fast"), "Rust is… (+5 chars)");
+/// assert_eq!(preview("Rust"), "Rust");
+/// set_preview_length(100);
+/// ```
+pub fn preview(text: &str) -> Cow<'_, str> {
+    match preview_end(text, preview_length()) {
+        Some(end) => Cow::Owned(format!(
+            "{}… (+{} chars)",
+            text[..end].trim_end(),
+            text[end..].chars().count()
+        )),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Byte index where the preview of a text ends, None if the text is not cut
+pub fn preview_end(text: &str, length: usize) -> Option<usize> {
+    if length == 0 {
+        return None;
+    }
+    let end = text.char_indices().nth(length).map(|(index, _)| index)?;
+    // Prefer the last space of the last quarter of the preview
+    let boundary = text[..end]
+        .char_indices()
+        .rev()
+        .take(length.div_ceil(4))
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(index, _)| index);
+    Some(boundary.filter(|index| *index > 0).unwrap_or(end))
+}
+
+/// Id of a text, a 64-bit FNV-1a hash of its bytes in hexadecimal.
+/// Unlike the standard library hasher, the algorithm is fixed,
+/// so ids are the same across runs, platforms and Rust versions.
+/// # Examples
+/// ```
+/// use quickner::hash_string;
+/// assert_eq!(hash_string("Rust"), hash_string("Rust"));
+/// assert_ne!(hash_string("Rust"), hash_string("rust"));
+/// ```
 pub fn hash_string(text: &str) -> String {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+    format!("{:x}", fnv1a(text))
+}
+
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    text.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Position of a document in a random order given by the seed, between 0 and 1.
+/// It only depends on the seed and the id, a sample keeps the same documents
+/// across runs whatever the order or the size of the corpus.
+pub(crate) fn sample_score(seed: u64, id: &str) -> f64 {
+    let hash = splitmix64(fnv1a(&format!("{seed}:{id}")));
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
 
-    let mut hasher = DefaultHasher::new();
-    text.hash(&mut hasher);
-    format!("{:x}", hasher.finish())
+/// The splitmix64 finalizer, spreads a hash over all the bits
+pub(crate) fn splitmix64(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
 }
 
 pub(crate) fn is_valid_utf8(text: &str) -> bool {
@@ -110,7 +520,17 @@ pub(crate) fn is_valid_utf8(text: &str) -> bool {
     }
 }
 
-pub(crate) fn char_to_byte(text: String, start: usize, end: usize) -> (usize, usize) {
+/// Convert the character offsets of a span, the offsets of the spans of a
+/// [`Document`](crate::Document), to byte offsets in the text, to slice it in Rust.
+/// An offset after the end of the text is the end of the text.
+/// # Examples
+/// ```
+/// use quickner::char_to_byte;
+/// let text = "Café in Paris";
+/// assert_eq!(char_to_byte(text, 8, 13), (9, 14));
+/// assert_eq!(&text[9..14], "Paris");
+/// ```
+pub fn char_to_byte(text: &str, start: usize, end: usize) -> (usize, usize) {
     let start = text.char_indices().nth(start);
     let end = text.char_indices().nth(end);
     let start = match start {
@@ -123,3 +543,278 @@ pub(crate) fn char_to_byte(text: String, start: usize, end: usize) -> (usize, us
     };
     (start, end)
 }
+
+/// Convert the byte offsets of a span, e.g. from a tool working on UTF-8 bytes,
+/// to character offsets. An offset inside a character is moved to the start of
+/// the next one, an offset after the end of the text is the end of the text.
+/// # Examples
+/// ```
+/// use quickner::byte_to_char;
+/// assert_eq!(byte_to_char("Café in Paris", 9, 14), (8, 13));
+/// ```
+pub fn byte_to_char(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let offset = |byte: usize| {
+        text.char_indices()
+            .take_while(|(index, _)| *index < byte)
+            .count()
+    };
+    (offset(start), offset(end))
+}
+
+/// Split a text into tokens, the runs of alphanumeric characters,
+/// returned as their character offsets. Punctuation and spaces separate
+/// the tokens and are not part of any token.
+/// # Examples
+/// ```ignore
+/// use utils::tokenize;
+/// assert_eq!(tokenize("New-York City"), vec![(0, 3), (4, 8), (9, 13)]);
+/// ```
+pub(crate) fn tokenize(text: &str) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut count = 0;
+    for (index, c) in text.chars().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(begin)) => {
+                tokens.push((begin, index));
+                start = None;
+            }
+            _ => {}
+        }
+        count = index + 1;
+    }
+    if let Some(begin) = start {
+        tokens.push((begin, count));
+    }
+    tokens
+}
+
+/// Edit distance between two texts, the number of insertions, deletions and
+/// substitutions of characters turning one into the other, `None` above `max`
+/// # Examples
+/// ```ignore
+/// use utils::levenshtein;
+/// let (a, b): (Vec<char>, Vec<char>) = ("mozila".chars().collect(), "mozilla".chars().collect());
+/// assert_eq!(levenshtein(&a, &b, 1), Some(1));
+/// ```
+pub(crate) fn levenshtein(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        let mut best = current[0];
+        for (j, cb) in b.iter().enumerate() {
+            current[j + 1] = (previous[j] + usize::from(ca != cb))
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+            best = best.min(current[j + 1]);
+        }
+        // The distance cannot go down from one row to the next
+        if best > max {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    Some(previous[b.len()]).filter(|distance| *distance <= max)
+}
+
+/// Letters with diacritics of Latin-1 and Latin Extended-A, and their base letter
+const DIACRITICS: &[(&str, char)] = &[
+    ("àáâãäåāăą", 'a'),
+    ("ÀÁÂÃÄÅĀĂĄ", 'A'),
+    ("çćĉċč", 'c'),
+    ("ÇĆĈĊČ", 'C'),
+    ("ďđ", 'd'),
+    ("ĎĐ", 'D'),
+    ("èéêëēĕėęě", 'e'),
+    ("ÈÉÊËĒĔĖĘĚ", 'E'),
+    ("ĝğġģ", 'g'),
+    ("ĜĞĠĢ", 'G'),
+    ("ĥħ", 'h'),
+    ("ĤĦ", 'H'),
+    ("ìíîïĩīĭįı", 'i'),
+    ("ÌÍÎÏĨĪĬĮİ", 'I'),
+    ("ĵ", 'j'),
+    ("Ĵ", 'J'),
+    ("ķ", 'k'),
+    ("Ķ", 'K'),
+    ("ĺļľŀł", 'l'),
+    ("ĹĻĽĿŁ", 'L'),
+    ("ñńņň", 'n'),
+    ("ÑŃŅŇ", 'N'),
+    ("òóôõöøōŏő", 'o'),
+    ("ÒÓÔÕÖØŌŎŐ", 'O'),
+    ("ŕŗř", 'r'),
+    ("ŔŖŘ", 'R'),
+    ("śŝşš", 's'),
+    ("ŚŜŞŠ", 'S'),
+    ("ţťŧ", 't'),
+    ("ŢŤŦ", 'T'),
+    ("ùúûüũūŭůűų", 'u'),
+    ("ÙÚÛÜŨŪŬŮŰŲ", 'U'),
+    ("ŵ", 'w'),
+    ("Ŵ", 'W'),
+    ("ýÿŷ", 'y'),
+    ("ÝŸŶ", 'Y'),
+    ("źżž", 'z'),
+    ("ŹŻŽ", 'Z'),
+];
+
+/// Replace the letters with diacritics by their base letter, e.g. "Montréal"
+/// becomes "Montreal". Each character is replaced by exactly one character,
+/// so the character offsets in the folded text are the ones of the original text.
+/// # Examples
+/// ```ignore
+/// use utils::fold_diacritics;
+/// assert_eq!(fold_diacritics("Montréal, Québec"), "Montreal, Quebec");
+/// ```
+pub(crate) fn fold_diacritics(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.chars()
+            .map(|c| match c.is_ascii() {
+                true => c,
+                false => DIACRITICS
+                    .iter()
+                    .find(|(letters, _)| letters.contains(c))
+                    .map(|(_, base)| *base)
+                    .unwrap_or(c),
+            })
+            .collect(),
+    )
+}
+
+/// Plural forms of a name, built from its last word with the regular
+/// English suffixes: "apple" gives "apples", "company" gives "companies",
+/// "box" gives "boxes". The French ones are added in French, "bureau" gives
+/// "bureaux", and the Spanish ones in Spanish, "ciudad" gives "ciudades".
+/// Names not ending with a letter have no plural.
+/// # Examples
+/// ```ignore
+/// use utils::plural_forms;
+/// assert_eq!(plural_forms("city", Language::English), vec!["citys", "cities"]);
+/// assert_eq!(plural_forms("Jon", Language::English), vec!["Jons"]);
+/// assert_eq!(plural_forms("plan", Language::Spanish), vec!["plans", "planes"]);
+/// ```
+pub(crate) fn plural_forms(name: &str, language: Language) -> Vec<String> {
+    if !name.chars().last().is_some_and(char::is_alphabetic) {
+        return Vec::new();
+    }
+    let lower = name.to_lowercase();
+    let mut forms = vec![format!("{name}s")];
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        forms.push(format!("{name}es"));
+    }
+    let mut chars = lower.chars().rev();
+    chars.next();
+    let before = chars.next();
+    if lower.ends_with('y') && before.is_some_and(|c| !"aeiou".contains(c)) {
+        forms.push(format!("{}ies", &name[..name.len() - 1]));
+    }
+    match language {
+        Language::English => {}
+        Language::French => {
+            if lower.ends_with("au") || lower.ends_with("eu") {
+                forms.push(format!("{name}x"));
+            }
+            if lower.ends_with("al") {
+                forms.push(format!("{}aux", &name[..name.len() - 2]));
+            }
+        }
+        Language::Spanish => {
+            if lower.ends_with(['d', 'l', 'n', 'r', 'j']) {
+                forms.push(format!("{name}es"));
+            }
+        }
+    }
+    forms
+}
+
+/// Make a path usable by the file system functions on every platform.
+/// On Windows, absolute paths longer than MAX_PATH are prefixed with `\\?\`
+/// (`\\?\UNC\` for network shares) so they are not truncated,
+/// other paths are returned unchanged.
+/// # Examples
+/// ```ignore
+/// use utils::long_path;
+/// let path = long_path("./annotations.jsonl");
+/// assert_eq!(path, std::path::PathBuf::from("./annotations.jsonl"));
+/// ```
+pub(crate) fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    #[cfg(windows)]
+    {
+        const MAX_PATH: usize = 260;
+        let raw = path.as_os_str().to_string_lossy().replace('/', "\\");
+        if path.is_absolute() && raw.len() >= MAX_PATH && !raw.starts_with(r"\\?\") {
+            return match raw.strip_prefix(r"\\") {
+                Some(share) => PathBuf::from(format!(r"\\?\UNC\{share}")),
+                None => PathBuf::from(format!(r"\\?\{raw}")),
+            };
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Format a system time as a UTC date, e.g. "2023-04-01 12:30 UTC"
+pub(crate) fn format_system_time(time: SystemTime) -> String {
+    let seconds = unix_seconds(time);
+    let (year, month, day) = civil_date(seconds);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Format a time as RFC 3339 in UTC, e.g. "2023-04-01T12:30:05Z"
+pub(crate) fn format_rfc3339(time: SystemTime) -> String {
+    let seconds = unix_seconds(time);
+    let (year, month, day) = civil_date(seconds);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Convert the seconds since the epoch to a civil date (proleptic Gregorian calendar)
+fn civil_date(seconds: u64) -> (i64, i64, i64) {
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,508 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    config::Fields,
+    document::Document,
+    models::Stamp,
+    quickner::Quickner,
+    utils::{char_to_byte, fold_diacritics, levenshtein, long_path, plural_forms, preview},
+};
+
+/// Options of the validation of a span dataset
+#[derive(Clone, Debug, Default)]
+pub struct SpanValidation {
+    /// Names of the id, text and spans fields of the records
+    pub fields: Fields,
+    /// Labels allowed in the spans, any label is accepted if empty
+    pub labels: HashSet<String>,
+    /// Whether overlapping spans are accepted
+    pub allow_overlaps: bool,
+}
+
+/// Report of the validation of a span dataset, serialized as JSON
+/// for the machines reading it (e.g. a CI job)
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    /// Path to the validated file
+    pub path: String,
+    /// Whether no error was found
+    pub valid: bool,
+    /// Number of records read
+    pub documents: usize,
+    /// Number of spans read
+    pub spans: usize,
+    /// The errors found, in the order of the file
+    pub errors: Vec<SpanError>,
+}
+
+/// An error found in a record of a span dataset
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SpanError {
+    /// Line of the record in the file, or position of the document
+    /// in the corpus, starting at 1 (0 for a single document)
+    pub line: usize,
+    /// Id of the record, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The span in error, None for the errors of the record
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<(usize, usize, String)>,
+    pub kind: SpanErrorKind,
+    pub message: String,
+}
+
+/// Kind of a span error
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanErrorKind {
+    /// The line is not a JSON object
+    InvalidJson,
+    /// The record has no text field, or the text is not a string
+    MissingText,
+    /// The spans field is not a list of [start, end, label]
+    /// or {"start": ..., "end": ..., "label": ...} spans
+    InvalidSpan,
+    /// The end of the span is not after its start
+    EmptySpan,
+    /// The span ends after the end of the text
+    OutOfBounds,
+    /// The span overlaps another span of the record
+    Overlap,
+    /// The same span is given twice
+    Duplicate,
+    /// The label of the span is not in the allowed labels
+    UnknownLabel,
+    /// The span starts or ends inside a word
+    PartialWord,
+    /// The text of the span is not an entity of the gazetteer with its label
+    SurfaceMismatch,
+}
+
+impl fmt::Display for SpanErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            SpanErrorKind::InvalidJson => "invalid_json",
+            SpanErrorKind::MissingText => "missing_text",
+            SpanErrorKind::InvalidSpan => "invalid_span",
+            SpanErrorKind::EmptySpan => "empty_span",
+            SpanErrorKind::OutOfBounds => "out_of_bounds",
+            SpanErrorKind::Overlap => "overlap",
+            SpanErrorKind::Duplicate => "duplicate",
+            SpanErrorKind::UnknownLabel => "unknown_label",
+            SpanErrorKind::PartialWord => "partial_word",
+            SpanErrorKind::SurfaceMismatch => "surface_mismatch",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+impl SpanValidation {
+    /// Validate the spans of a JSONL file, one record per line with a text and
+    /// its spans, as written by quickner or by other tools. Offsets are in
+    /// characters, the end is exclusive. Files ending with .gz are decompressed.
+    /// Nothing is imported, the records are checked one by one.
+    /// # Example
+    /// ```no_run
+    /// use quickner::SpanValidation;
+    /// let report = SpanValidation::default()
+    ///     .validate_jsonl("./annotations.jsonl")
+    ///     .unwrap();
+    /// println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    /// ```
+    /// # Errors
+    /// Returns an error if the file cannot be read
+    pub fn validate_jsonl(&self, path: &str) -> Result<ValidationReport, std::io::Error> {
+        let file = File::open(long_path(path))?;
+        let reader: Box<dyn BufRead> = match path.ends_with(".gz") {
+            true => Box::new(BufReader::new(GzDecoder::new(file))),
+            false => Box::new(BufReader::new(file)),
+        };
+        let mut report = ValidationReport {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value = match serde_json::from_str::<Value>(&line) {
+                Ok(value) if value.is_object() => value,
+                Ok(_) => {
+                    report.errors.push(SpanError::record(
+                        number,
+                        None,
+                        SpanErrorKind::InvalidJson,
+                        "the record is not a JSON object".to_string(),
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    report.errors.push(SpanError::record(
+                        number,
+                        None,
+                        SpanErrorKind::InvalidJson,
+                        e.to_string(),
+                    ));
+                    continue;
+                }
+            };
+            if Stamp::is_record(&value) {
+                continue;
+            }
+            report.documents += 1;
+            self.validate_record(number, &value, &mut report);
+        }
+        report.valid = report.errors.is_empty();
+        Ok(report)
+    }
+
+    fn validate_record(&self, number: usize, value: &Value, report: &mut ValidationReport) {
+        let id = value.get(&self.fields.id).map(|id| match id {
+            Value::String(id) => id.to_string(),
+            id => id.to_string(),
+        });
+        let text = match value.get(&self.fields.text).and_then(Value::as_str) {
+            Some(text) => text,
+            None => {
+                report.errors.push(SpanError::record(
+                    number,
+                    id,
+                    SpanErrorKind::MissingText,
+                    format!("no \"{}\" string field", self.fields.text),
+                ));
+                return;
+            }
+        };
+        let length = text.chars().count();
+        let values = match value.get(&self.fields.label) {
+            Some(Value::Array(values)) => values.as_slice(),
+            None | Some(Value::Null) => &[],
+            Some(_) => {
+                report.errors.push(SpanError::record(
+                    number,
+                    id,
+                    SpanErrorKind::InvalidSpan,
+                    format!("the \"{}\" field is not a list of spans", self.fields.label),
+                ));
+                return;
+            }
+        };
+        let mut spans = Vec::new();
+        for value in values {
+            report.spans += 1;
+            let span = match SpanValidation::span(value) {
+                Some(span) => span,
+                None => {
+                    report.errors.push(SpanError::record(
+                        number,
+                        id.clone(),
+                        SpanErrorKind::InvalidSpan,
+                        format!("invalid span {value}"),
+                    ));
+                    continue;
+                }
+            };
+            let error = |kind, message| SpanError {
+                line: number + 1,
+                id: id.clone(),
+                span: Some(span.clone()),
+                kind,
+                message,
+            };
+            if span.1 <= span.0 {
+                report.errors.push(error(
+                    SpanErrorKind::EmptySpan,
+                    format!("the span ends at {}, before its start", span.1),
+                ));
+            } else if span.1 > length {
+                report.errors.push(error(
+                    SpanErrorKind::OutOfBounds,
+                    format!(
+                        "the span ends at {}, the text has {} characters",
+                        span.1, length
+                    ),
+                ));
+            }
+            if !self.labels.is_empty() && !self.labels.contains(&span.2) {
+                report.errors.push(error(
+                    SpanErrorKind::UnknownLabel,
+                    format!("unknown label {}", span.2),
+                ));
+            }
+            spans.push(span);
+        }
+        spans.sort();
+        // The span reaching the furthest so far, nested spans overlap it too
+        let mut reach: Option<&(usize, usize, String)> = None;
+        for (index, span) in spans.iter().enumerate() {
+            let error = |kind, message| SpanError {
+                line: number + 1,
+                id: id.clone(),
+                span: Some(span.clone()),
+                kind,
+                message,
+            };
+            if index > 0 && spans[index - 1] == *span {
+                report.errors.push(error(
+                    SpanErrorKind::Duplicate,
+                    "the span is given twice".to_string(),
+                ));
+                continue;
+            }
+            match reach {
+                Some(previous) if !self.allow_overlaps && span.0 < previous.1 => {
+                    report.errors.push(error(
+                        SpanErrorKind::Overlap,
+                        format!(
+                            "the span overlaps ({}, {}, {})",
+                            previous.0, previous.1, previous.2
+                        ),
+                    ))
+                }
+                _ => {}
+            }
+            if reach.is_none_or(|previous| span.1 > previous.1) {
+                reach = Some(span);
+            }
+        }
+    }
+
+    /// Read a span given as [start, end, label] or {"start": ..., "end": ..., "label": ...}
+    fn span(value: &Value) -> Option<(usize, usize, String)> {
+        let (start, end, label) = match value {
+            Value::Array(values) if values.len() == 3 => (&values[0], &values[1], &values[2]),
+            Value::Object(object) => (
+                object.get("start")?,
+                object.get("end")?,
+                object.get("label")?,
+            ),
+            _ => return None,
+        };
+        Some((
+            usize::try_from(start.as_u64()?).ok()?,
+            usize::try_from(end.as_u64()?).ok()?,
+            label.as_str()?.to_string(),
+        ))
+    }
+}
+
+impl Document {
+    /// Check the offsets of the spans: each span must end after its start and
+    /// within the text, start and end on word bounds and be given once.
+    /// Offsets are in characters, the end is exclusive. The errors have no line,
+    /// see `Quickner::validate` for the position of the documents of a corpus.
+    /// # Example
+    /// ```
+    /// use quickner::{Document, SpanErrorKind};
+    /// let document = Document::new("Rust is fast".to_string(), vec![(1, 4, "PL".to_string())]);
+    /// let errors = document.validate_spans();
+    /// assert_eq!(errors[0].kind, SpanErrorKind::PartialWord);
+    /// ```
+    pub fn validate_spans(&self) -> Vec<SpanError> {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+        for span in &self.label {
+            let error = |kind, message| SpanError {
+                line: 0,
+                id: Some(self.id.clone()),
+                span: Some(span.clone()),
+                kind,
+                message,
+            };
+            if !seen.insert(span) {
+                errors.push(error(
+                    SpanErrorKind::Duplicate,
+                    "the span is given twice".to_string(),
+                ));
+                continue;
+            }
+            let (start, end, _) = *span;
+            if end <= start {
+                errors.push(error(
+                    SpanErrorKind::EmptySpan,
+                    format!("the span ends at {end}, before its start"),
+                ));
+                continue;
+            }
+            if end > chars.len() {
+                errors.push(error(
+                    SpanErrorKind::OutOfBounds,
+                    format!(
+                        "the span ends at {}, the text has {} characters",
+                        end,
+                        chars.len()
+                    ),
+                ));
+                continue;
+            }
+            let inside = |index: usize| {
+                index > 0
+                    && index < chars.len()
+                    && chars[index - 1].is_alphanumeric()
+                    && chars[index].is_alphanumeric()
+            };
+            if inside(start) || inside(end) {
+                let (first, last) = char_to_byte(&self.text, start, end);
+                errors.push(error(
+                    SpanErrorKind::PartialWord,
+                    format!(
+                        "the span \"{}\" starts or ends inside a word",
+                        preview(&self.text[first..last])
+                    ),
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl Quickner {
+    /// Audit the spans of every document, e.g. after importing a corpus whose
+    /// offsets may be off by a few characters (byte offsets read as characters).
+    /// The spans are checked with `Document::validate_spans`, then their text is
+    /// compared to the entities of the gazetteer with their label, if there are
+    /// entities. The errors hint at byte offsets when the text at the offsets
+    /// read as bytes is an entity with the label of the span. The line of an error is the position of its
+    /// document, starting at 1.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Quickner;
+    /// let quickner = Quickner::from_spacy("./annotations.json");
+    /// for error in quickner.validate() {
+    ///     println!("{}: {}", error.line, error.message);
+    /// }
+    /// ```
+    pub fn validate(&self) -> Vec<SpanError> {
+        let case_sensitive = self.config.entities.filters.case_sensitive;
+        let matching = &self.config.matching;
+        let normalize = |name: &str| {
+            let name = match case_sensitive {
+                true => name.to_string(),
+                false => name.to_lowercase(),
+            };
+            match matching.fold_diacritics {
+                true => fold_diacritics(&name).into_owned(),
+                false => name,
+            }
+        };
+        let mut entities: HashSet<(String, &str)> = HashSet::new();
+        for entity in &self.entities {
+            let name = normalize(&entity.name);
+            if matching.match_plurals {
+                for plural in plural_forms(&name, matching.language) {
+                    entities.insert((plural, entity.label.as_str()));
+                }
+            }
+            entities.insert((name, entity.label.as_str()));
+        }
+        // Entities matched with edits, by label
+        let mut fuzzy: HashMap<&str, Vec<Vec<char>>> = HashMap::new();
+        if let Some(options) = &matching.fuzzy {
+            for (name, label) in &entities {
+                if name.chars().count() >= options.min_len {
+                    fuzzy.entry(label).or_default().push(name.chars().collect());
+                }
+            }
+        }
+        let is_fuzzy_match = |surface: &str, label: &str| {
+            let surface: Vec<char> = surface.chars().collect();
+            let max_edits = matching.fuzzy.as_ref().map_or(0, |fuzzy| fuzzy.max_edits);
+            fuzzy.get(label).is_some_and(|names| {
+                names
+                    .iter()
+                    .any(|name| levenshtein(&surface, name, max_edits).is_some())
+            })
+        };
+        let mut errors = Vec::new();
+        for (index, document) in self.documents.iter().enumerate() {
+            let mut document_errors = document.validate_spans();
+            if !entities.is_empty() {
+                for span in &document.label {
+                    let label = span.2.as_str();
+                    // The text at the offsets read as bytes, when it is an entity
+                    let bytes = document
+                        .text
+                        .get(span.0..span.1)
+                        .filter(|bytes| entities.contains(&(normalize(bytes), label)));
+                    let hint = |message: String| match bytes {
+                        Some(bytes) => format!(
+                            "{message}, the offsets may be in bytes: \"{}\"",
+                            preview(bytes)
+                        ),
+                        None => message,
+                    };
+                    let mut invalid = false;
+                    for error in document_errors.iter_mut() {
+                        if error.span.as_ref() == Some(span) {
+                            invalid = true;
+                            if matches!(
+                                error.kind,
+                                SpanErrorKind::OutOfBounds | SpanErrorKind::PartialWord
+                            ) {
+                                error.message = hint(std::mem::take(&mut error.message));
+                            }
+                        }
+                    }
+                    if invalid {
+                        continue;
+                    }
+                    let (start, end) = char_to_byte(&document.text, span.0, span.1);
+                    let surface = &document.text[start..end];
+                    let surface_name = normalize(surface);
+                    if entities.contains(&(surface_name.clone(), label))
+                        || is_fuzzy_match(&surface_name, label)
+                    {
+                        continue;
+                    }
+                    document_errors.push(SpanError {
+                        line: 0,
+                        id: Some(document.id.clone()),
+                        span: Some(span.clone()),
+                        kind: SpanErrorKind::SurfaceMismatch,
+                        message: hint(format!(
+                            "\"{}\" is not a {} entity",
+                            preview(surface),
+                            label
+                        )),
+                    });
+                }
+            }
+            for mut error in document_errors {
+                error.line = index + 1;
+                errors.push(error);
+            }
+        }
+        errors
+    }
+}
+
+impl SpanError {
+    fn record(number: usize, id: Option<String>, kind: SpanErrorKind, message: String) -> Self {
+        SpanError {
+            line: number + 1,
+            id,
+            span: None,
+            kind,
+            message,
+        }
+    }
+}
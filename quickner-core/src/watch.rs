@@ -0,0 +1,315 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fs,
+    io::ErrorKind,
+    path::Path,
+    thread,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancel::is_cancelled,
+    config::{Compression, Format},
+    extract::Extractors,
+    quickner::Quickner,
+    utils::long_path,
+};
+
+/// State of a watched input, written as JSON next to the annotations
+/// (e.g. annotations_watch.json) after each pass of `Quickner::watch`
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct WatchState {
+    /// Size and modification time (nanoseconds since the epoch) of each file of the input
+    pub files: BTreeMap<String, (u64, u64)>,
+    /// Ids of the documents annotated and appended to the annotations
+    pub ids: BTreeSet<String>,
+}
+
+impl WatchState {
+    /// Path of the state of the annotations saved to `output`,
+    /// e.g. annotations_watch.json for annotations.jsonl
+    pub fn path_for(output: &str) -> String {
+        let path = Path::new(output);
+        let stem = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = stem.split('.').next().unwrap_or_default();
+        path.with_file_name(format!("{stem}_watch.json"))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Read the state, an empty state if the file does not exist
+    pub fn read(path: &str) -> Result<Self, std::io::Error> {
+        match fs::read_to_string(long_path(path)) {
+            Ok(state) => serde_json::from_str(&state)
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(WatchState::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the state to a temporary file renamed over the previous one,
+    /// so a crash never leaves a truncated state
+    pub fn write(&self, path: &str) -> Result<(), std::io::Error> {
+        let temporary = format!("{path}.tmp");
+        fs::write(long_path(&temporary), serde_json::to_string_pretty(self)?)?;
+        fs::rename(long_path(&temporary), long_path(path))
+    }
+}
+
+/// Options of `Quickner::watch`
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    /// Time between two scans of the input
+    pub interval: Duration,
+    /// Path of the state, next to the annotations if not set
+    pub state: Option<String>,
+    /// Run a single pass, e.g. from a scheduled job
+    pub once: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            interval: Duration::from_secs(5),
+            state: None,
+            once: false,
+        }
+    }
+}
+
+/// Size and modification time of the files of the texts input: the file itself,
+/// or the files of the directory read by an extractor, as they are read
+fn input_files(
+    path: &str,
+    extractors: &Extractors,
+) -> Result<BTreeMap<String, (u64, u64)>, std::io::Error> {
+    let files = match Path::new(path).is_dir() {
+        true => extractors.files(Path::new(path))?,
+        false => vec![Path::new(path).to_path_buf()],
+    };
+    files
+        .into_iter()
+        .map(|file| {
+            let metadata = fs::metadata(long_path(&file))?;
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64;
+            Ok((
+                file.to_string_lossy().to_string(),
+                (metadata.len(), modified),
+            ))
+        })
+        .collect()
+}
+
+impl Quickner {
+    /// Watch the texts input and annotate the new documents as they appear: the
+    /// input (a file, or the files of a directory, see `register_extractor`) is scanned every `interval`,
+    /// and when a file is added or changed, the texts are read again, the documents
+    /// not annotated yet are annotated and appended to the JSONL annotations.
+    /// The ids of the documents annotated are kept in a state file, so a restarted
+    /// watch continues where it stopped. A text changed in place is a new document,
+    /// its previous version stays in the annotations. Stops when the run is
+    /// cancelled, e.g. with Ctrl-C, or after a pass with `once`.
+    /// # Errors
+    /// Returns an error if the output is not an uncompressed JSONL file, or if
+    /// the input, the state or the annotations cannot be read or written
+    pub fn watch(&mut self, options: &WatchOptions) -> Result<(), Box<dyn Error>> {
+        let config = self.parse_config();
+        let output = &config.annotations.output;
+        if !matches!(config.annotations.format, Format::Jsonl)
+            || output.compression != Compression::None
+            || output.per_label.is_some()
+            || output.split_by_label
+        {
+            return Err(Box::new(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "The watch mode appends to a single uncompressed JSONL file, set format = \"jsonl\" without compression nor per-label files",
+            )));
+        }
+        let state_path = match &options.state {
+            Some(path) => path.to_string(),
+            None => WatchState::path_for(&output.path),
+        };
+        let mut state = WatchState::read(&state_path)?;
+        info!(
+            "Watching {} every {:?}, {} documents already annotated",
+            config.texts.input.path,
+            options.interval,
+            state.ids.len()
+        );
+        loop {
+            if let Some(appended) = self.watch_pass(&mut state)? {
+                state.write(&state_path)?;
+                info!(
+                    "{} documents appended to the annotations, {} in total",
+                    appended,
+                    state.ids.len()
+                );
+            }
+            if options.once {
+                return Ok(());
+            }
+            let started = Instant::now();
+            while started.elapsed() < options.interval {
+                if is_cancelled() || self.progress.is_cancelled() {
+                    info!("Watch stopped");
+                    return Ok(());
+                }
+                thread::sleep(options.interval.min(Duration::from_millis(100)));
+            }
+        }
+    }
+
+    /// Annotate and append the documents of the input missing from the state if
+    /// a file changed since the last pass. Returns the number of documents appended,
+    /// None if nothing changed
+    fn watch_pass(&mut self, state: &mut WatchState) -> Result<Option<usize>, Box<dyn Error>> {
+        let files = input_files(&self.config.texts.input.path, &self.extractors)?;
+        if files == state.files {
+            return Ok(None);
+        }
+        // The gazetteer read by the first pass is kept for the next ones
+        let mut pass = Quickner {
+            config: self.config.clone(),
+            config_file: self.config_file.clone(),
+            entities: self.entities.clone(),
+            progress: self.progress.clone(),
+            thread_pool: self.thread_pool.clone(),
+            extractors: self.extractors.clone(),
+            ..Default::default()
+        };
+        pass.read_texts()?;
+        pass.documents
+            .retain(|document| !state.ids.contains(&document.id));
+        let mut appended = 0;
+        if !pass.documents.is_empty() {
+            pass.process(false)?;
+            Format::append_jsonl(&pass.documents, &pass.config.annotations.output)?;
+            appended = pass.documents.len();
+            state
+                .ids
+                .extend(pass.documents.iter().map(|document| document.id.clone()));
+            self.entities = pass.entities;
+        }
+        state.files = files;
+        Ok(Some(appended))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use super::*;
+    use crate::{config::Config, progress::NoopProgress};
+
+    #[test]
+    fn state_path_is_next_to_the_annotations() {
+        assert_eq!(
+            WatchState::path_for("annotations.jsonl"),
+            "annotations_watch.json"
+        );
+        assert_eq!(
+            WatchState::path_for("out/corpus.v2.jsonl"),
+            "out/corpus_watch.json"
+        );
+        assert_eq!(
+            WatchState::path_for("out/annotations"),
+            "out/annotations_watch.json"
+        );
+    }
+
+    /// Instance watching the texts of a new directory
+    fn watcher(directory: &Path) -> Quickner {
+        fs::write(directory.join("entities.csv"), "name,label\nrust,PL\n").unwrap();
+        let mut config = Config::default();
+        config.texts.input.path = directory.join("texts.csv").to_string_lossy().to_string();
+        config.entities.input.path = directory.join("entities.csv").to_string_lossy().to_string();
+        config.annotations.output.path = directory
+            .join("annotations.jsonl")
+            .to_string_lossy()
+            .to_string();
+        Quickner {
+            config,
+            config_file: None,
+            progress: Arc::new(NoopProgress),
+            ..Default::default()
+        }
+    }
+
+    /// Ids of the documents appended to the annotations, in their order
+    fn appended(directory: &Path) -> Vec<String> {
+        fs::read_to_string(directory.join("annotations.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let document: serde_json::Value = serde_json::from_str(line).unwrap();
+                document["id"].as_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn watch_appends_only_the_new_documents() {
+        let directory: PathBuf =
+            std::env::temp_dir().join(format!("quickner_watch_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let texts = directory.join("texts.csv");
+        fs::write(&texts, "text\nrust is fast\nno entity here\n").unwrap();
+        let options = WatchOptions {
+            once: true,
+            ..Default::default()
+        };
+        let mut quickner = watcher(&directory);
+        quickner.watch(&options).unwrap();
+        let first = appended(&directory);
+        assert_eq!(first.len(), 2);
+        let state_path = directory.join("annotations_watch.json");
+        let state = WatchState::read(state_path.to_str().unwrap()).unwrap();
+        assert_eq!(state.ids, first.iter().cloned().collect());
+        assert_eq!(state.files.len(), 1);
+
+        // Nothing is appended nor written while the files are unchanged
+        let written = fs::read_to_string(&state_path).unwrap();
+        quickner.watch(&options).unwrap();
+        watcher(&directory).watch(&options).unwrap();
+        assert_eq!(appended(&directory), first);
+        assert_eq!(fs::read_to_string(&state_path).unwrap(), written);
+
+        // Only the documents of the new texts are appended, by a restarted watch too
+        fs::write(&texts, "text\nrust is fast\nno entity here\nrust again\n").unwrap();
+        watcher(&directory).watch(&options).unwrap();
+        let second = appended(&directory);
+        assert_eq!(second.len(), 3);
+        assert_eq!(second[..2], first[..]);
+        let state = WatchState::read(state_path.to_str().unwrap()).unwrap();
+        assert_eq!(state.ids.len(), 3);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn watch_needs_a_single_jsonl_output() {
+        let mut quickner = Quickner::default();
+        quickner.config.annotations.format = Format::Spacy;
+        let error = quickner.watch(&WatchOptions::default()).unwrap_err();
+        assert!(error.to_string().starts_with("The watch mode appends"));
+    }
+}
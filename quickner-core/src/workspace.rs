@@ -0,0 +1,230 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use flate2::read::GzDecoder;
+use serde_json::Value;
+
+use crate::{
+    config::{Compression, Config, ConfigError, Format},
+    document::Document,
+    models::{SpacyEntity, Stamp},
+    quickner::Quickner,
+    stats::Stats,
+    utils::{format_system_time, long_path},
+};
+
+/// Status of a quickner project found in a workspace
+#[derive(Clone, Debug)]
+pub struct ProjectStatus {
+    /// Path to the configuration file of the project
+    pub config: PathBuf,
+    /// Number of texts in the input file, or of files in the input directory,
+    /// None if the input cannot be read
+    pub texts: Option<usize>,
+    /// Statistics of the saved annotations, None if they were not saved yet
+    /// or if the output format cannot be read back (csv, brat and conll)
+    pub stats: Option<Stats>,
+    /// Last modification time of the saved annotations
+    pub last_run: Option<SystemTime>,
+}
+
+/// A directory of quickner projects
+#[derive(Debug, Default)]
+pub struct Workspace {
+    /// The projects found, sorted by configuration path
+    pub projects: Vec<ProjectStatus>,
+    /// The toml files that are not valid quickner configurations
+    pub skipped: Vec<(PathBuf, ConfigError)>,
+}
+
+impl Workspace {
+    /// Scan a directory for quickner configuration files.
+    /// The toml files of the directory and of its direct subdirectories are read,
+    /// the projects are not processed, only their inputs and saved annotations are read.
+    /// # Example
+    /// ```no_run
+    /// use quickner::Workspace;
+    /// let workspace = Workspace::scan("./projects").unwrap();
+    /// println!("{}", workspace.table());
+    /// ```
+    pub fn scan<P: AsRef<Path>>(directory: P) -> Result<Workspace, std::io::Error> {
+        let mut configs = Workspace::toml_files(directory.as_ref())?;
+        for entry in fs::read_dir(long_path(directory.as_ref()))? {
+            let entry = entry?.path();
+            if entry.is_dir() {
+                configs.extend(Workspace::toml_files(&entry)?);
+            }
+        }
+        configs.sort();
+        let mut workspace = Workspace::default();
+        for path in configs {
+            match Config::try_from_file(&path.to_string_lossy()) {
+                Ok(config) => workspace
+                    .projects
+                    .push(ProjectStatus::from_config(path, &config)),
+                Err(e) => workspace.skipped.push((path, e)),
+            }
+        }
+        Ok(workspace)
+    }
+
+    fn toml_files(directory: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(long_path(directory))? {
+            let entry = entry?.path();
+            if entry.is_file()
+                && entry.extension().and_then(|extension| extension.to_str()) == Some("toml")
+            {
+                files.push(entry);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Render the projects as a table with one row per project:
+    /// dataset sizes, last run date and span counts per label
+    pub fn table(&self) -> String {
+        let header = [
+            "PROJECT",
+            "TEXTS",
+            "DOCUMENTS",
+            "ANNOTATED",
+            "SPANS",
+            "LAST RUN",
+            "LABELS",
+        ]
+        .map(|column| column.to_string());
+        let missing = || "-".to_string();
+        let mut rows = vec![header];
+        for project in &self.projects {
+            let stats = project.stats.as_ref();
+            rows.push([
+                project.config.display().to_string(),
+                project
+                    .texts
+                    .map_or_else(missing, |texts| texts.to_string()),
+                stats.map_or_else(missing, |stats| stats.documents.to_string()),
+                stats.map_or_else(missing, |stats| stats.annotated_documents.to_string()),
+                stats.map_or_else(missing, |stats| stats.spans.to_string()),
+                project.last_run.map_or_else(missing, format_system_time),
+                stats
+                    .filter(|stats| !stats.labels.is_empty())
+                    .map_or_else(missing, |stats| {
+                        stats
+                            .labels
+                            .iter()
+                            .map(|(label, label_stats)| format!("{}: {}", label, label_stats.spans))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    }),
+            ]);
+        }
+        let mut widths = [0; 7];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .zip(widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect::<Vec<String>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl ProjectStatus {
+    /// Read the status of a project from its configuration
+    pub fn from_config(path: PathBuf, config: &Config) -> ProjectStatus {
+        let output = &config.annotations.output;
+        let extension = config.annotations.format.extension(&output.compression);
+        let saved = Path::new(&output.path).with_extension(extension);
+        let last_run = fs::metadata(long_path(&saved))
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let documents = match last_run {
+            Some(_) => ProjectStatus::saved_documents(config, &saved),
+            None => None,
+        };
+        let stats = documents.map(|documents| {
+            Quickner {
+                documents,
+                ..Default::default()
+            }
+            .stats()
+        });
+        ProjectStatus {
+            config: path,
+            texts: ProjectStatus::texts(config),
+            stats,
+            last_run,
+        }
+    }
+
+    fn texts(config: &Config) -> Option<usize> {
+        let path = Path::new(&config.texts.input.path);
+        if path.is_dir() {
+            return fs::read_dir(long_path(path))
+                .ok()
+                .map(|entries| entries.filter_map(Result::ok).count());
+        }
+        csv::Reader::from_path(long_path(path))
+            .ok()
+            .map(|mut reader| reader.records().count())
+    }
+
+    fn saved_documents(config: &Config, path: &Path) -> Option<Vec<Document>> {
+        let file = fs::File::open(long_path(path)).ok()?;
+        let reader: Box<dyn BufRead> = match config.annotations.output.compression {
+            Compression::Gzip => Box::new(BufReader::new(GzDecoder::new(file))),
+            Compression::None => Box::new(BufReader::new(file)),
+        };
+        match config.annotations.format {
+            Format::Jsonl => reader
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| serde_json::from_str::<Value>(&line).ok())
+                .filter(|value| !value.as_ref().is_some_and(Stamp::is_record))
+                .map(|value| {
+                    let value = config.annotations.output.fields.to_internal(value?);
+                    serde_json::from_value(value).ok()
+                })
+                .collect(),
+            Format::Spacy => {
+                let spacy: Vec<(String, SpacyEntity)> = serde_json::from_reader(reader).ok()?;
+                Some(
+                    spacy
+                        .into_iter()
+                        .map(|(text, entity)| Document::new(text, entity.entity))
+                        .collect(),
+                )
+            }
+            Format::Csv
+            | Format::Brat
+            | Format::Conll
+            | Format::Html
+            | Format::Nif
+            | Format::Tokens => None,
+        }
+    }
+}
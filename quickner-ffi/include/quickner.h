@@ -0,0 +1,53 @@
+/*
+ * quickner
+ *
+ * NER tool for quick and simple NER annotation
+ * Copyright (C) 2023, Omar MHAIMDAT
+ *
+ * Licensed under Mozilla Public License 2.0
+ *
+ * C interface of quickner, see quickner-ffi/README.md.
+ * Link with libquickner_ffi (.so, .dylib, .dll or the static library).
+ */
+
+#ifndef QUICKNER_H
+#define QUICKNER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A compiled gazetteer, read-only once created and shared by threads */
+typedef struct QuicknerContext QuicknerContext;
+
+/* Create a context from a configuration file, NULL on failure */
+QuicknerContext *quickner_context_from_config(const char *path);
+
+/* Create a context from a JSON list of entities, e.g. [{"name": "Rust", "label": "PL"}],
+ * lowercased unless case_sensitive is set, NULL on failure */
+QuicknerContext *quickner_context_from_entities(const char *entities, bool case_sensitive);
+
+/* Annotate `length` bytes of UTF-8 text (not necessarily NUL-terminated) and return
+ * the document as JSON, with span offsets in characters. NULL on failure.
+ * The string is freed with quickner_string_free */
+char *quickner_annotate(const QuicknerContext *context, const uint8_t *text, size_t length);
+
+/* Free a string returned by quickner_annotate, NULL is ignored */
+void quickner_string_free(char *string);
+
+/* Free a context, NULL is ignored */
+void quickner_context_free(QuicknerContext *context);
+
+/* Message of the last failure of the calling thread, NULL if none.
+ * Owned by quickner, valid until the next failure of the thread */
+const char *quickner_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QUICKNER_H */
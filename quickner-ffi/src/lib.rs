@@ -0,0 +1,344 @@
+// quickner
+//
+// NER tool for quick and simple NER annotation
+// Copyright (C) 2023, Omar MHAIMDAT
+//
+// Licensed under Mozilla Public License 2.0
+//
+
+//! C interface of quickner, to embed the matcher in services written in other
+//! languages (Go, Java, C#...), declared in `include/quickner.h`.
+//!
+//! A context compiles a gazetteer once, from a configuration file or from a JSON
+//! list of entities, then annotates UTF-8 texts into JSON documents:
+//! ```c
+//! QuicknerContext *context = quickner_context_from_config("config.toml");
+//! if (context == NULL) {
+//!     fprintf(stderr, "%s\n", quickner_last_error());
+//!     return 1;
+//! }
+//! const char *text = "Rust is made by Mozilla";
+//! char *document = quickner_annotate(context, (const uint8_t *)text, strlen(text));
+//! // {"id":"...","text":"Rust is made by Mozilla","label":[[0,4,"PL"],[16,23,"ORG"]],...}
+//! quickner_string_free(document);
+//! quickner_context_free(context);
+//! ```
+//!
+//! A context is read-only once created, it can be shared by the threads of the
+//! host. The functions never unwind into the caller: a failure or a panic
+//! returns NULL and its message is kept for `quickner_last_error`.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use quickner::{Config, Document, Entity, Matcher, Quickner};
+
+/// A compiled gazetteer
+pub struct QuicknerContext {
+    matcher: Matcher,
+}
+
+thread_local! {
+    /// Message of the last failure of the thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run `op`, returning NULL and keeping the message of its error or panic
+/// as the last error of the thread if it fails
+fn guard<T>(op: impl FnOnce() -> Result<*mut T, String>) -> *mut T {
+    let message = match catch_unwind(AssertUnwindSafe(op)) {
+        Ok(Ok(value)) => return value,
+        Ok(Err(message)) => message,
+        Err(panic) => match panic.downcast_ref::<&str>() {
+            Some(message) => format!("quickner panicked: {message}"),
+            None => match panic.downcast_ref::<String>() {
+                Some(message) => format!("quickner panicked: {message}"),
+                None => "quickner panicked".to_string(),
+            },
+        },
+    };
+    LAST_ERROR.with(|error| {
+        // A message with a NUL byte is cut at the NUL byte
+        let message = message.split('\0').next().unwrap_or_default().to_string();
+        *error.borrow_mut() = CString::new(message).ok();
+    });
+    ptr::null_mut()
+}
+
+/// The NUL-terminated UTF-8 string `name` given by the caller
+unsafe fn string<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, String> {
+    if pointer.is_null() {
+        return Err(format!("{name} is NULL"));
+    }
+    CStr::from_ptr(pointer)
+        .to_str()
+        .map_err(|e| format!("{name} is not valid UTF-8: {e}"))
+}
+
+/// Create a context from a configuration file: the entities and the excludes
+/// of the configuration are read and compiled with its matching options, context
+/// rules and label priority, as `quickner` does. The texts are not read.
+/// Returns NULL if the configuration cannot be read or is invalid, or if its
+/// entities or excludes cannot be read.
+///
+/// # Safety
+/// `path` must be a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn quickner_context_from_config(path: *const c_char) -> *mut QuicknerContext {
+    guard(|| {
+        let path = string(path, "path")?;
+        let config = Config::try_from_file(path).map_err(|e| e.to_string())?;
+        config.validate().map_err(|errors| {
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })?;
+        let mut quickner = Quickner {
+            config,
+            config_file: Some(path.to_string()),
+            ..Default::default()
+        };
+        quickner.load_gazetteer().map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(QuicknerContext {
+            matcher: quickner.compile_matcher(),
+        })))
+    })
+}
+
+/// Create a context from a JSON list of entities, e.g.
+/// `[{"name": "Rust", "label": "PL"}]`, without excludes or context rules.
+/// The entities are lowercased unless `case_sensitive` is set.
+/// Returns NULL if the JSON is not a list of entities.
+///
+/// # Safety
+/// `entities` must be a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn quickner_context_from_entities(
+    entities: *const c_char,
+    case_sensitive: bool,
+) -> *mut QuicknerContext {
+    guard(|| {
+        let entities: Vec<Entity> = serde_json::from_str(string(entities, "entities")?)
+            .map_err(|e| format!("Invalid entities: {e}"))?;
+        Ok(Box::into_raw(Box::new(QuicknerContext {
+            matcher: Matcher::new(&entities, case_sensitive),
+        })))
+    })
+}
+
+/// Annotate the `length` bytes of UTF-8 text at `text`, which need not be
+/// NUL-terminated, and return the document as JSON, as written in the JSONL
+/// output: `id`, `text`, and `label`, the spans as [start, end, label] with
+/// offsets in characters (Unicode scalar values), not bytes. The string is
+/// owned by the caller and freed with `quickner_string_free`.
+/// Returns NULL if the text is not valid UTF-8.
+///
+/// # Safety
+/// `context` must come from a `quickner_context_from_*` function and not be
+/// freed, `text` must point to `length` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn quickner_annotate(
+    context: *const QuicknerContext,
+    text: *const u8,
+    length: usize,
+) -> *mut c_char {
+    guard(|| {
+        let context = context.as_ref().ok_or("context is NULL")?;
+        let text = match length {
+            0 => "",
+            _ if text.is_null() => return Err("text is NULL".to_string()),
+            _ => std::str::from_utf8(slice::from_raw_parts(text, length))
+                .map_err(|e| format!("text is not valid UTF-8: {e}"))?,
+        };
+        let mut document = Document::from_string(text.to_string());
+        context.matcher.annotate(&mut document);
+        // The matcher lowercases the text unless it is case sensitive
+        document.text = text.into();
+        let json = serde_json::to_string(&document).map_err(|e| e.to_string())?;
+        CString::new(json)
+            .map(CString::into_raw)
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Free a string returned by `quickner_annotate`, NULL is ignored
+///
+/// # Safety
+/// `string` must come from `quickner_annotate` and not be freed already
+#[no_mangle]
+pub unsafe extern "C" fn quickner_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Free a context, NULL is ignored
+///
+/// # Safety
+/// `context` must come from a `quickner_context_from_*` function, not be
+/// freed already and not be used by another thread
+#[no_mangle]
+pub unsafe extern "C" fn quickner_context_free(context: *mut QuicknerContext) {
+    if !context.is_null() {
+        drop(Box::from_raw(context));
+    }
+}
+
+/// Message of the last failure of the calling thread, NULL if none. The string
+/// is owned by quickner and valid until the next failure of the thread
+#[no_mangle]
+pub extern "C" fn quickner_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    /// Message of the last failure of the thread
+    fn last_error() -> String {
+        let error = quickner_last_error();
+        assert!(!error.is_null());
+        unsafe { CStr::from_ptr(error) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// Document annotated by the context, as JSON
+    fn annotate(context: *const QuicknerContext, text: &[u8]) -> Option<serde_json::Value> {
+        let document = unsafe { quickner_annotate(context, text.as_ptr(), text.len()) };
+        if document.is_null() {
+            return None;
+        }
+        let json = unsafe { CStr::from_ptr(document) }.to_str().unwrap();
+        let value = serde_json::from_str(json).unwrap();
+        unsafe { quickner_string_free(document) };
+        Some(value)
+    }
+
+    fn context_from_entities(entities: &str) -> *mut QuicknerContext {
+        let entities = CString::new(entities).unwrap();
+        unsafe { quickner_context_from_entities(entities.as_ptr(), false) }
+    }
+
+    const FILTERS: &str = "alphanumeric = false\ncase_sensitive = false\nmin_length = 0\n\
+                           max_length = 1024\npunctuation = false\nnumbers = false\n\
+                           special_characters = false";
+
+    /// Directory of a test, with the entities, excludes and texts files
+    fn project(name: &str, excludes: Option<&str>) -> (PathBuf, CString) {
+        let directory =
+            std::env::temp_dir().join(format!("quickner_ffi_{}_{name}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("entities.csv"), "name,label\nRust,PL\n").unwrap();
+        fs::write(directory.join("texts.csv"), "text\nRust\n").unwrap();
+        let excludes = excludes
+            .map(|path| format!("path = \"{}\"", directory.join(path).display()))
+            .unwrap_or_default();
+        let config = format!(
+            "[texts.input]\npath = \"{texts}\"\n\n\
+             [texts.filters]\n{filters}\n\n\
+             [annotations]\nformat = \"jsonl\"\n\n\
+             [annotations.output]\npath = \"{output}\"\n\n\
+             [entities.input]\npath = \"{entities}\"\n\n\
+             [entities.filters]\n{filters}\n\n\
+             [entities.excludes]\n{excludes}\n",
+            filters = FILTERS,
+            texts = directory.join("texts.csv").display(),
+            output = directory.join("annotations").display(),
+            entities = directory.join("entities.csv").display(),
+        );
+        let path = directory.join("config.toml");
+        fs::write(&path, config).unwrap();
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+        (directory, path)
+    }
+
+    #[test]
+    fn annotate_returns_char_offsets() {
+        let context = context_from_entities(r#"[{"name": "Montréal", "label": "CITY"}]"#);
+        assert!(!context.is_null());
+        let document = annotate(context, "À Montréal, l'été".as_bytes()).unwrap();
+        assert_eq!(document["text"], "À Montréal, l'été");
+        assert_eq!(document["label"], serde_json::json!([[2, 10, "CITY"]]));
+        let document = annotate(context, b"").unwrap();
+        assert_eq!(document["label"], serde_json::json!([]));
+        unsafe { quickner_context_free(context) };
+    }
+
+    #[test]
+    fn null_and_invalid_arguments_return_null() {
+        let text = b"Rust";
+        let document = unsafe { quickner_annotate(ptr::null(), text.as_ptr(), text.len()) };
+        assert!(document.is_null());
+        assert_eq!(last_error(), "context is NULL");
+
+        let context = context_from_entities(r#"[{"name": "Rust", "label": "PL"}]"#);
+        let document = unsafe { quickner_annotate(context, ptr::null(), 4) };
+        assert!(document.is_null());
+        assert_eq!(last_error(), "text is NULL");
+        assert!(annotate(context, b"Rust \xff").is_none());
+        assert!(last_error().starts_with("text is not valid UTF-8"));
+        unsafe { quickner_context_free(context) };
+
+        assert!(unsafe { quickner_context_from_entities(ptr::null(), false) }.is_null());
+        assert_eq!(last_error(), "entities is NULL");
+        assert!(context_from_entities(r#"{"name": "Rust"}"#).is_null());
+        assert!(last_error().starts_with("Invalid entities"));
+        assert!(unsafe { quickner_context_from_config(ptr::null()) }.is_null());
+        assert_eq!(last_error(), "path is NULL");
+    }
+
+    #[test]
+    fn freeing_null_is_ignored() {
+        unsafe {
+            quickner_string_free(ptr::null_mut());
+            quickner_context_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn context_from_config_reads_the_gazetteer() {
+        let (directory, path) = project("config", None);
+        let context = unsafe { quickner_context_from_config(path.as_ptr()) };
+        assert!(!context.is_null(), "{}", last_error());
+        let document = annotate(context, b"Rust and rust").unwrap();
+        assert_eq!(
+            document["label"],
+            serde_json::json!([[0, 4, "PL"], [9, 13, "PL"]])
+        );
+        unsafe { quickner_context_free(context) };
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn unreadable_gazetteer_returns_null() {
+        let (directory, path) = project("excludes", Some("excludes.csv"));
+        fs::write(
+            directory.join("excludes.csv"),
+            "name,label\nrust\nc,PL,extra\n",
+        )
+        .unwrap();
+        assert!(unsafe { quickner_context_from_config(path.as_ptr()) }.is_null());
+        assert!(last_error().starts_with("Unable to parse the excludes file"));
+        fs::write(directory.join("excludes.csv"), "name\n").unwrap();
+        fs::write(directory.join("entities.csv"), "name\nRust\n").unwrap();
+        assert!(unsafe { quickner_context_from_config(path.as_ptr()) }.is_null());
+        assert!(last_error().starts_with("Unable to parse the entities file"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
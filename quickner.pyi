@@ -1,15 +1,17 @@
 from ast import Dict
-from typing import Iterator, Optional, List, Tuple, NewType, overload
+from typing import Any, Callable, Generator, Iterator, Optional, List, Tuple, NewType, Union, overload
 from enum import Enum
 
 Label = NewType("Label", List[Tuple[int, int, str]])
 
-def from_jsonl(path: str) -> Quickner:
+def from_jsonl(path: str, fields: Optional[Dict[str, str]] = None) -> Quickner:
     """
     Create a Quickner object from a JSONL file.
 
     Parameters:
         path (str): Path to the JSONL file.
+        fields (Dict[str, str]): Mapping of the default field names ("id", "text", "label")
+        to the field names used in the file, e.g. {"text": "content"}. Default is None.
 
     Returns:
         Quickner: Quickner object with:
@@ -17,6 +19,9 @@ def from_jsonl(path: str) -> Quickner:
         - the entities loaded from the JSONL file and infered from the annotations
         - the texts loaded from the JSONL file
         - A default configuration
+
+    Raises:
+        QuicknerError: If a key of `fields` is not "id", "text" or "label".
     """
     ...
 
@@ -36,6 +41,149 @@ def from_spacy(path: str) -> Quickner:
     """
     ...
 
+def from_dir(path: str) -> Quickner:
+    """
+    Create a Quickner object from every annotation file of a directory
+    and of its subdirectories: JSONL (.jsonl, .jsonl.gz), spaCy (.json),
+    brat (.ann with its .txt), CoNLL (.conll, or .txt with a word and a label per line)
+    and tokens (.tokens.jsonl). The other files are skipped.
+
+    Parameters:
+        path (str): Path to the directory.
+
+    Returns:
+        Quickner: Quickner object with the documents of every file, a document found
+        in several files is imported once with the spans of every file. The file of
+        each document is kept in its "source" metadata and its format in "source_format".
+    """
+    ...
+
+def from_sqlite(path: str) -> Quickner:
+    """
+    Create a Quickner object from a SQLite database saved with `Quickner.to_sqlite`.
+
+    Parameters:
+        path (str): Path to the SQLite database.
+
+    Returns:
+        Quickner: Quickner object with the documents, annotations and entities
+        stored in the database and a default configuration.
+    """
+    ...
+
+def set_preview_length(length: int) -> None:
+    """
+    Set the number of characters of the texts shown in the representations,
+    the pretty prints and the logs. Longer texts are cut at a word boundary
+    and followed by an ellipsis and the number of characters left out.
+
+    Parameters:
+        length (int): Number of characters shown, 0 shows the full texts. Default is 100.
+    """
+    ...
+
+def set_label_display(display: Dict[str, Dict[str, str]]) -> None:
+    """
+    Set the colors and names of the labels in the pretty prints and the HTML pages,
+    also set when a configuration with a [labels.display] table is processed.
+    The labels not listed keep their name and are given the colors in turn.
+
+    Parameters:
+        display (Dict[str, Dict[str, str]]): "color" and "name" of each label,
+        {"ORG": {"color": "blue", "name": "Organization"}}. The color is "red", "green",
+        "yellow", "blue", "magenta", "cyan" or an RGB color, "#4e79a7".
+
+    Raises:
+        QuicknerError: If a color is unknown.
+    """
+    ...
+
+def get_preview_length() -> int:
+    """
+    Number of characters of the texts shown in the representations and logs.
+
+    Returns:
+        int: The preview length, 0 if the full texts are shown.
+    """
+    ...
+
+def char_to_byte(text: str, start: int, end: int) -> Tuple[int, int]:
+    """
+    Convert the character offsets of a span, as in the labels of the documents,
+    to byte offsets in the UTF-8 encoded text.
+    >>> char_to_byte("Café in Paris", 8, 13)
+    (9, 14)
+
+    Returns:
+        Tuple[int, int]: Start and end of the span in bytes.
+    """
+    ...
+
+def byte_to_char(text: str, start: int, end: int) -> Tuple[int, int]:
+    """
+    Convert the byte offsets of a span in the UTF-8 encoded text, e.g. from a tool
+    working on bytes, to character offsets. An offset inside a character is moved
+    to the start of the next one.
+    >>> byte_to_char("Café in Paris", 9, 14)
+    (8, 13)
+
+    Returns:
+        Tuple[int, int]: Start and end of the span in characters.
+    """
+    ...
+
+def find_abbreviations(text: str) -> List[Tuple[str, str]]:
+    """
+    Find the abbreviations defined in a text, as "long form (SF)" or "SF (long form)",
+    with the algorithm of Schwartz and Hearst.
+    >>> find_abbreviations("The World Health Organization (WHO) said")
+    [('WHO', 'World Health Organization')]
+
+    Returns:
+        List[Tuple[str, str]]: Short and long form of each abbreviation.
+    """
+    ...
+
+def annotate_batch(
+    texts: List[str], entities: List[Entity], n_jobs: int = -1, case_sensitive: bool = False
+) -> List[Document]:
+    """
+    Annotate texts with the entities in parallel, the entities are compiled once.
+    >>> annotate_batch(["Rust is made by Mozilla"], [Entity("rust", "PL")])
+    [Document(id="...", text=Rust is made by Mozilla, label=[(0, 4, PL)])]
+
+    Parameters:
+        texts (List[str]): Texts to annotate.
+        entities (List[Entity]): Entities to match.
+        n_jobs (int): Number of threads, one per core with -1. Default is -1.
+        case_sensitive (bool): Match the case of the entities. Default is False.
+
+    Returns:
+        List[Document]: A document per text, in the order of the texts, with the case of the text.
+
+    Raises:
+        ValueError: If n_jobs is neither -1 nor positive.
+    """
+    ...
+
+def init_logging(level: str = "info", format: str = "text") -> bool:
+    """
+    Install a logger writing to stderr, quickner installs none by itself. The RUST_LOG
+    environment variable overrides the level.
+
+    Parameters:
+        level (str): "debug", "info", "warning" or "error". Default is "info".
+        format (str): "text", or "json" for one object per line, with the `stage`, `seconds`,
+        `count` and `unit` fields of the stages of a run. Default is "text".
+
+    Returns:
+        bool: False if a logger is already installed, nothing is changed then.
+
+    Raises:
+        QuicknerError: If the format is unknown.
+    """
+    ...
+
 class Text:
     """
     Text object.
@@ -52,8 +200,24 @@ class Entity:
     Attributes:
         name (str): Name of the entity.
         label (str): Label of the entity.
+        id (Optional[str]): Id of the entity, e.g. in a knowledge base.
+        canonical (Optional[str]): Canonical form of the entity.
+        attributes (Dict[str, str]): Extra attributes of the entity.
     """
-    def __init__(self, name: str, label: str) -> None: ...
+
+    name: str
+    label: str
+    id: Optional[str]
+    canonical: Optional[str]
+    attributes: Dict[str, str]
+    def __init__(
+        self,
+        name: str,
+        label: str,
+        id: Optional[str] = None,
+        canonical: Optional[str] = None,
+        attributes: Optional[Dict[str, str]] = None,
+    ) -> None: ...
 
 class Document:
     """
@@ -63,16 +227,55 @@ class Document:
         id (int): Id of the annotation.
         text (str): Text of the annotation.
         label (Label): Label of the annotation.
+        meta (Dict[str, str]): Metadata of the document, e.g. the extra columns
+        of the texts file.
+        extra (Dict[str, Any]): Fields of an imported JSONL record that are not fields
+        of a document, written back unchanged to the JSONL output.
+        relations (List[Tuple[Tuple[int, int, str], Tuple[int, int, str], str]]):
+        Relations between the spans, as (head span, tail span, label).
+        status (str): Who annotated the document: "machine", "corrected" or "gold". The spans
+        of a corrected or gold document are kept when it is annotated again, see Matching.verified.
+        tags (List[str]): Tags of the document as a whole, e.g. its topics, see Quickner.tag.
     """
 
     label: Label
     id: int
     text: str
+    meta: Dict[str, str]
+    extra: Dict[str, Any]
+    relations: List[Tuple[Tuple[int, int, str], Tuple[int, int, str], str]]
+    status: str
+    tags: List[str]
 
-    def __init__(self, text: str, label: Optional[Label]) -> None: ...
+    def __init__(
+        self,
+        text: str,
+        label: Optional[Label] = None,
+        meta: Optional[Dict[str, str]] = None,
+        status: str = "machine",
+    ) -> None: ...
     def __repr__(self) -> str: ...
     @staticmethod
     def from_string(text: str) -> Document: ...
+    @staticmethod
+    def from_dict(data: Dict[str, object]) -> Document:
+        """
+        Create a document from a dict with the keys of a JSONL record.
+
+        Parameters:
+            data (Dict[str, object]): "text", and optionally "id", "label" and "meta".
+            The id defaults to the hash of the text.
+
+        Raises:
+            QuicknerError: If the dict is not a document.
+        """
+        ...
+    def to_dict(self) -> Dict[str, object]:
+        """
+        Return the document as a dict with the keys of a JSONL record,
+        the spans are lists.
+        """
+        ...
     def annotate(self, entities: List[Entity], case_sensitive: bool = False) -> None:
         """
         Annotate a text with entities.
@@ -86,15 +289,138 @@ class Document:
         """
         ...
 
-    def pretty(self) -> str:
+    def pretty(self, length: Optional[int] = None) -> str:
         """
         Pretty print the document.
 
+        Parameters:
+            length (int): Number of characters shown, long texts are cut with an ellipsis.
+            Default is None (the preview length, see `set_preview_length`), 0 shows the full text.
+
+        Overlapping spans are all shown, each label is printed where its span ends.
+
         Returns:
             str: Pretty print of the document.
         """
         ...
 
+    def tokens(self) -> List[Tuple[str, int, int, str]]:
+        """
+        Tokens of the text with their character offsets and BIO tag, to train token
+        classification models. The tokens are the runs of alphanumeric characters and
+        each other non-space character, split at the bounds of the spans.
+        Of overlapping spans, the first one is kept, the longest if they start together.
+        >>> Document("New York, USA", [(0, 8, "LOC")]).tokens()
+        [('New', 0, 3, 'B-LOC'), ('York', 4, 8, 'I-LOC'), (',', 8, 9, 'O'), ('USA', 10, 13, 'O')]
+
+        Returns:
+            List[Tuple[str, int, int, str]]: Token, start, end and BIO tag of each token.
+        """
+        ...
+
+    def render_html(self, length: int = 0) -> str:
+        """
+        Render the document as an HTML fragment, the spans highlighted in <mark>
+        elements with a background color per label and followed by their label.
+        The text is escaped, overlapping spans are all shown.
+        >>> from IPython.display import HTML
+        >>> HTML(document.render_html())
+
+        Parameters:
+            length (int): Number of characters shown, long texts are cut with an ellipsis.
+            Default is 0, the full text.
+
+        Returns:
+            str: HTML fragment of the document.
+        """
+        ...
+
+    def validate_spans(self) -> List[SpanError]:
+        """
+        Check the offsets of the spans: each span must end after its start and
+        within the text, start and end on word bounds and be given once.
+        >>> Document("Rust is fast", [(1, 4, "PL")]).validate_spans()[0].kind
+        'partial_word'
+
+        Returns:
+            List[SpanError]: Errors of the spans, empty when they are all valid.
+        """
+        ...
+
+    def spans(self) -> List[Span]:
+        """
+        Spans of the document with their provenance, in the order of the spans.
+        >>> document = Document("I love rust")
+        >>> document.annotate([Entity("rust", "PL")])
+        >>> document.spans()[0].source
+        'gazetteer'
+
+        Returns:
+            List[Span]: The spans, with an unknown source when no provenance was recorded.
+        """
+        ...
+
+class Matcher:
+    """
+    Dictionary matcher compiled once from the entities, to annotate many
+    documents without building the automaton again for each of them.
+    >>> matcher = Matcher([Entity("Rust", "PL")])
+    >>> matcher.annotate(document)
+
+    Attributes:
+        entities (List[Entity]): Entities compiled into the matcher.
+    """
+
+    entities: List[Entity]
+
+    def __init__(
+        self,
+        entities: List[Entity],
+        case_sensitive: bool = False,
+        fold_diacritics: bool = False,
+        match_plurals: bool = False,
+        max_edits: int = 0,
+        min_len: int = 6,
+        language: str = "english",
+    ) -> None:
+        """
+        Compile the entities, without excludes, context rules or label priority.
+        See Quickner.compile_matcher for a matcher with the ones of a configuration.
+
+        Parameters:
+            entities (List[Entity]): Entities to match.
+            case_sensitive (bool): Case sensitive matching. Default is False.
+            fold_diacritics (bool): Match letters with and without diacritics alike. Default is False.
+            match_plurals (bool): Also match the plural forms of the entities. Default is False.
+            max_edits (int): Also match the words at most max_edits edits away from an entity,
+                where the exact matching found nothing. Default is 0, no fuzzy matching.
+            min_len (int): Shortest entity matched with edits, in characters. Default is 6.
+            language (str): Language of the plural forms: "english", "french" ("bureaux")
+                or "spanish" ("ciudades"). Default is "english".
+
+        Raises:
+            ValueError: If max_edits is not below min_len or the language is unknown.
+        """
+        ...
+    def annotate(self, document: Document) -> None:
+        """
+        Annotate the document in place, the spans found are added to its labels.
+        The text of the document is left unchanged.
+
+        Parameters:
+            document (Document): Document to annotate.
+        """
+        ...
+    def annotate_many(self, documents: List[Document]) -> None:
+        """
+        Annotate the documents in place, in parallel.
+
+        Parameters:
+            documents (List[Document]): Documents to annotate.
+        """
+        ...
+    def __len__(self) -> int: ...
+
 class Input:
     """
     Input configuration object.
@@ -102,10 +428,32 @@ class Input:
     Attributes:
         path (str): Path to the input file.
         filter (bool): Use filters. Default is False.
+        format (str): Format of the entities file ("csv", "tsv", "json", "toml", "txt").
+        Detected from the extension if None. Default is None.
+        split (str): How texts are split into documents, "paragraph" for one document per
+        paragraph of the .txt files of a directory, "sentence" for one document per sentence,
+        with the id of the text in the "parent" metadata. Default is None (one document per text or file).
+        limit (int): Maximum number of documents annotated, texts input only. Default is None (all).
+        sample (float): Fraction of the documents annotated, between 0 and 1, texts input only.
+        The same seed keeps the same documents. Default is None (all).
+        seed (int): Seed of the sample. Default is 0.
+        dedupe (float): Similarity from which documents are near-duplicates, between 0 and 1,
+        only the first of the duplicates is annotated, texts input only. Default is None.
+        id (str): How the ids of the documents are made, "hash" (of the text), "sequential"
+        (position in the input), "uuid" or "sha256" (of the text) or "column" (the `id` column
+        of the texts file), texts input only. Default is None (the hash of the text, or the
+        file names for a directory).
     """
 
     path: str
     filter: bool
+    format: Optional[str]
+    split: Optional[str]
+    limit: Optional[int]
+    sample: Optional[float]
+    seed: int
+    dedupe: Optional[float]
+    id: Optional[str]
 
 class Filters:
     """
@@ -122,6 +470,8 @@ class Filters:
         accept_special_characters (str): Accept special characters. Default is None.
         list_of_special_characters (List[str]): List of special characters to accept.
         Default is a list of special characters.
+        min_tokens (int): Minimum number of tokens, runs of letters and digits. Default is None, no minimum.
+        max_tokens (int): Maximum number of tokens. Default is None, no maximum.
     """
 
     alphanumeric: bool
@@ -133,6 +483,8 @@ class Filters:
     special_characters: bool
     accept_special_characters: Optional[str]
     list_of_special_characters: Optional[List[str]]
+    min_tokens: Optional[int]
+    max_tokens: Optional[int]
 
 class Texts:
     """
@@ -146,15 +498,153 @@ class Texts:
     input: Input
     filters: Filters
 
+class Fields:
+    """
+    Field names used in JSONL files.
+
+    Attributes:
+        id (str): Name of the id field. Default is "id".
+        text (str): Name of the text field. Default is "text".
+        label (str): Name of the label field. Default is "label".
+    """
+
+    id: str
+    text: str
+    label: str
+
+class JsonOptions:
+    """
+    JSON output options.
+
+    Attributes:
+        pretty (bool): Pretty-print JSON files (spaCy format). Default is False.
+        indent (int): Number of spaces used for indentation when pretty-printing. Default is 2.
+        ensure_ascii (bool): Escape non-ASCII characters as \\uXXXX. Default is False.
+    """
+
+    pretty: bool
+    indent: int
+    ensure_ascii: bool
+
+class HtmlOptions:
+    """
+    HTML output options.
+
+    Attributes:
+        title (str): Title of the pages. Default is "Annotations".
+        documents_per_page (int): Number of documents per page, the documents are written to
+        numbered pages (annotations_1.html, annotations_2.html, ...) if set. Default is None,
+        a single page.
+    """
+
+    title: str
+    documents_per_page: Optional[int]
+
+class NifOptions:
+    """
+    NIF output options.
+
+    Attributes:
+        base_uri (str): Base URI of the documents ({base_uri}documents/{id}) and of the
+        labels ({base_uri}labels/{label}). Default is "http://example.org/quickner/".
+        kb_uri (str): Prefix of the knowledge-base ids which are not URIs, e.g.
+        "http://www.wikidata.org/entity/". Default is None, {base_uri}entities/.
+    """
+
+    base_uri: str
+    kb_uri: Optional[str]
+
 class Output:
     """
     Output configuration object.
 
     Attributes:
         path (str): Path to the output file.
+        fields (Fields): Field names used in JSONL files.
+        json (JsonOptions): JSON output options.
+        html (HtmlOptions): HTML output options.
+        nif (NifOptions): NIF output options.
+        documents (str): Documents to export, "all", "annotated" (at least one span)
+        or "unannotated" (no span). Default is "all".
+        min_label_count (int): Labels with fewer spans are dropped from the export. Default is None.
+        rare_label (str): If set, labels below min_label_count are remapped to this label
+        instead of being dropped. Default is None.
+        min_confidence (float): Spans with a lower confidence are dropped from the export, the
+        spans without a confidence are kept. Default is None.
+        frozen_labels (str): Path to a JSON list of the labels expected in the export, `process`
+        fails before saving if labels are added or missing. Created with the labels of the first
+        run if it does not exist. Default is None.
+        create_dirs (bool): Create the missing directories of the output path. Default is True.
+        streaming (Streaming): Write the JSONL output from a writer thread fed by a bounded queue.
+        Default is None.
+        compression (str): Compression of the JSONL output, "none" or "gzip" (written to a
+        .jsonl.gz file). Default is "none".
+        per_label (PerLabel): Write one file per label or group of labels. Default is None.
+        split_by_label (bool): Write one file per label, like a per_label section without groups.
+        Default is False.
+        stamp (bool): Write a provenance stamp (quickner version, gazetteer and config hashes,
+        export time) as a JSONL header record or a brat note. Default is False.
+        report (bool): Write the report of the run next to the annotations saved by `process`,
+        e.g. annotations_report.json for annotations.jsonl. Default is True.
     """
 
     path: str
+    fields: Fields
+    json: JsonOptions
+    html: HtmlOptions
+    nif: NifOptions
+    documents: str
+    min_label_count: Optional[int]
+    rare_label: Optional[str]
+    min_confidence: Optional[float]
+    frozen_labels: Optional[str]
+    create_dirs: bool
+    streaming: Optional[Streaming]
+    compression: str
+    per_label: Optional[PerLabel]
+    split_by_label: bool
+    stamp: bool
+    report: bool
+
+class PerLabel:
+    """
+    Per-label export, one file per label (e.g. annotations_ORG.jsonl) holding
+    the documents restricted to the spans of the label.
+
+    Attributes:
+        groups (Dict[str, List[str]]): Name of the file of a group mapped to the labels
+        written to it, the labels without a group get a file of their own.
+    """
+
+    groups: Dict[str, List[str]]
+
+class Checkpoint:
+    """
+    Checkpoints of the annotation, the annotated documents are written to the
+    checkpoint directory every `every` documents and restored by a run with `resume`.
+
+    Attributes:
+        every (int): Number of documents annotated between two checkpoints. Default is 10000.
+        path (str): Directory of the checkpoint, the output path followed by .checkpoint if not set.
+        resume (bool): Skip the documents of the last checkpoint. Default is False.
+    """
+
+    every: int
+    path: Optional[str]
+    resume: bool
+
+class Streaming:
+    """
+    Streaming export options, only supported by the JSONL format.
+    The documents are serialized in parallel and written by a writer thread,
+    the workers wait when the queue is full so the memory used stays bounded.
+
+    Attributes:
+        queue_size (int): Number of serialized documents buffered before the workers
+        wait for the writer. Default is 1024.
+    """
+
+    queue_size: int
 
 class Format(Enum):
     """
@@ -166,6 +656,9 @@ class Format(Enum):
     SPACY = "spacy"
     BRAT = "brat"
     JSONL = "jsonl"
+    HTML = "html"
+    NIF = "nif"
+    TOKENS = "tokens"
 
 class AnnotationsConfig:
     """
@@ -174,7 +667,7 @@ class AnnotationsConfig:
     Attributes:
         output (Output): Output configuration.
         format (Format): Format of the output file. Default is "jsonl".
-        Possible values are "conll", "json", "spacy", "brat", "jsonl".
+        Possible values are "conll", "json", "spacy", "brat", "jsonl", "html", "nif", "tokens".
     """
 
     output: Output
@@ -185,7 +678,8 @@ class Excludes:
     Excludes configuration object.
 
     Attributes:
-        path (str): Path to the file containing the entities to exclude.
+        path (str): Path to the csv file of the texts to exclude from the annotation,
+        in its first column, with an optional `label` column scoping them to a label.
     """
 
     path: str
@@ -211,94 +705,1143 @@ class Logging:
     Attributes:
         level (str): Logging level. Default is "info".
         Possible values are "debug", "info", "warning", "error", "critical".
+        format (str): "text" or "json". Default is "text".
     """
 
     level: str
+    format: str
 
-class Config:
+class RouteEntities:
     """
-    Configuration object, parsed from a TOML file.
+    Entities of a route.
 
     Attributes:
-        texts (Texts): Texts configuration.
-        annotations (AnnotationsConfig): Annotations configuration.
-        entities (Entities): Entities configuration.
-        logging (Logging): Logging configuration.
+        input (Input): Input configuration of the entities file.
+        filters (Filters): Filters of the entities, the entities filters of the
+        configuration are used if None.
     """
 
-    texts: Texts
-    annotations: AnnotationsConfig
-    entities: Entities
-    logging: Logging
+    input: Input
+    filters: Optional[Filters]
 
-    def __init__(self, config_file: str) -> None: ...
+class Tags:
+    """
+    Document tags: the documents are tagged with the label of the keywords of
+    a second dictionary found in their text.
 
-class Quickner:
+    Attributes:
+        input (Input): Input configuration of the keywords file, the label of a keyword is its tag.
+        filters (Filters): Filters of the keywords, the entities filters of the
+        configuration are used if None.
+        min_matches (int): Matches of the keywords of a tag needed to tag a document. Default is 1.
     """
-    Quickner class to process texts and entities to generate annotations.
 
-    Parameters:
-        documents (List[Document]): List of documents.
-        entities (List[Entity]): List of entities.
-        config (Config): Configuration object.
+    input: Input
+    filters: Optional[Filters]
+    min_matches: int
+
+class Route:
+    """
+    Routing rule: documents whose metadata match every key of `when`
+    are annotated with the entities of the route instead of the default ones.
 
     Attributes:
-        documents (List[Document]): List of documents.
-        entities (List[Entity]): List of entities.
-        config (Config): Configuration object.
+        when (Dict[str, str]): Metadata to match, e.g. {"lang": "fr"}.
+        entities (RouteEntities): Entities of the route.
+    """
 
-    Methods:
-        process(save: bool = False): Process texts and entities to generate annotations.
-        save_annotations(path: str = None, format: Format = Format.JSONL): Save annotations to a file.
+    when: Dict[str, str]
+    entities: RouteEntities
+
+class Rule:
     """
+    Context rule, evaluated after the dictionary matching on the spans of `pattern`
+    annotated with `label`. The context of a span matches when one of the `require`
+    terms (if any) and none of the `forbid` terms appear within `window` tokens
+    before or after the span.
 
-    config_file: str
-    config: Config
-    documents: List[Document]
-    entities: List[Entity]
+    Attributes:
+        pattern (str): Text of the spans the rule applies to.
+        label (str): Label of the spans the rule applies to.
+        require (List[str]): Terms of which one must appear in the context.
+        forbid (List[str]): Terms that may not appear in the context.
+        window (int): Number of tokens on each side of the span, 10 by default.
+        action (str): "accept" keeps the span only if the context matches, "reject"
+        drops it if it does and "redirect" changes its label to `to` if it does.
+        to (Optional[str]): New label of the spans, with the redirect action.
+    """
 
-    @overload
-    def __init__(self) -> None: ...
-    @overload
-    def __init__(self, documents: List[Document]) -> None: ...
-    @overload
-    def __init__(self, entities: List[Entity]) -> None: ...
-    @overload
-    def __init__(self, documents: List[Document], entities: List[Entity]) -> None: ...
-    @overload
-    def __init__(
-        self, documents: List[Document], entities: List[Entity], config: Config
-    ) -> None: ...
-    @overload
-    def __init__(self, documents: List[Document], config: Config) -> None: ...
-    def process(self, save: Optional[bool] = False) -> None: ...
-    def save_annotations(
-        self, path: Optional[str] = None, format: Optional[Format] = Format.JSONL
-    ) -> None: ...
-    def to_jsonl(self, path: Optional[str] = None) -> None:
-        """
-        Save annotations to a JSONL file.
+    pattern: str
+    label: str
+    require: List[str]
+    forbid: List[str]
+    window: int
+    action: str
+    to: Optional[str]
 
-        Parameters:
-            path (str): Path to the output file. Default is the path defined in the configuration file.
+class Performance:
+    """
+    Performance options.
 
-        Returns:
-            None
-        """
-        ...
-    def to_csv(self, path: Optional[str] = None) -> None:
-        """
-        Save annotations to a CSV file.
+    Attributes:
+        num_threads (Optional[int]): Threads of the parallel stages, the global
+        pool of rayon (one thread per core) if not set.
+    """
 
-        Parameters:
-            path (str): Path to the output file. Default is the path defined in the configuration file.
+    num_threads: Optional[int]
 
-        Returns:
-            None
-        """
-        ...
+class RelationPattern:
+    """
+    Relation proposed after the annotation between the spans of `head` and `tail`
+    (any label if not set).
 
-    def to_spacy(self, path: Optional[str] = None) -> None:
+    Attributes:
+        label (str): Label of the relation.
+        head (Optional[str]): Label of the head span.
+        tail (Optional[str]): Label of the tail span.
+        pattern (Optional[str]): Words around the spans, with the "{head}" and
+        "{tail}" placeholders, e.g. "{head}, CEO of {tail}". The case and the
+        punctuation are ignored.
+        window (int): Most tokens between the spans without a pattern, 10 by default.
+    """
+
+    label: str
+    head: Optional[str]
+    tail: Optional[str]
+    pattern: Optional[str]
+    window: int
+
+class LabelPriority:
+    """
+    Label priority, used when entities of different labels match the exact same span.
+
+    Attributes:
+        order (List[str]): Labels in precedence order, only the first listed label
+        is kept for a span. Labels not listed come after the listed ones.
+        Empty by default, every label is kept.
+    """
+
+    order: List[str]
+
+class LabelDisplay:
+    """
+    How a label is rendered in the pretty prints and the HTML pages.
+
+    Attributes:
+        color (str): "red", "green", "yellow", "blue", "magenta", "cyan" or an RGB
+        color, "#4e79a7". Default is None (the colors are given in turn).
+        name (str): Name shown after the spans and in the legends. Default is None (the label).
+    """
+
+    color: Optional[str]
+    name: Optional[str]
+
+class LabelPrecision:
+    """
+    Guardrails of a high precision label: a span of the label is only kept if it
+    meets one of the conditions set.
+
+    Attributes:
+        title_case (bool): Keep the spans title-cased in the original text, the first word
+        and the words of more than 3 letters start with a capital letter. Default is False.
+        min_mentions (int): Keep the spans whose text is matched with the label at least this
+        many times in the document, 0 to disable. Default is 0.
+    """
+
+    title_case: bool
+    min_mentions: int
+
+class LabelAggregation:
+    """
+    Label kept when the annotators give the exact same span different labels.
+
+    Attributes:
+        strategy (str): "priority" (the first label of the label priority, every label is kept
+        without one), "vote" (the label with the most weighted votes of the sources, ties are
+        broken by the label priority) or "skip-conflicts" (the span is dropped). Default is "priority".
+        weights (Dict[str, float]): Weight of the votes of each source, 1 for the sources not listed.
+    """
+
+    strategy: str
+    weights: Dict[str, float]
+
+class Labels:
+    """
+    Labels configuration object.
+
+    Attributes:
+        priority (LabelPriority): Label priority.
+        display (Dict[str, LabelDisplay]): Color and name of the labels, see `set_label_display`.
+        precision (Dict[str, LabelPrecision]): Guardrails of the high precision labels.
+        aggregation (LabelAggregation): Aggregation of the conflicting labels of a span.
+    """
+
+    priority: LabelPriority
+    display: Dict[str, LabelDisplay]
+    precision: Dict[str, LabelPrecision]
+    aggregation: LabelAggregation
+
+class Matching:
+    """
+    Matching options, the entities and the texts are normalized at match time,
+    the spans keep the offsets of the original text.
+
+    Attributes:
+        fold_diacritics (bool): Match letters with and without diacritics alike, "Montreal" matches "Montréal".
+        match_plurals (bool): Also match the plural forms of the entities, "apple" matches "apples".
+        language (str): Language of the plural forms: "english", "french" or "spanish".
+        abbreviations (bool): Learn the abbreviations defined in the texts, see Quickner.learn_abbreviations.
+        propagate_mentions (bool): Annotate the repeated mentions of the spans of a document,
+        "Obama" after "Barack Obama".
+        fuzzy (Fuzzy): Fuzzy matching options, None if disabled.
+        verified (str): How the corrected and gold documents are annotated: "skip" (left as
+        they are), "append" (the spans not overlapping theirs are added) or "overwrite".
+    """
+
+    fold_diacritics: bool
+    match_plurals: bool
+    language: str
+    abbreviations: bool
+    propagate_mentions: bool
+    fuzzy: Optional[Fuzzy]
+    verified: str
+
+class Fuzzy:
+    """
+    Fuzzy matching options, the entities also match the words with typos or OCR errors,
+    where the exact matching found nothing.
+
+    Attributes:
+        max_edits (int): Largest number of inserted, deleted or substituted characters.
+        min_len (int): Shortest entity matched with edits, in characters.
+    """
+
+    max_edits: int
+    min_len: int
+
+class Config:
+    """
+    Configuration object, parsed from a TOML file.
+
+    Attributes:
+        texts (Texts): Texts configuration.
+        annotations (AnnotationsConfig): Annotations configuration.
+        entities (Entities): Entities configuration.
+        logging (Logging): Logging configuration.
+        labels (Labels): Labels configuration.
+        matching (Matching): Matching options.
+        checkpoint (Checkpoint): Checkpoints of the annotation, None if disabled.
+        routes (List[Route]): Routing rules, the first matching route wins.
+        rules (List[Rule]): Context rules, evaluated in order after the dictionary matching.
+        relations (List[RelationPattern]): Relations proposed between the spans.
+        performance (Performance): Threads of the parallel stages.
+        tags (Tags): Tags of the documents, None if disabled.
+    """
+
+    texts: Texts
+    annotations: AnnotationsConfig
+    entities: Entities
+    logging: Logging
+    labels: Labels
+    matching: Matching
+    checkpoint: Optional[Checkpoint]
+    routes: List[Route]
+    rules: List[Rule]
+    relations: List[RelationPattern]
+    performance: Performance
+    tags: Optional[Tags]
+
+    def __init__(
+        self, path: str = "config.toml", validate: bool = True, profile: Optional[str] = None
+    ) -> None:
+        """
+        Parse the configuration file, merged over the files of its `include` list, relative
+        to it. The settings of the [profiles.<profile>] section replace the ones of the file, then the QUICKNER_SECTION__KEY environment
+        variables override them, e.g. QUICKNER_TEXTS__INPUT__PATH.
+
+        Parameters:
+            path (str): Path to the TOML configuration file.
+            validate (bool): Check that the paths exist, that the filters make sense
+            and that no settings conflict. Default is True.
+            profile (str): Profile to apply. Default is the QUICKNER_PROFILE
+            environment variable, no profile if it is not set.
+
+        Raises:
+            QuicknerError: If the file cannot be read or parsed, the profile does not exist, or is invalid.
+        """
+        ...
+
+    @staticmethod
+    def from_dict(data: Dict[str, object], validate: bool = True) -> Config:
+        """
+        Create a configuration from a dict with the sections of the configuration file,
+        e.g. as returned by `to_dict` or read from a JSON file.
+
+        Parameters:
+            data (Dict[str, object]): The "texts", "annotations" and "entities" sections,
+            and optionally the other sections.
+            validate (bool): Check the configuration as the constructor does. Default is True.
+
+        Raises:
+            QuicknerError: If the dict is not a configuration, or is invalid.
+        """
+        ...
+    def to_dict(self) -> Dict[str, object]:
+        """
+        Return the configuration as a dict with the sections of the configuration file.
+        """
+        ...
+    def save(self, path: str) -> None:
+        """
+        Save the configuration to a TOML file, read back by `Config(path)`.
+        The paths are saved as they are, resolved from the directory of the file read.
+
+        Parameters:
+            path (str): Path to the TOML file.
+        """
+        ...
+    def validate(self) -> List[str]:
+        """
+        Check the configuration.
+
+        Returns:
+            List[str]: The problems found, empty if the configuration is valid.
+        """
+        ...
+
+class LabelStats:
+    """
+    Statistics of a label in an annotated corpus.
+
+    Attributes:
+        spans (int): Number of spans with the label.
+        documents (int): Number of documents with at least one span with the label.
+        surfaces (int): Number of unique surface forms annotated with the label.
+    """
+    spans: int
+    documents: int
+    surfaces: int
+
+    def to_dict(self) -> Dict[str, int]: ...
+
+class Stats:
+    """
+    Statistics of an annotated corpus.
+
+    Attributes:
+        documents (int): Number of documents.
+        annotated_documents (int): Number of documents with at least one span.
+        spans (int): Number of spans.
+        entities (int): Number of entities in the gazetteer.
+        labels (Dict[str, LabelStats]): Statistics per label.
+    """
+    documents: int
+    annotated_documents: int
+    spans: int
+    entities: int
+    labels: Dict[str, LabelStats]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class PeriodStats:
+    """
+    Statistics of the documents of a time period.
+
+    Attributes:
+        period (str): The period, e.g. 2023-05 for a month.
+        documents (int): Number of documents.
+        annotated_documents (int): Number of documents with at least one span.
+        spans (int): Number of spans.
+        new_entities (int): Number of entities annotated for the first time in this period.
+        entities (List[Tuple[str, str, int, bool]]): (entity, label, count, new) of the
+        entities annotated in the period, most frequent first, entities are lowercased.
+    """
+    period: str
+    documents: int
+    annotated_documents: int
+    spans: int
+    new_entities: int
+    entities: List[Tuple[str, str, int, bool]]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class Cooccurrences:
+    """
+    Entities and labels annotated together, counted once per document.
+
+    Attributes:
+        window (Optional[int]): Maximum number of tokens between two spans counted together,
+            anywhere in the same document if None.
+        entities (List[Tuple[str, str, str, str, int]]): Pairs of different entities as
+            (source, source_label, target, target_label, count), most frequent first.
+            The surface forms are lowercased.
+        labels (Dict[str, Dict[str, int]]): Number of documents in which two labels appear
+            together, in both orders. A label is paired with itself when two different
+            entities have it.
+    """
+    window: Optional[int]
+    entities: List[Tuple[str, str, str, str, int]]
+    labels: Dict[str, Dict[str, int]]
+
+    def to_dict(self) -> Dict[str, object]: ...
+    def label_matrix(self) -> Tuple[List[str], NDArray]:  # noqa: F821
+        """
+        Return the labels and the symmetric matrix of their counts, in the order of the labels.
+        """
+        ...
+    def entity_matrix(self) -> Tuple[List[Tuple[str, str]], NDArray]:  # noqa: F821
+        """
+        Return the entities, as (name, label) sorted by label then name,
+        and the symmetric matrix of their counts.
+        """
+        ...
+
+class TimeStats:
+    """
+    Statistics of an annotated corpus per time period.
+
+    Attributes:
+        field (str): Metadata field holding the date of the documents.
+        period (str): Length of the periods: "day", "month" or "year".
+        periods (List[PeriodStats]): Statistics of each period, oldest first.
+        undated (int): Number of documents without a valid date.
+    """
+    field: str
+    period: str
+    periods: List[PeriodStats]
+    undated: int
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+    def to_csv(self, path: str) -> None:
+        """
+        Save the entity frequencies to a CSV file, one row per entity and period
+        with the columns period, documents, label, entity, count and new.
+        """
+        ...
+
+class GazetteerStats:
+    """
+    Statistics of the gazetteer compiled into the matcher.
+
+    Attributes:
+        patterns (int): Number of patterns in the automaton.
+        memory (int): Heap memory used by the automaton, in bytes.
+        longest (Optional[str]): Longest pattern.
+        shortest (Optional[str]): Shortest pattern.
+        duplicates (Dict[str, List[str]]): Surface forms given several labels, with their labels.
+        substrings (Dict[str, List[str]]): Patterns containing other patterns,
+        with the patterns they contain.
+    """
+    patterns: int
+    memory: int
+    longest: Optional[str]
+    shortest: Optional[str]
+    duplicates: Dict[str, List[str]]
+    substrings: Dict[str, List[str]]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class LabelSuggestion:
+    """
+    A label suggested for a frequent term missing from the gazetteer,
+    to be approved before adding it to the entities.
+
+    Attributes:
+        term (str): The term, lowercased.
+        label (str): The suggested label.
+        score (float): Mean cosine similarity between the term and the nearest entries of the label.
+        frequency (int): Number of occurrences of the term outside the annotated spans.
+        neighbors (List[str]): The nearest gazetteer entries with the label.
+    """
+    term: str
+    label: str
+    score: float
+    frequency: int
+    neighbors: List[str]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class EntityCandidate:
+    """
+    A title-case sequence of the documents missing from the gazetteer,
+    to be reviewed before adding it to the entities.
+
+    Attributes:
+        name (str): The most frequent surface form of the candidate.
+        label (Optional[str]): Label of the spans found in the most similar contexts, None without spans.
+        frequency (int): Number of occurrences of the candidate outside the annotated spans.
+        score (float): Cosine similarity between the contexts of the candidate and of the label.
+        context (List[str]): The most specific words around the occurrences of the candidate.
+    """
+    name: str
+    label: Optional[str]
+    frequency: int
+    score: float
+    context: List[str]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class Metrics:
+    """
+    Precision, recall and F1 score computed from exact span matches.
+    """
+    precision: float
+    recall: float
+    f1: float
+    true_positives: int
+    false_positives: int
+    false_negatives: int
+
+    def to_dict(self) -> Dict[str, float]: ...
+
+class Evaluation:
+    """
+    Evaluation of the annotations against a reference corpus.
+
+    Attributes:
+        overall (Metrics): Micro-averaged metrics over every label.
+        labels (Dict[str, Metrics]): Metrics per label.
+        documents (int): Number of documents found in both corpora.
+    """
+    overall: Metrics
+    labels: Dict[str, Metrics]
+    documents: int
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class SpanError:
+    """
+    Error found on a span by a validation.
+
+    Attributes:
+        line (int): Position of the document in the corpus, starting at 1 (0 for a single document).
+        id (Optional[str]): Id of the document.
+        span (Optional[Tuple[int, int, str]]): The span in error.
+        kind (str): Kind of error: empty_span, out_of_bounds, duplicate, partial_word or surface_mismatch.
+        message (str): Description of the error.
+    """
+    line: int
+    id: Optional[str]
+    span: Optional[Tuple[int, int, str]]
+    kind: str
+    message: str
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class Span:
+    """
+    Span of a document with its provenance.
+
+    Attributes:
+        start (int): Start of the span, in characters.
+        end (int): End of the span, in characters, exclusive.
+        label (str): Label of the span.
+        source (str): Annotator of the span: gazetteer, fuzzy, regex, rule, mention, import or unknown.
+        entity_name (Optional[str]): Name of the entity of the gazetteer matched, or the span repeated by a mention.
+        confidence (Optional[float]): Confidence of the annotator in the span, between 0 and 1.
+        aggregation (Optional[Aggregation]): Decision taken when the annotators gave the span
+        different labels, see `[labels.aggregation]`.
+    """
+    start: int
+    end: int
+    label: str
+    source: str
+    entity_name: Optional[str]
+    confidence: Optional[float]
+    aggregation: Optional[Aggregation]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class Aggregation:
+    """
+    Aggregation of the labels given to the exact same span by the annotators.
+
+    Attributes:
+        strategy (str): "priority", "vote" or "skip-conflicts".
+        sources (List[str]): Sources which gave the span its label.
+        score (float): Weighted votes of the sources for the label.
+        rejected (Dict[str, float]): Weighted votes for the labels dropped, by label.
+    """
+    strategy: str
+    sources: List[str]
+    score: float
+    rejected: Dict[str, float]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class DuplicateCluster:
+    """
+    Documents found to be duplicates of the same document.
+
+    Attributes:
+        kept (str): Id of the first document of the cluster, kept by `dedupe`.
+        duplicates (List[Tuple[str, float]]): (id, similarity) of the duplicates, the
+        similarity to the kept document is 1.0 for the same text once normalized.
+    """
+    kept: str
+    duplicates: List[Tuple[str, float]]
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class MergeReport:
+    """
+    Counts of a merge of two corpora, see Quickner.merge.
+
+    Attributes:
+        documents_added (int): Documents of the other corpus added, their id was not found.
+        documents_identical (int): Documents found in both corpora with the same spans.
+        conflicts (int): Documents found in both corpora with different spans, resolved by the strategy.
+        text_conflicts (List[str]): Ids of the documents with different texts, kept as they were.
+        entities_added (int): Entities of the other corpus added.
+    """
+    documents_added: int
+    documents_identical: int
+    conflicts: int
+    text_conflicts: List[str]
+    entities_added: int
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class PruneReport:
+    """
+    Entities removed by Quickner.prune_entities.
+
+    Attributes:
+        documents (int): Documents inspected.
+        frequent (List[Tuple[str, str, int]]): (name, label, documents) of the entities matched in
+        too many documents, most frequent first.
+        rare (List[Tuple[str, str, int]]): (name, label, documents) of the entities matched in too
+        few documents.
+        spans_removed (int): Spans of the frequent entities removed from the documents.
+    """
+    documents: int
+    frequent: List[Tuple[str, str, int]]
+    rare: List[Tuple[str, str, int]]
+    spans_removed: int
+
+    def to_dict(self) -> Dict[str, object]: ...
+
+class ProcessTask:
+    """
+    A run of `Quickner.process_async` in a background thread, awaitable for its RunReport.
+
+    Attributes:
+        future (concurrent.futures.Future): Future of the RunReport of the run.
+    """
+
+    future: "concurrent.futures.Future[RunReport]"  # noqa: F821
+
+    def progress(self) -> Tuple[int, int]:
+        """
+        Number of documents annotated and total, the total is 0 until the annotation starts.
+        """
+        ...
+    def done(self) -> bool: ...
+    def result(self, timeout: Optional[float] = None) -> RunReport:
+        """
+        Wait for the report of the run, at most `timeout` seconds.
+        The exception of the run is raised again.
+        """
+        ...
+    def cancel(self) -> None:
+        """
+        Request the cancellation of the run: the current batch is finished and
+        the run ends with an exception. The other runs are not cancelled.
+        """
+        ...
+    def __await__(self) -> Generator[Any, None, RunReport]: ...
+
+class RunReport:
+    """
+    Report of a run of `process`, written as JSON next to the saved annotations.
+
+    Attributes:
+        started (str): Start of the run, RFC 3339 in UTC.
+        seconds (float): Wall time of the run in seconds.
+        threads (int): Number of threads of the parallel stages.
+        output (Optional[str]): Path of the annotations, if they were saved.
+        stats (Stats): Statistics of the annotated corpus.
+        gazetteer (Dict[str, int]): Number of entities of the gazetteer per label.
+        top_entities (List[Tuple[str, str, int]]): (entity, label, count) of the 100 most
+        annotated entities, most frequent first.
+        filtered_texts (int): Number of texts rejected by the filters of the texts.
+        duplicate_texts (int): Number of duplicate texts, annotated once.
+        near_duplicate_texts (int): Number of documents dropped by `dedupe` of the texts input.
+        stages (List[Tuple[str, float]]): (stage, seconds) of each stage of the run.
+    """
+    started: str
+    seconds: float
+    threads: int
+    output: Optional[str]
+    stats: Stats
+    gazetteer: Dict[str, int]
+    top_entities: List[Tuple[str, str, int]]
+    filtered_texts: int
+    duplicate_texts: int
+    near_duplicate_texts: int
+    stages: List[Tuple[str, float]]
+
+    def to_json(self) -> str:
+        """
+        The report as JSON, as written next to the annotations.
+        """
+        ...
+    def to_dict(self) -> Dict[str, object]:
+        """
+        The report as a dict, with the configuration of the run and
+        previews of the first filtered texts.
+        """
+        ...
+
+class Documents:
+    """
+    Lazy view on the documents of a Quickner instance, documents are only
+    converted to Python objects when accessed.
+
+    Supports len(), iteration, indexing and slicing, a slice returns a list of documents.
+    """
+
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[Document]: ...
+    @overload
+    def __getitem__(self, index: int) -> Document: ...
+    @overload
+    def __getitem__(self, index: slice) -> List[Document]: ...
+
+class Quickner:
+    """
+    Quickner class to process texts and entities to generate annotations.
+
+    Parameters:
+        documents (List[Document]): List of documents.
+        entities (List[Entity]): List of entities.
+        config (Config): Configuration object.
+
+    Attributes:
+        documents (Documents): Lazy view on the documents.
+        entities (List[Entity]): List of entities.
+        config (Config): Configuration object.
+
+    Methods:
+        process(save: bool = False): Process texts and entities to generate annotations.
+        save_annotations(path: str = None, format: Format = Format.JSONL): Save annotations to a file.
+    """
+
+    config_file: str
+    config: Config
+    documents: Documents
+    entities: List[Entity]
+
+    @overload
+    def __init__(self) -> None: ...
+    @overload
+    def __init__(self, documents: List[Document]) -> None: ...
+    @overload
+    def __init__(self, entities: List[Entity]) -> None: ...
+    @overload
+    def __init__(self, documents: List[Document], entities: List[Entity]) -> None: ...
+    @overload
+    def __init__(
+        self, documents: List[Document], entities: List[Entity], config: Config
+    ) -> None: ...
+    @overload
+    def __init__(self, documents: List[Document], config: Config) -> None: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[Document]: ...
+    @overload
+    def __getitem__(self, index: int) -> Document: ...
+    @overload
+    def __getitem__(self, index: slice) -> List[Document]: ...
+    def process(
+        self,
+        save: Optional[bool] = False,
+        progress: Union[Callable[[int, int], None], bool, None] = None,
+        progress_every: int = 1000,
+    ) -> RunReport:
+        """
+        Annotate the texts with the entities.
+
+        Parameters:
+            save (bool): Save the annotations to the configured output. Default is False.
+            progress (Callable[[int, int], None] | bool): Called with the number of documents
+            annotated and the total every `progress_every` documents, e.g. to update a notebook
+            widget. An exception raised by the callable stops the run and is raised again.
+            True shows the progress bar, False hides it. Default is None (progress bar).
+            progress_every (int): Number of documents between two calls of `progress`.
+            Default is 1000.
+
+        Returns:
+            RunReport: The report of the run, also written next to the annotations when
+            they are saved, unless `report` is disabled in the output.
+        """
+        ...
+    def process_async(self, save: Optional[bool] = False, progress_every: int = 1000) -> "ProcessTask":
+        """
+        Run `process` in a background thread on a copy of the instance, without
+        blocking the event loop. The instance is replaced by the processed copy
+        when the run ends, changes made to it in the meantime are lost.
+
+        Parameters:
+            save (bool): Save the annotations to the configured output. Default is False.
+            progress_every (int): Number of documents between two updates of the progress.
+            Default is 1000.
+
+        Returns:
+            ProcessTask: The run, awaitable for its RunReport.
+        """
+        ...
+    def preview(self, n: int = 10) -> List[Document]:
+        """
+        Annotate only the first documents, without saving anything, to check the
+        filters and the gazetteer before a full run. The Quickner object is not changed.
+
+        Parameters:
+            n (int): Number of documents annotated. Default is 10.
+
+        Returns:
+            List[Document]: The annotated documents, see `Document.pretty`.
+        """
+        ...
+    def save_annotations(
+        self, path: Optional[str] = None, format: Optional[Format] = Format.JSONL
+    ) -> None: ...
+    def annotate_incremental(self, entities: List[Entity]) -> None:
+        """
+        Annotate the documents with new entities only, merging the new spans
+        with the existing ones. Entities already known are ignored.
+
+        Parameters:
+            entities (List[Entity]): New entities to annotate.
+
+        Returns:
+            None
+        """
+        ...
+    def compile_matcher(self) -> Matcher:
+        """
+        Compile the entities into a matcher applying the excludes, the context
+        rules and the label priority of the configuration, to annotate single
+        documents as they come without compiling the entities again.
+        >>> matcher = quickner.compile_matcher()
+        >>> matcher.annotate(Document("rust is made by Mozilla"))
+
+        Returns:
+            Matcher: The compiled matcher.
+        """
+        ...
+    def balance(self, max_per_label: int, seed: int = 0) -> int:
+        """
+        Downsample the documents of the over-represented labels to get a more
+        balanced training set. A document is kept if one of its labels is still
+        in fewer than `max_per_label` kept documents, the documents of the rarest
+        labels first, then in a random order given by the seed. The documents
+        without spans and the order of the documents are kept.
+
+        Parameters:
+            max_per_label (int): Maximum number of documents annotated with a label.
+            seed (int): Seed of the random order. Default is 0.
+
+        Returns:
+            int: Number of documents removed.
+        """
+        ...
+    def duplicates(self, threshold: float = 0.9) -> List[DuplicateCluster]:
+        """
+        Find the exact and near-duplicate documents. Texts are compared lowercased,
+        without diacritics and punctuation, by the Jaccard similarity of their shingles
+        of 3 words; candidate pairs are found with MinHash signatures.
+
+        Parameters:
+            threshold (float): Similarity from which documents are duplicates, between 0
+            and 1, 1.0 only finds the same texts once normalized. Default is 0.9.
+
+        Returns:
+            List[DuplicateCluster]: The clusters, a document is a duplicate of the first
+            document similar to it.
+        """
+        ...
+    def dedupe(self, threshold: float = 0.9) -> List[DuplicateCluster]:
+        """
+        Remove the duplicates found by `duplicates`, the first document of each cluster is kept.
+
+        Parameters:
+            threshold (float): Similarity from which documents are duplicates. Default is 0.9.
+
+        Returns:
+            List[DuplicateCluster]: The clusters of the documents removed.
+        """
+        ...
+    def merge(self, other: Quickner, strategy: str = "union-spans") -> MergeReport:
+        """
+        Merge the documents and entities of another instance, e.g. annotated in a parallel
+        batch. The documents are matched by id, the new ones are added in their order.
+        The indexes are rebuilt.
+
+        Parameters:
+            other (Quickner): Instance merged into this one, left unchanged.
+            strategy (str): How the documents annotated differently are resolved: "keep-ours",
+            "keep-theirs" or "union-spans" (the spans and metadata of both documents, unless
+            their texts differ). The entities are matched by name and label, theirs replace
+            ours with "keep-theirs". Default is "union-spans".
+
+        Returns:
+            MergeReport: Counts of the documents and entities merged.
+
+        Raises:
+            QuicknerError: If the strategy is unknown.
+        """
+        ...
+    def prune_entities(self, max_doc_frequency: float = 0.5, min_doc_frequency: int = 1) -> PruneReport:
+        """
+        Remove the noisy entities once the documents are annotated: the ones matched in too many
+        documents, likely stopwords, with their spans (except in the verified documents), and
+        the ones matched in too few, e.g. never. An entity is matched in a document holding a
+        span of its label whose text is its name, ignoring the case unless the matching is case
+        sensitive. The indexes are rebuilt.
+
+        Parameters:
+            max_doc_frequency (float): Share of the documents above which an entity is removed,
+            between 0 and 1 (1.0 keeps them all). Default is 0.5.
+            min_doc_frequency (int): Documents below which an entity is removed, 0 keeps them
+            all. Default is 1.
+
+        Returns:
+            PruneReport: The entities removed, with the number of documents matching them.
+
+        Raises:
+            QuicknerError: If max_doc_frequency is not between 0 and 1.
+        """
+        ...
+    def filter_spans(self, predicate: Callable[[Span], bool]) -> int:
+        """
+        Keep the spans for which the predicate returns True, with their provenance and the
+        metadata of their entity, e.g. to remove the spans of an annotator or below a
+        confidence. The predicate is called on every span before any is removed.
+        >>> quick.filter_spans(lambda span: span.source != "fuzzy")
+        3
+
+        Parameters:
+            predicate (Callable[[Span], bool]): Called with each span of the documents.
+
+        Returns:
+            int: Number of spans removed.
+        """
+        ...
+    def register_extractor(self, extensions: List[str], function: Callable[[str], str]) -> None:
+        """
+        Read the files of the given extensions in a directory of texts with a function, e.g.
+        the .docx or .html files, instead of the extractor registered before for one of them.
+        The .txt files are read as UTF-8 and the .pdf files with `pdftotext` by default.
+        >>> quick.register_extractor(["html", "htm"], lambda path: BeautifulSoup(open(path), "html.parser").get_text())
+
+        Parameters:
+            extensions (List[str]): Extensions of the files, in any case, with or without the dot.
+            function (Callable[[str], str]): Called with the path of a file, returns its text.
+            An exception raised by the function stops `process`.
+
+        Raises:
+            QuicknerError: If the function is not callable.
+        """
+        ...
+    def set_status(self, ids: List[str], status: str) -> int:
+        """
+        Set the status of the documents with the given ids, e.g. once their spans were corrected.
+        >>> quick.set_status([document.id for document in reviewed], "gold")
+        12
+
+        Parameters:
+            ids (List[str]): Ids of the documents.
+            status (str): "machine", "corrected" or "gold".
+
+        Returns:
+            int: Number of documents changed.
+
+        Raises:
+            QuicknerError: If the status is unknown.
+        """
+        ...
+    def tag(self, keywords: List[Entity], min_matches: int = 1) -> int:
+        """
+        Tag the documents with the label of the keywords found at least `min_matches` times
+        in their text, e.g. their topics. The tags of a corrected or gold document are
+        handled as its spans, see Matching.verified.
+        >>> quick.tag([Entity("goal", "sports"), Entity("inflation", "finance")])
+        42
+
+        Parameters:
+            keywords (List[Entity]): Keywords, their label is the tag.
+            min_matches (int): Matches of the keywords of a tag needed to tag a document. Default is 1.
+
+        Returns:
+            int: Number of documents with at least one tag.
+        """
+        ...
+    def rename_label(self, old: str, new: str) -> int:
+        """
+        Rename a label in the spans of the documents, with their provenance, the metadata of
+        their entity and their relations, and in the entities.
+        >>> quick.rename_label("ORG", "ORGANIZATION")
+        12
+
+        Parameters:
+            old (str): Label renamed.
+            new (str): New name of the label.
+
+        Returns:
+            int: Number of spans renamed.
+        """
+        ...
+    def enable_history(self, path: str) -> None:
+        """
+        Keep an append-only history of the changes of the documents in a JSONL file, one line
+        per operation: `add_document`, `annotate_incremental`, `balance`, `dedupe`, `merge`,
+        `filter_spans`, `rename_label` and setting `documents`. An existing history is continued.
+
+        Parameters:
+            path (str): Path of the history file, created if missing.
+
+        Raises:
+            QuicknerError: If the history file cannot be read or created.
+        """
+        ...
+    def undo(self, n: int = 1) -> int:
+        """
+        Revert the last `n` operations of the history not reverted yet, latest first. The undo
+        is recorded in the history too.
+        >>> quick.balance(100)
+        >>> quick.undo()
+        1
+
+        Parameters:
+            n (int): Number of operations reverted. Default is 1.
+
+        Returns:
+            int: Number of operations reverted, fewer than `n` if the history has fewer.
+
+        Raises:
+            QuicknerError: If the history is not enabled or cannot be read or written.
+        """
+        ...
+    def history(self) -> List[Dict[str, Any]]:
+        """
+        The operations of the history, oldest first, empty if it is not enabled. Each one has
+        its `seq`, `time`, `operation`, the numbers of `documents_added`, `documents_removed`,
+        `spans_added` and `spans_removed`, the `undone` operations for an undo, and the
+        `changes`: the `index`, `before` and `after` of each document changed.
+
+        Returns:
+            List[Dict[str, Any]]: The operations.
+
+        Raises:
+            QuicknerError: If the history file cannot be read.
+        """
+        ...
+    def entities_union(self, other: Union[Quickner, List[Entity]]) -> List[Entity]:
+        """
+        The entities, then the entities of `other` with a name and label not among them,
+        e.g. to combine the gazetteers of two corpora. The entities are compared on their
+        name and label, the first one is kept. The instance is not changed.
+        >>> quick.entities = quick.entities_union(other)
+
+        Parameters:
+            other (Union[Quickner, List[Entity]]): A Quickner object or a list of entities.
+
+        Returns:
+            List[Entity]: The entities of the union.
+        """
+        ...
+    def entities_intersect(self, other: Union[Quickner, List[Entity]]) -> List[Entity]:
+        """
+        The entities with a name and label among the entities of `other`, in their order.
+
+        Parameters:
+            other (Union[Quickner, List[Entity]]): A Quickner object or a list of entities.
+
+        Returns:
+            List[Entity]: The entities of the intersection.
+        """
+        ...
+    def entities_diff(self, other: Union[Quickner, List[Entity]]) -> List[Entity]:
+        """
+        The entities with a name and label not among the entities of `other`, in their order.
+
+        Parameters:
+            other (Union[Quickner, List[Entity]]): A Quickner object or a list of entities.
+
+        Returns:
+            List[Entity]: The entities missing from `other`.
+        """
+        ...
+    def export_entities(self, path: str, format: Optional[str] = None) -> None:
+        """
+        Save the entities to a file that can be used as the entities file of a configuration.
+        >>> quick.export_entities("gazetteer.csv")
+
+        Parameters:
+            path (str): Path to the file, or to a directory with the txt format.
+            format (str): "csv", "tsv", "json", "toml" or "txt" (a file per label, without
+            the metadata). Default is None (detected from the extension, csv if unknown).
+
+        Raises:
+            QuicknerError: If the format is unknown or the file cannot be written.
+        """
+        ...
+    def learn_abbreviations(self) -> List[Entity]:
+        """
+        Find the abbreviations defined in the documents and add the ones whose long
+        form or short form is an entity to the entities, with the same label, id and
+        metadata. The canonical form of a new entity is the one of its source entity,
+        or the long form.
+
+        Returns:
+            List[Entity]: The entities added.
+        """
+        ...
+    @staticmethod
+    def from_dict(data: Dict[str, object]) -> Quickner:
+        """
+        Create a Quickner object from a dict, without reading any file.
+
+        Parameters:
+            data (Dict[str, object]): "config" (see Config.from_dict), "entities" (dicts
+            with "name" and "label") and "documents" (see Document.from_dict), all optional.
+
+        Raises:
+            QuicknerError: If the dict is not a Quickner object.
+        """
+        ...
+    def to_dict(self) -> Dict[str, object]:
+        """
+        Return the configuration, the entities and the documents as a dict
+        of plain Python objects, e.g. to be written with json.dump.
+        """
+        ...
+    def to_jsonl(self, path: Optional[str] = None) -> None:
+        """
+        Save annotations to a JSONL file.
+
+        Parameters:
+            path (str): Path to the output file. Default is the path defined in the configuration file.
+
+        Returns:
+            None
+        """
+        ...
+    def to_csv(self, path: Optional[str] = None) -> None:
+        """
+        Save annotations to a CSV file with the columns id, text, start, end, label and span_text,
+        one row per span. A document without spans gets a row with empty span columns.
+
+        Parameters:
+            path (str): Path to the output file. Default is the path defined in the configuration file.
+
+        Returns:
+            None
+        """
+        ...
+
+    def to_spacy(self, path: Optional[str] = None) -> None:
         """
         Save annotations to a Spacy file.
 
@@ -374,6 +1917,324 @@ class Quickner:
         """
         ...
 
+    def run_metadata(self) -> Dict[str, Dict[str, object]]:
+        """
+        Parameters (config hash, gazetteer size, ...) and dataset metrics
+        (documents, annotated and unannotated documents, spans per label)
+        describing the run.
+
+        Returns:
+            Dict[str, Dict[str, object]]: {"params": {...}, "metrics": {...}}
+        """
+        ...
+
+    def log_run(self, tracker: object) -> None:
+        """
+        Report the run parameters and the dataset metrics to an experiment tracker.
+        >>> import mlflow
+        >>> with mlflow.start_run():
+        ...     quickner.log_run(mlflow)
+        >>> import wandb
+        >>> quickner.log_run(wandb.init(project="ner"))
+
+        Parameters:
+            tracker (object): The mlflow module, the wandb module or a wandb run,
+            or any callable taking (params, metrics).
+        """
+        ...
+
+    def to_sqlite(self, path: str) -> None:
+        """
+        Save the documents, entities and annotations to a SQLite database.
+        The database is created if it does not exist. Documents already stored
+        are updated (keyed on their id) and their annotations replaced.
+        The metadata of the documents and entities is kept.
+
+        Parameters:
+            path (str): Path to the SQLite database.
+        """
+        ...
+
+    @staticmethod
+    def from_dir(path: str) -> "Quickner":
+        """
+        Import every annotation file of a directory and of its subdirectories
+        (jsonl, spaCy, brat and CoNLL), see `quickner.from_dir`.
+
+        Parameters:
+            path (str): Path to the directory.
+
+        Returns:
+            Quickner: Quickner object with a default configuration.
+        """
+        ...
+
+    @staticmethod
+    def from_sqlite(path: str) -> "Quickner":
+        """
+        Load the documents, entities and annotations from a SQLite database.
+
+        Parameters:
+            path (str): Path to the SQLite database.
+
+        Returns:
+            Quickner: Quickner object with a default configuration.
+        """
+        ...
+
+    def save_project(self, path: str) -> None:
+        """
+        Save the configuration, entities, excludes, documents and their indexes
+        to a single gzip-compressed JSON file, to persist an annotation session.
+
+        Parameters:
+            path (str): Path to the project file, e.g. session.qnr.
+        """
+        ...
+
+    @staticmethod
+    def load_project(path: str) -> "Quickner":
+        """
+        Load a project saved with `save_project`, in the state it was saved.
+
+        Parameters:
+            path (str): Path to the project file.
+
+        Raises:
+            QuicknerError: If the file cannot be read or is not a project.
+        """
+        ...
+
+    def stats(self) -> Stats:
+        """
+        Compute the statistics of the annotated corpus.
+
+        Returns:
+            Stats: Overall and per-label statistics.
+        """
+        ...
+
+    def time_stats(self, field: str = "date", period: str = "month") -> TimeStats:
+        """
+        Compute the statistics of the annotated corpus per time period, from the
+        date in the metadata of the documents (YYYY, YYYY-MM or YYYY-MM-DD).
+
+        Parameters:
+            field (str): Metadata field holding the date of the documents.
+            period (str): Length of the periods: "day", "month" or "year".
+
+        Returns:
+            TimeStats: Entity frequencies and new entities per period.
+
+        Raises:
+            QuicknerError: If the period is unknown.
+        """
+        ...
+
+    def cooccurrences(self, window: Optional[int] = None) -> Cooccurrences:
+        """
+        Count the entities and labels annotated together, e.g. to build a graph of
+        related entities. Each pair is counted once per document.
+
+        Parameters:
+            window (Optional[int]): Maximum number of tokens between two spans counted
+                together, anywhere in the same document if None.
+
+        Returns:
+            Cooccurrences: The pairs of entities and the counts of the pairs of labels.
+        """
+        ...
+
+    def gazetteer_stats(self) -> GazetteerStats:
+        """
+        Compute the statistics of the gazetteer compiled into the matcher,
+        they are also logged when annotating.
+
+        Returns:
+            GazetteerStats: Pattern count, automaton memory, longest and shortest patterns,
+            surface forms with several labels and patterns containing other patterns.
+        """
+        ...
+
+    def to_entity_ruler(self, path: str) -> None:
+        """
+        Save the gazetteer as the JSONL patterns of a spaCy EntityRuler, e.g.
+        {"label": "ORG", "pattern": [{"LOWER": "apple"}]}. Case sensitive entities are
+        exact phrase patterns, the other ones have one token pattern per word.
+
+        Parameters:
+            path (str): Path to the patterns file, loaded with
+            nlp.add_pipe("entity_ruler").from_disk(path).
+        """
+        ...
+
+    def to_entity_links(self, path: str) -> None:
+        """
+        Save the spans linked to the entities of the gazetteer as JSONL, one document per line,
+        to train or evaluate an entity linking model. A span gets the id and canonical form
+        of its entity when only one entity has its text and label, and the list of the
+        candidate entities otherwise:
+        {"id": "...", "text": "...", "spans": [{"start": 24, "end": 29, "label": "LOC",
+        "text": "paris", "candidates": [{"id": "Q90"}, {"id": "Q830149"}]}]}
+
+        Parameters:
+            path (str): Path to the JSONL file.
+        """
+        ...
+
+    def to_docbin(self, path: str, lang: str = "en") -> None:
+        """
+        Save the annotations as a serialized spaCy DocBin, ready for `spacy train`.
+        Requires spaCy to be installed.
+
+        The texts are tokenized with a blank pipeline of the language, spans not aligned
+        with the tokens are skipped with a warning and overlapping spans are resolved
+        by spaCy, the longest span wins.
+
+        Parameters:
+            path (str): Path to the .spacy file.
+            lang (str): Language of the blank pipeline used for tokenization. Default is "en".
+        """
+        ...
+
+    def to_pandas(self) -> "pandas.DataFrame":
+        """
+        Export the spans as a pandas DataFrame, requires pandas to be installed.
+
+        Returns:
+            pandas.DataFrame: One row per span with the columns
+            id, text, start, end, label and entity_text.
+        """
+        ...
+
+    def subset(
+        self,
+        labels: Optional[List[str]] = None,
+        entities: Optional[List[str]] = None,
+        meta: Optional[Dict[str, str]] = None,
+    ) -> "Quickner":
+        """
+        Create a new instance restricted to the documents matching every condition,
+        the configuration, the entities and the order of the documents are kept.
+
+        Parameters:
+            labels (List[str]): Labels the documents have at least one span of.
+            entities (List[str]): Entities annotated in the documents, case insensitive.
+            meta (Dict[str, str]): Metadata values of the documents.
+
+        Returns:
+            Quickner: The subset, on which stats, evaluation, export and splitting can be run.
+        """
+        ...
+
+    def search(
+        self,
+        text: Optional[str] = None,
+        labels: Optional[List[str]] = None,
+        without_labels: Optional[List[str]] = None,
+        entities: Optional[List[str]] = None,
+        meta: Optional[Dict[str, str]] = None,
+        limit: Optional[int] = None,
+    ) -> List[Document]:
+        """
+        Find the documents matching every condition, in the order of the corpus.
+        The text is looked up in an index of the tokens built on the first search.
+
+        Parameters:
+            text (str): Substring of the texts of the documents, case insensitive.
+            labels (List[str]): Labels the documents have at least one span of.
+            without_labels (List[str]): Labels the documents have no span of.
+            entities (List[str]): Entities annotated in the documents, case insensitive.
+            meta (Dict[str, str]): Metadata values of the documents.
+            limit (int): Most documents returned, all of them by default.
+
+        Returns:
+            List[Document]: The documents matching the query.
+        """
+        ...
+
+    def suggest_labels(
+        self,
+        vectors: str,
+        min_frequency: int = 3,
+        max_terms: int = 1000,
+        neighbors: int = 5,
+        min_similarity: float = 0.5,
+    ) -> List[LabelSuggestion]:
+        """
+        Suggest labels for the frequent terms missing from the gazetteer, each term
+        gets the label of its nearest entities in the vector space.
+
+        Parameters:
+            vectors (str): Path to a word vectors file in the word2vec or GloVe text format.
+            min_frequency (int): Minimum number of occurrences of a term.
+            max_terms (int): Maximum number of terms compared to the gazetteer, the most frequent ones.
+            neighbors (int): Number of nearest entities voting for the label of a term.
+            min_similarity (float): Minimum cosine similarity between a term and the entities of its label.
+
+        Returns:
+            List[LabelSuggestion]: The suggestions, best score first.
+        """
+        ...
+
+    def discover(
+        self,
+        min_frequency: int = 3,
+        max_words: int = 4,
+        window: int = 5,
+        max_candidates: int = 100,
+        path: Optional[str] = None,
+    ) -> List[EntityCandidate]:
+        """
+        Propose new entities: the frequent title-case sequences of the documents
+        ("Bank of America") missing from the gazetteer and from the spans. Each
+        candidate gets the label of the spans found in the most similar contexts.
+        The texts must keep their case: `process()` lowercases them unless the
+        entity filters are case sensitive.
+
+        Parameters:
+            min_frequency (int): Minimum number of occurrences of a candidate.
+            max_words (int): Maximum number of words of a candidate.
+            window (int): Number of words on each side of an occurrence making its context.
+            max_candidates (int): Maximum number of candidates, the best ranked ones.
+            path (Optional[str]): Path to a CSV file the candidates are saved to, for review.
+
+        Returns:
+            List[EntityCandidate]: The candidates, ranked by context similarity and frequency.
+        """
+        ...
+
+    def evaluate(self, reference: "Quickner") -> Evaluation:
+        """
+        Evaluate the annotations against a reference (gold) corpus.
+        Documents are matched by id and spans must match exactly.
+        Evaluating two annotators against each other gives their agreement.
+        >>> quickner.evaluate(gold).overall.f1
+        0.875
+
+        Parameters:
+            reference (Quickner): Reference corpus.
+
+        Returns:
+            Evaluation: Overall and per-label precision, recall and F1 score.
+        """
+        ...
+
+    def validate(self) -> List[SpanError]:
+        """
+        Audit the spans of every document, e.g. after an import whose offsets may
+        be off by a few characters. The offsets are checked as by
+        Document.validate_spans, and the text of each span must be an entity with
+        the same label (compared in lowercase unless the filters are case sensitive).
+        Errors hint at byte offsets when the text read at the offsets as bytes is an entity.
+        >>> for error in quickner.validate():
+        ...     print(error.line, error.kind, error.message)
+
+        Returns:
+            List[SpanError]: Errors of the spans, empty when they are all valid.
+        """
+        ...
+
     def numpy(self) -> NDArray:  # noqa: F821
         """
         Convert the list of documents to a Numpy array.
@@ -382,3 +2243,14 @@ class Quickner:
             NDArray: Numpy array of documents.
         """
         ...
+    def spans_numpy(self) -> Tuple[List[str], NDArray]:  # noqa: F821
+        """
+        Export the spans as a structured numpy array, built in Rust without
+        a Python object per span, requires numpy to be installed.
+
+        Returns:
+            Tuple[List[str], NDArray]: The sorted labels, and one record per span
+            with the fields doc_idx (uint32), start (uint32), end (uint32) and
+            label_id (uint16), the index of the label in the labels.
+        """
+        ...
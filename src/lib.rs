@@ -1,18 +1,27 @@
 use pyo3::prelude::*;
 use pyquickner::QuicknerError;
+use std::{collections::HashMap, str::FromStr};
 mod pyconfig;
 mod pydocument;
 mod pyentity;
+mod pyextract;
+mod pyjson;
+mod pymatcher;
 mod pymodels;
+mod pyprogress;
 mod pyquickner;
-mod utils;
+mod pystats;
 
 /// Load data from JSONL and return a Quickner object
 /// Parse the annotations and entities from the JSONL file
+/// Field names can be remapped, e.g. fields={"text": "content"}
 #[pyfunction]
-fn from_jsonl(path: String) -> PyResult<pyquickner::PyQuickner> {
-    let quick = pyquickner::PyQuickner::from_jsonl(Some(&path));
-    Ok(quick)
+#[pyo3(signature = (path, fields = None))]
+fn from_jsonl(
+    path: String,
+    fields: Option<HashMap<String, String>>,
+) -> PyResult<pyquickner::PyQuickner> {
+    pyquickner::PyQuickner::from_jsonl(Some(&path), fields)
 }
 
 /// Load data from Spacy JSON format and return a Quickner object
@@ -23,16 +32,145 @@ fn from_spacy(path: String) -> PyResult<pyquickner::PyQuickner> {
     Ok(quick)
 }
 
+/// Import every annotation file of a directory and return a Quickner object
+#[pyfunction]
+fn from_dir(path: String) -> PyResult<pyquickner::PyQuickner> {
+    pyquickner::PyQuickner::from_dir(&path)
+}
+
+/// Load data from a SQLite database and return a Quickner object
+#[pyfunction]
+fn from_sqlite(path: String) -> PyResult<pyquickner::PyQuickner> {
+    pyquickner::PyQuickner::from_sqlite(&path)
+}
+
+/// Set the number of characters of the texts shown in the representations,
+/// the pretty prints and the logs, 0 shows the full texts
+#[pyfunction]
+fn set_preview_length(length: usize) {
+    ::quickner::set_preview_length(length);
+}
+
+/// Number of characters of the texts shown in the representations and logs
+#[pyfunction]
+fn get_preview_length() -> usize {
+    ::quickner::preview_length()
+}
+
+/// Set the colors and names of the labels in the pretty prints and the HTML pages,
+/// e.g. {"ORG": {"color": "blue", "name": "Organization"}}
+#[pyfunction]
+fn set_label_display(py: Python, display: &PyAny) -> PyResult<()> {
+    ::quickner::set_label_display(pyjson::from_python(py, display)?);
+    Ok(())
+}
+
+/// Convert the character offsets of a span, as used by the documents,
+/// to byte offsets in the UTF-8 encoded text
+#[pyfunction]
+fn char_to_byte(text: &str, start: usize, end: usize) -> (usize, usize) {
+    ::quickner::char_to_byte(text, start, end)
+}
+
+/// Convert the byte offsets of a span in the UTF-8 encoded text to character offsets
+#[pyfunction]
+fn byte_to_char(text: &str, start: usize, end: usize) -> (usize, usize) {
+    ::quickner::byte_to_char(text, start, end)
+}
+
+/// Find the abbreviations defined in a text, e.g. "World Health Organization (WHO)",
+/// as (short form, long form) pairs
+#[pyfunction]
+fn find_abbreviations(text: &str) -> Vec<(String, String)> {
+    ::quickner::find_abbreviations(text)
+        .into_iter()
+        .map(|abbreviation| (abbreviation.short, abbreviation.long))
+        .collect()
+}
+
+/// Annotate texts with the entities in parallel, the entities are compiled once
+#[pyfunction]
+#[pyo3(signature = (texts, entities, n_jobs = -1, case_sensitive = false))]
+fn annotate_batch(
+    py: Python,
+    texts: Vec<String>,
+    entities: Vec<pyentity::PyEntity>,
+    n_jobs: isize,
+    case_sensitive: bool,
+) -> PyResult<Vec<pydocument::PyDocument>> {
+    pymatcher::annotate_batch(py, texts, entities, n_jobs, case_sensitive)
+}
+
+/// Install a logger writing to stderr, quickner installs none by itself.
+/// The format is "text" or "json", one object per line with the timings and
+/// counts of the stages of a run. Returns False if a logger is already installed
+#[pyfunction]
+#[pyo3(signature = (level = "info", format = "text"))]
+fn init_logging(level: &str, format: &str) -> PyResult<bool> {
+    let format = ::quickner::LogFormat::from_str(format).map_err(QuicknerError::new_err)?;
+    Ok(::quickner::init_logging(level, format).is_ok())
+}
+
 /// A Python module implemented in Rust.
 #[pymodule]
 fn quickner(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_wrapped(wrap_pyfunction!(from_jsonl))?;
     m.add_wrapped(wrap_pyfunction!(from_spacy))?;
+    m.add_wrapped(wrap_pyfunction!(from_dir))?;
+    m.add_wrapped(wrap_pyfunction!(from_sqlite))?;
+    m.add_wrapped(wrap_pyfunction!(set_preview_length))?;
+    m.add_wrapped(wrap_pyfunction!(get_preview_length))?;
+    m.add_wrapped(wrap_pyfunction!(set_label_display))?;
+    m.add_wrapped(wrap_pyfunction!(char_to_byte))?;
+    m.add_wrapped(wrap_pyfunction!(byte_to_char))?;
+    m.add_wrapped(wrap_pyfunction!(find_abbreviations))?;
+    m.add_wrapped(wrap_pyfunction!(annotate_batch))?;
+    m.add_wrapped(wrap_pyfunction!(init_logging))?;
     m.add_class::<pyquickner::PyQuickner>()?;
+    m.add_class::<pyprogress::PyProcessTask>()?;
+    m.add_class::<pyquickner::PyDocuments>()?;
     m.add_class::<pyconfig::PyConfig>()?;
     m.add_class::<pyconfig::PyFormat>()?;
+    m.add_class::<pyconfig::PyFields>()?;
+    m.add_class::<pyconfig::PyJsonOptions>()?;
+    m.add_class::<pyconfig::PyHtmlOptions>()?;
+    m.add_class::<pyconfig::PyNifOptions>()?;
+    m.add_class::<pyconfig::PyStreaming>()?;
+    m.add_class::<pyconfig::PyCheckpoint>()?;
+    m.add_class::<pyconfig::PyPerformance>()?;
+    m.add_class::<pyconfig::PyPerLabel>()?;
+    m.add_class::<pyconfig::PyLabels>()?;
+    m.add_class::<pyconfig::PyLabelPriority>()?;
+    m.add_class::<pyconfig::PyLabelDisplay>()?;
+    m.add_class::<pyconfig::PyLabelPrecision>()?;
+    m.add_class::<pyconfig::PyLabelAggregation>()?;
+    m.add_class::<pyconfig::PyMatching>()?;
+    m.add_class::<pyconfig::PyFuzzy>()?;
+    m.add_class::<pyconfig::PyRoute>()?;
+    m.add_class::<pyconfig::PyRouteEntities>()?;
+    m.add_class::<pyconfig::PyTags>()?;
+    m.add_class::<pyconfig::PyRule>()?;
+    m.add_class::<pyconfig::PyRelationPattern>()?;
     m.add_class::<pydocument::PyDocument>()?;
     m.add_class::<pyentity::PyEntity>()?;
+    m.add_class::<pymatcher::PyMatcher>()?;
+    m.add_class::<pystats::PyStats>()?;
+    m.add_class::<pystats::PyLabelStats>()?;
+    m.add_class::<pystats::PyGazetteerStats>()?;
+    m.add_class::<pystats::PyPeriodStats>()?;
+    m.add_class::<pystats::PyTimeStats>()?;
+    m.add_class::<pystats::PyLabelSuggestion>()?;
+    m.add_class::<pystats::PyEntityCandidate>()?;
+    m.add_class::<pystats::PyCooccurrences>()?;
+    m.add_class::<pystats::PyEvaluation>()?;
+    m.add_class::<pystats::PyMetrics>()?;
+    m.add_class::<pystats::PySpanError>()?;
+    m.add_class::<pystats::PySpan>()?;
+    m.add_class::<pystats::PyRunReport>()?;
+    m.add_class::<pystats::PyDuplicateCluster>()?;
+    m.add_class::<pystats::PyMergeReport>()?;
+    m.add_class::<pystats::PyAggregation>()?;
+    m.add_class::<pystats::PyPruneReport>()?;
     m.add("QuicknerError", _py.get_type::<QuicknerError>())?;
     Ok(())
 }
@@ -1,12 +1,21 @@
 use pyo3::prelude::*;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{Display, Formatter},
+    str::FromStr,
 };
 
-use crate::utils::{colorize, TermColor};
+use crate::{
+    pyjson::{from_python, to_python},
+    pyquickner::QuicknerError,
+};
 use quickner::{
-    Annotations, Config, Entities, Excludes, Filters, Format, Input, Logging, Output, Texts,
+    colorize, AggregationStrategy, Annotations, Checkpoint, Compression, Config, DocumentSelection,
+    Entities, EntitiesFormat, Excludes, Fields, Filters, Format, Fuzzy, HtmlOptions, IdStrategy,
+    Input, JsonOptions, LabelAggregation, LabelDisplay, LabelPrecision, LabelPriority, Labels,
+    Language, LogFormat, Logging, Matching, NifOptions, Output, PerLabel, Performance,
+    RelationPattern, Route, RouteEntities, Rule, RuleAction, SpanSource, Split, Streaming, Tags,
+    TermColor, Texts, VerifiedPolicy,
 };
 use serde::{Deserialize, Serialize};
 
@@ -21,6 +30,22 @@ pub struct PyConfig {
     pub entities: PyEntities,
     #[pyo3(get)]
     pub logging: Option<PyLogging>,
+    #[pyo3(get)]
+    pub labels: PyLabels,
+    #[pyo3(get)]
+    pub matching: PyMatching,
+    #[pyo3(get)]
+    pub checkpoint: Option<PyCheckpoint>,
+    #[pyo3(get)]
+    pub routes: Vec<PyRoute>,
+    #[pyo3(get)]
+    pub rules: Vec<PyRule>,
+    #[pyo3(get)]
+    pub relations: Vec<PyRelationPattern>,
+    #[pyo3(get)]
+    pub performance: PyPerformance,
+    #[pyo3(get)]
+    pub tags: Option<PyTags>,
 }
 
 impl Default for PyConfig {
@@ -30,6 +55,13 @@ impl Default for PyConfig {
                 input: PyInput {
                     path: "None".to_string(),
                     filter: None,
+                    format: None,
+                    split: None,
+                    limit: None,
+                    sample: None,
+                    seed: 0,
+                    dedupe: None,
+                    id: None,
                 },
                 filters: PyFilters {
                     alphanumeric: false,
@@ -41,11 +73,29 @@ impl Default for PyConfig {
                     special_characters: false,
                     accept_special_characters: None,
                     list_of_special_characters: None,
+                    min_tokens: None,
+                    max_tokens: None,
                 },
             },
             annotations: PyAnnotations {
                 output: PyOutput {
                     path: "None".to_string(),
+                    fields: PyFields::from(Fields::default()),
+                    json: PyJsonOptions::from(JsonOptions::default()),
+                    html: PyHtmlOptions::from(HtmlOptions::default()),
+                    nif: PyNifOptions::from(NifOptions::default()),
+                    documents: DocumentSelection::default().to_string(),
+                    min_label_count: None,
+                    rare_label: None,
+                    min_confidence: None,
+                    frozen_labels: None,
+                    create_dirs: true,
+                    streaming: None,
+                    compression: Compression::default().to_string(),
+                    per_label: None,
+                    split_by_label: false,
+                    stamp: false,
+                    report: true,
                 },
                 format: PyFormat::SPACY,
             },
@@ -53,6 +103,13 @@ impl Default for PyConfig {
                 input: PyInput {
                     path: "None".to_string(),
                     filter: None,
+                    format: None,
+                    split: None,
+                    limit: None,
+                    sample: None,
+                    seed: 0,
+                    dedupe: None,
+                    id: None,
                 },
                 filters: PyFilters {
                     alphanumeric: false,
@@ -64,10 +121,20 @@ impl Default for PyConfig {
                     special_characters: false,
                     accept_special_characters: None,
                     list_of_special_characters: None,
+                    min_tokens: None,
+                    max_tokens: None,
                 },
                 excludes: PyExcludes { path: None },
             },
             logging: None,
+            labels: PyLabels::from(Labels::default()),
+            matching: PyMatching::from(Matching::default()),
+            checkpoint: None,
+            routes: Vec::new(),
+            rules: Vec::new(),
+            relations: Vec::new(),
+            performance: PyPerformance::from(Performance::default()),
+            tags: None,
         }
     }
 }
@@ -86,6 +153,323 @@ pub struct PyAnnotations {
 pub struct PyOutput {
     #[pyo3(get)]
     pub path: String,
+    #[pyo3(get)]
+    pub fields: PyFields,
+    #[pyo3(get)]
+    pub json: PyJsonOptions,
+    #[pyo3(get)]
+    pub html: PyHtmlOptions,
+    #[pyo3(get)]
+    pub nif: PyNifOptions,
+    #[pyo3(get)]
+    pub documents: String,
+    #[pyo3(get)]
+    pub min_label_count: Option<usize>,
+    #[pyo3(get)]
+    pub rare_label: Option<String>,
+    #[pyo3(get)]
+    pub min_confidence: Option<PyFraction>,
+    #[pyo3(get)]
+    pub frozen_labels: Option<String>,
+    #[pyo3(get)]
+    pub create_dirs: bool,
+    #[pyo3(get)]
+    pub streaming: Option<PyStreaming>,
+    #[pyo3(get)]
+    pub compression: String,
+    #[pyo3(get)]
+    pub per_label: Option<PyPerLabel>,
+    #[pyo3(get)]
+    pub split_by_label: bool,
+    #[pyo3(get)]
+    pub stamp: bool,
+    #[pyo3(get)]
+    pub report: bool,
+}
+
+impl From<Output> for PyOutput {
+    fn from(output: Output) -> Self {
+        PyOutput {
+            path: output.path,
+            fields: PyFields::from(output.fields),
+            json: PyJsonOptions::from(output.json),
+            html: PyHtmlOptions::from(output.html),
+            nif: PyNifOptions::from(output.nif),
+            documents: output.documents.to_string(),
+            min_label_count: output.min_label_count,
+            rare_label: output.rare_label,
+            min_confidence: output.min_confidence.map(PyFraction),
+            frozen_labels: output.frozen_labels,
+            create_dirs: output.create_dirs,
+            streaming: output.streaming.map(PyStreaming::from),
+            compression: output.compression.to_string(),
+            per_label: output.per_label.map(PyPerLabel::from),
+            split_by_label: output.split_by_label,
+            stamp: output.stamp,
+            report: output.report,
+        }
+    }
+}
+
+impl From<PyOutput> for Output {
+    fn from(output: PyOutput) -> Self {
+        Output {
+            path: output.path,
+            fields: Fields::from(output.fields),
+            json: JsonOptions::from(output.json),
+            html: HtmlOptions::from(output.html),
+            nif: NifOptions::from(output.nif),
+            documents: DocumentSelection::from_str(&output.documents).unwrap_or_default(),
+            min_label_count: output.min_label_count,
+            rare_label: output.rare_label,
+            min_confidence: output.min_confidence.map(|confidence| confidence.0),
+            frozen_labels: output.frozen_labels,
+            create_dirs: output.create_dirs,
+            streaming: output.streaming.map(Streaming::from),
+            compression: Compression::from_str(&output.compression).unwrap_or_default(),
+            per_label: output.per_label.map(PerLabel::from),
+            split_by_label: output.split_by_label,
+            stamp: output.stamp,
+            report: output.report,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "PerLabel")]
+pub struct PyPerLabel {
+    #[pyo3(get)]
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+impl From<PerLabel> for PyPerLabel {
+    fn from(per_label: PerLabel) -> Self {
+        PyPerLabel {
+            groups: per_label.groups.into_iter().collect(),
+        }
+    }
+}
+
+impl From<PyPerLabel> for PerLabel {
+    fn from(per_label: PyPerLabel) -> Self {
+        PerLabel {
+            groups: per_label.groups.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Streaming")]
+pub struct PyStreaming {
+    #[pyo3(get)]
+    pub queue_size: usize,
+}
+
+impl From<Streaming> for PyStreaming {
+    fn from(streaming: Streaming) -> Self {
+        PyStreaming {
+            queue_size: streaming.queue_size,
+        }
+    }
+}
+
+impl From<PyStreaming> for Streaming {
+    fn from(streaming: PyStreaming) -> Self {
+        Streaming {
+            queue_size: streaming.queue_size,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Checkpoint")]
+pub struct PyCheckpoint {
+    #[pyo3(get)]
+    pub every: usize,
+    #[pyo3(get)]
+    pub path: Option<String>,
+    #[pyo3(get)]
+    pub resume: bool,
+}
+
+impl From<Checkpoint> for PyCheckpoint {
+    fn from(checkpoint: Checkpoint) -> Self {
+        PyCheckpoint {
+            every: checkpoint.every,
+            path: checkpoint.path,
+            resume: checkpoint.resume,
+        }
+    }
+}
+
+impl From<PyCheckpoint> for Checkpoint {
+    fn from(checkpoint: PyCheckpoint) -> Self {
+        Checkpoint {
+            every: checkpoint.every,
+            path: checkpoint.path,
+            resume: checkpoint.resume,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Performance")]
+pub struct PyPerformance {
+    #[pyo3(get)]
+    pub num_threads: Option<usize>,
+}
+
+impl From<Performance> for PyPerformance {
+    fn from(performance: Performance) -> Self {
+        PyPerformance {
+            num_threads: performance.num_threads,
+        }
+    }
+}
+
+impl From<PyPerformance> for Performance {
+    fn from(performance: PyPerformance) -> Self {
+        Performance {
+            num_threads: performance.num_threads,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "JsonOptions")]
+pub struct PyJsonOptions {
+    #[pyo3(get)]
+    pub pretty: bool,
+    #[pyo3(get)]
+    pub indent: usize,
+    #[pyo3(get)]
+    pub ensure_ascii: bool,
+}
+
+impl From<JsonOptions> for PyJsonOptions {
+    fn from(options: JsonOptions) -> Self {
+        PyJsonOptions {
+            pretty: options.pretty,
+            indent: options.indent,
+            ensure_ascii: options.ensure_ascii,
+        }
+    }
+}
+
+impl From<PyJsonOptions> for JsonOptions {
+    fn from(options: PyJsonOptions) -> Self {
+        JsonOptions {
+            pretty: options.pretty,
+            indent: options.indent,
+            ensure_ascii: options.ensure_ascii,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "HtmlOptions")]
+pub struct PyHtmlOptions {
+    #[pyo3(get)]
+    pub title: String,
+    #[pyo3(get)]
+    pub documents_per_page: Option<usize>,
+}
+
+impl From<HtmlOptions> for PyHtmlOptions {
+    fn from(options: HtmlOptions) -> Self {
+        PyHtmlOptions {
+            title: options.title,
+            documents_per_page: options.documents_per_page,
+        }
+    }
+}
+
+impl From<PyHtmlOptions> for HtmlOptions {
+    fn from(options: PyHtmlOptions) -> Self {
+        HtmlOptions {
+            title: options.title,
+            documents_per_page: options.documents_per_page,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "NifOptions")]
+pub struct PyNifOptions {
+    #[pyo3(get)]
+    pub base_uri: String,
+    #[pyo3(get)]
+    pub kb_uri: Option<String>,
+}
+
+impl From<NifOptions> for PyNifOptions {
+    fn from(options: NifOptions) -> Self {
+        PyNifOptions {
+            base_uri: options.base_uri,
+            kb_uri: options.kb_uri,
+        }
+    }
+}
+
+impl From<PyNifOptions> for NifOptions {
+    fn from(options: PyNifOptions) -> Self {
+        NifOptions {
+            base_uri: options.base_uri,
+            kb_uri: options.kb_uri,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Fields")]
+pub struct PyFields {
+    #[pyo3(get)]
+    pub id: String,
+    #[pyo3(get)]
+    pub text: String,
+    #[pyo3(get)]
+    pub label: String,
+}
+
+impl From<Fields> for PyFields {
+    fn from(fields: Fields) -> Self {
+        PyFields {
+            id: fields.id,
+            text: fields.text,
+            label: fields.label,
+        }
+    }
+}
+
+impl From<PyFields> for Fields {
+    fn from(fields: PyFields) -> Self {
+        Fields {
+            id: fields.id,
+            text: fields.text,
+            label: fields.label,
+        }
+    }
+}
+
+impl PyFields {
+    /// Build the field names from a mapping of default names to custom names,
+    /// e.g. {"text": "content", "label": "labels"}, an unknown default name is an error
+    pub(crate) fn from_mapping(mapping: HashMap<String, String>) -> PyResult<PyFields> {
+        let mut fields = PyFields::from(Fields::default());
+        for (field, name) in mapping {
+            match field.as_str() {
+                "id" => fields.id = name,
+                "text" => fields.text = name,
+                "label" => fields.label = name,
+                _ => {
+                    return Err(QuicknerError::new_err(format!(
+                        "Unknown field {field:?}, expected \"id\", \"text\" or \"label\""
+                    )))
+                }
+            }
+        }
+        Ok(fields)
+    }
 }
 
 #[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
@@ -106,11 +490,390 @@ pub struct PyExcludes {
     pub path: Option<String>,
 }
 
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Route")]
+pub struct PyRoute {
+    #[pyo3(get)]
+    pub when: BTreeMap<String, String>,
+    #[pyo3(get)]
+    pub entities: PyRouteEntities,
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "RouteEntities")]
+pub struct PyRouteEntities {
+    #[pyo3(get)]
+    pub input: PyInput,
+    #[pyo3(get)]
+    pub filters: Option<PyFilters>,
+}
+
+impl From<Route> for PyRoute {
+    fn from(route: Route) -> Self {
+        PyRoute {
+            when: route.when.into_iter().collect(),
+            entities: PyRouteEntities {
+                input: PyInput::from(route.entities.input),
+                filters: route.entities.filters.map(PyFilters::from),
+            },
+        }
+    }
+}
+
+impl From<PyRoute> for Route {
+    fn from(route: PyRoute) -> Self {
+        Route {
+            when: route.when.into_iter().collect(),
+            entities: RouteEntities {
+                input: Input::from(route.entities.input),
+                filters: route.entities.filters.map(Filters::from),
+            },
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Tags")]
+pub struct PyTags {
+    #[pyo3(get)]
+    pub input: PyInput,
+    #[pyo3(get)]
+    pub filters: Option<PyFilters>,
+    #[pyo3(get)]
+    pub min_matches: usize,
+}
+
+impl From<Tags> for PyTags {
+    fn from(tags: Tags) -> Self {
+        PyTags {
+            input: PyInput::from(tags.input),
+            filters: tags.filters.map(PyFilters::from),
+            min_matches: tags.min_matches,
+        }
+    }
+}
+
+impl From<PyTags> for Tags {
+    fn from(tags: PyTags) -> Self {
+        Tags {
+            input: Input::from(tags.input),
+            filters: tags.filters.map(Filters::from),
+            min_matches: tags.min_matches,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Rule")]
+pub struct PyRule {
+    #[pyo3(get)]
+    pub pattern: String,
+    #[pyo3(get)]
+    pub label: String,
+    #[pyo3(get)]
+    pub require: Vec<String>,
+    #[pyo3(get)]
+    pub forbid: Vec<String>,
+    #[pyo3(get)]
+    pub window: usize,
+    #[pyo3(get)]
+    pub action: String,
+    #[pyo3(get)]
+    pub to: Option<String>,
+}
+
+impl From<Rule> for PyRule {
+    fn from(rule: Rule) -> Self {
+        PyRule {
+            pattern: rule.pattern,
+            label: rule.label,
+            require: rule.require,
+            forbid: rule.forbid,
+            window: rule.window,
+            action: rule.action.to_string(),
+            to: rule.to,
+        }
+    }
+}
+
+impl From<PyRule> for Rule {
+    fn from(rule: PyRule) -> Self {
+        Rule {
+            pattern: rule.pattern,
+            label: rule.label,
+            require: rule.require,
+            forbid: rule.forbid,
+            window: rule.window,
+            action: RuleAction::from_str(&rule.action).unwrap_or_default(),
+            to: rule.to,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "RelationPattern")]
+pub struct PyRelationPattern {
+    #[pyo3(get)]
+    pub label: String,
+    #[pyo3(get)]
+    pub head: Option<String>,
+    #[pyo3(get)]
+    pub tail: Option<String>,
+    #[pyo3(get)]
+    pub pattern: Option<String>,
+    #[pyo3(get)]
+    pub window: usize,
+}
+
+impl From<RelationPattern> for PyRelationPattern {
+    fn from(relation: RelationPattern) -> Self {
+        PyRelationPattern {
+            label: relation.label,
+            head: relation.head,
+            tail: relation.tail,
+            pattern: relation.pattern,
+            window: relation.window,
+        }
+    }
+}
+
+impl From<PyRelationPattern> for RelationPattern {
+    fn from(relation: PyRelationPattern) -> Self {
+        RelationPattern {
+            label: relation.label,
+            head: relation.head,
+            tail: relation.tail,
+            pattern: relation.pattern,
+            window: relation.window,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Labels")]
+pub struct PyLabels {
+    #[pyo3(get)]
+    pub priority: PyLabelPriority,
+    #[pyo3(get)]
+    pub display: BTreeMap<String, PyLabelDisplay>,
+    #[pyo3(get)]
+    pub precision: BTreeMap<String, PyLabelPrecision>,
+    #[pyo3(get)]
+    pub aggregation: PyLabelAggregation,
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "LabelAggregation")]
+pub struct PyLabelAggregation {
+    #[pyo3(get)]
+    pub strategy: String,
+    #[pyo3(get)]
+    pub weights: BTreeMap<String, PyFraction>,
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "LabelPrecision")]
+pub struct PyLabelPrecision {
+    #[pyo3(get)]
+    pub title_case: bool,
+    #[pyo3(get)]
+    pub min_mentions: usize,
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "LabelDisplay")]
+pub struct PyLabelDisplay {
+    #[pyo3(get)]
+    pub color: Option<String>,
+    #[pyo3(get)]
+    pub name: Option<String>,
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "LabelPriority")]
+pub struct PyLabelPriority {
+    #[pyo3(get)]
+    pub order: Vec<String>,
+}
+
+impl From<Labels> for PyLabels {
+    fn from(labels: Labels) -> Self {
+        PyLabels {
+            priority: PyLabelPriority {
+                order: labels.priority.order,
+            },
+            display: labels
+                .display
+                .into_iter()
+                .map(|(label, display)| {
+                    (
+                        label,
+                        PyLabelDisplay {
+                            color: display.color.map(|color| color.to_string()),
+                            name: display.name,
+                        },
+                    )
+                })
+                .collect(),
+            precision: labels
+                .precision
+                .into_iter()
+                .map(|(label, precision)| {
+                    (
+                        label,
+                        PyLabelPrecision {
+                            title_case: precision.title_case,
+                            min_mentions: precision.min_mentions,
+                        },
+                    )
+                })
+                .collect(),
+            aggregation: PyLabelAggregation {
+                strategy: labels.aggregation.strategy.to_string(),
+                weights: labels
+                    .aggregation
+                    .weights
+                    .into_iter()
+                    .map(|(source, weight)| (source.to_string(), PyFraction(weight)))
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl From<PyLabels> for Labels {
+    fn from(labels: PyLabels) -> Self {
+        Labels {
+            priority: LabelPriority {
+                order: labels.priority.order,
+            },
+            display: labels
+                .display
+                .into_iter()
+                .map(|(label, display)| {
+                    (
+                        label,
+                        LabelDisplay {
+                            color: display
+                                .color
+                                .and_then(|color| TermColor::from_str(&color).ok()),
+                            name: display.name,
+                        },
+                    )
+                })
+                .collect(),
+            precision: labels
+                .precision
+                .into_iter()
+                .map(|(label, precision)| {
+                    (
+                        label,
+                        LabelPrecision {
+                            title_case: precision.title_case,
+                            min_mentions: precision.min_mentions,
+                        },
+                    )
+                })
+                .collect(),
+            aggregation: LabelAggregation {
+                strategy: AggregationStrategy::from_str(&labels.aggregation.strategy)
+                    .unwrap_or_default(),
+                weights: labels
+                    .aggregation
+                    .weights
+                    .into_iter()
+                    .filter_map(|(source, weight)| {
+                        SpanSource::from_str(&source)
+                            .ok()
+                            .map(|source| (source, weight.0))
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Matching")]
+pub struct PyMatching {
+    #[pyo3(get)]
+    pub fold_diacritics: bool,
+    #[pyo3(get)]
+    pub match_plurals: bool,
+    #[pyo3(get)]
+    pub language: String,
+    #[pyo3(get)]
+    pub abbreviations: bool,
+    #[pyo3(get)]
+    pub propagate_mentions: bool,
+    #[pyo3(get)]
+    pub fuzzy: Option<PyFuzzy>,
+    #[pyo3(get)]
+    pub verified: String,
+}
+
+#[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
+#[pyclass(name = "Fuzzy")]
+pub struct PyFuzzy {
+    #[pyo3(get)]
+    pub max_edits: usize,
+    #[pyo3(get)]
+    pub min_len: usize,
+}
+
+impl From<Fuzzy> for PyFuzzy {
+    fn from(fuzzy: Fuzzy) -> Self {
+        PyFuzzy {
+            max_edits: fuzzy.max_edits,
+            min_len: fuzzy.min_len,
+        }
+    }
+}
+
+impl From<PyFuzzy> for Fuzzy {
+    fn from(fuzzy: PyFuzzy) -> Self {
+        Fuzzy {
+            max_edits: fuzzy.max_edits,
+            min_len: fuzzy.min_len,
+        }
+    }
+}
+
+impl From<Matching> for PyMatching {
+    fn from(matching: Matching) -> Self {
+        PyMatching {
+            fold_diacritics: matching.fold_diacritics,
+            match_plurals: matching.match_plurals,
+            language: matching.language.to_string(),
+            abbreviations: matching.abbreviations,
+            propagate_mentions: matching.propagate_mentions,
+            fuzzy: matching.fuzzy.map(PyFuzzy::from),
+            verified: matching.verified.to_string(),
+        }
+    }
+}
+
+impl From<PyMatching> for Matching {
+    fn from(matching: PyMatching) -> Self {
+        Matching {
+            fold_diacritics: matching.fold_diacritics,
+            match_plurals: matching.match_plurals,
+            language: Language::from_str(&matching.language).unwrap_or_default(),
+            abbreviations: matching.abbreviations,
+            propagate_mentions: matching.propagate_mentions,
+            fuzzy: matching.fuzzy.map(Fuzzy::from),
+            verified: VerifiedPolicy::from_str(&matching.verified).unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
 #[pyclass(name = "Logging")]
 pub struct PyLogging {
     #[pyo3(get)]
     pub level: String,
+    #[pyo3(get)]
+    pub format: String,
 }
 
 #[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
@@ -129,6 +892,78 @@ pub struct PyInput {
     pub path: String,
     #[pyo3(get)]
     pub filter: Option<bool>,
+    #[pyo3(get)]
+    pub format: Option<String>,
+    #[pyo3(get)]
+    pub split: Option<String>,
+    #[pyo3(get)]
+    pub limit: Option<usize>,
+    #[pyo3(get)]
+    pub sample: Option<PyFraction>,
+    #[pyo3(get)]
+    pub seed: u64,
+    #[pyo3(get)]
+    pub dedupe: Option<PyFraction>,
+    #[pyo3(get)]
+    pub id: Option<String>,
+}
+
+/// A fraction of the config, wrapped to compare and hash it with the other fields
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PyFraction(pub f64);
+
+impl PartialEq for PyFraction {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for PyFraction {}
+
+impl std::hash::Hash for PyFraction {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl IntoPy<PyObject> for PyFraction {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        self.0.into_py(py)
+    }
+}
+
+impl From<Input> for PyInput {
+    fn from(input: Input) -> Self {
+        PyInput {
+            path: input.path,
+            filter: input.filter,
+            format: input.format.map(|format| format.to_string()),
+            split: input.split.map(|split| split.to_string()),
+            limit: input.limit,
+            sample: input.sample.map(PyFraction),
+            seed: input.seed,
+            dedupe: input.dedupe.map(PyFraction),
+            id: input.id.map(|id| id.to_string()),
+        }
+    }
+}
+
+impl From<PyInput> for Input {
+    fn from(input: PyInput) -> Self {
+        Input {
+            path: input.path,
+            filter: input.filter,
+            format: input
+                .format
+                .and_then(|format| EntitiesFormat::from_str(&format).ok()),
+            split: input.split.and_then(|split| Split::from_str(&split).ok()),
+            limit: input.limit,
+            sample: input.sample.map(|sample| sample.0),
+            seed: input.seed,
+            dedupe: input.dedupe.map(|dedupe| dedupe.0),
+            id: input.id.and_then(|id| IdStrategy::from_str(&id).ok()),
+        }
+    }
 }
 
 #[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
@@ -152,6 +987,50 @@ pub struct PyFilters {
     pub accept_special_characters: Option<String>,
     #[pyo3(get)]
     pub list_of_special_characters: Option<Vec<char>>,
+    #[pyo3(get)]
+    pub min_tokens: Option<usize>,
+    #[pyo3(get)]
+    pub max_tokens: Option<usize>,
+}
+
+impl From<Filters> for PyFilters {
+    fn from(filters: Filters) -> Self {
+        PyFilters {
+            alphanumeric: filters.alphanumeric,
+            case_sensitive: filters.case_sensitive,
+            min_length: filters.min_length,
+            max_length: filters.max_length,
+            punctuation: filters.punctuation,
+            numbers: filters.numbers,
+            special_characters: filters.special_characters,
+            accept_special_characters: filters.accept_special_characters,
+            list_of_special_characters: filters
+                .list_of_special_characters
+                .map(|list| list.into_iter().collect::<Vec<char>>()),
+            min_tokens: filters.min_tokens,
+            max_tokens: filters.max_tokens,
+        }
+    }
+}
+
+impl From<PyFilters> for Filters {
+    fn from(filters: PyFilters) -> Self {
+        Filters {
+            alphanumeric: filters.alphanumeric,
+            case_sensitive: filters.case_sensitive,
+            min_length: filters.min_length,
+            max_length: filters.max_length,
+            punctuation: filters.punctuation,
+            numbers: filters.numbers,
+            special_characters: filters.special_characters,
+            accept_special_characters: filters.accept_special_characters,
+            list_of_special_characters: filters
+                .list_of_special_characters
+                .map(|list| list.into_iter().collect::<HashSet<char>>()),
+            min_tokens: filters.min_tokens,
+            max_tokens: filters.max_tokens,
+        }
+    }
 }
 
 impl Display for PyFilters {
@@ -166,7 +1045,9 @@ impl Display for PyFilters {
             \tnumbers: {},
             \tspecial_characters: {},
             \taccept_special_characters: {},
-            \tlist_of_special_characters: {}",
+            \tlist_of_special_characters: {},
+            \tmin_tokens: {:?},
+            \tmax_tokens: {:?}",
             self.alphanumeric,
             self.case_sensitive,
             self.min_length,
@@ -181,7 +1062,9 @@ impl Display for PyFilters {
                 .as_ref()
                 .unwrap_or(&vec![' '])
                 .iter()
-                .collect::<String>()
+                .collect::<String>(),
+            self.min_tokens,
+            self.max_tokens
         )
     }
 }
@@ -195,6 +1078,9 @@ pub enum PyFormat {
     SPACY,
     BRAT,
     CONLL,
+    HTML,
+    NIF,
+    TOKENS,
 }
 
 impl Display for PyFormat {
@@ -205,6 +1091,9 @@ impl Display for PyFormat {
             PyFormat::SPACY => write!(f, "spacy"),
             PyFormat::BRAT => write!(f, "brat"),
             PyFormat::CONLL => write!(f, "conll"),
+            PyFormat::HTML => write!(f, "html"),
+            PyFormat::NIF => write!(f, "nif"),
+            PyFormat::TOKENS => write!(f, "tokens"),
         }
     }
 }
@@ -212,15 +1101,67 @@ impl Display for PyFormat {
 #[pymethods]
 impl PyConfig {
     #[new]
-    #[pyo3(signature = (path = None))]
-    pub fn new(path: Option<&str>) -> PyResult<Self> {
+    #[pyo3(signature = (path = None, validate = true, profile = None))]
+    pub fn new(path: Option<&str>, validate: bool, profile: Option<&str>) -> PyResult<Self> {
         let path = match path {
             Some(path) => path.to_string(),
             None => "config.toml".to_string(),
         };
-        let config: Config = Config::from_file(path.as_str());
+        let config = match Config::try_from_file_with_profile(path.as_str(), profile) {
+            Ok(config) => config,
+            Err(error) => return Err(QuicknerError::new_err(error.to_string())),
+        };
+        if validate {
+            if let Err(errors) = config.validate() {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                return Err(QuicknerError::new_err(format!(
+                    "Invalid configuration file {}:\n  - {}",
+                    path,
+                    errors.join("\n  - ")
+                )));
+            }
+        }
         Ok(PyConfig::from_config(config))
     }
+
+    /// Create a configuration from a dict with the sections of the configuration file
+    #[staticmethod]
+    #[pyo3(signature = (data, validate = true))]
+    pub fn from_dict(py: Python, data: &PyAny, validate: bool) -> PyResult<Self> {
+        let config: Config = from_python(py, data)?;
+        if validate {
+            if let Err(errors) = config.validate() {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                return Err(QuicknerError::new_err(format!(
+                    "Invalid configuration:\n  - {}",
+                    errors.join("\n  - ")
+                )));
+            }
+        }
+        Ok(PyConfig::from_config(config))
+    }
+
+    /// Return the configuration as a dict with the sections of the configuration file
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_python(py, &PyConfig::to_config(self.clone()))
+    }
+
+    /// Save the configuration to a TOML file, read back by `Config(path)`
+    pub fn save(&self, path: &str) -> PyResult<()> {
+        PyConfig::to_config(self.clone())
+            .to_file(path)
+            .map_err(|e| QuicknerError::new_err(e.to_string()))
+    }
+
+    /// Check the configuration and return the problems found,
+    /// an empty list means the configuration is valid
+    pub fn validate(&self) -> Vec<String> {
+        match PyConfig::to_config(self.clone()).validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
     // Pretty print the config
     fn __repr__(&self) -> PyResult<String> {
         let mut output = String::new();
@@ -262,7 +1203,8 @@ impl PyConfig {
             self.logging
                 .as_ref()
                 .unwrap_or(&PyLogging {
-                    level: "None".to_string()
+                    level: "None".to_string(),
+                    format: "None".to_string()
                 })
                 .level
         ));
@@ -271,61 +1213,28 @@ impl PyConfig {
 }
 
 impl PyConfig {
-    fn from_config(config: Config) -> PyConfig {
+    pub(crate) fn from_config(config: Config) -> PyConfig {
         PyConfig {
             texts: PyTexts {
-                input: PyInput {
-                    path: config.texts.input.path,
-                    filter: config.texts.input.filter,
-                },
-                filters: PyFilters {
-                    alphanumeric: config.texts.filters.alphanumeric,
-                    case_sensitive: config.texts.filters.case_sensitive,
-                    min_length: config.texts.filters.min_length,
-                    max_length: config.texts.filters.max_length,
-                    punctuation: config.texts.filters.punctuation,
-                    numbers: config.texts.filters.numbers,
-                    special_characters: config.texts.filters.special_characters,
-                    accept_special_characters: config.texts.filters.accept_special_characters,
-                    list_of_special_characters: config
-                        .texts
-                        .filters
-                        .list_of_special_characters
-                        .map(|list| list.into_iter().collect::<Vec<char>>()),
-                },
+                input: PyInput::from(config.texts.input),
+                filters: PyFilters::from(config.texts.filters),
             },
             annotations: PyAnnotations {
-                output: PyOutput {
-                    path: config.annotations.output.path,
-                },
+                output: PyOutput::from(config.annotations.output),
                 format: match config.annotations.format {
                     quickner::Format::Csv => PyFormat::CSV,
                     quickner::Format::Jsonl => PyFormat::JSONL,
                     quickner::Format::Spacy => PyFormat::SPACY,
                     quickner::Format::Brat => PyFormat::BRAT,
                     quickner::Format::Conll => PyFormat::CONLL,
+                    quickner::Format::Html => PyFormat::HTML,
+                    quickner::Format::Nif => PyFormat::NIF,
+                    quickner::Format::Tokens => PyFormat::TOKENS,
                 },
             },
             entities: PyEntities {
-                input: PyInput {
-                    path: config.entities.input.path,
-                    filter: config.entities.input.filter,
-                },
-                filters: PyFilters {
-                    alphanumeric: config.entities.filters.alphanumeric,
-                    case_sensitive: config.entities.filters.case_sensitive,
-                    min_length: config.entities.filters.min_length,
-                    max_length: config.entities.filters.max_length,
-                    punctuation: config.entities.filters.punctuation,
-                    numbers: config.entities.filters.numbers,
-                    special_characters: config.entities.filters.special_characters,
-                    accept_special_characters: config.entities.filters.accept_special_characters,
-                    list_of_special_characters: config
-                        .entities
-                        .filters
-                        .list_of_special_characters
-                        .map(|list| list.into_iter().collect::<Vec<char>>()),
-                },
+                input: PyInput::from(config.entities.input),
+                filters: PyFilters::from(config.entities.filters),
                 excludes: PyExcludes {
                     path: config.entities.excludes.path,
                 },
@@ -333,67 +1242,47 @@ impl PyConfig {
             logging: match config.logging {
                 Some(logging) => Some(PyLogging {
                     level: logging.level,
+                    format: logging.format.to_string(),
                 }),
                 None => None,
             },
+            labels: PyLabels::from(config.labels),
+            matching: PyMatching::from(config.matching),
+            checkpoint: config.checkpoint.map(PyCheckpoint::from),
+            routes: config.routes.into_iter().map(PyRoute::from).collect(),
+            rules: config.rules.into_iter().map(PyRule::from).collect(),
+            relations: config
+                .relations
+                .into_iter()
+                .map(PyRelationPattern::from)
+                .collect(),
+            performance: PyPerformance::from(config.performance),
+            tags: config.tags.map(PyTags::from),
         }
     }
 
     pub(crate) fn to_config(config: PyConfig) -> Config {
         Config {
             texts: Texts {
-                input: Input {
-                    path: config.texts.input.path,
-                    filter: config.texts.input.filter,
-                },
-                filters: Filters {
-                    alphanumeric: config.texts.filters.alphanumeric,
-                    case_sensitive: config.texts.filters.case_sensitive,
-                    min_length: config.texts.filters.min_length,
-                    max_length: config.texts.filters.max_length,
-                    punctuation: config.texts.filters.punctuation,
-                    numbers: config.texts.filters.numbers,
-                    special_characters: config.texts.filters.special_characters,
-                    accept_special_characters: config.texts.filters.accept_special_characters,
-                    list_of_special_characters: config
-                        .texts
-                        .filters
-                        .list_of_special_characters
-                        .map(|list| list.into_iter().collect::<HashSet<char>>()),
-                },
+                input: Input::from(config.texts.input),
+                filters: Filters::from(config.texts.filters),
             },
             annotations: Annotations {
-                output: Output {
-                    path: config.annotations.output.path,
-                },
+                output: Output::from(config.annotations.output),
                 format: match config.annotations.format {
                     PyFormat::CSV => Format::Csv,
                     PyFormat::JSONL => Format::Jsonl,
                     PyFormat::SPACY => Format::Spacy,
                     PyFormat::BRAT => Format::Brat,
                     PyFormat::CONLL => Format::Conll,
+                    PyFormat::HTML => Format::Html,
+                    PyFormat::NIF => Format::Nif,
+                    PyFormat::TOKENS => Format::Tokens,
                 },
             },
             entities: Entities {
-                input: Input {
-                    path: config.entities.input.path,
-                    filter: config.entities.input.filter,
-                },
-                filters: Filters {
-                    alphanumeric: config.entities.filters.alphanumeric,
-                    case_sensitive: config.entities.filters.case_sensitive,
-                    min_length: config.entities.filters.min_length,
-                    max_length: config.entities.filters.max_length,
-                    punctuation: config.entities.filters.punctuation,
-                    numbers: config.entities.filters.numbers,
-                    special_characters: config.entities.filters.special_characters,
-                    accept_special_characters: config.entities.filters.accept_special_characters,
-                    list_of_special_characters: config
-                        .entities
-                        .filters
-                        .list_of_special_characters
-                        .map(|list| list.into_iter().collect::<HashSet<char>>()),
-                },
+                input: Input::from(config.entities.input),
+                filters: Filters::from(config.entities.filters),
                 excludes: Excludes {
                     path: config.entities.excludes.path,
                 },
@@ -401,9 +1290,22 @@ impl PyConfig {
             logging: match config.logging {
                 Some(logging) => Some(Logging {
                     level: logging.level,
+                    format: LogFormat::from_str(&logging.format).unwrap_or_default(),
                 }),
                 None => None,
             },
+            labels: Labels::from(config.labels),
+            matching: Matching::from(config.matching),
+            checkpoint: config.checkpoint.map(Checkpoint::from),
+            routes: config.routes.into_iter().map(Route::from).collect(),
+            rules: config.rules.into_iter().map(Rule::from).collect(),
+            relations: config
+                .relations
+                .into_iter()
+                .map(RelationPattern::from)
+                .collect(),
+            performance: Performance::from(config.performance),
+            tags: config.tags.map(Tags::from),
         }
     }
 }
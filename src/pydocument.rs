@@ -1,20 +1,53 @@
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+
 use crate::{
     pyentity::PyEntity,
-    utils::{colorize, TermColor},
+    pyjson::{from_python, to_python},
+    pyquickner::QuicknerError,
+    pystats::{PySpan, PySpanError},
 };
 use pyo3::prelude::*;
-use quickner::{hash_string, Document};
+use quickner::{hash_string, preview, Document, DocumentStatus, Relation, Span};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A span (start, end, label) of a document
+type SpanTuple = (usize, usize, String);
+
+fn is_machine(status: &DocumentStatus) -> bool {
+    !status.is_verified()
+}
 
 #[derive(Eq, PartialEq, Serialize, Deserialize, Clone, Hash, Debug)]
 #[pyclass(name = "Document")]
 pub struct PyDocument {
+    /// Hash of the text when missing from a dict
     #[pyo3(get)]
+    #[serde(default)]
     pub id: String,
+    pub text: Arc<str>,
     #[pyo3(get)]
-    pub text: String,
-    #[pyo3(get)]
+    #[serde(default)]
     pub label: Vec<(usize, usize, String)>,
+    #[pyo3(get)]
+    #[serde(default)]
+    pub meta: BTreeMap<String, String>,
+    /// Provenance of the spans, see `spans`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub span_meta: Vec<Span>,
+    /// Relations between the spans, see `relations`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relations: Vec<Relation>,
+    /// Tags of the document as a whole, e.g. its topics
+    #[pyo3(get)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Whether the spans were checked by a human, see `status`
+    #[serde(default, skip_serializing_if = "is_machine")]
+    pub status: DocumentStatus,
+    /// Fields of an imported JSONL record that are not fields of a document, see `extra`
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl From<PyDocument> for Document {
@@ -23,6 +56,13 @@ impl From<PyDocument> for Document {
             id: document.id,
             text: document.text,
             label: document.label,
+            meta: document.meta.into_iter().collect(),
+            entity_meta: Vec::new(),
+            span_meta: document.span_meta,
+            relations: document.relations,
+            tags: document.tags,
+            status: document.status,
+            extra: document.extra,
         }
     }
 }
@@ -33,6 +73,12 @@ impl From<Document> for PyDocument {
             id: document.id,
             text: document.text,
             label: document.label,
+            meta: document.meta.into_iter().collect(),
+            span_meta: document.span_meta,
+            relations: document.relations,
+            tags: document.tags,
+            status: document.status,
+            extra: document.extra,
         }
     }
 }
@@ -50,14 +96,25 @@ impl FromIterator<PyDocument> for Vec<Document> {
 #[pymethods]
 impl PyDocument {
     #[new]
-    #[pyo3(signature = (text, label=None))]
-    pub fn new(text: &str, label: Option<Vec<(usize, usize, String)>>) -> Self {
+    #[pyo3(signature = (text, label=None, meta=None, status="machine"))]
+    pub fn new(
+        text: &str,
+        label: Option<Vec<(usize, usize, String)>>,
+        meta: Option<BTreeMap<String, String>>,
+        status: &str,
+    ) -> PyResult<Self> {
         let id = hash_string(text);
-        PyDocument {
+        Ok(PyDocument {
             id,
-            text: text.to_string(),
-            label: label.unwrap_or(Vec::new()),
-        }
+            text: text.into(),
+            label: label.unwrap_or_default(),
+            meta: meta.unwrap_or_default(),
+            span_meta: Vec::new(),
+            relations: Vec::new(),
+            tags: Vec::new(),
+            status: DocumentStatus::from_str(status).map_err(QuicknerError::new_err)?,
+            extra: Map::new(),
+        })
     }
 
     #[staticmethod]
@@ -65,17 +122,49 @@ impl PyDocument {
         let id = hash_string(text);
         PyDocument {
             id,
-            text: text.to_string(),
+            text: text.into(),
             label: Vec::new(),
+            meta: BTreeMap::new(),
+            span_meta: Vec::new(),
+            relations: Vec::new(),
+            tags: Vec::new(),
+            status: DocumentStatus::Machine,
+            extra: Map::new(),
+        }
+    }
+
+    /// Create a document from a dict with the keys of a JSONL record,
+    /// the id defaults to the hash of the text
+    #[staticmethod]
+    pub fn from_dict(py: Python, data: &PyAny) -> PyResult<Self> {
+        let mut document: PyDocument = from_python(py, data)?;
+        if document.id.is_empty() {
+            document.id = hash_string(&document.text);
         }
+        Ok(document)
+    }
+
+    /// Return the document as a dict with the keys of a JSONL record
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_python(py, self)
+    }
+
+    #[getter]
+    pub fn text(&self) -> &str {
+        &self.text
     }
 
     // Annotate the document with the given entities
     #[pyo3(signature = (entities, case_sensitive = false))]
     pub fn annotate(&mut self, entities: Vec<PyEntity>, case_sensitive: bool) {
-        let mut annotation = Document::from_string(self.text.clone());
+        let mut annotation = Document::from_string(self.text.to_string());
         let entities = entities.into_iter().collect();
         annotation.annotate(entities, case_sensitive);
+        for span in annotation.span_meta {
+            if !self.span_meta.iter().any(|meta| meta.is(&span.to_tuple())) {
+                self.span_meta.push(span);
+            }
+        }
         self.label.extend(
             annotation
                 .label
@@ -86,6 +175,51 @@ impl PyDocument {
         self.set_unique_labels();
     }
 
+    /// Spans of the document with their provenance: the annotator,
+    /// the entity matched and the confidence
+    fn spans(&self) -> Vec<PySpan> {
+        Document::from(self.clone())
+            .spans()
+            .into_iter()
+            .map(PySpan::from)
+            .collect()
+    }
+
+    /// Who annotated the document: "machine", "corrected" or "gold", the spans
+    /// of a corrected or gold document are kept when it is annotated again
+    #[getter]
+    fn status(&self) -> String {
+        self.status.to_string()
+    }
+
+    #[setter]
+    fn set_status(&mut self, status: &str) -> PyResult<()> {
+        self.status = DocumentStatus::from_str(status).map_err(QuicknerError::new_err)?;
+        Ok(())
+    }
+
+    /// Fields of the imported JSONL record that are not fields of a document,
+    /// written back unchanged to the JSONL output
+    #[getter]
+    fn extra(&self, py: Python) -> PyResult<PyObject> {
+        to_python(py, &self.extra)
+    }
+
+    /// Relations between the spans, as (head span, tail span, label)
+    #[getter]
+    fn relations(&self) -> Vec<(SpanTuple, SpanTuple, String)> {
+        self.relations
+            .iter()
+            .map(|relation| {
+                (
+                    relation.head_span.clone(),
+                    relation.tail_span.clone(),
+                    relation.label.clone(),
+                )
+            })
+            .collect()
+    }
+
     fn set_unique_labels(&mut self) {
         let mut labels: Vec<(usize, usize, String)> = Vec::new();
         for (start, end, label) in &self.label {
@@ -96,12 +230,13 @@ impl PyDocument {
         self.label = labels;
     }
 
-    // Pretty print the annotation
+    // Pretty print the annotation, long texts are cut to the preview length
     // Example: Document(id=1, text="Hello World", label=[(0, 5, "Hello"), (6, 11, "World")])
     pub fn __repr__(&self) -> PyResult<String> {
         let mut repr = format!(
             "Document(id=\"{}\", text=\"{}\", label=[",
-            self.id, self.text
+            self.id,
+            preview(&self.text)
         );
         for (start, end, label) in &self.label {
             repr.push_str(&format!("({start}, {end}, {label}), "));
@@ -115,54 +250,33 @@ impl PyDocument {
         Ok(repr)
     }
 
-    // TODO: This method is not correct, it does not handle overlapping labels
     // Pretty print the annotation
     // With colors for the labels in the text
     // Example: "Hello World" -> "Hello" [Hello] "World"
-    fn pretty(&self) -> PyResult<String> {
-        // Keep track of the color per label
-        let mut color_map: std::collections::HashMap<String, TermColor> =
-            std::collections::HashMap::new();
-        let possible_colors = vec![
-            TermColor::Red,
-            TermColor::Green,
-            TermColor::Yellow,
-            TermColor::Blue,
-            TermColor::Magenta,
-            TermColor::Cyan,
-        ];
-        // Assign a color to each label
-        for (_, _, label) in &self.label {
-            if !color_map.contains_key(label) {
-                let color = possible_colors[color_map.len() % possible_colors.len()];
-                color_map.insert(label.to_string(), color);
-            }
-        }
-        // Build the pretty string, labels are not sorted
-        // and they can overlap
-        // colorize the substring associated with the label
-        // Example: "Hello World" -> "colorized(Hello)[VERB] World"
-        let mut pretty = String::new();
-        let mut start = 0;
-        let mut sorted_label: Vec<(usize, usize, String)> = self.label.clone();
-        sorted_label.sort_by(|a, b| a.0.cmp(&b.0));
-        for (start_label, end_label, label) in sorted_label {
-            let color = color_map.get(&label);
-            if let Some(color) = color {
-                // Handle case of this string: 'ne comprend absolument rien � twitter '
-                // because of the � character
-                if start_label > self.text.len() || end_label > self.text.len() {
-                    return Err(pyo3::exceptions::PyValueError::new_err(
-                        "start_label is greater than the length of the text",
-                    ));
-                }
-                pretty.push_str(&self.text[start..start_label]);
-                pretty.push_str(&colorize(&self.text[start_label..end_label], *color));
-                pretty.push_str(&format!("[{label}]"));
-                start = end_label;
-            }
-        }
-        pretty.push_str(&self.text[start..]);
-        Ok(pretty)
+    // Long texts are cut after `length` characters, the preview length by default
+    #[pyo3(signature = (length = None))]
+    fn pretty(&self, length: Option<usize>) -> PyResult<String> {
+        Ok(Document::from(self.clone()).render_ansi(length))
+    }
+
+    // HTML fragment of the annotation, the spans highlighted in <mark> elements
+    // The full text is rendered by default
+    #[pyo3(signature = (length = 0))]
+    fn render_html(&self, length: usize) -> PyResult<String> {
+        Ok(Document::from(self.clone()).render_html(Some(length)))
+    }
+
+    // Tokens of the text as (token, start, end, BIO tag), the offsets in characters
+    fn tokens(&self) -> Vec<(String, usize, usize, String)> {
+        Document::from(self.clone()).tokens()
+    }
+
+    // Check the offsets of the spans: within the text, on word bounds and not duplicated
+    fn validate_spans(&self) -> Vec<PySpanError> {
+        Document::from(self.clone())
+            .validate_spans()
+            .into_iter()
+            .map(PySpanError::from)
+            .collect()
     }
 }
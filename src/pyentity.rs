@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use pyo3::prelude::*;
 
 use serde::{Deserialize, Serialize};
@@ -9,6 +11,15 @@ pub struct PyEntity {
     pub name: String,
     #[pyo3(get)]
     pub label: String,
+    #[pyo3(get)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[pyo3(get)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+    #[pyo3(get)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
 }
 
 impl From<quickner::Entity> for PyEntity {
@@ -16,6 +27,9 @@ impl From<quickner::Entity> for PyEntity {
         PyEntity {
             name: entity.name,
             label: entity.label,
+            id: entity.id,
+            canonical: entity.canonical,
+            attributes: entity.attributes.into_iter().collect(),
         }
     }
 }
@@ -25,6 +39,9 @@ impl From<PyEntity> for quickner::Entity {
         quickner::Entity {
             name: entity.name,
             label: entity.label,
+            id: entity.id,
+            canonical: entity.canonical,
+            attributes: entity.attributes.into_iter().collect(),
         }
     }
 }
@@ -42,11 +59,20 @@ impl FromIterator<PyEntity> for Vec<quickner::Entity> {
 #[pymethods]
 impl PyEntity {
     #[new]
-    #[pyo3(signature = (name, label))]
-    pub fn new(name: &str, label: &str) -> Self {
+    #[pyo3(signature = (name, label, id = None, canonical = None, attributes = None))]
+    pub fn new(
+        name: &str,
+        label: &str,
+        id: Option<String>,
+        canonical: Option<String>,
+        attributes: Option<BTreeMap<String, String>>,
+    ) -> Self {
         PyEntity {
             name: name.to_string(),
             label: label.to_string(),
+            id,
+            canonical,
+            attributes: attributes.unwrap_or_default(),
         }
     }
 
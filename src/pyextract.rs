@@ -0,0 +1,45 @@
+use std::{io::Error, path::Path};
+
+use pyo3::prelude::*;
+use quickner::TextExtractor;
+
+/// Reads the files of the given extensions with a Python callable, called
+/// with the path of the file and returning its text. An exception raised
+/// by the callable stops the reading of the texts.
+pub struct PyExtractor {
+    extensions: Vec<String>,
+    function: PyObject,
+}
+
+impl PyExtractor {
+    pub fn new(extensions: Vec<String>, function: PyObject) -> Self {
+        PyExtractor {
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect(),
+            function,
+        }
+    }
+}
+
+impl TextExtractor for PyExtractor {
+    fn extensions(&self) -> Vec<String> {
+        self.extensions.clone()
+    }
+
+    fn extract(&self, path: &Path) -> Result<String, Error> {
+        Python::with_gil(|py| {
+            self.function
+                .call1(py, (path.to_string_lossy(),))
+                .and_then(|text| text.extract::<String>(py))
+                .map_err(|e| {
+                    Error::other(format!(
+                        "Unable to extract the text of {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })
+        })
+    }
+}
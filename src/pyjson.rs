@@ -0,0 +1,23 @@
+use pyo3::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::pyquickner::QuicknerError;
+
+/// Convert a value to plain Python objects (dicts, lists, strings, numbers)
+/// through JSON, the dicts have the same layout as the JSON and TOML files
+pub(crate) fn to_python<T: Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| QuicknerError::new_err(e.to_string()))?;
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (json,))?
+        .into_py(py))
+}
+
+/// Read a value from plain Python objects, the reverse of [`to_python`]
+pub(crate) fn from_python<T: DeserializeOwned>(py: Python, object: &PyAny) -> PyResult<T> {
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (object,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(|e| QuicknerError::new_err(e.to_string()))
+}
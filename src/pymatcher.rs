@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use quickner::{Document, Entity, Fuzzy, Language, Matcher, Matching, Quickner};
+
+use crate::{pydocument::PyDocument, pyentity::PyEntity};
+
+/// Annotate texts with the entities, compiled once, in `n_jobs` threads or
+/// one per core with -1. The documents keep the case of the texts
+pub(crate) fn annotate_batch(
+    py: Python,
+    texts: Vec<String>,
+    entities: Vec<PyEntity>,
+    n_jobs: isize,
+    case_sensitive: bool,
+) -> PyResult<Vec<PyDocument>> {
+    let quickner = match n_jobs {
+        -1 => Quickner::default(),
+        n_jobs if n_jobs > 0 => Quickner::default().with_thread_pool(n_jobs as usize),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "n_jobs must be -1 or positive, got {n_jobs}"
+            )))
+        }
+    };
+    let entities: Vec<Entity> = entities.into_iter().collect();
+    let documents = py.allow_threads(|| {
+        let matcher = Matcher::new(&entities, case_sensitive);
+        let mut documents: Vec<Document> = texts
+            .iter()
+            .map(|text| Document::from_string(text.to_string()))
+            .collect();
+        quickner.install(|| matcher.annotate_many(&mut documents));
+        for (document, text) in documents.iter_mut().zip(texts) {
+            document.text = text.into();
+        }
+        documents
+    });
+    Ok(documents.into_iter().map(PyDocument::from).collect())
+}
+
+/// Dictionary matcher compiled once, to annotate many documents
+/// without building the automaton again for each of them
+#[derive(Clone)]
+#[pyclass(name = "Matcher")]
+pub struct PyMatcher {
+    pub matcher: Matcher,
+}
+
+impl From<Matcher> for PyMatcher {
+    fn from(matcher: Matcher) -> Self {
+        PyMatcher { matcher }
+    }
+}
+
+impl PyMatcher {
+    /// Annotate a copy of the document, its text is left unchanged
+    fn annotated(&self, document: &PyDocument) -> Document {
+        let mut annotated = Document::from(document.clone());
+        self.matcher.annotate(&mut annotated);
+        annotated
+    }
+}
+
+#[pymethods]
+impl PyMatcher {
+    #[new]
+    #[pyo3(signature = (entities, case_sensitive = false, fold_diacritics = false, match_plurals = false, max_edits = 0, min_len = 6, language = "english"))]
+    pub fn new(
+        entities: Vec<PyEntity>,
+        case_sensitive: bool,
+        fold_diacritics: bool,
+        match_plurals: bool,
+        max_edits: usize,
+        min_len: usize,
+        language: &str,
+    ) -> PyResult<Self> {
+        if max_edits > 0 && min_len <= max_edits {
+            return Err(PyValueError::new_err(format!(
+                "max_edits must be below min_len, got {max_edits} and {min_len}"
+            )));
+        }
+        let language = Language::from_str(language).map_err(PyValueError::new_err)?;
+        let entities: Vec<Entity> = entities.into_iter().collect();
+        let matching = Matching {
+            fold_diacritics,
+            match_plurals,
+            language,
+            fuzzy: (max_edits > 0).then_some(Fuzzy { max_edits, min_len }),
+            ..Default::default()
+        };
+        Ok(PyMatcher {
+            matcher: Matcher::with_matching(&entities, case_sensitive, matching),
+        })
+    }
+
+    #[getter]
+    pub fn entities(&self) -> Vec<PyEntity> {
+        self.matcher
+            .entities()
+            .iter()
+            .cloned()
+            .map(PyEntity::from)
+            .collect()
+    }
+
+    // Annotate the document in place, the spans found are added to its labels
+    pub fn annotate(&self, mut document: PyRefMut<PyDocument>) {
+        let annotated = self.annotated(&document);
+        document.label = annotated.label;
+        document.span_meta = annotated.span_meta;
+        document.relations = annotated.relations;
+    }
+
+    // Annotate the documents in place, in parallel
+    pub fn annotate_many(&self, py: Python, mut documents: Vec<PyRefMut<PyDocument>>) {
+        let mut annotated: Vec<Document> = documents
+            .iter()
+            .map(|document| Document::from((**document).clone()))
+            .collect();
+        py.allow_threads(|| self.matcher.annotate_many(&mut annotated));
+        for (document, annotated) in documents.iter_mut().zip(annotated) {
+            document.label = annotated.label;
+            document.span_meta = annotated.span_meta;
+            document.relations = annotated.relations;
+        }
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.matcher.entities().len()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!("Matcher(entities={})", self.matcher.entities().len())
+    }
+}
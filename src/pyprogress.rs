@@ -0,0 +1,164 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use pyo3::prelude::*;
+use quickner::ProgressReporter;
+
+/// Reports the progress of the annotation to a Python callable,
+/// called with the number of documents annotated and the total.
+/// An exception raised by the callable cancels the run, it is raised
+/// again by `process`.
+pub struct PyProgress {
+    callback: PyObject,
+    every: usize,
+    total: AtomicUsize,
+    error: Mutex<Option<PyErr>>,
+    cancelled: AtomicBool,
+}
+
+impl PyProgress {
+    pub fn new(callback: PyObject, every: usize) -> Self {
+        PyProgress {
+            callback,
+            every: every.max(1),
+            total: AtomicUsize::new(0),
+            error: Mutex::new(None),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// The exception raised by the callable, if any
+    pub fn take_error(&self) -> Option<PyErr> {
+        self.error.lock().ok()?.take()
+    }
+
+    fn call(&self, done: usize) {
+        let mut error = match self.error.lock() {
+            Ok(error) => error,
+            Err(_) => return,
+        };
+        if error.is_some() {
+            return;
+        }
+        let total = self.total.load(Ordering::SeqCst);
+        let called = Python::with_gil(|py| self.callback.call1(py, (done, total)).map(|_| ()));
+        if let Err(e) = called {
+            *error = Some(e);
+            self.cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl ProgressReporter for PyProgress {
+    fn start(&self, _message: &str, total: usize) {
+        self.total.store(total, Ordering::SeqCst);
+        self.call(0);
+    }
+
+    fn update(&self, done: usize) {
+        self.call(done);
+    }
+
+    fn interval(&self) -> usize {
+        self.every
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Keeps the progress of a run in the background, polled with `ProcessTask.progress`
+pub struct PollProgress {
+    every: usize,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl PollProgress {
+    pub fn new(every: usize) -> Self {
+        PollProgress {
+            every: every.max(1),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+}
+
+impl ProgressReporter for PollProgress {
+    fn start(&self, _message: &str, total: usize) {
+        self.total.store(total, Ordering::SeqCst);
+        self.done.store(0, Ordering::SeqCst);
+    }
+
+    fn update(&self, done: usize) {
+        self.done.store(done, Ordering::SeqCst);
+    }
+
+    fn interval(&self) -> usize {
+        self.every
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// A run of `process_async` in a background thread. It is awaitable, and its
+/// `future` is a `concurrent.futures.Future` of the report of the run.
+#[pyclass(name = "ProcessTask")]
+pub struct PyProcessTask {
+    #[pyo3(get)]
+    pub future: PyObject,
+    pub progress: Arc<PollProgress>,
+}
+
+#[pymethods]
+impl PyProcessTask {
+    /// Number of documents annotated and total, the total is 0 until
+    /// the annotation starts
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.progress.done.load(Ordering::SeqCst),
+            self.progress.total.load(Ordering::SeqCst),
+        )
+    }
+
+    pub fn done(&self, py: Python) -> PyResult<bool> {
+        self.future.call_method0(py, "done")?.extract(py)
+    }
+
+    /// Wait for the report of the run, at most `timeout` seconds,
+    /// the exception of the run is raised again
+    #[pyo3(signature = (timeout = None))]
+    pub fn result(&self, py: Python, timeout: Option<f64>) -> PyResult<PyObject> {
+        self.future.call_method1(py, "result", (timeout,))
+    }
+
+    /// Request the cancellation of the run: the current batch is finished and
+    /// the run ends with an exception. The other runs are not cancelled
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn __await__(&self, py: Python) -> PyResult<PyObject> {
+        let asyncio = PyModule::import(py, "asyncio")?;
+        let future = asyncio.call_method1("wrap_future", (&self.future,))?;
+        Ok(future.call_method0("__await__")?.into())
+    }
+
+    pub fn __repr__(&self, py: Python) -> PyResult<String> {
+        let (done, total) = self.progress();
+        Ok(format!(
+            "ProcessTask(done={}, progress={done}/{total})",
+            match self.done(py)? {
+                true => "True",
+                false => "False",
+            }
+        ))
+    }
+}
@@ -1,38 +1,182 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    num::TryFromIntError,
+    str::FromStr,
+    sync::Arc,
+};
 
 use crate::{
-    pyconfig::{
-        PyAnnotations, PyConfig, PyEntities, PyExcludes, PyFilters, PyFormat, PyInput, PyLogging,
-        PyOutput, PyTexts,
-    },
+    pyconfig::{PyConfig, PyFields, PyFormat},
     pydocument::PyDocument,
     pyentity::PyEntity,
-    utils::{colorize, TermColor},
+    pyextract::PyExtractor,
+    pyjson::{from_python, to_python},
+    pymatcher::PyMatcher,
+    pyprogress::{PollProgress, PyProcessTask, PyProgress},
+    pystats::{
+        PyCooccurrences, PyDuplicateCluster, PyEntityCandidate, PyEvaluation, PyGazetteerStats,
+        PyLabelSuggestion, PyMergeReport, PyPruneReport, PyRunReport, PySpan, PySpanError, PyStats,
+        PyTimeStats,
+    },
 };
-use numpy::PyArray2;
+use numpy::{Element, PyArray1, PyArray2, PyArrayDescr};
 use pyo3::create_exception;
+use pyo3::once_cell::GILOnceCell;
 use pyo3::{
     exceptions::{self, PyGeneratorExit},
     prelude::*,
-    types::{PyDict, PyTuple},
+    types::{PyDict, PySlice, PyTuple},
 };
-use quickner::{Document, Entity, Quickner, SpacyEntity};
+use quickner::{
+    candidates_to_csv, colorize, hash_string, Config, DiscoverOptions, Document, DocumentStatus,
+    EntitiesFormat, Entity, IndicatifProgress, MergeStrategy, NoopProgress, Period,
+    ProgressReporter, Query, Quickner, RunReport, SpacyEntity, Stamp, SuggestOptions, TermColor,
+};
+use serde::{Deserialize, Serialize};
+use std::os::raw::c_long;
 
 create_exception!(quickner, QuicknerError, exceptions::PyException);
 
+/// Configuration, entities and documents of a Quickner object, as a dict
+#[derive(Serialize, Deserialize)]
+struct QuicknerDict {
+    #[serde(default)]
+    config: Option<Config>,
+    #[serde(default)]
+    entities: Vec<PyEntity>,
+    #[serde(default)]
+    documents: Vec<PyDocument>,
+}
+
 #[pyclass(name = "Quickner")]
 pub struct PyQuickner {
     #[pyo3(get)]
     pub config: PyConfig,
     #[pyo3(get)]
     pub config_path: String,
-    #[pyo3(get)]
     pub documents: Vec<PyDocument>,
     #[pyo3(get)]
     pub entities: Vec<PyEntity>,
     quickner: Quickner,
 }
 
+/// Lazy view on the documents of a Quickner instance,
+/// documents are only converted to Python objects when accessed
+#[pyclass(name = "Documents")]
+pub struct PyDocuments {
+    quickner: Py<PyQuickner>,
+}
+
+#[pyclass(name = "DocumentsIterator")]
+pub struct PyDocumentsIterator {
+    quickner: Py<PyQuickner>,
+    index: usize,
+}
+
+/// The instance of a view, an error while it is being changed,
+/// e.g. from a progress callback of `process` or from another thread
+fn borrow_viewed<'py>(
+    py: Python<'py>,
+    quickner: &'py Py<PyQuickner>,
+) -> PyResult<PyRef<'py, PyQuickner>> {
+    quickner.try_borrow(py).map_err(|_| {
+        QuicknerError::new_err("The documents cannot be read while the instance is being changed")
+    })
+}
+
+/// Index of a document, or slice of documents, as passed to __getitem__
+#[derive(FromPyObject)]
+pub enum DocumentIndex<'a> {
+    Int(isize),
+    Slice(&'a PySlice),
+}
+
+impl DocumentIndex<'_> {
+    fn get(&self, py: Python, documents: &[PyDocument]) -> PyResult<PyObject> {
+        match self {
+            DocumentIndex::Int(index) => {
+                let position = if *index < 0 {
+                    *index + documents.len() as isize
+                } else {
+                    *index
+                };
+                if position < 0 || position >= documents.len() as isize {
+                    return Err(exceptions::PyIndexError::new_err(
+                        "document index out of range",
+                    ));
+                }
+                Ok(documents[position as usize].clone().into_py(py))
+            }
+            DocumentIndex::Slice(slice) => {
+                let indices = slice.indices(documents.len() as c_long)?;
+                let documents: Vec<PyDocument> = (0..indices.slicelength)
+                    .map(|i| documents[(indices.start + i * indices.step) as usize].clone())
+                    .collect();
+                Ok(documents.into_py(py))
+            }
+        }
+    }
+}
+
+/// Entities given to the set operations: those of a Quickner instance or a list
+#[derive(FromPyObject)]
+pub enum EntitySource<'a> {
+    Quickner(PyRef<'a, PyQuickner>),
+    Entities(Vec<PyEntity>),
+}
+
+impl EntitySource<'_> {
+    fn entities(self) -> Vec<Entity> {
+        match self {
+            EntitySource::Quickner(quickner) => quickner.quickner.entities.clone(),
+            EntitySource::Entities(entities) => entities.into_iter().collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyDocuments {
+    fn __len__(&self, py: Python) -> PyResult<usize> {
+        Ok(borrow_viewed(py, &self.quickner)?.documents.len())
+    }
+
+    fn __iter__(&self, py: Python) -> PyDocumentsIterator {
+        PyDocumentsIterator {
+            quickner: self.quickner.clone_ref(py),
+            index: 0,
+        }
+    }
+
+    fn __getitem__(&self, py: Python, index: DocumentIndex) -> PyResult<PyObject> {
+        index.get(py, &borrow_viewed(py, &self.quickner)?.documents)
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let documents = borrow_viewed(py, &self.quickner)?
+            .documents
+            .iter()
+            .map(|document| document.__repr__())
+            .collect::<PyResult<Vec<String>>>()?;
+        Ok(format!("[{}]", documents.join(", ")))
+    }
+}
+
+#[pymethods]
+impl PyDocumentsIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python) -> PyResult<Option<PyDocument>> {
+        let document = borrow_viewed(py, &slf.quickner)?
+            .documents
+            .get(slf.index)
+            .cloned();
+        slf.index += 1;
+        Ok(document)
+    }
+}
+
 #[pyclass(name = "SpacyEntity")]
 pub struct PySpacyEntity {
     #[pyo3(get)]
@@ -103,21 +247,39 @@ impl PyQuickner {
             }
             None => quickner.entities = Vec::new(),
         }
-        let config = match config {
-            Some(config) => config,
-            None => PyConfig::default(),
-        };
+        let config = config.unwrap_or_default();
         quickner.config = PyConfig::to_config(config);
         PyQuickner::from(quickner)
     }
 
+    /// Lazy view on the documents, supporting len(), iteration, indexing and slicing
+    #[getter(documents)]
+    pub fn get_documents(slf: PyRef<'_, Self>) -> PyDocuments {
+        PyDocuments {
+            quickner: Py::from(slf),
+        }
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn __iter__(slf: PyRef<'_, Self>) -> PyDocumentsIterator {
+        PyDocumentsIterator {
+            quickner: Py::from(slf),
+            index: 0,
+        }
+    }
+
+    pub fn __getitem__(&self, py: Python, index: DocumentIndex) -> PyResult<PyObject> {
+        index.get(py, &self.documents)
+    }
+
     #[setter(documents)]
     pub fn documents(&mut self, documents: Vec<PyDocument>) {
         self.documents = (*documents).to_vec();
-        self.quickner.documents = documents.into_iter().collect();
-        self.quickner.documents_hash = Quickner::document_hash(&self.quickner.documents);
-        self.quickner.build_label_index();
-        self.quickner.build_entity_index();
+        self.quickner
+            .set_documents(documents.into_iter().map(Document::from).collect());
     }
 
     #[setter(entities)]
@@ -143,11 +305,7 @@ impl PyQuickner {
         }
         let entities = &mut self.entities;
         entities.push(entity.clone());
-        let entity = Entity {
-            name: entity.name,
-            label: entity.label,
-        };
-        self.quickner.add_entity(entity);
+        self.quickner.add_entity(Entity::from(entity));
     }
 
     pub fn __repr__(&self) -> PyResult<String> {
@@ -180,27 +338,514 @@ impl PyQuickner {
         Ok(repr)
     }
 
-    #[pyo3(signature = (save = false))]
-    pub fn process(&mut self, save: bool) -> PyResult<()> {
-        let annotations: Result<(), _> = self.quickner.process(save);
-        match annotations {
-            Ok(annotations) => annotations,
-            Err(error) => return Err(PyErr::new::<exceptions::PyException, _>(error.to_string())),
+    /// `progress` is a callable called with the number of documents annotated
+    /// and the total every `progress_every` documents, or a bool showing
+    /// or hiding the progress bar. Returns the report of the run, also written
+    /// next to the annotations when they are saved
+    #[pyo3(signature = (save = false, progress = None, progress_every = 1000))]
+    pub fn process(
+        &mut self,
+        py: Python,
+        save: bool,
+        progress: Option<PyObject>,
+        progress_every: usize,
+    ) -> PyResult<PyRunReport> {
+        let mut callback = None;
+        let reporter: Option<Arc<dyn ProgressReporter>> = match progress {
+            Some(progress) => match progress.extract::<bool>(py) {
+                Ok(true) => Some(Arc::new(IndicatifProgress::new())),
+                Ok(false) => Some(Arc::new(NoopProgress)),
+                Err(_) if progress.as_ref(py).is_callable() => {
+                    let reporter = Arc::new(PyProgress::new(progress, progress_every));
+                    callback = Some(reporter.clone());
+                    Some(reporter)
+                }
+                Err(_) => {
+                    return Err(QuicknerError::new_err(
+                        "progress must be a callable or a bool",
+                    ))
+                }
+            },
+            None => None,
         };
-        self.documents = self
+        let previous =
+            reporter.map(|reporter| std::mem::replace(&mut self.quickner.progress, reporter));
+        let config_hash = self.config_hash();
+        let quickner = &mut self.quickner;
+        // The GIL is released during the run, other Python threads keep running
+        let report = py.allow_threads(|| run(quickner, save, config_hash));
+        if let Some(previous) = previous {
+            self.quickner.progress = previous;
+        }
+        if let Some(error) = callback.and_then(|callback| callback.take_error()) {
+            return Err(error);
+        }
+        let report = report?;
+        self.sync();
+        Ok(PyRunReport::from(report))
+    }
+
+    /// Run `process` in a background thread on a copy of the instance and
+    /// return a `ProcessTask` right away, to await in an event loop or to poll.
+    /// The instance is replaced by the processed copy when the run ends,
+    /// changes made to it in the meantime are lost.
+    #[pyo3(signature = (save = false, progress_every = 1000))]
+    pub fn process_async(
+        slf: &PyCell<Self>,
+        py: Python,
+        save: bool,
+        progress_every: usize,
+    ) -> PyResult<PyProcessTask> {
+        let progress = Arc::new(PollProgress::new(progress_every));
+        let (mut quickner, config_hash) = {
+            let this = slf.borrow();
+            (this.quickner.clone(), this.config_hash())
+        };
+        let slf: Py<Self> = slf.into();
+        quickner.progress = progress.clone();
+        let future: PyObject = PyModule::import(py, "concurrent.futures")?
+            .getattr("Future")?
+            .call0()?
+            .into();
+        future.call_method0(py, "set_running_or_notify_cancel")?;
+        let task = PyProcessTask {
+            future: future.clone_ref(py),
+            progress,
+        };
+        std::thread::Builder::new()
+            .name("quickner-process".to_string())
+            .spawn(move || {
+                let report = run(&mut quickner, save, config_hash);
+                Python::with_gil(|py| {
+                    let report = report.and_then(|report| {
+                        let mut this = slf.try_borrow_mut(py)?;
+                        quickner.progress = this.quickner.progress.clone();
+                        this.quickner = quickner;
+                        this.sync();
+                        Ok(PyRunReport::from(report).into_py(py))
+                    });
+                    let outcome = match report {
+                        Ok(report) => future.call_method1(py, "set_result", (report,)),
+                        Err(error) => future.call_method1(py, "set_exception", (error,)),
+                    };
+                    if let Err(error) = outcome {
+                        error.print(py);
+                    }
+                })
+            })
+            .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        Ok(task)
+    }
+
+    /// Annotate only the first `n` documents without saving anything,
+    /// to check the filters and the gazetteer before a full run
+    #[pyo3(signature = (n = 10))]
+    pub fn preview(&self, n: usize) -> PyResult<Vec<PyDocument>> {
+        let documents = self
             .quickner
-            .documents
-            .clone()
+            .preview(n)
+            .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        Ok(documents.into_iter().map(PyDocument::from).collect())
+    }
+
+    /// Annotate the documents with new entities only,
+    /// without re-running the annotation over the known entities
+    #[pyo3(signature = (entities))]
+    pub fn annotate_incremental(&mut self, entities: Vec<PyEntity>) {
+        let entities: Vec<Entity> = entities.into_iter().collect();
+        self.quickner.annotate_incremental(&entities);
+        self.sync();
+    }
+
+    /// Compile the entities into a matcher applying the excludes, context rules
+    /// and label priority, to annotate single documents without compiling it again
+    pub fn compile_matcher(&self) -> PyMatcher {
+        PyMatcher::from(self.quickner.compile_matcher())
+    }
+
+    /// Downsample the documents of the over-represented labels,
+    /// returns the number of documents removed
+    #[pyo3(signature = (max_per_label, seed = 0))]
+    pub fn balance(&mut self, max_per_label: usize, seed: u64) -> usize {
+        let removed = self.quickner.balance(max_per_label, seed);
+        self.sync();
+        removed
+    }
+
+    /// Add the abbreviations defined in the documents whose long form or
+    /// short form is an entity to the entities, returns the entities added
+    pub fn learn_abbreviations(&mut self) -> Vec<PyEntity> {
+        let learned = self.quickner.learn_abbreviations();
+        self.sync();
+        learned.into_iter().map(PyEntity::from).collect()
+    }
+
+    /// The entities, then the entities of `other` (a Quickner instance or a list)
+    /// with a name and label not among them
+    pub fn entities_union(&self, other: EntitySource) -> Vec<PyEntity> {
+        let entities = self.quickner.entities_union(&other.entities());
+        entities.into_iter().map(PyEntity::from).collect()
+    }
+
+    /// The entities with a name and label among the entities of `other`
+    pub fn entities_intersect(&self, other: EntitySource) -> Vec<PyEntity> {
+        let entities = self.quickner.entities_intersect(&other.entities());
+        entities.into_iter().map(PyEntity::from).collect()
+    }
+
+    /// The entities with a name and label not among the entities of `other`
+    pub fn entities_diff(&self, other: EntitySource) -> Vec<PyEntity> {
+        let entities = self.quickner.entities_diff(&other.entities());
+        entities.into_iter().map(PyEntity::from).collect()
+    }
+
+    /// Save the entities to a file: "csv", "tsv", "json", "toml" or "txt"
+    /// (a directory with a file per label), detected from the extension if not given
+    #[pyo3(signature = (path, format = None))]
+    pub fn export_entities(&self, path: &str, format: Option<&str>) -> PyResult<()> {
+        let format = format
+            .map(EntitiesFormat::from_str)
+            .transpose()
+            .map_err(QuicknerError::new_err)?;
+        self.quickner
+            .export_entities(path, format)
+            .map_err(|error| QuicknerError::new_err(error.to_string()))
+    }
+
+    /// Find the exact and near-duplicate documents, whose shingles have
+    /// a Jaccard similarity of at least `threshold` once normalized
+    #[pyo3(signature = (threshold = 0.9))]
+    pub fn duplicates(&self, threshold: f64) -> PyResult<Vec<PyDuplicateCluster>> {
+        check_threshold(threshold)?;
+        Ok(self
+            .quickner
+            .duplicates(threshold)
             .into_iter()
-            .map(PyDocument::from)
-            .collect::<Vec<PyDocument>>();
-        self.entities = self
+            .map(PyDuplicateCluster::from)
+            .collect())
+    }
+
+    /// Remove the duplicates found by `duplicates`, keeping the first document
+    /// of each cluster, returns the clusters
+    #[pyo3(signature = (threshold = 0.9))]
+    pub fn dedupe(&mut self, threshold: f64) -> PyResult<Vec<PyDuplicateCluster>> {
+        check_threshold(threshold)?;
+        let clusters = self.quickner.dedupe(threshold);
+        self.sync();
+        Ok(clusters.into_iter().map(PyDuplicateCluster::from).collect())
+    }
+
+    /// Merge the documents (by id) and entities of another instance, the documents
+    /// annotated differently are resolved with the strategy: "keep-ours",
+    /// "keep-theirs" or "union-spans"
+    #[pyo3(signature = (other, strategy = "union-spans"))]
+    pub fn merge(&mut self, other: &PyQuickner, strategy: &str) -> PyResult<PyMergeReport> {
+        let strategy = MergeStrategy::from_str(strategy).map_err(QuicknerError::new_err)?;
+        let report = self.quickner.merge(other.quickner.clone(), strategy);
+        self.sync();
+        Ok(PyMergeReport::from(report))
+    }
+
+    /// Remove the entities matched in more than `max_doc_frequency` of the documents
+    /// (a share between 0 and 1), with their spans, and the ones matched in fewer
+    /// than `min_doc_frequency` documents
+    #[pyo3(signature = (max_doc_frequency = 0.5, min_doc_frequency = 1))]
+    pub fn prune_entities(
+        &mut self,
+        max_doc_frequency: f32,
+        min_doc_frequency: usize,
+    ) -> PyResult<PyPruneReport> {
+        if !(max_doc_frequency > 0.0 && max_doc_frequency <= 1.0) {
+            return Err(QuicknerError::new_err(format!(
+                "max_doc_frequency must be between 0 and 1, got {max_doc_frequency}"
+            )));
+        }
+        let report = self
             .quickner
-            .entities
-            .clone()
+            .prune_entities(max_doc_frequency, min_doc_frequency);
+        self.sync();
+        Ok(PyPruneReport::from(report))
+    }
+
+    /// Keep the spans for which the predicate, called with each Span, returns True,
+    /// e.g. `lambda span: (span.confidence or 1.0) >= 0.8`.
+    /// Returns the number of spans removed
+    pub fn filter_spans(&mut self, predicate: &PyAny) -> PyResult<usize> {
+        // The predicate is called on every span first, nothing is removed if it raises
+        let mut kept = Vec::new();
+        for document in &self.quickner.documents {
+            for span in document.spans() {
+                kept.push(predicate.call1((PySpan::from(span),))?.is_true()?);
+            }
+        }
+        let mut kept = kept.into_iter();
+        let removed = self.quickner.filter_spans(|_| kept.next().unwrap_or(true));
+        self.sync();
+        Ok(removed)
+    }
+
+    /// Set the status of the documents with the given ids: "machine", "corrected"
+    /// or "gold", returns the number of documents changed
+    pub fn set_status(&mut self, ids: Vec<String>, status: &str) -> PyResult<usize> {
+        let status = DocumentStatus::from_str(status).map_err(QuicknerError::new_err)?;
+        let changed = self.quickner.set_status(&ids, status);
+        self.sync();
+        Ok(changed)
+    }
+
+    /// Tag the documents with the label of the keywords found at least
+    /// `min_matches` times in their text, returns the number of documents tagged
+    #[pyo3(signature = (keywords, min_matches = 1))]
+    pub fn tag(&mut self, keywords: Vec<PyEntity>, min_matches: usize) -> usize {
+        let keywords: Vec<Entity> = keywords.into_iter().collect();
+        let tagged = self.quickner.tag_documents(&keywords, min_matches);
+        self.sync();
+        tagged
+    }
+
+    /// Read the files of the given extensions in a directory of texts with a
+    /// callable, called with the path of the file and returning its text
+    pub fn register_extractor(
+        &mut self,
+        py: Python,
+        extensions: Vec<String>,
+        function: PyObject,
+    ) -> PyResult<()> {
+        if !function.as_ref(py).is_callable() {
+            return Err(QuicknerError::new_err("the extractor must be a callable"));
+        }
+        self.quickner
+            .register_extractor(PyExtractor::new(extensions, function));
+        Ok(())
+    }
+
+    /// Rename a label in the documents and the entities, returns the number of spans renamed
+    pub fn rename_label(&mut self, old: &str, new: &str) -> usize {
+        let renamed = self.quickner.rename_label(old, new);
+        self.sync();
+        renamed
+    }
+
+    /// Keep an append-only history of the changes of the documents in a JSONL
+    /// file, to revert them with `undo`
+    pub fn enable_history(&mut self, path: &str) -> PyResult<()> {
+        self.quickner
+            .enable_history(path)
+            .map_err(|error| QuicknerError::new_err(error.to_string()))
+    }
+
+    /// Revert the last `n` operations of the history, returns the number reverted
+    #[pyo3(signature = (n = 1))]
+    pub fn undo(&mut self, n: usize) -> PyResult<usize> {
+        let undone = self
+            .quickner
+            .undo(n)
+            .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        self.sync();
+        Ok(undone)
+    }
+
+    /// The operations of the history as dicts, oldest first
+    pub fn history(&self, py: Python) -> PyResult<PyObject> {
+        let history = self
+            .quickner
+            .read_history()
+            .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        to_python(py, &history)
+    }
+
+    /// Compute the statistics of the annotated corpus
+    pub fn stats(&self) -> PyStats {
+        PyStats::from(self.quickner.stats())
+    }
+
+    /// Compute the statistics of the annotated corpus per time period,
+    /// from the date in the `field` metadata of the documents
+    #[pyo3(signature = (field = "date", period = "month"))]
+    pub fn time_stats(&self, field: &str, period: &str) -> PyResult<PyTimeStats> {
+        let period = Period::from_str(period).map_err(QuicknerError::new_err)?;
+        Ok(PyTimeStats::from(self.quickner.time_stats(field, period)))
+    }
+
+    /// Count the entities and labels annotated together in the same documents,
+    /// with at most `window` tokens between their spans if it is set
+    #[pyo3(signature = (window = None))]
+    pub fn cooccurrences(&self, window: Option<usize>) -> PyCooccurrences {
+        PyCooccurrences::from(self.quickner.cooccurrences(window))
+    }
+
+    /// Compute the statistics of the gazetteer compiled into the matcher
+    pub fn gazetteer_stats(&self) -> PyGazetteerStats {
+        PyGazetteerStats::from(self.quickner.gazetteer_stats())
+    }
+
+    /// Suggest labels for the frequent terms missing from the gazetteer,
+    /// from their nearest entities in a word vectors file (word2vec or GloVe text format)
+    /// The suggestions are sorted by score, best first, and are meant to be reviewed
+    #[pyo3(signature = (vectors, min_frequency = 3, max_terms = 1000, neighbors = 5, min_similarity = 0.5))]
+    pub fn suggest_labels(
+        &self,
+        vectors: &str,
+        min_frequency: usize,
+        max_terms: usize,
+        neighbors: usize,
+        min_similarity: f32,
+    ) -> PyResult<Vec<PyLabelSuggestion>> {
+        let options = SuggestOptions {
+            min_frequency,
+            max_terms,
+            neighbors,
+            min_similarity,
+        };
+        match self.quickner.suggest_labels(vectors, &options) {
+            Ok(suggestions) => Ok(suggestions
+                .into_iter()
+                .map(PyLabelSuggestion::from)
+                .collect()),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
+    /// Propose new entities: the frequent title-case sequences missing from the
+    /// gazetteer and from the spans, with the label of the spans found in the most
+    /// similar contexts. The candidates are saved as CSV to `path` if given
+    #[pyo3(signature = (min_frequency = 3, max_words = 4, window = 5, max_candidates = 100, path = None))]
+    pub fn discover(
+        &self,
+        min_frequency: usize,
+        max_words: usize,
+        window: usize,
+        max_candidates: usize,
+        path: Option<&str>,
+    ) -> PyResult<Vec<PyEntityCandidate>> {
+        let options = DiscoverOptions {
+            min_frequency,
+            max_words,
+            window,
+            max_candidates,
+        };
+        let candidates = self.quickner.discover(&options);
+        if let Some(path) = path {
+            candidates_to_csv(&candidates, path)
+                .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        }
+        Ok(candidates
             .into_iter()
-            .map(PyEntity::from)
-            .collect::<Vec<PyEntity>>();
+            .map(PyEntityCandidate::from)
+            .collect())
+    }
+
+    /// Evaluate the annotations against a reference (gold) corpus
+    /// Documents are matched by id, spans must match exactly
+    #[pyo3(signature = (reference))]
+    pub fn evaluate(&self, reference: PyRef<PyQuickner>) -> PyEvaluation {
+        PyEvaluation::from(self.quickner.evaluate(&reference.quickner))
+    }
+
+    /// Audit the spans of every document: offsets within the text, on word bounds,
+    /// not duplicated, and the text of the span is an entity with its label.
+    /// The line of an error is the position of its document, starting at 1
+    pub fn validate(&self) -> Vec<PySpanError> {
+        self.quickner
+            .validate()
+            .into_iter()
+            .map(PySpanError::from)
+            .collect()
+    }
+
+    /// Create a new instance restricted to the documents matching every condition:
+    /// at least one span of each label, each entity annotated and the metadata values.
+    /// Stats, evaluation, export and splitting can be run on the subset
+    #[pyo3(signature = (labels = None, entities = None, meta = None))]
+    pub fn subset(
+        &self,
+        labels: Option<Vec<String>>,
+        entities: Option<Vec<String>>,
+        meta: Option<HashMap<String, String>>,
+    ) -> PyQuickner {
+        let mut query = Query::new();
+        for label in labels.unwrap_or_default() {
+            query = query.with_label(&label);
+        }
+        for name in entities.unwrap_or_default() {
+            query = query.with_entity(&name);
+        }
+        for (key, value) in meta.unwrap_or_default() {
+            query = query.with_meta(&key, &value);
+        }
+        PyQuickner::from(self.quickner.subset(&query))
+    }
+
+    /// Find the documents matching every condition: the substring of the text,
+    /// case insensitive, at least one span of each label, no span of the labels
+    /// excluded, each entity annotated and the metadata values.
+    /// Returns at most `limit` documents, in the order of the corpus
+    #[pyo3(signature = (text = None, labels = None, without_labels = None, entities = None, meta = None, limit = None))]
+    pub fn search(
+        &self,
+        text: Option<&str>,
+        labels: Option<Vec<String>>,
+        without_labels: Option<Vec<String>>,
+        entities: Option<Vec<String>>,
+        meta: Option<HashMap<String, String>>,
+        limit: Option<usize>,
+    ) -> Vec<PyDocument> {
+        let mut query = Query::new();
+        if let Some(text) = text {
+            query = query.contains_text(text);
+        }
+        for label in labels.unwrap_or_default() {
+            query = query.with_label(&label);
+        }
+        for label in without_labels.unwrap_or_default() {
+            query = query.without_label(&label);
+        }
+        for name in entities.unwrap_or_default() {
+            query = query.with_entity(&name);
+        }
+        for (key, value) in meta.unwrap_or_default() {
+            query = query.with_meta(&key, &value);
+        }
+        query.limit = limit;
+        self.quickner
+            .search(&query)
+            .into_iter()
+            .map(|document| PyDocument::from(document.clone()))
+            .collect()
+    }
+
+    /// Parameters and metrics describing the run, for experiment tracking
+    /// Returns a dict with a "params" and a "metrics" dict
+    pub fn run_metadata<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let (params, metrics) = self.tracking(py)?;
+        let metadata = PyDict::new(py);
+        metadata.set_item("params", params)?;
+        metadata.set_item("metrics", metrics)?;
+        Ok(metadata)
+    }
+
+    /// Report the run parameters and the dataset metrics to an experiment tracker.
+    /// The tracker can be the mlflow module (log_params/log_metrics),
+    /// the wandb module or a wandb run (config.update/log),
+    /// or any callable taking (params, metrics)
+    #[pyo3(signature = (tracker))]
+    pub fn log_run(&self, py: Python, tracker: &PyAny) -> PyResult<()> {
+        let (params, metrics) = self.tracking(py)?;
+        if tracker.hasattr("log_params")? && tracker.hasattr("log_metrics")? {
+            tracker.call_method1("log_params", (params,))?;
+            tracker.call_method1("log_metrics", (metrics,))?;
+        } else if tracker.hasattr("log")? && tracker.hasattr("config")? {
+            tracker
+                .getattr("config")?
+                .call_method1("update", (params,))?;
+            tracker.call_method1("log", (metrics,))?;
+        } else if tracker.is_callable() {
+            tracker.call1((params, metrics))?;
+        } else {
+            return Err(exceptions::PyTypeError::new_err(
+                "tracker must be mlflow, wandb or a callable taking (params, metrics)",
+            ));
+        }
         Ok(())
     }
 
@@ -216,23 +861,38 @@ impl PyQuickner {
             PyFormat::SPACY => quickner::Format::Spacy,
             PyFormat::BRAT => quickner::Format::Brat,
             PyFormat::CONLL => quickner::Format::Conll,
+            PyFormat::HTML => quickner::Format::Html,
+            PyFormat::NIF => quickner::Format::Nif,
+            PyFormat::TOKENS => quickner::Format::Tokens,
         };
-        let save_annotations = format.save(&self.quickner.documents, &path);
+        let output = quickner::Output {
+            path,
+            ..self.quickner.config.annotations.output.clone()
+        };
+        let save_annotations =
+            format.save_stamped(&self.quickner.documents, &output, &self.stamp());
         match save_annotations {
             Ok(_) => Ok(save_annotations.unwrap()),
             Err(error) => Err(PyErr::new::<exceptions::PyException, _>(error.to_string())),
         }
     }
 
-    #[pyo3(signature = (path = None))]
+    #[pyo3(signature = (path = None, fields = None))]
     #[staticmethod]
-    pub fn from_jsonl(path: Option<&str>) -> PyQuickner {
+    pub fn from_jsonl(
+        path: Option<&str>,
+        fields: Option<HashMap<String, String>>,
+    ) -> PyResult<PyQuickner> {
         let path = match path {
             Some(path) => path.to_string(),
             None => String::from(""),
         };
-        let quickner = Quickner::from_jsonl(path.as_str());
-        PyQuickner::from(quickner)
+        let fields = match fields {
+            Some(fields) => PyFields::from_mapping(fields)?.into(),
+            None => quickner::Fields::default(),
+        };
+        let quickner = Quickner::from_jsonl_with_fields(path.as_str(), &fields);
+        Ok(PyQuickner::from(quickner))
     }
 
     #[pyo3(signature = (path = None))]
@@ -246,33 +906,134 @@ impl PyQuickner {
         PyQuickner::from(quickner)
     }
 
+    /// Import every annotation file of a directory (jsonl, spaCy, brat, CoNLL),
+    /// the file of each document is kept in its "source" metadata
+    #[pyo3(signature = (path))]
+    #[staticmethod]
+    pub fn from_dir(path: &str) -> PyResult<PyQuickner> {
+        match Quickner::from_dir(path) {
+            Ok(quickner) => Ok(PyQuickner::from(quickner)),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
+    /// Save the documents, entities and annotations to a SQLite database
+    /// Documents already in the database are updated, keyed on their id
+    #[pyo3(signature = (path))]
+    pub fn to_sqlite(&self, path: &str) -> PyResult<()> {
+        match self.quickner.to_sqlite(path) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
+    /// Load the documents, entities and annotations from a SQLite database
+    #[pyo3(signature = (path))]
+    #[staticmethod]
+    pub fn from_sqlite(path: &str) -> PyResult<PyQuickner> {
+        match Quickner::from_sqlite(path) {
+            Ok(quickner) => Ok(PyQuickner::from(quickner)),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
+    /// Save the configuration, entities, excludes, documents and indexes to a single
+    /// gzip-compressed JSON file, to reload an annotation session with `load_project`
+    #[pyo3(signature = (path))]
+    pub fn save_project(&self, path: &str) -> PyResult<()> {
+        self.quickner
+            .save_project(path)
+            .map_err(|error| QuicknerError::new_err(error.to_string()))
+    }
+
+    /// Load a project saved with `save_project`
+    #[pyo3(signature = (path))]
+    #[staticmethod]
+    pub fn load_project(path: &str) -> PyResult<PyQuickner> {
+        match Quickner::load_project(path) {
+            Ok(quickner) => Ok(PyQuickner::from(quickner)),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
+    /// Create a Quickner object from a dict with the "config", "entities" and
+    /// "documents" keys, as returned by `to_dict`, without reading any file
+    #[staticmethod]
+    pub fn from_dict(py: Python, data: &PyAny) -> PyResult<PyQuickner> {
+        let data: QuicknerDict = from_python(py, data)?;
+        let documents = data
+            .documents
+            .into_iter()
+            .map(|mut document| {
+                if document.id.is_empty() {
+                    document.id = hash_string(&document.text);
+                }
+                document
+            })
+            .collect();
+        let config = data.config.map(PyConfig::from_config);
+        Ok(PyQuickner::new(
+            Some(documents),
+            Some(data.entities),
+            config,
+        ))
+    }
+
+    /// Return the configuration, the entities and the documents as a dict
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_python(
+            py,
+            &QuicknerDict {
+                config: Some(self.quickner.config.clone()),
+                entities: self.entities.clone(),
+                documents: self.documents.clone(),
+            },
+        )
+    }
+
     #[pyo3(signature = (path = None))]
     pub fn to_jsonl(&self, path: Option<&str>) {
         let path = match path {
             Some(path) => path.to_string(),
             None => self.config.annotations.output.path.clone(),
         };
-        let documents: Vec<Document> = self
-            .documents
-            .iter()
-            .map(|annotation| Document::new((*annotation.text).to_string(), (*annotation.label).to_vec()))
-            .collect();
+        let documents: Vec<Document> = self.documents.iter().cloned().map(Document::from).collect();
+        let output = quickner::Output {
+            path,
+            ..self.quickner.config.annotations.output.clone()
+        };
         quickner::Format::Jsonl
-            .save(&documents, path.as_str())
+            .save_stamped(&documents, &output, &self.stamp())
             .unwrap();
     }
 
+    /// Save the gazetteer as the JSONL patterns of a spaCy EntityRuler,
+    /// loaded with `nlp.add_pipe("entity_ruler").from_disk(path)`
+    #[pyo3(signature = (path))]
+    pub fn to_entity_ruler(&self, path: &str) -> PyResult<()> {
+        match self.quickner.to_entity_ruler(path) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
+    /// Save the spans linked to the entities of the gazetteer as JSONL, with the id
+    /// of the entity when unambiguous and the candidate entities otherwise
+    #[pyo3(signature = (path))]
+    pub fn to_entity_links(&self, path: &str) -> PyResult<()> {
+        match self.quickner.to_entity_links(path) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(QuicknerError::new_err(error.to_string())),
+        }
+    }
+
     #[pyo3(signature = (path = None))]
     pub fn to_csv(&self, path: Option<&str>) {
         let path = match path {
             Some(path) => path.to_string(),
             None => self.config.annotations.output.path.clone(),
         };
-        let documents: Vec<Document> = self
-            .documents
-            .iter()
-            .map(|annotation| Document::new((*annotation.text).to_string(), (*annotation.label).to_vec()))
-            .collect();
+        let documents: Vec<Document> = self.documents.iter().cloned().map(Document::from).collect();
         quickner::Format::Csv
             .save(&documents, path.as_str())
             .unwrap();
@@ -284,11 +1045,7 @@ impl PyQuickner {
             Some(path) => path.to_string(),
             None => self.config.annotations.output.path.clone(),
         };
-        let documents: Vec<Document> = self
-            .documents
-            .iter()
-            .map(|annotation| Document::new((*annotation.text).to_string(), (*annotation.label).to_vec()))
-            .collect();
+        let documents: Vec<Document> = self.documents.iter().cloned().map(Document::from).collect();
         quickner::Format::Spacy
             .save(&documents, path.as_str())
             .unwrap();
@@ -314,14 +1071,12 @@ impl PyQuickner {
             documents
         };
         // Remove duplicates
-        let documents = documents
-            .into_iter()
-            .fold(Vec::new(), |mut acc, document| {
-                if !acc.contains(&document) {
-                    acc.push(document);
-                }
-                acc
-            });
+        let documents = documents.into_iter().fold(Vec::new(), |mut acc, document| {
+            if !acc.contains(&document) {
+                acc.push(document);
+            }
+            acc
+        });
         println!("{:?}", documents);
         documents
     }
@@ -348,14 +1103,12 @@ impl PyQuickner {
             documents
         };
         // Remove duplicates
-        let documents: Vec<_> = documents
-            .into_iter()
-            .fold(Vec::new(), |mut acc, document| {
-                if !acc.contains(&document) {
-                    acc.push(document);
-                }
-                acc
-            });
+        let documents: Vec<_> = documents.into_iter().fold(Vec::new(), |mut acc, document| {
+            if !acc.contains(&document) {
+                acc.push(document);
+            }
+            acc
+        });
         documents
     }
 
@@ -379,13 +1132,13 @@ impl PyQuickner {
         PySpacyGenerator { entities: spacy }
     }
 
-    /// Convert Vec<Document> to numpy array of (string, array of (int, int, string))
-    /// where the first int is the start index and the second int is the end index
-    /// of the entity in the string.
-    /// The string is the text of the document.
-    /// The array of (int, int, string) is the list of entities in the document.
-    /// Return a numpy array like so: array(['rust is made by Mozilla', list([(0, 4, 'PL'), (16, 23, 'ORG')])], dtype=object)
-    /// And type is numpy.ndarray
+    // Convert Vec<Document> to numpy array of (string, array of (int, int, string))
+    // where the first int is the start index and the second int is the end index
+    // of the entity in the string.
+    // The string is the text of the document.
+    // The array of (int, int, string) is the list of entities in the document.
+    // Return a numpy array like so: array(['rust is made by Mozilla', list([(0, 4, 'PL'), (16, 23, 'ORG')])], dtype=object)
+    // And type is numpy.ndarray
     // pub fn numpy(&self) -> Py<PyArray1<PyObject>> {
     //     Python::with_gil(|py| {
     //         let numpy = PyModule::import(py, "numpy").unwrap();
@@ -395,6 +1148,108 @@ impl PyQuickner {
     //     })
     // }
 
+    /// Export the spans as a pandas DataFrame, one row per span
+    /// Columns: id, text, start, end, label, entity_text
+    pub fn to_pandas(&self, py: Python) -> PyResult<PyObject> {
+        let pandas = PyModule::import(py, "pandas").map_err(|_| {
+            exceptions::PyImportError::new_err(
+                "to_pandas requires pandas, install it with `pip install pandas`",
+            )
+        })?;
+        let spans: usize = self
+            .quickner
+            .documents
+            .iter()
+            .map(|document| document.label.len())
+            .sum();
+        let mut ids = Vec::with_capacity(spans);
+        let mut texts = Vec::with_capacity(spans);
+        let mut starts = Vec::with_capacity(spans);
+        let mut ends = Vec::with_capacity(spans);
+        let mut labels = Vec::with_capacity(spans);
+        let mut entity_texts = Vec::with_capacity(spans);
+        for document in &self.quickner.documents {
+            for (start, end, label) in &document.label {
+                ids.push(document.id.as_str());
+                texts.push(&*document.text);
+                starts.push(*start);
+                ends.push(*end);
+                labels.push(label.as_str());
+                entity_texts.push(
+                    document
+                        .text
+                        .chars()
+                        .skip(*start)
+                        .take(end.saturating_sub(*start))
+                        .collect::<String>(),
+                );
+            }
+        }
+        let columns = ["id", "text", "start", "end", "label", "entity_text"];
+        let data = PyDict::new(py);
+        data.set_item(columns[0], ids)?;
+        data.set_item(columns[1], texts)?;
+        data.set_item(columns[2], starts)?;
+        data.set_item(columns[3], ends)?;
+        data.set_item(columns[4], labels)?;
+        data.set_item(columns[5], entity_texts)?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("columns", columns.to_vec())?;
+        Ok(pandas
+            .getattr("DataFrame")?
+            .call((data,), Some(kwargs))?
+            .into())
+    }
+
+    /// Save the annotations as a serialized spaCy DocBin, ready for `spacy train`
+    /// The texts are tokenized with a blank pipeline of the language,
+    /// spans not aligned with the tokens are skipped with a warning and
+    /// overlapping spans are resolved by spaCy, the longest span wins
+    #[pyo3(signature = (path, lang = "en"))]
+    pub fn to_docbin(&self, py: Python, path: &str, lang: &str) -> PyResult<()> {
+        let spacy = PyModule::import(py, "spacy").map_err(|_| {
+            exceptions::PyImportError::new_err(
+                "to_docbin requires spaCy, install it with `pip install spacy`",
+            )
+        })?;
+        let nlp = spacy.call_method1("blank", (lang,))?;
+        let filter_spans = spacy.getattr("util")?.getattr("filter_spans")?;
+        let mut docs = Vec::with_capacity(self.quickner.documents.len());
+        let mut skipped = 0;
+        for document in &self.quickner.documents {
+            let doc = nlp.call_method1("make_doc", (&*document.text,))?;
+            let mut spans = Vec::with_capacity(document.label.len());
+            for (start, end, label) in &document.label {
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("label", label)?;
+                let span = doc.call_method("char_span", (*start, *end), Some(kwargs))?;
+                if span.is_none() {
+                    skipped += 1;
+                } else {
+                    spans.push(span);
+                }
+            }
+            doc.setattr("ents", filter_spans.call1((spans,))?)?;
+            docs.push(doc);
+        }
+        if skipped > 0 {
+            PyErr::warn(
+                py,
+                py.get_type::<exceptions::PyUserWarning>(),
+                &format!("{skipped} spans not aligned with the spaCy tokens were skipped"),
+                1,
+            )?;
+        }
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("docs", docs)?;
+        let docbin = spacy
+            .getattr("tokens")?
+            .getattr("DocBin")?
+            .call((), Some(kwargs))?;
+        docbin.call_method1("to_disk", (path,))?;
+        Ok(())
+    }
+
     pub fn numpy(&self) -> PyResult<Py<PyArray2<PyObject>>> {
         Python::with_gil(|py| {
             let numpy = PyModule::import(py, "numpy").unwrap();
@@ -421,7 +1276,7 @@ impl PyQuickner {
                         py,
                         &[
                             document.id.clone().to_object(py),
-                            document.text.clone().to_object(py),
+                            document.text.to_object(py),
                             entities.to_object(py),
                         ],
                     )
@@ -446,103 +1301,219 @@ impl PyQuickner {
             }
         })
     }
+
+    /// Return the sorted labels and a structured numpy array of the spans,
+    /// one record (doc_idx, start, end, label_id) per span, label_id being
+    /// the index of the label. The array is built in Rust and handed to numpy
+    /// without a Python object per span.
+    pub fn spans_numpy<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Vec<String>, &'py PyArray1<SpanRecord>)> {
+        PyModule::import(py, "numpy").map_err(|_| {
+            exceptions::PyImportError::new_err(
+                "spans_numpy requires numpy, install it with `pip install numpy`",
+            )
+        })?;
+        let labels: Vec<String> = self
+            .quickner
+            .documents
+            .iter()
+            .flat_map(|document| document.label.iter().map(|(_, _, label)| label))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .cloned()
+            .collect();
+        let ids: HashMap<&str, u16> = labels
+            .iter()
+            .enumerate()
+            .map(|(id, label)| Ok((label.as_str(), u16::try_from(id)?)))
+            .collect::<Result<_, TryFromIntError>>()
+            .map_err(|_| {
+                QuicknerError::new_err(format!(
+                    "{} labels do not fit in the uint16 label_id",
+                    labels.len()
+                ))
+            })?;
+        let overflow = |e: TryFromIntError| {
+            QuicknerError::new_err(format!("A span does not fit in the uint32 fields: {e}"))
+        };
+        let mut records = Vec::with_capacity(
+            self.quickner
+                .documents
+                .iter()
+                .map(|document| document.label.len())
+                .sum(),
+        );
+        for (index, document) in self.quickner.documents.iter().enumerate() {
+            let doc_idx = u32::try_from(index).map_err(overflow)?;
+            for (start, end, label) in &document.label {
+                records.push(SpanRecord {
+                    doc_idx,
+                    start: u32::try_from(*start).map_err(overflow)?,
+                    end: u32::try_from(*end).map_err(overflow)?,
+                    label_id: ids[label.as_str()],
+                });
+            }
+        }
+        Ok((labels, PyArray1::from_vec(py, records)))
+    }
+}
+
+/// A span of `spans_numpy`, a record of its structured array
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct SpanRecord {
+    doc_idx: u32,
+    start: u32,
+    end: u32,
+    label_id: u16,
+}
+
+/// The numpy dtype of the records, with the offsets and size of the C layout
+unsafe impl Element for SpanRecord {
+    const IS_COPY: bool = true;
+
+    fn get_dtype(py: Python<'_>) -> &PyArrayDescr {
+        static DTYPE: GILOnceCell<Py<PyArrayDescr>> = GILOnceCell::new();
+        DTYPE
+            .get_or_init(py, || {
+                let spec = PyDict::new(py);
+                spec.set_item("names", ["doc_idx", "start", "end", "label_id"])
+                    .and_then(|_| spec.set_item("formats", ["u4", "u4", "u4", "u2"]))
+                    .and_then(|_| spec.set_item("offsets", [0, 4, 8, 12]))
+                    .and_then(|_| spec.set_item("itemsize", std::mem::size_of::<SpanRecord>()))
+                    .and_then(|_| PyArrayDescr::new(py, spec))
+                    .expect("Unable to build the dtype of the spans")
+                    .into()
+            })
+            .as_ref(py)
+    }
+}
+
+impl PyQuickner {
+    /// Provenance stamp of the exports, the configuration is hashed
+    /// as it may not come from a file
+    fn stamp(&self) -> Stamp {
+        Stamp::new()
+            .with_gazetteer(&self.quickner.entities)
+            .with_config_hash(Some(self.config_hash()))
+    }
+
+    fn config_hash(&self) -> String {
+        hash_string(&format!("{:?}", self.config))
+    }
+
+    /// Run parameters (config hash, gazetteer size) and dataset metrics
+    /// (document and span counts, per label) reported by `log_run`
+    fn tracking<'py>(&self, py: Python<'py>) -> PyResult<(&'py PyDict, &'py PyDict)> {
+        let params = PyDict::new(py);
+        params.set_item("quickner.config_hash", self.config_hash())?;
+        params.set_item("quickner.config_path", &self.config_path)?;
+        params.set_item(
+            "quickner.format",
+            self.config.annotations.format.to_string(),
+        )?;
+        params.set_item(
+            "quickner.case_sensitive",
+            self.config.entities.filters.case_sensitive,
+        )?;
+        params.set_item("quickner.entities", self.quickner.entities.len())?;
+
+        let stats = self.quickner.stats();
+        let metrics = PyDict::new(py);
+        metrics.set_item("documents", stats.documents)?;
+        metrics.set_item("annotated_documents", stats.annotated_documents)?;
+        metrics.set_item(
+            "unannotated_documents",
+            stats.documents - stats.annotated_documents,
+        )?;
+        metrics.set_item("spans", stats.spans)?;
+        metrics.set_item("labels", stats.labels.len())?;
+        for (label, stats) in &stats.labels {
+            metrics.set_item(format!("labels.{label}.spans"), stats.spans)?;
+            metrics.set_item(format!("labels.{label}.documents"), stats.documents)?;
+        }
+        Ok((params, metrics))
+    }
+
+    /// Refresh the Python documents and entities from the inner Quickner
+    fn sync(&mut self) {
+        self.documents = self
+            .quickner
+            .documents
+            .clone()
+            .into_iter()
+            .map(PyDocument::from)
+            .collect::<Vec<PyDocument>>();
+        self.entities = self
+            .quickner
+            .entities
+            .clone()
+            .into_iter()
+            .map(PyEntity::from)
+            .collect::<Vec<PyEntity>>();
+    }
 }
 
 impl From<Quickner> for PyQuickner {
     fn from(quickner: Quickner) -> Self {
         PyQuickner {
             quickner: quickner.clone(),
-            config: PyConfig {
-                texts: PyTexts {
-                    input: PyInput {
-                        path: quickner.config.texts.input.path,
-                        filter: quickner.config.texts.input.filter,
-                    },
-                    filters: PyFilters {
-                        alphanumeric: quickner.config.texts.filters.alphanumeric,
-                        case_sensitive: quickner.config.texts.filters.case_sensitive,
-                        min_length: quickner.config.texts.filters.min_length,
-                        max_length: quickner.config.texts.filters.max_length,
-                        punctuation: quickner.config.texts.filters.punctuation,
-                        numbers: quickner.config.texts.filters.numbers,
-                        special_characters: quickner.config.texts.filters.special_characters,
-                        accept_special_characters: quickner
-                            .config
-                            .texts
-                            .filters
-                            .accept_special_characters,
-                        list_of_special_characters: quickner
-                            .config
-                            .texts
-                            .filters
-                            .list_of_special_characters
-                            .map(|list| list.into_iter().collect::<Vec<char>>()),
-                    },
-                },
-                annotations: PyAnnotations {
-                    output: PyOutput {
-                        path: quickner.config.annotations.output.path,
-                    },
-                    format: match quickner.config.annotations.format {
-                        quickner::Format::Csv => PyFormat::CSV,
-                        quickner::Format::Jsonl => PyFormat::JSONL,
-                        quickner::Format::Spacy => PyFormat::SPACY,
-                        quickner::Format::Brat => PyFormat::BRAT,
-                        quickner::Format::Conll => PyFormat::CONLL,
-                    },
-                },
-                entities: PyEntities {
-                    input: PyInput {
-                        path: quickner.config.entities.input.path,
-                        filter: quickner.config.entities.input.filter,
-                    },
-                    filters: PyFilters {
-                        alphanumeric: quickner.config.entities.filters.alphanumeric,
-                        case_sensitive: quickner.config.entities.filters.case_sensitive,
-                        min_length: quickner.config.entities.filters.min_length,
-                        max_length: quickner.config.entities.filters.max_length,
-                        punctuation: quickner.config.entities.filters.punctuation,
-                        numbers: quickner.config.entities.filters.numbers,
-                        special_characters: quickner.config.entities.filters.special_characters,
-                        accept_special_characters: quickner
-                            .config
-                            .entities
-                            .filters
-                            .accept_special_characters,
-                        list_of_special_characters: quickner
-                            .config
-                            .entities
-                            .filters
-                            .list_of_special_characters
-                            .map(|list| list.into_iter().collect::<Vec<char>>()),
-                    },
-                    excludes: PyExcludes {
-                        path: quickner.config.entities.excludes.path,
-                    },
-                },
-                logging: match quickner.config.logging {
-                    Some(logging) => Some(PyLogging {
-                        level: logging.level,
-                    }),
-                    None => None,
-                },
-            },
+            config: PyConfig::from_config(quickner.config),
             config_path: quickner.config_file.unwrap_or("".to_string()),
             documents: quickner
                 .documents
                 .iter()
-                .map(|annotation| {
-                    PyDocument::new(annotation.text.as_str(), Some(annotation.label.clone()))
-                })
+                .cloned()
+                .map(PyDocument::from)
                 .collect(),
             entities: quickner
                 .entities
                 .iter()
-                .map(|entity| PyEntity {
-                    name: entity.name.clone(),
-                    label: entity.label.clone(),
-                })
+                .cloned()
+                .map(PyEntity::from)
                 .collect(),
         }
     }
 }
+
+/// Annotate the documents, then save them if `save`, stamped with the hash
+/// of the Python configuration and the gazetteer loaded by the run.
+/// Called without the GIL.
+fn run(quickner: &mut Quickner, save: bool, config_hash: String) -> PyResult<RunReport> {
+    let mut report = quickner
+        .process(false)
+        .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+    if save {
+        let stamp = Stamp::new()
+            .with_gazetteer(&quickner.entities)
+            .with_config_hash(Some(config_hash));
+        let annotations = &quickner.config.annotations;
+        let saved = quickner
+            .install(|| {
+                annotations
+                    .format
+                    .save_stamped(&quickner.documents, &annotations.output, &stamp)
+            })
+            .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        report.output = Some(saved);
+        if annotations.output.report {
+            report
+                .save(&RunReport::path_for(&annotations.output.path))
+                .map_err(|error| QuicknerError::new_err(error.to_string()))?;
+        }
+    }
+    Ok(report)
+}
+
+/// Similarity thresholds are between 0 (excluded) and 1
+fn check_threshold(threshold: f64) -> PyResult<()> {
+    if threshold > 0.0 && threshold <= 1.0 {
+        Ok(())
+    } else {
+        Err(QuicknerError::new_err(format!(
+            "threshold must be between 0 and 1, got {threshold}"
+        )))
+    }
+}
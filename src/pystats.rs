@@ -0,0 +1,989 @@
+use std::collections::{BTreeMap, HashMap};
+
+use numpy::PyArray2;
+use pyo3::{prelude::*, types::PyDict};
+
+use crate::QuicknerError;
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "LabelStats")]
+pub struct PyLabelStats {
+    #[pyo3(get)]
+    pub spans: usize,
+    #[pyo3(get)]
+    pub documents: usize,
+    #[pyo3(get)]
+    pub surfaces: usize,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "Stats")]
+pub struct PyStats {
+    #[pyo3(get)]
+    pub documents: usize,
+    #[pyo3(get)]
+    pub annotated_documents: usize,
+    #[pyo3(get)]
+    pub spans: usize,
+    #[pyo3(get)]
+    pub entities: usize,
+    #[pyo3(get)]
+    pub labels: HashMap<String, PyLabelStats>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "PeriodStats")]
+pub struct PyPeriodStats {
+    #[pyo3(get)]
+    pub period: String,
+    #[pyo3(get)]
+    pub documents: usize,
+    #[pyo3(get)]
+    pub annotated_documents: usize,
+    #[pyo3(get)]
+    pub spans: usize,
+    #[pyo3(get)]
+    pub new_entities: usize,
+    /// (entity, label, count, new) of the entities annotated in the period
+    #[pyo3(get)]
+    pub entities: Vec<(String, String, usize, bool)>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "TimeStats")]
+pub struct PyTimeStats {
+    #[pyo3(get)]
+    pub field: String,
+    #[pyo3(get)]
+    pub period: String,
+    #[pyo3(get)]
+    pub periods: Vec<PyPeriodStats>,
+    #[pyo3(get)]
+    pub undated: usize,
+    stats: quickner::TimeStats,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "Cooccurrences")]
+pub struct PyCooccurrences {
+    #[pyo3(get)]
+    pub window: Option<usize>,
+    /// (source, source_label, target, target_label, count) of the pairs of entities
+    #[pyo3(get)]
+    pub entities: Vec<(String, String, String, String, usize)>,
+    #[pyo3(get)]
+    pub labels: HashMap<String, HashMap<String, usize>>,
+    cooccurrences: quickner::Cooccurrences,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "GazetteerStats")]
+pub struct PyGazetteerStats {
+    #[pyo3(get)]
+    pub patterns: usize,
+    #[pyo3(get)]
+    pub memory: usize,
+    #[pyo3(get)]
+    pub longest: Option<String>,
+    #[pyo3(get)]
+    pub shortest: Option<String>,
+    #[pyo3(get)]
+    pub duplicates: HashMap<String, Vec<String>>,
+    #[pyo3(get)]
+    pub substrings: HashMap<String, Vec<String>>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "LabelSuggestion")]
+pub struct PyLabelSuggestion {
+    #[pyo3(get)]
+    pub term: String,
+    #[pyo3(get)]
+    pub label: String,
+    #[pyo3(get)]
+    pub score: f32,
+    #[pyo3(get)]
+    pub frequency: usize,
+    #[pyo3(get)]
+    pub neighbors: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "EntityCandidate")]
+pub struct PyEntityCandidate {
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub label: Option<String>,
+    #[pyo3(get)]
+    pub frequency: usize,
+    #[pyo3(get)]
+    pub score: f32,
+    #[pyo3(get)]
+    pub context: Vec<String>,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "Metrics")]
+pub struct PyMetrics {
+    #[pyo3(get)]
+    pub precision: f64,
+    #[pyo3(get)]
+    pub recall: f64,
+    #[pyo3(get)]
+    pub f1: f64,
+    #[pyo3(get)]
+    pub true_positives: usize,
+    #[pyo3(get)]
+    pub false_positives: usize,
+    #[pyo3(get)]
+    pub false_negatives: usize,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "Evaluation")]
+pub struct PyEvaluation {
+    #[pyo3(get)]
+    pub overall: PyMetrics,
+    #[pyo3(get)]
+    pub labels: HashMap<String, PyMetrics>,
+    #[pyo3(get)]
+    pub documents: usize,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "SpanError")]
+pub struct PySpanError {
+    #[pyo3(get)]
+    pub line: usize,
+    #[pyo3(get)]
+    pub id: Option<String>,
+    #[pyo3(get)]
+    pub span: Option<(usize, usize, String)>,
+    #[pyo3(get)]
+    pub kind: String,
+    #[pyo3(get)]
+    pub message: String,
+}
+
+/// A span of a document with its provenance
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "Span")]
+pub struct PySpan {
+    #[pyo3(get)]
+    pub start: usize,
+    #[pyo3(get)]
+    pub end: usize,
+    #[pyo3(get)]
+    pub label: String,
+    /// Annotator of the span: gazetteer, fuzzy, regex, rule, import or unknown
+    #[pyo3(get)]
+    pub source: String,
+    #[pyo3(get)]
+    pub entity_name: Option<String>,
+    #[pyo3(get)]
+    pub confidence: Option<f32>,
+    /// Decision taken when the annotators gave the span different labels
+    #[pyo3(get)]
+    pub aggregation: Option<PyAggregation>,
+}
+
+impl From<quickner::Span> for PySpan {
+    fn from(span: quickner::Span) -> Self {
+        PySpan {
+            start: span.start,
+            end: span.end,
+            label: span.label,
+            source: span.source.to_string(),
+            entity_name: span.entity_name,
+            confidence: span.confidence,
+            aggregation: span.aggregation.map(PyAggregation::from),
+        }
+    }
+}
+
+/// Aggregation of the labels given to a span by the annotators
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "Aggregation")]
+pub struct PyAggregation {
+    /// Strategy of the decision: priority, vote or skip-conflicts
+    #[pyo3(get)]
+    pub strategy: String,
+    /// Sources which gave the span its label
+    #[pyo3(get)]
+    pub sources: Vec<String>,
+    #[pyo3(get)]
+    pub score: f64,
+    /// Weighted votes for the labels dropped, by label
+    #[pyo3(get)]
+    pub rejected: BTreeMap<String, f64>,
+}
+
+impl From<quickner::Aggregation> for PyAggregation {
+    fn from(aggregation: quickner::Aggregation) -> Self {
+        PyAggregation {
+            strategy: aggregation.strategy.to_string(),
+            sources: aggregation
+                .sources
+                .iter()
+                .map(|source| source.to_string())
+                .collect(),
+            score: aggregation.score,
+            rejected: aggregation.rejected,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "DuplicateCluster")]
+pub struct PyDuplicateCluster {
+    #[pyo3(get)]
+    pub kept: String,
+    /// (id, similarity) of the duplicates of the kept document
+    #[pyo3(get)]
+    pub duplicates: Vec<(String, f64)>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[pyclass(name = "MergeReport")]
+pub struct PyMergeReport {
+    #[pyo3(get)]
+    pub documents_added: usize,
+    #[pyo3(get)]
+    pub documents_identical: usize,
+    #[pyo3(get)]
+    pub conflicts: usize,
+    /// Ids of the documents with different texts, kept as they were
+    #[pyo3(get)]
+    pub text_conflicts: Vec<String>,
+    #[pyo3(get)]
+    pub entities_added: usize,
+}
+
+impl From<quickner::MergeReport> for PyMergeReport {
+    fn from(report: quickner::MergeReport) -> Self {
+        PyMergeReport {
+            documents_added: report.documents_added,
+            documents_identical: report.documents_identical,
+            conflicts: report.conflicts,
+            text_conflicts: report.text_conflicts,
+            entities_added: report.entities_added,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[pyclass(name = "PruneReport")]
+pub struct PyPruneReport {
+    #[pyo3(get)]
+    pub documents: usize,
+    /// (name, label, documents) of the entities matched in too many documents
+    #[pyo3(get)]
+    pub frequent: Vec<(String, String, usize)>,
+    /// (name, label, documents) of the entities matched in too few documents
+    #[pyo3(get)]
+    pub rare: Vec<(String, String, usize)>,
+    #[pyo3(get)]
+    pub spans_removed: usize,
+}
+
+impl From<quickner::PruneReport> for PyPruneReport {
+    fn from(report: quickner::PruneReport) -> Self {
+        let entities = |entities: Vec<quickner::PrunedEntity>| {
+            entities
+                .into_iter()
+                .map(|entity| (entity.name, entity.label, entity.documents))
+                .collect()
+        };
+        PyPruneReport {
+            documents: report.documents,
+            frequent: entities(report.frequent),
+            rare: entities(report.rare),
+            spans_removed: report.spans_removed,
+        }
+    }
+}
+
+impl From<quickner::DuplicateCluster> for PyDuplicateCluster {
+    fn from(cluster: quickner::DuplicateCluster) -> Self {
+        PyDuplicateCluster {
+            kept: cluster.kept,
+            duplicates: cluster.duplicates,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+#[pyclass(name = "RunReport")]
+pub struct PyRunReport {
+    #[pyo3(get)]
+    pub started: String,
+    #[pyo3(get)]
+    pub seconds: f64,
+    #[pyo3(get)]
+    pub threads: usize,
+    #[pyo3(get)]
+    pub output: Option<String>,
+    #[pyo3(get)]
+    pub stats: PyStats,
+    #[pyo3(get)]
+    pub gazetteer: HashMap<String, usize>,
+    /// (entity, label, count) of the most annotated entities
+    #[pyo3(get)]
+    pub top_entities: Vec<(String, String, usize)>,
+    #[pyo3(get)]
+    pub filtered_texts: usize,
+    #[pyo3(get)]
+    pub duplicate_texts: usize,
+    #[pyo3(get)]
+    pub near_duplicate_texts: usize,
+    /// (stage, seconds) of each stage of the run
+    #[pyo3(get)]
+    pub stages: Vec<(String, f64)>,
+    /// The whole report as JSON, configuration included
+    pub json: String,
+}
+
+impl From<quickner::RunReport> for PyRunReport {
+    fn from(report: quickner::RunReport) -> Self {
+        PyRunReport {
+            json: report.to_json(),
+            started: report.started,
+            seconds: report.seconds,
+            threads: report.threads,
+            output: report.output,
+            stats: PyStats::from(report.stats),
+            gazetteer: report.gazetteer.into_iter().collect(),
+            top_entities: report
+                .top_entities
+                .into_iter()
+                .map(|entity| (entity.name, entity.label, entity.count))
+                .collect(),
+            filtered_texts: report.skipped_texts.filtered,
+            duplicate_texts: report.skipped_texts.duplicates,
+            near_duplicate_texts: report.skipped_texts.near_duplicates,
+            stages: report
+                .stages
+                .into_iter()
+                .map(|stage| (stage.stage, stage.seconds))
+                .collect(),
+        }
+    }
+}
+
+impl From<quickner::SpanError> for PySpanError {
+    fn from(error: quickner::SpanError) -> Self {
+        PySpanError {
+            line: error.line,
+            id: error.id,
+            span: error.span,
+            kind: error.kind.to_string(),
+            message: error.message,
+        }
+    }
+}
+
+impl From<quickner::LabelStats> for PyLabelStats {
+    fn from(stats: quickner::LabelStats) -> Self {
+        PyLabelStats {
+            spans: stats.spans,
+            documents: stats.documents,
+            surfaces: stats.surfaces,
+        }
+    }
+}
+
+impl From<quickner::Stats> for PyStats {
+    fn from(stats: quickner::Stats) -> Self {
+        PyStats {
+            documents: stats.documents,
+            annotated_documents: stats.annotated_documents,
+            spans: stats.spans,
+            entities: stats.entities,
+            labels: stats
+                .labels
+                .into_iter()
+                .map(|(label, stats)| (label, PyLabelStats::from(stats)))
+                .collect(),
+        }
+    }
+}
+
+impl From<quickner::PeriodStats> for PyPeriodStats {
+    fn from(stats: quickner::PeriodStats) -> Self {
+        PyPeriodStats {
+            period: stats.period,
+            documents: stats.documents,
+            annotated_documents: stats.annotated_documents,
+            spans: stats.spans,
+            new_entities: stats.new_entities,
+            entities: stats
+                .entities
+                .into_iter()
+                .map(|entity| (entity.entity, entity.label, entity.count, entity.new))
+                .collect(),
+        }
+    }
+}
+
+impl From<quickner::TimeStats> for PyTimeStats {
+    fn from(stats: quickner::TimeStats) -> Self {
+        PyTimeStats {
+            field: stats.field.to_string(),
+            period: stats.period.to_string(),
+            periods: stats
+                .periods
+                .iter()
+                .cloned()
+                .map(PyPeriodStats::from)
+                .collect(),
+            undated: stats.undated,
+            stats,
+        }
+    }
+}
+
+impl From<quickner::Cooccurrences> for PyCooccurrences {
+    fn from(cooccurrences: quickner::Cooccurrences) -> Self {
+        PyCooccurrences {
+            window: cooccurrences.window,
+            entities: cooccurrences
+                .entities
+                .iter()
+                .map(|pair| {
+                    (
+                        pair.source.clone(),
+                        pair.source_label.clone(),
+                        pair.target.clone(),
+                        pair.target_label.clone(),
+                        pair.count,
+                    )
+                })
+                .collect(),
+            labels: cooccurrences
+                .labels
+                .iter()
+                .map(|(label, counts)| (label.clone(), counts.clone().into_iter().collect()))
+                .collect(),
+            cooccurrences,
+        }
+    }
+}
+
+impl From<quickner::GazetteerStats> for PyGazetteerStats {
+    fn from(stats: quickner::GazetteerStats) -> Self {
+        PyGazetteerStats {
+            patterns: stats.patterns,
+            memory: stats.memory,
+            longest: stats.longest,
+            shortest: stats.shortest,
+            duplicates: stats.duplicates.into_iter().collect(),
+            substrings: stats.substrings.into_iter().collect(),
+        }
+    }
+}
+
+impl From<quickner::LabelSuggestion> for PyLabelSuggestion {
+    fn from(suggestion: quickner::LabelSuggestion) -> Self {
+        PyLabelSuggestion {
+            term: suggestion.term,
+            label: suggestion.label,
+            score: suggestion.score,
+            frequency: suggestion.frequency,
+            neighbors: suggestion.neighbors,
+        }
+    }
+}
+
+impl From<quickner::EntityCandidate> for PyEntityCandidate {
+    fn from(candidate: quickner::EntityCandidate) -> Self {
+        PyEntityCandidate {
+            name: candidate.name,
+            label: candidate.label,
+            frequency: candidate.frequency,
+            score: candidate.score,
+            context: candidate.context,
+        }
+    }
+}
+
+impl From<quickner::Metrics> for PyMetrics {
+    fn from(metrics: quickner::Metrics) -> Self {
+        PyMetrics {
+            precision: metrics.precision,
+            recall: metrics.recall,
+            f1: metrics.f1,
+            true_positives: metrics.true_positives,
+            false_positives: metrics.false_positives,
+            false_negatives: metrics.false_negatives,
+        }
+    }
+}
+
+impl From<quickner::Evaluation> for PyEvaluation {
+    fn from(evaluation: quickner::Evaluation) -> Self {
+        PyEvaluation {
+            overall: PyMetrics::from(evaluation.overall),
+            labels: evaluation
+                .labels
+                .into_iter()
+                .map(|(label, metrics)| (label, PyMetrics::from(metrics)))
+                .collect(),
+            documents: evaluation.documents,
+        }
+    }
+}
+
+#[pymethods]
+impl PyLabelStats {
+    /// Return the statistics as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("spans", self.spans)?;
+        dict.set_item("documents", self.documents)?;
+        dict.set_item("surfaces", self.surfaces)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "LabelStats(spans={}, documents={}, surfaces={})",
+            self.spans, self.documents, self.surfaces
+        ))
+    }
+}
+
+#[pymethods]
+impl PyStats {
+    /// Return the statistics as a dict, labels included
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("documents", self.documents)?;
+        dict.set_item("annotated_documents", self.annotated_documents)?;
+        dict.set_item("spans", self.spans)?;
+        dict.set_item("entities", self.entities)?;
+        let labels = PyDict::new(py);
+        for (label, stats) in &self.labels {
+            labels.set_item(label, stats.to_dict(py)?)?;
+        }
+        dict.set_item("labels", labels)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Stats(documents={}, annotated_documents={}, spans={}, entities={}, labels={})",
+            self.documents,
+            self.annotated_documents,
+            self.spans,
+            self.entities,
+            self.labels.len()
+        ))
+    }
+}
+
+#[pymethods]
+impl PyPeriodStats {
+    /// Return the statistics as a dict, entities included
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("period", &self.period)?;
+        dict.set_item("documents", self.documents)?;
+        dict.set_item("annotated_documents", self.annotated_documents)?;
+        dict.set_item("spans", self.spans)?;
+        dict.set_item("new_entities", self.new_entities)?;
+        let mut entities = Vec::new();
+        for (entity, label, count, new) in &self.entities {
+            let item = PyDict::new(py);
+            item.set_item("entity", entity)?;
+            item.set_item("label", label)?;
+            item.set_item("count", count)?;
+            item.set_item("new", new)?;
+            entities.push(item);
+        }
+        dict.set_item("entities", entities)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "PeriodStats(period={}, documents={}, spans={}, new_entities={})",
+            self.period, self.documents, self.spans, self.new_entities
+        ))
+    }
+}
+
+#[pymethods]
+impl PyTimeStats {
+    /// Return the statistics as a dict, periods included
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("field", &self.field)?;
+        dict.set_item("period", &self.period)?;
+        let periods = self
+            .periods
+            .iter()
+            .map(|period| period.to_dict(py))
+            .collect::<PyResult<Vec<&PyDict>>>()?;
+        dict.set_item("periods", periods)?;
+        dict.set_item("undated", self.undated)?;
+        Ok(dict)
+    }
+
+    /// Save the entity frequencies to a CSV file, one row per entity and period
+    /// with the columns period, documents, label, entity, count and new
+    #[pyo3(signature = (path))]
+    pub fn to_csv(&self, path: &str) -> PyResult<()> {
+        self.stats
+            .to_csv(path)
+            .map_err(|error| QuicknerError::new_err(error.to_string()))
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "TimeStats(field={}, period={}, periods={}, undated={})",
+            self.field,
+            self.period,
+            self.periods.len(),
+            self.undated
+        ))
+    }
+}
+
+#[pymethods]
+impl PyGazetteerStats {
+    /// Return the statistics as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("patterns", self.patterns)?;
+        dict.set_item("memory", self.memory)?;
+        dict.set_item("longest", &self.longest)?;
+        dict.set_item("shortest", &self.shortest)?;
+        dict.set_item("duplicates", &self.duplicates)?;
+        dict.set_item("substrings", &self.substrings)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "GazetteerStats(patterns={}, memory={}, duplicates={}, substrings={})",
+            self.patterns,
+            self.memory,
+            self.duplicates.len(),
+            self.substrings.len()
+        ))
+    }
+}
+
+#[pymethods]
+impl PyLabelSuggestion {
+    /// Return the suggestion as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("term", &self.term)?;
+        dict.set_item("label", &self.label)?;
+        dict.set_item("score", self.score)?;
+        dict.set_item("frequency", self.frequency)?;
+        dict.set_item("neighbors", &self.neighbors)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "LabelSuggestion(term={}, label={}, score={:.4}, frequency={})",
+            self.term, self.label, self.score, self.frequency
+        ))
+    }
+}
+
+#[pymethods]
+impl PyCooccurrences {
+    /// Return the co-occurrences as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("window", self.window)?;
+        let entities = self
+            .cooccurrences
+            .entities
+            .iter()
+            .map(|pair| {
+                let entity = PyDict::new(py);
+                entity.set_item("source", &pair.source)?;
+                entity.set_item("source_label", &pair.source_label)?;
+                entity.set_item("target", &pair.target)?;
+                entity.set_item("target_label", &pair.target_label)?;
+                entity.set_item("count", pair.count)?;
+                Ok(entity)
+            })
+            .collect::<PyResult<Vec<&PyDict>>>()?;
+        dict.set_item("entities", entities)?;
+        dict.set_item("labels", &self.labels)?;
+        Ok(dict)
+    }
+
+    /// Return the labels and the symmetric numpy matrix of their counts
+    pub fn label_matrix<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Vec<String>, &'py PyArray2<usize>)> {
+        let (labels, matrix) = self.cooccurrences.label_matrix();
+        Ok((labels, matrix_to_numpy(py, matrix)?))
+    }
+
+    /// Return the entities, as (name, label), and the symmetric numpy matrix of their counts
+    pub fn entity_matrix<'py>(&self, py: Python<'py>) -> PyResult<EntityMatrix<'py>> {
+        let (entities, matrix) = self.cooccurrences.entity_matrix();
+        Ok((entities, matrix_to_numpy(py, matrix)?))
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Cooccurrences(window={}, entities={}, labels={})",
+            self.window
+                .map_or_else(|| "None".to_string(), |window| window.to_string()),
+            self.entities.len(),
+            self.labels.len()
+        ))
+    }
+}
+
+/// Entities, as (name, label), and the numpy matrix of their co-occurrences
+type EntityMatrix<'py> = (Vec<(String, String)>, &'py PyArray2<usize>);
+
+/// A square matrix as a numpy array, an empty matrix has the shape (0, 0)
+fn matrix_to_numpy(py: Python<'_>, matrix: Vec<Vec<usize>>) -> PyResult<&PyArray2<usize>> {
+    let size = matrix.len();
+    let values: Vec<usize> = matrix.into_iter().flatten().collect();
+    numpy::PyArray1::from_vec(py, values)
+        .reshape([size, size])
+        .map_err(|error| QuicknerError::new_err(error.to_string()))
+}
+
+#[pymethods]
+impl PyEntityCandidate {
+    /// Return the candidate as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("label", &self.label)?;
+        dict.set_item("frequency", self.frequency)?;
+        dict.set_item("score", self.score)?;
+        dict.set_item("context", &self.context)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "EntityCandidate(name={}, label={}, frequency={}, score={:.4})",
+            self.name,
+            self.label.as_deref().unwrap_or("None"),
+            self.frequency,
+            self.score
+        ))
+    }
+}
+
+#[pymethods]
+impl PyMetrics {
+    /// Return the metrics as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("precision", self.precision)?;
+        dict.set_item("recall", self.recall)?;
+        dict.set_item("f1", self.f1)?;
+        dict.set_item("true_positives", self.true_positives)?;
+        dict.set_item("false_positives", self.false_positives)?;
+        dict.set_item("false_negatives", self.false_negatives)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Metrics(precision={:.4}, recall={:.4}, f1={:.4})",
+            self.precision, self.recall, self.f1
+        ))
+    }
+}
+
+#[pymethods]
+impl PyEvaluation {
+    /// Return the evaluation as a dict, per-label metrics included
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("overall", self.overall.to_dict(py)?)?;
+        let labels = PyDict::new(py);
+        for (label, metrics) in &self.labels {
+            labels.set_item(label, metrics.to_dict(py)?)?;
+        }
+        dict.set_item("labels", labels)?;
+        dict.set_item("documents", self.documents)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Evaluation(overall={}, labels={}, documents={})",
+            self.overall.__repr__()?,
+            self.labels.len(),
+            self.documents
+        ))
+    }
+}
+
+#[pymethods]
+impl PySpanError {
+    /// Return the error as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("line", self.line)?;
+        dict.set_item("id", self.id.clone())?;
+        dict.set_item("span", self.span.clone())?;
+        dict.set_item("kind", self.kind.clone())?;
+        dict.set_item("message", self.message.clone())?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "SpanError(line={}, kind={}, message={:?})",
+            self.line, self.kind, self.message
+        ))
+    }
+}
+
+#[pymethods]
+impl PySpan {
+    /// Return the span as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("start", self.start)?;
+        dict.set_item("end", self.end)?;
+        dict.set_item("label", self.label.clone())?;
+        dict.set_item("source", self.source.clone())?;
+        dict.set_item("entity_name", self.entity_name.clone())?;
+        dict.set_item("confidence", self.confidence)?;
+        let aggregation = match &self.aggregation {
+            Some(aggregation) => Some(aggregation.to_dict(py)?),
+            None => None,
+        };
+        dict.set_item("aggregation", aggregation)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Span(start={}, end={}, label={}, source={})",
+            self.start, self.end, self.label, self.source
+        ))
+    }
+}
+
+#[pymethods]
+impl PyAggregation {
+    /// Return the aggregation as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("strategy", self.strategy.clone())?;
+        dict.set_item("sources", self.sources.clone())?;
+        dict.set_item("score", self.score)?;
+        dict.set_item("rejected", self.rejected.clone())?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "Aggregation(strategy={}, sources={:?}, score={})",
+            self.strategy, self.sources, self.score
+        ))
+    }
+}
+
+#[pymethods]
+impl PyDuplicateCluster {
+    /// Return the cluster as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("kept", self.kept.clone())?;
+        dict.set_item("duplicates", self.duplicates.clone())?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "DuplicateCluster(kept={}, duplicates={})",
+            self.kept,
+            self.duplicates.len()
+        ))
+    }
+}
+
+#[pymethods]
+impl PyPruneReport {
+    /// Return the report as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("documents", self.documents)?;
+        dict.set_item("frequent", self.frequent.clone())?;
+        dict.set_item("rare", self.rare.clone())?;
+        dict.set_item("spans_removed", self.spans_removed)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "PruneReport(documents={}, frequent={}, rare={}, spans_removed={})",
+            self.documents,
+            self.frequent.len(),
+            self.rare.len(),
+            self.spans_removed
+        ))
+    }
+}
+
+#[pymethods]
+impl PyMergeReport {
+    /// Return the report as a dict
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("documents_added", self.documents_added)?;
+        dict.set_item("documents_identical", self.documents_identical)?;
+        dict.set_item("conflicts", self.conflicts)?;
+        dict.set_item("text_conflicts", self.text_conflicts.clone())?;
+        dict.set_item("entities_added", self.entities_added)?;
+        Ok(dict)
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "MergeReport(documents_added={}, documents_identical={}, conflicts={}, text_conflicts={}, entities_added={})",
+            self.documents_added,
+            self.documents_identical,
+            self.conflicts,
+            self.text_conflicts.len(),
+            self.entities_added
+        ))
+    }
+}
+
+#[pymethods]
+impl PyRunReport {
+    /// Return the report as JSON, as written next to the annotations
+    pub fn to_json(&self) -> String {
+        self.json.clone()
+    }
+
+    /// Return the report as a dict, configuration included
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let json = py.import("json")?;
+        json.call_method1("loads", (self.json.as_str(),))?
+            .downcast::<PyDict>()
+            .map_err(|error| QuicknerError::new_err(error.to_string()))
+    }
+
+    pub fn __repr__(&self) -> PyResult<String> {
+        Ok(format!(
+            "RunReport(documents={}, spans={}, entities={}, seconds={:.3})",
+            self.stats.documents, self.stats.spans, self.stats.entities, self.seconds
+        ))
+    }
+}
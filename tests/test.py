@@ -1,6 +1,37 @@
+import asyncio
+import csv
+import gzip
+import importlib.util
+import json
+import os
+import re
+import subprocess
+import sys
+import tempfile
+import types
 import unittest
+from unittest import mock
 
-from quickner import Quickner, Document, Entity
+from quickner import Config, Format, Matcher, Quickner, QuicknerError, Document, Entity, from_dir, from_jsonl, from_spacy
+from quickner import annotate_batch, byte_to_char, init_logging, char_to_byte, find_abbreviations, get_preview_length, set_label_display, set_preview_length
+
+# The command line tests run the quickner binary of QUICKNER_BIN or of a cargo build of the repository
+ROOT = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+QUICKNER_BIN = os.environ.get("QUICKNER_BIN") or next(
+    (
+        path
+        for path in (
+            os.path.join(ROOT, "quickner-core", "target", "debug", "quickner"),
+            os.path.join(ROOT, "target", "debug", "quickner"),
+        )
+        if os.path.isfile(path)
+    ),
+    None,
+)
+
+
+def run_cli(*args, check=True) -> subprocess.CompletedProcess:
+    return subprocess.run([QUICKNER_BIN, *args], capture_output=True, text=True, check=check)
 
 
 # TODO(Omar): Significantly improve tests with pytest
@@ -164,6 +195,134 @@ class TestQuickner(unittest.TestCase):
         self.assertEqual(rust.label[0][2], "ORG")
         self.assertEqual(rust.label[1][2], "PL")
 
+    def test_preview_length(self):
+        document = Document("rust is made by Mozilla " * 100, [(0, 4, "PL")])
+        self.assertEqual(get_preview_length(), 100)
+        self.assertLess(len(repr(document)), 200)
+        self.assertIn("… (+", repr(document))
+        self.assertEqual(document.text, "rust is made by Mozilla " * 100)
+        self.assertIn("… (+", document.pretty())
+        self.assertNotIn("…", document.pretty(0))
+        try:
+            set_preview_length(0)
+            self.assertIn(document.text, repr(document))
+        finally:
+            set_preview_length(100)
+
+    def test_render_overlapping_spans(self):
+        document = Document("New York City <3", [(0, 13, "ORG"), (4, 8, "LOC")])
+        self.assertEqual(
+            document.pretty(0),
+            "\x1b[31mNew \x1b[0m\x1b[32mYork\x1b[0m[LOC]\x1b[31m City\x1b[0m[ORG] <3",
+        )
+        html = document.render_html()
+        self.assertIn('data-labels="ORG LOC"', html)
+        self.assertIn('<span class="label">LOC</span>', html)
+        self.assertTrue(html.endswith(" &lt;3"))
+
+    def test_validate_spans(self):
+        document = Document("Café in New York", [(1, 4, "ORG"), (8, 16, "LOC"), (8, 16, "LOC"), (8, 20, "LOC")])
+        kinds = [error.kind for error in document.validate_spans()]
+        self.assertEqual(kinds, ["partial_word", "duplicate", "out_of_bounds"])
+        documents = [
+            Document("Café in New York", [(8, 16, "LOC")]),
+            Document("Café in New York today", [(9, 17, "LOC")]),
+            Document("Café in New York", [(0, 4, "LOC")]),
+        ]
+        quick = Quickner(documents=documents, entities=[Entity("New York", "LOC")])
+        errors = quick.validate()
+        self.assertEqual([(error.line, error.kind) for error in errors], [(2, "partial_word"), (3, "surface_mismatch")])
+        self.assertIn("bytes", errors[0].message)
+        self.assertEqual(errors[1].to_dict()["span"], (0, 4, "LOC"))
+
+    def test_matcher(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        matcher = Matcher(entities)
+        self.assertEqual(len(matcher), 10)
+        document = Document("Rust is made by Mozilla")
+        matcher.annotate(document)
+        self.assertEqual(document.text, "Rust is made by Mozilla")
+        self.assertEqual(document.label, [(0, 4, "PL"), (16, 23, "ORG")])
+        documents = [Document(text) for text in self.texts]
+        matcher.annotate_many(documents)
+        self.assertEqual(sum(len(document.label) for document in documents), 12)
+        quick = Quickner(documents=[Document(text) for text in self.texts], entities=entities)
+        quick.process()
+        compiled = quick.compile_matcher()
+        for document, processed in zip(documents, quick.documents):
+            copy = Document(document.text)
+            compiled.annotate(copy)
+            self.assertEqual(sorted(copy.label), sorted(processed.label))
+
+    def test_annotate_batch(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = annotate_batch(self.texts, entities)
+        self.assertEqual([document.text for document in documents], list(self.texts))
+        matcher = Matcher(entities)
+        expected = [Document(text) for text in self.texts]
+        matcher.annotate_many(expected)
+        self.assertEqual([document.label for document in documents], [document.label for document in expected])
+        self.assertEqual([document.id for document in documents], [document.id for document in expected])
+        single = annotate_batch(["Rust is made by Mozilla", "rust is made by mozilla"], entities, n_jobs=1, case_sensitive=True)
+        self.assertEqual([document.label for document in single], [[(0, 4, "PL"), (16, 23, "ORG")], []])
+        self.assertEqual(annotate_batch([], entities), [])
+        with self.assertRaises(ValueError):
+            annotate_batch(self.texts, entities, n_jobs=0)
+
+    def test_fuzzy_matching(self):
+        entities = [Entity("Mozilla", "ORG"), Entity("New York City", "LOC"), Entity("Rust", "PL")]
+        matcher = Matcher(entities, max_edits=1)
+        document = Document("Mozila and mozzilla released it; Mozilla too, Rsut in New Yrok City and NewYork City")
+        matcher.annotate(document)
+        # "Rsut" is too short, "Yrok" is two edits away
+        self.assertEqual(document.label, [(0, 6, "ORG"), (11, 19, "ORG"), (33, 40, "ORG"), (72, 84, "LOC")])
+        with self.assertRaises(ValueError):
+            Matcher(entities, max_edits=2, min_len=2)
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMicrosoft,ORG\nMicrosoft Word,PRODUCT\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nMicrsoft released Micros0ft Word for Microsoft.\n")
+            config_path = self._write_config(
+                directory, entities_path, texts_path, extra="\n[matching.fuzzy]\nmax_edits = 1\nmin_len = 6"
+            )
+            config = Config(config_path)
+            self.assertEqual((config.matching.fuzzy.max_edits, config.matching.fuzzy.min_len), (1, 6))
+            quick = Quickner(config=config)
+            quick.process()
+            self.assertEqual(quick.documents[0].label, [(0, 8, "ORG"), (18, 32, "PRODUCT"), (37, 46, "ORG")])
+            self.assertEqual(quick.validate(), [])
+            with open(config_path, "a") as f:
+                f.write("max_edits = 0\n")
+            with self.assertRaises(QuicknerError):
+                Config(config_path)
+
+    def test_shared_texts(self):
+        document = Document("Rust est créé par Mozilla")
+        self.assertIsInstance(document.text, str)
+        self.assertEqual(document.text, "Rust est créé par Mozilla")
+        self.assertEqual(Document.from_dict(document.to_dict()).text, document.text)
+        # The documents sharing a text keep it when one of them is lowercased
+        quick = Quickner(documents=[document, Document("Python was created by Guido van Rossum")])
+        quick.entities = [Entity(*(entity)) for entity in self.entities]
+        subset = quick.subset()
+        copy = quick.documents[0]
+        quick.process()
+        self.assertEqual(quick.documents[0].text, "rust est créé par mozilla")
+        self.assertEqual(quick.documents[0].label, [(0, 4, "PL")])
+        self.assertEqual(copy.text, "Rust est créé par Mozilla")
+        self.assertEqual(subset.documents[0].text, "Rust est créé par Mozilla")
+        self.assertEqual(document.text, "Rust est créé par Mozilla")
+        document.annotate([Entity("Rust", "PL")], case_sensitive=True)
+        self.assertEqual(document.text, "Rust est créé par Mozilla")
+        self.assertEqual(document.label, [(0, 4, "PL")])
+        with tempfile.TemporaryDirectory() as directory:
+            path = quick.save_annotations(os.path.join(directory, "annotations"), Format.JSONL)
+            loaded = Quickner.from_jsonl(path + ".jsonl")
+        self.assertEqual([document.text for document in loaded.documents], [document.text for document in quick.documents])
+
     def test_character_level_slicing(self):
         entity = Entity("Python", "PL")
         document = Document("Indizes auf Zeichenebene anstelle von Indizes auf Byteebene, um Python-Slicing zu unterstützen")
@@ -173,6 +332,2586 @@ class TestQuickner(unittest.TestCase):
         self.assertEqual(len(document.label), 1)
         self.assertEqual(label, "Python")
 
+    def test_annotate_incremental(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents, entities=entities[:4])
+        quick.process()
+        labels_count = sum(len(document.label) for document in quick.documents)
+        self.assertEqual(labels_count, 6)
+        quick.annotate_incremental(entities)
+        self.assertEqual(len(quick.entities), 10)
+        labels_count = sum(len(document.label) for document in quick.documents)
+        self.assertEqual(labels_count, 12)
+        self._test_correct(quick.documents)
+        self.assertEqual(len(quick.find_documents_by_label("PERSON")), 3)
+
+    def _write_config(
+        self, directory: str, entities_path: str, texts_path: str = None, extra: str = ""
+    ) -> str:
+        if texts_path is None:
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\n")
+                for text in self.texts:
+                    f.write(f'"{text}"\n')
+        split = 'split = "paragraph"' if os.path.isdir(texts_path) else ""
+        config_path = os.path.join(directory, "config.toml")
+        with open(config_path, "w") as f:
+            f.write(
+                f"""
+[texts.input]
+path = "{texts_path}"
+filter = false
+{split}
+
+[texts.filters]
+alphanumeric = false
+case_sensitive = false
+min_length = 0
+max_length = 1024
+punctuation = false
+numbers = false
+special_characters = false
+
+[annotations]
+format = "jsonl"
+
+[annotations.output]
+path = "{os.path.join(directory, "annotations.jsonl")}"
+
+[entities.input]
+path = "{entities_path}"
+filter = false
+
+[entities.filters]
+alphanumeric = false
+case_sensitive = false
+min_length = 0
+max_length = 1024
+punctuation = false
+numbers = false
+special_characters = false
+
+[entities.excludes]
+{extra}
+"""
+            )
+        return config_path
+
+    def test_case_sensitivity_of_the_entities_filters(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities[:4]:
+                    f.write(f"{name},{label}\n")
+            entities = [Entity(*(entity)) for entity in self.entities]
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                content = f.read()
+            # The texts filters are case sensitive, the entities filters are not
+            with open(config_path, "w") as f:
+                f.write(content.replace("case_sensitive = false", "case_sensitive = true", 1))
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            self.assertEqual(sum(len(document.label) for document in quick.documents), 6)
+            quick.annotate_incremental(entities)
+            self.assertEqual(sum(len(document.label) for document in quick.documents), 12)
+            self._test_correct(quick.documents)
+            # The other way around, "rust" is not matched
+            with open(config_path, "w") as f:
+                f.write("case_sensitive = true".join(content.rsplit("case_sensitive = false", 1)))
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            self.assertEqual(sum(len(document.label) for document in quick.documents), 4)
+            quick.annotate_incremental(entities)
+            self.assertEqual(sum(len(document.label) for document in quick.documents), 10)
+            self.assertEqual(quick.documents[0].label, [(16, 23, "ORG")])
+
+    def test_entities_from_json_and_txt(self):
+        with tempfile.TemporaryDirectory() as directory:
+            json_path = os.path.join(directory, "entities.json")
+            with open(json_path, "w") as f:
+                json.dump([{"name": name, "label": label} for name, label in self.entities], f)
+            quick = Quickner(config=Config(self._write_config(directory, json_path)))
+            quick.process()
+            self.assertEqual(len(quick.entities), 10)
+            self._test_correct(quick.documents)
+
+            txt_directory = os.path.join(directory, "gazetteer")
+            os.mkdir(txt_directory)
+            for name, label in self.entities:
+                with open(os.path.join(txt_directory, f"{label}.txt"), "a") as f:
+                    f.write(name + "\n")
+            quick = Quickner(config=Config(self._write_config(directory, txt_directory)))
+            quick.process()
+            self.assertEqual(len(quick.entities), 10)
+            self._test_correct(quick.documents)
+
+            config_path = self._write_config(directory, json_path)
+            with open(config_path) as f:
+                template = f.read()
+            with open(config_path, "w") as f:
+                f.write(template.replace("[entities.input]\n", '[entities.input]\nformat = "json"\n'))
+            self.assertEqual(Config(config_path).validate(), [])
+            with open(config_path, "w") as f:
+                f.write(template.replace("[texts.input]\n", '[texts.input]\nformat = "json"\n'))
+            with self.assertRaisesRegex(QuicknerError, "texts.input.format"):
+                Config(config_path)
+
+    def test_init_logging(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+            config = Config(self._write_config(directory, entities_path, extra='\n[logging]\nlevel = "error"\nformat = "json"'))
+            self.assertEqual(config.logging.format, "json")
+        with self.assertRaises(QuicknerError):
+            init_logging("error", format="xml")
+        # A single logger per process, the second call changes nothing
+        init_logging("error")
+        self.assertFalse(init_logging("debug", format="json"))
+
+    def test_config_includes(self):
+        with tempfile.TemporaryDirectory() as directory:
+            project = os.path.join(directory, "project")
+            shared = os.path.join(directory, "shared", "labels")
+            os.makedirs(project)
+            os.makedirs(shared)
+            with open(os.path.join(shared, "gazetteer.csv"), "w") as f:
+                f.write("name,label\nrust,PL\n")
+            with open(os.path.join(project, "texts.csv"), "w") as f:
+                f.write("text\nrust is fast\n")
+            with open(os.path.join(shared, "labels.toml"), "w") as f:
+                f.write('[entities.input]\npath = "gazetteer.csv"\nfilter = false\n\n[labels.priority]\norder = ["PL"]\n')
+            with open(os.path.join(directory, "shared", "base.toml"), "w") as f:
+                f.write('include = ["labels/labels.toml"]\n\n[labels.priority]\norder = ["ORG"]\n')
+            config_path = os.path.join(project, "config.toml")
+            with open(self._write_config(project, "unused.csv", "texts.csv")) as f:
+                base = f.read()
+            base = base.replace('[entities.input]\npath = "unused.csv"\nfilter = false\n', "")
+            with open(config_path, "w") as f:
+                f.write('include = ["../shared/base.toml"]\n' + base)
+            config = Config(config_path)
+            # The includes are merged in order, the including file wins
+            self.assertEqual(config.labels.priority.order, ["ORG"])
+            self.assertEqual(os.path.realpath(config.entities.input.path), os.path.realpath(os.path.join(shared, "gazetteer.csv")))
+            self.assertEqual(os.path.realpath(config.texts.input.path), os.path.realpath(os.path.join(project, "texts.csv")))
+            quick = Quickner(config=config)
+            quick.process()
+            self.assertEqual(quick.documents[0].label, [(0, 4, "PL")])
+            with open(os.path.join(directory, "shared", "base.toml"), "w") as f:
+                f.write('include = ["../project/config.toml"]\n')
+            with self.assertRaises(QuicknerError):
+                Config(config_path)
+
+    def test_label_aggregation(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+            jsonl_path = os.path.join(directory, "reviewed.jsonl")
+            with open(jsonl_path, "w") as f:
+                f.write(json.dumps({"id": "1", "text": "Jordan visited Jordan", "label": [[0, 6, "PERSON"]]}) + "\n")
+
+            def annotate(aggregation):
+                config = Config(self._write_config(directory, entities_path, extra=aggregation))
+                quick = Quickner(documents=from_jsonl(jsonl_path).documents, config=config)
+                quick.annotate_incremental([Entity("jordan", "LOC")])
+                return quick.documents[0]
+
+            # Every label is kept without a label priority
+            document = annotate("")
+            self.assertEqual(sorted(document.label), [(0, 6, "LOC"), (0, 6, "PERSON"), (15, 21, "LOC")])
+            extra = '\n[labels.aggregation]\nstrategy = "vote"\nweights = { import = 3.0 }'
+            config = Config(self._write_config(directory, entities_path, extra=extra))
+            self.assertEqual(config.labels.aggregation.strategy, "vote")
+            self.assertEqual(config.labels.aggregation.weights, {"import": 3.0})
+            document = annotate(extra)
+            self.assertEqual(document.label, [(0, 6, "PERSON"), (15, 21, "LOC")])
+            aggregation = document.spans()[0].aggregation
+            self.assertEqual(aggregation.strategy, "vote")
+            self.assertEqual(aggregation.sources, ["import"])
+            self.assertEqual(aggregation.score, 3.0)
+            self.assertEqual(aggregation.rejected, {"LOC": 1.0})
+            self.assertIsNone(document.spans()[1].aggregation)
+            # The gazetteer outvotes the import with a lower weight
+            document = annotate('\n[labels.aggregation]\nstrategy = "vote"\nweights = { import = 0.5 }')
+            self.assertEqual(document.label, [(0, 6, "LOC"), (15, 21, "LOC")])
+            self.assertEqual(document.spans()[0].aggregation.sources, ["gazetteer"])
+            document = annotate('\n[labels.aggregation]\nstrategy = "skip-conflicts"')
+            self.assertEqual(document.label, [(15, 21, "LOC")])
+            with self.assertRaises(QuicknerError):
+                Config(self._write_config(directory, entities_path, extra='\n[labels.aggregation]\nweights = { import = -1.0 }'))
+
+    def test_prune_entities(self):
+        documents = [
+            Document("May the rust team ship in May", [(0, 3, "PRODUCT"), (8, 12, "PL"), (26, 29, "PRODUCT")]),
+            Document("Rust may be fast", [(0, 4, "PL"), (5, 8, "PRODUCT")]),
+            Document("Python may be slow", [(0, 6, "PL"), (7, 10, "PRODUCT")]),
+        ]
+        entities = [Entity("may", "PRODUCT"), Entity("rust", "PL"), Entity("python", "PL"), Entity("cobol", "PL")]
+        quick = Quickner(documents=documents, entities=entities)
+        report = quick.prune_entities(max_doc_frequency=0.9, min_doc_frequency=1)
+        self.assertEqual(report.documents, 3)
+        self.assertEqual(report.frequent, [("may", "PRODUCT", 3)])
+        self.assertEqual(report.rare, [("cobol", "PL", 0)])
+        self.assertEqual(report.spans_removed, 4)
+        self.assertEqual([(entity.name, entity.label) for entity in quick.entities], [("rust", "PL"), ("python", "PL")])
+        self.assertEqual([document.label for document in quick.documents], [[(8, 12, "PL")], [(0, 4, "PL")], [(0, 6, "PL")]])
+        # Python is matched in a single document
+        report = quick.prune_entities(max_doc_frequency=1.0, min_doc_frequency=2)
+        self.assertEqual(report.rare, [("python", "PL", 1)])
+        self.assertEqual(report.spans_removed, 0)
+        self.assertEqual(report.to_dict()["frequent"], [])
+        with self.assertRaises(QuicknerError):
+            quick.prune_entities(max_doc_frequency=1.5)
+
+    def test_entity_set_operations(self):
+        quick = Quickner(entities=[Entity("rust", "PL"), Entity("mozilla", "ORG", id="Q169925")])
+        other = [Entity("mozilla", "ORG"), Entity("python", "PL"), Entity("rust", "GAME")]
+        pairs = lambda entities: [(entity.name, entity.label) for entity in entities]
+        self.assertEqual(
+            pairs(quick.entities_union(other)),
+            [("rust", "PL"), ("mozilla", "ORG"), ("python", "PL"), ("rust", "GAME")],
+        )
+        self.assertEqual(pairs(quick.entities_intersect(other)), [("mozilla", "ORG")])
+        self.assertEqual(quick.entities_intersect(other)[0].id, "Q169925")
+        self.assertEqual(pairs(quick.entities_diff(other)), [("rust", "PL")])
+        self.assertEqual(pairs(quick.entities_diff(Quickner(entities=other))), [("rust", "PL")])
+        self.assertEqual(len(quick.entities), 2)
+        with tempfile.TemporaryDirectory() as directory:
+            csv_path = os.path.join(directory, "gazetteer.csv")
+            quick.entities = quick.entities_union(other)
+            quick.export_entities(csv_path)
+            with open(csv_path) as f:
+                rows = list(csv.DictReader(f))
+            self.assertEqual(list(rows[0].keys()), ["name", "label", "id"])
+            self.assertEqual(rows[1], {"name": "mozilla", "label": "ORG", "id": "Q169925"})
+            json_path = os.path.join(directory, "gazetteer.json")
+            quick.export_entities(json_path)
+            with open(json_path) as f:
+                self.assertEqual(json.load(f)[1], {"name": "mozilla", "label": "ORG", "id": "Q169925"})
+            txt_path = os.path.join(directory, "gazetteer")
+            quick.export_entities(txt_path, format="txt")
+            self.assertEqual(sorted(os.listdir(txt_path)), ["GAME.txt", "ORG.txt", "PL.txt"])
+            with open(os.path.join(txt_path, "PL.txt")) as f:
+                self.assertEqual(f.read(), "rust\npython\n")
+            # The exported entities are read back as the entities of a configuration
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nrust is made by mozilla\n")
+            for path in [csv_path, json_path, txt_path]:
+                reread = Quickner(config=Config(self._write_config(directory, path, texts_path)))
+                reread.process()
+                self.assertEqual(len(reread.entities), 4)
+            with self.assertRaises(QuicknerError):
+                quick.export_entities(csv_path, format="xlsx")
+
+    def test_texts_from_directory(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            texts_directory = os.path.join(directory, "texts")
+            os.mkdir(texts_directory)
+            with open(os.path.join(texts_directory, "languages.txt"), "w") as f:
+                f.write("\n\n".join(self.texts[:2]))
+            with open(os.path.join(texts_directory, "more.txt"), "w") as f:
+                f.write("\n\n".join(self.texts[2:]))
+            config_path = self._write_config(directory, entities_path, texts_directory)
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+        self.assertEqual(len(quick.documents), 5)
+        self.assertEqual(quick.documents[0].id, "languages-0")
+        self.assertEqual(quick.documents[4].id, "more-2")
+        self._test_correct(quick.documents)
+
+    def test_text_extractors(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\nmozilla,ORG\n")
+            texts_directory = os.path.join(directory, "texts")
+            os.mkdir(texts_directory)
+            with open(os.path.join(texts_directory, "a.txt"), "w") as f:
+                f.write("rust is made by mozilla")
+            with open(os.path.join(texts_directory, "b.MD"), "w") as f:
+                f.write("# rust\nmade by mozilla")
+            with open(os.path.join(texts_directory, "c.html"), "w") as f:
+                f.write("<p>rust</p>")
+            config = Config(self._write_config(directory, entities_path, texts_directory))
+            quick = Quickner(config=config)
+            quick.process()
+            self.assertEqual([document.id for document in quick.documents], ["a-0"])
+            read = []
+
+            def markdown(path):
+                read.append(os.path.basename(path))
+                with open(path) as f:
+                    return f.read().replace("# ", "")
+
+            quick = Quickner(config=config)
+            quick.register_extractor([".md"], markdown)
+            quick.process()
+            self.assertEqual(read, ["b.MD"])
+            self.assertEqual([document.id for document in quick.documents], ["a-0", "b-0"])
+            self.assertEqual(quick.documents[1].text, "rust\nmade by mozilla")
+            self.assertEqual(quick.documents[1].label, [(0, 4, "PL"), (13, 20, "ORG")])
+            self.assertEqual(quick.documents[1].meta, {"file": "b.MD"})
+            # The last extractor registered for an extension is used, its errors stop the run
+            quick = Quickner(config=config)
+            quick.register_extractor(["txt"], lambda path: 1 / 0)
+            with self.assertRaisesRegex(Exception, "division by zero"):
+                quick.process()
+            with self.assertRaises(QuicknerError):
+                quick.register_extractor(["txt"], "not a function")
+
+    def test_from_jsonl_with_custom_fields(self):
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "annotations.jsonl")
+            with open(path, "w") as f:
+                for text, label in self.annotations:
+                    f.write(json.dumps({"content": text, "labels": label}) + "\n")
+            quick = Quickner.from_jsonl(path, fields={"text": "content", "label": "labels"})
+            with self.assertRaisesRegex(QuicknerError, "txt"):
+                Quickner.from_jsonl(path, fields={"txt": "content"})
+        self.assertEqual(len(quick.documents), 4)
+        self._test_correct(quick.documents)
+
+    def test_json_output_options(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nRust,PL\nMozilla,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nRust et Mozilla ont créé Firefox\n")
+
+            def run(options):
+                config = Config(self._write_config(directory, entities_path, texts_path, extra=options))
+                quick = Quickner(config=config)
+                quick.process(True)
+                with open(os.path.join(directory, "annotations.jsonl")) as f:
+                    jsonl = f.read()
+                quick.save_annotations(os.path.join(directory, "spacy"), Format.SPACY)
+                with open(os.path.join(directory, "spacy.json")) as f:
+                    spacy = f.read()
+                self.assertEqual(json.loads(jsonl)["text"], "rust et mozilla ont créé firefox")
+                self.assertEqual(json.loads(spacy)[0][1]["entity"], [[0, 4, "PL"], [8, 15, "ORG"]])
+                return config.annotations.output.json, jsonl, spacy
+
+            options, jsonl, spacy = run("")
+            self.assertEqual((options.pretty, options.indent, options.ensure_ascii), (False, 2, False))
+            self.assertIn("créé", jsonl)
+            self.assertEqual(len(spacy.splitlines()), 1)
+
+            options, jsonl, spacy = run("\n[annotations.output.json]\npretty = true\nindent = 4\nensure_ascii = true")
+            self.assertEqual((options.pretty, options.indent, options.ensure_ascii), (True, 4, True))
+            # The JSONL lines stay compact, only the JSON files are pretty-printed
+            self.assertEqual(len(jsonl.splitlines()), 1)
+            self.assertIn("cr\\u00e9\\u00e9", jsonl)
+            self.assertNotIn("é", spacy)
+            self.assertTrue(spacy.splitlines()[1].startswith("    ["))
+            self.assertFalse(spacy.splitlines()[1].startswith("     "))
+
+    def test_export_document_selection(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nRust,PL\nMozilla,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nRust is made by Mozilla\nNothing to see here\n")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                template = f.read()
+            annotations_path = os.path.join(directory, "annotations.jsonl")
+
+            def exported():
+                with open(annotations_path) as f:
+                    return [json.loads(line)["text"] for line in f]
+
+            for selection, texts in (
+                ("all", ["rust is made by mozilla", "nothing to see here"]),
+                ("annotated", ["rust is made by mozilla"]),
+                ("unannotated", ["nothing to see here"]),
+            ):
+                with open(config_path, "w") as f:
+                    f.write(template.replace("[annotations.output]\n", f'[annotations.output]\ndocuments = "{selection}"\n'))
+                config = Config(config_path)
+                self.assertEqual(config.annotations.output.documents, selection)
+                quick = Quickner(config=config)
+                quick.process(True)
+                # The selection is applied on export only
+                self.assertEqual(len(quick.documents), 2)
+                self.assertEqual(exported(), texts)
+            with open(config_path, "w") as f:
+                f.write(template.replace("[annotations.output]\n", '[annotations.output]\ndocuments = "some"\n'))
+            with self.assertRaises(QuicknerError):
+                Config(config_path)
+
+    def test_export_label_floor(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                template = f.read()
+
+            def exported(options):
+                with open(config_path, "w") as f:
+                    f.write(template.replace("[annotations.output]\n", "[annotations.output]\n" + options))
+                config = Config(config_path)
+                quick = Quickner(config=config)
+                quick.process(True)
+                # The floor is applied on export only
+                self.assertEqual(sum(len(document.label) for document in quick.documents), 12)
+                labels = []
+                with open(os.path.join(directory, "annotations.jsonl")) as f:
+                    for line in f:
+                        document = json.loads(line)
+                        labels.extend(span[2] for span in document["label"])
+                        # The metadata of the spans follows their labels
+                        self.assertEqual(
+                            sorted(meta["label"] for meta in document.get("span_meta", [])),
+                            sorted(span[2] for span in document["label"]),
+                        )
+                return config.annotations.output, sorted(labels)
+
+            output, labels = exported("")
+            self.assertEqual((output.min_label_count, output.rare_label), (None, None))
+            self.assertEqual(labels, ["ORG"] * 3 + ["PERSON"] * 3 + ["PL"] * 6)
+            # ORG and PERSON have 3 spans each, PL has 6
+            output, labels = exported("min_label_count = 4\n")
+            self.assertEqual(output.min_label_count, 4)
+            self.assertEqual(labels, ["PL"] * 6)
+            output, labels = exported('min_label_count = 4\nrare_label = "MISC"\n')
+            self.assertEqual(output.rare_label, "MISC")
+            self.assertEqual(labels, ["MISC"] * 6 + ["PL"] * 6)
+            _, labels = exported('min_label_count = 3\nrare_label = "MISC"\n')
+            self.assertEqual(labels, ["ORG"] * 3 + ["PERSON"] * 3 + ["PL"] * 6)
+
+    def test_output_naming(self):
+        documents = [Document(text) for text in self.texts]
+        entities = [Entity(*(entity)) for entity in self.entities]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        with tempfile.TemporaryDirectory() as directory:
+            # Only the extension of the file name is replaced, not the dots of the directories
+            output = os.path.join(directory, "v1.2", "annotations")
+            os.mkdir(os.path.dirname(output))
+            for format, extensions in (
+                (Format.JSONL, [".jsonl"]),
+                (Format.SPACY, [".json"]),
+                (Format.BRAT, [".ann", ".txt"]),
+                (Format.CONLL, [".txt"]),
+            ):
+                for path in (output, output + ".out"):
+                    self.assertEqual(quick.save_annotations(path, format), output)
+                    for extension in extensions:
+                        self.assertTrue(os.path.isfile(output + extension), output + extension)
+            self.assertEqual(sorted(os.listdir(directory)), ["v1.2"])
+            with open(output + ".jsonl") as f:
+                self.assertEqual(len(f.readlines()), 5)
+            # Paths longer than the 260 characters of MAX_PATH on Windows
+            deep = os.path.join(directory, *["a" * 50] * 6)
+            os.makedirs(deep)
+            path = quick.save_annotations(os.path.join(deep, "annotations.jsonl"), Format.JSONL)
+            self.assertGreater(len(path), 260)
+            loaded = Quickner.from_jsonl(path + ".jsonl")
+            self.assertEqual(len(loaded.documents), 5)
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_document_selection(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nRust,PL\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nRust is fast\nNothing to see here\n")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            annotations_path = os.path.join(directory, "annotations.jsonl")
+            run_cli("--config", config_path, "-q", "--annotated-only")
+            with open(annotations_path) as f:
+                self.assertEqual([json.loads(line)["text"] for line in f], ["rust is fast"])
+            run_cli("--config", config_path, "-q", "--unannotated-only")
+            with open(annotations_path) as f:
+                self.assertEqual([json.loads(line)["text"] for line in f], ["nothing to see here"])
+            conflict = run_cli("--config", config_path, "--annotated-only", "--unannotated-only", check=False)
+            self.assertNotEqual(conflict.returncode, 0)
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_workspace_status(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            # A project run, one never run and a toml file which is not a configuration
+            news = os.path.join(directory, "news")
+            os.mkdir(news)
+            Quickner(config=Config(self._write_config(news, entities_path))).process(True)
+            self._write_config(directory, entities_path)
+            with open(os.path.join(directory, "notes.toml"), "w") as f:
+                f.write('title = "notes"\n')
+            # Only the direct subdirectories are scanned
+            nested = os.path.join(news, "old")
+            os.mkdir(nested)
+            self._write_config(nested, entities_path)
+
+            status = run_cli("workspace", "status", directory)
+            rows = [re.split(r"\s{2,}", line) for line in status.stdout.splitlines()]
+            self.assertEqual(rows[0], ["PROJECT", "TEXTS", "DOCUMENTS", "ANNOTATED", "SPANS", "LAST RUN", "LABELS"])
+            self.assertEqual(len(rows), 3)
+            self.assertEqual(rows[1], [os.path.join(directory, "config.toml"), "5", "-", "-", "-", "-", "-"])
+            self.assertEqual(rows[2][:5], [os.path.join(news, "config.toml"), "5", "5", "5", "12"])
+            self.assertRegex(rows[2][5], r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2} UTC$")
+            self.assertEqual(rows[2][6], "ORG: 3, PERSON: 3, PL: 6")
+            self.assertIn("Skipped " + os.path.join(directory, "notes.toml"), status.stderr)
+
+            empty = os.path.join(directory, "empty")
+            os.mkdir(empty)
+            self.assertEqual(run_cli("workspace", "status", empty).stdout.strip(), f"No quickner project found in {empty}")
+            self.assertNotEqual(run_cli("workspace", "status", os.path.join(directory, "missing"), check=False).returncode, 0)
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_validate_spans(self):
+        records = [
+            {"doc_id": "a", "content": "Rust is made by Mozilla", "spans": [[0, 4, "PL"], {"start": 16, "end": 23, "label": "ORG"}]},
+            {"doc_id": "b", "content": "Short", "spans": [[0, 40, "PL"]]},
+            {"doc_id": "c", "content": "Guido van Rossum", "spans": [[0, 16, "PERSON"], [0, 5, "PERSON"]]},
+            {"doc_id": "d", "content": "Rust", "spans": [[0, 4, "LOC"], [2, 2, "PL"]]},
+        ]
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "spans.jsonl")
+            with open(path, "w") as f:
+                for record in records:
+                    f.write(json.dumps(record) + "\n")
+                f.write("not json\n")
+            fields = ["--id-field", "doc_id", "--text-field", "content", "--label-field", "spans"]
+
+            def validate(path, *options):
+                result = run_cli("validate-spans", path, *options, check=False)
+                return result.returncode, json.loads(result.stdout)
+
+            code, report = validate(path, *fields, "--labels", "PL,ORG,PERSON")
+            self.assertEqual(code, 1)
+            self.assertEqual((report["valid"], report["documents"], report["spans"]), (False, 4, 7))
+            self.assertEqual(
+                [(error["line"], error.get("id"), error["kind"]) for error in report["errors"]],
+                [
+                    (2, "b", "out_of_bounds"),
+                    (3, "c", "overlap"),
+                    (4, "d", "unknown_label"),
+                    (4, "d", "empty_span"),
+                    (4, "d", "overlap"),
+                    (5, None, "invalid_json"),
+                ],
+            )
+            # Any label is accepted without --labels, the overlaps with --allow-overlaps
+            _, report = validate(path, *fields, "--allow-overlaps")
+            self.assertEqual(
+                [error["kind"] for error in report["errors"]], ["out_of_bounds", "empty_span", "invalid_json"]
+            )
+            # The default fields are id, text and label
+            _, report = validate(path)
+            self.assertEqual([error["kind"] for error in report["errors"]], ["missing_text"] * 4 + ["invalid_json"])
+
+            valid = os.path.join(directory, "valid.jsonl.gz")
+            with gzip.open(valid, "wt") as f:
+                f.write(json.dumps(records[0]) + "\n")
+            code, report = validate(valid, *fields, "--labels", "PL,ORG")
+            self.assertEqual(code, 0)
+            self.assertEqual((report["valid"], report["documents"], report["spans"], report["errors"]), (True, 1, 2, []))
+            missing = run_cli("validate-spans", os.path.join(directory, "missing.jsonl"), check=False)
+            self.assertEqual(missing.returncode, 2)
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_roundtrip(self):
+        records = [
+            {"id": "a", "text": "Rust is made by Mozilla", "label": [[0, 4, "PL"], [16, 23, "ORG"]], "meta": {"source": "web"}},
+            {"id": "b", "text": "Guido van Rossum  created Python", "label": [[0, 16, "PERSON"], [26, 32, "PL"]]},
+        ]
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "annotations.jsonl")
+            with open(path, "w") as f:
+                for record in records:
+                    f.write(json.dumps(record) + "\n")
+
+            def roundtrip(format, path=path):
+                result = run_cli("roundtrip", "--format", format, path, check=False)
+                report = json.loads(result.stdout)
+                self.assertEqual(report["format"], format)
+                self.assertEqual((report["documents"], report["spans"]), (2, 4))
+                self.assertEqual(result.returncode, 0 if report["lossless"] else 1)
+                return report["lossless"], [(change["index"], change["kind"]) for change in report["changes"]]
+
+            self.assertEqual(roundtrip("jsonl"), (True, []))
+            self.assertEqual(roundtrip("tokens"), (True, []))
+            self.assertEqual(roundtrip("csv"), (False, [(0, "lost_meta")]))
+            self.assertEqual(roundtrip("spacy"), (False, [(0, "altered_id"), (1, "altered_id")]))
+            # CoNLL joins the tokens with single spaces, moving the spans after a double space
+            lossless, changes = roundtrip("conll")
+            self.assertFalse(lossless)
+            self.assertEqual(
+                changes,
+                [(0, "altered_id"), (0, "lost_meta"), (1, "altered_id"), (1, "altered_text"), (1, "altered_span")],
+            )
+
+            # The input can be compressed JSONL or spaCy JSON
+            with open(path, "rb") as f, gzip.open(path + ".gz", "wb") as compressed:
+                compressed.write(f.read())
+            self.assertEqual(roundtrip("jsonl", path + ".gz"), (True, []))
+            spacy = os.path.join(directory, "annotations.json")
+            with open(spacy, "w") as f:
+                json.dump([[record["text"], {"entity": record["label"]}] for record in records], f)
+            self.assertEqual(roundtrip("spacy", spacy)[1], [])
+
+            self.assertEqual(run_cli("roundtrip", "--format", "jsonl", path + ".missing", check=False).returncode, 2)
+            self.assertNotEqual(run_cli("roundtrip", "--format", "xml", path, check=False).returncode, 0)
+
+    def test_read_texts_in_chunks(self):
+        def word(index):
+            letters = ""
+            while True:
+                index, letter = divmod(index, 26)
+                letters += chr(ord("a") + letter)
+                if index == 0:
+                    return letters
+
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\nr,PL\nmozilla,ORG\n")
+            # More rows than a chunk, with duplicates across the chunks and texts filtered out
+            texts_path = os.path.join(directory, "texts.csv")
+            rows = 120_000
+            expected = []
+            with open(texts_path, "w") as f:
+                f.write("text,source\n")
+                for index in range(rows):
+                    if index in (50_000, 110_000):
+                        f.write(f"rust doc {word(7)},copy\n")
+                    elif index in (1, 100_001):
+                        f.write("rs,short\n")
+                    else:
+                        f.write(f"rust doc {word(index)},{'web' if index % 2 else ''}\n")
+                        expected.append(f"rust doc {word(index)}")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                template = f.read()
+            with open(config_path, "w") as f:
+                f.write(template.replace("filter = false", "filter = true").replace("min_length = 0", "min_length = 3"))
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            # The texts keep the order of the file, the first of the duplicates is kept
+            self.assertEqual([document.text for document in quick.documents], expected)
+            self.assertEqual(quick.documents[7].meta, {})
+            self.assertEqual(quick.documents[8].meta, {"source": "web"})
+            # "r" is shorter than the minimum length of the entities
+            self.assertEqual([entity.name for entity in quick.entities], ["rust", "mozilla"])
+            self.assertEqual(sum(len(document.label) for document in quick.documents), len(expected))
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_stage_timings(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            run = run_cli("--config", self._write_config(directory, entities_path))
+            summary = run.stderr[run.stderr.index("Run summary") :].splitlines()
+            self.assertRegex(summary[0], r"^Run summary: 5 documents, 12 spans, 10 entities in \S+")
+            stages = [line.split()[-3] for line in summary[1:6]]
+            self.assertEqual(stages, ["entities", "texts", "preparation", "annotation", "save"])
+            for line in summary[1:6]:
+                self.assertRegex(line, r"\(\d+%\)$")
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_bench(self):
+        def bench(*options):
+            corpus = ["--documents", "100", "--words", "20", "--entities", "30", "--iterations", "1"]
+            return json.loads(run_cli("bench", *corpus, *options).stdout)
+
+        report = bench("--threads", "1,2", "--seed", "3")
+        self.assertEqual((report["documents"], report["entities"]), (100, 30))
+        self.assertEqual([run["threads"] for run in report["runs"]], [1, 2])
+        # The same spans are found whatever the thread count, the mentions and
+        # the entities nested in them
+        spans = report["runs"][0]["spans"]
+        self.assertGreaterEqual(spans, report["mentions"])
+        self.assertEqual(report["runs"][1]["spans"], spans)
+        for run in report["runs"]:
+            self.assertGreater(run["documents_per_second"], 0)
+            self.assertGreater(run["megabytes_per_second"], 0)
+        # The same seed gives the same corpus
+        self.assertEqual(bench("--threads", "1", "--seed", "3")["bytes"], report["bytes"])
+        self.assertNotEqual(bench("--threads", "1", "--seed", "4")["bytes"], report["bytes"])
+        self.assertEqual(len(bench()["runs"]), 1)
+
+        self.assertEqual(bench("--density", "0")["mentions"], 0)
+        self.assertGreater(bench("--density", "0.5")["mentions"], report["mentions"])
+        # The mentions with a typo are only found by the fuzzy matching
+        exact = bench("--typos", "1", "--threads", "1")
+        fuzzy = bench("--typos", "1", "--threads", "1", "--max-edits", "1")
+        self.assertEqual(exact["bytes"], fuzzy["bytes"])
+        self.assertLess(exact["runs"][0]["spans"], fuzzy["runs"][0]["spans"])
+        for option in ("--fold-diacritics", "--match-plurals"):
+            folded = bench(option, "--threads", "1", "--seed", "3")
+            self.assertEqual(folded["runs"][0]["spans"], spans)
+        self.assertNotEqual(run_cli("bench", "--density", "dense", check=False).returncode, 0)
+
+    @unittest.skipUnless(QUICKNER_BIN, "the quickner binary is not built")
+    def test_cli_output_modes(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\n")
+                for text in self.texts + self.texts[:2] + ("Go",):
+                    f.write(f'"{text}"\n')
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                template = f.read()
+            with open(config_path, "w") as f:
+                f.write(template.replace("filter = false", "filter = true", 1).replace("min_length = 0", "min_length = 3", 1))
+            annotations_path = os.path.join(directory, "annotations.jsonl")
+
+            default = run_cli("--config", config_path)
+            self.assertIn("INFO", default.stderr)
+            self.assertEqual(default.stdout, "")
+            # The progress bar is only drawn on a terminal, the logs are kept
+            no_progress = run_cli("--config", config_path, "--no-progress")
+            self.assertIn("Run summary", no_progress.stderr)
+            os.remove(annotations_path)
+            quiet = run_cli("--config", config_path, "-q")
+            self.assertEqual((quiet.stdout, quiet.stderr), ("", ""))
+            self.assertTrue(os.path.isfile(annotations_path))
+
+            run = run_cli("--config", config_path, "--output-json")
+            self.assertEqual(run.stderr, "")
+            self.assertEqual(len(run.stdout.splitlines()), 1)
+            summary = json.loads(run.stdout)
+            self.assertEqual(summary["status"], "ok")
+            self.assertEqual(
+                {key: summary[key] for key in ("documents", "annotated_documents", "spans", "entities")},
+                {"documents": 5, "annotated_documents": 5, "spans": 12, "entities": 10},
+            )
+            self.assertEqual((summary["filtered_texts"], summary["duplicate_texts"]), (1, 2))
+            self.assertEqual(summary["output"], os.path.join(directory, "annotations"))
+            self.assertGreater(summary["seconds"], 0)
+            self.assertEqual(list(summary["stages"]), ["entities", "texts", "preparation", "annotation", "save"])
+
+            with open(texts_path, "w") as f:
+                f.write("content\nRust is made by Mozilla\n")
+            failed = run_cli("--config", config_path, "--output-json", check=False)
+            self.assertEqual(failed.returncode, 1)
+            summary = json.loads(failed.stdout)
+            self.assertEqual(summary["status"], "error")
+            self.assertIn("text", summary["error"])
+
+    def test_save_jsonl_with_custom_fields(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            config_path = self._write_config(
+                directory, entities_path, extra='\n[annotations.output.fields]\ntext = "content"\nlabel = "labels"'
+            )
+            quick = Quickner(config=Config(config_path))
+            quick.process(True)
+            path = os.path.join(directory, "annotations.jsonl")
+            with open(path) as f:
+                lines = [json.loads(line) for line in f]
+            self.assertEqual(len(lines), 5)
+            for line in lines:
+                self.assertIn("content", line)
+                self.assertIn("labels", line)
+                self.assertNotIn("text", line)
+                self.assertNotIn("label", line)
+            loaded = Quickner.from_jsonl(path, fields={"text": "content", "label": "labels"})
+            self.assertEqual(len(loaded.documents), 5)
+            self._test_correct(loaded.documents)
+
+    def test_from_dir(self):
+        with tempfile.TemporaryDirectory() as directory:
+            with open(os.path.join(directory, "a.jsonl"), "w") as f:
+                f.write(json.dumps({"text": "Rust is made by Mozilla", "label": [[0, 4, "PL"]]}) + "\n")
+            os.mkdir(os.path.join(directory, "old"))
+            with open(os.path.join(directory, "old", "b.json"), "w") as f:
+                json.dump([["Rust is made by Mozilla", {"entity": [[16, 23, "ORG"]]}]], f)
+            with open(os.path.join(directory, "old", "c.txt"), "w") as f:
+                f.write("Guido\tPERSON\nwrote\tO\nPython\tPL\n")
+            with open(os.path.join(directory, "notes.txt"), "w") as f:
+                f.write("not an annotation file\n")
+            quick = Quickner.from_dir(directory)
+        self.assertEqual(len(quick.documents), 2)
+        rust, guido = quick.documents
+        self.assertEqual(rust.label, [(0, 4, "PL"), (16, 23, "ORG")])
+        self.assertEqual(rust.meta["source"], "a.jsonl,old/b.json")
+        self.assertEqual(guido.meta["source_format"], "conll")
+        self.assertEqual(guido.label, [(0, 5, "PERSON"), (12, 18, "PL")])
+
+    def test_multibyte_offsets(self):
+        text = "Café in São Paulo"
+        self.assertEqual(char_to_byte(text, 8, 17), (9, 19))
+        self.assertEqual(byte_to_char(text, 9, 19), (8, 17))
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "annotations.json")
+            with open(path, "w") as f:
+                json.dump([[text, {"entity": [[8, 17, "LOC"]]}]], f)
+            quick = Quickner.from_spacy(path)
+            self.assertEqual([entity.name for entity in quick.entities], ["são paulo"])
+            quick.save_annotations(os.path.join(directory, "annotations"), Format.CONLL)
+            with open(os.path.join(directory, "annotations.txt")) as f:
+                lines = f.read().splitlines()
+        self.assertEqual(lines[:4], ["Café\tO", "in\tO", "São\tLOC", "Paulo\tLOC"])
+
+    def test_balance(self):
+        documents = [Document(f"apple store number {i}") for i in range(10)]
+        documents.append(Document("apple and rust"))
+        quick = Quickner(documents=documents, entities=[Entity("apple", "ORG"), Entity("rust", "PL")])
+        quick.process()
+        removed = quick.balance(3, seed=1)
+        self.assertEqual(removed, 8)
+        self.assertEqual(quick.stats().labels["ORG"].documents, 3)
+        self.assertIn("apple and rust", [document.text for document in quick.documents])
+
+    def test_stats_and_evaluate(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        stats = quick.stats()
+        self.assertEqual(stats.documents, 5)
+        self.assertEqual(stats.spans, 12)
+        self.assertEqual(stats.labels["PERSON"].spans, 3)
+        self.assertEqual(stats.to_dict()["labels"]["ORG"]["documents"], 3)
+        gold = Quickner(
+            documents=[Document(text, label) for text, label in self.annotations]
+        )
+        evaluation = quick.evaluate(gold)
+        self.assertEqual(evaluation.documents, 4)
+        self.assertEqual(evaluation.overall.f1, 1.0)
+        self.assertEqual(evaluation.to_dict()["labels"]["PERSON"]["true_positives"], 3)
+
+    def test_gazetteer_stats(self):
+        entities = [Entity("New York", "LOC"), Entity("York", "LOC"), Entity("Apple", "ORG"), Entity("Apple", "PRODUCT")]
+        quick = Quickner(documents=[Document("Apple opened a store in New York")], entities=entities)
+        stats = quick.gazetteer_stats()
+        self.assertEqual(stats.patterns, 4)
+        self.assertGreater(stats.memory, 0)
+        self.assertEqual((stats.longest, stats.shortest), ("New York", "York"))
+        self.assertEqual(stats.duplicates, {"Apple": ["ORG", "PRODUCT"]})
+        self.assertEqual(stats.substrings, {"New York": ["York"]})
+
+    def test_sqlite_roundtrip(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "corpus.db")
+            quick.to_sqlite(path)
+            # Saving twice upserts instead of duplicating
+            quick.to_sqlite(path)
+            loaded = Quickner.from_sqlite(path)
+        self.assertEqual(len(loaded.documents), 5)
+        self.assertEqual(len(loaded.entities), 10)
+        self.assertEqual(
+            sorted(document.id for document in loaded.documents),
+            sorted(document.id for document in quick.documents),
+        )
+        self._test_correct(loaded.documents)
+
+    def test_log_run(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        logged = {}
+
+        class Tracker:
+            def log_params(self, params):
+                logged["params"] = params
+
+            def log_metrics(self, metrics):
+                logged["metrics"] = metrics
+
+        quick.log_run(Tracker())
+        self.assertEqual(logged["params"]["quickner.entities"], 10)
+        self.assertEqual(logged["metrics"]["documents"], 5)
+        self.assertEqual(logged["metrics"]["labels.ORG.spans"], 3)
+        quick.log_run(lambda params, metrics: logged.update(callback=metrics))
+        self.assertEqual(logged["callback"], quick.run_metadata()["metrics"])
+        with self.assertRaises(TypeError):
+            quick.log_run(42)
+
+    def test_config_validation(self):
+        with tempfile.TemporaryDirectory() as directory:
+            config_path = self._write_config(directory, os.path.join(directory, "missing.csv"))
+            with self.assertRaises(QuicknerError) as context:
+                Config(config_path)
+            self.assertIn("entities.input.path", str(context.exception))
+            config = Config(config_path, validate=False)
+            self.assertEqual(len(config.validate()), 1)
+            broken_path = os.path.join(directory, "broken.toml")
+            with open(broken_path, "w") as f:
+                f.write("[texts\n")
+            with self.assertRaises(QuicknerError):
+                Config(broken_path)
+
+    def test_save_creates_output_directory(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "nested", "output.v2", "annotations")
+            quick.save_annotations(path)
+            self.assertTrue(os.path.isfile(path + ".jsonl"))
+
+    def test_html_export(self):
+        entities = [Entity(*(entity)) for entity in self.entities]
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        with tempfile.TemporaryDirectory() as directory:
+            path = quick.save_annotations(os.path.join(directory, "review"), format=Format.HTML)
+            with open(path + ".html") as f:
+                page = f.read()
+        self.assertTrue(page.startswith("<!DOCTYPE html>"))
+        self.assertIn("5 documents, 12 spans", page)
+        self.assertIn('<mark class="entity" data-labels="PERSON"', page)
+
+    def test_label_display(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\nmozilla,ORG\n")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path, "a") as f:
+                f.write('\n[labels.display]\nORG = { color = "#4e79a7", name = "Organization" }\nPL = { color = "cyan" }\n')
+            config = Config(config_path)
+            self.assertEqual(config.labels.display["ORG"].name, "Organization")
+            self.assertEqual(config.labels.display["PL"].color, "cyan")
+            try:
+                quick = Quickner(config=config)
+                quick.process()
+                self.assertEqual(
+                    quick.documents[0].pretty(),
+                    "\x1b[36mrust\x1b[0m[PL] is made by \x1b[38;2;78;121;167mmozilla\x1b[0m[Organization]",
+                )
+                path = quick.save_annotations(os.path.join(directory, "review"), format=Format.HTML)
+                with open(path + ".html") as f:
+                    page = f.read()
+                self.assertIn('data-labels="ORG" style="background: #4e79a7">mozilla<span class="label">Organization</span>', page)
+                set_label_display({"PL": {"name": "Language"}})
+                self.assertEqual(quick.documents[0].pretty(), "\x1b[31mrust\x1b[0m[Language] is made by \x1b[32mmozilla\x1b[0m[ORG]")
+                with self.assertRaises(QuicknerError):
+                    set_label_display({"PL": {"color": "teal"}})
+            finally:
+                set_label_display({})
+
+    def test_nif_export(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label,kb_id\nMozilla,ORG,Q169925\nrust,PL,\n")
+            extra = '\n[annotations.output.nif]\nkb_uri = "http://www.wikidata.org/entity/"'
+            quick = Quickner(config=Config(self._write_config(directory, entities_path, extra=extra)))
+            quick.process()
+            path = quick.save_annotations(os.path.join(directory, "links"), format=Format.NIF)
+            with open(path + ".ttl") as f:
+                turtle = f.read()
+        self.assertIn("@prefix nif:", turtle)
+        self.assertIn('nif:anchorOf "mozilla"', turtle)
+        self.assertIn("itsrdf:taIdentRef <http://www.wikidata.org/entity/Q169925>", turtle)
+        self.assertEqual(turtle.count("itsrdf:taIdentRef"), 1)
+        self.assertEqual(turtle.count("a nif:Phrase"), 4)
+
+    def test_tokens_export(self):
+        document = Document("New York, USA", [(0, 8, "LOC")])
+        self.assertEqual(
+            document.tokens(),
+            [("New", 0, 3, "B-LOC"), ("York", 4, 8, "I-LOC"), (",", 8, 9, "O"), ("USA", 10, 13, "O")],
+        )
+        # The tokens are split at the bounds of the spans, the first of overlapping spans is kept
+        document = Document("C++ rocks, e-mail me", [(0, 3, "PL"), (11, 12, "X"), (11, 19, "TECH")])
+        self.assertEqual(
+            document.tokens(),
+            [("C", 0, 1, "B-PL"), ("+", 1, 2, "I-PL"), ("+", 2, 3, "I-PL"), ("rocks", 4, 9, "O"), (",", 9, 10, "O"),
+             ("e", 11, 12, "B-TECH"), ("-", 12, 13, "I-TECH"), ("mail", 13, 17, "I-TECH"), ("m", 18, 19, "I-TECH"),
+             ("e", 19, 20, "O")],
+        )
+        quick = Quickner(documents=[Document(text) for text in self.texts], entities=[Entity(*entity) for entity in self.entities])
+        quick.process()
+        with tempfile.TemporaryDirectory() as directory:
+            path = quick.save_annotations(os.path.join(directory, "annotations"), format=Format.TOKENS)
+            with open(path + ".tokens.jsonl") as f:
+                records = [json.loads(line) for line in f]
+            loaded = from_dir(directory)
+        self.assertEqual(len(records), len(quick.documents))
+        self.assertEqual(records[0]["id"], quick.documents[0].id)
+        self.assertEqual(records[0]["text"], quick.documents[0].text)
+        self.assertEqual([tuple(token) for token in records[0]["tokens"]], quick.documents[0].tokens())
+        self.assertEqual(
+            sorted((document.text, document.label) for document in loaded.documents),
+            sorted((document.text, sorted(document.label)) for document in quick.documents),
+        )
+
+    def test_csv_export(self):
+        quick = Quickner(documents=[Document("Rust, by Mozilla", [(0, 4, "PL"), (9, 16, "ORG")]), Document("Nothing here", [])])
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "annotations")
+            quick.to_csv(path)
+            with open(path + ".csv", newline="") as f:
+                rows = list(csv.reader(f))
+            loaded = from_dir(directory)
+        self.assertEqual(rows[0], ["id", "text", "start", "end", "label", "span_text"])
+        self.assertEqual(rows[1][1:], ["Rust, by Mozilla", "0", "4", "PL", "Rust"])
+        self.assertEqual(rows[2][1:], ["Rust, by Mozilla", "9", "16", "ORG", "Mozilla"])
+        self.assertEqual(rows[3][1:], ["Nothing here", "", "", "", ""])
+        self.assertEqual(rows[1][0], quick.documents[0].id)
+        self.assertEqual(
+            [(document.id, document.text, document.label) for document in loaded.documents],
+            [(document.id, document.text, document.label) for document in quick.documents],
+        )
+
+    def test_discover(self):
+        texts = [
+            ("She works at Mozilla headquarters in the city.", [(13, 20, "ORG")]),
+            ("He works at Globex Corporation headquarters in the city.", []),
+            ("The code is written in Rust for speed.", [(23, 27, "PL")]),
+            ("The code is written in Zig for speed.", []),
+            ("They work at Globex Corporation headquarters now.", []),
+            ("The tool is written in Zig today.", []),
+            ("Yesterday we met the Globex Corporation team.", []),
+            ("yesterday was fine.", []),
+        ]
+        quick = Quickner(documents=[Document(text, label) for text, label in texts], entities=[Entity("mozilla", "ORG"), Entity("rust", "PL")])
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "candidates.csv")
+            candidates = quick.discover(min_frequency=2, path=path)
+            with open(path, newline="") as f:
+                rows = list(csv.DictReader(f))
+        self.assertEqual([(c.name, c.label, c.frequency) for c in candidates], [("Zig", "PL", 2), ("Globex Corporation", "ORG", 3)])
+        self.assertIn("team", candidates[1].context)
+        self.assertEqual([(row["name"], row["label"]) for row in rows], [("Zig", "PL"), ("Globex Corporation", "ORG")])
+        # The sequences written in the gazetteer or annotated are not candidates
+        self.assertEqual(quick.discover(min_frequency=1, max_words=1)[0].name, "Zig")
+
+    def test_cooccurrences(self):
+        quick = Quickner(documents=[
+            Document("Mozilla makes Rust", [(0, 7, "ORG"), (14, 18, "PL")]),
+            Document("Rust was started at Mozilla, and mozilla still funds it", [(0, 4, "PL"), (20, 27, "ORG"), (33, 40, "ORG")]),
+            Document("Rust is not only used at Google but at Mozilla too", [(0, 4, "PL"), (25, 31, "ORG"), (39, 46, "ORG")]),
+            Document("Python", [(0, 6, "PL")]),
+        ])
+        cooccurrences = quick.cooccurrences()
+        self.assertEqual(cooccurrences.entities[0], ("mozilla", "ORG", "rust", "PL", 3))
+        self.assertEqual(len(cooccurrences.entities), 3)
+        self.assertEqual(cooccurrences.labels, {"ORG": {"ORG": 1, "PL": 3}, "PL": {"ORG": 3}})
+        # Within 3 tokens, "Rust ... Google" and "Rust ... Mozilla" are too far apart in the last document
+        window = quick.cooccurrences(window=3)
+        self.assertEqual(window.entities, [("mozilla", "ORG", "rust", "PL", 2), ("google", "ORG", "mozilla", "ORG", 1)])
+        self.assertEqual(window.to_dict()["window"], 3)
+
+    @unittest.skipUnless(importlib.util.find_spec("numpy"), "numpy is not installed")
+    def test_cooccurrence_matrices(self):
+        quick = Quickner(documents=[
+            Document("Mozilla makes Rust", [(0, 7, "ORG"), (14, 18, "PL")]),
+            Document("Rust is used at Google and Mozilla", [(0, 4, "PL"), (16, 22, "ORG"), (27, 34, "ORG")]),
+        ])
+        cooccurrences = quick.cooccurrences()
+        labels, matrix = cooccurrences.label_matrix()
+        self.assertEqual(labels, ["ORG", "PL"])
+        self.assertEqual(matrix.tolist(), [[1, 2], [2, 0]])
+        entities, matrix = cooccurrences.entity_matrix()
+        self.assertEqual(entities, [("google", "ORG"), ("mozilla", "ORG"), ("rust", "PL")])
+        self.assertEqual(matrix.tolist(), [[0, 1, 1], [1, 0, 2], [1, 2, 0]])
+
+    @unittest.skipUnless(importlib.util.find_spec("numpy"), "numpy is not installed")
+    def test_spans_numpy(self):
+        quick = Quickner(documents=[
+            Document("Mozilla makes Rust", [(0, 7, "ORG"), (14, 18, "PL")]),
+            Document("No entity here", []),
+            Document("Rust is used at Google", [(0, 4, "PL"), (16, 22, "ORG")]),
+        ])
+        labels, spans = quick.spans_numpy()
+        self.assertEqual(labels, ["ORG", "PL"])
+        self.assertEqual(spans.dtype.names, ("doc_idx", "start", "end", "label_id"))
+        self.assertEqual(str(spans.dtype["label_id"]), "uint16")
+        self.assertEqual(spans.tolist(), [(0, 0, 7, 0), (0, 14, 18, 1), (2, 0, 4, 1), (2, 16, 22, 0)])
+        self.assertEqual(spans["start"].tolist(), [0, 14, 0, 16])
+        labels, spans = Quickner(documents=[Document("No entity here", [])]).spans_numpy()
+        self.assertEqual((labels, len(spans)), ([], 0))
+
+    def test_spans_numpy_without_numpy(self):
+        quick = Quickner(documents=[Document("Mozilla makes Rust", [(0, 7, "ORG")])])
+        with mock.patch.dict(sys.modules, {"numpy": None}):
+            with self.assertRaises(ImportError):
+                quick.spans_numpy()
+
+    def test_merge(self):
+        ours = Quickner(
+            documents=[Document("Rust by Mozilla", [(0, 4, "PL")]), Document("Python", []), Document("Go", [(0, 2, "PL")])],
+            entities=[Entity("rust", "PL")],
+        )
+        theirs = Quickner(
+            documents=[
+                Document("Rust by Mozilla", [(8, 15, "ORG")]),
+                Document("Go", [(0, 2, "PL")]),
+                Document("Java", [(0, 4, "PL")]),
+            ],
+            entities=[Entity("rust", "PL"), Entity("mozilla", "ORG")],
+        )
+        ids = [document.id for document in ours.documents]
+        report = ours.merge(theirs)
+        self.assertEqual(
+            report.to_dict(),
+            {"documents_added": 1, "documents_identical": 1, "conflicts": 1, "text_conflicts": [], "entities_added": 1},
+        )
+        self.assertEqual(len(ours.documents), 4)
+        self.assertEqual(ours.documents[0].label, [(0, 4, "PL"), (8, 15, "ORG")])
+        self.assertEqual(ours.documents[3].text, "Java")
+        self.assertEqual([entity.name for entity in ours.entities], ["rust", "mozilla"])
+        self.assertEqual(len(theirs.documents), 3)
+        self.assertEqual(ours.find_documents_by_label("ORG")[0].id, ids[0])
+        kept = Quickner(documents=[Document("Rust by Mozilla", [(0, 4, "PL")])])
+        kept.merge(theirs, strategy="keep-ours")
+        self.assertEqual(kept.documents[0].label, [(0, 4, "PL")])
+        kept.merge(theirs, strategy="keep_theirs")
+        self.assertEqual(kept.documents[0].label, [(8, 15, "ORG")])
+        # Same id, different texts
+        conflicting = Quickner(documents=[Document.from_dict({"id": ids[0], "text": "Go"})])
+        report = Quickner(documents=[Document("Rust by Mozilla", [(0, 4, "PL")])]).merge(conflicting)
+        self.assertEqual(report.text_conflicts, [ids[0]])
+        with self.assertRaises(QuicknerError):
+            ours.merge(theirs, strategy="newest")
+
+    def test_history_undo(self):
+        quick = Quickner(
+            documents=[Document("Rust by Mozilla", [(0, 4, "PL"), (8, 15, "ORG")]), Document("Go", [(0, 2, "PL")])],
+            entities=[Entity("mozilla", "ORG")],
+        )
+        with self.assertRaises(QuicknerError):
+            quick.undo()
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "history.jsonl")
+            quick.enable_history(path)
+            self.assertEqual(quick.rename_label("ORG", "ORGANIZATION"), 1)
+            self.assertEqual(quick.entities[0].label, "ORGANIZATION")
+            quick.filter_spans(lambda span: span.label != "PL")
+            quick.add_document(Document("Java", [(0, 4, "PL")]))
+            self.assertEqual(quick.documents[0].label, [(8, 15, "ORGANIZATION")])
+            history = quick.history()
+            self.assertEqual([entry["operation"] for entry in history], ["rename_label", "filter_spans", "add_document"])
+            self.assertEqual([entry["seq"] for entry in history], [1, 2, 3])
+            self.assertEqual((history[0]["spans_added"], history[0]["spans_removed"]), (1, 1))
+            self.assertEqual(history[1]["spans_removed"], 2)
+            self.assertEqual(history[2]["documents_added"], 1)
+            self.assertEqual(quick.undo(2), 2)
+            self.assertEqual([document.text for document in quick.documents], ["Rust by Mozilla", "Go"])
+            self.assertEqual(quick.documents[0].label, [(0, 4, "PL"), (8, 15, "ORGANIZATION")])
+            self.assertEqual(len(quick.find_documents_by_label("PL")), 2)
+            # The undo is not undone, the next one reverts the rename
+            self.assertEqual(quick.undo(5), 1)
+            self.assertEqual(quick.documents[0].label, [(0, 4, "PL"), (8, 15, "ORG")])
+            self.assertEqual(quick.undo(), 0)
+            history = quick.history()
+            self.assertEqual([entry["undone"] for entry in history[3:]], [[3, 2], [1]])
+            # A new instance continues the history
+            other = Quickner(documents=quick.documents)
+            other.enable_history(path)
+            other.balance(1)
+            self.assertEqual(other.history()[-1]["seq"], 6)
+            self.assertEqual(len(other.documents), 1)
+            self.assertEqual(other.undo(), 1)
+            self.assertEqual(len(other.documents), 2)
+
+    def test_history_undo_dedupe(self):
+        # Exact duplicates share the id of their text
+        quick = Quickner(
+            documents=[
+                Document("Rust by Mozilla", [(0, 4, "PL")]),
+                Document("Go by Google", [(0, 2, "PL")]),
+                Document("Rust by Mozilla", [(8, 15, "ORG")]),
+            ]
+        )
+        with tempfile.TemporaryDirectory() as directory:
+            quick.enable_history(os.path.join(directory, "history.jsonl"))
+            quick.dedupe(1.0)
+            self.assertEqual([document.text for document in quick.documents], ["Rust by Mozilla", "Go by Google"])
+            history = quick.history()
+            self.assertEqual([entry["operation"] for entry in history], ["dedupe"])
+            self.assertEqual((history[0]["documents_removed"], history[0]["spans_removed"]), (1, 1))
+            self.assertEqual(quick.undo(1), 1)
+            self.assertEqual(
+                [(document.text, document.label) for document in quick.documents],
+                [
+                    ("Rust by Mozilla", [(0, 4, "PL")]),
+                    ("Go by Google", [(0, 2, "PL")]),
+                    ("Rust by Mozilla", [(8, 15, "ORG")]),
+                ],
+            )
+            # The duplicates are changed and reverted one by one
+            quick.filter_spans(lambda span: span.label != "ORG")
+            self.assertEqual(quick.documents[2].label, [])
+            self.assertEqual(quick.undo(1), 1)
+            self.assertEqual([document.label for document in quick.documents], [[(0, 4, "PL")], [(0, 2, "PL")], [(8, 15, "ORG")]])
+
+    def test_fold_diacritics_and_plurals(self):
+        matcher = Matcher(
+            [Entity("Montreal", "CITY"), Entity("Québec", "CITY"), Entity("apple", "FOOD")],
+            fold_diacritics=True,
+            match_plurals=True,
+        )
+        document = Document("Montréal and Quebec sell apples")
+        matcher.annotate(document)
+        self.assertEqual(document.text, "Montréal and Quebec sell apples")
+        self.assertEqual(document.label, [(0, 8, "CITY"), (13, 19, "CITY"), (25, 31, "FOOD")])
+        self.assertEqual(document.text[0:8], "Montréal")
+        document = Document("Montréal and Quebec sell apples")
+        Matcher([Entity("Montreal", "CITY"), Entity("apple", "FOOD")]).annotate(document)
+        self.assertEqual(document.label, [])
+        # The French and Spanish plurals are only matched in their language
+        document = Document("Mr Jones opened the bureaux")
+        Matcher([Entity("Jon", "PER"), Entity("bureau", "ORG")], match_plurals=True).annotate(document)
+        self.assertEqual(document.label, [])
+        document = Document("Mr Jones opened the bureaux")
+        Matcher([Entity("Jon", "PER"), Entity("bureau", "ORG")], match_plurals=True, language="french").annotate(document)
+        self.assertEqual(document.label, [(20, 27, "ORG")])
+        document = Document("Dos ciudades")
+        Matcher([Entity("ciudad", "LOC")], match_plurals=True, language="spanish").annotate(document)
+        self.assertEqual(document.label, [(4, 12, "LOC")])
+        with self.assertRaises(ValueError):
+            Matcher([Entity("ciudad", "LOC")], language="latin")
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMontreal,CITY\ncompany,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nTwo companies moved to Montréal\n")
+            extra = "\n[matching]\nfold_diacritics = true\nmatch_plurals = true"
+            config = Config(self._write_config(directory, entities_path, texts_path, extra=extra))
+            self.assertTrue(config.matching.fold_diacritics)
+            self.assertTrue(config.matching.match_plurals)
+            self.assertEqual(config.matching.language, "english")
+            quick = Quickner(config=config)
+            quick.process()
+        document = quick.documents[0]
+        self.assertEqual(sorted(document.label), [(4, 13, "ORG"), (23, 31, "CITY")])
+        self.assertEqual(document.text[23:31].lower(), "montréal")
+        # The entities matched folded or in the plural are not pruned as never matched
+        report = quick.prune_entities(max_doc_frequency=1.0, min_doc_frequency=1)
+        self.assertEqual(report.rare, [])
+        self.assertEqual(sorted((entity.name, entity.label) for entity in quick.entities), [("company", "ORG"), ("montreal", "CITY")])
+        report = quick.prune_entities(max_doc_frequency=0.5, min_doc_frequency=1)
+        self.assertEqual(sorted(report.frequent), [("company", "ORG", 1), ("montreal", "CITY", 1)])
+        self.assertEqual(report.spans_removed, 2)
+
+    def test_entity_token_filters(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\nApple Inc,ORG\nThe Mozilla Foundation and Corporation,ORG\n")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                texts, entities = f.read().split("[entities.input]")
+            entities = entities.replace("filter = false", "filter = true")
+            entities = entities.replace("[entities.filters]", "[entities.filters]\nmin_tokens = 2\nmax_tokens = 3")
+            with open(config_path, "w") as f:
+                f.write(texts + "[entities.input]" + entities)
+            config = Config(config_path)
+            self.assertEqual(config.entities.filters.min_tokens, 2)
+            self.assertEqual(config.entities.filters.max_tokens, 3)
+            self.assertIsNone(config.texts.filters.min_tokens)
+            quick = Quickner(config=config)
+            quick.process()
+        self.assertEqual([entity.name for entity in quick.entities], ["apple inc"])
+
+    def test_config_profiles_and_env_overrides(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\n")
+            prod_path = os.path.join(directory, "prod.jsonl")
+            extra = f'\n[profiles.prod.annotations.output]\npath = "{prod_path}"\n'
+            config_path = self._write_config(directory, entities_path, extra=extra)
+            self.assertTrue(Config(config_path).annotations.output.path.endswith("annotations.jsonl"))
+            self.assertEqual(Config(config_path, profile="prod").annotations.output.path, prod_path)
+            with self.assertRaises(QuicknerError):
+                Config(config_path, profile="staging")
+            overrides = {
+                "QUICKNER_PROFILE": "prod",
+                "QUICKNER_TEXTS__FILTERS__MAX_LENGTH": "512",
+                "QUICKNER_ENTITIES__INPUT__PATH": "2023",
+            }
+            with mock.patch.dict(os.environ, overrides):
+                config = Config(config_path, validate=False)
+        self.assertEqual(config.annotations.output.path, prod_path)
+        self.assertEqual(config.texts.filters.max_length, 512)
+        self.assertTrue(config.entities.input.path.endswith("2023"))
+
+    def test_config_save(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\n")
+            extra = '\n[matching]\nfuzzy = { max_edits = 1 }\n\n[[rules]]\npattern = "rust"\nlabel = "PL"\nforbid = ["iron"]\n'
+            data = Config(self._write_config(directory, entities_path, extra=extra)).to_dict()
+            data["texts"]["filters"]["max_length"] = 300
+            config = Config.from_dict(data)
+            path = os.path.join(directory, "saved.toml")
+            config.save(path)
+            saved = Config(path)
+        self.assertEqual(saved.texts.filters.max_length, 300)
+        self.assertEqual(saved.to_dict(), config.to_dict())
+
+    def test_streaming_export(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path)))
+            quick.process()
+            expected = quick.save_annotations(os.path.join(directory, "buffered"))
+            config_path = self._write_config(
+                directory, entities_path, extra="\n[annotations.output.streaming]\nqueue_size = 2"
+            )
+            config = Config(config_path)
+            self.assertEqual(config.annotations.output.streaming.queue_size, 2)
+            quick = Quickner(config=config)
+            quick.process()
+            streamed = quick.save_annotations(os.path.join(directory, "streamed"))
+            with open(expected + ".jsonl") as f:
+                expected_lines = sorted(f.read().splitlines())
+            with open(streamed + ".jsonl") as f:
+                self.assertEqual(sorted(f.read().splitlines()), expected_lines)
+
+    def test_per_label_export(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nMozilla,ORG\nrust,PL\npython,PL\nChris Lattner,PERSON\n")
+            extra = '\n[annotations.output.per_label]\ngroups = { TECH = ["PL", "PERSON"] }'
+            config_path = self._write_config(directory, entities_path, extra=extra)
+            with open(config_path) as f:
+                content = f.read().replace("[annotations.output]\n", '[annotations.output]\ndocuments = "annotated"\n')
+            with open(config_path, "w") as f:
+                f.write(content)
+            quick = Quickner(config=Config(config_path))
+            quick.process(True)
+            self.assertEqual(sorted(os.listdir(directory)), ["annotations_ORG.jsonl", "annotations_TECH.jsonl", "annotations_report.json", "config.toml", "entities.csv", "texts.csv"])
+            for name, labels in (("ORG", {"ORG"}), ("TECH", {"PL", "PERSON"})):
+                with open(os.path.join(directory, f"annotations_{name}.jsonl")) as f:
+                    documents = [json.loads(line) for line in f]
+                self.assertTrue(documents)
+                self.assertTrue(all(span[2] in labels for document in documents for span in document["label"]))
+                self.assertTrue(all(document["label"] for document in documents))
+
+    def test_split_by_label(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMozilla,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                content = f.read().replace("[annotations.output]\n", "[annotations.output]\nsplit_by_label = true\n")
+            with open(config_path, "w") as f:
+                f.write(content)
+            config = Config(config_path)
+            self.assertTrue(config.annotations.output.split_by_label)
+            quick = Quickner(config=config)
+            quick.process(True)
+            self.assertEqual(sorted(os.listdir(directory)), ["annotations_ORG.jsonl", "annotations_PL.jsonl", "annotations_report.json", "config.toml", "entities.csv", "texts.csv"])
+            with open(os.path.join(directory, "annotations_PL.jsonl")) as f:
+                documents = [json.loads(line) for line in f]
+        # Every document, with the spans of the label only
+        self.assertEqual(len(documents), len(self.texts))
+        self.assertTrue(all(span[2] == "PL" for document in documents for span in document["label"]))
+        self.assertTrue(any(document["label"] for document in documents))
+
+    def test_label_scoped_excludes(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            excludes_path = os.path.join(directory, "excludes.csv")
+            with open(excludes_path, "w") as f:
+                f.write("name,label\nApple,ORG\nRust,ORG\nmade by Mozilla,\n")
+            config_path = self._write_config(directory, entities_path, extra=f'path = "{excludes_path}"')
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            labels = {document.text: document.label for document in quick.documents}
+            self.assertEqual(labels["rust is made by mozilla"], [(0, 4, "PL")])
+            self.assertEqual(labels["swift was created by chris lattner and apple"], [(0, 5, "PL"), (21, 34, "PERSON")])
+            self.assertIn((36, 40, "PL"), labels["you can find more information about rust at https://www.rust-lang.org/"])
+
+    def test_time_stats(self):
+        documents = [
+            Document("Apple makes phones", meta={"date": "2023-01-05"}),
+            Document("Rust is made by Mozilla", meta={"date": "2023-01-20T10:00:00Z"}),
+            Document("Apple and Rust", meta={"date": "2023-02-01"}),
+            Document("Apple", meta={"date": "unknown"}),
+        ]
+        entities = [Entity("apple", "ORG"), Entity("rust", "PL"), Entity("mozilla", "ORG")]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        stats = quick.time_stats()
+        self.assertEqual([period.period for period in stats.periods], ["2023-01", "2023-02"])
+        self.assertEqual(stats.undated, 1)
+        self.assertEqual(stats.periods[0].new_entities, 3)
+        self.assertEqual(stats.periods[1].entities, [("apple", "ORG", 1, False), ("rust", "PL", 1, False)])
+        self.assertEqual([period.period for period in quick.time_stats(period="year").periods], ["2023"])
+        with self.assertRaises(QuicknerError):
+            quick.time_stats(period="week")
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "drift.csv")
+            stats.to_csv(path)
+            with open(path) as f:
+                lines = f.read().splitlines()
+        self.assertEqual(lines[0], "period,documents,label,entity,count,new")
+        self.assertEqual(len(lines), 6)
+
+    def test_context_rules(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\napple,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nThe Apple CEO presented the iPhone\nI baked an apple pie\nAn apple a day\n")
+            rules = """
+[[rules]]
+pattern = "apple"
+label = "ORG"
+require = ["pie"]
+action = "redirect"
+to = "FOOD"
+
+[[rules]]
+pattern = "apple"
+label = "ORG"
+require = ["iphone", "ceo"]
+window = 3
+"""
+            config_path = self._write_config(directory, entities_path, texts_path, extra=rules)
+            config = Config(config_path)
+            self.assertEqual([rule.action for rule in config.rules], ["redirect", "accept"])
+            quick = Quickner(config=config)
+            quick.process()
+            labels = {document.text: document.label for document in quick.documents}
+            self.assertEqual(labels["the apple ceo presented the iphone"], [(4, 9, "ORG")])
+            self.assertEqual(labels["i baked an apple pie"], [(11, 16, "FOOD")])
+            self.assertEqual(labels["an apple a day"], [])
+
+    def test_high_precision_labels(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\napple,ORG\nbank of america,ORG\nbill,PERSON\npie,FOOD\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write(
+                    "text\nApple and Bank of America\nan apple pie\n\"the bill, then another bill\"\nthe bill\n"
+                )
+            precision = '\n[labels.precision]\nORG = { title_case = true }\nPERSON = { min_mentions = 2 }'
+            config = Config(self._write_config(directory, entities_path, texts_path, extra=precision))
+            self.assertTrue(config.labels.precision["ORG"].title_case)
+            self.assertEqual(config.labels.precision["PERSON"].min_mentions, 2)
+            quick = Quickner(config=config)
+            quick.process()
+            self.assertEqual(
+                [document.label for document in quick.documents],
+                [[(0, 5, "ORG"), (10, 25, "ORG")], [(9, 12, "FOOD")], [(4, 8, "PERSON"), (23, 27, "PERSON")], []],
+            )
+            with self.assertRaisesRegex(QuicknerError, "labels.precision.ORG"):
+                Config(self._write_config(directory, entities_path, texts_path, extra="\n[labels.precision]\nORG = {}"))
+
+    def test_propagate_mentions(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nBarack Obama,PERSON\nMichelle Obama,PERSON\nWhite House,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nBarack Obama left the White House. Obama and Barack spoke of the House\nObama spoke\n")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            self.assertEqual(quick.documents[0].label, [(0, 12, "PERSON"), (22, 33, "ORG")])
+            config_path = self._write_config(
+                directory, entities_path, texts_path, extra="[matching]\npropagate_mentions = true"
+            )
+            config = Config(config_path)
+            self.assertTrue(config.matching.propagate_mentions)
+            quick = Quickner(config=config)
+            quick.process()
+            self.assertEqual(
+                quick.documents[0].label,
+                [(0, 12, "PERSON"), (22, 33, "ORG"), (35, 40, "PERSON"), (45, 51, "PERSON"), (65, 70, "ORG")],
+            )
+            spans = quick.documents[0].spans()
+            self.assertEqual((spans[2].source, spans[2].entity_name), ("mention", "barack obama"))
+            self.assertEqual(spans[2].to_dict()["confidence"], 0.5)
+            # Mentions are propagated within a document only
+            self.assertEqual(quick.documents[1].label, [])
+
+    def test_verified_documents(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\nmozilla,ORG\nrust is made,PL\n")
+            documents = [
+                Document("rust is made by mozilla", [(0, 4, "LANGUAGE")], status="gold"),
+                Document("rust is made by mozilla today"),
+            ]
+            self.assertEqual(documents[0].status, "gold")
+            self.assertEqual(documents[0].to_dict()["status"], "gold")
+            self.assertNotIn("status", documents[1].to_dict())
+            with self.assertRaises(QuicknerError):
+                Document("rust", status="reviewed")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path)), documents=documents)
+            self.assertEqual(quick.config.matching.verified, "skip")
+            quick.process()
+            self.assertEqual(quick.documents[0].label, [(0, 4, "LANGUAGE")])
+            self.assertEqual(quick.documents[1].label, [(0, 4, "PL"), (0, 12, "PL"), (16, 23, "ORG")])
+            config = Config(self._write_config(directory, entities_path, extra='\n[matching]\nverified = "append"'))
+            self.assertEqual(config.matching.verified, "append")
+            quick = Quickner(config=config, documents=documents)
+            quick.process()
+            # The spans overlapping the gold span are not added
+            self.assertEqual(quick.documents[0].label, [(0, 4, "LANGUAGE"), (16, 23, "ORG")])
+            self.assertEqual([span.source for span in quick.documents[0].spans()], ["unknown", "gazetteer"])
+            # The status is written to and read from the JSONL annotations
+            path = os.path.join(directory, "annotations.jsonl")
+            quick.to_jsonl(path)
+            quick = from_jsonl(path)
+            self.assertEqual([document.status for document in quick.documents], ["gold", "machine"])
+            self.assertEqual(quick.set_status([quick.documents[1].id], "corrected"), 1)
+            self.assertEqual(quick.documents[1].status, "corrected")
+            with self.assertRaises(QuicknerError):
+                quick.set_status([], "reviewed")
+
+    def test_document_tags(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nmozilla,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nThe match ended with a late goal and a second goal\nMozilla raised its rates\nNothing here\n")
+            keywords_path = os.path.join(directory, "keywords.csv")
+            with open(keywords_path, "w") as f:
+                f.write("name,label\ngoal,sports\nmatch,sports\nrates,finance\nmozilla,tech\n")
+            extra = f'\n[tags]\nmin_matches = 2\n[tags.input]\npath = "{keywords_path}"\nfilter = false'
+            config = Config(self._write_config(directory, entities_path, texts_path, extra=extra))
+            self.assertEqual(config.tags.min_matches, 2)
+            quick = Quickner(config=config)
+            quick.process(True)
+            self.assertEqual([document.tags for document in quick.documents], [["sports"], [], []])
+            self.assertEqual(quick.documents[1].label, [(0, 7, "ORG")])
+            with open(os.path.join(directory, "annotations.jsonl")) as f:
+                records = [json.loads(line) for line in f]
+            self.assertEqual(records[0]["tags"], ["sports"])
+            self.assertNotIn("tags", records[1])
+            self.assertEqual(from_jsonl(os.path.join(directory, "annotations.jsonl")).documents[0].tags, ["sports"])
+            # The tags of a verified document are kept unless `verified` is set
+            quick.set_status([quick.documents[0].id], "gold")
+            keywords = [Entity("goal", "sports"), Entity("rates", "finance"), Entity("mozilla", "tech")]
+            self.assertEqual(quick.tag(keywords), 1)
+            self.assertEqual([document.tags for document in quick.documents], [["sports"], ["finance", "tech"], []])
+            self.assertEqual(quick.tag([Entity("late", "news")]), 0)
+            self.assertEqual(quick.documents[0].tags, ["sports"])
+
+    def test_relations(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nTim Cook,PERSON\nApple,ORG\nParis,CITY\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write('text\n"Tim Cook, CEO of Apple, visited Paris"\nApple hired Tim Cook\n')
+            relations = """
+[[relations]]
+label = "CEO_OF"
+head = "PERSON"
+tail = "ORG"
+pattern = "{head}, CEO of {tail}"
+
+[[relations]]
+label = "VISITED"
+head = "PERSON"
+tail = "CITY"
+window = 5
+"""
+            config_path = self._write_config(directory, entities_path, texts_path, extra=relations)
+            config = Config(config_path)
+            self.assertEqual([relation.window for relation in config.relations], [10, 5])
+            quick = Quickner(config=config)
+            quick.process()
+            tim, apple, paris = (0, 8, "PERSON"), (17, 22, "ORG"), (32, 37, "CITY")
+            self.assertEqual(quick.documents[0].relations, [(tim, apple, "CEO_OF"), (tim, paris, "VISITED")])
+            self.assertEqual(quick.documents[1].relations, [])
+            path = quick.save_annotations(os.path.join(directory, "annotations"))
+            with open(path + ".jsonl") as f:
+                record = json.loads(f.readline())
+            self.assertEqual(record["relations"][0], {"head_span": list(tim), "tail_span": list(apple), "label": "CEO_OF"})
+            self.assertEqual(Quickner.from_jsonl(path + ".jsonl").documents[0].relations, quick.documents[0].relations)
+            brat_directory = os.path.join(directory, "brat")
+            os.mkdir(brat_directory)
+            quick.save_annotations(os.path.join(brat_directory, "annotations"), Format.BRAT)
+            with open(os.path.join(brat_directory, "annotations.ann")) as f:
+                lines = f.read().splitlines()
+            self.assertIn("R0\tCEO_OF Arg1:T0 Arg2:T1", lines)
+            self.assertEqual(from_dir(brat_directory).documents[0].relations, quick.documents[0].relations)
+            config_path = self._write_config(directory, entities_path, texts_path, extra='[[relations]]\nlabel = "X"\npattern = "{head} of"')
+            with self.assertRaises(QuicknerError):
+                Config(config_path)
+
+    def test_sentence_split(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\napple,ORG\ngosling,PERSON\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text,source\n\"Dr. Smith works at Apple. He met J. Gosling in 2020! Is it great?\",news\n")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                content = f.read().replace("[texts.input]\n", '[texts.input]\nsplit = "sentence"\n')
+            with open(config_path, "w") as f:
+                f.write(content)
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            texts = [document.text for document in quick.documents]
+            self.assertEqual(texts, ["dr. smith works at apple.", "he met j. gosling in 2020!", "is it great?"])
+            parents = {document.meta["parent"] for document in quick.documents}
+            self.assertEqual(len(parents), 1)
+            self.assertEqual([document.meta["sentence"] for document in quick.documents], ["0", "1", "2"])
+            self.assertEqual(quick.documents[1].id, parents.pop() + "-1")
+            self.assertEqual(quick.documents[0].meta["source"], "news")
+            self.assertEqual(quick.documents[1].label, [(10, 17, "PERSON")])
+
+    def test_limit_and_sample(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\napple,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\n" + "".join(f"apple text {index}\n" for index in range(20)))
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                template = f.read()
+
+            def run(options):
+                with open(config_path, "w") as f:
+                    f.write(template.replace("[texts.input]\n", "[texts.input]\n" + options))
+                quick = Quickner(config=Config(config_path))
+                quick.process()
+                return [document.id for document in quick.documents]
+
+            self.assertEqual(len(run("limit = 2\n")), 2)
+            sample = run("sample = 0.5\nseed = 1\n")
+            self.assertTrue(0 < len(sample) < 20)
+            self.assertEqual(run("sample = 0.5\nseed = 1\n"), sample)
+            self.assertEqual(run("sample = 0.5\nseed = 1\nlimit = 2\n"), sample[:2])
+            self.assertEqual(Config(config_path).texts.input.sample, 0.5)
+
+    def test_id_strategies(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\napple,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("id,text,source\na-1,apple pie,web\nb-2,apple tart,news\nc-3,apple pie,web\n")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                template = f.read()
+
+            def run(strategy):
+                with open(config_path, "w") as f:
+                    f.write(template.replace("[texts.input]\n", f'[texts.input]\nid = "{strategy}"\n'))
+                quick = Quickner(config=Config(config_path))
+                quick.process()
+                return quick.documents
+
+            self.assertEqual([document.id for document in run("sequential")], ["0", "1"])
+            documents = run("column")
+            self.assertEqual([document.id for document in documents], ["a-1", "b-2", "c-3"])
+            self.assertEqual(documents[0].meta, {"source": "web"})
+            sha256 = [document.id for document in run("sha256")]
+            self.assertEqual(len(sha256[0]), 64)
+            self.assertEqual(sha256, [document.id for document in run("sha256")])
+            uuid = run("uuid")[0].id
+            self.assertEqual([len(part) for part in uuid.split("-")], [8, 4, 4, 4, 12])
+            self.assertEqual(uuid[14], "8")
+            self.assertEqual(Config(config_path).texts.input.id, "uuid")
+            # A missing id column or an empty id is an error of the run
+            with open(texts_path, "w") as f:
+                f.write("text\napple pie\n")
+            with self.assertRaisesRegex(QuicknerError, "missing field `id`"):
+                run("column")
+            with open(texts_path, "w") as f:
+                f.write("id,text\na-1,apple pie\n,apple tart\n")
+            with self.assertRaisesRegex(QuicknerError, "empty id"):
+                run("column")
+            with open(texts_path, "w") as f:
+                f.write("content\napple pie\n")
+            with self.assertRaisesRegex(QuicknerError, "missing field `text`"):
+                run("sequential")
+
+    def test_frozen_labels(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            labels_path = os.path.join(directory, "labels.json")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                content = f.read().replace(
+                    "[annotations.output]\n", f'[annotations.output]\nfrozen_labels = "{labels_path}"\n'
+                )
+            with open(config_path, "w") as f:
+                f.write(content)
+            Quickner(config=Config(config_path)).process()
+            with open(labels_path) as f:
+                self.assertEqual(json.load(f), ["ORG", "PL"])
+            Quickner(config=Config(config_path)).process()
+            with open(labels_path, "w") as f:
+                json.dump(["ORG", "PERSON"], f)
+            with self.assertRaises(Exception) as context:
+                Quickner(config=Config(config_path)).process(True)
+            self.assertIn("new labels PL", str(context.exception))
+            self.assertIn("missing labels PERSON", str(context.exception))
+            self.assertFalse(os.path.exists(os.path.join(directory, "annotations.jsonl")))
+
+    def test_preview(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMozilla,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            quick = Quickner(config=Config(config_path))
+            documents = quick.preview(2)
+            self.assertEqual(len(documents), 2)
+            self.assertEqual(documents[0].label, [(0, 4, "PL"), (16, 23, "ORG")])
+            self.assertEqual(documents[0].pretty(), "\x1b[31mrust\x1b[0m[PL] is made by \x1b[32mmozilla\x1b[0m[ORG]")
+            self.assertEqual(len(quick.documents), 0)
+            self.assertFalse(os.path.exists(os.path.join(directory, "annotations.jsonl")))
+
+    def test_progress_callback(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            calls = []
+            quick = Quickner(config=Config(config_path))
+            quick.process(progress=lambda done, total: calls.append((done, total)), progress_every=2)
+            total = len(quick.documents)
+            self.assertEqual(calls[0], (0, total))
+            self.assertEqual(calls[-1], (total, total))
+            self.assertTrue(all(done <= 2 * index for index, (done, _) in enumerate(calls)))
+
+            def stop(done, total):
+                if done > 0:
+                    raise KeyboardInterrupt("stopped")
+
+            with self.assertRaises(KeyboardInterrupt):
+                Quickner(config=Config(config_path)).process(progress=stop, progress_every=2)
+            Quickner(config=Config(config_path)).process(progress=False)
+            with self.assertRaises(QuicknerError):
+                Quickner(config=Config(config_path)).process(progress="bar")
+
+            # The view cannot be read while the instance is being processed
+            quick = Quickner(config=Config(config_path))
+            view = quick.documents
+            errors = []
+
+            def read(done, total):
+                for access in (len, repr, lambda view: view[0], lambda view: next(iter(view))):
+                    try:
+                        access(view)
+                    except QuicknerError as error:
+                        errors.append(str(error))
+
+            quick.process(progress=read, progress_every=100)
+            self.assertTrue(errors)
+            self.assertEqual(len(errors) % 4, 0)
+            self.assertIn("being changed", errors[0])
+            self.assertEqual(len(view), len(quick.documents))
+
+    def test_process_async(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            quick = Quickner(config=Config(config_path))
+            task = quick.process_async(progress_every=2)
+            report = task.result(timeout=30)
+            self.assertTrue(task.done())
+            self.assertEqual(report.gazetteer, {"ORG": 1, "PL": 1})
+            self.assertEqual(task.progress(), (len(quick.documents), len(quick.documents)))
+            self.assertTrue(any(document.label for document in quick.documents))
+            self.assertIn("done=True", repr(task))
+
+            async def run():
+                return await Quickner(config=Config(config_path)).process_async()
+
+            self.assertEqual(asyncio.run(run()).gazetteer, report.gazetteer)
+            # Cancelling a task stops only its run
+            task = Quickner(config=Config(config_path)).process_async()
+            task.cancel()
+            with self.assertRaisesRegex(Exception, "cancelled"):
+                task.result(timeout=30)
+            other = Quickner(config=Config(config_path))
+            self.assertEqual(other.process().gazetteer, report.gazetteer)
+            self.assertTrue(any(document.label for document in other.documents))
+            # The annotations cannot be saved over a directory
+            os.mkdir(os.path.join(directory, "annotations.jsonl"))
+            with self.assertRaises(QuicknerError):
+                Quickner(config=Config(config_path)).process_async(save=True).result(timeout=30)
+
+    def test_gzip_export(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                content = f.read().replace("[annotations.output]\n", '[annotations.output]\ncompression = "gzip"\n')
+            with open(config_path, "w") as f:
+                f.write(content)
+            config = Config(config_path)
+            self.assertEqual(config.annotations.output.compression, "gzip")
+            quick = Quickner(config=config)
+            quick.process(True)
+            path = os.path.join(directory, "annotations.jsonl.gz")
+            with gzip.open(path, "rt") as f:
+                lines = [json.loads(line) for line in f]
+            self.assertEqual(len(lines), len(self.texts))
+            self.assertEqual(len(from_jsonl(path).documents), len(self.texts))
+
+    def test_export_stamp(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            with open(config_path) as f:
+                content = f.read().replace("[annotations.output]\n", "[annotations.output]\nstamp = true\n")
+            with open(config_path, "w") as f:
+                f.write(content)
+            config = Config(config_path)
+            self.assertTrue(config.annotations.output.stamp)
+            quick = Quickner(config=config)
+            quick.process(True)
+            path = os.path.join(directory, "annotations.jsonl")
+            with open(path) as f:
+                lines = [json.loads(line) for line in f]
+            self.assertEqual(len(lines), len(self.texts) + 1)
+            stamp = lines[0]["quickner"]
+            self.assertEqual(set(stamp), {"version", "gazetteer_hash", "config_hash", "created"})
+            self.assertEqual(len(from_jsonl(path).documents), len(self.texts))
+            quick.to_jsonl(os.path.join(directory, "again.jsonl"))
+            with open(os.path.join(directory, "again.jsonl")) as f:
+                again = json.loads(f.readline())["quickner"]
+            self.assertEqual(again["gazetteer_hash"], stamp["gazetteer_hash"])
+            self.assertEqual(again["config_hash"], stamp["config_hash"])
+            # The runs logged are tied to the files by the same hash
+            self.assertEqual(quick.run_metadata()["params"]["quickner.config_hash"], stamp["config_hash"])
+
+    def test_checkpoint_resume(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            checkpoint_path = os.path.join(directory, "checkpoint")
+            extra = f'\n[checkpoint]\nevery = 2\npath = "{checkpoint_path}"'
+            config_path = self._write_config(directory, entities_path, extra=extra)
+            with open(config_path) as f:
+                content = f.read().replace("[annotations.output]\n", "[annotations.output]\nstamp = true\n")
+            with open(config_path, "w") as f:
+                f.write(content)
+            quick = Quickner(config=Config(config_path))
+            quick.process(True)
+            self.assertEqual(quick.config.checkpoint.every, 2)
+            self.assertFalse(os.path.exists(checkpoint_path))
+            with open(os.path.join(directory, "annotations.jsonl")) as f:
+                gazetteer_hash = json.loads(f.readline())["quickner"]["gazetteer_hash"]
+            # Checkpoint of a crashed run holding the first document only
+            first = quick.documents[0]
+            os.makedirs(checkpoint_path)
+            with open(os.path.join(checkpoint_path, "chunk_00001.jsonl"), "w") as f:
+                f.write(json.dumps({"id": first.id, "text": first.text, "label": [[0, 1, "CHECKPOINT"]]}) + "\n")
+            manifest = {
+                "version": "0",
+                "gazetteer_hash": gazetteer_hash,
+                "config_hash": None,
+                "documents": len(self.texts),
+                "annotated": 1,
+                "chunks": ["chunk_00001.jsonl"],
+                "updated": "",
+            }
+            with open(os.path.join(checkpoint_path, "manifest.json"), "w") as f:
+                json.dump(manifest, f)
+            with open(config_path, "a") as f:
+                f.write("\nresume = true\n")
+            resumed = Quickner(config=Config(config_path))
+            self.assertTrue(resumed.config.checkpoint.resume)
+            resumed.process()
+            self.assertFalse(os.path.exists(checkpoint_path))
+        self.assertEqual(resumed.documents[0].label, [(0, 1, "CHECKPOINT")])
+        for document, expected in list(zip(resumed.documents, quick.documents))[1:]:
+            self.assertEqual(document.label, expected.label)
+
+    def test_run_report(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nApple loves rust\nApple loves rust\nrust and Apple and rust\nok\n")
+            config_path = self._write_config(directory, entities_path, texts_path)
+            with open(config_path) as f:
+                content = f.read().replace("filter = false\n", "filter = true\n", 1).replace("min_length = 0", "min_length = 3", 1)
+            with open(config_path, "w") as f:
+                f.write(content)
+            report = Quickner(config=Config(config_path)).process(True)
+            self.assertEqual(report.output, os.path.join(directory, "annotations"))
+            self.assertEqual(report.stats.documents, 2)
+            self.assertEqual((report.filtered_texts, report.duplicate_texts), (1, 1))
+            self.assertEqual(report.gazetteer, {"ORG": 1, "PL": 1})
+            self.assertEqual(report.top_entities[0], ("rust", "PL", 3))
+            self.assertEqual([stage for stage, _ in report.stages][-1], "annotation")
+            with open(os.path.join(directory, "annotations_report.json")) as f:
+                saved = json.load(f)
+            self.assertEqual(saved, report.to_dict())
+            self.assertEqual(saved["skipped_texts"]["examples"], ["ok"])
+            self.assertEqual(saved["config"]["texts"]["filters"]["min_length"], 3)
+            os.remove(os.path.join(directory, "annotations_report.json"))
+            with open(config_path, "w") as f:
+                f.write(content.replace("[annotations.output]\n", "[annotations.output]\nreport = false\n"))
+            config = Config(config_path)
+            self.assertFalse(config.annotations.output.report)
+            Quickner(config=config).process(True)
+            self.assertFalse(os.path.exists(os.path.join(directory, "annotations_report.json")))
+            self.assertIsNone(Quickner(config=config).process().output)
+
+    def test_thread_pool(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            config = Config(config_path)
+            self.assertIsNone(config.performance.num_threads)
+            self.assertGreaterEqual(Quickner(config=config).process().threads, 1)
+            config_path = self._write_config(directory, entities_path, extra="[performance]\nnum_threads = 2")
+            config = Config(config_path)
+            self.assertEqual(config.performance.num_threads, 2)
+            calls = []
+            quick = Quickner(config=config)
+            report = quick.process(progress=lambda done, total: calls.append(done), progress_every=1)
+            self.assertEqual(report.threads, 2)
+            self.assertEqual(report.to_dict()["threads"], 2)
+            self.assertEqual(calls[-1], len(self.texts))
+            self.assertEqual(report.stats.documents, len(self.texts))
+            config_path = self._write_config(directory, entities_path, extra="[performance]\nnum_threads = 0")
+            with self.assertRaises(QuicknerError):
+                Config(config_path)
+
+    def test_dict_round_trip(self):
+        document = Document("Apple loves rust", [(0, 5, "ORG")], {"source": "news"})
+        data = document.to_dict()
+        self.assertEqual(data, {"id": document.id, "text": "Apple loves rust", "label": [[0, 5, "ORG"]], "meta": {"source": "news"}})
+        restored = Document.from_dict(json.loads(json.dumps(data)))
+        self.assertEqual((restored.id, restored.text, restored.label, restored.meta), (document.id, document.text, document.label, document.meta))
+        self.assertEqual(Document.from_dict({"text": "Apple loves rust"}).id, document.id)
+        with self.assertRaises(QuicknerError):
+            Document.from_dict({"label": []})
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            config = Config(self._write_config(directory, entities_path))
+            data = config.to_dict()
+            self.assertEqual(data["annotations"]["format"], "jsonl")
+            restored = Config.from_dict(json.loads(json.dumps(data)))
+            self.assertEqual(restored.to_dict(), data)
+            self.assertEqual(restored.texts.input.path, config.texts.input.path)
+            data["texts"]["filters"]["min_length"] = 2000
+            with self.assertRaises(QuicknerError):
+                Config.from_dict(data)
+            self.assertEqual(Config.from_dict(data, validate=False).texts.filters.min_length, 2000)
+        quick = Quickner(
+            documents=[Document(text) for text in self.texts],
+            entities=[Entity(name, label) for name, label in self.entities],
+        )
+        quick.process()
+        data = json.loads(json.dumps(quick.to_dict()))
+        self.assertEqual(set(data), {"config", "entities", "documents"})
+        self.assertEqual(data["entities"][0], {"name": "rust", "label": "PL"})
+        restored = Quickner.from_dict(data)
+        self.assertEqual([d.label for d in restored.documents], [d.label for d in quick.documents])
+        self.assertEqual(len(restored.entities), len(quick.entities))
+        restored = Quickner.from_dict({"documents": [{"text": text} for text in self.texts], "entities": data["entities"]})
+        restored.process()
+        self.assertEqual([d.label for d in restored.documents], [d.label for d in quick.documents])
+
+    def test_save_and_load_project(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMozilla,ORG\nrust,PL\nApple,ORG\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path)))
+            quick.process()
+            quick.add_document(Document("Apple ships rust", [(0, 5, "ORG")], {"source": "manual"}))
+            path = os.path.join(directory, "session.qnr")
+            quick.save_project(path)
+            with gzip.open(path) as f:
+                self.assertEqual(len(json.load(f)["documents"]), len(self.texts) + 1)
+            loaded = Quickner.load_project(path)
+        self.assertEqual(loaded.to_dict(), quick.to_dict())
+        self.assertEqual(loaded.config_path, quick.config_path)
+        self.assertEqual(len(loaded.find_documents_by_label("ORG")), len(quick.find_documents_by_label("ORG")))
+        self.assertEqual(loaded.documents[-1].meta, {"source": "manual"})
+        with tempfile.NamedTemporaryFile(suffix=".qnr") as f:
+            f.write(b"not a project")
+            f.flush()
+            with self.assertRaises(QuicknerError):
+                Quickner.load_project(f.name)
+
+    def test_dedupe(self):
+        texts = [
+            "Apple unveiled a new iPhone in Cupertino on Tuesday, with a faster chip and a better camera.",
+            "rust is made by Mozilla",
+            "APPLE unveiled a new iPhone in Cupertino on Tuesday -- with a faster chip and a better camera!",
+            "Apple unveiled a new iPhone in Cupertino on Tuesday, with a faster chip and a better camera. Read more.",
+            "Rust is made by Mozilla.",
+        ]
+        quick = Quickner(documents=[Document(text) for text in texts])
+        ids = [document.id for document in quick.documents]
+        clusters = quick.duplicates(1.0)
+        self.assertEqual([(c.kept, c.duplicates) for c in clusters], [(ids[0], [(ids[2], 1.0)]), (ids[1], [(ids[4], 1.0)])])
+        clusters = quick.duplicates(0.8)
+        self.assertEqual(clusters[0].kept, ids[0])
+        self.assertEqual([id for id, _ in clusters[0].duplicates], [ids[2], ids[3]])
+        self.assertTrue(0.8 <= clusters[0].duplicates[1][1] < 1.0)
+        self.assertEqual(len(quick.documents), 5)
+        with self.assertRaises(QuicknerError):
+            quick.duplicates(0)
+        self.assertEqual(len(quick.dedupe(0.8)), 2)
+        self.assertEqual([document.id for document in quick.documents], [ids[0], ids[1]])
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nMozilla,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\n" + "".join(f'"{text}"\n' for text in texts))
+            config_path = self._write_config(directory, entities_path, texts_path, extra="")
+            with open(config_path) as f:
+                content = f.read().replace("filter = false\n", "filter = false\ndedupe = 0.8\n", 1)
+            with open(config_path, "w") as f:
+                f.write(content)
+            config = Config(config_path)
+            self.assertEqual(config.texts.input.dedupe, 0.8)
+            report = Quickner(config=config).process()
+            self.assertEqual(report.stats.documents, 2)
+            self.assertEqual(report.near_duplicate_texts, 3)
+
+    def test_entity_aliases(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label,id,aliases\nIBM,ORG,Q37156,I.B.M.|International Business Machines| IBM \nRust,PL,,\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nInternational Business Machines is also called IBM or I.B.M. and uses Rust\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path, texts_path)))
+            quick.process()
+        entities = {entity.name: entity for entity in quick.entities}
+        self.assertEqual(set(entities), {"ibm", "i.b.m.", "international business machines", "rust"})
+        for name in ("ibm", "i.b.m.", "international business machines"):
+            self.assertEqual((entities[name].label, entities[name].id, entities[name].canonical), ("ORG", "Q37156", "IBM"))
+        self.assertIsNone(entities["rust"].canonical)
+        self.assertEqual(quick.documents[0].label, [(0, 31, "ORG"), (47, 50, "ORG"), (54, 60, "ORG"), (70, 74, "PL")])
+
+    def test_abbreviations(self):
+        self.assertEqual(find_abbreviations("The World Health Organization (WHO) said"), [("WHO", "World Health Organization")])
+        self.assertEqual(find_abbreviations("WHO (World Health Organization) said"), [("WHO", "World Health Organization")])
+        self.assertEqual(find_abbreviations("non-small cell lung cancer (NSCLC; see below)"), [("NSCLC", "non-small cell lung cancer")])
+        self.assertEqual(find_abbreviations("as shown in the results (Figure 2)"), [])
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label,id\nWorld Health Organization,ORG,Q7817\nBRCA1,GENE,\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nThe World Health Organization (WHO) published a report.\nThe breast cancer gene 1 (BRCA1) is studied by WHO.\n")
+            config_path = self._write_config(directory, entities_path, texts_path, extra="\n[matching]\nabbreviations = true")
+            config = Config(config_path)
+            self.assertTrue(config.matching.abbreviations)
+            quick = Quickner(config=config)
+            quick.process()
+        entities = {entity.name: entity for entity in quick.entities}
+        self.assertEqual((entities["who"].label, entities["who"].id, entities["who"].canonical), ("ORG", "Q7817", "World Health Organization"))
+        self.assertEqual((entities["breast cancer gene 1"].label, entities["breast cancer gene 1"].canonical), ("GENE", "breast cancer gene 1"))
+        self.assertEqual(quick.documents[0].label, [(4, 29, "ORG"), (31, 34, "ORG")])
+        self.assertEqual(quick.documents[1].label, [(4, 24, "GENE"), (26, 31, "GENE"), (47, 50, "ORG")])
+        quick = Quickner(documents=[Document("Magnetic resonance imaging (MRI) is safe")], entities=[Entity("magnetic resonance imaging", "TEST")])
+        self.assertEqual([(entity.name, entity.label) for entity in quick.learn_abbreviations()], [("mri", "TEST")])
+        self.assertEqual(len(quick.entities), 2)
+
+    def test_document_meta_passthrough(self):
+        with tempfile.TemporaryDirectory() as directory:
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write('text,source,language\n"Apple makes computers",news,en\n')
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path, texts_path)))
+            quick.process()
+            self.assertEqual(quick.documents[0].meta, {"source": "news", "language": "en"})
+            jsonl_path = os.path.join(directory, "annotations.jsonl")
+            quick.to_jsonl(jsonl_path)
+            with open(jsonl_path) as f:
+                self.assertEqual(json.loads(f.readline())["meta"], {"source": "news", "language": "en"})
+            self.assertEqual(from_jsonl(jsonl_path).documents[0].meta, {"source": "news", "language": "en"})
+            spacy_path = os.path.join(directory, "annotations.json")
+            quick.to_spacy(spacy_path)
+            self.assertEqual(from_spacy(spacy_path).documents[0].meta, {"source": "news", "language": "en"})
+            extra_path = os.path.join(directory, "extra.jsonl")
+            with open(extra_path, "w") as f:
+                f.write(json.dumps({"text": "Apple", "label": [], "source": "blog", "year": 2023}) + "\n")
+            self.assertEqual(from_jsonl(extra_path).documents[0].meta, {})
+            self.assertEqual(from_jsonl(extra_path).documents[0].extra, {"source": "blog", "year": 2023})
+
+    def test_jsonl_unknown_fields_roundtrip(self):
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "input.jsonl")
+            record = {
+                "id": "doc-1",
+                "text": "Apple makes computers",
+                "label": [[0, 5, "ORG"]],
+                "source": "crawler",
+                "scores": {"quality": 0.9},
+                "flags": [1, None, "x"],
+            }
+            with open(path, "w") as f:
+                f.write(json.dumps(record) + "\n")
+            quick = from_jsonl(path)
+            document = quick.documents[0]
+            self.assertEqual(document.extra, {"source": "crawler", "scores": {"quality": 0.9}, "flags": [1, None, "x"]})
+            self.assertEqual(document.to_dict()["scores"], {"quality": 0.9})
+            self.assertEqual(Document.from_dict(record).extra["flags"], [1, None, "x"])
+            output = os.path.join(directory, "output.jsonl")
+            quick.to_jsonl(output)
+            with open(output) as f:
+                exported = json.loads(f.readline())
+            for key, value in record.items():
+                self.assertEqual(exported[key], value)
+
+    def test_sequence_protocols(self):
+        documents = [Document(text) for text in self.texts]
+        quick = Quickner(documents=documents)
+        self.assertEqual(len(quick), 5)
+        self.assertEqual(len(quick.documents), 5)
+        self.assertEqual([document.text for document in quick], list(self.texts))
+        self.assertEqual([document.text for document in quick.documents], list(self.texts))
+        self.assertEqual([document.text for document in quick.documents[1:3]], list(self.texts[1:3]))
+        self.assertEqual([document.text for document in quick[::-2]], list(self.texts[::-2]))
+        self.assertEqual(quick[-1].text, self.texts[-1])
+        self.assertEqual(quick.documents[0].id, documents[0].id)
+        with self.assertRaises(IndexError):
+            quick.documents[5]
+        view = quick.documents
+        quick.add_document(Document("Added later"))
+        self.assertEqual(len(view), 6)
+
+    def test_to_entity_ruler(self):
+        entities = [Entity("Guido van Rossum", "PERSON"), Entity("Apple", "ORG", id="Q312")]
+        quick = Quickner(entities=entities)
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "patterns.jsonl")
+            quick.to_entity_ruler(path)
+            with open(path) as f:
+                patterns = [json.loads(line) for line in f]
+        self.assertEqual(patterns, [
+            {"label": "PERSON", "pattern": [{"LOWER": "guido"}, {"LOWER": "van"}, {"LOWER": "rossum"}]},
+            {"label": "ORG", "pattern": [{"LOWER": "apple"}], "id": "Q312"},
+        ])
+
+    def test_to_entity_links(self):
+        entities = [
+            Entity("Apple", "ORG", id="Q312"),
+            Entity("Paris", "LOC", id="Q90"),
+            Entity("Paris", "LOC", id="Q830149"),
+            Entity("Rust", "PL"),
+        ]
+        documents = [Document("Apple opened a store in Paris"), Document("Rust")]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        with tempfile.TemporaryDirectory() as directory:
+            path = os.path.join(directory, "links.jsonl")
+            quick.to_entity_links(path)
+            with open(path) as f:
+                links = [json.loads(line) for line in f]
+        self.assertEqual(links[0]["spans"], [
+            {"start": 0, "end": 5, "label": "ORG", "text": "apple", "id": "Q312"},
+            {"start": 24, "end": 29, "label": "LOC", "text": "paris", "candidates": [{"id": "Q90"}, {"id": "Q830149"}]},
+        ])
+        self.assertEqual(links[1]["spans"], [{"start": 0, "end": 4, "label": "PL", "text": "rust"}])
+
+    def test_to_docbin(self):
+        saved = {}
+
+        class Span:
+            def __init__(self, start, end, label):
+                self.start, self.end, self.label = start, end, label
+
+        class Doc:
+            def __init__(self, text):
+                self.text = text
+                self.ents = ()
+
+            def char_span(self, start, end, label):
+                # Tokens are split on spaces
+                starts = {0} | {i + 1 for i, c in enumerate(self.text) if c == " "}
+                ends = {len(self.text)} | {i for i, c in enumerate(self.text) if c == " "}
+                return Span(start, end, label) if start in starts and end in ends else None
+
+        class Language:
+            def make_doc(self, text):
+                return Doc(text)
+
+        class DocBin:
+            def __init__(self, docs):
+                self.docs = docs
+
+            def to_disk(self, path):
+                saved[path] = self.docs
+
+        def blank(lang):
+            saved["lang"] = lang
+            return Language()
+
+        spacy = types.ModuleType("spacy")
+        spacy.blank = blank
+        spacy.util = types.SimpleNamespace(filter_spans=lambda spans: sorted(spans, key=lambda span: span.start))
+        spacy.tokens = types.SimpleNamespace(DocBin=DocBin)
+        documents = [
+            Document("rust is made by Mozilla", [(0, 4, "PL"), (16, 23, "ORG")]),
+            Document("rusty code", [(0, 4, "PL")]),
+        ]
+        quick = Quickner(documents=documents)
+        with mock.patch.dict(sys.modules, {"spacy": spacy}):
+            with self.assertWarns(UserWarning):
+                quick.to_docbin("train.spacy", lang="fr")
+        self.assertEqual(saved["lang"], "fr")
+        docs = saved["train.spacy"]
+        self.assertEqual([doc.text for doc in docs], ["rust is made by Mozilla", "rusty code"])
+        self.assertEqual([(span.start, span.end, span.label) for span in docs[0].ents], [(0, 4, "PL"), (16, 23, "ORG")])
+        self.assertEqual(list(docs[1].ents), [])
+        with mock.patch.dict(sys.modules, {"spacy": None}):
+            with self.assertRaises(ImportError):
+                quick.to_docbin("train.spacy")
+
+    def test_to_pandas(self):
+        class DataFrame:
+            def __init__(self, data, columns):
+                self.data = data
+                self.columns = columns
+
+        pandas = types.ModuleType("pandas")
+        pandas.DataFrame = DataFrame
+        documents = [Document(text) for text in self.texts[:2]]
+        entities = [Entity(*(entity)) for entity in self.entities]
+        quick = Quickner(documents=documents, entities=entities)
+        quick.process()
+        with mock.patch.dict(sys.modules, {"pandas": pandas}):
+            frame = quick.to_pandas()
+        self.assertEqual(frame.columns, ["id", "text", "start", "end", "label", "entity_text"])
+        spans = [(start, end, label) for document in quick.documents for (start, end, label) in document.label]
+        self.assertEqual(len(frame.data["id"]), len(spans))
+        self.assertEqual(list(zip(frame.data["start"], frame.data["end"], frame.data["label"])), spans)
+        self.assertEqual(frame.data["entity_text"][0], frame.data["text"][0][spans[0][0]:spans[0][1]])
+        with mock.patch.dict(sys.modules, {"pandas": None}):
+            with self.assertRaises(ImportError):
+                quick.to_pandas()
+
+    def test_suggest_labels(self):
+        with tempfile.TemporaryDirectory() as directory:
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\n")
+                for text in ["apple and microsoft compete", "google and microsoft compete", "microsoft hires golang developers", "rust and golang are fast", "python and golang"]:
+                    f.write(f'"{text}"\n')
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\napple,ORG\ngoogle,ORG\nrust,PL\npython,PL\n")
+            vectors_path = os.path.join(directory, "vectors.txt")
+            with open(vectors_path, "w") as f:
+                f.write("8 3\napple 1 0.1 0\ngoogle 0.9 0.2 0\nmicrosoft 0.95 0.1 0.05\nrust 0 1 0.1\npython 0.1 0.9 0\ngolang 0.05 0.95 0.1\nand 0 0 1\ncompete 0.3 0.3 0.3\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path, texts_path)))
+            quick.process()
+            suggestions = quick.suggest_labels(vectors_path, neighbors=2)
+            self.assertEqual([(s.term, s.label, s.frequency) for s in suggestions], [("golang", "PL", 3), ("microsoft", "ORG", 3)])
+            self.assertEqual(sorted(suggestions[1].neighbors), ["apple", "google"])
+            self.assertGreater(suggestions[0].score, 0.9)
+            self.assertEqual(quick.suggest_labels(vectors_path, min_frequency=4), [])
+            with self.assertRaises(QuicknerError):
+                quick.suggest_labels(os.path.join(directory, "missing.txt"))
+
+    def test_subset(self):
+        with tempfile.TemporaryDirectory() as directory:
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write('text,source\n"Apple makes computers",news\n"I love rust and Apple",tweets\n"rust is fast",tweets\n')
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nrust,PL\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path, texts_path)))
+            quick.process()
+        tweets = quick.subset(meta={"source": "tweets"})
+        self.assertEqual([document.text for document in tweets.documents], ["i love rust and apple", "rust is fast"])
+        self.assertEqual(tweets.stats().documents, 2)
+        both = quick.subset(labels=["ORG"], entities=["Rust"])
+        self.assertEqual([document.text for document in both.documents], ["i love rust and apple"])
+        self.assertEqual(len(quick.subset(labels=["ORG"], meta={"source": "blog"}).documents), 0)
+        self.assertEqual(len(quick.subset().documents), 3)
+
+    def test_search(self):
+        with tempfile.TemporaryDirectory() as directory:
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write(
+                    'text,source\n"Apple announced a merger",news\n"Mergers of Apple and Tim",tweets\n'
+                    '"rust is fast",tweets\n"The merger failed",news\n'
+                )
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\nTim,PER\nrust,PL\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path, texts_path)))
+            quick.process()
+        texts = lambda documents: [document.text for document in documents]
+        self.assertEqual(
+            texts(quick.search(text="MERGER")),
+            ["apple announced a merger", "mergers of apple and tim", "the merger failed"],
+        )
+        self.assertEqual(texts(quick.search(text="erger", labels=["ORG"])), ["apple announced a merger", "mergers of apple and tim"])
+        self.assertEqual(texts(quick.search(text="merger", labels=["ORG"], without_labels=["PER"])), ["apple announced a merger"])
+        self.assertEqual(texts(quick.search(text="announced a merg")), ["apple announced a merger"])
+        self.assertEqual(texts(quick.search(text="merger", meta={"source": "news"}, limit=1)), ["apple announced a merger"])
+        self.assertEqual(quick.search(text="merger acquisition"), [])
+        self.assertEqual(texts(quick.search(without_labels=["ORG", "PL"])), ["the merger failed"])
+        self.assertEqual(len(quick.search()), 4)
+
+    def test_deterministic_order(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nrust,PL\nApple,ORG\npython,PL\n")
+            config_path = self._write_config(directory, entities_path)
+            runs = []
+            for _ in range(2):
+                quick = Quickner(config=Config(config_path))
+                quick.process()
+                runs.append(quick)
+        first, second = runs
+        self.assertEqual([document.text for document in first.documents], [text.lower() for text in self.texts])
+        self.assertEqual([document.id for document in first.documents], [document.id for document in second.documents])
+        self.assertEqual([(entity.name, entity.label) for entity in first.entities], [("rust", "PL"), ("apple", "ORG"), ("python", "PL")])
+
+    def test_config_relative_paths(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\n")
+                for name, label in self.entities:
+                    f.write(f"{name},{label}\n")
+            config_path = self._write_config(directory, "entities.csv")
+            cwd = os.getcwd()
+            try:
+                os.chdir(tempfile.gettempdir())
+                config = Config(config_path)
+            finally:
+                os.chdir(cwd)
+            self.assertEqual(config.entities.input.path, entities_path)
+
+    def test_multi_label_entities(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG|PRODUCT\nChris Lattner,PERSON\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path)))
+            quick.process()
+        document = quick.find_documents_by_entity("apple")[0]
+        self.assertEqual(document.label, [(21, 34, "PERSON"), (39, 44, "ORG"), (39, 44, "PRODUCT")])
+
+    def test_entity_metadata(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label,id,canonical,country\nApple,ORG,Q312,Apple Inc.,US\n")
+            quick = Quickner(config=Config(self._write_config(directory, entities_path)))
+            quick.process()
+            entity = quick.entities[0]
+            self.assertEqual((entity.id, entity.canonical), ("Q312", "Apple Inc."))
+            self.assertEqual(entity.attributes, {"country": "US"})
+            path = quick.save_annotations(os.path.join(directory, "annotations"))
+            with open(path + ".jsonl") as f:
+                lines = [json.loads(line) for line in f]
+        document = next(line for line in lines if "entity_meta" in line)
+        self.assertEqual(
+            document["entity_meta"],
+            [{"start": 39, "end": 44, "label": "ORG", "id": "Q312", "canonical": "Apple Inc.", "attributes": {"country": "US"}}],
+        )
+
+    def test_span_provenance(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMicrosoft,ORG\nApple,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nMicrsoft and Microsoft sell apple pie\n")
+            config_path = self._write_config(
+                directory,
+                entities_path,
+                texts_path,
+                extra='\n[matching.fuzzy]\nmax_edits = 1\nmin_len = 6\n\n[[rules]]\npattern = "apple"\nlabel = "ORG"\n'
+                'require = ["pie"]\naction = "redirect"\nto = "FOOD"',
+            )
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            spans = quick.documents[0].spans()
+            self.assertEqual(
+                [(span.label, span.source, span.entity_name) for span in spans],
+                [("ORG", "fuzzy", "microsoft"), ("ORG", "gazetteer", "microsoft"), ("FOOD", "rule", "apple")],
+            )
+            self.assertEqual(round(spans[0].to_dict()["confidence"], 3), 0.889)
+            path = quick.save_annotations(os.path.join(directory, "annotations"))
+            with open(path + ".jsonl") as f:
+                record = json.loads(f.readline())
+            self.assertEqual(
+                record["span_meta"][1],
+                {"start": 13, "end": 22, "label": "ORG", "source": "gazetteer", "entity_name": "microsoft", "confidence": 1.0},
+            )
+            reread = Quickner.from_jsonl(path + ".jsonl")
+            self.assertEqual([span.source for span in reread.documents[0].spans()], ["fuzzy", "gazetteer", "rule"])
+            imported_path = os.path.join(directory, "imported.jsonl")
+            with open(imported_path, "w") as f:
+                f.write(json.dumps({"text": "rust is fast", "label": [[0, 4, "PL"]]}) + "\n")
+            self.assertEqual([span.source for span in Quickner.from_jsonl(imported_path).documents[0].spans()], ["import"])
+        self.assertEqual([span.source for span in Document("rust is fast", [(0, 4, "PL")]).spans()], ["unknown"])
+
+    def test_confidence(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nMicrosoft,ORG\nApple,ORG\n")
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text\nMicrsoft and Microsoft\nApple and Mircosoft\n")
+            extra = "\n[matching.fuzzy]\nmax_edits = 2\nmin_len = 6"
+            config_path = self._write_config(directory, entities_path, texts_path, extra=extra)
+            quick = Quickner(config=Config(config_path))
+            quick.process()
+            confidences = [[round(span.confidence, 3) for span in document.spans()] for document in quick.documents]
+            self.assertEqual(confidences, [[0.889, 1.0], [1.0, 0.778]])
+            with open(config_path) as f:
+                content = f.read().replace("[annotations.output]\n", "[annotations.output]\nmin_confidence = 0.8\n")
+            with open(config_path, "w") as f:
+                f.write(content)
+            config = Config(config_path)
+            self.assertEqual(config.annotations.output.min_confidence, 0.8)
+            quick = Quickner(config=config)
+            quick.process(save=True)
+            with open(config.annotations.output.path) as f:
+                labels = [json.loads(line)["label"] for line in f]
+            self.assertEqual(labels, [[[0, 8, "ORG"], [13, 22, "ORG"]], [[0, 5, "ORG"]]])
+            self.assertEqual(quick.filter_spans(lambda span: span.confidence == 1.0), 2)
+            self.assertEqual([document.label for document in quick.documents], [[(13, 22, "ORG")], [(0, 5, "ORG")]])
+            self.assertEqual(quick.find_documents_by_entity("micrsoft"), [])
+            with self.assertRaises(ZeroDivisionError):
+                quick.filter_spans(lambda span: 1 / 0)
+            self.assertEqual(len(quick.documents[0].label), 1)
+            with open(config_path, "w") as f:
+                f.write(content.replace("min_confidence = 0.8", "min_confidence = 2"))
+            with self.assertRaises(QuicknerError):
+                Config(config_path)
+
+    def test_label_priority(self):
+        with tempfile.TemporaryDirectory() as directory:
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG|PRODUCT\n")
+            config_path = self._write_config(
+                directory, entities_path, extra='\n[labels.priority]\norder = ["PRODUCT"]'
+            )
+            config = Config(config_path)
+            self.assertEqual(config.labels.priority.order, ["PRODUCT"])
+            quick = Quickner(config=config)
+            quick.process()
+        document = quick.find_documents_by_entity("apple")[0]
+        self.assertEqual(document.label, [(39, 44, "PRODUCT")])
+
+    def test_routes(self):
+        with tempfile.TemporaryDirectory() as directory:
+            texts_path = os.path.join(directory, "texts.csv")
+            with open(texts_path, "w") as f:
+                f.write("text,lang\n")
+                f.write('"Apple fait des ordinateurs",fr\n')
+                f.write('"Apple makes computers",en\n')
+            entities_path = os.path.join(directory, "entities.csv")
+            with open(entities_path, "w") as f:
+                f.write("name,label\nApple,ORG\n")
+            french_path = os.path.join(directory, "entities_fr.csv")
+            with open(french_path, "w") as f:
+                f.write("name,label\nApple,ORGANISATION\n")
+            extra = f"""
+[[routes]]
+when = {{ lang = "fr" }}
+[routes.entities.input]
+path = "{french_path}"
+"""
+            config = Config(self._write_config(directory, entities_path, texts_path, extra))
+            self.assertEqual(config.routes[0].when, {"lang": "fr"})
+            quick = Quickner(config=config)
+            quick.process()
+        labels = {document.text: document.label for document in quick.documents}
+        self.assertEqual(labels["apple fait des ordinateurs"], [(0, 5, "ORGANISATION")])
+        self.assertEqual(labels["apple makes computers"], [(0, 5, "ORG")])
+
 
 if __name__ == "__main__":
     unittest.main()